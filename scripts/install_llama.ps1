@@ -0,0 +1,53 @@
+# Simple helper to build llama.cpp's HTTP server on Windows (non-privileged).
+# Intended as a convenience script — review before running on your machine.
+$ErrorActionPreference = "Stop"
+
+Write-Host "SuperAgent: Llama.cpp installer helper (Windows)"
+
+Write-Host "Checking for required tools: git, cmake"
+foreach ($t in @("git", "cmake")) {
+    if (-not (Get-Command $t -ErrorAction SilentlyContinue)) {
+        Write-Error "Missing $t - install it (e.g. 'winget install Git.Git' / 'winget install Kitware.CMake')"
+        exit 2
+    }
+}
+# cmake picks Visual Studio's compiler; without it the configure step fails
+if (-not (Get-Command cl -ErrorAction SilentlyContinue) -and -not (Test-Path "${env:ProgramFiles(x86)}\Microsoft Visual Studio\Installer\vswhere.exe")) {
+    Write-Error "No C++ compiler found. Install Visual Studio Build Tools with the 'Desktop development with C++' workload."
+    exit 2
+}
+
+# Clone and build llama.cpp (may change with upstream)
+$workdir = Join-Path $PWD "third_party"
+New-Item -ItemType Directory -Force -Path $workdir | Out-Null
+Set-Location $workdir
+if (-not (Test-Path "llama.cpp")) {
+    Write-Host "Cloning llama.cpp (https://github.com/ggerganov/llama.cpp)..."
+    git clone https://github.com/ggerganov/llama.cpp.git
+}
+Set-Location llama.cpp
+Write-Host "Pulling latest..."
+git pull --rebase
+if ($LASTEXITCODE -ne 0) { Write-Host "git pull failed; building the current checkout" }
+
+Write-Host "Building... (this may take a while)"
+cmake -B build
+if ($LASTEXITCODE -ne 0) { exit $LASTEXITCODE }
+cmake --build build --config Release -j $env:NUMBER_OF_PROCESSORS
+if ($LASTEXITCODE -ne 0) { exit $LASTEXITCODE }
+
+# Copy the server where `agent models serve` looks for it
+# `agent models install` sets SUPERCODE_HOME to the state home (see `agent paths`)
+$bin = Join-Path $env:APPDATA "super-agent\bin"
+if ($env:SUPERCODE_HOME) { $bin = Join-Path $env:SUPERCODE_HOME "bin" }
+New-Item -ItemType Directory -Force -Path $bin | Out-Null
+$server = Get-ChildItem -Path "build\bin" -Recurse -Include "llama-server.exe", "server.exe" | Select-Object -First 1
+if ($server) {
+    Copy-Item $server.FullName $bin -Force
+    Get-ChildItem -Path $server.DirectoryName -Filter "*.dll" | Copy-Item -Destination $bin -Force
+    Write-Host "Installed $($server.Name) to $bin"
+} else {
+    Write-Host "Build finished but no server binary was found under build\bin; see docs/MODEL_SERVICE_FA.md."
+}
+
+Write-Host "Done."
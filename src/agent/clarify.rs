@@ -0,0 +1,89 @@
+//! Clarifying questions before planning. With `clarify_questions = N` the
+//! planner model is shown the goal and may ask one question at a time, each
+//! seeing the answers so far, until it has what it needs or N questions have
+//! been asked. Questions go through the run's `HumanChannel`: a prompt
+//! answered with `/answer` in the TUI, stdin in the CLI. An empty answer
+//! stops the questions; the answers are kept on the task and become part of
+//! its goal.
+
+use crate::agent::human::HumanChannel;
+use crate::llm::Llm;
+use crate::tasks::Clarification;
+use crate::types::Message;
+use serde_json::{json, Value};
+
+fn schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {"question": {"type": ["string", "null"]}},
+        "required": ["question"]
+    })
+}
+
+fn request(goal: &str, answers: &[Clarification], left: usize) -> Vec<Message> {
+    let mut user = format!("Goal:\n{}", goal);
+    for c in answers {
+        user.push_str(&format!("\n\nQ: {}\nA: {}", c.question, c.answer));
+    }
+    vec![
+        Message::new(
+            "system",
+            format!(
+                "You are about to plan the goal below. If something you cannot find out yourself would change the plan \
+                 (which of several readings is meant, a choice only the user can make, a missing name or value), ask \
+                 one short question about it. Otherwise, or once the answers settle it, reply with a null question. \
+                 You may ask at most {} more.",
+                left
+            ),
+        ),
+        Message::new("user", user),
+    ]
+}
+
+/// Ask up to `max` questions about `goal` through `human`.
+pub async fn clarify(llm: &dyn Llm, human: &HumanChannel, goal: &str, max: usize) -> anyhow::Result<Vec<Clarification>> {
+    let schema = schema();
+    let mut answers: Vec<Clarification> = vec![];
+    while answers.len() < max {
+        let reply = crate::llm::structured::complete(llm, &request(goal, &answers, max - answers.len()), &schema, 2).await?;
+        let Some(question) = reply["question"].as_str().map(str::trim).filter(|q| !q.is_empty()) else { break };
+        let answer = match human.ask("planner", question, None, None).await {
+            Ok(answer) => answer,
+            Err(e) => {
+                tracing::warn!("planner question not answered: {}", e);
+                break;
+            }
+        };
+        if answer.trim().is_empty() {
+            break;
+        }
+        answers.push(Clarification { question: question.to_string(), answer: answer.trim().to_string() });
+    }
+    Ok(answers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::human::Prompt;
+    use crate::llm::mock::ScriptedLlm;
+
+    #[tokio::test]
+    async fn test_questions_stop_at_the_bound() -> anyhow::Result<()> {
+        let (human, mut rx) = HumanChannel::remote();
+        tokio::spawn(async move {
+            while let Some(Prompt::Question(q)) = rx.recv().await {
+                let _ = q.reply.send(format!("answer to {}", q.question));
+            }
+        });
+        let llm = ScriptedLlm::replies(&[r#"{"question": "Which database?"}"#, r#"{"question": "Which schema?"}"#, r#"{"question": "More?"}"#]);
+        let answers = clarify(&llm, &human, "add a migration", 2).await?;
+        assert_eq!(answers.iter().map(|c| c.answer.as_str()).collect::<Vec<_>>(), vec!["answer to Which database?", "answer to Which schema?"]);
+        assert_eq!(llm.calls().len(), 2);
+        assert!(llm.calls()[1].iter().any(|m| m.content.contains("Q: Which database?\nA: answer to Which database?")));
+
+        let llm = ScriptedLlm::replies(&[r#"{"question": null}"#]);
+        assert!(clarify(&llm, &human, "fix the typo in README", 2).await?.is_empty());
+        Ok(())
+    }
+}
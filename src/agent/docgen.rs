@@ -0,0 +1,225 @@
+//! Documentation generation. `agent docgen [path]` takes the public items
+//! the symbol index finds without a doc comment, has the model draft one for
+//! each (a file per request), and shows every draft as a small diff to
+//! accept or reject before anything is written. `--overview` also drafts an
+//! architecture overview of the project for `docs/ARCHITECTURE.md`.
+
+use crate::llm::Llm;
+use crate::symbols::{Language, Symbol};
+use crate::types::Message;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Where `--overview` writes, relative to the project root.
+pub const OVERVIEW_FILE: &str = "docs/ARCHITECTURE.md";
+
+/// A drafted doc comment for one item.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DocEdit {
+    pub file: String,
+    /// Line of the item it documents.
+    pub line: usize,
+    pub name: String,
+    /// The text, without comment markers.
+    pub doc: String,
+}
+
+fn schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "docs": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {"name": {"type": "string"}, "doc": {"type": "string"}},
+                    "required": ["name", "doc"]
+                }
+            }
+        },
+        "required": ["docs"]
+    })
+}
+
+fn request(file: &str, source: &str, items: &[&Symbol]) -> Vec<Message> {
+    let list: Vec<String> = items.iter().map(|s| format!("- `{}` (line {}): {}", s.name, s.line, s.signature)).collect();
+    vec![
+        Message::new(
+            "system",
+            "You write doc comments. For each item, say in one or two plain sentences what it is for or what it does, \
+             then anything a caller must know (errors, panics, units). Do not repeat the signature or the name, and \
+             leave out comment markers.",
+        ),
+        Message::new("user", format!("Draft doc comments for these items of `{}`:\n{}\n\nSource:\n```\n{}\n```", file, list.join("\n"), source)),
+    ]
+}
+
+/// Draft doc comments for `symbols` (the undocumented ones) under `root`.
+pub async fn draft(llm: &dyn Llm, root: &Path, symbols: &[Symbol]) -> anyhow::Result<Vec<DocEdit>> {
+    let mut by_file: BTreeMap<&str, Vec<&Symbol>> = BTreeMap::new();
+    for s in symbols.iter().filter(|s| !s.documented) {
+        by_file.entry(&s.file).or_default().push(s);
+    }
+    let schema = schema();
+    let mut edits = vec![];
+    for (file, mut items) in by_file {
+        let source = std::fs::read_to_string(root.join(file))?;
+        let reply = crate::llm::structured::complete(llm, &request(file, &source, &items), &schema, 2).await?;
+        for doc in reply["docs"].as_array().into_iter().flatten() {
+            let (name, text) = (doc["name"].as_str().unwrap_or_default(), doc["doc"].as_str().unwrap_or_default().trim());
+            // the first item of that name not drafted yet; names the model made up are dropped
+            let Some(at) = items.iter().position(|s| s.name == name) else { continue };
+            let item = items.remove(at);
+            if !text.is_empty() {
+                edits.push(DocEdit { file: file.to_string(), line: item.line, name: item.name.clone(), doc: text.to_string() });
+            }
+        }
+    }
+    Ok(edits)
+}
+
+/// Line index (0-based) the comment goes at and the comment's lines.
+fn comment(language: Language, lines: &[&str], line: usize, doc: &str) -> (usize, Vec<String>) {
+    let item = lines.get(line - 1).copied().unwrap_or_default();
+    let indent: String = item.chars().take_while(|c| c.is_whitespace()).collect();
+    let text: Vec<&str> = doc.lines().collect();
+    match language {
+        Language::Python => {
+            let inner = format!("{}    ", indent);
+            let body = match text.as_slice() {
+                [one] => vec![format!("{}\"\"\"{}\"\"\"", inner, one)],
+                _ => {
+                    let mut body: Vec<String> = text.iter().map(|l| if l.is_empty() { String::new() } else { format!("{}{}", inner, l) }).collect();
+                    body[0] = format!("{}\"\"\"{}", inner, text[0]);
+                    body.push(format!("{}\"\"\"", inner));
+                    body
+                }
+            };
+            (line, body)
+        }
+        Language::Rust | Language::Script => {
+            // above any attributes or decorators of the item
+            let mut at = line - 1;
+            while at > 0 && (lines[at - 1].trim_start().starts_with("#[") || lines[at - 1].trim_start().starts_with('@')) {
+                at -= 1;
+            }
+            let body = if language == Language::Rust {
+                text.iter().map(|l| if l.is_empty() { format!("{}///", indent) } else { format!("{}/// {}", indent, l) }).collect()
+            } else {
+                let mut body = vec![format!("{}/**", indent)];
+                body.extend(text.iter().map(|l| if l.is_empty() { format!("{} *", indent) } else { format!("{} * {}", indent, l) }));
+                body.push(format!("{} */", indent));
+                body
+            };
+            (at, body)
+        }
+    }
+}
+
+/// `edit` as a unified diff against `source`.
+pub fn diff(edit: &DocEdit, source: &str) -> String {
+    let Some(language) = Language::of(Path::new(&edit.file)) else { return String::new() };
+    let lines: Vec<&str> = source.lines().collect();
+    let (at, body) = comment(language, &lines, edit.line, &edit.doc);
+    // one line of context: the `def` above a docstring, the item below a comment
+    let python = language == Language::Python;
+    let (start, context) = if python { (at, lines[at - 1]) } else { (at + 1, lines.get(at).copied().unwrap_or_default()) };
+    let mut out = format!("--- a/{0}\n+++ b/{0}\n@@ -{1},1 +{1},{2} @@\n", edit.file, start, body.len() + 1);
+    if python {
+        out.push_str(&format!(" {}\n", context));
+    }
+    for l in &body {
+        out.push_str(&format!("+{}\n", l));
+    }
+    if !python {
+        out.push_str(&format!(" {}\n", context));
+    }
+    out
+}
+
+/// Write `edits` into their files; returns how many were applied.
+pub fn apply(root: &Path, edits: &[DocEdit]) -> anyhow::Result<usize> {
+    let mut by_file: BTreeMap<&str, Vec<&DocEdit>> = BTreeMap::new();
+    for e in edits {
+        by_file.entry(&e.file).or_default().push(e);
+    }
+    let mut applied = 0;
+    for (file, mut edits) in by_file {
+        let Some(language) = Language::of(Path::new(file)) else { continue };
+        let path = root.join(file);
+        let source = std::fs::read_to_string(&path)?;
+        let mut lines: Vec<String> = source.lines().map(str::to_string).collect();
+        // bottom up, so earlier line numbers stay valid
+        edits.sort_by_key(|e| std::cmp::Reverse(e.line));
+        for e in edits {
+            let view: Vec<&str> = lines.iter().map(String::as_str).collect();
+            let (at, body) = comment(language, &view, e.line, &e.doc);
+            lines.splice(at..at, body);
+            applied += 1;
+        }
+        let mut text = lines.join("\n");
+        if source.ends_with('\n') {
+            text.push('\n');
+        }
+        std::fs::write(&path, text)?;
+    }
+    Ok(applied)
+}
+
+/// Draft an architecture overview in Markdown from the index.
+pub async fn overview(llm: &dyn Llm, project: &str, symbols: &[Symbol]) -> anyhow::Result<String> {
+    let mut by_file: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for s in symbols {
+        by_file.entry(&s.file).or_default().push(&s.signature);
+    }
+    let mut outline = String::new();
+    for (file, items) in by_file {
+        outline.push_str(&format!("{}\n", file));
+        for sig in items.iter().take(20) {
+            outline.push_str(&format!("  {}\n", sig));
+        }
+    }
+    let messages = vec![
+        Message::new(
+            "system",
+            "You write project documentation in Markdown. Describe the architecture: what the project does, its main \
+             modules and how they fit together, and where to start reading. Base it only on the outline given.",
+        ),
+        Message::new("user", format!("Write docs/ARCHITECTURE.md for {}. Public items by file:\n\n{}", project, outline)),
+    ];
+    Ok(llm.chat(&messages).await?.trim().to_string() + "\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::mock::ScriptedLlm;
+    use crate::symbols::parse;
+
+    #[tokio::test]
+    async fn test_drafts_become_diffs_and_doc_comments() {
+        let td = tempfile::tempdir().unwrap();
+        std::fs::create_dir(td.path().join("src")).unwrap();
+        let rust = "#[derive(Debug)]\npub struct Point;\n\n/// Adds.\npub fn add(a: i32) -> i32 {\n    a\n}\npub fn sub(a: i32) -> i32 {\n    a\n}\n";
+        std::fs::write(td.path().join("src/lib.rs"), rust).unwrap();
+        std::fs::write(td.path().join("geo.py"), "def area(r):\n    return r\n").unwrap();
+        let mut symbols = parse("src/lib.rs", rust);
+        symbols.extend(parse("geo.py", "def area(r):\n    return r\n"));
+
+        let llm = ScriptedLlm::replies(&[
+            r#"{"docs": [{"name": "area", "doc": "Area of a circle of radius `r`."}]}"#,
+            r#"{"docs": [{"name": "sub", "doc": "Subtracts."}, {"name": "Point", "doc": "A point.\n\nHas no fields."}, {"name": "made_up", "doc": "x"}]}"#,
+        ]);
+        let edits = draft(&llm, td.path(), &symbols).await.unwrap();
+        assert_eq!(edits.iter().map(|e| e.name.as_str()).collect::<Vec<_>>(), vec!["area", "sub", "Point"]);
+        assert_eq!(diff(&edits[2], rust), "--- a/src/lib.rs\n+++ b/src/lib.rs\n@@ -1,1 +1,4 @@\n+/// A point.\n+///\n+/// Has no fields.\n #[derive(Debug)]\n");
+
+        assert_eq!(apply(td.path(), &edits).unwrap(), 3);
+        let lib = std::fs::read_to_string(td.path().join("src/lib.rs")).unwrap();
+        assert!(lib.starts_with("/// A point.\n///\n/// Has no fields.\n#[derive(Debug)]\npub struct Point;\n"));
+        assert!(lib.contains("}\n/// Subtracts.\npub fn sub"));
+        let py = std::fs::read_to_string(td.path().join("geo.py")).unwrap();
+        assert_eq!(py, "def area(r):\n    \"\"\"Area of a circle of radius `r`.\"\"\"\n    return r\n");
+    }
+}
@@ -0,0 +1,36 @@
+//! Agent runs as events for observers outside the run: the model server's
+//! `/v1/events` WebSocket streams them as JSON to dashboards and attached
+//! TUIs. Unlike `memory::events`, which sessions use to talk to each
+//! other, this bus is process-wide and only reports. Publishing with nobody
+//! listening costs nothing; a listener that falls more than [`BUFFER`]
+//! events behind misses the oldest ones.
+
+use crate::tasks::TaskStatus;
+use serde::Serialize;
+use std::sync::OnceLock;
+use tokio::sync::broadcast;
+
+pub const BUFFER: usize = 256;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AgentEvent {
+    RunStarted { task: String, title: String },
+    /// What an agent of a run did, e.g. "routed to qwen (code)" or "done".
+    Action { agent: String, action: String },
+    RunFinished { task: String, status: TaskStatus },
+}
+
+fn bus() -> &'static broadcast::Sender<AgentEvent> {
+    static BUS: OnceLock<broadcast::Sender<AgentEvent>> = OnceLock::new();
+    BUS.get_or_init(|| broadcast::channel(BUFFER).0)
+}
+
+pub fn publish(event: AgentEvent) {
+    let _ = bus().send(event);
+}
+
+/// Events published from now on.
+pub fn subscribe() -> broadcast::Receiver<AgentEvent> {
+    bus().subscribe()
+}
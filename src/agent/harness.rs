@@ -0,0 +1,308 @@
+//! Drives whole `SuperAgent` runs against a [`ScriptedLlm`] in a throwaway
+//! home directory, so tests exercise planning, subtasks, checkpoints and
+//! artifacts without a model server.
+
+use crate::agent::super_agent::SuperAgent;
+use crate::graph::pipeline::Pipeline;
+use crate::llm::mock::ScriptedLlm;
+use crate::memory::events::EventKind;
+use crate::tasks::{Task, TaskStore};
+use std::sync::Arc;
+use tempfile::TempDir;
+
+pub struct TestAgentHarness {
+    pub home: TempDir,
+    pub llm: Arc<ScriptedLlm>,
+    pub agent: SuperAgent,
+}
+
+/// What a harness run produced.
+pub struct HarnessRun {
+    pub result: anyhow::Result<String>,
+    pub task: Task,
+    /// `SessionEvent::describe` of everything the agents published.
+    pub events: Vec<String>,
+}
+
+impl TestAgentHarness {
+    /// Harness answering from a YAML fixture (see `llm::mock::Script`).
+    pub fn new(fixture: &str) -> anyhow::Result<Self> {
+        Self::with_llm(ScriptedLlm::from_yaml(fixture)?)
+    }
+
+    pub fn with_llm(llm: ScriptedLlm) -> anyhow::Result<Self> {
+        Ok(Self::in_home(llm, tempfile::tempdir()?))
+    }
+
+    /// Harness over an existing home, e.g. to resume an earlier run.
+    pub fn in_home(llm: ScriptedLlm, home: TempDir) -> Self {
+        let llm = Arc::new(llm);
+        let mut agent = SuperAgent::new();
+        // its own bus, so runs of other tests do not show up in `events`
+        agent.events = crate::memory::events::EventBus::default();
+        agent.llm = llm.clone();
+        agent.tasks = Some(TaskStore::new(home.path()));
+        agent.file_policy = crate::tools::policy::FileAccessPolicy::new(home.path(), &crate::config::FilesConfig::default());
+        Self { home, llm, agent }
+    }
+
+    pub fn pipeline(mut self, pipeline: Pipeline) -> Self {
+        self.agent.pipeline = Some(pipeline);
+        self
+    }
+
+    pub fn store(&self) -> TaskStore {
+        TaskStore::new(self.home.path())
+    }
+
+    pub async fn run(&mut self, goal: &str) -> HarnessRun {
+        self.run_task(Task::new(goal, goal)).await
+    }
+
+    pub async fn run_task(&mut self, task: Task) -> HarnessRun {
+        let mut sub = self.agent.events.subscribe("harness", &[EventKind::AgentAction, EventKind::Commentary]);
+        let id = task.id.clone();
+        let result = self.agent.run_task(task).await;
+        let task = self.store().find(&id).expect("run_task saves the task");
+        HarnessRun { result, task, events: sub.drain().iter().map(|e| e.describe()).collect() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tasks::checkpoint::CheckpointStore;
+    use crate::tasks::retry::{FailureCause, RetryPolicy};
+    use crate::tasks::TaskStatus;
+
+    const FIXTURE: &str = r#"
+responses:
+  - prompt: "^Plan for goal: ship"
+    reply: "1. build\n2. test"
+  - prompt: "^Plan for goal: built"
+    reply: "looks good"
+  - prompt: "^Carry out this step(.|\n)*build$"
+    reply: '{"tool": "echo", "input": "built"}'
+  - prompt: "^Carry out this step(.|\n)*test$"
+    reply: '{"tool": "echo", "input": "tested"}'
+"#;
+
+    #[tokio::test]
+    async fn test_full_run_is_deterministic() -> anyhow::Result<()> {
+        let mut h = TestAgentHarness::new(FIXTURE)?;
+        let run = h.run("ship").await;
+        let report = run.result?;
+        assert!(report.contains("Review:\nlooks good"), "{}", report);
+        assert!(report.contains("\n  2. test — "), "{}", report);
+        assert_eq!(run.task.status, TaskStatus::Completed);
+        // planner, one tool call per step, critic
+        assert_eq!(h.llm.calls().len(), 4);
+        let steps: Vec<String> = h.store().tree().into_iter().filter(|(d, _)| *d == 1).map(|(_, t)| t.title).collect();
+        assert_eq!(steps, vec!["build", "test"]);
+        assert!(run.events.iter().any(|e| e.contains("step 2 Completed")), "{:?}", run.events);
+        assert!(CheckpointStore::new(h.home.path()).load(&run.task.id).is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_steps_run_the_tools_the_model_picks() -> anyhow::Result<()> {
+        let fixture = r#"
+responses:
+  - call: 1
+    reply: "write the notes"
+  - prompt: "^Carry out this step"
+    reply: '{"tool": "write_file", "input": "notes.txt\nhello"}'
+default: "fine"
+"#;
+        let mut h = TestAgentHarness::new(fixture)?;
+        let run = h.run("notes").await;
+        assert!(run.result?.contains("wrote 5 bytes to"));
+        assert_eq!(std::fs::read_to_string(h.home.path().join("notes.txt"))?, "hello");
+        let files = crate::tasks::artifacts::list(h.home.path(), &run.task.id);
+        assert!(files.contains(&std::path::PathBuf::from("files/notes.txt")), "{:?}", files);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_watcher_sees_task_and_file_changes() -> anyhow::Result<()> {
+        let fixture = r#"
+responses:
+  - call: 1
+    reply: "1. write\n2. check"
+  - prompt: "^Carry out this step(.|\n)*write$"
+    reply: '{"tool": "write_file", "input": "notes.txt\nhello"}'
+default: '{"tool": "echo", "input": "ok"}'
+"#;
+        let mut h = TestAgentHarness::new(fixture)?;
+        let task = Task::new("notes", "notes");
+        let session = task.id[..8].to_string();
+        let mut watcher = h.agent.events.subscribe("watcher", &[EventKind::TaskUpdate, EventKind::FileChange]);
+        watcher.source = Some(session.clone());
+        h.run_task(task).await.result?;
+
+        let seen: Vec<String> = watcher.drain().iter().map(|e| e.describe()).collect();
+        assert_eq!(seen.first(), Some(&format!("[{}] task {} -> Running", session, session)));
+        assert_eq!(seen.last(), Some(&format!("[{}] task {} -> Completed", session, session)));
+        assert!(seen.contains(&format!("[{}] wrote notes.txt", session)), "{:?}", seen);
+        // both steps ran and finished
+        assert_eq!(seen.iter().filter(|e| e.ends_with("-> Completed")).count(), 3, "{:?}", seen);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_pair_mode_narrates_the_run() -> anyhow::Result<()> {
+        let mut h = TestAgentHarness::new(FIXTURE)?;
+        h.agent.commentary = true;
+        let run = h.run("ship").await;
+        run.result?;
+        for line in ["planner … planning: ship", "executor … step 2/2: test", "critic … reviewing the result"] {
+            let line = format!("[{}] {}", &run.task.id[..8], line);
+            assert!(run.events.contains(&line), "{} not in {:?}", line, run.events);
+        }
+
+        let mut quiet = TestAgentHarness::new(FIXTURE)?;
+        assert!(!quiet.run("ship").await.events.iter().any(|e| e.contains(" … ")));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_edges_follow_scripted_output() -> anyhow::Result<()> {
+        let mut pipeline = Pipeline::default();
+        pipeline.add_node(crate::graph::pipeline::PipelineNode::new("fixer", "planner"), None)?;
+        pipeline.set_edge("critic", "fixer", crate::graph::pipeline::EdgeCondition::parse("contains:broken")?)?;
+        // a node limited to other tools can still finish its steps with echo
+        if let Some(executor) = pipeline.nodes.iter_mut().find(|n| n.name == "executor") {
+            executor.tools = vec!["read_file".into()];
+        }
+        let fixture = r#"
+responses:
+  - call: 1
+    reply: "just echo"
+  - prompt: "^Carry out this step"
+    reply: '{"tool": "echo", "input": "echoed"}'
+  - call: 3
+    reply: "all good"
+"#;
+        let mut h = TestAgentHarness::new(fixture)?.pipeline(pipeline);
+        let run = h.run("ship").await;
+        assert!(run.result?.contains("critic:\nall good\n\nTime: "));
+        assert!(run.events.iter().any(|e| e.contains("fixer: skipped")));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_failed_pipeline_node_fails_the_run_unless_routed() -> anyhow::Result<()> {
+        let fixture = r#"
+responses:
+  - call: 1
+    reply: "1. build\n2. test"
+  - prompt: "^Carry out this step"
+    reply: '{"tool": "missing", "input": ""}'
+default: "fine"
+"#;
+        let mut h = TestAgentHarness::new(fixture)?.pipeline(Pipeline::default());
+        h.agent.retry = RetryPolicy { max_attempts: 1, ..Default::default() };
+        let err = h.run("ship").await.result.unwrap_err().to_string();
+        assert!(err.starts_with("executor failed"), "{}", err);
+
+        let mut routed = Pipeline::default();
+        routed.add_node(crate::graph::pipeline::PipelineNode::new("fixer", "planner"), None)?;
+        routed.set_edge("executor", "fixer", crate::graph::pipeline::EdgeCondition::OnFailure)?;
+        let mut h = TestAgentHarness::new(fixture)?.pipeline(routed);
+        h.agent.retry = RetryPolicy { max_attempts: 1, ..Default::default() };
+        assert!(h.run("ship").await.result?.contains("fixer:\nfine"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_retry_replaces_the_previous_plans_steps() -> anyhow::Result<()> {
+        let fixture = r#"
+responses:
+  - prompt: "^Plan for goal: ship"
+    times: 1
+    reply: "1. alpha\n2. beta"
+  - prompt: "^Plan for goal: ship"
+    reply: "1. gamma\n2. delta"
+  - prompt: "^Carry out this step(.|\n)*beta$"
+    reply: '{"tool": "missing", "input": ""}'
+  - prompt: "^Carry out this step"
+    reply: '{"tool": "echo", "input": "done"}'
+  - prompt: "Classify the failure"
+    reply: '{"cause": "bad_plan", "explanation": "beta cannot work"}'
+default: "fine"
+"#;
+        let mut h = TestAgentHarness::new(fixture)?;
+        h.agent.retry = RetryPolicy { max_attempts: 2, backoff_ms: 0, ..Default::default() };
+        // routed steps still answer from the script
+        let tier = crate::agent::router::Tier { model: "small".into(), max_difficulty: 1.0, cost_per_1k: 0.0, ms_per_1k: 0.0 };
+        h.agent.router = crate::agent::router::Router::new(crate::agent::router::RouterConfig { tiers: vec![tier], ..Default::default() });
+        let run = h.run("ship").await;
+        run.result?;
+        assert_eq!(run.task.attempts, 2);
+        assert_eq!(run.task.routing.iter().filter(|d| d.step == "planner").count(), 1, "only the last attempt's decisions");
+        let graph = &h.agent.graph;
+        let edges: Vec<_> = graph.edges.iter().map(|&(from, to)| (graph.nodes[from].name.as_str(), graph.nodes[to].name.as_str(), to - from)).collect();
+        assert_eq!(edges, [("planner", "executor", 1), ("executor", "critic", 1)], "edges join the last attempt's nodes");
+        let mut steps: Vec<Task> = h.store().list().into_iter().filter(|t| t.parent_id.as_deref() == Some(run.task.id.as_str())).collect();
+        steps.sort_by_key(|t| t.index);
+        assert_eq!(steps.iter().map(|t| (t.title.as_str(), t.status)).collect::<Vec<_>>(), [("gamma", TaskStatus::Completed), ("delta", TaskStatus::Completed)]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_failed_run_is_diagnosed_and_resumable() -> anyhow::Result<()> {
+        let fixture = r#"
+responses:
+  - call: 1
+    reply: "1. build\n2. test"
+  - prompt: "^Carry out this step"
+    reply: '{"tool": "echo", "input": "done"}'
+  - prompt: "Classify the failure"
+    reply: '{"cause": "flaky_test", "explanation": "timed out"}'
+"#;
+        let mut h = TestAgentHarness::new(fixture)?;
+        h.agent.retry = RetryPolicy { max_attempts: 1, ..Default::default() };
+        // nothing scripts the final structured result, so the run fails there
+        h.agent.output_schema = Some(serde_json::json!({"type": "object", "required": ["ok"]}));
+        let run = h.run("ship").await;
+        assert!(run.result.is_err());
+        assert_eq!(run.task.status, TaskStatus::Failed);
+        assert_eq!(run.task.failure_cause(), Some(FailureCause::FlakyTest));
+
+        // the plan survived in the checkpoint, so resuming only asks the critic
+        let cp = CheckpointStore::new(h.home.path()).load(&run.task.id)?;
+        assert!(cp.result("planner").is_some());
+        let mut resumed = TestAgentHarness::in_home(ScriptedLlm::replies(&["fine"]), h.home);
+        resumed.agent.checkpoint = Some(cp.clone());
+        let run = resumed.run_task(cp.task).await;
+        assert!(run.result?.contains("Review:\nfine"));
+        assert_eq!(resumed.llm.calls().len(), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_resumed_step_keeps_the_executor_memory() -> anyhow::Result<()> {
+        let fixture = r#"
+responses:
+  - call: 1
+    reply: "1. build\n2. test"
+  - prompt: "^Carry out this step(.|\n)*build$"
+    reply: '{"tool": "echo", "input": "built in /tmp/out"}'
+default: "no tool call"
+"#;
+        let mut h = TestAgentHarness::new(fixture)?;
+        h.agent.retry = RetryPolicy { max_attempts: 1, ..Default::default() };
+        let run = h.run("ship").await;
+        assert!(run.result.is_err());
+
+        let cp = CheckpointStore::new(h.home.path()).load(&run.task.id)?;
+        let llm = ScriptedLlm::replies(&[r#"{"tool": "echo", "input": "tested"}"#, "fine"]);
+        let mut resumed = TestAgentHarness::in_home(llm, h.home);
+        resumed.agent.checkpoint = Some(cp.clone());
+        assert!(resumed.run_task(cp.task).await.result?.contains("tested"));
+        // the test step still sees the build step's tool output
+        let calls = resumed.llm.calls();
+        assert!(calls[0].iter().any(|m| m.content.contains("built in /tmp/out")), "{:?}", calls[0]);
+        Ok(())
+    }
+}
@@ -0,0 +1,199 @@
+use crate::models::metrics::estimate_tokens;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::sync::{mpsc, oneshot};
+
+/// Tokens a step's reply is assumed to take, for plan estimates.
+const STEP_REPLY_TOKENS: u64 = 512;
+
+/// A question from a `human_input` pipeline node, waiting for an answer.
+#[derive(Debug)]
+pub struct HumanPrompt {
+    pub node: String,
+    pub question: String,
+    pub default: Option<String>,
+    pub reply: oneshot::Sender<String>,
+}
+
+/// Rough size of a plan: its steps and the tokens executing them takes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlanEstimate {
+    pub steps: usize,
+    pub tokens: u64,
+}
+
+impl PlanEstimate {
+    /// Numbered or bulleted lines count as steps; a plan without any is one
+    /// step. Each step is sent the plan and gets a reply.
+    pub fn of(plan: &str) -> Self {
+        let steps = plan
+            .lines()
+            .map(str::trim_start)
+            .filter(|l| l.starts_with(['-', '*']) || l.split_once(['.', ')']).is_some_and(|(n, _)| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit())))
+            .count()
+            .max(1);
+        Self { steps, tokens: steps as u64 * (estimate_tokens(plan) + STEP_REPLY_TOKENS) }
+    }
+
+    pub fn describe(&self) -> String {
+        format!("{} step(s), ~{} tokens", self.steps, self.tokens)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlanDecision {
+    Approve,
+    /// Run this plan instead.
+    Edit(String),
+    Abort,
+}
+
+/// A plan waiting for approval before execution (`approve_plan`).
+#[derive(Debug)]
+pub struct PlanReview {
+    pub plan: String,
+    pub estimate: PlanEstimate,
+    pub reply: oneshot::Sender<PlanDecision>,
+}
+
+/// What a run asks a front end.
+#[derive(Debug)]
+pub enum Prompt {
+    Question(HumanPrompt),
+    Plan(PlanReview),
+}
+
+/// The run stopped because its plan was not approved. Runs are not retried
+/// after it.
+#[derive(Error, Debug)]
+#[error("plan not approved")]
+pub struct PlanRejected;
+
+/// Where questions go: a front end (the TUI) when attached, stdin otherwise.
+#[derive(Debug, Clone, Default)]
+pub enum HumanChannel {
+    #[default]
+    Stdin,
+    Remote(mpsc::UnboundedSender<Prompt>),
+}
+
+impl HumanChannel {
+    pub fn remote() -> (Self, mpsc::UnboundedReceiver<Prompt>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        (HumanChannel::Remote(tx), rx)
+    }
+
+    /// Show `plan` and wait for a decision. On stdin the plan is printed
+    /// and only `y` approves; it cannot be edited there.
+    pub async fn review_plan(&self, plan: &str) -> anyhow::Result<PlanDecision> {
+        let estimate = PlanEstimate::of(plan);
+        match self {
+            HumanChannel::Stdin => {
+                let text = format!("Plan ({}):\n{}\n\nApprove this plan? [y/N] ", estimate.describe(), plan);
+                let answer = tokio::task::spawn_blocking(move || {
+                    eprint!("{}", text);
+                    let mut line = String::new();
+                    std::io::stdin().read_line(&mut line)?;
+                    Ok::<_, std::io::Error>(line)
+                })
+                .await??;
+                Ok(if answer.trim().eq_ignore_ascii_case("y") { PlanDecision::Approve } else { PlanDecision::Abort })
+            }
+            HumanChannel::Remote(tx) => {
+                let (reply, rx) = oneshot::channel();
+                tx.send(Prompt::Plan(PlanReview { plan: plan.to_string(), estimate, reply }))
+                    .map_err(|_| anyhow::anyhow!("no front end to approve the plan"))?;
+                Ok(rx.await.unwrap_or(PlanDecision::Abort))
+            }
+        }
+    }
+
+    /// Ask and wait. After `timeout` the default is used; without one, or
+    /// when nobody is listening, it's an error. An empty answer also takes
+    /// the default.
+    pub async fn ask(&self, node: &str, question: &str, default: Option<&str>, timeout: Option<Duration>) -> anyhow::Result<String> {
+        let answer = async {
+            match self {
+                HumanChannel::Stdin => {
+                    let prompt = match default {
+                        Some(d) => format!("[{}] {} ({}): ", node, question, d),
+                        None => format!("[{}] {}: ", node, question),
+                    };
+                    tokio::task::spawn_blocking(move || {
+                        use std::io::Write;
+                        print!("{}", prompt);
+                        std::io::stdout().flush()?;
+                        let mut line = String::new();
+                        std::io::stdin().read_line(&mut line)?;
+                        Ok::<_, std::io::Error>(line.trim().to_string())
+                    })
+                    .await?
+                    .map_err(anyhow::Error::from)
+                }
+                HumanChannel::Remote(tx) => {
+                    let (reply, rx) = oneshot::channel();
+                    let prompt = HumanPrompt { node: node.into(), question: question.into(), default: default.map(str::to_string), reply };
+                    tx.send(Prompt::Question(prompt)).map_err(|_| anyhow::anyhow!("no front end to ask {:?}", question))?;
+                    rx.await.map_err(|_| anyhow::anyhow!("question {:?} was dismissed", question))
+                }
+            }
+        };
+        let answer = match timeout {
+            Some(t) => match tokio::time::timeout(t, answer).await {
+                Ok(answer) => answer,
+                Err(_) => {
+                    return default
+                        .map(str::to_string)
+                        .ok_or_else(|| anyhow::anyhow!("no answer to {:?} within {}s", question, t.as_secs()))
+                }
+            },
+            None => answer.await,
+        }?;
+        match (answer.is_empty(), default) {
+            (true, Some(d)) => Ok(d.to_string()),
+            _ => Ok(answer),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_remote_answer_and_timeout_default() -> anyhow::Result<()> {
+        let (channel, mut rx) = HumanChannel::remote();
+        tokio::spawn(async move {
+            let Some(Prompt::Question(prompt)) = rx.recv().await else { panic!("expected a question") };
+            assert_eq!(prompt.question, "Ship it?");
+            let _ = prompt.reply.send("yes".into());
+            // leave the second question unanswered
+            let _unanswered = rx.recv().await;
+            tokio::time::sleep(Duration::from_secs(5)).await;
+        });
+        assert_eq!(channel.ask("approve", "Ship it?", None, None).await?, "yes");
+        let timeout = Some(Duration::from_millis(20));
+        assert_eq!(channel.ask("approve", "Really?", Some("no"), timeout).await?, "no");
+        assert!(channel.ask("approve", "Again?", None, timeout).await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_plan_review_over_a_remote_channel() -> anyhow::Result<()> {
+        let plan = "1. read src/lib.rs\n2. add the function\n- run the tests";
+        assert_eq!(PlanEstimate::of(plan).steps, 3);
+        assert_eq!(PlanEstimate::of("just do it").steps, 1);
+
+        let (channel, mut rx) = HumanChannel::remote();
+        tokio::spawn(async move {
+            let Some(Prompt::Plan(review)) = rx.recv().await else { panic!("expected a plan") };
+            assert_eq!(review.estimate.steps, 3);
+            let _ = review.reply.send(PlanDecision::Edit("1. read src/lib.rs".into()));
+            // a dropped review aborts
+            let _dropped = rx.recv().await;
+        });
+        assert_eq!(channel.review_plan(plan).await?, PlanDecision::Edit("1. read src/lib.rs".into()));
+        assert_eq!(channel.review_plan(plan).await?, PlanDecision::Abort);
+        Ok(())
+    }
+}
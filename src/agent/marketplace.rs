@@ -0,0 +1,341 @@
+//! Skill marketplace: a JSON index of installable skills, searched with
+//! `agent skills search` and installed into the skills directory with
+//! `agent skills install`. An index looks like:
+//!
+//! ```json
+//! {"skills": [{
+//!     "name": "fast-grep",
+//!     "version": "0.3.0",
+//!     "description": "Regex search over the workspace",
+//!     "url": "fast-grep-0.3.0.wasm",
+//!     "sha256": "9f86d0…",
+//!     "signature": "hex ed25519 signature of the file",
+//!     "permissions": {"read": ["."]}
+//! }]}
+//! ```
+//!
+//! Relative `url`s resolve against the index location. Every download must
+//! match its `sha256`; signatures are checked against `skills.trusted_keys`.
+
+use crate::agent::skill_manifest::{PanelManifest, Permissions, SkillManifest};
+use crate::config::SkillsConfig;
+use crate::errors::{ConfigError, PluginError};
+use anyhow::Result;
+use ed25519_dalek::{Signature, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IndexEntry {
+    pub name: String,
+    pub version: String,
+    #[serde(default)]
+    pub description: String,
+    pub url: String,
+    pub sha256: String,
+    #[serde(default)]
+    pub signature: Option<String>,
+    #[serde(default)]
+    pub permissions: Permissions,
+    /// Copied into the installed manifest; see [`PanelManifest`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub panel: Option<PanelManifest>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SkillIndex {
+    #[serde(default)]
+    pub skills: Vec<IndexEntry>,
+}
+
+/// An index entry next to the version installed locally, if any.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Listing {
+    pub entry: IndexEntry,
+    pub installed: Option<String>,
+}
+
+impl Listing {
+    pub fn status(&self) -> &'static str {
+        match &self.installed {
+            None => "available",
+            Some(v) if *v == self.entry.version => "installed",
+            Some(_) => "update available",
+        }
+    }
+}
+
+/// How an installed file was verified.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Verified {
+    Checksum,
+    /// Checksum and a signature from the trusted key with this prefix.
+    Signed(String),
+}
+
+impl Verified {
+    pub fn describe(&self) -> String {
+        match self {
+            Verified::Checksum => "sha256 ok, unsigned".into(),
+            Verified::Signed(key) => format!("sha256 ok, signed by {}", key),
+        }
+    }
+}
+
+pub struct Marketplace {
+    config: SkillsConfig,
+    skills_dir: PathBuf,
+}
+
+impl Marketplace {
+    pub fn new(config: SkillsConfig, skills_dir: PathBuf) -> Self {
+        Self { config, skills_dir }
+    }
+
+    pub async fn index(&self) -> Result<SkillIndex> {
+        if self.config.index_url.is_empty() {
+            return Err(ConfigError::Missing { key: "skills.index_url", purpose: "to search and install skills" }.into());
+        }
+        let bytes = fetch(&self.config.index_url).await?;
+        serde_json::from_slice(&bytes).map_err(|e| PluginError::BadIndex { url: self.config.index_url.clone(), reason: e.to_string() }.into())
+    }
+
+    /// Index entries whose name or description contains `term`
+    /// (case-insensitive); an empty term lists everything.
+    pub async fn search(&self, term: &str) -> Result<Vec<Listing>> {
+        let term = term.to_lowercase();
+        Ok(self
+            .index()
+            .await?
+            .skills
+            .into_iter()
+            .filter(|e| e.name.to_lowercase().contains(&term) || e.description.to_lowercase().contains(&term))
+            .map(|entry| Listing { installed: self.installed(&entry.name), entry })
+            .collect())
+    }
+
+    /// Version recorded in the installed skill's manifest.
+    pub fn installed(&self, name: &str) -> Option<String> {
+        let manifest = SkillManifest::for_skill(&self.skills_dir.join(name)).ok().flatten()?;
+        Some(manifest.version)
+    }
+
+    /// Download, verify and place the newest index entry called `name`,
+    /// with its manifest beside it. Hot reload picks it up from there.
+    pub async fn install(&self, name: &str) -> Result<(IndexEntry, Verified)> {
+        let entry = self
+            .index()
+            .await?
+            .skills
+            .into_iter()
+            .filter(|e| e.name == name)
+            .max_by(|a, b| version_key(&a.version).cmp(&version_key(&b.version)))
+            .ok_or_else(|| PluginError::NotInIndex(name.to_string()))?;
+        if entry.name.is_empty() || !entry.name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            return Err(PluginError::InvalidName(entry.name).into());
+        }
+        let ext = artifact_extension(&entry.url)?;
+        let bytes = fetch(&resolve(&self.config.index_url, &entry.url)).await?;
+        let verified = self.verify(&entry, &bytes)?;
+
+        fs::create_dir_all(&self.skills_dir)?;
+        let manifest = SkillManifest {
+            name: entry.name.clone(),
+            version: entry.version.clone(),
+            description: entry.description.clone(),
+            permissions: entry.permissions.clone(),
+            panel: entry.panel.clone(),
+        };
+        // manifest first: the engine checks it when the skill file appears
+        write_atomic(&self.skills_dir.join(format!("{}.toml", entry.name)), toml::to_string_pretty(&manifest)?.as_bytes())?;
+        write_atomic(&self.skills_dir.join(format!("{}.{}", entry.name, ext)), &bytes)?;
+        Ok((entry, verified))
+    }
+
+    pub fn verify(&self, entry: &IndexEntry, bytes: &[u8]) -> Result<Verified> {
+        let digest = hex::encode(Sha256::digest(bytes));
+        if !digest.eq_ignore_ascii_case(entry.sha256.trim()) {
+            return Err(PluginError::ChecksumMismatch { name: entry.name.clone(), expected: entry.sha256.clone(), actual: digest }.into());
+        }
+        if let Some(sig) = &entry.signature {
+            let sig = Signature::from_slice(&hex::decode(sig.trim())?).map_err(|e| PluginError::BadSignature { name: entry.name.clone(), reason: e.to_string() })?;
+            for key in &self.config.trusted_keys {
+                if parse_key(key)?.verify_strict(bytes, &sig).is_ok() {
+                    return Ok(Verified::Signed(key.chars().take(12).collect()));
+                }
+            }
+            if !self.config.trusted_keys.is_empty() {
+                return Err(PluginError::UntrustedSignature(entry.name.clone()).into());
+            }
+        }
+        if self.config.require_signature {
+            return Err(PluginError::Unsigned(entry.name.clone()).into());
+        }
+        Ok(Verified::Checksum)
+    }
+}
+
+pub(crate) fn parse_key(hex_key: &str) -> Result<VerifyingKey> {
+    let bytes: [u8; 32] = hex::decode(hex_key.trim())?
+        .try_into()
+        .map_err(|_| PluginError::BadTrustedKey { key: hex_key.to_string(), reason: "not 32 bytes".into() })?;
+    Ok(VerifyingKey::from_bytes(&bytes).map_err(|e| PluginError::BadTrustedKey { key: hex_key.to_string(), reason: e.to_string() })?)
+}
+
+/// `http(s)://` URLs are downloaded; anything else is a local path.
+async fn fetch(url: &str) -> Result<Vec<u8>> {
+    if url.starts_with("http://") || url.starts_with("https://") {
+        crate::net::check(url)?;
+        let resp = crate::net::client(crate::net::SKILLS).get(url).send().await?.error_for_status()?;
+        return Ok(resp.bytes().await?.to_vec());
+    }
+    let path = url.strip_prefix("file://").unwrap_or(url);
+    tokio::fs::read(path).await.map_err(|e| anyhow::anyhow!("{}: {}", path, e))
+}
+
+/// `url` of an index entry, relative to the index: a remote index's
+/// entries resolve against its URL (`/x.wasm` against its origin), a local
+/// index's against its directory.
+fn resolve(index_url: &str, url: &str) -> String {
+    if url.contains("://") {
+        return url.to_string();
+    }
+    if let Ok(base) = reqwest::Url::parse(index_url) {
+        if matches!(base.scheme(), "http" | "https") {
+            if let Ok(joined) = base.join(url) {
+                return joined.to_string();
+            }
+        }
+    }
+    if url.starts_with('/') {
+        return url.to_string();
+    }
+    match index_url.rfind('/') {
+        Some(i) => format!("{}/{}", &index_url[..i], url),
+        None => url.to_string(),
+    }
+}
+
+/// Numeric parts of a version (`1.10.0-beta` is `[1, 10, 0]`), so that
+/// `1.10.0` sorts after `1.9.2`.
+fn version_key(version: &str) -> Vec<u64> {
+    let release = version.trim().trim_start_matches('v').split(['-', '+']).next().unwrap_or_default();
+    release.split('.').map(|part| part.parse().unwrap_or(0)).collect()
+}
+
+fn artifact_extension(url: &str) -> Result<&str> {
+    let file = url.rsplit('/').next().unwrap_or(url);
+    match Path::new(file).extension().and_then(|e| e.to_str()) {
+        Some(ext @ ("wasm" | "so" | "dylib" | "dll")) => Ok(ext),
+        _ => Err(PluginError::UnsupportedArtifact(url.to_string()).into()),
+    }
+}
+
+fn write_atomic(path: &Path, bytes: &[u8]) -> Result<()> {
+    let tmp = path.with_extension("part");
+    fs::write(&tmp, bytes)?;
+    fs::rename(tmp, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    const SKILL: &[u8] = b"\0asm\x01\0\0\0";
+
+    fn publish(dir: &Path, signature: Option<String>) -> Result<String> {
+        let entry = IndexEntry {
+            name: "grep".into(),
+            version: "0.2.0".into(),
+            description: "Regex search".into(),
+            url: "grep-0.2.0.wasm".into(),
+            sha256: hex::encode(Sha256::digest(SKILL)),
+            signature,
+            permissions: Permissions::default(),
+            panel: None,
+        };
+        fs::write(dir.join("grep-0.2.0.wasm"), SKILL)?;
+        // an older release listed first; install takes the newest
+        let old = IndexEntry { version: "0.1.0".into(), description: "Old".into(), url: "grep-0.1.0.wasm".into(), sha256: "00".into(), ..entry.clone() };
+        let index = dir.join("index.json");
+        fs::write(&index, serde_json::to_string(&SkillIndex { skills: vec![old, entry] })?)?;
+        Ok(index.to_string_lossy().into_owned())
+    }
+
+    #[tokio::test]
+    async fn test_search_and_install_verified_skill() -> Result<()> {
+        let td = tempfile::tempdir()?;
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let signature = hex::encode(key.sign(SKILL).to_bytes());
+        let config = SkillsConfig {
+            index_url: publish(td.path(), Some(signature))?,
+            trusted_keys: vec![hex::encode(key.verifying_key().to_bytes())],
+            require_signature: true,
+        };
+        let skills_dir = td.path().join("skills");
+        let market = Marketplace::new(config, skills_dir.clone());
+
+        let found = market.search("REGEX").await?;
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].status(), "available");
+        assert!(market.search("nothing").await?.is_empty());
+
+        let (entry, verified) = market.install("grep").await?;
+        assert!(matches!(verified, Verified::Signed(_)));
+        assert_eq!(fs::read(skills_dir.join("grep.wasm"))?, SKILL);
+        assert_eq!(market.installed("grep").as_deref(), Some("0.2.0"));
+        assert_eq!(market.search("regex").await?[0].status(), "installed");
+
+        let tampered = IndexEntry { sha256: hex::encode(Sha256::digest(b"other")), ..entry.clone() };
+        assert!(market.verify(&tampered, SKILL).is_err());
+        let unsigned = IndexEntry { signature: None, ..entry };
+        assert!(market.verify(&unsigned, SKILL).is_err(), "signature required");
+        Ok(())
+    }
+
+    #[test]
+    fn test_entry_urls_and_versions() {
+        assert_eq!(resolve("https://skills.example.com/v1/index.json", "grep.wasm"), "https://skills.example.com/v1/grep.wasm");
+        assert_eq!(resolve("https://skills.example.com/v1/index.json", "/dl/grep.wasm"), "https://skills.example.com/dl/grep.wasm");
+        assert_eq!(resolve("/srv/index.json", "/dl/grep.wasm"), "/dl/grep.wasm");
+        assert_eq!(resolve("/srv/index.json", "grep.wasm"), "/srv/grep.wasm");
+        assert!(version_key("1.10.0") > version_key("1.9.2"));
+        assert!(version_key("v2.0.0-beta") > version_key("1.99"));
+    }
+
+    #[tokio::test]
+    async fn test_untrusted_signature_is_refused() -> Result<()> {
+        let td = tempfile::tempdir()?;
+        let signature = hex::encode(SigningKey::from_bytes(&[1; 32]).sign(SKILL).to_bytes());
+        let trusted = SigningKey::from_bytes(&[2; 32]).verifying_key();
+        // opting out of signatures still refuses one from an unknown key
+        let config = SkillsConfig {
+            index_url: publish(td.path(), Some(signature))?,
+            trusted_keys: vec![hex::encode(trusted.to_bytes())],
+            require_signature: false,
+        };
+        let market = Marketplace::new(config, td.path().join("skills"));
+        assert!(market.install("grep").await.is_err());
+        assert!(market.installed("grep").is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_unsigned_skill_needs_opt_out() -> Result<()> {
+        let td = tempfile::tempdir()?;
+        let config = SkillsConfig { index_url: publish(td.path(), None)?, ..SkillsConfig::default() };
+        assert!(config.require_signature);
+        let market = Marketplace::new(config.clone(), td.path().join("skills"));
+        assert!(market.install("grep").await.is_err());
+        assert!(market.installed("grep").is_none());
+
+        let market = Marketplace::new(SkillsConfig { require_signature: false, ..config }, td.path().join("skills"));
+        let (_, verified) = market.install("grep").await?;
+        assert!(matches!(verified, Verified::Checksum));
+        Ok(())
+    }
+}
@@ -1,8 +1,25 @@
 pub mod super_agent;
+pub mod events;
+pub mod human;
+pub mod clarify;
+#[cfg(test)]
+pub mod harness;
 pub mod sub_agent;
+pub mod router;
 pub mod micro_agent;
 pub mod plugin_engine;
+#[cfg(feature = "native-plugins")]
+pub mod native_plugin;
+pub mod skill_manifest;
+pub mod marketplace;
 pub mod project_scanner;
+pub mod review;
+pub mod test_gen;
+pub mod docgen;
+pub mod refactor;
+pub mod triage;
+pub mod shell;
+pub mod watch;
 
 use async_trait::async_trait;
 
@@ -0,0 +1,132 @@
+//! Native tool plugins (`native-plugins` feature): cdylibs in the skills
+//! directory for tools WASM can't serve fast enough. A plugin needs a
+//! manifest beside it (see `skill_manifest`) and exports this C ABI:
+//!
+//! ```c
+//! uint32_t    supercode_abi_version(void);   // must return 1
+//! const char* supercode_declare_tools(void); // JSON: [{"name", "description"}]
+//! char*       supercode_invoke(const char* tool, const char* input_json);
+//! void        supercode_free(char* result);  // frees what invoke returned
+//! ```
+//!
+//! Native code runs unsandboxed: the manifest's permissions are checked
+//! against the workspace policy at load, but not enforced afterwards.
+
+use crate::agent::skill_manifest::SkillManifest;
+use crate::errors::PluginError;
+use crate::tools::policy::FileAccessPolicy;
+use libloading::Library;
+use serde::Deserialize;
+use std::ffi::{c_char, CStr, CString};
+use std::path::Path;
+
+pub const ABI_VERSION: u32 = 1;
+
+type AbiVersionFn = unsafe extern "C" fn() -> u32;
+type DeclareToolsFn = unsafe extern "C" fn() -> *const c_char;
+type InvokeFn = unsafe extern "C" fn(*const c_char, *const c_char) -> *mut c_char;
+type FreeFn = unsafe extern "C" fn(*mut c_char);
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct NativeTool {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+}
+
+pub struct NativePlugin {
+    pub manifest: SkillManifest,
+    pub tools: Vec<NativeTool>,
+    invoke: InvokeFn,
+    free: FreeFn,
+    // keeps the function pointers above valid
+    _lib: Library,
+}
+
+impl NativePlugin {
+    pub fn is_library(path: &Path) -> bool {
+        matches!(path.extension().and_then(|e| e.to_str()), Some("so" | "dylib" | "dll"))
+    }
+
+    pub fn load(path: &Path, policy: &FileAccessPolicy) -> anyhow::Result<Self> {
+        let manifest = SkillManifest::for_skill(path)?
+            .ok_or_else(|| PluginError::BadNativePlugin { path: path.display().to_string(), reason: "no manifest".into() })?;
+        manifest.check(policy)?;
+        // SAFETY: loading runs the library's initialisers; plugins in the
+        // skills directory are trusted like any other installed code.
+        let lib = unsafe { Library::new(path)? };
+        let (version, declare, invoke, free) = unsafe {
+            (
+                *lib.get::<AbiVersionFn>(b"supercode_abi_version\0")?,
+                *lib.get::<DeclareToolsFn>(b"supercode_declare_tools\0")?,
+                *lib.get::<InvokeFn>(b"supercode_invoke\0")?,
+                *lib.get::<FreeFn>(b"supercode_free\0")?,
+            )
+        };
+        let abi = unsafe { version() };
+        if abi != ABI_VERSION {
+            return Err(PluginError::BadNativePlugin { path: path.display().to_string(), reason: format!("plugin ABI {} (expected {})", abi, ABI_VERSION) }.into());
+        }
+        let declared = unsafe { declare() };
+        if declared.is_null() {
+            return Err(PluginError::BadNativePlugin { path: path.display().to_string(), reason: "supercode_declare_tools returned null".into() }.into());
+        }
+        let json = unsafe { CStr::from_ptr(declared) }.to_str()?;
+        let tools = serde_json::from_str(json).map_err(|e| PluginError::BadNativePlugin { path: path.display().to_string(), reason: format!("bad tool declaration: {}", e) })?;
+        Ok(Self { manifest, tools, invoke, free, _lib: lib })
+    }
+
+    pub fn has_tool(&self, name: &str) -> bool {
+        self.tools.iter().any(|t| t.name == name)
+    }
+
+    pub fn invoke(&self, tool: &str, input: &str) -> anyhow::Result<String> {
+        let (tool, input) = (CString::new(tool)?, CString::new(input)?);
+        let out = unsafe { (self.invoke)(tool.as_ptr(), input.as_ptr()) };
+        if out.is_null() {
+            return Err(PluginError::Trapped { name: self.manifest.name.clone(), reason: "returned no result".into() }.into());
+        }
+        let text = unsafe { CStr::from_ptr(out) }.to_string_lossy().into_owned();
+        unsafe { (self.free)(out) };
+        Ok(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLUGIN_C: &str = r#"
+#include <stdlib.h>
+#include <string.h>
+unsigned supercode_abi_version(void) { return 1; }
+const char* supercode_declare_tools(void) { return "[{\"name\": \"upper\", \"description\": \"Uppercase\"}]"; }
+char* supercode_invoke(const char* tool, const char* input) {
+    size_t n = strlen(input);
+    char* out = malloc(n + 1);
+    for (size_t i = 0; i <= n; i++) out[i] = (input[i] >= 'a' && input[i] <= 'z') ? input[i] - 32 : input[i];
+    return out;
+}
+void supercode_free(char* p) { free(p); }
+"#;
+
+    #[test]
+    fn test_load_and_invoke_native_plugin() -> anyhow::Result<()> {
+        let td = tempfile::tempdir()?;
+        std::fs::write(td.path().join("upper.c"), PLUGIN_C)?;
+        let lib = td.path().join("upper.so");
+        let built = std::process::Command::new("cc").args(["-shared", "-fPIC", "-o"]).arg(&lib).arg(td.path().join("upper.c")).status();
+        if !built.map(|s| s.success()).unwrap_or(false) {
+            eprintln!("no C compiler, skipping");
+            return Ok(());
+        }
+        let policy = FileAccessPolicy::new(td.path(), &crate::config::FilesConfig::default());
+        assert!(NativePlugin::load(&lib, &policy).is_err(), "a manifest is required");
+
+        std::fs::write(td.path().join("upper.toml"), "name = \"upper\"\nversion = \"1.0.0\"\n")?;
+        let plugin = NativePlugin::load(&lib, &policy)?;
+        assert!(plugin.has_tool("upper"));
+        assert_eq!(plugin.invoke("upper", r#"{"text": "hi"}"#)?, r#"{"TEXT": "HI"}"#);
+        Ok(())
+    }
+}
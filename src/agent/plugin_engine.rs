@@ -1,9 +1,15 @@
 use anyhow::Result;
 use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
-use wasmtime::{Engine, Module, Store, Linker};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use wasmtime::component::Component;
+use wasmtime::{Config, Engine, Module, Store, Linker};
 use wasmtime_wasi::WasiCtxBuilder;
+use crate::agent::skill_manifest::SkillManifest;
+use crate::errors::PluginError;
+use crate::memory::events::{EventBus, SessionEvent};
+use crate::tools::policy::{Access, FileAccessPolicy};
 use std::io::Write;
 
 /// Simple stdout capturer implementing `Write`
@@ -22,65 +28,330 @@ impl Write for WriteCapturer {
     fn flush(&mut self) -> std::io::Result<()> { Ok(()) }
 }
 
+/// `<home>/skills` (see `crate::paths::home`).
+pub fn default_skills_dir() -> PathBuf {
+    crate::paths::home().join("skills")
+}
+
+/// Host side of `wit/skill.wit`.
+mod bindings {
+    wasmtime::component::bindgen!({ path: "wit/skill.wit", world: "skill" });
+}
+
+pub use bindings::ToolMetadata;
+use bindings::supercode::skill::host::{self as wit_host, Level};
+
+/// A skill is either a component implementing the `skill` world or a core
+/// module using the older `host.write`/`host.readdir` imports.
+#[derive(Clone)]
+enum Compiled {
+    Component(Component),
+    Core(Module),
+}
+
+/// Store data for component skills.
+struct ComponentState {
+    skill: String,
+}
+
+impl wit_host::Host for ComponentState {
+    fn log(&mut self, level: Level, message: String) -> wasmtime::Result<()> {
+        let skill = &self.skill;
+        match level {
+            Level::Debug => tracing::debug!(skill, "{}", message),
+            Level::Info => tracing::info!(skill, "{}", message),
+            Level::Warn => tracing::warn!(skill, "{}", message),
+            Level::Error => tracing::error!(skill, "{}", message),
+        }
+        Ok(())
+    }
+}
+
+/// Compiled skill plus what it was compiled from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SkillVersion {
+    pub version: u32,
+    modified: Option<SystemTime>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SkillChange {
+    Added { name: String },
+    Updated { name: String, version: u32 },
+    Removed { name: String },
+    /// The file failed to compile; any previous module stays loaded.
+    Failed { name: String, error: String },
+}
+
+impl SkillChange {
+    pub fn describe(&self) -> String {
+        match self {
+            SkillChange::Added { name } => format!("skill {} loaded", name),
+            SkillChange::Updated { name, version } => format!("skill {} reloaded (v{})", name, version),
+            SkillChange::Removed { name } => format!("skill {} removed", name),
+            SkillChange::Failed { name, error } => format!("skill {} failed to compile: {}", name, error),
+        }
+    }
+}
+
 pub struct PluginEngine {
     engine: Engine,
-    modules: HashMap<String, Module>,
+    modules: HashMap<String, Compiled>,
+    versions: HashMap<String, SkillVersion>,
+    /// Modification time of skill files that never compiled, so each broken
+    /// version is compiled and reported once.
+    failed: HashMap<String, Option<SystemTime>>,
     skills_dir: PathBuf,
+    policy: FileAccessPolicy,
+    /// Where skill changes are announced to running agents.
+    events: Option<EventBus>,
+    /// cdylib plugins; loaded once, not hot-reloaded.
+    #[cfg(feature = "native-plugins")]
+    native: Vec<crate::agent::native_plugin::NativePlugin>,
 }
 
 impl PluginEngine {
-    pub fn new(skills_dir: Option<PathBuf>) -> Result<Self> {
-        let engine = Engine::default();
-        let skills_dir = skills_dir.unwrap_or_else(|| {
-            let mut p = dirs::home_dir().unwrap_or_else(|| std::path::PathBuf::from("./"));
-            p.push(".supercode/skills");
-            p
-        });
-        Ok(Self { engine, modules: HashMap::new(), skills_dir })
+    /// `policy` restricts the directories skills may list via `host.readdir`.
+    pub fn new(skills_dir: Option<PathBuf>, policy: FileAccessPolicy) -> Result<Self> {
+        let engine = Engine::new(Config::new().wasm_component_model(true))?;
+        let skills_dir = skills_dir.unwrap_or_else(default_skills_dir);
+        Ok(Self {
+            engine,
+            modules: HashMap::new(),
+            versions: HashMap::new(),
+            failed: HashMap::new(),
+            skills_dir,
+            policy,
+            events: None,
+            #[cfg(feature = "native-plugins")]
+            native: vec![],
+        })
+    }
+
+    /// Announce skill changes on `events` as `skills` agent actions.
+    pub fn with_events(mut self, events: EventBus) -> Self {
+        self.events = Some(events);
+        self
     }
 
     /// Scan the skills directory, compile wasm modules and cache them as `Module`
     pub fn load_skills(&mut self) -> Result<()> {
         fs::create_dir_all(&self.skills_dir)?;
+        for (name, p) in self.skill_files()? {
+            let module = self.compile(&p)?;
+            self.modules.insert(name.clone(), module);
+            self.versions.insert(name, SkillVersion { version: 1, modified: Self::mtime(&p) });
+        }
+        #[cfg(feature = "native-plugins")]
+        self.load_native()?;
+        Ok(())
+    }
+
+    /// Load every cdylib in the skills directory; broken plugins are logged
+    /// and skipped.
+    #[cfg(feature = "native-plugins")]
+    fn load_native(&mut self) -> Result<()> {
+        use crate::agent::native_plugin::NativePlugin;
         for entry in fs::read_dir(&self.skills_dir)? {
-            let e = entry?;
-            let p = e.path();
-            if p.is_file() {
-                let ext = p.extension().and_then(|s| s.to_str()).unwrap_or("");
-                let name = p.file_stem().and_then(|s| s.to_str()).unwrap_or("skill").to_string();
-                if ext == "wasm" {
-                    let module = Module::from_file(&self.engine, &p)?;
-                    self.modules.insert(name, module);
-                } else if ext == "wat" {
-                    // parse WAT text into wasm bytes
-                    let s = std::fs::read_to_string(&p)?;
-                    let wasm = wat::parse_str(&s)?;
-                    let module = Module::new(&self.engine, &wasm)?;
-                    self.modules.insert(name, module);
+            let p = entry?.path();
+            if !NativePlugin::is_library(&p) {
+                continue;
+            }
+            match NativePlugin::load(&p, &self.policy) {
+                Ok(plugin) => {
+                    tracing::info!("native plugin {} {} loaded", plugin.manifest.name, plugin.manifest.version);
+                    self.native.push(plugin);
                 }
+                Err(e) => tracing::warn!("skipping native plugin {}: {}", p.display(), e),
             }
         }
         Ok(())
     }
 
-    /// Call a skill by name. The skill can call host-provided functions to communicate
-    /// with the host. We provide a `host.write(ptr, len)` function which reads linear memory
+    /// Tools provided by native plugins, with the plugin providing each.
+    #[cfg(feature = "native-plugins")]
+    pub fn native_tools(&self) -> Vec<(&crate::agent::native_plugin::NativeTool, &str)> {
+        self.native.iter().flat_map(|p| p.tools.iter().map(move |t| (t, p.manifest.name.as_str()))).collect()
+    }
+
+    /// `.wasm`/`.wat` files in the skills directory by skill name.
+    fn skill_files(&self) -> Result<Vec<(String, PathBuf)>> {
+        let mut out = vec![];
+        for entry in fs::read_dir(&self.skills_dir)? {
+            let p = entry?.path();
+            let ext = p.extension().and_then(|s| s.to_str()).unwrap_or("");
+            if p.is_file() && (ext == "wasm" || ext == "wat") {
+                let name = p.file_stem().and_then(|s| s.to_str()).unwrap_or("skill").to_string();
+                out.push((name, p));
+            }
+        }
+        Ok(out)
+    }
+
+    fn compile(&self, p: &Path) -> Result<Compiled> {
+        if let Some(manifest) = SkillManifest::for_skill(p)? {
+            manifest.check(&self.policy)?;
+        }
+        let wasm = if p.extension().map(|e| e == "wat").unwrap_or(false) {
+            // parse WAT text into wasm bytes
+            wat::parse_str(fs::read_to_string(p)?)?
+        } else {
+            fs::read(p)?
+        };
+        // the binary header's layer field (bytes 6..8) is 1 for components
+        if wasm.get(6..8) == Some(&[1, 0]) {
+            Ok(Compiled::Component(Component::new(&self.engine, &wasm)?))
+        } else {
+            Ok(Compiled::Core(Module::new(&self.engine, &wasm)?))
+        }
+    }
+
+    fn instantiate_component(&self, name: &str, component: &Component) -> Result<(bindings::Skill, Store<ComponentState>)> {
+        let mut store = Store::new(&self.engine, ComponentState { skill: name.to_string() });
+        let mut linker = wasmtime::component::Linker::new(&self.engine);
+        bindings::Skill::add_to_linker(&mut linker, |state: &mut ComponentState| state)?;
+        let (skill, _) = bindings::Skill::instantiate(&mut store, component, &linker)?;
+        Ok((skill, store))
+    }
+
+    /// Tool metadata of a component skill; `None` for legacy core modules.
+    pub fn metadata(&self, name: &str) -> Result<Option<ToolMetadata>> {
+        match self.modules.get(name).ok_or_else(|| PluginError::SkillNotFound(name.to_string()))? {
+            Compiled::Component(component) => {
+                let (skill, mut store) = self.instantiate_component(name, component)?;
+                Ok(Some(skill.call_metadata(&mut store)?))
+            }
+            Compiled::Core(_) => Ok(None),
+        }
+    }
+
+    fn mtime(p: &Path) -> Option<SystemTime> {
+        fs::metadata(p).and_then(|m| m.modified()).ok()
+    }
+
+    /// Loaded skills with their versions, by name.
+    pub fn skills(&self) -> Vec<(String, u32)> {
+        let mut out: Vec<(String, u32)> = self.versions.iter().map(|(n, v)| (n.clone(), v.version)).collect();
+        out.sort();
+        out
+    }
+
+    /// Loaded skills whose manifest asks for a TUI panel.
+    pub fn panels(&self) -> Vec<(String, crate::agent::skill_manifest::PanelManifest)> {
+        let mut out = vec![];
+        for (name, p) in self.skill_files().unwrap_or_default() {
+            if !self.modules.contains_key(&name) {
+                continue;
+            }
+            match SkillManifest::for_skill(&p) {
+                Ok(Some(SkillManifest { panel: Some(panel), .. })) => out.push((name, panel)),
+                Ok(_) => {}
+                Err(e) => tracing::warn!("skill {}: {}", name, e),
+            }
+        }
+        out.sort_by(|a, b| a.0.cmp(&b.0));
+        out
+    }
+
+    /// Recompile skills whose file changed since it was loaded, load new
+    /// ones and drop deleted ones. Cheap when nothing changed, so it can be
+    /// polled. Each change is announced on the event bus.
+    pub fn reload_changed(&mut self) -> Result<Vec<SkillChange>> {
+        self.sync(false)
+    }
+
+    /// Recompile every skill (`/skills reload`).
+    pub fn reload_all(&mut self) -> Result<Vec<SkillChange>> {
+        self.sync(true)
+    }
+
+    fn sync(&mut self, force: bool) -> Result<Vec<SkillChange>> {
+        fs::create_dir_all(&self.skills_dir)?;
+        let files = self.skill_files()?;
+        let mut changes = vec![];
+        for (name, p) in &files {
+            let modified = Self::mtime(p);
+            let previous = self.versions.get(name).copied();
+            let seen = previous.map(|v| v.modified).or_else(|| self.failed.get(name).copied());
+            if !force && seen == Some(modified) {
+                continue;
+            }
+            match self.compile(p) {
+                Ok(module) => {
+                    self.failed.remove(name);
+                    let version = previous.map(|v| v.version + 1).unwrap_or(1);
+                    self.modules.insert(name.clone(), module);
+                    self.versions.insert(name.clone(), SkillVersion { version, modified });
+                    changes.push(match previous {
+                        Some(_) => SkillChange::Updated { name: name.clone(), version },
+                        None => SkillChange::Added { name: name.clone() },
+                    });
+                }
+                Err(e) => {
+                    // remember the mtime so a broken file is reported once
+                    match self.versions.get_mut(name) {
+                        Some(v) => v.modified = modified,
+                        None => {
+                            self.failed.insert(name.clone(), modified);
+                        }
+                    }
+                    changes.push(SkillChange::Failed { name: name.clone(), error: e.to_string() });
+                }
+            }
+        }
+        self.failed.retain(|n, _| files.iter().any(|(f, _)| f == n));
+        let gone: Vec<String> = self.versions.keys().filter(|n| !files.iter().any(|(f, _)| f == *n)).cloned().collect();
+        for name in gone {
+            self.modules.remove(&name);
+            self.versions.remove(&name);
+            changes.push(SkillChange::Removed { name });
+        }
+        for change in &changes {
+            tracing::info!("{}", change.describe());
+            if let Some(events) = &self.events {
+                events.publish(SessionEvent::AgentAction { session: "skills".into(), agent: "plugin_engine".into(), action: change.describe() });
+            }
+        }
+        Ok(changes)
+    }
+
+    /// Call a skill by name. Component skills get `input` (JSON, `{}` when
+    /// absent) through `invoke` and return its JSON reply.
+    ///
+    /// Core modules are the compatibility path: they can call host-provided functions to
+    /// communicate with the host. We provide a `host.write(ptr, len)` function which reads linear memory
     /// from the guest and appends it to a host-side buffer which is returned as the output.
-    pub fn call_skill(&self, name: &str, _input: Option<&str>) -> Result<String> {
+    pub fn call_skill(&self, name: &str, input: Option<&str>) -> Result<String> {
         use wasmtime::{Caller, Extern};
         use std::sync::{Arc, Mutex};
 
-        let module = self.modules.get(name).ok_or_else(|| anyhow::anyhow!("skill not found"))?;
+        #[cfg(feature = "native-plugins")]
+        if !self.modules.contains_key(name) {
+            if let Some(plugin) = self.native.iter().find(|p| p.has_tool(name)) {
+                return plugin.invoke(name, input.unwrap_or("{}"));
+            }
+        }
+        let module = match self.modules.get(name).ok_or_else(|| PluginError::SkillNotFound(name.to_string()))? {
+            Compiled::Component(component) => {
+                let (skill, mut store) = self.instantiate_component(name, component)?;
+                return skill
+                    .call_invoke(&mut store, input.unwrap_or("{}"))?
+                    .map_err(|e| PluginError::Trapped { name: name.to_string(), reason: e.to_string() }.into());
+            }
+            Compiled::Core(module) => module,
+        };
 
         // Host state: a simple buffer to collect strings emitted by the wasm module.
         struct HostData {
             out: Arc<Mutex<String>>,
             wasi: wasmtime_wasi::WasiCtx,
+            policy: FileAccessPolicy,
         }
 
         let host_state = HostData {
             out: Arc::new(Mutex::new(String::new())),
             wasi: WasiCtxBuilder::new().inherit_stdio().build(),
+            policy: self.policy.clone(),
         };
         let mut store = Store::new(&self.engine, host_state);
         let mut linker: Linker<HostData> = Linker::new(&self.engine);
@@ -114,9 +385,22 @@ impl PluginEngine {
                 return Ok(());
             }
             let path = String::from_utf8_lossy(&buf).to_string();
+            let policy = caller.data().policy.clone();
+            let dir = match policy.check(std::path::Path::new(&path), Access::Read) {
+                Ok(dir) => dir,
+                Err(e) => {
+                    tracing::warn!("skill readdir blocked: {}", e);
+                    if let Ok(mut out) = caller.data().out.lock() {
+                        out.push_str(&serde_json::json!({ "error": e.to_string() }).to_string());
+                    }
+                    return Ok(());
+                }
+            };
             let mut list = vec![];
-            if let Ok(entries) = std::fs::read_dir(&path) {
+            if let Ok(entries) = std::fs::read_dir(&dir) {
                 for e in entries.flatten() {
+                    // hide denied entries instead of leaking their names
+                    if policy.check(&e.path(), Access::Read).is_err() { continue; }
                     if let Some(n) = e.file_name().to_str() { list.push(n.to_string()); }
                 }
             }
@@ -167,11 +451,11 @@ mod tests {
             )
         )"#;
 
-        let engine = Engine::default();
-        let mut pe = PluginEngine { engine: engine.clone(), modules: HashMap::new(), skills_dir: dirs::home_dir().unwrap_or_default() };
+        let mut pe = PluginEngine::new(None, FileAccessPolicy::unrestricted())?;
+        let engine = pe.engine.clone();
         let wasm = wat::parse_str(wat)?;
         let module = Module::new(&engine, &wasm)?;
-        pe.modules.insert("test".into(), module);
+        pe.modules.insert("test".into(), Compiled::Core(module));
         let out = pe.call_skill("test", None)?;
         assert!(out.contains("Hello Wasm"));
         Ok(())
@@ -197,14 +481,97 @@ mod tests {
             )
         )"#, path, path.len());
 
-        let engine = Engine::default();
-        let mut pe = PluginEngine { engine: engine.clone(), modules: HashMap::new(), skills_dir: dirs::home_dir().unwrap_or_default() };
+        let mut pe = PluginEngine::new(None, FileAccessPolicy::unrestricted())?;
+        let engine = pe.engine.clone();
         let wasm = wat::parse_str(&wat)?;
         let module = Module::new(&engine, &wasm)?;
-        pe.modules.insert("readdir_test".into(), module);
+        pe.modules.insert("readdir_test".into(), Compiled::Core(module));
         let out = pe.call_skill("readdir_test", None)?;
         assert!(out.contains("a.txt"));
         assert!(out.contains("b.txt"));
         Ok(())
     }
+
+    #[test]
+    fn test_hot_reload_replaces_changed_modules() -> anyhow::Result<()> {
+        let td = tempfile::tempdir()?;
+        let skill = |text: &str| format!(r#"(module
+            (import "host" "write" (func $write (param i32 i32)))
+            (memory (export "memory") 1)
+            (data (i32.const 0) "{}")
+            (func (export "run") i32.const 0 i32.const {} call $write))"#, text, text.len());
+        let path = td.path().join("greet.wat");
+        std::fs::write(&path, skill("v1"))?;
+        let events = EventBus::default();
+        let mut watcher = events.subscribe("agent", &[crate::memory::events::EventKind::AgentAction]);
+        let mut pe = PluginEngine::new(Some(td.path().to_path_buf()), FileAccessPolicy::unrestricted())?.with_events(events);
+        pe.load_skills()?;
+        assert!(pe.reload_changed()?.is_empty());
+
+        std::fs::write(&path, skill("v2"))?;
+        let f = std::fs::File::options().write(true).open(&path)?;
+        f.set_modified(SystemTime::now() + std::time::Duration::from_secs(5))?;
+        assert_eq!(pe.reload_changed()?, vec![SkillChange::Updated { name: "greet".into(), version: 2 }]);
+        assert_eq!(pe.call_skill("greet", None)?, "v2");
+        assert!(watcher.drain().iter().any(|e| e.describe().contains("greet reloaded (v2)")));
+
+        std::fs::write(td.path().join("broken.wat"), "(module")?;
+        std::fs::remove_file(&path)?;
+        let changes = pe.reload_changed()?;
+        assert!(matches!(changes.as_slice(), [SkillChange::Failed { .. }, SkillChange::Removed { .. }]), "{:?}", changes);
+        assert!(pe.call_skill("greet", None).is_err());
+        assert!(pe.reload_changed()?.is_empty(), "a new broken file is reported once");
+        assert!(pe.skills().is_empty());
+        Ok(())
+    }
+
+    /// Component echoing its input, written against `wit/skill.wit`.
+    const ECHO_COMPONENT: &str = r#"(component
+        (core module $m
+            (memory (export "memory") 1)
+            (global $bump (mut i32) (i32.const 1024))
+            (data (i32.const 100) "echo")
+            (data (i32.const 110) "Echo the input")
+            (data (i32.const 130) "{}")
+            (func (export "cabi_realloc") (param i32 i32 i32 i32) (result i32)
+                (local $p i32)
+                (local.set $p (global.get $bump))
+                (global.set $bump (i32.add (global.get $bump) (local.get 3)))
+                (local.get $p))
+            (func (export "metadata") (result i32)
+                (i32.store (i32.const 32) (i32.const 100))
+                (i32.store (i32.const 36) (i32.const 4))
+                (i32.store (i32.const 40) (i32.const 110))
+                (i32.store (i32.const 44) (i32.const 14))
+                (i32.store (i32.const 48) (i32.const 130))
+                (i32.store (i32.const 52) (i32.const 2))
+                (i32.const 32))
+            (func (export "invoke") (param i32 i32) (result i32)
+                (i32.store8 (i32.const 64) (i32.const 0))
+                (i32.store (i32.const 68) (local.get 0))
+                (i32.store (i32.const 72) (local.get 1))
+                (i32.const 64))
+        )
+        (core instance $i (instantiate $m))
+        (type $meta (record (field "name" string) (field "description" string) (field "input-schema" string)))
+        (export $meta-out "tool-metadata" (type $meta))
+        (func $metadata (result $meta-out)
+            (canon lift (core func $i "metadata") (memory $i "memory") (realloc (func $i "cabi_realloc"))))
+        (func $invoke (param "input" string) (result (result string (error string)))
+            (canon lift (core func $i "invoke") (memory $i "memory") (realloc (func $i "cabi_realloc"))))
+        (export "metadata" (func $metadata))
+        (export "invoke" (func $invoke))
+    )"#;
+
+    #[test]
+    fn test_component_skill_metadata_and_invoke() -> anyhow::Result<()> {
+        let td = tempfile::tempdir()?;
+        std::fs::write(td.path().join("echo.wat"), ECHO_COMPONENT)?;
+        let mut pe = PluginEngine::new(Some(td.path().to_path_buf()), FileAccessPolicy::unrestricted())?;
+        pe.load_skills()?;
+        let meta = pe.metadata("echo")?.expect("component skills describe their tool");
+        assert_eq!((meta.name.as_str(), meta.description.as_str(), meta.input_schema.as_str()), ("echo", "Echo the input", "{}"));
+        assert_eq!(pe.call_skill("echo", Some(r#"{"x": 1}"#))?, r#"{"x": 1}"#);
+        Ok(())
+    }
 }
@@ -1,26 +1,29 @@
 use crate::agent::plugin_engine::PluginEngine;
 use crate::agent::LocalAgent;
+use crate::tools::policy::FileAccessPolicy;
 use async_trait::async_trait;
+use parking_lot::RwLock;
 use std::path::PathBuf;
 use std::time::Duration;
 
 pub struct ProjectScannerAgent {
-    pub engine: PluginEngine,
+    /// Reloaded on every tick, so edited skills take effect without a restart.
+    pub engine: RwLock<PluginEngine>,
     pub interval: Duration,
 }
 
 impl ProjectScannerAgent {
-    pub fn new(skills_dir: Option<PathBuf>, interval: Duration) -> anyhow::Result<Self> {
-        let mut engine = PluginEngine::new(skills_dir)?;
+    pub fn new(skills_dir: Option<PathBuf>, interval: Duration, policy: FileAccessPolicy) -> anyhow::Result<Self> {
+        let mut engine = PluginEngine::new(skills_dir, policy)?;
         engine.load_skills()?;
-        Ok(Self { engine, interval })
+        Ok(Self { engine: RwLock::new(engine), interval })
     }
 
     #[cfg(test)]
     fn for_tests(skills_dir: Option<std::path::PathBuf>) -> anyhow::Result<Self> {
-        let mut engine = PluginEngine::new(skills_dir)?;
+        let mut engine = PluginEngine::new(skills_dir, FileAccessPolicy::unrestricted())?;
         engine.load_skills()?;
-        Ok(Self { engine, interval: std::time::Duration::from_secs(1) })
+        Ok(Self { engine: RwLock::new(engine), interval: std::time::Duration::from_secs(1) })
     }
 }
 
@@ -30,8 +33,12 @@ impl LocalAgent for ProjectScannerAgent {
 
     async fn run_forever(&self) -> anyhow::Result<()> {
         loop {
+            if let Err(e) = self.engine.write().reload_changed() {
+                tracing::warn!("skill reload failed: {}", e);
+            }
             // Attempt to call the wasm skill if available
-            match self.engine.call_skill("project_scanner", None) {
+            let result = self.engine.read().call_skill("project_scanner", None);
+            match result {
                 Ok(out) => {
                     // In a real system we'd push this into shared memory or a database
                     tracing::info!("ProjectScanner output-length = {}", out.len());
@@ -54,7 +61,7 @@ mod tests {
         let skills_dir = Some(std::path::PathBuf::from("./scripts/skills"));
         let agent = ProjectScannerAgent::for_tests(skills_dir)?;
         // call the skill directly
-        let out = agent.engine.call_skill("project_scanner", None)?;
+        let out = agent.engine.read().call_skill("project_scanner", None)?;
         // output should be JSON array (maybe empty) or string
         assert!(out.len() >= 0);
         Ok(())
@@ -0,0 +1,311 @@
+//! Scoped refactoring. `agent refactor "<instruction>" --paths src/tui`
+//! limits a change to the selected paths: only files in scope are shown to
+//! the model and the patch tool refuses to write anywhere else. The model
+//! first plans which files change and how, then writes the change as one
+//! unified diff; each diff is shown for review before `apply_patch` applies
+//! it. The project's build and test commands then verify the result, and
+//! failures go back to the model for a follow-up diff. When the change is
+//! rejected or never verifies, every file is put back as it was.
+
+use crate::config::FilesConfig;
+use crate::llm::Llm;
+use crate::tools::build::run_tests;
+use crate::tools::patch::{self, PatchTool};
+use crate::tools::policy::{glob_match, FileAccessPolicy};
+use crate::tools::registry::{Tool, ToolRegistry};
+use crate::types::{AgentInput, Message};
+use crate::workspace::{file_index, ProjectProfile};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+
+/// Bytes of file contents sent with the patch request.
+const MAX_CONTEXT_BYTES: usize = 48_000;
+
+/// Globs for `--paths`: a directory covers everything under it.
+pub fn scope_globs(root: &Path, paths: &[String]) -> Vec<String> {
+    paths
+        .iter()
+        .map(|p| {
+            let p = p.trim_start_matches("./").trim_end_matches('/');
+            if root.join(p).is_dir() { format!("{}/", p) } else { p.to_string() }
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct PlannedChange {
+    pub file: String,
+    pub change: String,
+}
+
+fn plan_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "steps": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {"file": {"type": "string"}, "change": {"type": "string"}},
+                    "required": ["file", "change"]
+                }
+            }
+        },
+        "required": ["steps"]
+    })
+}
+
+/// Outcome of `run`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RefactorReport {
+    pub plan: Vec<PlannedChange>,
+    /// Files the applied patches touched.
+    pub changed: Vec<String>,
+    pub attempts: usize,
+    /// Whether the change was kept.
+    pub applied: bool,
+    /// Why it was not, or the build and test output.
+    pub detail: String,
+}
+
+impl RefactorReport {
+    pub fn render(&self) -> String {
+        let mut out = String::from("Plan:\n");
+        for (i, step) in self.plan.iter().enumerate() {
+            out.push_str(&format!("{}. {}: {}\n", i + 1, step.file, step.change));
+        }
+        if self.applied {
+            out.push_str(&format!("\nChanged {} file(s) in {} attempt(s): {}\n", self.changed.len(), self.attempts, self.changed.join(", ")));
+        } else {
+            out.push_str("\nNothing was changed.\n");
+        }
+        if !self.detail.is_empty() {
+            out.push_str(&format!("{}\n", self.detail));
+        }
+        out
+    }
+}
+
+/// Original contents of the files a refactor touched; `None` for files it
+/// created.
+#[derive(Default)]
+struct Backup(Vec<(PathBuf, Option<String>)>);
+
+impl Backup {
+    fn save(&mut self, path: PathBuf) {
+        if !self.0.iter().any(|(p, _)| *p == path) {
+            let original = std::fs::read_to_string(&path).ok();
+            self.0.push((path, original));
+        }
+    }
+
+    fn restore(&self) {
+        for (path, original) in &self.0 {
+            let res = match original {
+                Some(text) => std::fs::write(path, text),
+                None => std::fs::remove_file(path),
+            };
+            if let Err(e) = res {
+                log::warn!("could not restore {}: {}", path.display(), e);
+            }
+        }
+    }
+}
+
+/// Run the build and test commands; `None` when the project has neither.
+fn verify(root: &Path, profile: &ProjectProfile) -> anyhow::Result<Option<(bool, String)>> {
+    let commands: Vec<&String> = [&profile.build_command, &profile.test_command].into_iter().flatten().collect();
+    if commands.is_empty() {
+        return Ok(None);
+    }
+    for command in commands {
+        let run = run_tests(root, command, "")?;
+        if !run.passed {
+            return Ok(Some((false, format!("`{}` failed:\n{}", command, run.output))));
+        }
+    }
+    Ok(Some((true, String::new())))
+}
+
+/// The diff in `reply`: its fenced block, or all of it.
+fn extract_diff(reply: &str) -> String {
+    let mut parts = reply.split("```");
+    match (parts.next(), parts.next()) {
+        (Some(_), Some(block)) => block.split_once('\n').map(|(_, d)| d).unwrap_or(block).to_string(),
+        _ => reply.to_string(),
+    }
+}
+
+/// What to change and where.
+#[derive(Debug, Clone)]
+pub struct RefactorRequest {
+    pub instruction: String,
+    /// Globs relative to the root, from `scope_globs`.
+    pub scope: Vec<String>,
+    /// Diffs tried (the first plus repairs) before giving up.
+    pub attempts: usize,
+}
+
+/// Plan and apply `request` under `root`, asking `review` about every diff.
+pub async fn run(
+    llm: &dyn Llm,
+    root: &Path,
+    request: &RefactorRequest,
+    files: &FilesConfig,
+    profile: &ProjectProfile,
+    mut review: impl FnMut(&str) -> anyhow::Result<bool>,
+) -> anyhow::Result<RefactorReport> {
+    let (instruction, scope) = (request.instruction.as_str(), &request.scope);
+    let in_scope: Vec<String> = file_index(root, profile, &FileAccessPolicy::new(root, files))
+        .into_iter()
+        .filter(|f| scope.iter().any(|g| glob_match(g, f)))
+        .collect();
+    if in_scope.is_empty() {
+        anyhow::bail!("no files match {}", scope.join(", "));
+    }
+
+    let messages = vec![
+        Message::new(
+            "system",
+            "You plan code changes. List each file that must change for the instruction and what changes in it. \
+             Only files from the list given may change; new files must be inside the same directories.",
+        ),
+        Message::new("user", format!("Instruction: {}\n\nFiles in scope:\n{}", instruction, in_scope.join("\n"))),
+    ];
+    let reply = crate::llm::structured::complete(llm, &messages, &plan_schema(), 2).await?;
+    let plan: Vec<PlannedChange> = serde_json::from_value(reply["steps"].clone())?;
+    let mut report = RefactorReport { plan, changed: vec![], attempts: 0, applied: false, detail: String::new() };
+    if report.plan.is_empty() {
+        report.detail = "The model found nothing to change.".into();
+        return Ok(report);
+    }
+
+    let mut context = String::new();
+    for step in report.plan.iter().filter(|s| in_scope.contains(&s.file)) {
+        let Ok(text) = std::fs::read_to_string(root.join(&step.file)) else { continue };
+        if context.len() + text.len() > MAX_CONTEXT_BYTES {
+            break;
+        }
+        context.push_str(&format!("--- {} ---\n{}\n", step.file, text));
+    }
+    let steps: Vec<String> = report.plan.iter().map(|s| format!("- {}: {}", s.file, s.change)).collect();
+    let mut messages = vec![
+        Message::new(
+            "system",
+            format!(
+                "You make code changes as one unified diff with `--- a/path` and `+++ b/path` headers and `@@` hunks \
+                 with three lines of context. Use `--- /dev/null` for new files. Only these paths may change: {}. \
+                 Reply with the diff in a single fenced block.",
+                scope.join(", ")
+            ),
+        ),
+        Message::new("user", format!("Instruction: {}\n\nPlan:\n{}\n\nCurrent files:\n{}", instruction, steps.join("\n"), context)),
+    ];
+
+    let mut write_scope = files.clone();
+    write_scope.allow = scope.to_vec();
+    let policy = FileAccessPolicy::new(root, &write_scope).with_writable(&profile.policy.writable);
+    let tool: std::sync::Arc<dyn Tool> = std::sync::Arc::new(PatchTool { policy, recorder: None });
+    let name = tool.name();
+    let tools = ToolRegistry::new();
+    tools.register(tool);
+    let mut backup = Backup::default();
+    while report.attempts < request.attempts.max(1) {
+        report.attempts += 1;
+        let reply = llm.chat(&messages).await?;
+        let diff = extract_diff(&reply);
+        messages.push(Message::new("assistant", reply));
+        if !review(&diff)? {
+            backup.restore();
+            report.detail = "The change was rejected.".into();
+            return Ok(report);
+        }
+        let touched = patch::parse(&diff).map(|ps| ps.into_iter().map(|p| p.path).collect::<Vec<_>>()).unwrap_or_default();
+        for file in &touched {
+            backup.save(root.join(file));
+        }
+        if let Err(e) = tools.run(name, AgentInput { text: diff }) {
+            messages.push(Message::new("user", format!("The patch did not apply: {}\nReply with a corrected diff against the current files.", e)));
+            report.detail = format!("The last patch did not apply: {}", e);
+            continue;
+        }
+        for file in touched {
+            if !report.changed.contains(&file) {
+                report.changed.push(file);
+            }
+        }
+        match verify(root, profile)? {
+            None => {
+                report.applied = true;
+                report.detail = "No build or test command is configured, so the change is unverified.".into();
+                return Ok(report);
+            }
+            Some((true, _)) => {
+                report.applied = true;
+                report.detail = "The build and tests pass.".into();
+                return Ok(report);
+            }
+            Some((false, output)) => {
+                messages.push(Message::new(
+                    "user",
+                    format!("After the patch, {}\nReply with a diff against the files as they are now that fixes this.", output),
+                ));
+                report.detail = output;
+            }
+        }
+    }
+    backup.restore();
+    report.changed.clear();
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::mock::ScriptedLlm;
+    use crate::workspace::ProjectKind;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_out_of_scope_patches_are_refused_and_failures_rolled_back() {
+        let td = tempfile::tempdir().unwrap();
+        for dir in ["src/tui", "src/core"] {
+            std::fs::create_dir_all(td.path().join(dir)).unwrap();
+        }
+        std::fs::write(td.path().join("src/tui/app.rs"), "fn draw() {}\n").unwrap();
+        std::fs::write(td.path().join("src/core/lib.rs"), "fn core() {}\n").unwrap();
+        let scope = scope_globs(td.path(), &["src/tui".to_string()]);
+        assert_eq!(scope, vec!["src/tui/"]);
+        let mut profile = ProjectProfile::for_kind(ProjectKind::Unknown);
+        profile.test_command = Some("grep -q render src/tui/app.rs".into());
+
+        let plan = r#"{"steps": [{"file": "src/tui/app.rs", "change": "rename draw to render"}]}"#;
+        let outside = "```diff\n--- a/src/core/lib.rs\n+++ b/src/core/lib.rs\n@@ -1 +1 @@\n-fn core() {}\n+fn kernel() {}\n```";
+        let inside = "```diff\n--- a/src/tui/app.rs\n+++ b/src/tui/app.rs\n@@ -1 +1 @@\n-fn draw() {}\n+fn render() {}\n```";
+        let llm = ScriptedLlm::replies(&[plan, outside, inside]);
+        let mut reviewed = 0;
+        let request = RefactorRequest { instruction: "rename draw".into(), scope, attempts: 3 };
+        let report = run(&llm, td.path(), &request, &Default::default(), &profile, |_| {
+            reviewed += 1;
+            Ok(true)
+        })
+        .await
+        .unwrap();
+        assert!(report.applied, "{}", report.detail);
+        assert_eq!((report.attempts, reviewed), (2, 2));
+        assert_eq!(report.changed, vec!["src/tui/app.rs"]);
+        assert_eq!(std::fs::read_to_string(td.path().join("src/core/lib.rs")).unwrap(), "fn core() {}\n");
+        assert!(llm.calls()[2].last().unwrap().content.starts_with("The patch did not apply"));
+
+        // a change whose tests keep failing is undone
+        profile.test_command = Some("false".into());
+        let undo = "```diff\n--- a/src/tui/app.rs\n+++ b/src/tui/app.rs\n@@ -1 +1 @@\n-fn render() {}\n+fn paint() {}\n```";
+        let llm = ScriptedLlm::replies(&[plan, undo]);
+        let request = RefactorRequest { instruction: "rename render".into(), attempts: 1, ..request };
+        let report = run(&llm, td.path(), &request, &Default::default(), &profile, |_| Ok(true)).await.unwrap();
+        assert!(!report.applied);
+        assert!(report.detail.contains("`false` failed"));
+        assert_eq!(std::fs::read_to_string(td.path().join("src/tui/app.rs")).unwrap(), "fn render() {}\n");
+    }
+}
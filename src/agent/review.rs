@@ -0,0 +1,190 @@
+//! Code review over a git diff. `agent review [--base main]` (or `/review`
+//! in the TUI) diffs the working tree against where the current branch left
+//! `base`, splits the diff by file into chunks of at most
+//! `review.chunk_bytes`, and asks the model to review each chunk as JSON
+//! findings. Findings come back most severe first; the TUI lists them in the
+//! Review view, where Enter opens the file at the finding's line and `f`
+//! starts an agent run to fix it.
+
+use crate::llm::Llm;
+use crate::types::Message;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::Path;
+
+/// `[review]` in config.toml.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReviewConfig {
+    /// Branch diffs are taken against when `--base` is not given.
+    pub base: String,
+    /// Largest chunk of diff sent in one request; a bigger file goes alone.
+    pub chunk_bytes: usize,
+}
+
+impl Default for ReviewConfig {
+    fn default() -> Self {
+        Self { base: "main".into(), chunk_bytes: 16_000 }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Nit,
+    Minor,
+    Major,
+    Critical,
+}
+
+impl Severity {
+    pub fn label(self) -> &'static str {
+        match self {
+            Severity::Nit => "nit",
+            Severity::Minor => "minor",
+            Severity::Major => "major",
+            Severity::Critical => "critical",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Finding {
+    pub severity: Severity,
+    pub file: String,
+    /// Line in the new version of the file.
+    #[serde(default)]
+    pub line: Option<usize>,
+    pub message: String,
+    #[serde(default)]
+    pub suggestion: Option<String>,
+}
+
+impl Finding {
+    /// `src/lib.rs:12`, or just the file.
+    pub fn location(&self) -> String {
+        match self.line {
+            Some(line) => format!("{}:{}", self.file, line),
+            None => self.file.clone(),
+        }
+    }
+
+    /// Goal for an agent run that fixes this finding.
+    pub fn fix_goal(&self) -> String {
+        let mut goal = format!("Fix this review finding at {}: {}", self.location(), self.message);
+        if let Some(s) = &self.suggestion {
+            goal.push_str(&format!("\nSuggested fix: {}", s));
+        }
+        goal
+    }
+}
+
+/// Schema of one chunk's reply.
+pub fn schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "findings": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "severity": {"type": "string", "enum": ["nit", "minor", "major", "critical"]},
+                        "file": {"type": "string"},
+                        "line": {"type": ["integer", "null"]},
+                        "message": {"type": "string"},
+                        "suggestion": {"type": ["string", "null"]}
+                    },
+                    "required": ["severity", "file", "message"]
+                }
+            }
+        },
+        "required": ["findings"]
+    })
+}
+
+/// The branch's changes: the working tree against the merge base of `base`
+/// and HEAD, so uncommitted edits are reviewed too.
+pub fn git_diff(root: &Path, base: &str) -> anyhow::Result<String> {
+    let git = |args: &[&str]| -> anyhow::Result<String> {
+        let out = std::process::Command::new("git").args(args).current_dir(root).output()?;
+        if !out.status.success() {
+            anyhow::bail!("git {}: {}", args.join(" "), String::from_utf8_lossy(&out.stderr).trim());
+        }
+        Ok(String::from_utf8_lossy(&out.stdout).into_owned())
+    };
+    let merge_base = git(&["merge-base", base, "HEAD"])?;
+    git(&["diff", "--no-color", merge_base.trim()])
+}
+
+/// Split `diff` at file boundaries into chunks of at most `max_bytes`.
+pub fn split_by_file(diff: &str, max_bytes: usize) -> Vec<String> {
+    let mut files: Vec<String> = vec![];
+    for line in diff.split_inclusive('\n') {
+        match files.last_mut() {
+            Some(file) if !line.starts_with("diff --git ") => file.push_str(line),
+            _ => files.push(line.to_string()),
+        }
+    }
+    let mut chunks: Vec<String> = vec![];
+    for file in files {
+        match chunks.last_mut() {
+            Some(chunk) if chunk.len() + file.len() <= max_bytes => chunk.push_str(&file),
+            _ => chunks.push(file),
+        }
+    }
+    chunks
+}
+
+fn request(chunk: &str) -> Vec<Message> {
+    vec![
+        Message::new(
+            "system",
+            "You are reviewing a code change. Report bugs, risky changes, missing error handling and unclear code in the \
+             diff below; say nothing about unchanged code. For each finding give the file, the line in the new version, \
+             a severity (critical, major, minor or nit), what is wrong and, when you can, a concrete suggestion. \
+             Reply with no findings when the change looks right.",
+        ),
+        Message::new("user", chunk),
+    ]
+}
+
+/// Review `diff` chunk by chunk; findings are sorted most severe first.
+pub async fn review(llm: &dyn Llm, diff: &str, config: &ReviewConfig) -> anyhow::Result<Vec<Finding>> {
+    let schema = schema();
+    let mut findings = vec![];
+    for chunk in split_by_file(diff, config.chunk_bytes) {
+        let reply = crate::llm::structured::complete(llm, &request(&chunk), &schema, 2).await?;
+        let found: Vec<Finding> = serde_json::from_value(reply["findings"].clone())?;
+        findings.extend(found);
+    }
+    findings.sort_by(|a, b| b.severity.cmp(&a.severity).then(a.file.cmp(&b.file)).then(a.line.cmp(&b.line)));
+    Ok(findings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::mock::ScriptedLlm;
+
+    const DIFF: &str = "diff --git a/a.rs b/a.rs\n+fn a() {}\ndiff --git a/b.rs b/b.rs\n+fn b() {}\n";
+
+    #[test]
+    fn test_diffs_split_at_file_boundaries() {
+        assert_eq!(split_by_file(DIFF, 1000).len(), 1);
+        let chunks = split_by_file(DIFF, 40);
+        assert_eq!(chunks, vec!["diff --git a/a.rs b/a.rs\n+fn a() {}\n", "diff --git a/b.rs b/b.rs\n+fn b() {}\n"]);
+    }
+
+    #[tokio::test]
+    async fn test_findings_are_merged_most_severe_first() {
+        let llm = ScriptedLlm::replies(&[
+            r#"{"findings": [{"severity": "nit", "file": "a.rs", "line": 1, "message": "name"}]}"#,
+            r#"{"findings": [{"severity": "major", "file": "b.rs", "line": 1, "message": "panics", "suggestion": "return an error"}]}"#,
+        ]);
+        let findings = review(&llm, DIFF, &ReviewConfig { chunk_bytes: 40, ..Default::default() }).await.unwrap();
+        assert_eq!(findings.iter().map(|f| f.severity).collect::<Vec<_>>(), vec![Severity::Major, Severity::Nit]);
+        assert_eq!(findings[0].fix_goal(), "Fix this review finding at b.rs:1: panics\nSuggested fix: return an error");
+        assert_eq!(llm.calls().len(), 2);
+    }
+}
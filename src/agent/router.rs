@@ -0,0 +1,139 @@
+//! Per-step model routing. Each step's difficulty is estimated from its type
+//! and prompt length; the cheapest configured tier that can handle it is
+//! picked, unless that would break the run's cost budget or the per-step
+//! latency limit. Every decision is kept for the task record.
+
+use crate::models::metrics::estimate_tokens;
+use serde::{Deserialize, Serialize};
+
+/// Tokens assumed for a step's reply when estimating cost and latency.
+const REPLY_ALLOWANCE: u64 = 512;
+
+/// One model in `[router]`, listed cheapest first.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Tier {
+    pub model: String,
+    /// Hardest step (0..1) this model gets.
+    #[serde(default = "one")]
+    pub max_difficulty: f64,
+    /// Cost units per 1000 tokens; any unit, as long as `budget` uses it too.
+    #[serde(default)]
+    pub cost_per_1k: f64,
+    /// Rough generation time per 1000 tokens.
+    #[serde(default)]
+    pub ms_per_1k: f64,
+}
+
+fn one() -> f64 {
+    1.0
+}
+
+/// `[router]` in config.toml. Routing is off while `tiers` is empty.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RouterConfig {
+    pub tiers: Vec<Tier>,
+    /// Cost allowed per run; past it steps go to the cheapest tier that fits.
+    pub budget: Option<f64>,
+    /// Steps estimated to take longer on a tier go to a faster one.
+    pub max_step_latency_ms: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoutingDecision {
+    pub step: String,
+    pub model: String,
+    pub difficulty: f64,
+    pub estimated_tokens: u64,
+    pub estimated_cost: f64,
+    pub reason: String,
+}
+
+/// 0..1: planning and review are harder than execution, and long prompts
+/// are harder than short ones.
+pub fn difficulty(step: &str, prompt: &str) -> f64 {
+    let base = match step {
+        "planner" | "critic" | "reviewer" | "architect" => 0.6,
+        "executor" => 0.2,
+        _ => 0.4,
+    };
+    let length = (estimate_tokens(prompt) as f64 / 2000.0).min(1.0) * 0.4;
+    (base + length).min(1.0)
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Router {
+    pub config: RouterConfig,
+    pub decisions: Vec<RoutingDecision>,
+    spent: f64,
+}
+
+impl Router {
+    /// `None` when no tiers are configured.
+    pub fn new(config: RouterConfig) -> Option<Self> {
+        (!config.tiers.is_empty()).then(|| Self { config, ..Default::default() })
+    }
+
+    /// Pick the model for `step` and record the decision.
+    pub fn route(&mut self, step: &str, prompt: &str) -> &RoutingDecision {
+        let d = difficulty(step, prompt);
+        let tokens = estimate_tokens(prompt) + REPLY_ALLOWANCE;
+        let tiers = &self.config.tiers;
+        let wanted = tiers.iter().position(|t| t.max_difficulty >= d).unwrap_or(tiers.len() - 1);
+        let cost = |t: &Tier| t.cost_per_1k * tokens as f64 / 1000.0;
+        let over_budget = |t: &Tier| self.config.budget.is_some_and(|b| self.spent + cost(t) > b);
+        let too_slow = |t: &Tier| self.config.max_step_latency_ms.is_some_and(|l| t.ms_per_1k * tokens as f64 / 1000.0 > l);
+        let mut reason = format!("difficulty {:.2} ≤ {:.2}", d, tiers[wanted].max_difficulty);
+        let mut pick = wanted;
+        // step down to cheaper tiers until the limits hold; the cheapest is the floor
+        while pick > 0 && (over_budget(&tiers[pick]) || too_slow(&tiers[pick])) {
+            reason = if over_budget(&tiers[pick]) { format!("{} over budget", tiers[pick].model) } else { format!("{} over latency limit", tiers[pick].model) };
+            pick -= 1;
+        }
+        let tier = &tiers[pick];
+        let decision = RoutingDecision {
+            step: step.to_string(),
+            model: tier.model.clone(),
+            difficulty: d,
+            estimated_tokens: tokens,
+            estimated_cost: cost(tier),
+            reason,
+        };
+        self.spent += decision.estimated_cost;
+        tracing::info!(step, model = %decision.model, difficulty = d, tokens, cost = decision.estimated_cost, reason = %decision.reason, "routed step");
+        self.decisions.push(decision);
+        self.decisions.last().expect("just pushed")
+    }
+
+    /// Forget the decisions and spending so far, for a new attempt.
+    pub fn reset(&mut self) {
+        self.decisions.clear();
+        self.spent = 0.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tier(model: &str, max_difficulty: f64, cost_per_1k: f64) -> Tier {
+        Tier { model: model.into(), max_difficulty, cost_per_1k, ms_per_1k: 0.0 }
+    }
+
+    #[test]
+    fn test_routes_by_difficulty_then_budget() {
+        assert!(Router::new(RouterConfig::default()).is_none());
+        let config = RouterConfig { tiers: vec![tier("small", 0.5, 0.0), tier("large", 1.0, 1.0)], budget: Some(1.5), max_step_latency_ms: None };
+        let mut router = Router::new(config).expect("tiers configured");
+
+        assert_eq!(router.route("executor", "write the file").model, "small");
+        let plan = router.route("planner", "design the module");
+        assert_eq!((plan.model.as_str(), plan.estimated_tokens), ("large", 515));
+        // a long executor prompt is hard too, but the budget is spent
+        let long = "word ".repeat(3000);
+        let d = router.route("executor", &long);
+        assert_eq!(d.model, "small");
+        assert_eq!(d.reason, "large over budget");
+        assert_eq!(router.decisions.len(), 3);
+    }
+}
@@ -0,0 +1,87 @@
+//! Natural-language shell commands. Typing `!find the 5 largest files under
+//! target` in the TUI asks the model for one command line for this platform's
+//! shell. The TUI shows the command for confirmation, and only then runs it
+//! with the `shell` tool, streaming its output into the terminal pane.
+
+use crate::llm::Llm;
+use crate::types::Message;
+use serde_json::{json, Value};
+use std::path::Path;
+
+/// A command the model proposed for a request.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Proposal {
+    pub intent: String,
+    pub command: String,
+    /// What the command does, in a sentence.
+    pub explanation: String,
+    /// Whether it deletes, overwrites or otherwise changes anything.
+    pub destructive: bool,
+}
+
+fn schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "command": {"type": "string"},
+            "explanation": {"type": "string"},
+            "destructive": {"type": "boolean"}
+        },
+        "required": ["command", "explanation", "destructive"]
+    })
+}
+
+/// `command` without the fences or prompt sign models like to add.
+fn clean(command: &str) -> String {
+    let command = command.trim().trim_matches('`').trim();
+    command.strip_prefix("$ ").unwrap_or(command).trim().to_string()
+}
+
+/// Ask for a command line that does `intent`, run from `cwd`.
+pub async fn propose(llm: &dyn Llm, intent: &str, cwd: &Path) -> anyhow::Result<Proposal> {
+    let shell = if cfg!(windows) { "cmd.exe" } else { "POSIX sh" };
+    let messages = vec![
+        Message::new(
+            "system",
+            format!(
+                "You turn requests into a single command line for {} on {}, run from {}. Prefer standard tools and \
+                 read-only commands; never use sudo. Explain in one sentence what the command does, and mark it \
+                 destructive if it deletes, moves, overwrites or installs anything.",
+                shell,
+                std::env::consts::OS,
+                cwd.display()
+            ),
+        ),
+        Message::new("user", intent),
+    ];
+    let reply = crate::llm::structured::complete(llm, &messages, &schema(), 2).await?;
+    let command = clean(reply["command"].as_str().unwrap_or_default());
+    if command.is_empty() {
+        anyhow::bail!("no command for {:?}", intent);
+    }
+    Ok(Proposal {
+        intent: intent.to_string(),
+        command,
+        explanation: reply["explanation"].as_str().unwrap_or_default().trim().to_string(),
+        destructive: reply["destructive"].as_bool().unwrap_or(true),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::mock::ScriptedLlm;
+
+    #[tokio::test]
+    async fn test_proposals_are_cleaned_up() {
+        let llm = ScriptedLlm::replies(&[
+            r#"{"command": "`$ du -a target | sort -rn | head -5`", "explanation": "Lists the five largest entries.", "destructive": false}"#,
+            r#"{"command": " ", "explanation": "", "destructive": false}"#,
+        ]);
+        let proposal = propose(&llm, "find the 5 largest files under target", Path::new("/work")).await.unwrap();
+        assert_eq!(proposal.command, "du -a target | sort -rn | head -5");
+        assert!(!proposal.destructive);
+        assert!(llm.calls()[0].iter().any(|m| m.content.contains("run from /work")));
+        assert!(propose(&llm, "nothing", Path::new("/work")).await.is_err());
+    }
+}
@@ -0,0 +1,95 @@
+use crate::errors::PluginError;
+use crate::tools::policy::{Access, FileAccessPolicy};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// What a skill may touch. Paths are checked against the workspace policy
+/// when the skill loads; `network` is declarative and shown to the user.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Permissions {
+    pub read: Vec<PathBuf>,
+    pub write: Vec<PathBuf>,
+    pub network: bool,
+}
+
+/// `<skill>.toml` next to a skill file, shared by WASM and native skills:
+///
+/// ```toml
+/// name = "fast-grep"
+/// version = "0.3.0"
+/// description = "Regex search over the workspace"
+/// [permissions]
+/// read = ["."]
+/// [panel]          # optional: show the skill's reply as a TUI panel
+/// title = "Matches"
+/// interval_secs = 30
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SkillManifest {
+    pub name: String,
+    #[serde(default)]
+    pub version: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub permissions: Permissions,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub panel: Option<PanelManifest>,
+}
+
+/// `[panel]`: the skill is called with no input every `interval_secs` and
+/// its reply shown in the Panels view.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PanelManifest {
+    /// Tab title; the skill name when empty.
+    #[serde(default)]
+    pub title: String,
+    #[serde(default = "default_panel_interval")]
+    pub interval_secs: u64,
+}
+
+fn default_panel_interval() -> u64 {
+    30
+}
+
+impl SkillManifest {
+    /// The manifest beside `skill`, if there is one.
+    pub fn for_skill(skill: &Path) -> anyhow::Result<Option<Self>> {
+        let path = skill.with_extension("toml");
+        if !path.exists() {
+            return Ok(None);
+        }
+        let text = std::fs::read_to_string(&path)?;
+        toml::from_str(&text).map(Some).map_err(|e| PluginError::BadManifest { path: path.display().to_string(), reason: e.to_string() }.into())
+    }
+
+    /// Refuse skills asking for paths the workspace policy denies.
+    pub fn check(&self, policy: &FileAccessPolicy) -> anyhow::Result<()> {
+        let requested = self.permissions.read.iter().map(|p| (p, Access::Read)).chain(self.permissions.write.iter().map(|p| (p, Access::Write)));
+        for (path, access) in requested {
+            policy.check(path, access).map_err(|e| PluginError::PermissionDenied { skill: self.name.clone(), path: path.display().to_string(), reason: e.to_string() })?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifest_permissions_checked_against_policy() -> anyhow::Result<()> {
+        let td = tempfile::tempdir()?;
+        let skill = td.path().join("grep.wasm");
+        assert!(SkillManifest::for_skill(&skill)?.is_none());
+        std::fs::write(td.path().join("grep.toml"), "name = \"grep\"\n[permissions]\nread = [\"src\"]\n")?;
+        let manifest = SkillManifest::for_skill(&skill)?.expect("manifest");
+        let policy = FileAccessPolicy::new(td.path(), &crate::config::FilesConfig::default());
+        manifest.check(&policy)?;
+
+        let greedy = SkillManifest { permissions: Permissions { write: vec!["/etc".into()], ..Default::default() }, ..manifest };
+        assert!(greedy.check(&policy).is_err());
+        Ok(())
+    }
+}
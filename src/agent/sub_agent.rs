@@ -1,9 +1,14 @@
 use crate::llm::Llm;
 use crate::memory::store::MemoryStore;
-use crate::tools::registry::ToolRegistry;
-use crate::types::{AgentInput, AgentOutput, AgentId, Message};
+use crate::tools::parallel;
+use crate::tools::registry::{ToolOutput, ToolRegistry, ToolResult};
+use crate::tools::schema::extract_json;
+use crate::types::{AgentOutput, AgentId, Message};
 use std::sync::Arc;
 
+/// Re-prompts for an invalid tool call while executing a step.
+const STEP_RETRIES: usize = 2;
+
 #[derive(Clone)]
 pub struct SubAgent {
     pub id: AgentId,
@@ -36,39 +41,131 @@ impl SubAgent {
         Ok(resp)
     }
 
-    pub async fn execute(&self, plan: &str) -> anyhow::Result<AgentOutput> {
-        // a very small deterministic executor that calls tools / microagents
-        let input = AgentInput {
-            text: plan.to_string(),
-        };
-
-        // for v0.1 try the echo tool if present
-        match self.tools.run("echo", input) {
-            Ok(to) => Ok(AgentOutput { text: to.text }),
-            Err(_) => Ok(AgentOutput {
-                text: format!("executed: {}", plan),
-            }),
+    /// Ask the model for a tool call and run it; when the model asks for
+    /// several, their outputs are joined in call order.
+    pub async fn call_tool(&self, request: &str, max_retries: usize) -> anyhow::Result<ToolOutput> {
+        let results = self.call_tools(request, max_retries).await?;
+        let texts = results.into_iter().collect::<anyhow::Result<Vec<_>>>()?;
+        let bytes_written = texts.iter().map(|o| o.bytes_written).sum();
+        Ok(ToolOutput { text: texts.into_iter().map(|o| o.text).collect::<Vec<_>>().join("\n"), bytes_written })
+    }
+
+    /// Ask the model for one or more tool calls, validate them against the
+    /// tool schemas and run them (see `tools::parallel`). A malformed reply is
+    /// answered with a structured `tool` error message and the model is
+    /// re-prompted, up to `max_retries` times. The call and one `tool`
+    /// message per result, in call order, go to short-term memory.
+    pub async fn call_tools(&self, request: &str, max_retries: usize) -> anyhow::Result<Vec<ToolResult>> {
+        let mut messages = self.memory.get_short();
+        messages.push(Message::new(
+            "system",
+            format!(
+                "Reply only with a JSON tool call: {{\"tool\": <name>, \"input\": <input>}}, or an array of them to run several at once. \
+                 In a string input, {{{{output.N}}}} stands for the output of call N (0-based) in the same array. Available tools:\n{}",
+                self.tools.describe()
+            ),
+        ));
+        messages.push(Message::new("user", request));
+        let schema = self.tools.batch_schema();
+        for attempt in 0..=max_retries {
+            // a grammar-capable backend can only emit the envelope; tool inputs are still validated below
+            let resp = self.llm.chat_constrained(&messages, &schema).await?;
+            messages.push(Message::new("assistant", &resp));
+            let errors = match extract_json(&resp) {
+                Some(v) => match self.tools.validate_batch(&v) {
+                    Ok(calls) => {
+                        let sequential = parallel::sequential(&calls);
+                        tracing::info!(agent = %self.role, calls = calls.len(), sequential = sequential.iter().filter(|s| **s).count(), "running tool calls");
+                        let names: Vec<String> = calls.iter().map(|c| c.tool.clone()).collect();
+                        let results = parallel::run(&self.tools, calls, parallel::DEFAULT_LIMIT).await;
+                        self.memory.add_short(Message::new("assistant", &resp));
+                        for (i, (name, result)) in names.iter().zip(&results).enumerate() {
+                            let content = match result {
+                                Ok(out) => serde_json::json!({"call": i, "tool": name, "output": out.text}),
+                                Err(e) => serde_json::json!({"call": i, "tool": name, "error": e.to_string()}),
+                            };
+                            self.memory.add_short(Message::new("tool", content.to_string()));
+                        }
+                        return Ok(results);
+                    }
+                    Err(errs) => errs,
+                },
+                None => vec!["$: response is not valid JSON".to_string()],
+            };
+            tracing::warn!(agent = %self.role, attempt, "invalid tool call, re-prompting: {}", errors.join("; "));
+            let result = serde_json::json!({"error": "invalid_tool_call", "details": errors});
+            messages.push(Message::new("tool", result.to_string()));
+            messages.push(Message::new("user", "The tool call above was invalid. Reply with a corrected JSON tool call."));
         }
+        Err(anyhow::anyhow!("no valid tool call after {} attempts", max_retries + 1))
+    }
+
+    /// Carry out `plan` (a step, or a whole plan) with this agent's tools:
+    /// the model picks the calls and [`call_tool`](Self::call_tool) runs
+    /// them. A failed call fails the step, so the run's retry policy sees it.
+    pub async fn execute(&self, plan: &str) -> anyhow::Result<AgentOutput> {
+        let request = format!(
+            "Carry out this step with the tools. When no other tool is needed, call `echo` with your answer.\n\n{}",
+            plan
+        );
+        let out = self.call_tool(&request, STEP_RETRIES).await?;
+        Ok(AgentOutput { text: out.text })
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::llm::mock::MockLlm;
+    use crate::llm::mock::{MockLlm, ScriptedLlm};
     use std::sync::Arc;
 
     #[tokio::test]
     async fn test_plan_and_execute_with_mock() {
-        let mock = Arc::new(MockLlm::new("this is a plan"));
-        let agent = SubAgent::new("planner", mock);
+        let llm = Arc::new(ScriptedLlm::replies(&["this is a plan", r#"{"tool": "echo", "input": "done"}"#]));
+        let agent = SubAgent::new("planner", llm.clone());
 
         let plan = agent.plan("do something").await.expect("plan failed");
         assert_eq!(plan, "this is a plan");
 
-        // register a local echo tool and execute
+        // the model picks the tool the step runs
         agent.tools.register(Arc::new(crate::tools::registry::EchoTool));
         let out = agent.execute(&plan).await.expect("exec failed");
-        assert_eq!(out.text, "this is a plan");
+        assert_eq!(out.text, "done");
+        assert!(llm.calls()[1].last().is_some_and(|m| m.content.ends_with("this is a plan")));
+    }
+
+    #[tokio::test]
+    async fn test_call_tool_repairs_invalid_call() {
+        let llm = Arc::new(ScriptedLlm::replies(&[
+            "not json",
+            r#"{"tool": "ech0", "input": "x"}"#,
+            r#"{"tool": "echo", "input": "fixed"}"#,
+        ]));
+        let agent = SubAgent::new("executor", llm.clone());
+        agent.tools.register(Arc::new(crate::tools::registry::EchoTool));
+
+        let out = agent.call_tool("say fixed", 2).await.expect("tool call failed");
+        assert_eq!(out.text, "fixed");
+        let seen = llm.calls();
+        let last_error = seen[2][seen[2].len() - 2].clone();
+        assert_eq!(last_error.role, "tool");
+        assert!(last_error.content.contains("$.tool: must be one of"));
+
+        let agent = SubAgent::new("executor", Arc::new(MockLlm::new("nope")));
+        assert!(agent.call_tool("anything", 1).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_call_tools_runs_a_batch_in_order() -> anyhow::Result<()> {
+        let llm = Arc::new(ScriptedLlm::replies(&[r#"[{"tool": "echo", "input": "one"}, {"tool": "echo", "input": "{{output.0}} two"}]"#]));
+        let agent = SubAgent::new("executor", llm);
+        agent.tools.register(Arc::new(crate::tools::registry::EchoTool));
+
+        let results = agent.call_tools("echo twice", 0).await?;
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[1].as_ref().map(|o| o.text.as_str()).ok(), Some("one two"));
+        let transcript: Vec<String> = agent.memory.get_short().into_iter().filter(|m| m.role == "tool").map(|m| m.content).collect();
+        assert_eq!(transcript, [r#"{"call":0,"output":"one","tool":"echo"}"#, r#"{"call":1,"output":"one two","tool":"echo"}"#]);
+        Ok(())
     }
 }
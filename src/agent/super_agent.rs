@@ -1,62 +1,630 @@
 use crate::agent::sub_agent::SubAgent;
 use crate::graph::dag::AgentGraph;
+use crate::agent::clarify;
+use crate::agent::human::{HumanChannel, PlanDecision, PlanRejected};
+use crate::agent::router::Router;
+use crate::graph::pipeline::{EdgeCondition, NodeKind, Pipeline};
 use crate::llm::llama::LlamaClient;
 use crate::llm::Llm;
-use crate::tools::registry::EchoTool;
-use crate::types::AgentState;
+use crate::agent::events::{self, AgentEvent};
+use crate::memory::events::{EventBus, SessionEvent};
+use crate::memory::shared::SharedVars;
+use crate::tasks::artifacts::ArtifactRecorder;
+use crate::tasks::checkpoint::{Checkpoint, CheckpointStore};
+use crate::tasks::retry::{self, RetryPolicy};
+use crate::tasks::timing::{self, TimingStore};
+use crate::tasks::{Task, TaskStatus, TaskStore};
+use crate::tools::build::RunTestsTool;
+use crate::tools::dry_run::{self, Proposals};
+use crate::tools::registry::{EchoTool, Tool, ToolRegistry};
+use crate::tools::fs::{ReadFileTool, WriteFileTool};
+use crate::tools::git::GitTool;
+use crate::tools::patch::PatchTool;
+use crate::tools::policy::FileAccessPolicy;
+use crate::tools::shared_vars::{GetSharedVarTool, SetSharedVarTool};
+use crate::tools::shell::ShellTool;
+use crate::types::{AgentOutput, AgentState, Message};
+use crate::workspace::{ProjectKind, ProjectProfile};
 use std::sync::Arc;
 
 pub struct SuperAgent {
     pub graph: AgentGraph,
     pub scheduler: AgentState,
     pub llm: Arc<dyn Llm>,
+    pub shared_vars: SharedVars,
+    pub events: EventBus,
+    pub profile: ProjectProfile,
+    pub file_policy: FileAccessPolicy,
+    /// When set, the run ends with a JSON result conforming to this schema.
+    pub output_schema: Option<serde_json::Value>,
+    /// Where runs are recorded as tasks; `None` runs untracked.
+    pub tasks: Option<TaskStore>,
+    pub recorder: ArtifactRecorder,
+    /// Persona prompt every sub-agent starts with (a task's agent profile).
+    pub system_prompt: Option<String>,
+    /// Used for tasks that don't carry their own policy.
+    pub retry: RetryPolicy,
+    /// Custom agent graph; `None` runs plan → execute → review.
+    pub pipeline: Option<Pipeline>,
+    /// Where `human_input` nodes ask their questions.
+    pub human: HumanChannel,
+    /// Node outputs so far; set from disk to resume a run, otherwise started
+    /// by `run_task` for tracked runs.
+    pub checkpoint: Option<Checkpoint>,
+    /// Picks a model per step; `None` runs every step on `llm`.
+    pub router: Option<Router>,
+    /// Tokens of the profile's notes each sub-agent starts with.
+    pub notes_budget: u64,
+    /// Set for a dry run: mutating tools only record what they would do.
+    pub dry_run: Option<Proposals>,
+    /// Ask `human` to approve the plan before executing it.
+    pub approve_plan: bool,
+    /// Clarifying questions the planner may ask `human` before planning.
+    pub clarify_questions: usize,
+    /// Pair mode: narrate steps and tool calls on `events` as commentary.
+    pub commentary: bool,
+    /// Session the run's events are published under; `run_task` sets it to
+    /// the task's short id (as the Tasks view shows it) when empty.
+    pub session: String,
+    endpoint: String,
 }
 
 impl SuperAgent {
     pub fn new() -> Self {
         let endpoint = std::env::var("LLAMA_ENDPOINT").unwrap_or_else(|_| "http://127.0.0.1:8080".into());
         let model = std::env::var("LLAMA_MODEL").unwrap_or_else(|_| "local.gguf".into());
-        let llm: Arc<dyn Llm> = Arc::new(LlamaClient::new(endpoint, model));
+        let llm: Arc<dyn Llm> = Arc::new(LlamaClient::new(endpoint.clone(), model));
         Self {
             graph: AgentGraph::new(),
             scheduler: AgentState::Idle,
             llm,
+            shared_vars: SharedVars::new(),
+            events: EventBus::shared(),
+            profile: ProjectProfile::for_kind(ProjectKind::Unknown),
+            file_policy: FileAccessPolicy::new(
+                std::env::current_dir().unwrap_or_else(|_| ".".into()),
+                &crate::config::FilesConfig::default(),
+            ),
+            output_schema: None,
+            tasks: None,
+            recorder: ArtifactRecorder::default(),
+            system_prompt: None,
+            retry: RetryPolicy::default(),
+            pipeline: None,
+            human: HumanChannel::default(),
+            checkpoint: None,
+            router: None,
+            notes_budget: crate::memory::summary::MemoryConfig::default().notes_budget,
+            dry_run: None,
+            approve_plan: false,
+            clarify_questions: 0,
+            commentary: false,
+            session: String::new(),
+            endpoint,
         }
     }
 
-    pub async fn run_goal(&mut self, goal: String) -> anyhow::Result<()> {
+    /// Where the run's model is served.
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    /// Use `model` on `endpoint` instead of the `LLAMA_*` environment.
+    pub fn connect(&mut self, endpoint: &str, model: &str) {
+        self.endpoint = endpoint.to_string();
+        self.llm = Arc::new(LlamaClient::new(endpoint, model));
+    }
+
+    fn publish(&self, agent: &str, action: &str) {
+        events::publish(AgentEvent::Action { agent: agent.into(), action: action.into() });
+        self.events.publish(SessionEvent::AgentAction {
+            session: self.session.clone(),
+            agent: agent.into(),
+            action: action.into(),
+        });
+    }
+
+    /// Tell a pair-mode watcher what `agent` is doing.
+    fn narrate(&self, agent: &str, text: &str) {
+        if self.commentary {
+            self.events.publish(SessionEvent::Commentary { session: self.session.clone(), agent: agent.into(), text: text.into() });
+        }
+    }
+
+    /// The tools every sub-agent of this run can use; `shell`, `git` and
+    /// `run_tests` need a workspace root, and `shell` and `git` an opt-in
+    /// (see `audit::shell_enabled`).
+    fn tools(&self) -> Vec<Arc<dyn Tool>> {
+        let policy = self.file_policy.clone().with_writable(&self.profile.policy.writable);
+        let mut tools: Vec<Arc<dyn Tool>> = vec![
+            Arc::new(EchoTool),
+            Arc::new(GetSharedVarTool { vars: self.shared_vars.clone() }),
+            Arc::new(SetSharedVarTool { vars: self.shared_vars.clone() }),
+            Arc::new(ReadFileTool { policy: policy.clone() }),
+            Arc::new(WriteFileTool { policy: policy.clone(), recorder: Some(self.recorder.clone()) }),
+            Arc::new(PatchTool { policy: policy.clone(), recorder: Some(self.recorder.clone()) }),
+        ];
+        if let (Some(command), Some(root)) = (&self.profile.test_command, self.file_policy.root()) {
+            tools.push(Arc::new(RunTestsTool { root: root.to_path_buf(), command: command.clone() }));
+        }
+        if let Some(root) = self.file_policy.root().filter(|_| crate::tools::audit::shell_enabled()) {
+            tools.push(Arc::new(ShellTool { root: root.to_path_buf(), policy: policy.clone(), sink: None }));
+            tools.push(Arc::new(GitTool { root: root.to_path_buf(), policy: policy.clone() }));
+        }
+        tools
+    }
+
+    /// Register this run's tools on `reg`; in a dry run the mutating ones are
+    /// recorders.
+    fn register_tools(&self, reg: &ToolRegistry) {
+        for tool in self.tools() {
+            reg.register(match &self.dry_run {
+                Some(proposals) => dry_run::wrap(tool, proposals),
+                None => tool,
+            });
+        }
+        reg.set_recorder(self.recorder.clone());
+    }
+
+    /// Carry out the actions a dry run proposed with this run's real tools,
+    /// recording them like any other tool call.
+    pub fn apply_proposals(&self, proposals: &Proposals) -> anyhow::Result<String> {
+        let reg = ToolRegistry::new();
+        for tool in self.tools() {
+            reg.register(tool);
+        }
+        reg.set_recorder(self.recorder.clone());
+        proposals.apply(&reg)
+    }
+
+    /// Tools available to this run's sub-agents.
+    pub fn tool_names(&self) -> Vec<String> {
+        let reg = ToolRegistry::new();
+        self.register_tools(&reg);
+        reg.names()
+    }
+
+    /// Tell watchers `task` (the run's or a step's) is now in its status.
+    fn announce(&self, task: &Task) {
+        self.events.publish(SessionEvent::TaskUpdate {
+            session: self.session.clone(),
+            task_id: task.id[..8].to_string(),
+            status: format!("{:?}", task.status),
+        });
+    }
+
+    fn save_task(&self, task: &Task) {
+        if let Some(store) = &self.tasks {
+            if let Err(e) = store.save(task) {
+                tracing::warn!("could not save task {}: {}", task.id, e);
+            }
+        }
+    }
+
+    /// Sub-agent for `role` with this run's tools and persona.
+    fn sub_agent(&self, role: &str) -> SubAgent {
+        let agent = SubAgent::new(role, Arc::clone(&self.llm));
+        self.register_tools(&agent.tools);
+        if self.commentary {
+            agent.tools.set_commentary(self.events.clone(), &self.session, role);
+        }
+        if let Some(prompt) = &self.system_prompt {
+            agent.memory.add_short(Message::new("system", prompt));
+        }
+        if let Some(notes) = crate::memory::notes::context(&self.profile.notes, self.notes_budget) {
+            agent.memory.add_short(notes);
+        }
+        agent
+    }
+
+    /// Sub-agent for `role` on `model`, through this run's LLM.
+    fn agent_on(&self, role: &str, model: &str) -> SubAgent {
+        let mut agent = self.sub_agent(role);
+        if let Some(llm) = self.llm.for_model(model) {
+            agent.llm = llm;
+        }
+        agent
+    }
+
+    /// Sub-agent for `role` on the model the router picks for `prompt`.
+    fn routed_agent(&mut self, role: &str, prompt: &str) -> SubAgent {
+        let Some(router) = self.router.as_mut() else {
+            return self.sub_agent(role);
+        };
+        let decision = router.route(role, prompt);
+        let (model, note) = (decision.model.clone(), format!("routed to {} ({})", decision.model, decision.reason));
+        self.publish(role, &note);
+        self.agent_on(role, &model)
+    }
+
+    pub async fn run_goal(&mut self, goal: String) -> anyhow::Result<String> {
+        self.run_task(Self::goal_task(goal)).await
+    }
+
+    /// The task `run_goal` runs: titled by the goal's first line.
+    pub fn goal_task(goal: String) -> Task {
+        let title: String = goal.lines().next().unwrap_or_default().chars().take(60).collect();
+        Task::new(title, goal)
+    }
+
+    /// Run `task`: record it, run the pipeline on its goal (retrying per its
+    /// policy, with each failure diagnosed and recorded), then gather its
+    /// artifacts into `<home>/artifacts/<task-id>`. Each finished node is
+    /// checkpointed; a run resumed from `self.checkpoint` skips those nodes.
+    /// Returns the report.
+    pub async fn run_task(&mut self, mut task: Task) -> anyhow::Result<String> {
+        let policy = task.retry.clone().unwrap_or_else(|| self.retry.clone());
+        task.status = TaskStatus::Running;
+        task.started_at = Some(crate::tasks::now_secs());
+        if self.session.is_empty() {
+            self.session = task.id[..8].to_string();
+        }
+        // files the tools write resolve against the policy root, not the cwd
+        if let Some(root) = self.file_policy.root() {
+            self.recorder.set_root(root);
+        }
+        self.recorder.set_events(self.events.clone(), &self.session);
+        events::publish(AgentEvent::RunStarted { task: task.id.clone(), title: task.title.clone() });
+        if let Some(store) = &self.tasks {
+            task.estimate_secs = timing::estimate(&TimingStore::new(store.home()).load(), &task.title);
+        }
+        self.save_task(&task);
+        self.announce(&task);
+        match &self.checkpoint {
+            Some(cp) if cp.task.id == task.id => {
+                for (k, v) in &cp.shared_vars {
+                    self.shared_vars.set(k, v);
+                }
+                tracing::info!("resuming {} after {} completed node(s)", task.id, cp.completed.len());
+            }
+            _ => self.checkpoint = self.tasks.is_some().then(|| Checkpoint::new(task.clone(), self.pipeline.clone())),
+        }
+        let resuming = self.checkpoint.as_ref().is_some_and(|cp| !cp.completed.is_empty());
+        if self.clarify_questions > 0 && task.clarifications.is_empty() && !resuming {
+            match clarify::clarify(self.llm.as_ref(), &self.human, &task.goal(), self.clarify_questions).await {
+                Ok(answers) if !answers.is_empty() => {
+                    self.publish("planner", &format!("{} question(s) answered", answers.len()));
+                    task.clarifications = answers;
+                    self.save_task(&task);
+                    if let Some(cp) = self.checkpoint.as_mut() {
+                        cp.task = task.clone();
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => tracing::warn!("could not ask clarifying questions: {}", e),
+            }
+        }
+        let goal = task.goal();
+
+        let base_llm = Arc::clone(&self.llm);
+        let mut result;
+        loop {
+            task.attempts += 1;
+            if task.attempts > 1 {
+                if let Some(llm) = policy.escalate_model.as_deref().and_then(|m| base_llm.for_model(m)) {
+                    self.llm = llm;
+                }
+                self.publish("super", &format!("retrying (attempt {}/{})", task.attempts, policy.max_attempts));
+                if let Some(cp) = self.checkpoint.as_mut() {
+                    cp.completed.clear();
+                }
+                tokio::time::sleep(policy.delay(task.attempts)).await;
+            }
+            // each attempt is routed, and budgeted, afresh
+            if let Some(router) = self.router.as_mut() {
+                router.reset();
+            }
+            result = self.run_pipeline(&task, &goal).await;
+            if let Some(router) = &self.router {
+                task.routing = router.decisions.clone();
+            }
+            let Err(e) = &result else { break };
+            if e.is::<PlanRejected>() {
+                break;
+            }
+            let diagnosis = retry::diagnose(self.llm.as_ref(), &goal, &e.to_string(), task.attempts).await;
+            tracing::warn!("task {} attempt {} failed ({}): {}", task.id, task.attempts, diagnosis.cause.label(), e);
+            task.failures.push(diagnosis);
+            // keep an external cancel rather than overwriting it with Running
+            let cancelled = self.is_cancelled(&task);
+            if cancelled {
+                task.status = TaskStatus::Cancelled;
+                self.announce(&task);
+            }
+            self.save_task(&task);
+            if cancelled || task.attempts >= policy.max_attempts {
+                break;
+            }
+        }
+        self.llm = base_llm;
+        if let (Ok(report), Some(proposals)) = (&mut result, &self.dry_run) {
+            report.push_str(&format!("\n\n{}", proposals.report()));
+        }
+        if let (Ok(report), Some(store)) = (&mut result, &self.tasks) {
+            let mut steps: Vec<Task> = store.list().into_iter().filter(|t| t.parent_id.as_deref() == Some(task.id.as_str())).collect();
+            steps.sort_by_key(|t| t.index);
+            report.push_str(&format!("\n\n{}", timing::report(&task, &steps, crate::tasks::now_secs())));
+        }
+
+        let summary = match &result {
+            Ok(report) => report.clone(),
+            Err(e) => format!("failed: {}", e),
+        };
+        let cancelled = self.is_cancelled(&task) || result.as_ref().is_err_and(|e| e.is::<PlanRejected>());
+        task.finish(match (&result, cancelled) {
+            (_, true) => TaskStatus::Cancelled,
+            (Ok(_), false) => TaskStatus::Completed,
+            (Err(_), false) => TaskStatus::Failed,
+        });
+        if let Some(store) = &self.tasks {
+            // failed runs keep their checkpoint so they can be resumed
+            if task.status != TaskStatus::Failed {
+                CheckpointStore::new(store.home()).remove(&task.id);
+            }
+            match self.recorder.collect(store.home(), &task.id, &format!("# {}\n\n{}\n", goal, summary)) {
+                Ok(dir) => tracing::info!("task {} artifacts in {}", task.id, dir.display()),
+                Err(e) => tracing::warn!("could not collect artifacts for {}: {}", task.id, e),
+            }
+        }
+        self.save_task(&task);
+        self.announce(&task);
+        self.record_timing(&task);
+        events::publish(AgentEvent::RunFinished { task: task.id.clone(), status: task.status });
+        result
+    }
+
+    /// Add a completed task's or step's duration to the timing history.
+    fn record_timing(&self, task: &Task) {
+        if let Some(store) = &self.tasks {
+            if let Err(e) = TimingStore::new(store.home()).record(task) {
+                tracing::warn!("could not record the time of {}: {}", task.id, e);
+            }
+        }
+    }
+
+    /// Output of `node` from the checkpoint being resumed; a node that
+    /// failed runs again.
+    fn resumed(&self, node: &str) -> Option<(bool, String)> {
+        let r = self.checkpoint.as_ref()?.result(node).filter(|r| r.ok)?;
+        self.publish(node, "resumed from checkpoint");
+        Some((r.ok, r.output.clone()))
+    }
+
+    /// Record `node`'s output and rewrite the checkpoint file.
+    fn checkpoint_node(&mut self, node: &str, ok: bool, output: &str) {
+        let (Some(cp), Some(store)) = (self.checkpoint.as_mut(), self.tasks.as_ref()) else { return };
+        cp.record(node, ok, output);
+        cp.shared_vars = self.shared_vars.list().into_iter().collect();
+        if let Err(e) = CheckpointStore::new(store.home()).save(cp) {
+            tracing::warn!("could not checkpoint {}: {}", cp.task.id, e);
+        }
+    }
+
+    /// Whether `task` was cancelled from outside this run.
+    fn is_cancelled(&self, task: &Task) -> bool {
+        self.tasks.as_ref().and_then(|s| s.find(&task.id).ok()).map(|t| t.status == TaskStatus::Cancelled).unwrap_or(false)
+    }
+
+    /// Execute a multi-step plan as subtasks of `task`, so progress rolls up
+    /// to the parent and cancelling it skips the remaining steps. Untracked
+    /// runs and single-step plans execute the plan as a whole.
+    async fn execute_plan(&mut self, executor: &SubAgent, task: &Task, plan: &str) -> anyhow::Result<AgentOutput> {
+        let steps = crate::tasks::steps_from_plan(plan);
+        let Some(store) = self.tasks.clone().filter(|_| steps.len() > 1) else {
+            return executor.execute(plan).await;
+        };
+        // a resumed run or a retry with the same plan finds the subtasks it
+        // created last time; a new plan replaces them
+        let mut existing: Vec<Task> = store.list().into_iter().filter(|t| t.parent_id.as_deref() == Some(task.id.as_str())).collect();
+        existing.sort_by_key(|t| t.index);
+        let reused = existing.iter().map(|t| &t.title).eq(steps.iter());
+        let subtasks: Vec<Task> = if reused {
+            existing
+        } else {
+            for old in &existing {
+                store.remove(&old.id)?;
+            }
+            steps.iter().enumerate().map(|(i, s)| task.subtask(i, s.clone())).collect()
+        };
+        for sub in &subtasks {
+            store.save(sub)?;
+        }
+        // the executor carries on with what the finished steps left in its memory
+        if let Some(memory) = self.checkpoint.as_ref().and_then(|cp| cp.short_term.get(&executor.role)).filter(|_| reused) {
+            executor.memory.set_short(memory.clone());
+        }
+        let mut outputs = vec![];
+        let total = subtasks.len();
+        for mut sub in subtasks {
+            let step = format!("step:{}", sub.id);
+            if sub.status == TaskStatus::Completed {
+                if let Some((_, out)) = self.resumed(&step) {
+                    outputs.push(out);
+                    continue;
+                }
+            }
+            if store.find(&sub.id).map(|t| t.status == TaskStatus::Cancelled).unwrap_or(false) {
+                continue;
+            }
+            if store.find(&task.id).map(|t| t.status == TaskStatus::Cancelled).unwrap_or(false) {
+                return Err(anyhow::anyhow!("task {} cancelled", task.id));
+            }
+            self.narrate("executor", &format!("step {}/{}: {}", sub.index + 1, total, sub.title));
+            sub.status = TaskStatus::Running;
+            sub.started_at = Some(crate::tasks::now_secs());
+            sub.estimate_secs = timing::estimate(&TimingStore::new(store.home()).load(), &sub.title);
+            store.save(&sub)?;
+            self.announce(&sub);
+            let res = executor.execute(&sub.title).await;
+            sub.finish(if res.is_ok() { TaskStatus::Completed } else { TaskStatus::Failed });
+            store.save(&sub)?;
+            self.announce(&sub);
+            self.record_timing(&sub);
+            self.publish("executor", &format!("step {} {:?}", sub.index + 1, sub.status));
+            let text = res?.text;
+            if let Some(cp) = self.checkpoint.as_mut() {
+                cp.short_term.insert(executor.role.clone(), executor.memory.get_short());
+            }
+            self.checkpoint_node(&step, true, &text);
+            outputs.push(text);
+        }
+        Ok(AgentOutput { text: outputs.join("\n") })
+    }
+
+    /// Run the nodes of `pipeline` in order. A node sees the goal plus the
+    /// outputs of predecessors whose edge condition holds; nodes with
+    /// predecessors but no satisfied edge are skipped.
+    async fn run_graph(&mut self, pipeline: &Pipeline, task: &Task, goal: &str) -> anyhow::Result<String> {
+        let order = pipeline.order()?;
+        let mut results: std::collections::HashMap<String, (bool, String)> = Default::default();
+        let mut report = vec![];
+        for name in order {
+            let node = pipeline.node(&name).expect("ordered node exists");
+            let incoming: Vec<_> = pipeline.incoming(&name).collect();
+            let inputs: Vec<&str> = incoming
+                .iter()
+                .filter_map(|e| results.get(&e.from).filter(|(ok, out)| e.condition.matches(*ok, out)).map(|(_, out)| out.as_str()))
+                .collect();
+            if !incoming.is_empty() && inputs.is_empty() {
+                self.publish(&name, "skipped");
+                continue;
+            }
+            if let Some((ok, out)) = self.resumed(&name) {
+                report.push(format!("{}:\n{}", name, out));
+                results.insert(name, (ok, out));
+                continue;
+            }
+            let mut input = node.prompt.clone().map(|p| format!("{}\n\n", p)).unwrap_or_default();
+            input.push_str(goal);
+            for upstream in &inputs {
+                input.push_str("\n\n");
+                input.push_str(upstream);
+            }
+            self.narrate(&name, &format!("working as {}", node.role));
+            let res = if node.kind == NodeKind::HumanInput {
+                self.publish(&name, "waiting for input");
+                let question = node.prompt.as_deref().unwrap_or_default();
+                let timeout = node.timeout_secs.map(std::time::Duration::from_secs);
+                let answer = self.human.ask(&name, question, node.default.as_deref(), timeout).await;
+                answer.map(|a| format!("{}\nAnswer: {}", question, a))
+            } else {
+                // a model pinned on the node wins over the router
+                let agent = match &node.model {
+                    Some(model) => self.agent_on(&node.role, model),
+                    None => self.routed_agent(&node.role, &input),
+                };
+                if !node.tools.is_empty() {
+                    // steps finish by calling echo, so the node keeps it
+                    let mut keep = node.tools.clone();
+                    keep.push("echo".into());
+                    agent.tools.retain(&keep);
+                }
+                match node.role.as_str() {
+                    "executor" => self.execute_plan(&agent, task, &input).await.map(|o| o.text),
+                    _ => agent.plan(&input).await,
+                }
+            };
+            let id = self.graph.nodes.len();
+            self.graph.add_node(&name, AgentState::Executing);
+            for e in &incoming {
+                if let Some(from) = self.graph.nodes.iter().rposition(|n| n.name == e.from) {
+                    self.graph.add_edge(from, id);
+                }
+            }
+            self.publish(&name, if res.is_ok() { "done" } else { "failed" });
+            let (ok, out) = match res {
+                Ok(out) => (true, out),
+                Err(e) => (false, format!("error: {}", e)),
+            };
+            self.checkpoint_node(&name, ok, &out);
+            report.push(format!("{}:\n{}", name, out));
+            results.insert(name, (ok, out));
+        }
+        // a failure is handled when the pipeline routes it somewhere
+        let mut failed: Vec<&str> = results
+            .iter()
+            .filter(|(name, (ok, _))| !ok && !pipeline.edges.iter().any(|e| &e.from == *name && e.condition == EdgeCondition::OnFailure))
+            .map(|(name, _)| name.as_str())
+            .collect();
+        if !failed.is_empty() {
+            failed.sort();
+            self.scheduler = AgentState::Failed;
+            anyhow::bail!("{} failed\n\n{}", failed.join(", "), report.join("\n\n"));
+        }
+        self.scheduler = AgentState::Completed;
+        Ok(report.join("\n\n"))
+    }
+
+    /// Plan, execute and review `goal`; returns the report.
+    async fn run_pipeline(&mut self, task: &Task, goal: &str) -> anyhow::Result<String> {
+        if let Some(pipeline) = self.pipeline.clone() {
+            return self.run_graph(&pipeline, task, goal).await;
+        }
         self.scheduler = AgentState::Planning;
 
         // create planner subagent
-        let planner = SubAgent::new("planner", Arc::clone(&self.llm));
-        // register a basic echo tool so execution can be demonstrated
-        let reg = &planner.tools;
-        reg.register(Arc::new(EchoTool));
-
-        let plan = planner.plan(&goal).await?;
+        let plan = match self.resumed("planner") {
+            Some((_, plan)) => plan,
+            None => {
+                self.narrate("planner", &format!("planning: {}", goal.lines().next().unwrap_or_default()));
+                let planner = self.routed_agent("planner", goal);
+                let mut plan = planner.plan(goal).await?;
+                if self.approve_plan {
+                    match self.human.review_plan(&plan).await? {
+                        PlanDecision::Approve => {}
+                        PlanDecision::Edit(edited) => plan = edited,
+                        PlanDecision::Abort => return Err(PlanRejected.into()),
+                    }
+                    self.publish("planner", "plan approved");
+                }
+                self.checkpoint_node("planner", true, &plan);
+                plan
+            }
+        };
+        let planner = self.graph.nodes.len();
         self.graph.add_node("planner", AgentState::Planning);
+        self.publish("planner", "planned");
         self.scheduler = AgentState::Executing;
 
-        let executor = SubAgent::new("executor", Arc::clone(&self.llm));
-        executor.tools.register(Arc::new(EchoTool));
-        let out = executor.execute(&plan).await?;
+        let out = match self.resumed("executor") {
+            Some((_, text)) => AgentOutput { text },
+            None => {
+                self.narrate("executor", "carrying out the plan");
+                let executor = self.routed_agent("executor", &plan);
+                let out = self.execute_plan(&executor, task, &plan).await?;
+                self.checkpoint_node("executor", true, &out.text);
+                out
+            }
+        };
         self.graph.add_node("executor", AgentState::Executing);
-        self.graph.add_edge(0, 1);
+        self.publish("executor", "executed");
+        self.graph.add_edge(planner, planner + 1);
 
         self.scheduler = AgentState::Reviewing;
 
         // simple critic via llm
-        let critic = SubAgent::new("critic", Arc::clone(&self.llm));
+        self.narrate("critic", "reviewing the result");
+        let critic = self.routed_agent("critic", &out.text);
         let critique = match critic.plan(&out.text).await {
             Ok(text) => text,
             Err(e) => format!("critic error: {}", e),
         };
         self.graph.add_node("critic", AgentState::Reviewing);
-        self.graph.add_edge(1, 2);
+        self.publish("critic", "reviewed");
+        self.graph.add_edge(planner + 1, planner + 2);
+
+        if let Some(schema) = &self.output_schema {
+            let messages = vec![Message::new(
+                "user",
+                format!("Goal: {}\n\nPlan:\n{}\n\nExecution:\n{}\n\nReview:\n{}\n\nProduce the final result.", goal, plan, out.text, critique),
+            )];
+            let result = crate::llm::structured::complete(self.llm.as_ref(), &messages, schema, 2).await;
+            self.scheduler = if result.is_ok() { AgentState::Completed } else { AgentState::Failed };
+            return Ok(serde_json::to_string_pretty(&result?)?);
+        }
 
         self.scheduler = AgentState::Completed;
 
-        println!("Plan:\n{}\n\nExecution:\n{}\n\nReview:\n{}", plan, out.text, critique);
-        Ok(())
+        Ok(format!("Plan:\n{}\n\nExecution:\n{}\n\nReview:\n{}", plan, out.text, critique))
     }
 }
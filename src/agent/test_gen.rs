@@ -0,0 +1,249 @@
+//! Test generation. `agent test-gen <file|module>` finds the public
+//! functions of a source file, asks the model for unit tests, writes them
+//! where the project keeps tests and runs `test_command`; failing output goes
+//! back to the model for a repair, up to `--attempts` times. Tests that never
+//! pass are removed again, so the tree is left as it was.
+//!
+//! Where tests go follows each language's convention: Rust tests are a
+//! `#[cfg(test)] mod generated_tests` appended to the file, Python tests
+//! `tests/test_<name>.py`, JavaScript and TypeScript `<name>.test.<ext>`
+//! next to the source.
+
+use crate::llm::Llm;
+use crate::symbols::{self, SymbolKind};
+use crate::tools::build::run_tests;
+use crate::types::Message;
+use std::path::{Path, PathBuf};
+
+/// Module name Rust tests are written under.
+pub const RUST_MODULE: &str = "generated_tests";
+
+/// `src/foo/bar.rs` or `foo/bar.py` itself, or the file a module path
+/// (`foo::bar`, `foo.bar`) names.
+pub fn resolve(root: &Path, target: &str) -> anyhow::Result<PathBuf> {
+    if root.join(target).is_file() {
+        return Ok(PathBuf::from(target));
+    }
+    let parts: Vec<&str> = target.split("::").flat_map(|p| p.split('.')).filter(|p| !p.is_empty() && *p != "crate").collect();
+    let rel = parts.join("/");
+    let candidates = [
+        format!("src/{}.rs", rel),
+        format!("src/{}/mod.rs", rel),
+        format!("{}.py", rel),
+        format!("{}/__init__.py", rel),
+        format!("src/{}.ts", rel),
+        format!("src/{}.js", rel),
+    ];
+    candidates
+        .into_iter()
+        .map(PathBuf::from)
+        .find(|c| root.join(c).is_file())
+        .ok_or_else(|| anyhow::anyhow!("no file or module named {:?} in {}", target, root.display()))
+}
+
+/// Signatures of the public functions in `source`, from the symbol index.
+pub fn public_functions(path: &Path, source: &str) -> Vec<String> {
+    symbols::parse(&path.to_string_lossy(), source)
+        .into_iter()
+        .filter(|s| s.kind == SymbolKind::Function)
+        .map(|s| s.signature)
+        .collect()
+}
+
+/// Where the tests for `source` go.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Destination {
+    /// A test module appended to the source file itself.
+    Inline(PathBuf),
+    File(PathBuf),
+}
+
+impl Destination {
+    pub fn for_source(source: &Path) -> anyhow::Result<Self> {
+        let stem = source.file_stem().and_then(|s| s.to_str()).unwrap_or("module");
+        match source.extension().and_then(|e| e.to_str()) {
+            Some("rs") => Ok(Destination::Inline(source.to_path_buf())),
+            Some("py") => Ok(Destination::File(PathBuf::from("tests").join(format!("test_{}.py", stem)))),
+            Some(ext @ ("js" | "ts" | "mjs")) => Ok(Destination::File(source.with_file_name(format!("{}.test.{}", stem, ext)))),
+            _ => anyhow::bail!("no test convention for {}", source.display()),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        match self {
+            Destination::Inline(p) | Destination::File(p) => p,
+        }
+    }
+
+    fn instructions(&self) -> String {
+        match self {
+            Destination::Inline(_) => format!(
+                "The tests go in a `#[cfg(test)] mod {}` at the end of the file that already has `use super::*;`: \
+                 reply with the `#[test]` functions only, not the module.",
+                RUST_MODULE
+            ),
+            Destination::File(p) => format!("The tests are the whole of `{}`, importing what they test.", p.display()),
+        }
+    }
+}
+
+/// Outcome of `generate`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestGenReport {
+    pub source: PathBuf,
+    pub functions: Vec<String>,
+    pub destination: PathBuf,
+    pub attempts: usize,
+    pub passed: bool,
+    /// Tail of the last test run.
+    pub output: String,
+}
+
+impl TestGenReport {
+    pub fn render(&self) -> String {
+        let mut out = format!("# Tests for {}\n\n{} public function(s):\n", self.source.display(), self.functions.len());
+        for f in &self.functions {
+            out.push_str(&format!("- `{}`\n", f));
+        }
+        if self.passed {
+            out.push_str(&format!("\nWrote passing tests to {} after {} attempt(s).\n", self.destination.display(), self.attempts));
+        } else {
+            out.push_str(&format!(
+                "\nThe tests still failed after {} attempt(s) and were not kept. Last run:\n\n```\n{}\n```\n",
+                self.attempts, self.output
+            ));
+        }
+        out
+    }
+}
+
+/// The first fenced code block of `reply`, or all of it.
+pub fn extract_code(reply: &str) -> String {
+    let mut parts = reply.split("```");
+    match (parts.next(), parts.next()) {
+        (Some(_), Some(block)) => block.split_once('\n').map(|(_, code)| code).unwrap_or(block).trim_end().to_string(),
+        _ => reply.trim().to_string(),
+    }
+}
+
+fn request(source: &Path, text: &str, functions: &[String], destination: &Destination) -> Vec<Message> {
+    let list: Vec<String> = functions.iter().map(|f| format!("- {}", f)).collect();
+    vec![
+        Message::new(
+            "system",
+            "You write unit tests. Test the current behaviour of the code, one focused test per case, without network \
+             or file system access. Reply with a single fenced code block and nothing else.",
+        ),
+        Message::new(
+            "user",
+            format!(
+                "Write unit tests for the public functions of `{}`. {}\n\nPublic functions:\n{}\n\nSource:\n```\n{}\n```",
+                source.display(),
+                destination.instructions(),
+                list.join("\n"),
+                text
+            ),
+        ),
+    ]
+}
+
+/// Generate tests for `source` (relative to `root`), run them with
+/// `test_command` and repair them until they pass or `attempts` runs out.
+pub async fn generate(llm: &dyn Llm, root: &Path, source: &Path, test_command: &str, attempts: usize) -> anyhow::Result<TestGenReport> {
+    let text = std::fs::read_to_string(root.join(source))?;
+    let functions = public_functions(source, &text);
+    if functions.is_empty() {
+        anyhow::bail!("{} has no public functions to test", source.display());
+    }
+    let destination = Destination::for_source(source)?;
+    let dest = root.join(destination.path());
+    if matches!(destination, Destination::Inline(_)) && text.contains(&format!("mod {}", RUST_MODULE)) {
+        anyhow::bail!("{} already has a `{}` module", source.display(), RUST_MODULE);
+    }
+    if matches!(destination, Destination::File(_)) && dest.exists() {
+        anyhow::bail!("{} already exists", destination.path().display());
+    }
+
+    let mut messages = request(source, &text, &functions, &destination);
+    let mut report = TestGenReport { source: source.to_path_buf(), functions, destination: destination.path().to_path_buf(), attempts: 0, passed: false, output: String::new() };
+    while report.attempts < attempts.max(1) {
+        report.attempts += 1;
+        let reply = llm.chat(&messages).await?;
+        let tests = extract_code(&reply);
+        match &destination {
+            Destination::Inline(_) => {
+                let indented: Vec<String> = tests.lines().map(|l| if l.is_empty() { String::new() } else { format!("    {}", l) }).collect();
+                let module = format!("\n#[cfg(test)]\nmod {} {{\n    use super::*;\n\n{}\n}}\n", RUST_MODULE, indented.join("\n"));
+                std::fs::write(&dest, format!("{}{}", text, module))?;
+            }
+            Destination::File(_) => {
+                if let Some(dir) = dest.parent() {
+                    std::fs::create_dir_all(dir)?;
+                }
+                std::fs::write(&dest, format!("{}\n", tests))?;
+            }
+        }
+        let run = run_tests(root, test_command, "")?;
+        report.passed = run.passed;
+        report.output = run.output;
+        if report.passed {
+            return Ok(report);
+        }
+        log::info!("generated tests for {} failed (attempt {})", source.display(), report.attempts);
+        messages.push(Message::new("assistant", reply));
+        messages.push(Message::new(
+            "user",
+            format!(
+                "The tests failed:\n```\n{}\n```\nFix the tests, not the code under test, and reply with the whole corrected code block.",
+                report.output
+            ),
+        ));
+    }
+    match &destination {
+        Destination::Inline(_) => std::fs::write(&dest, &text)?,
+        Destination::File(_) => std::fs::remove_file(&dest)?,
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::mock::ScriptedLlm;
+
+    #[test]
+    fn test_public_functions_and_destinations_follow_the_language() {
+        let rust = "pub fn add(a: i32, b: i32) -> i32 {\n    a + b\n}\nfn private() {}\npub(crate) async fn load(p: &Path) -> Result<()> {";
+        assert_eq!(public_functions(Path::new("src/math.rs"), rust), vec!["pub fn add(a: i32, b: i32) -> i32", "pub(crate) async fn load(p: &Path) -> Result<()>"]);
+        let python = "def area(r):\n    return r\ndef _helper():\n    pass\n";
+        assert_eq!(public_functions(Path::new("geo.py"), python), vec!["def area(r)"]);
+
+        assert_eq!(Destination::for_source(Path::new("src/math.rs")).unwrap(), Destination::Inline(PathBuf::from("src/math.rs")));
+        assert_eq!(Destination::for_source(Path::new("pkg/geo.py")).unwrap(), Destination::File(PathBuf::from("tests/test_geo.py")));
+        assert_eq!(Destination::for_source(Path::new("src/util.ts")).unwrap(), Destination::File(PathBuf::from("src/util.test.ts")));
+        assert_eq!(extract_code("Here:\n```python\ndef test_a():\n    pass\n```\n"), "def test_a():\n    pass");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_failing_tests_are_repaired_then_kept() {
+        let td = tempfile::tempdir().unwrap();
+        std::fs::create_dir(td.path().join("pkg")).unwrap();
+        std::fs::write(td.path().join("pkg/geo.py"), "def area(r):\n    return 3 * r * r\n").unwrap();
+        assert_eq!(resolve(td.path(), "pkg.geo").unwrap(), PathBuf::from("pkg/geo.py"));
+
+        // the "test runner" passes once the tests mention 3
+        let llm = ScriptedLlm::replies(&["```python\nassert area(1) == 4\n```", "```python\nassert area(1) == 3\n```"]);
+        let report = generate(&llm, td.path(), Path::new("pkg/geo.py"), "grep -q '== 3' tests/test_geo.py", 3).await.unwrap();
+        assert!(report.passed);
+        assert_eq!(report.attempts, 2);
+        assert_eq!(std::fs::read_to_string(td.path().join("tests/test_geo.py")).unwrap(), "assert area(1) == 3\n");
+        assert!(llm.calls()[1].last().unwrap().content.starts_with("The tests failed"));
+
+        let llm = ScriptedLlm::replies(&["```python\nassert False\n```"]);
+        std::fs::remove_file(td.path().join("tests/test_geo.py")).unwrap();
+        let report = generate(&llm, td.path(), Path::new("pkg/geo.py"), "false", 1).await.unwrap();
+        assert!(!report.passed);
+        assert!(!td.path().join("tests/test_geo.py").exists());
+    }
+}
@@ -0,0 +1,287 @@
+//! Error-log triage. `agent triage <logfile|->` takes a stack trace or log,
+//! finds the frames that point into the project (`src/db.rs:42:9`,
+//! `File "app/models.py", line 12`, `at load (src/api.ts:7:3)`) and matches
+//! them against the file and symbol index. The log is split into chunks of at
+//! most `CHUNK_BYTES`; each chunk that mentions an error or a project frame
+//! goes to the model with the source around its frames, and comes back as
+//! likely root causes with suggested fixes. `--open` starts the TUI with the
+//! implicated files open in viewer tabs.
+
+use crate::llm::Llm;
+use crate::symbols::Symbol;
+use crate::types::Message;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// Largest chunk of log sent in one request.
+pub const CHUNK_BYTES: usize = 12_000;
+
+/// Lines of source shown on each side of a frame.
+const CONTEXT_LINES: usize = 6;
+
+/// A frame of the log that points into the project.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Frame {
+    /// Path relative to the project root.
+    pub file: String,
+    pub line: usize,
+    /// The public item the line falls in, from the symbol index.
+    pub symbol: Option<String>,
+}
+
+impl Frame {
+    pub fn location(&self) -> String {
+        format!("{}:{}", self.file, self.line)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Cause {
+    pub summary: String,
+    #[serde(default)]
+    pub file: Option<String>,
+    #[serde(default)]
+    pub line: Option<usize>,
+    pub explanation: String,
+    pub fix: String,
+}
+
+fn schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "causes": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "summary": {"type": "string"},
+                        "file": {"type": ["string", "null"]},
+                        "line": {"type": ["integer", "null"]},
+                        "explanation": {"type": "string"},
+                        "fix": {"type": "string"}
+                    },
+                    "required": ["summary", "explanation", "fix"]
+                }
+            }
+        },
+        "required": ["causes"]
+    })
+}
+
+/// `(path, line)` of every source location in `text`, in order.
+pub fn locations(text: &str) -> Vec<(String, usize)> {
+    static PATTERNS: OnceLock<[Regex; 2]> = OnceLock::new();
+    let patterns = PATTERNS.get_or_init(|| {
+        [
+            Regex::new(r#"File "(?P<path>[^"]+)", line (?P<line>\d+)"#).expect("valid frame pattern"),
+            Regex::new(r"(?P<path>[\w.@~/\\-]+\.(?:rs|py|js|mjs|ts|tsx|go|java|kt|rb|c|cc|cpp|h)):(?P<line>\d+)").expect("valid frame pattern"),
+        ]
+    });
+    let mut out = vec![];
+    for line in text.lines() {
+        for re in patterns.iter() {
+            for caps in re.captures_iter(line) {
+                if let Ok(n) = caps["line"].parse() {
+                    out.push((caps["path"].to_string(), n));
+                }
+            }
+        }
+    }
+    out
+}
+
+/// The indexed file `path` (as a log prints it) refers to: the path itself,
+/// or for absolute paths and other checkouts the longest indexed path it
+/// ends with. Library and toolchain frames resolve to nothing.
+fn resolve(root: &Path, path: &str, files: &[String]) -> Option<String> {
+    let path = path.replace('\\', "/");
+    let prefix = format!("{}/", root.display());
+    let rel = path.strip_prefix(&prefix).unwrap_or(&path).trim_start_matches("./");
+    if files.iter().any(|f| f == rel) {
+        return Some(rel.to_string());
+    }
+    files.iter().filter(|f| f.contains('/') && rel.ends_with(&format!("/{}", f))).max_by_key(|f| f.len()).cloned()
+}
+
+/// Project frames in `log`, first occurrence of each location only.
+pub fn frames(root: &Path, log: &str, files: &[String], symbols: &[Symbol]) -> Vec<Frame> {
+    let mut out: Vec<Frame> = vec![];
+    for (path, line) in locations(log) {
+        let Some(file) = resolve(root, &path, files) else { continue };
+        if out.iter().any(|f| f.file == file && f.line == line) {
+            continue;
+        }
+        let symbol = symbols.iter().filter(|s| s.file == file && s.line <= line).max_by_key(|s| s.line).map(|s| s.name.clone());
+        out.push(Frame { file, line, symbol });
+    }
+    out
+}
+
+/// Split `log` at line boundaries into chunks of at most `max_bytes`.
+pub fn chunk(log: &str, max_bytes: usize) -> Vec<String> {
+    let mut chunks: Vec<String> = vec![];
+    for line in log.split_inclusive('\n') {
+        match chunks.last_mut() {
+            Some(chunk) if chunk.len() + line.len() <= max_bytes => chunk.push_str(line),
+            _ => chunks.push(line.to_string()),
+        }
+    }
+    chunks
+}
+
+fn mentions_error(chunk: &str) -> bool {
+    let lower = chunk.to_lowercase();
+    ["error", "panic", "exception", "traceback", "fatal", "failed"].iter().any(|w| lower.contains(w))
+}
+
+fn source_around(root: &Path, frame: &Frame) -> Option<String> {
+    let text = std::fs::read_to_string(root.join(&frame.file)).ok()?;
+    let lines: Vec<&str> = text.lines().collect();
+    let start = frame.line.saturating_sub(CONTEXT_LINES + 1);
+    let end = (frame.line + CONTEXT_LINES).min(lines.len());
+    let numbered: Vec<String> = (start..end).map(|i| format!("{:>5} {}", i + 1, lines[i])).collect();
+    Some(format!("{} (in {}):\n{}", frame.location(), frame.symbol.as_deref().unwrap_or("?"), numbered.join("\n")))
+}
+
+fn request(chunk: &str, context: &[String]) -> Vec<Message> {
+    let mut user = format!("Log:\n```\n{}\n```", chunk);
+    if !context.is_empty() {
+        user.push_str(&format!("\n\nProject source at the frames in this log:\n{}", context.join("\n\n")));
+    }
+    vec![
+        Message::new(
+            "system",
+            "You triage errors. From the log and the project source given, work out the most likely root causes, most \
+             likely first. For each give a one-line summary, the project file and line at fault when you can tell, \
+             why it fails, and a concrete fix. Reply with no causes when the log shows no error.",
+        ),
+        Message::new("user", user),
+    ]
+}
+
+/// Outcome of `triage`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TriageReport {
+    pub frames: Vec<Frame>,
+    pub causes: Vec<Cause>,
+}
+
+impl TriageReport {
+    /// Files the causes and frames point at, each once with its first line.
+    pub fn implicated(&self) -> Vec<(String, Option<usize>)> {
+        let named = self.causes.iter().filter_map(|c| c.file.clone().map(|f| (f, c.line)));
+        let mut out: Vec<(String, Option<usize>)> = vec![];
+        for (file, line) in named.chain(self.frames.iter().map(|f| (f.file.clone(), Some(f.line)))) {
+            if !out.iter().any(|(f, _)| *f == file) {
+                out.push((file, line));
+            }
+        }
+        out
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = String::from("# Triage\n\n");
+        if self.frames.is_empty() {
+            out.push_str("No frames point into the project.\n");
+        } else {
+            out.push_str("Project frames:\n");
+            for f in &self.frames {
+                match &f.symbol {
+                    Some(s) => out.push_str(&format!("- {} in `{}`\n", f.location(), s)),
+                    None => out.push_str(&format!("- {}\n", f.location())),
+                }
+            }
+        }
+        if self.causes.is_empty() {
+            out.push_str("\nNo likely cause found.\n");
+            return out;
+        }
+        out.push_str("\n## Likely causes\n");
+        for (i, c) in self.causes.iter().enumerate() {
+            let at = match (&c.file, c.line) {
+                (Some(f), Some(l)) => format!(" ({}:{})", f, l),
+                (Some(f), None) => format!(" ({})", f),
+                _ => String::new(),
+            };
+            out.push_str(&format!("\n{}. {}{}\n   {}\n   Fix: {}\n", i + 1, c.summary, at, c.explanation, c.fix));
+        }
+        out
+    }
+}
+
+/// Triage `log` against the project under `root`, whose readable files and
+/// public items are `files` and `symbols`.
+pub async fn triage(llm: &dyn Llm, root: &Path, log: &str, files: &[String], symbols: &[Symbol]) -> anyhow::Result<TriageReport> {
+    let all = frames(root, log, files, symbols);
+    let mut chunks: Vec<(Vec<Frame>, String)> = chunk(log, CHUNK_BYTES).into_iter().map(|c| (frames(root, &c, files, symbols), c)).collect();
+    // the tail of a log without an obvious error is the best guess
+    let relevant = chunks.iter().any(|(f, c)| !f.is_empty() || mentions_error(c));
+    if relevant {
+        chunks.retain(|(f, c)| !f.is_empty() || mentions_error(c));
+    } else if chunks.len() > 1 {
+        chunks.drain(..chunks.len() - 1);
+    }
+
+    let schema = schema();
+    let mut causes: Vec<Cause> = vec![];
+    for (frames, text) in chunks {
+        let context: Vec<String> = frames.iter().filter_map(|f| source_around(root, f)).collect();
+        let reply = crate::llm::structured::complete(llm, &request(&text, &context), &schema, 2).await?;
+        let found: Vec<Cause> = serde_json::from_value(reply["causes"].clone())?;
+        for cause in found {
+            if !causes.iter().any(|c| c.summary == cause.summary) {
+                causes.push(cause);
+            }
+        }
+    }
+    Ok(TriageReport { frames: all, causes })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::mock::ScriptedLlm;
+    use crate::symbols::parse;
+
+    #[tokio::test]
+    async fn test_frames_resolve_to_project_files_and_symbols() {
+        let td = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(td.path().join("src")).unwrap();
+        let db = "pub fn open() {\n    let x = 1;\n    x.unwrap();\n}\n";
+        std::fs::write(td.path().join("src/db.rs"), db).unwrap();
+        std::fs::write(td.path().join("app.py"), "def main():\n    load()\n").unwrap();
+        let files = vec!["src/db.rs".to_string(), "app.py".to_string()];
+        let mut symbols = parse("src/db.rs", db);
+        symbols.extend(parse("app.py", "def main():\n    load()\n"));
+
+        let log = format!(
+            "INFO starting\nthread 'main' panicked at src/db.rs:3:7:\n  at /rustc/abc/library/core/src/option.rs:931:5\n  at {}/src/db.rs:3:7\n\
+             Traceback (most recent call last):\n  File \"/usr/lib/python3/site-packages/req/app.py\", line 9\n  File \"./app.py\", line 2, in main\n",
+            td.path().display()
+        );
+        let found = frames(td.path(), &log, &files, &symbols);
+        assert_eq!(found, vec![
+            Frame { file: "src/db.rs".into(), line: 3, symbol: Some("open".into()) },
+            Frame { file: "app.py".into(), line: 2, symbol: Some("main".into()) },
+        ]);
+
+        let llm = ScriptedLlm::replies(&[
+            r#"{"causes": [{"summary": "unwrap on None", "file": "app.py", "line": 2, "explanation": "x is None", "fix": "handle None"}]}"#,
+        ]);
+        let report = triage(&llm, td.path(), &log, &files, &symbols).await.unwrap();
+        assert_eq!(report.implicated(), vec![("app.py".to_string(), Some(2)), ("src/db.rs".to_string(), Some(3))]);
+        let sent = llm.calls()[0].last().unwrap().content.clone();
+        assert!(sent.contains("src/db.rs:3 (in open):") && sent.contains("    3     x.unwrap();"));
+        assert!(report.render().contains("1. unwrap on None (app.py:2)\n   x is None\n   Fix: handle None\n"));
+    }
+
+    #[test]
+    fn test_logs_chunk_at_lines() {
+        assert_eq!(chunk("a\nb\nc\n", 4), vec!["a\nb\n", "c\n"]);
+        assert!(!mentions_error("INFO ok\n"));
+    }
+}
@@ -0,0 +1,254 @@
+//! Watch mode. `agent run --goal … --watch` (or `/run --watch` in the TUI)
+//! runs the goal, then waits for files under the project to change and runs
+//! again: the goal itself, or the lighter `[watch] pipeline` once the first
+//! run is done. Changes are found by polling modification times and
+//! debounced until the tree has been quiet for `debounce_ms`; files a run
+//! writes itself are accepted when it ends, so they do not retrigger it.
+//! `WatchLog` keeps one line per run for the terminal and the dashboard.
+
+use crate::agent::super_agent::SuperAgent;
+use crate::graph::pipeline::Pipeline;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+/// `[watch]` in config.toml.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WatchConfig {
+    /// Directories (relative to the project) whose files trigger a re-run.
+    pub paths: Vec<String>,
+    /// File and directory names never watched.
+    pub ignore: Vec<String>,
+    /// Quiet time after the last change before re-running.
+    pub debounce_ms: u64,
+    pub poll_ms: u64,
+    /// Pipeline re-runs use instead of the full goal run, e.g. a short
+    /// validation pipeline; `--on-change` overrides it.
+    pub pipeline: Option<String>,
+}
+
+impl Default for WatchConfig {
+    fn default() -> Self {
+        Self {
+            paths: vec![".".into()],
+            ignore: vec![".git".into(), "target".into(), "node_modules".into(), ".supercode".into()],
+            debounce_ms: 800,
+            poll_ms: 500,
+            pipeline: None,
+        }
+    }
+}
+
+/// Files under the watched paths and when each last changed.
+pub struct FileWatcher {
+    roots: Vec<PathBuf>,
+    ignore: Vec<String>,
+    seen: HashMap<PathBuf, SystemTime>,
+}
+
+impl FileWatcher {
+    pub fn new(root: &Path, config: &WatchConfig) -> Self {
+        let roots = config.paths.iter().map(|p| root.join(p)).collect();
+        let mut watcher = Self { roots, ignore: config.ignore.clone(), seen: HashMap::new() };
+        watcher.seen = watcher.scan();
+        watcher
+    }
+
+    fn scan(&self) -> HashMap<PathBuf, SystemTime> {
+        let mut out = HashMap::new();
+        let mut stack = self.roots.clone();
+        while let Some(dir) = stack.pop() {
+            let Ok(entries) = std::fs::read_dir(&dir) else { continue };
+            for entry in entries.flatten() {
+                if self.ignore.iter().any(|i| entry.file_name().to_string_lossy() == i.as_str()) {
+                    continue;
+                }
+                let Ok(meta) = entry.metadata() else { continue };
+                if meta.is_dir() {
+                    stack.push(entry.path());
+                } else if let Ok(modified) = meta.modified() {
+                    out.insert(entry.path(), modified);
+                }
+            }
+        }
+        out
+    }
+
+    /// Files added, changed or removed since the last poll, sorted.
+    pub fn poll(&mut self) -> Vec<PathBuf> {
+        let now = self.scan();
+        let mut changed: Vec<PathBuf> = now
+            .iter()
+            .filter(|(path, modified)| self.seen.get(*path) != Some(modified))
+            .map(|(path, _)| path.clone())
+            .chain(self.seen.keys().filter(|path| !now.contains_key(*path)).cloned())
+            .collect();
+        changed.sort();
+        self.seen = now;
+        changed
+    }
+
+    /// Wait for a change, then until nothing has changed for `debounce`;
+    /// returns every file changed meanwhile.
+    pub async fn changes(&mut self, poll: Duration, debounce: Duration) -> Vec<PathBuf> {
+        let mut changed: Vec<PathBuf> = vec![];
+        let mut last = Instant::now();
+        loop {
+            tokio::time::sleep(poll).await;
+            let batch = self.poll();
+            if !batch.is_empty() {
+                last = Instant::now();
+                for path in batch {
+                    if !changed.contains(&path) {
+                        changed.push(path);
+                    }
+                }
+            } else if !changed.is_empty() && last.elapsed() >= debounce {
+                changed.sort();
+                return changed;
+            }
+        }
+    }
+}
+
+/// One run in watch mode.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WatchRun {
+    pub number: usize,
+    /// Files whose change started it; empty for the first run.
+    pub changed: Vec<PathBuf>,
+    pub ok: bool,
+    pub took: Duration,
+    /// First line of the report or the error.
+    pub detail: String,
+}
+
+impl WatchRun {
+    /// `run 3 after src/lib.rs (+1 more): passed in 4.2s, fixed since run 2`.
+    pub fn describe(&self, previous: Option<&WatchRun>) -> String {
+        let trigger = match self.changed.as_slice() {
+            [] => String::new(),
+            [one] => format!(" after {}", one.display()),
+            [first, rest @ ..] => format!(" after {} (+{} more)", first.display(), rest.len()),
+        };
+        let status = if self.ok { "passed" } else { "failed" };
+        let trend = match previous {
+            Some(p) if p.ok && !self.ok => format!(", broken since run {}", p.number),
+            Some(p) if !p.ok && self.ok => format!(", fixed since run {}", p.number),
+            Some(p) if !p.ok => ", still failing".to_string(),
+            _ => String::new(),
+        };
+        let mut line = format!("run {}{}: {} in {:.1}s{}", self.number, trigger, status, self.took.as_secs_f64(), trend);
+        if !self.ok && !self.detail.is_empty() {
+            line.push_str(&format!(" ({})", self.detail));
+        }
+        line
+    }
+}
+
+/// Runs so far in one watch session. Clones share the same list.
+#[derive(Clone, Default)]
+pub struct WatchLog(Arc<Mutex<Vec<(WatchRun, String)>>>);
+
+impl WatchLog {
+    /// Add `run` and return its line.
+    pub fn push(&self, run: WatchRun) -> String {
+        let mut runs = self.0.lock();
+        let line = run.describe(runs.last().map(|(r, _)| r));
+        runs.push((run, line.clone()));
+        line
+    }
+
+    /// Lines of the latest `n` runs, oldest first.
+    pub fn lines(&self, n: usize) -> Vec<String> {
+        let runs = self.0.lock();
+        runs[runs.len().saturating_sub(n)..].iter().map(|(_, l)| l.clone()).collect()
+    }
+}
+
+/// Run `goal` on `sa`, then again on every debounced change under `root`
+/// until the future is dropped. Re-runs use `on_change` when given, skip
+/// plan approval and reuse the first run's answers to clarifying questions
+/// rather than asking again. `report` gets each run's line and full report.
+pub async fn run(
+    mut sa: SuperAgent,
+    goal: String,
+    on_change: Option<Pipeline>,
+    root: &Path,
+    config: &WatchConfig,
+    log: WatchLog,
+    mut report: impl FnMut(&str, &anyhow::Result<String>),
+) {
+    let mut watcher = FileWatcher::new(root, config);
+    let mut changed: Vec<PathBuf> = vec![];
+    let mut clarifications = vec![];
+    loop {
+        let since = Instant::now();
+        let mut task = SuperAgent::goal_task(goal.clone());
+        task.clarifications = clarifications.clone();
+        let id = task.id.clone();
+        crate::transcript::begin_run(&task.id);
+        let result = sa.run_task(task).await;
+        if sa.clarify_questions > 0 {
+            clarifications = sa.tasks.as_ref().and_then(|s| s.find(&id).ok()).map(|t| t.clarifications).unwrap_or_default();
+            sa.clarify_questions = 0;
+        }
+        let detail = match &result {
+            Ok(r) => r.lines().next().unwrap_or_default().to_string(),
+            Err(e) => e.to_string(),
+        };
+        let relative = changed.iter().map(|p| p.strip_prefix(root).unwrap_or(p).to_path_buf()).collect();
+        let line = log.push(WatchRun { number: log.lines(usize::MAX).len() + 1, changed: relative, ok: result.is_ok(), took: since.elapsed(), detail });
+        report(&line, &result);
+
+        // what the run wrote itself is not a change to react to
+        watcher.poll();
+        sa.approve_plan = false;
+        if on_change.is_some() {
+            sa.pipeline = on_change.clone();
+        }
+        changed = watcher.changes(Duration::from_millis(config.poll_ms), Duration::from_millis(config.debounce_ms)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_changes_are_debounced_and_ignored_dirs_skipped() {
+        let td = tempfile::tempdir().unwrap();
+        std::fs::create_dir(td.path().join("target")).unwrap();
+        std::fs::write(td.path().join("lib.rs"), "a").unwrap();
+        let config = WatchConfig { debounce_ms: 30, poll_ms: 10, ..Default::default() };
+        let mut watcher = FileWatcher::new(td.path(), &config);
+        assert!(watcher.poll().is_empty());
+
+        std::fs::write(td.path().join("target").join("out.o"), "x").unwrap();
+        std::fs::write(td.path().join("new.rs"), "b").unwrap();
+        std::fs::remove_file(td.path().join("lib.rs")).unwrap();
+        let changed = watcher.changes(Duration::from_millis(10), Duration::from_millis(30)).await;
+        assert_eq!(changed, vec![td.path().join("lib.rs"), td.path().join("new.rs")]);
+        assert!(watcher.poll().is_empty());
+    }
+
+    #[test]
+    fn test_log_lines_follow_the_trend() {
+        let log = WatchLog::default();
+        let run = |number: usize, ok: bool, changed: &[&str]| WatchRun {
+            number,
+            changed: changed.iter().map(PathBuf::from).collect(),
+            ok,
+            took: Duration::from_millis(1500),
+            detail: "2 tests failed".into(),
+        };
+        assert_eq!(log.push(run(1, false, &[])), "run 1: failed in 1.5s (2 tests failed)");
+        assert_eq!(log.push(run(2, false, &["src/a.rs"])), "run 2 after src/a.rs: failed in 1.5s, still failing (2 tests failed)");
+        assert_eq!(log.push(run(3, true, &["src/a.rs", "src/b.rs"])), "run 3 after src/a.rs (+1 more): passed in 1.5s, fixed since run 2");
+        assert_eq!(log.lines(1).len(), 1);
+    }
+}
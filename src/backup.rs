@@ -0,0 +1,311 @@
+//! `agent backup create|restore <path>`: a tarball of the state worth
+//! keeping across machines and upgrades. That is config.toml (which also
+//! holds the model aliases: `[remotes]`, `[router]`), the saved sessions,
+//! the memory index and the pane layouts. Models, secrets, tasks and
+//! transcripts stay out.
+//!
+//! The archive is a plain ustar file with a `MANIFEST.json` first, listing
+//! each file's size and SHA-256. Restore checks every file against it
+//! before writing any of them. `agent self-update` takes one of these
+//! under `<home>/backups/` before it swaps the binary.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Component, Path, PathBuf};
+
+pub const MANIFEST: &str = "MANIFEST.json";
+const VERSION: u32 = 1;
+const BLOCK: usize = 512;
+/// Directories under the state home that are backed up whole.
+const HOME_DIRS: &[&str] = &["sessions", "memory"];
+/// Files of the data directory that are backed up.
+const DATA_FILES: &[&str] = &["layouts.json"];
+
+/// `(archive path, contents)` of each file, in archive order.
+type Files = Vec<(String, Vec<u8>)>;
+
+/// Where the backed-up state lives; archive paths start with `config/`,
+/// `home/` or `data/` after these.
+#[derive(Debug, Clone)]
+pub struct Places {
+    pub config: PathBuf,
+    pub home: PathBuf,
+    pub data: PathBuf,
+}
+
+impl Places {
+    pub fn current() -> Self {
+        Self { config: crate::paths::config_dir(), home: crate::paths::home(), data: crate::paths::data_dir() }
+    }
+
+    fn root(&self, name: &str) -> &Path {
+        match name {
+            "config" => &self.config,
+            "data" => &self.data,
+            _ => &self.home,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    pub path: String,
+    pub bytes: u64,
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub version: u32,
+    /// The agent version that wrote it.
+    pub agent: String,
+    pub created_at: u64,
+    pub files: Vec<Entry>,
+}
+
+impl Manifest {
+    pub fn describe(&self) -> String {
+        let bytes: u64 = self.files.iter().map(|f| f.bytes).sum();
+        format!("{} file(s), {} byte(s), written by {} at {}", self.files.len(), bytes, self.agent, self.created_at)
+    }
+}
+
+fn sha256(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+fn walk(dir: &Path, out: &mut Vec<PathBuf>) {
+    let mut entries: Vec<PathBuf> = std::fs::read_dir(dir).into_iter().flatten().flatten().map(|e| e.path()).collect();
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            walk(&path, out);
+        } else if path.is_file() {
+            out.push(path);
+        }
+    }
+}
+
+/// `(archive path, file)` for everything a backup holds.
+fn sources(places: &Places) -> Vec<(String, PathBuf)> {
+    let mut out = vec![];
+    let config = places.config.join("config.toml");
+    if config.is_file() {
+        out.push(("config/config.toml".to_string(), config));
+    }
+    for dir in HOME_DIRS {
+        let mut files = vec![];
+        walk(&places.home.join(dir), &mut files);
+        for file in files {
+            if let Ok(rel) = file.strip_prefix(&places.home) {
+                out.push((format!("home/{}", rel.to_string_lossy().replace('\\', "/")), file.clone()));
+            }
+        }
+    }
+    for name in DATA_FILES {
+        let file = places.data.join(name);
+        if file.is_file() {
+            out.push((format!("data/{}", name), file));
+        }
+    }
+    out
+}
+
+/// Write a backup of `places` to `out`.
+pub fn create(places: &Places, out: &Path) -> anyhow::Result<Manifest> {
+    let mut files = vec![];
+    for (name, path) in sources(places) {
+        let data = std::fs::read(&path).map_err(|e| anyhow::anyhow!("cannot read {}: {}", path.display(), e))?;
+        files.push((name, data));
+    }
+    let manifest = Manifest {
+        version: VERSION,
+        agent: crate::update::CURRENT.to_string(),
+        created_at: crate::tasks::now_secs(),
+        files: files.iter().map(|(name, data)| Entry { path: name.clone(), bytes: data.len() as u64, sha256: sha256(data) }).collect(),
+    };
+    let mut tar = vec![];
+    append(&mut tar, MANIFEST, &serde_json::to_vec_pretty(&manifest)?)?;
+    for (name, data) in &files {
+        append(&mut tar, name, data)?;
+    }
+    tar.extend([0u8; BLOCK * 2]);
+    if let Some(dir) = out.parent().filter(|d| !d.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(out, tar)?;
+    Ok(manifest)
+}
+
+/// The manifest of the backup at `archive`, once every file in it matches.
+pub fn verify(archive: &Path) -> anyhow::Result<(Manifest, Files)> {
+    let raw = std::fs::read(archive).map_err(|e| anyhow::anyhow!("cannot read {}: {}", archive.display(), e))?;
+    let mut files = read(&raw)?;
+    if files.first().map(|f| f.0.as_str()) != Some(MANIFEST) {
+        anyhow::bail!("{} is not a backup: it does not start with {}", archive.display(), MANIFEST);
+    }
+    let manifest: Manifest = serde_json::from_slice(&files.remove(0).1)?;
+    if manifest.version > VERSION {
+        anyhow::bail!("backup format {} is newer than this agent reads ({})", manifest.version, VERSION);
+    }
+    if files.len() != manifest.files.len() {
+        anyhow::bail!("backup lists {} file(s) but holds {}", manifest.files.len(), files.len());
+    }
+    for (entry, (name, data)) in manifest.files.iter().zip(&files) {
+        if entry.path != *name || entry.bytes != data.len() as u64 || entry.sha256 != sha256(data) {
+            anyhow::bail!("{} is damaged: {} does not match the manifest", archive.display(), name);
+        }
+        split(name)?;
+    }
+    Ok((manifest, files))
+}
+
+/// The root and the relative path of archive path `name`.
+fn split(name: &str) -> anyhow::Result<(&str, &str)> {
+    let Some((root, rest)) = name.split_once('/').filter(|(root, _)| ["config", "home", "data"].contains(root)) else {
+        anyhow::bail!("unexpected path {} in backup", name);
+    };
+    if !Path::new(rest).components().all(|c| matches!(c, Component::Normal(_))) {
+        anyhow::bail!("unsafe path {} in backup", name);
+    }
+    Ok((root, rest))
+}
+
+/// Where archive path `name` is restored to.
+fn target(places: &Places, name: &str) -> anyhow::Result<PathBuf> {
+    let (root, rest) = split(name)?;
+    Ok(places.root(root).join(rest))
+}
+
+/// Verify the backup at `archive`, then write its files over `places`.
+pub fn restore(places: &Places, archive: &Path) -> anyhow::Result<Manifest> {
+    let (manifest, files) = verify(archive)?;
+    for (name, data) in files {
+        let path = target(places, &name)?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&path, data).map_err(|e| anyhow::anyhow!("cannot write {}: {}", path.display(), e))?;
+    }
+    Ok(manifest)
+}
+
+/// Where `agent self-update` puts its backup before moving to `to`.
+pub fn pre_upgrade_path(home: &Path, to: &str) -> PathBuf {
+    home.join("backups").join(format!("pre-upgrade-{}-to-{}-{}.tar", crate::update::CURRENT, to, crate::tasks::now_secs()))
+}
+
+fn octal(field: &mut [u8], value: u64) {
+    let text = format!("{:0width$o}\0", value, width = field.len() - 1);
+    field.copy_from_slice(text.as_bytes());
+}
+
+/// A ustar header and `data`, padded to whole blocks.
+fn append(tar: &mut Vec<u8>, name: &str, data: &[u8]) -> anyhow::Result<()> {
+    let mut header = [0u8; BLOCK];
+    let (prefix, base) = match name.len() {
+        n if n <= 100 => ("", name),
+        _ => name
+            .char_indices()
+            .filter(|&(i, c)| c == '/' && i <= 155 && name.len() - i - 1 <= 100)
+            .map(|(i, _)| (&name[..i], &name[i + 1..]))
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("path too long to back up: {}", name))?,
+    };
+    header[..base.len()].copy_from_slice(base.as_bytes());
+    octal(&mut header[100..108], 0o644);
+    octal(&mut header[108..116], 0);
+    octal(&mut header[116..124], 0);
+    octal(&mut header[124..136], data.len() as u64);
+    octal(&mut header[136..148], crate::tasks::now_secs());
+    header[156] = b'0';
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[263..265].copy_from_slice(b"00");
+    header[345..345 + prefix.len()].copy_from_slice(prefix.as_bytes());
+    header[148..156].fill(b' ');
+    let sum: u64 = header.iter().map(|&b| b as u64).sum();
+    octal(&mut header[148..155], sum);
+    header[155] = b' ';
+    tar.extend_from_slice(&header);
+    tar.extend_from_slice(data);
+    tar.resize(tar.len().div_ceil(BLOCK) * BLOCK, 0);
+    Ok(())
+}
+
+fn text(field: &[u8]) -> String {
+    String::from_utf8_lossy(field.split(|&b| b == 0).next().unwrap_or(&[])).into_owned()
+}
+
+fn parse_octal(field: &[u8]) -> anyhow::Result<u64> {
+    let digits = text(field);
+    u64::from_str_radix(digits.trim(), 8).map_err(|_| anyhow::anyhow!("bad number {:?} in tar header", digits))
+}
+
+/// The regular files of a tar archive, in order.
+fn read(raw: &[u8]) -> anyhow::Result<Files> {
+    let mut files = vec![];
+    let mut at = 0;
+    while at + BLOCK <= raw.len() {
+        let header = &raw[at..at + BLOCK];
+        if header.iter().all(|&b| b == 0) {
+            break;
+        }
+        let sum: u64 = header.iter().enumerate().map(|(i, &b)| if (148..156).contains(&i) { b' ' as u64 } else { b as u64 }).sum();
+        if parse_octal(&header[148..156])? != sum {
+            anyhow::bail!("damaged tar header at byte {}", at);
+        }
+        let size = parse_octal(&header[124..136])? as usize;
+        let prefix = text(&header[345..500]);
+        let name = if prefix.is_empty() { text(&header[..100]) } else { format!("{}/{}", prefix, text(&header[..100])) };
+        let start = at + BLOCK;
+        let data = raw.get(start..start + size).ok_or_else(|| anyhow::anyhow!("{} is cut short", name))?;
+        if matches!(header[156], b'0' | 0) {
+            files.push((name, data.to_vec()));
+        }
+        at = start + size.div_ceil(BLOCK) * BLOCK;
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backup_round_trips_and_catches_damage() -> anyhow::Result<()> {
+        let td = tempfile::tempdir()?;
+        let places = |root: &str| Places { config: td.path().join(root).join("config"), home: td.path().join(root).join("home"), data: td.path().join(root).join("data") };
+        let from = places("from");
+        let long = format!("sessions/{}/{}.json", "nested".repeat(12), "s".repeat(40));
+        for (path, text) in [
+            (from.config.join("config.toml"), "llm_model = \"remote:work\"\n"),
+            (from.home.join(&long), "{}"),
+            (from.home.join("memory/summaries.jsonl"), "{\"text\":\"notes\"}\n"),
+            (from.home.join("tasks/t.json"), "not backed up"),
+            (from.data.join("layouts.json"), "{}"),
+            (from.data.join("models/big.gguf"), "weights"),
+        ] {
+            std::fs::create_dir_all(path.parent().unwrap())?;
+            std::fs::write(path, text)?;
+        }
+        let archive = td.path().join("backup.tar");
+        let manifest = create(&from, &archive)?;
+        let names: Vec<&str> = manifest.files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(names, vec!["config/config.toml", &format!("home/{}", long), "home/memory/summaries.jsonl", "data/layouts.json"]);
+
+        let to = places("to");
+        restore(&to, &archive)?;
+        assert_eq!(std::fs::read_to_string(to.config.join("config.toml"))?, "llm_model = \"remote:work\"\n");
+        assert_eq!(std::fs::read_to_string(to.home.join(&long))?, "{}");
+        assert!(!to.home.join("tasks").exists() && !to.data.join("models").exists());
+
+        // flip a byte of the config's contents
+        let mut raw = std::fs::read(&archive)?;
+        let at = raw.windows(9).position(|w| w == b"llm_model").unwrap();
+        raw[at] = b'L';
+        std::fs::write(&archive, &raw)?;
+        assert!(restore(&places("again"), &archive).unwrap_err().to_string().contains("config/config.toml does not match"));
+        assert!(!td.path().join("again").exists());
+        Ok(())
+    }
+}
@@ -6,17 +6,274 @@ use clap::{Parser, Subcommand};
 pub struct Commands {
     #[command(subcommand)]
     pub command: Cmd,
+    /// How a failing command reports its error on stderr
+    #[arg(long, global = true, value_enum, default_value = "text")]
+    pub error_format: ErrorFormat,
+    /// Make no network requests beyond this machine and use the local model server
+    #[arg(long, global = true)]
+    pub offline: bool,
+    /// Print how long each startup step took when the command exits
+    #[arg(long, global = true)]
+    pub profile_startup: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ErrorFormat {
+    /// `error[E201]: …` with causes and a hint
+    Text,
+    /// `{"error": {"code", "message", "hint", "details"}}`
+    Json,
 }
 
 #[derive(Subcommand, Debug)]
 pub enum Cmd {
-    Run { #[arg(short, long)] goal: String },
+    Run {
+        #[arg(short, long, required_unless_present = "resume")] goal: Option<String>,
+        /// Resume a failed or interrupted run (task id or prefix) from its checkpoint
+        #[arg(long, conflicts_with_all = ["goal", "pipeline"])] resume: Option<String>,
+        /// JSON schema file the final result must conform to
+        #[arg(long)] schema: Option<String>,
+        /// Pipeline name (or .toml path) to run instead of plan → execute → review
+        #[arg(long)] pipeline: Option<String>,
+        /// Record what write_file and other mutating tools would do instead of doing it, then offer to run for real
+        #[arg(long, conflicts_with = "resume")] dry_run: bool,
+        /// Approve the plan without asking, even when approve_plan is set, and skip clarifying questions
+        #[arg(long)] yes: bool,
+        /// Keep running: re-run the goal whenever files in the project change
+        #[arg(long, conflicts_with_all = ["resume", "dry_run"])] watch: bool,
+        /// Pipeline to run on changes instead of the goal's full run (default: [watch] pipeline)
+        #[arg(long, requires = "watch")] on_change: Option<String>,
+    },
     Chat,
     Graph,
     Logs,
-    Tui,
+    Tui {
+        /// Pane layouts are saved under this name
+        #[arg(long, default_value = "default")] session: String,
+    },
     Exit,
+    /// Print the model server's Prometheus metrics
+    Metrics,
     Models { #[command(subcommand)] cmd: ModelCmd },
+    Tasks { #[command(subcommand)] cmd: TaskCmd },
+    Pipelines { #[command(subcommand)] cmd: PipelineCmd },
+    Tools { #[command(subcommand)] cmd: ToolCmd },
+    Skills { #[command(subcommand)] cmd: SkillCmd },
+    /// Check backends, model_dir, config, ports, GPU and the wasm engine
+    Doctor {
+        #[command(subcommand)] cmd: Option<DoctorCmd>,
+        /// Print the report as JSON
+        #[arg(long)] json: bool,
+    },
+    /// Update this binary from the project's GitHub releases
+    SelfUpdate {
+        #[arg(long, value_enum, default_value = "stable")] channel: crate::update::Channel,
+        /// Only report whether an update is available
+        #[arg(long)] check: bool,
+        /// Apply without asking
+        #[arg(long)] yes: bool,
+    },
+    /// Your own usage stats, recorded locally once turned on
+    Stats { #[command(subcommand)] cmd: Option<StatsCmd> },
+    /// Back up config, sessions and the memory index to a tarball, or restore one
+    Backup { #[command(subcommand)] cmd: BackupCmd },
+    /// Saved chat history and its retention limits
+    Memory { #[command(subcommand)] cmd: MemoryCmd },
+    /// Saved chat sessions, and importing chats from other agents
+    Sessions { #[command(subcommand)] cmd: SessionCmd },
+    /// Show what was sent to providers in a run (see [transcript] in config.toml); lists runs without an id
+    Transcript {
+        /// Run id or prefix; an `agent run` uses its task id
+        #[arg()] run: Option<String>,
+        /// Print the raw entries as JSON lines
+        #[arg(long)] json: bool,
+    },
+    /// Replay recorded agent runs
+    Trace { #[command(subcommand)] cmd: TraceCmd },
+    /// Review the current branch's changes (including uncommitted ones) and list findings
+    Review {
+        /// Branch to diff against (default: [review] base, "main")
+        #[arg(long)] base: Option<String>,
+        /// Start an agent run to fix each finding that comes with a suggestion
+        #[arg(long)] fix: bool,
+        /// Print the findings as JSON
+        #[arg(long)] json: bool,
+    },
+    /// Generate unit tests for a file or module, run them with the project's test_command and keep the ones that pass
+    TestGen {
+        /// Source file (src/foo.rs) or module path (foo::bar, pkg.mod)
+        target: String,
+        /// Test runs (the first plus repairs) before giving up
+        #[arg(long, default_value_t = 3)] attempts: usize,
+    },
+    /// Draft doc comments for undocumented public items and apply the ones you accept
+    Docgen {
+        /// Only files under this path
+        path: Option<String>,
+        /// Also draft an architecture overview into docs/ARCHITECTURE.md
+        #[arg(long)] overview: bool,
+        /// Accept every draft without asking
+        #[arg(long)] yes: bool,
+    },
+    /// Plan and apply a multi-file change limited to the given paths, then verify it with the build and tests
+    Refactor {
+        instruction: String,
+        /// Paths or globs the change may touch (e.g. src/tui)
+        #[arg(long, num_args = 1.., required = true)] paths: Vec<String>,
+        /// Diffs tried (the first plus repairs) before giving up
+        #[arg(long, default_value_t = 3)] attempts: usize,
+        /// Apply each diff without asking
+        #[arg(long)] yes: bool,
+    },
+    /// Find likely root causes and fixes for a stack trace or error log
+    Triage {
+        /// Log file, or - to read it from stdin
+        log: String,
+        /// Print the frames and causes as JSON
+        #[arg(long)] json: bool,
+        /// Open the implicated files in the TUI's file viewer afterwards
+        #[arg(long)] open: bool,
+    },
+    /// API keys in the OS keyring or the encrypted secrets file
+    Secrets { #[command(subcommand)] cmd: SecretCmd },
+    /// Serve JSON-RPC on stdin/stdout for editor integrations (see docs/EDITOR_PROTOCOL.md)
+    EditorServe {
+        /// Print the wire format reference instead of serving
+        #[arg(long)] reference: bool,
+    },
+    /// Print where config, state, models and caches are kept
+    Paths,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum DoctorCmd {
+    /// Check proxy/TLS settings, DNS, connections and HTTP for each endpoint
+    Network,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum StatsCmd {
+    /// Start recording command names and configured features
+    On,
+    /// Stop recording; what was recorded stays until `clear`
+    Off,
+    /// Write the raw events (JSON lines) to a file, or stdout
+    Export { #[arg(long)] out: Option<std::path::PathBuf> },
+    /// Delete everything recorded
+    Clear,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SecretCmd {
+    /// Store a secret; the value is prompted for without echo, or read from
+    /// stdin when piped, so it stays out of shell history and `ps`
+    Set {
+        #[arg()] name: String,
+    },
+    /// Show a secret, masked unless --reveal
+    Get {
+        #[arg()] name: String,
+        #[arg(long)] reveal: bool,
+    },
+    /// Delete a secret
+    Rm { #[arg()] name: String },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SkillCmd {
+    /// Search the skill index (skills.index_url) by name or description
+    Search { #[arg(default_value = "")] term: String },
+    /// Download a skill from the index, verify it and add it to the skills directory
+    Install { #[arg()] name: String },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum PipelineCmd {
+    /// List pipelines in <home>/pipelines (see `agent paths`) with their node order, flagging invalid ones
+    List,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ToolCmd {
+    /// List the tools agent runs can call, marking disabled ones
+    List,
+    /// Slowest and most-failing tools from the audit log
+    Stats,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum BackupCmd {
+    /// Write config.toml, sessions, the memory index and layouts (not models or secrets) to a .tar
+    Create { path: std::path::PathBuf },
+    /// Check a backup against its manifest, then write its files back
+    Restore {
+        path: std::path::PathBuf,
+        /// Restore without asking
+        #[arg(long)] yes: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum MemoryCmd {
+    /// Delete sessions and transcripts beyond the [memory.retention] limits, after showing them
+    Prune {
+        /// Only show what would be deleted
+        #[arg(long)] dry_run: bool,
+        /// Delete without asking
+        #[arg(long)] yes: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SessionCmd {
+    /// List saved sessions, newest first
+    List,
+    /// Import another agent's chat history as sessions you can continue in the TUI
+    Import {
+        #[arg(long, value_enum)] format: crate::memory::sessions::Format,
+        /// Transcript file (.aider.chat.history.md, a Claude Code session .jsonl, or OpenAI-style JSONL)
+        file: std::path::PathBuf,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum TraceCmd {
+    /// Write a run's tool calls as a shell script that repeats them without a model
+    ExportScript {
+        /// Run id or prefix; an `agent run` uses its task id
+        #[arg()] run: String,
+        /// Write the script here (made executable) instead of to stdout
+        #[arg(long, short)] output: Option<std::path::PathBuf>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum TaskCmd {
+    /// List recorded tasks, newest first, subtasks indented
+    List,
+    /// Cancel a task and its unfinished subtasks
+    Cancel { #[arg()] id: String },
+    /// List a task's artifacts (id may be a prefix)
+    Artifacts {
+        #[arg()] id: String,
+        /// Open the artifact directory
+        #[arg(long)] open: bool,
+    },
+    /// Create a task from a template
+    New {
+        #[arg(long)] template: String,
+        /// Override the template's title
+        #[arg(long)] title: Option<String>,
+        /// Run the task right away
+        #[arg(long)] run: bool,
+    },
+    /// List task templates
+    Templates,
+    /// Save an existing task as a template
+    SaveTemplate {
+        #[arg()] id: String,
+        #[arg()] name: String,
+    },
 }
 
 #[derive(Subcommand, Debug)]
@@ -24,6 +281,24 @@ pub enum ModelCmd {
     List,
     Import { #[arg()] path: String },
     Remove { #[arg()] name: String },
+    /// Check model files for truncation and corruption, and against their recorded SHA-256
+    Verify {
+        /// Only this model
+        #[arg()] name: Option<String>,
+        /// Record the checksum of sound models that have none yet
+        #[arg(long)] record: bool,
+    },
+    /// start [model] | stop | restart [model]: stopping drains requests in flight first
     Serve { #[arg()] action: String, #[arg()] model: Option<String> },
     Install { #[arg()] tool: Option<String> },
+    /// Measure load time and throughput of a local model
+    Bench {
+        #[arg()] name: String,
+        /// Text used to build the benchmark prompts
+        #[arg(long)] prompt_file: Option<String>,
+        /// native (default) or llama
+        #[arg(long)] provider: Option<String>,
+        /// Save the result and compare with earlier runs
+        #[arg(long)] save: bool,
+    },
 }
@@ -1,8 +1,11 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use crate::tasks::retry::RetryPolicy;
 use std::fs;
 use std::path::PathBuf;
+use std::time::SystemTime;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum ThemeName {
     DarkPlus,
     Light,
@@ -16,12 +19,115 @@ pub enum ThemeName {
     Peacocks,
 }
 
+impl ThemeName {
+    pub fn all() -> [ThemeName; 10] {
+        [
+            ThemeName::DarkPlus,
+            ThemeName::Light,
+            ThemeName::Monokai,
+            ThemeName::SolarizedDark,
+            ThemeName::SolarizedLight,
+            ThemeName::Dracula,
+            ThemeName::OneDark,
+            ThemeName::Nord,
+            ThemeName::Gruvbox,
+            ThemeName::Peacocks,
+        ]
+    }
+}
+
 impl Default for ThemeName {
     fn default() -> Self {
         ThemeName::DarkPlus
     }
 }
 
+/// Path globs agents may (`allow`) or must never (`deny`) touch, relative to
+/// the workspace root. An empty `allow` list means everything not denied.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FilesConfig {
+    #[serde(default)]
+    pub allow: Vec<String>,
+    #[serde(default)]
+    pub deny: Vec<String>,
+    /// Globs agents may write; empty means every path they may touch.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub writable: Vec<String>,
+}
+
+impl Default for FilesConfig {
+    fn default() -> Self {
+        Self {
+            allow: vec![],
+            writable: vec![],
+            deny: vec![
+                "secrets/".into(),
+                ".git/".into(),
+                "**/.env".into(),
+                "**/*.pem".into(),
+                "**/*.key".into(),
+            ],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UiConfig {
+    /// Catalog under `locales/` (`en`, `fa`).
+    pub language: String,
+    /// Ghost-text completions in the chat input.
+    pub autocomplete: bool,
+    /// Activity log lines kept; older ones are dropped.
+    pub log_lines: usize,
+    /// Notifications kept.
+    pub notification_lines: usize,
+    /// Lines of `/term` output kept for the terminal pane.
+    pub terminal_lines: usize,
+}
+
+impl Default for UiConfig {
+    fn default() -> Self {
+        Self { language: "en".into(), autocomplete: true, log_lines: 2000, notification_lines: 200, terminal_lines: 1000 }
+    }
+}
+
+/// Where `agent skills search|install` finds skills and which publishers
+/// it trusts.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SkillsConfig {
+    /// URL or path of the JSON index (see `agent::marketplace`).
+    pub index_url: String,
+    /// Hex ed25519 public keys whose signatures are accepted.
+    pub trusted_keys: Vec<String>,
+    /// Refuse skills without a valid signature from a trusted key. On by
+    /// default.
+    pub require_signature: bool,
+}
+
+impl Default for SkillsConfig {
+    fn default() -> Self {
+        Self { index_url: String::new(), trusted_keys: vec![], require_signature: true }
+    }
+}
+
+/// Models used beside the chat model.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModelsConfig {
+    /// Speech-to-text for push-to-talk in the TUI.
+    pub whisper: crate::models::whisper::WhisperConfig,
+    /// Prompt prefix (KV cache) reuse for llama.cpp and native models.
+    pub prefix_cache: crate::models::prefix_cache::PrefixCacheConfig,
+    /// Models (by name in `model_dir`) the TUI and the model server start
+    /// loading at launch; see `models::preload`.
+    pub preload: Vec<String>,
+    /// Let `agent models serve start` fall back to the echo mock when
+    /// llama.cpp cannot start a model. For testing; off by default.
+    pub allow_mock: bool,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RuntimeConfig {
     pub llm_endpoint: String,
@@ -29,32 +135,160 @@ pub struct RuntimeConfig {
     pub theme: ThemeName,
     pub model_dir: std::path::PathBuf,
     pub model_server_addr: std::net::SocketAddr,
+    /// Fallback ports when `model_server_addr` is busy.
+    #[serde(default)]
+    pub model_server_ports: crate::models::instance::PortRange,
+    /// `[server]`: the interface the model server listens on and the browser
+    /// origins allowed to call it.
+    #[serde(default)]
+    pub server: crate::models::access::ServerConfig,
+    #[serde(default)]
+    pub files: FilesConfig,
+    /// Named system prompts, selectable per conversation with `/persona`.
+    #[serde(default)]
+    pub personas: BTreeMap<String, String>,
+    #[serde(default)]
+    pub density: crate::tui::layout::Density,
+    #[serde(default)]
+    pub ui: UiConfig,
+    /// Task templates by name; see `tasks::templates`.
+    #[serde(default)]
+    pub templates: BTreeMap<String, crate::tasks::templates::TaskTemplate>,
+    /// Default retry policy for tasks.
+    #[serde(default)]
+    pub retry: RetryPolicy,
+    /// Retry policies by agent profile, overriding `retry`.
+    #[serde(default)]
+    pub retry_profiles: BTreeMap<String, RetryPolicy>,
+    /// Show the generated plan for approval before an agent run executes
+    /// it; `approve_plan` in `.supercode.toml` overrides this per project.
+    #[serde(default)]
+    pub approve_plan: bool,
+    /// Clarifying questions the planner may ask before an agent run plans
+    /// its goal; 0 asks none.
+    #[serde(default)]
+    pub clarify_questions: usize,
+    /// Pair mode: agent runs started in the TUI narrate what they are doing
+    /// in a dim feed beneath the chat; `/pair` toggles it.
+    #[serde(default)]
+    pub pair_mode: bool,
+    #[serde(default)]
+    pub skills: SkillsConfig,
+    #[serde(default)]
+    pub models: ModelsConfig,
+    /// `[inference]`: default sampling params, stop sequences and output
+    /// limit; `/params` overrides them per session.
+    #[serde(default)]
+    pub inference: crate::llm::params::InferenceParams,
+    /// `[router]`: per-step model choice for agent runs.
+    #[serde(default)]
+    pub router: crate::agent::router::RouterConfig,
+    /// `[network]`: proxy, CA bundle and client certificate for outbound HTTP.
+    #[serde(default)]
+    pub network: crate::net::NetworkConfig,
+    /// `[remotes.<name>]`: OpenAI-compatible servers chosen with
+    /// `remote:<name>/<model>` model names.
+    #[serde(default)]
+    pub remotes: BTreeMap<String, crate::llm::remote::RemoteEndpoint>,
+    /// `[chat_retry]`: how often an interrupted chat request is tried.
+    #[serde(default)]
+    pub chat_retry: crate::llm::resume::ChatRetryConfig,
+    /// `[update]`: where `agent self-update` looks and which keys it trusts.
+    #[serde(default)]
+    pub update: crate::update::UpdateConfig,
+    /// `[stats]`: opt-in, local-only usage stats.
+    #[serde(default)]
+    pub stats: crate::stats::StatsConfig,
+    /// `[tools]`: disabled tools and the tool call audit log.
+    #[serde(default)]
+    pub tools: crate::tools::audit::ToolsConfig,
+    /// `[transcript]`: per-run logs of what providers were sent, redacted.
+    #[serde(default)]
+    pub transcript: crate::transcript::TranscriptConfig,
+    /// `[memory]`: when long conversations are compacted into a summary, and
+    /// how much of the project notes goes with each request.
+    #[serde(default)]
+    pub memory: crate::memory::summary::MemoryConfig,
+    /// `[watch]`: what `agent run --watch` watches and how re-runs start.
+    #[serde(default)]
+    pub watch: crate::agent::watch::WatchConfig,
+    /// `[review]`: the default base branch and chunk size of `agent review`.
+    #[serde(default)]
+    pub review: crate::agent::review::ReviewConfig,
+    /// `[[panels]]`: shell commands whose output gets a tab in the Panels view.
+    #[serde(default)]
+    pub panels: Vec<crate::tui::views::panels::PanelConfig>,
 }
 
 impl Default for RuntimeConfig {
     fn default() -> Self {
-        let mut model_dir = dirs::data_dir().unwrap_or_else(|| std::path::PathBuf::from("./"));
-        model_dir.push("super-agent/models");
+        let model_dir = crate::paths::models_dir();
         Self {
             llm_endpoint: "http://127.0.0.1:8080".into(),
             llm_model: "local.gguf".into(),
             theme: ThemeName::default(),
             model_dir,
             model_server_addr: std::net::SocketAddr::from(([127,0,0,1], 11400)),
+            model_server_ports: Default::default(),
+            server: Default::default(),
+            files: FilesConfig::default(),
+            personas: BTreeMap::new(),
+            density: crate::tui::layout::Density::default(),
+            ui: UiConfig::default(),
+            templates: BTreeMap::new(),
+            retry: RetryPolicy::default(),
+            retry_profiles: BTreeMap::new(),
+            approve_plan: false,
+            clarify_questions: 0,
+            pair_mode: false,
+            skills: SkillsConfig::default(),
+            models: ModelsConfig::default(),
+            inference: Default::default(),
+            router: Default::default(),
+            network: Default::default(),
+            remotes: BTreeMap::new(),
+            chat_retry: Default::default(),
+            update: Default::default(),
+            stats: Default::default(),
+            tools: Default::default(),
+            transcript: Default::default(),
+            memory: Default::default(),
+            watch: Default::default(),
+            review: Default::default(),
+            panels: vec![],
         }
     }
 }
 
 impl RuntimeConfig {
-    pub fn path() -> Option<PathBuf> {
-        if let Some(mut d) = dirs::config_dir() {
-            d.push("super-agent");
-            fs::create_dir_all(&d).ok()?;
-            d.push("config.toml");
-            Some(d)
-        } else {
-            None
+    /// Policy for a task run under `profile`, unless the task sets its own.
+    pub fn retry_for(&self, profile: Option<&str>) -> RetryPolicy {
+        profile.and_then(|p| self.retry_profiles.get(p)).unwrap_or(&self.retry).clone()
+    }
+
+    /// Whether agent runs in `project` wait for plan approval.
+    pub fn approve_plan_for(&self, project: &crate::workspace::ProjectProfile) -> bool {
+        project.approve_plan.unwrap_or(self.approve_plan)
+    }
+
+    /// Where the local model server really listens; it moves to a free
+    /// port when `model_server_addr` is busy.
+    pub fn server_addr(&self) -> std::net::SocketAddr {
+        crate::models::instance::running_addr(&crate::paths::home(), self.model_server_addr)
+    }
+
+    /// `llm_endpoint`, following the model server to its actual port.
+    pub fn chat_endpoint(&self) -> String {
+        // offline, a remote provider gives way to the local model server
+        if crate::net::check(&self.llm_endpoint).is_err() {
+            return format!("http://{}", crate::models::instance::running_addr(&crate::paths::home(), self.model_server_addr));
         }
+        crate::models::instance::resolve_endpoint(&crate::paths::home(), &self.llm_endpoint, self.model_server_addr)
+    }
+
+    pub fn path() -> Option<PathBuf> {
+        crate::paths::ensure_private(&crate::paths::config_dir()).ok()?;
+        Some(crate::paths::config_file())
     }
 
     pub fn load() -> Self {
@@ -76,6 +310,191 @@ impl RuntimeConfig {
             fs::write(&p, s)?;
             return Ok(());
         }
-        Err(anyhow::anyhow!("no config path"))
+        Err(crate::errors::ConfigError::NoPath.into())
+    }
+
+    /// Save over the file on disk without reverting its restart-only
+    /// settings: edits to those made since launch wait for the restart.
+    pub fn save_live(&self) -> anyhow::Result<()> {
+        let on_disk = Self::path().and_then(|p| fs::read_to_string(p).ok()).and_then(|s| toml::from_str::<RuntimeConfig>(&s).ok());
+        match on_disk {
+            Some(disk) => self.merged_into(disk).save(),
+            None => self.save(),
+        }
+    }
+
+    /// `disk` with this config's live settings.
+    fn merged_into(&self, mut disk: RuntimeConfig) -> RuntimeConfig {
+        disk.apply_reload(self.clone());
+        disk
+    }
+}
+
+/// Result of applying a reloaded config: settings that took effect and
+/// settings that only change after a restart.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ConfigChanges {
+    pub applied: Vec<&'static str>,
+    pub needs_restart: Vec<&'static str>,
+}
+
+impl ConfigChanges {
+    pub fn is_empty(&self) -> bool {
+        self.applied.is_empty() && self.needs_restart.is_empty()
+    }
+
+    pub fn describe(&self) -> String {
+        let mut out = format!("config reloaded: {}", if self.applied.is_empty() { "-".to_string() } else { self.applied.join(", ") });
+        if !self.needs_restart.is_empty() {
+            out.push_str(&format!("; restart to apply: {}", self.needs_restart.join(", ")));
+        }
+        out
+    }
+}
+
+impl RuntimeConfig {
+    /// Copy settings that are safe to change at runtime from `new` and
+    /// report the rest. Restart-only settings keep their current value.
+    pub fn apply_reload(&mut self, mut new: RuntimeConfig) -> ConfigChanges {
+        let mut changes = ConfigChanges::default();
+        macro_rules! live {
+            ($field:ident) => {
+                if self.$field != new.$field {
+                    self.$field = new.$field;
+                    changes.applied.push(stringify!($field));
+                }
+            };
+        }
+        macro_rules! restart {
+            ($field:ident) => {
+                if self.$field != new.$field {
+                    changes.needs_restart.push(stringify!($field));
+                }
+            };
+        }
+        live!(theme);
+        live!(density);
+        live!(personas);
+        live!(llm_endpoint);
+        live!(llm_model);
+        live!(ui);
+        live!(templates);
+        live!(retry);
+        live!(retry_profiles);
+        live!(approve_plan);
+        live!(clarify_questions);
+        live!(pair_mode);
+        live!(skills);
+        // warm-up and the hosted server's fallback are set up at launch
+        if self.models.preload != new.models.preload {
+            changes.needs_restart.push("models.preload");
+            new.models.preload = self.models.preload.clone();
+        }
+        if self.models.allow_mock != new.models.allow_mock {
+            changes.needs_restart.push("models.allow_mock");
+            new.models.allow_mock = self.models.allow_mock;
+        }
+        live!(models);
+        live!(inference);
+        live!(router);
+        live!(network);
+        live!(remotes);
+        live!(chat_retry);
+        live!(update);
+        live!(stats);
+        live!(tools);
+        live!(transcript);
+        live!(memory);
+        live!(watch);
+        live!(review);
+        live!(panels);
+        restart!(model_dir);
+        restart!(model_server_addr);
+        restart!(model_server_ports);
+        restart!(server);
+        restart!(files);
+        changes
+    }
+}
+
+/// Polls the config file's modification time; cheap enough to call every tick.
+pub struct ConfigWatcher {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+impl ConfigWatcher {
+    pub fn new(path: PathBuf) -> Self {
+        let modified = Self::mtime(&path);
+        Self { path, modified }
+    }
+
+    fn mtime(path: &PathBuf) -> Option<SystemTime> {
+        fs::metadata(path).and_then(|m| m.modified()).ok()
+    }
+
+    /// Returns the re-read config when the file changed since the last poll.
+    pub fn poll(&mut self) -> Option<anyhow::Result<RuntimeConfig>> {
+        let modified = Self::mtime(&self.path);
+        if modified.is_none() || modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+        Some(
+            fs::read_to_string(&self.path)
+                .map_err(anyhow::Error::from)
+                .and_then(|s| toml::from_str::<RuntimeConfig>(&s).map_err(anyhow::Error::from)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reload_applies_live_settings_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        let mut current = RuntimeConfig::default();
+        fs::write(&path, toml::to_string_pretty(&current).unwrap()).unwrap();
+        let mut watcher = ConfigWatcher::new(path.clone());
+        assert!(watcher.poll().is_none());
+
+        let mut edited = current.clone();
+        edited.theme = ThemeName::Nord;
+        edited.model_server_addr = std::net::SocketAddr::from(([127, 0, 0, 1], 9999));
+        fs::write(&path, toml::to_string_pretty(&edited).unwrap()).unwrap();
+        // force a visible mtime change on coarse-grained filesystems
+        let later = SystemTime::now() + std::time::Duration::from_secs(5);
+        fs::File::options().write(true).open(&path).unwrap().set_modified(later).unwrap();
+
+        let reloaded = watcher.poll().unwrap().unwrap();
+        let changes = current.apply_reload(reloaded);
+        assert_eq!(changes.applied, vec!["theme"]);
+        assert_eq!(changes.needs_restart, vec!["model_server_addr"]);
+        assert_eq!(current.theme, ThemeName::Nord);
+        assert_eq!(current.model_server_addr.port(), 11400);
+        assert!(watcher.poll().is_none());
+    }
+
+    #[test]
+    fn test_saving_keeps_restart_only_edits_on_disk() {
+        let disk = RuntimeConfig {
+            model_server_addr: std::net::SocketAddr::from(([127, 0, 0, 1], 9999)),
+            models: ModelsConfig { preload: vec!["qwen".into()], ..Default::default() },
+            ..Default::default()
+        };
+        let mut running = RuntimeConfig { theme: ThemeName::Nord, ..Default::default() };
+
+        let saved = running.merged_into(disk.clone());
+        assert_eq!(saved.theme, ThemeName::Nord);
+        assert_eq!(saved.model_server_addr, disk.model_server_addr);
+        assert_eq!(saved.models.preload, vec!["qwen".to_string()]);
+
+        let changes = running.apply_reload(disk);
+        assert_eq!(changes.applied, vec!["theme"]);
+        assert_eq!(changes.needs_restart, vec!["models.preload", "model_server_addr"]);
+        assert!(running.models.preload.is_empty());
     }
 }
@@ -0,0 +1,221 @@
+//! `agent doctor`: checks the environment a run depends on and says how to
+//! fix what is missing. Each check yields a [`Finding`]; `--json` prints the
+//! [`Report`] for scripts and bug reports.
+
+use crate::config::RuntimeConfig;
+use serde::Serialize;
+use std::path::Path;
+use std::process::Command;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Ok,
+    /// Works, but something is degraded or optional is missing.
+    Warn,
+    Fail,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Finding {
+    pub check: &'static str,
+    pub status: Status,
+    pub detail: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fix: Option<String>,
+}
+
+impl Finding {
+    fn ok(check: &'static str, detail: impl Into<String>) -> Self {
+        Self { check, status: Status::Ok, detail: detail.into(), fix: None }
+    }
+
+    fn warn(check: &'static str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self { check, status: Status::Warn, detail: detail.into(), fix: Some(fix.into()) }
+    }
+
+    fn fail(check: &'static str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self { check, status: Status::Fail, detail: detail.into(), fix: Some(fix.into()) }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    pub ok: bool,
+    pub findings: Vec<Finding>,
+}
+
+impl Report {
+    pub fn new(findings: Vec<Finding>) -> Self {
+        Self { ok: findings.iter().all(|f| f.status != Status::Fail), findings }
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        for f in &self.findings {
+            let mark = match f.status {
+                Status::Ok => "ok  ",
+                Status::Warn => "warn",
+                Status::Fail => "FAIL",
+            };
+            out.push_str(&format!("{} {:<13} {}\n", mark, f.check, f.detail));
+            if let Some(fix) = &f.fix {
+                out.push_str(&format!("     {:<13} → {}\n", "", fix));
+            }
+        }
+        out
+    }
+}
+
+/// Run every check against the config file at `config_path`.
+pub async fn run(config_path: Option<&Path>) -> Report {
+    let (config, config_finding) = check_config(config_path);
+    let mut findings = vec![config_finding, check_model_dir(&config.model_dir)];
+    findings.push(check_backends().await);
+    let listen = config.server.listen_addr(config.model_server_addr);
+    findings.push(check_port(listen, &config.model_server_ports).await);
+    findings.push(match crate::models::access::exposure_warning(listen) {
+        Some(warning) => Finding::warn("exposure", warning, "if other machines need it, put an authenticating reverse proxy in front"),
+        None => Finding::ok("exposure", format!("the model server only listens on {}", listen.ip())),
+    });
+    findings.push(check_gpu());
+    findings.push(check_wasm(&crate::agent::plugin_engine::default_skills_dir()));
+    findings.push(check_llm_endpoint(&config).await);
+    Report::new(findings)
+}
+
+fn check_config(path: Option<&Path>) -> (RuntimeConfig, Finding) {
+    let Some(path) = path else {
+        return (RuntimeConfig::default(), Finding::warn("config", "no config directory on this platform", "defaults are in use"));
+    };
+    if !path.exists() {
+        return (RuntimeConfig::default(), Finding::ok("config", format!("{} not created yet; using defaults", path.display())));
+    }
+    match std::fs::read_to_string(path).map_err(anyhow::Error::from).and_then(|s| toml::from_str::<RuntimeConfig>(&s).map_err(anyhow::Error::from)) {
+        Ok(cfg) => (cfg, Finding::ok("config", format!("{} is valid", path.display()))),
+        Err(e) => (
+            RuntimeConfig::default(),
+            Finding::fail("config", format!("{}: {}", path.display(), e.to_string().lines().next().unwrap_or_default()), format!("fix the TOML in {} or move it aside; defaults are used until then", path.display())),
+        ),
+    }
+}
+
+fn check_model_dir(dir: &Path) -> Finding {
+    if !dir.exists() {
+        return Finding::warn("model_dir", format!("{} does not exist", dir.display()), format!("mkdir -p {} (or `agent models import <file>` creates it)", dir.display()));
+    }
+    let probe = dir.join(".doctor-write-test");
+    match std::fs::write(&probe, b"") {
+        Ok(()) => {
+            let _ = std::fs::remove_file(&probe);
+            let models = std::fs::read_dir(dir).map(|d| d.flatten().filter(|e| e.path().extension().is_some_and(|x| x == "gguf")).count()).unwrap_or(0);
+            Finding::ok("model_dir", format!("{} is writable, {} gguf model(s)", dir.display(), models))
+        }
+        Err(e) => Finding::fail("model_dir", format!("{} is not writable: {}", dir.display(), e), format!("chmod u+rwx {} or set model_dir in config.toml", dir.display())),
+    }
+}
+
+async fn check_backends() -> Finding {
+    let llama = crate::models::server::find_llama_binary();
+    let ollama = which::which("ollama").ok();
+    let ollama_up = crate::net::client(crate::net::MODEL_SERVER).get("http://127.0.0.1:11434/api/version").send().await.is_ok_and(|r| r.status().is_success());
+    let describe = |found: &Option<std::path::PathBuf>| found.as_ref().map_or("not found".to_string(), |p| p.display().to_string());
+    let detail = format!("llama.cpp: {}; ollama: {}{}", describe(&llama), describe(&ollama), if ollama_up { " (running)" } else { "" });
+    match (&llama, ollama.is_some() || ollama_up) {
+        (Some(_), _) => Finding::ok("backends", detail),
+        (None, true) => Finding::warn("backends", detail, "`agent models install llama` to serve gguf models yourself"),
+        (None, false) => Finding::fail("backends", detail, "`agent models install llama`, or install Ollama and point llm_endpoint at it"),
+    }
+}
+
+async fn check_port(addr: std::net::SocketAddr, ports: &crate::models::instance::PortRange) -> Finding {
+    match std::net::TcpListener::bind(addr) {
+        Ok(_) => Finding::ok("port", format!("{} is free", addr)),
+        Err(e) if e.kind() == std::io::ErrorKind::AddrInUse => {
+            let ours = crate::net::client(crate::net::MODEL_SERVER).get(format!("http://{}/v1/models", addr)).send().await.is_ok_and(|r| r.status().is_success());
+            if ours {
+                Finding::ok("port", format!("{} is in use by a running model server", addr))
+            } else if ports.auto {
+                Finding::warn("port", format!("{} is in use by another program", addr), format!("the model server will take a free port in {}-{}; set model_server_addr to avoid the move", ports.start, ports.end))
+            } else {
+                Finding::fail("port", format!("{} is in use by another program", addr), format!("stop whatever listens on port {} or change model_server_addr", addr.port()))
+            }
+        }
+        Err(e) => Finding::fail("port", format!("cannot bind {}: {}", addr, e), "pick another model_server_addr"),
+    }
+}
+
+fn check_gpu() -> Finding {
+    let first_line = |program: &str, args: &[&str]| -> Option<String> {
+        let out = Command::new(program).args(args).output().ok().filter(|o| o.status.success())?;
+        String::from_utf8_lossy(&out.stdout).lines().map(str::trim).find(|l| !l.is_empty()).map(str::to_string)
+    };
+    if let Some(gpu) = first_line("nvidia-smi", &["-L"]) {
+        return Finding::ok("gpu", format!("CUDA: {}", gpu));
+    }
+    if let Some(gpu) = first_line("rocm-smi", &["--showproductname"]) {
+        return Finding::ok("gpu", format!("ROCm: {}", gpu));
+    }
+    if cfg!(all(target_os = "macos", target_arch = "aarch64")) {
+        return Finding::ok("gpu", "Apple Silicon (Metal)");
+    }
+    if let Some(gpu) = first_line("vulkaninfo", &["--summary"]) {
+        return Finding::ok("gpu", format!("Vulkan: {}", gpu));
+    }
+    Finding::warn("gpu", "no GPU visible; models run on the CPU", "install the GPU driver (nvidia-smi / rocm-smi should list it) and a GPU build of llama.cpp")
+}
+
+fn check_wasm(skills_dir: &Path) -> Finding {
+    let engine = || -> anyhow::Result<i32> {
+        let engine = wasmtime::Engine::default();
+        let module = wasmtime::Module::new(&engine, wat::parse_str(r#"(module (func (export "answer") (result i32) i32.const 42))"#)?)?;
+        let mut store = wasmtime::Store::new(&engine, ());
+        let instance = wasmtime::Instance::new(&mut store, &module, &[])?;
+        instance.get_typed_func::<(), i32>(&mut store, "answer")?.call(&mut store, ())
+    };
+    if let Err(e) = engine().and_then(|v| if v == 42 { Ok(v) } else { Err(anyhow::anyhow!("test module returned {}", v)) }) {
+        return Finding::fail("wasm", format!("engine self-test failed: {}", e), "reinstall agent; the wasm runtime does not work on this machine");
+    }
+    let dir = skills_dir.to_path_buf();
+    let loaded = crate::agent::plugin_engine::PluginEngine::new(Some(dir.clone()), crate::tools::policy::FileAccessPolicy::unrestricted()).and_then(|mut pe| pe.load_skills().map(|_| pe.skills().len()));
+    match loaded {
+        Ok(n) => Finding::ok("wasm", format!("engine works, {} skill(s) in {}", n, dir.display())),
+        Err(e) => Finding::fail("wasm", format!("skills in {} do not load: {}", dir.display(), e), "remove or rebuild the broken skill"),
+    }
+}
+
+async fn check_llm_endpoint(config: &RuntimeConfig) -> Finding {
+    let url = format!("{}/v1/models", config.llm_endpoint.trim_end_matches('/'));
+    let checks = crate::net::diagnose(crate::net::LLM, &url).await;
+    match checks.iter().find(|c| !c.ok) {
+        None => Finding::ok("llm_endpoint", format!("{} answers", config.llm_endpoint)),
+        Some(c) => Finding::warn("llm_endpoint", format!("{}: {}", config.llm_endpoint, c.detail), "start the model server (`agent models serve start <model>`) or run `agent doctor network`"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_and_model_dir_findings() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        assert_eq!(check_config(Some(&path)).1.status, Status::Ok);
+        std::fs::write(&path, "llm_endpoint = [").unwrap();
+        let (_, bad) = check_config(Some(&path));
+        assert_eq!(bad.status, Status::Fail);
+        assert!(bad.fix.unwrap().contains("config.toml"));
+
+        assert_eq!(check_model_dir(&dir.path().join("missing")).status, Status::Warn);
+        std::fs::write(dir.path().join("tiny.gguf"), b"").unwrap();
+        assert!(check_model_dir(dir.path()).detail.ends_with("1 gguf model(s)"));
+        assert_eq!(check_wasm(&dir.path().join("skills")).status, Status::Ok);
+
+        let report = Report::new(vec![Finding::fail("port", "in use", "change it"), Finding::ok("gpu", "x")]);
+        assert!(!report.ok);
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["findings"][0]["status"], "fail");
+        assert!(json["findings"][1].get("fix").is_none());
+    }
+}
@@ -0,0 +1,312 @@
+//! `agent editor-serve`: a JSON-RPC server editors (Zed, Neovim, ...) spawn
+//! to get chat, file edits and diagnostics from the agent. Events on the
+//! agent event bus are forwarded as `event` notifications.
+
+pub mod protocol;
+
+use crate::agent::super_agent::SuperAgent;
+use crate::llm::Llm;
+use crate::memory::conversation::Conversation;
+use crate::memory::events::{EventBus, EventKind};
+use crate::tasks::TaskStore;
+use crate::tools::policy::{Access, FileAccessPolicy};
+use crate::types::Message;
+use parking_lot::Mutex;
+use protocol::*;
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::sync::mpsc;
+
+/// How often bus events are forwarded to the editor.
+const EVENT_POLL_MS: u64 = 200;
+
+#[derive(Clone)]
+pub struct EditorServer {
+    llm: Arc<dyn Llm>,
+    policy: FileAccessPolicy,
+    events: EventBus,
+    tasks: Option<TaskStore>,
+    endpoint: Option<(String, String)>,
+    conversations: Arc<Mutex<HashMap<String, Conversation>>>,
+}
+
+/// A failed request, turned into a JSON-RPC error response.
+struct Failure(i64, String);
+
+impl From<anyhow::Error> for Failure {
+    fn from(e: anyhow::Error) -> Self {
+        Failure(REQUEST_FAILED, e.to_string())
+    }
+}
+
+impl EditorServer {
+    pub fn new(llm: Arc<dyn Llm>, policy: FileAccessPolicy) -> Self {
+        Self {
+            llm,
+            policy,
+            events: EventBus::shared(),
+            tasks: None,
+            endpoint: None,
+            conversations: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Agents started with `run` connect to this model server and record
+    /// their tasks in `tasks`.
+    pub fn with_agent(mut self, endpoint: &str, model: &str, tasks: TaskStore) -> Self {
+        self.endpoint = Some((endpoint.into(), model.into()));
+        self.tasks = Some(tasks);
+        self
+    }
+
+    /// Serve requests from `reader` until EOF or `shutdown`.
+    pub async fn serve<R, W>(self, reader: R, mut writer: W) -> anyhow::Result<()>
+    where
+        R: AsyncBufRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        let mut lines = reader.lines();
+        let mut sub = self.events.subscribe("editor", &EventKind::all());
+        let (tx, mut rx) = mpsc::unbounded_channel::<Response>();
+        let mut poll = tokio::time::interval(Duration::from_millis(EVENT_POLL_MS));
+        let mut shutdown = None;
+        loop {
+            tokio::select! {
+                line = lines.next_line() => {
+                    let Some(line) = line? else { break };
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    let req = match serde_json::from_str::<Request>(&line) {
+                        Ok(req) => req,
+                        Err(e) => {
+                            write_line(&mut writer, &Response::error(Value::Null, PARSE_ERROR, e.to_string())).await?;
+                            continue;
+                        }
+                    };
+                    if req.method == "shutdown" {
+                        shutdown = req.id;
+                        break;
+                    }
+                    let (server, tx) = (self.clone(), tx.clone());
+                    tokio::spawn(async move {
+                        let id = req.id.clone();
+                        let result = server.handle(&req.method, req.params).await;
+                        // notifications get no response, not even an error
+                        if let Some(id) = id {
+                            let _ = tx.send(match result {
+                                Ok(v) => Response::ok(id, v),
+                                Err(Failure(code, message)) => Response::error(id, code, message),
+                            });
+                        }
+                    });
+                }
+                Some(resp) = rx.recv() => write_line(&mut writer, &resp).await?,
+                _ = poll.tick() => {
+                    for ev in sub.drain() {
+                        let params = EventParams { kind: format!("{:?}", ev.kind()).to_lowercase(), text: ev.describe() };
+                        let note = Notification { jsonrpc: "2.0".into(), method: "event".into(), params: serde_json::to_value(params)? };
+                        write_line(&mut writer, &note).await?;
+                    }
+                }
+            }
+        }
+        // answer requests still in flight before going away
+        drop(tx);
+        while let Some(resp) = rx.recv().await {
+            write_line(&mut writer, &resp).await?;
+        }
+        if let Some(id) = shutdown {
+            write_line(&mut writer, &Response::ok(id, Value::Null)).await?;
+        }
+        Ok(())
+    }
+
+    async fn handle(&self, method: &str, params: Value) -> Result<Value, Failure> {
+        match method {
+            "initialize" => reply(InitializeResult {
+                name: "supercode".into(),
+                version: env!("CARGO_PKG_VERSION").into(),
+                capabilities: ["chat", "edit", "diagnostics", "run", "events"].map(String::from).to_vec(),
+            }),
+            "chat" => reply(self.chat(parse(params)?).await?),
+            "edit" => reply(self.edit(parse(params)?).await?),
+            "diagnostics" => reply(self.diagnostics(parse(params)?).await?),
+            "run" => reply(self.run(parse(params)?).await?),
+            other => Err(Failure(METHOD_NOT_FOUND, format!("unknown method: {}", other))),
+        }
+    }
+
+    async fn chat(&self, p: ChatParams) -> anyhow::Result<ChatResult> {
+        let id = p.conversation.unwrap_or_else(|| "default".into());
+        let mut text = p.message;
+        if let Some(sel) = p.selection {
+            text = format!("{}\n\nSelected in {}:\n```\n{}\n```", text, sel.path, sel.text);
+        }
+        let messages = {
+            let mut convs = self.conversations.lock();
+            let conv = convs.entry(id.clone()).or_insert_with(|| Conversation::new(id.clone()));
+            conv.push(Message::new("user", text));
+            conv.request_messages()
+        };
+        let reply = self.llm.chat(&messages).await?;
+        if let Some(conv) = self.conversations.lock().get_mut(&id) {
+            conv.push(Message::new("assistant", reply.clone()));
+        }
+        Ok(ChatResult { conversation: id, reply })
+    }
+
+    async fn edit(&self, p: EditParams) -> anyhow::Result<EditResult> {
+        self.policy.check(Path::new(&p.path), Access::Write)?;
+        let content = self.content(&p.path, p.content)?;
+        let schema = json!({
+            "type": "object",
+            "required": ["content", "summary"],
+            "properties": {"content": {"type": "string"}, "summary": {"type": "string"}}
+        });
+        let prompt = format!(
+            "Rewrite the file {} following this instruction: {}\nReturn the complete new file as `content` and one line describing the change as `summary`.\n\n{}",
+            p.path, p.instruction, content
+        );
+        let v = crate::llm::structured::complete(self.llm.as_ref(), &[Message::new("user", prompt)], &schema, 1).await?;
+        Ok(EditResult {
+            edits: vec![TextEdit { path: p.path, new_text: v["content"].as_str().unwrap_or_default().to_string() }],
+            summary: v["summary"].as_str().unwrap_or_default().to_string(),
+        })
+    }
+
+    async fn diagnostics(&self, p: DiagnosticsParams) -> anyhow::Result<DiagnosticsResult> {
+        let content = self.content(&p.path, p.content)?;
+        let schema = json!({
+            "type": "object",
+            "required": ["diagnostics"],
+            "properties": {"diagnostics": {"type": "array", "items": {
+                "type": "object",
+                "required": ["line", "severity", "message"],
+                "properties": {
+                    "line": {"type": "integer"},
+                    "severity": {"type": "string", "enum": ["error", "warning", "info"]},
+                    "message": {"type": "string"}
+                }
+            }}}
+        });
+        let numbered: String = content.lines().enumerate().map(|(i, l)| format!("{:>4} {}\n", i + 1, l)).collect();
+        let prompt = format!("Review {} and list bugs and problems by line number. Reply with an empty list if there are none.\n\n{}", p.path, numbered);
+        let v = crate::llm::structured::complete(self.llm.as_ref(), &[Message::new("user", prompt)], &schema, 1).await?;
+        Ok(DiagnosticsResult { path: p.path, diagnostics: serde_json::from_value(v["diagnostics"].clone())? })
+    }
+
+    async fn run(&self, p: RunParams) -> anyhow::Result<RunResult> {
+        let mut sa = SuperAgent::new();
+        match &self.endpoint {
+            Some((endpoint, model)) => sa.connect(endpoint, model),
+            None => sa.llm = self.llm.clone(),
+        }
+        sa.events = self.events.clone();
+        sa.file_policy = self.policy.clone();
+        sa.tasks = self.tasks.clone();
+        Ok(RunResult { report: sa.run_goal(p.goal).await? })
+    }
+
+    /// The editor's buffer if it sent one, the file on disk otherwise.
+    fn content(&self, path: &str, sent: Option<String>) -> anyhow::Result<String> {
+        match sent {
+            Some(text) => Ok(text),
+            None => Ok(std::fs::read_to_string(self.policy.check(Path::new(path), Access::Read)?)?),
+        }
+    }
+}
+
+fn parse<T: DeserializeOwned>(params: Value) -> Result<T, Failure> {
+    serde_json::from_value(params).map_err(|e| Failure(INVALID_PARAMS, e.to_string()))
+}
+
+fn reply<T: serde::Serialize>(v: T) -> Result<Value, Failure> {
+    serde_json::to_value(v).map_err(|e| Failure(REQUEST_FAILED, e.to_string()))
+}
+
+async fn write_line<W: AsyncWrite + Unpin, T: serde::Serialize>(writer: &mut W, msg: &T) -> anyhow::Result<()> {
+    let mut line = serde_json::to_vec(msg)?;
+    line.push(b'\n');
+    writer.write_all(&line).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::mock::ScriptedLlm;
+    use tokio::io::BufReader;
+
+    async fn exchange(server: EditorServer, input: &str) -> anyhow::Result<Vec<Value>> {
+        let mut out = vec![];
+        server.serve(BufReader::new(input.as_bytes()), &mut out).await?;
+        String::from_utf8(out)?.lines().map(|l| Ok(serde_json::from_str(l)?)).collect()
+    }
+
+    #[tokio::test]
+    async fn test_chat_edit_and_errors_over_stdio() -> anyhow::Result<()> {
+        let td = tempfile::tempdir()?;
+        std::fs::write(td.path().join("lib.rs"), "fn add() {}\n")?;
+        let llm = ScriptedLlm::from_yaml(
+            r#"
+responses:
+  - prompt: "^Rewrite the file lib.rs"
+    reply: '{"content": "/// Adds.\nfn add() {}\n", "summary": "documented add"}'
+  - prompt: "Selected in lib.rs"
+    reply: "It adds."
+"#,
+        )?;
+        let policy = FileAccessPolicy::new(td.path(), &crate::config::FilesConfig::default());
+        let mut server = EditorServer::new(Arc::new(llm), policy);
+        server.events = EventBus::default();
+        // EOF right after the requests: the server still answers all of them
+        let input = [
+            r#"{"jsonrpc": "2.0", "id": 1, "method": "chat", "params": {"message": "what?", "selection": {"path": "lib.rs", "text": "fn add"}}}"#,
+            r#"{"jsonrpc": "2.0", "id": 2, "method": "edit", "params": {"path": "lib.rs", "instruction": "document"}}"#,
+            r#"{"jsonrpc": "2.0", "id": 3, "method": "edit", "params": {"path": ".git/config", "instruction": "x", "content": ""}}"#,
+            r#"{"jsonrpc": "2.0", "id": 4, "method": "hover"}"#,
+            r#"{"jsonrpc": "2.0", "id": 5, "method": "chat", "params": {}}"#,
+            "not json",
+        ]
+        .join("\n");
+        let mut out = exchange(server, &input).await?;
+        out.sort_by_key(|v| v["id"].as_i64());
+        let by_id = |id: i64| out.iter().find(|v| v["id"] == id).cloned().unwrap_or_default();
+
+        assert_eq!(by_id(1)["result"]["reply"], "It adds.");
+        assert_eq!(by_id(2)["result"]["edits"][0]["newText"], "/// Adds.\nfn add() {}\n");
+        assert_eq!(by_id(3)["error"]["code"], REQUEST_FAILED);
+        assert_eq!(by_id(4)["error"]["code"], METHOD_NOT_FOUND);
+        assert_eq!(by_id(5)["error"]["code"], INVALID_PARAMS);
+        assert_eq!(out.iter().find(|v| v["id"].is_null()).map(|v| v["error"]["code"].clone()), Some(json!(PARSE_ERROR)));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_diagnostics_and_shutdown() -> anyhow::Result<()> {
+        let llm = ScriptedLlm::replies(&[r#"{"diagnostics": [{"line": 2, "severity": "error", "message": "unclosed brace"}]}"#]);
+        let mut server = EditorServer::new(Arc::new(llm), FileAccessPolicy::unrestricted());
+        server.events = EventBus::default();
+        let result = server.handle("diagnostics", json!({"path": "a.rs", "content": "fn a() {\n{\n"})).await.map_err(|f| anyhow::anyhow!(f.1))?;
+        let result: DiagnosticsResult = serde_json::from_value(result)?;
+        assert_eq!(result.diagnostics, vec![Diagnostic { line: 2, severity: Severity::Error, message: "unclosed brace".into() }]);
+
+        let out = exchange(server, "{\"jsonrpc\": \"2.0\", \"id\": 9, \"method\": \"shutdown\"}\n{\"jsonrpc\": \"2.0\", \"id\": 10, \"method\": \"initialize\"}\n").await?;
+        assert_eq!(out.len(), 1, "nothing is served after shutdown");
+        assert_eq!(out[0]["id"], 9);
+        Ok(())
+    }
+
+    #[test]
+    fn test_reference_doc_is_current() {
+        let committed = include_str!("../../docs/EDITOR_PROTOCOL.md");
+        assert_eq!(committed, reference(), "regenerate with `agent editor-serve --reference > docs/EDITOR_PROTOCOL.md`");
+    }
+}
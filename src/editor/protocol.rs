@@ -0,0 +1,271 @@
+//! Wire types of `agent editor-serve`: JSON-RPC 2.0, one message per line
+//! on stdin/stdout. `docs/EDITOR_PROTOCOL.md` is generated from these types
+//! by [`reference`].
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+pub const PARSE_ERROR: i64 = -32700;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+/// The method ran and failed (model unreachable, path denied, ...).
+pub const REQUEST_FAILED: i64 = -32000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Request {
+    pub jsonrpc: String,
+    /// Absent for notifications, which get no response.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<Value>,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Response {
+    pub jsonrpc: String,
+    pub id: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<RpcError>,
+}
+
+impl Response {
+    pub fn ok(id: Value, result: Value) -> Self {
+        Self { jsonrpc: "2.0".into(), id, result: Some(result), error: None }
+    }
+
+    pub fn error(id: Value, code: i64, message: impl Into<String>) -> Self {
+        Self { jsonrpc: "2.0".into(), id, result: None, error: Some(RpcError { code, message: message.into() }) }
+    }
+}
+
+/// Server → editor message without an id.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Notification {
+    pub jsonrpc: String,
+    pub method: String,
+    pub params: Value,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InitializeResult {
+    pub name: String,
+    pub version: String,
+    pub capabilities: Vec<String>,
+}
+
+/// Text the user selected, sent as context with a chat message.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Selection {
+    pub path: String,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChatParams {
+    pub message: String,
+    /// Conversation to continue; omitted means `default`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub conversation: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub selection: Option<Selection>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChatResult {
+    pub conversation: String,
+    pub reply: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EditParams {
+    pub path: String,
+    pub instruction: String,
+    /// Unsaved buffer contents; read from disk when omitted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+}
+
+/// Replace the whole file at `path` with `newText`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TextEdit {
+    pub path: String,
+    #[serde(rename = "newText")]
+    pub new_text: String,
+}
+
+/// Edits for the editor to apply; the server never writes the file itself.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EditResult {
+    pub edits: Vec<TextEdit>,
+    pub summary: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiagnosticsParams {
+    pub path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    /// 1-based.
+    pub line: u32,
+    pub severity: Severity,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiagnosticsResult {
+    pub path: String,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunParams {
+    pub goal: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunResult {
+    pub report: String,
+}
+
+/// Params of the `event` notification: an event from the agent event bus.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EventParams {
+    pub kind: String,
+    pub text: String,
+}
+
+struct MethodDoc {
+    name: &'static str,
+    about: &'static str,
+    params: Value,
+    result: Option<Value>,
+}
+
+fn to_value<T: Serialize>(v: T) -> Value {
+    serde_json::to_value(v).expect("protocol types serialize")
+}
+
+fn methods() -> Vec<MethodDoc> {
+    vec![
+        MethodDoc {
+            name: "initialize",
+            about: "First request. Returns the server's name, version and capabilities.",
+            params: json!({}),
+            result: Some(to_value(InitializeResult {
+                name: "supercode".into(),
+                version: "0.2.0".into(),
+                capabilities: vec!["chat".into(), "edit".into(), "diagnostics".into(), "run".into(), "events".into()],
+            })),
+        },
+        MethodDoc {
+            name: "chat",
+            about: "Send a message to a conversation, optionally with the current selection as context.",
+            params: to_value(ChatParams {
+                message: "What does this function do?".into(),
+                conversation: Some("default".into()),
+                selection: Some(Selection { path: "src/main.rs".into(), text: "fn main() {}".into() }),
+            }),
+            result: Some(to_value(ChatResult { conversation: "default".into(), reply: "It is the entry point.".into() })),
+        },
+        MethodDoc {
+            name: "edit",
+            about: "Rewrite a file according to an instruction. The editor applies the returned edits.",
+            params: to_value(EditParams { path: "src/lib.rs".into(), instruction: "add doc comments".into(), content: None }),
+            result: Some(to_value(EditResult {
+                edits: vec![TextEdit { path: "src/lib.rs".into(), new_text: "/// Adds.\npub fn add() {}\n".into() }],
+                summary: "documented add".into(),
+            })),
+        },
+        MethodDoc {
+            name: "diagnostics",
+            about: "Review a file and report problems by line.",
+            params: to_value(DiagnosticsParams { path: "src/lib.rs".into(), content: Some("pub fn add() {}\n".into()) }),
+            result: Some(to_value(DiagnosticsResult {
+                path: "src/lib.rs".into(),
+                diagnostics: vec![Diagnostic { line: 1, severity: Severity::Warning, message: "missing docs".into() }],
+            })),
+        },
+        MethodDoc {
+            name: "run",
+            about: "Run the agent on a goal. Progress arrives as `event` notifications.",
+            params: to_value(RunParams { goal: "fix the failing test".into() }),
+            result: Some(to_value(RunResult { report: "Plan:\n1. ...".into() })),
+        },
+        MethodDoc {
+            name: "shutdown",
+            about: "Answer with `null` and exit.",
+            params: json!({}),
+            result: Some(Value::Null),
+        },
+        MethodDoc {
+            name: "event",
+            about: "Notification from the server for each event on the agent event bus.",
+            params: to_value(EventParams { kind: "agent".into(), text: "[run] planner: started".into() }),
+            result: None,
+        },
+    ]
+}
+
+/// Markdown reference of the wire format, built from the types above.
+pub fn reference() -> String {
+    fn pretty<T: Serialize>(v: &T) -> String {
+        serde_json::to_string_pretty(v).expect("json")
+    }
+    let mut out = String::from(
+        "# Editor protocol\n\n\
+         <!-- generated by `agent editor-serve --reference`; do not edit -->\n\n\
+         `agent editor-serve` speaks JSON-RPC 2.0 on stdin/stdout, one JSON message per line.\n\
+         Requests carry an `id`; the server answers each with a response holding the same `id`.\n\
+         Requests are handled concurrently, so responses may arrive out of order.\n\n\
+         ## Errors\n\n",
+    );
+    for (code, about) in [
+        (PARSE_ERROR, "the line is not a JSON-RPC request"),
+        (METHOD_NOT_FOUND, "unknown method"),
+        (INVALID_PARAMS, "params do not match the method"),
+        (REQUEST_FAILED, "the method failed, e.g. the model is unreachable or the path is denied"),
+    ] {
+        out.push_str(&format!("- `{}`: {}\n", code, about));
+    }
+    out.push_str(&format!(
+        "\nExample error response:\n\n```json\n{}\n```\n",
+        pretty(&Response::error(json!(7), METHOD_NOT_FOUND, "unknown method: hover"))
+    ));
+    for m in methods() {
+        out.push_str(&format!("\n## `{}`\n\n{}\n\n", m.name, m.about));
+        match m.result {
+            Some(result) => {
+                let request = Request { jsonrpc: "2.0".into(), id: Some(json!(1)), method: m.name.into(), params: m.params };
+                out.push_str(&format!("Request:\n\n```json\n{}\n```\n\n", pretty(&request)));
+                out.push_str(&format!("Response:\n\n```json\n{}\n```\n", pretty(&Response::ok(json!(1), result))));
+            }
+            None => {
+                let note = Notification { jsonrpc: "2.0".into(), method: m.name.into(), params: m.params };
+                out.push_str(&format!("```json\n{}\n```\n", pretty(&note)));
+            }
+        }
+    }
+    out
+}
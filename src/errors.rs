@@ -0,0 +1,352 @@
+//! User-facing errors. Each has a stable code (see docs/ERRORS.md) and,
+//! where there is something to do about it, a hint. They travel inside
+//! `anyhow::Error`; `ErrorReport::from_anyhow` finds the first coded error
+//! in the chain for the TUI toast and `--error-format json`.
+//!
+//! Codes: E1xx config, E2xx providers and models, E3xx tools, E4xx plugins
+//! and skills. E000 is anything not classified yet.
+
+use serde::Serialize;
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone)]
+pub enum AppError {
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+    #[error(transparent)]
+    Provider(#[from] ProviderError),
+    #[error(transparent)]
+    Tool(#[from] ToolError),
+    #[error(transparent)]
+    Plugin(#[from] PluginError),
+}
+
+#[derive(Error, Debug, Clone)]
+pub enum ConfigError {
+    #[error("no config directory on this system")]
+    NoPath,
+    #[error("{key} is not set")]
+    Missing { key: &'static str, purpose: &'static str },
+    #[error("{key}: {reason}")]
+    Invalid { key: &'static str, reason: String },
+    #[error("another agent {kind} (pid {pid}) is using {dir}")]
+    InstanceLocked { kind: String, pid: String, dir: String },
+    #[error("offline mode: {url} is not on this machine")]
+    Offline { url: String },
+}
+
+#[derive(Error, Debug, Clone)]
+pub enum ProviderError {
+    #[error("model {0} not found")]
+    ModelNotFound(String),
+    #[error("unknown provider {0}: expected native or llama")]
+    UnknownProvider(String),
+    #[error("llama.cpp server binary not found")]
+    BinaryNotFound,
+    #[error("llama provider failed to start or its health check failed")]
+    StartFailed,
+    #[error("{program} failed: {reason}")]
+    ProgramFailed { program: String, reason: String },
+    #[error("no chat model is available")]
+    NoModel { local: Vec<String> },
+    #[error("provider unavailable: {0}")]
+    Unavailable(String),
+    #[error("provider timed out")]
+    Timeout,
+    #[error("provider is rate limited")]
+    RateLimited { retry_after: Option<u64> },
+    #[error("bad request: {detail}")]
+    BadRequest { detail: String },
+}
+
+#[derive(Error, Debug, Clone)]
+pub enum ToolError {
+    #[error("tool {0} not found")]
+    NotFound(String),
+    #[error("{tool}: {reason}")]
+    InvalidInput { tool: &'static str, reason: String },
+    #[error("shared variable not set: {0}")]
+    VarNotSet(String),
+    #[error("skipped: call {0} it depends on failed")]
+    DependencyFailed(usize),
+    #[error("tool task failed: {0}")]
+    Crashed(String),
+    #[error("tool {0} is disabled")]
+    Disabled(String),
+    #[error("command not allowed: {0}")]
+    CommandNotAllowed(String),
+}
+
+#[derive(Error, Debug, Clone)]
+pub enum PluginError {
+    #[error("skill {0} not found")]
+    SkillNotFound(String),
+    #[error("skill {0} is not in the index")]
+    NotInIndex(String),
+    #[error("invalid skill name {0:?}")]
+    InvalidName(String),
+    #[error("bad skill index {url}: {reason}")]
+    BadIndex { url: String, reason: String },
+    #[error("unsupported skill artifact {0}")]
+    UnsupportedArtifact(String),
+    #[error("checksum mismatch for {name}: expected {expected}, got {actual}")]
+    ChecksumMismatch { name: String, expected: String, actual: String },
+    #[error("bad signature for {name}: {reason}")]
+    BadSignature { name: String, reason: String },
+    #[error("signature for {0} does not match any trusted key")]
+    UntrustedSignature(String),
+    #[error("{0} has no signature from a trusted key (skills.require_signature is set)")]
+    Unsigned(String),
+    #[error("trusted key {key}: {reason}")]
+    BadTrustedKey { key: String, reason: String },
+    #[error("{path}: {reason}")]
+    BadManifest { path: String, reason: String },
+    #[error("skill {skill} requests {path}: {reason}")]
+    PermissionDenied { skill: String, path: String, reason: String },
+    #[cfg_attr(not(feature = "native-plugins"), allow(dead_code))]
+    #[error("{path}: {reason}")]
+    BadNativePlugin { path: String, reason: String },
+    #[error("skill {name}: {reason}")]
+    Trapped { name: String, reason: String },
+}
+
+impl ConfigError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ConfigError::NoPath => "E101",
+            ConfigError::Missing { .. } => "E102",
+            ConfigError::Invalid { .. } => "E103",
+            ConfigError::InstanceLocked { .. } => "E104",
+            ConfigError::Offline { .. } => "E105",
+        }
+    }
+
+    pub fn hint(&self) -> Option<String> {
+        Some(match self {
+            ConfigError::NoPath => "set HOME (or APPDATA on Windows) so config.toml has somewhere to live".into(),
+            ConfigError::Missing { key, purpose } => format!("set {} in config.toml {}", key, purpose),
+            ConfigError::Invalid { key, .. } => format!("fix {} in config.toml; `agent doctor` checks the whole file", key),
+            ConfigError::InstanceLocked { .. } => "close the other agent or give this one a different model_dir".into(),
+            ConfigError::Offline { .. } => "drop --offline and set network.offline = false in config.toml to allow outbound requests".into(),
+        })
+    }
+}
+
+impl ProviderError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ProviderError::ModelNotFound(_) => "E201",
+            ProviderError::UnknownProvider(_) => "E202",
+            ProviderError::BinaryNotFound => "E203",
+            ProviderError::StartFailed => "E204",
+            ProviderError::ProgramFailed { .. } => "E205",
+            ProviderError::NoModel { .. } => "E206",
+            ProviderError::Unavailable(_) => "E207",
+            ProviderError::Timeout => "E208",
+            ProviderError::RateLimited { .. } => "E209",
+            ProviderError::BadRequest { .. } => "E210",
+        }
+    }
+
+    /// Short name of the kind of failure, for the `type` of error bodies.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ProviderError::ModelNotFound(_) => "model_not_found",
+            ProviderError::Unavailable(_) | ProviderError::NoModel { .. } => "unavailable",
+            ProviderError::Timeout => "timeout",
+            ProviderError::RateLimited { .. } => "rate_limited",
+            ProviderError::BadRequest { .. } => "bad_request",
+            _ => "provider_error",
+        }
+    }
+
+    /// Whether another provider may answer the same request: this one is
+    /// down, slow or busy, rather than the request being wrong.
+    pub fn should_fall_back(&self) -> bool {
+        matches!(self, ProviderError::Unavailable(_) | ProviderError::Timeout | ProviderError::RateLimited { .. })
+    }
+
+    pub fn hint(&self) -> Option<String> {
+        match self {
+            ProviderError::ModelNotFound(_) => Some("`agent models list` shows installed models; `agent models import` adds one".into()),
+            ProviderError::UnknownProvider(_) => None,
+            ProviderError::BinaryNotFound => Some("run `agent models install`, or put llama-server on PATH".into()),
+            ProviderError::StartFailed => Some("`agent doctor` checks the port, the binary and the GPU".into()),
+            ProviderError::ProgramFailed { .. } => Some("check the program's output above and `agent doctor`".into()),
+            ProviderError::NoModel { local } => Some(match local.first() {
+                Some(name) => format!("serve one of your models: `agent models serve start {}`", name),
+                None => "download a GGUF model and `agent models import <path>` it, or point llm_endpoint at a running server (llama.cpp, Ollama, LM Studio)".into(),
+            }),
+            ProviderError::Unavailable(_) => Some("`agent models serve start` starts the model server; `agent doctor` checks the port and the binary".into()),
+            ProviderError::Timeout => Some("raise network.timeout_secs in config.toml, or ask for fewer max_tokens".into()),
+            ProviderError::RateLimited { retry_after: Some(secs) } => Some(format!("wait {}s and try again", secs)),
+            ProviderError::RateLimited { retry_after: None } => Some("wait a moment and try again".into()),
+            ProviderError::BadRequest { .. } => None,
+        }
+    }
+}
+
+impl ToolError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ToolError::NotFound(_) => "E301",
+            ToolError::InvalidInput { .. } => "E302",
+            ToolError::VarNotSet(_) => "E303",
+            ToolError::DependencyFailed(_) => "E304",
+            ToolError::Crashed(_) => "E305",
+            ToolError::Disabled(_) => "E306",
+            ToolError::CommandNotAllowed(_) => "E307",
+        }
+    }
+
+    pub fn hint(&self) -> Option<String> {
+        match self {
+            ToolError::NotFound(_) => Some("`/skills` lists the skills that provide extra tools".into()),
+            ToolError::VarNotSet(_) => Some("set it with `/var set <key> <value>`".into()),
+            ToolError::Disabled(name) => Some(format!("`/tools enable {}` in the TUI, or remove it from tools.disabled (and policy.tools in .supercode.toml)", name)),
+            ToolError::CommandNotAllowed(_) => Some("add a matching regex to tools.commands in config.toml, or to policy.commands in .supercode.toml".into()),
+            _ => None,
+        }
+    }
+}
+
+impl PluginError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            PluginError::SkillNotFound(_) => "E401",
+            PluginError::NotInIndex(_) => "E402",
+            PluginError::InvalidName(_) => "E403",
+            PluginError::BadIndex { .. } => "E404",
+            PluginError::UnsupportedArtifact(_) => "E405",
+            PluginError::ChecksumMismatch { .. } => "E406",
+            PluginError::BadSignature { .. } => "E407",
+            PluginError::UntrustedSignature(_) => "E408",
+            PluginError::Unsigned(_) => "E409",
+            PluginError::BadTrustedKey { .. } => "E410",
+            PluginError::BadManifest { .. } => "E411",
+            PluginError::PermissionDenied { .. } => "E412",
+            PluginError::BadNativePlugin { .. } => "E413",
+            PluginError::Trapped { .. } => "E414",
+        }
+    }
+
+    pub fn hint(&self) -> Option<String> {
+        match self {
+            PluginError::SkillNotFound(_) => Some("`/skills` lists loaded skills; `/skills reload` picks up new files".into()),
+            PluginError::NotInIndex(_) => Some("`agent skills search` shows what the index offers".into()),
+            PluginError::ChecksumMismatch { .. } => Some("the download or the index is corrupt or tampered with; do not install it".into()),
+            PluginError::UntrustedSignature(_) | PluginError::Unsigned(_) => Some("add the publisher's key to skills.trusted_keys if you trust it".into()),
+            PluginError::BadTrustedKey { .. } => Some("skills.trusted_keys entries are 64 hex characters (an ed25519 public key)".into()),
+            PluginError::PermissionDenied { .. } => Some("widen files.allow in config.toml or remove the path from the skill's [permissions]".into()),
+            _ => None,
+        }
+    }
+}
+
+impl AppError {
+    /// The first coded error in `e`'s chain.
+    pub fn find(e: &anyhow::Error) -> Option<AppError> {
+        e.chain().find_map(|cause| {
+            cause
+                .downcast_ref::<AppError>()
+                .cloned()
+                .or_else(|| cause.downcast_ref::<ConfigError>().cloned().map(AppError::Config))
+                .or_else(|| cause.downcast_ref::<ProviderError>().cloned().map(AppError::Provider))
+                .or_else(|| cause.downcast_ref::<ToolError>().cloned().map(AppError::Tool))
+                .or_else(|| cause.downcast_ref::<PluginError>().cloned().map(AppError::Plugin))
+        })
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::Config(e) => e.code(),
+            AppError::Provider(e) => e.code(),
+            AppError::Tool(e) => e.code(),
+            AppError::Plugin(e) => e.code(),
+        }
+    }
+
+    pub fn hint(&self) -> Option<String> {
+        match self {
+            AppError::Config(e) => e.hint(),
+            AppError::Provider(e) => e.hint(),
+            AppError::Tool(e) => e.hint(),
+            AppError::Plugin(e) => e.hint(),
+        }
+    }
+}
+
+/// What the user sees: code, one-line message, hint and the cause chain.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ErrorReport {
+    pub code: &'static str,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hint: Option<String>,
+    /// Causes under the message, outermost first.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub details: Vec<String>,
+    /// The operation that failed, to grep the logs for.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+}
+
+impl ErrorReport {
+    pub fn from_anyhow(e: &anyhow::Error) -> Self {
+        let coded = AppError::find(e).map(|e| (e.code(), e.hint()));
+        let (code, hint) = coded.unwrap_or(("E000", None));
+        let details = e.chain().skip(1).map(|c| c.to_string()).collect();
+        Self { code, message: e.to_string(), hint, details, request_id: crate::request_id::current() }
+    }
+
+    /// `[E201] model x not found`; unclassified errors are just the message.
+    pub fn summary(&self) -> String {
+        match self.code {
+            "E000" => self.message.clone(),
+            code => format!("[{}] {}", code, self.message),
+        }
+    }
+
+    /// Summary, causes and hint, one per line, for terminals.
+    pub fn render(&self) -> String {
+        let mut out = format!("error[{}]: {}", self.code, self.message);
+        for cause in &self.details {
+            out.push_str(&format!("\n  caused by: {}", cause));
+        }
+        if let Some(hint) = &self.hint {
+            out.push_str(&format!("\n  hint: {}", hint));
+        }
+        if let Some(id) = &self.request_id {
+            out.push_str(&format!("\n  request: {}", id));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reports_find_the_coded_error_in_the_chain() {
+        let e = anyhow::Error::from(PluginError::NotInIndex("grep".into())).context("installing grep");
+        let report = ErrorReport::from_anyhow(&e);
+        assert_eq!(report.code, "E402");
+        assert_eq!(report.message, "installing grep");
+        assert_eq!(report.details, ["skill grep is not in the index"]);
+        assert!(report.hint.unwrap().contains("agent skills search"));
+
+        let wrapped = anyhow::Error::from(AppError::from(ConfigError::Missing { key: "skills.index_url", purpose: "to search for skills" }));
+        let report = ErrorReport::from_anyhow(&wrapped);
+        assert_eq!((report.code, report.summary().as_str()), ("E102", "[E102] skills.index_url is not set"));
+        assert_eq!(report.hint.as_deref(), Some("set skills.index_url in config.toml to search for skills"));
+
+        let plain = ErrorReport::from_anyhow(&anyhow::anyhow!("boom"));
+        assert_eq!((plain.code, plain.hint.is_none()), ("E000", true));
+        assert_eq!(plain.render(), "error[E000]: boom");
+        let traced = ErrorReport { request_id: Some("ab12".into()), ..plain.clone() };
+        assert_eq!(traced.render(), "error[E000]: boom\n  request: ab12");
+        let json = serde_json::to_value(&plain).unwrap();
+        assert_eq!(json, serde_json::json!({"code": "E000", "message": "boom"}));
+    }
+}
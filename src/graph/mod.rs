@@ -1,2 +1,3 @@
 pub mod dag;
 pub mod fsm;
+pub mod pipeline;
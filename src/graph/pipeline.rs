@@ -0,0 +1,431 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// When an edge passes its source node's output on to the target.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EdgeCondition {
+    #[default]
+    Always,
+    OnSuccess,
+    OnFailure,
+    /// The source output contains this text (case-insensitive).
+    Contains(String),
+}
+
+impl EdgeCondition {
+    /// `always`, `success`, `failure` or `contains:<text>`.
+    pub fn parse(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "always" => Ok(EdgeCondition::Always),
+            "success" => Ok(EdgeCondition::OnSuccess),
+            "failure" => Ok(EdgeCondition::OnFailure),
+            _ => match s.strip_prefix("contains:") {
+                Some(text) if !text.is_empty() => Ok(EdgeCondition::Contains(text.to_string())),
+                _ => Err(anyhow::anyhow!("unknown edge condition {:?} (always, success, failure, contains:<text>)", s)),
+            },
+        }
+    }
+
+    pub fn matches(&self, ok: bool, output: &str) -> bool {
+        match self {
+            EdgeCondition::Always => true,
+            EdgeCondition::OnSuccess => ok,
+            EdgeCondition::OnFailure => !ok,
+            EdgeCondition::Contains(text) => output.to_lowercase().contains(&text.to_lowercase()),
+        }
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            EdgeCondition::Always => "always".into(),
+            EdgeCondition::OnSuccess => "success".into(),
+            EdgeCondition::OnFailure => "failure".into(),
+            EdgeCondition::Contains(text) => format!("contains:{}", text),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeKind {
+    #[default]
+    Agent,
+    /// Pauses the run to ask the user `prompt`; the answer is the output.
+    HumanInput,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PipelineNode {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "is_agent")]
+    pub kind: NodeKind,
+    /// `planner`, `executor`, `critic` or any other role; only `executor`
+    /// runs tools, the rest answer with the model. Unused by human input.
+    #[serde(default)]
+    pub role: String,
+    /// Instruction prepended to the node's input; the question for human
+    /// input nodes.
+    #[serde(default)]
+    pub prompt: Option<String>,
+    /// Human input only: how long to wait before using `default`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<String>,
+    /// Tools the node may call besides `echo`; empty allows all.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<String>,
+    /// Model for this node instead of the run's.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+}
+
+impl PipelineNode {
+    pub fn new(name: impl Into<String>, role: impl Into<String>) -> Self {
+        Self { name: name.into(), kind: NodeKind::Agent, role: role.into(), prompt: None, timeout_secs: None, default: None, tools: vec![], model: None }
+    }
+
+    /// A node asking the user `question`.
+    pub fn human(name: impl Into<String>, question: impl Into<String>) -> Self {
+        Self { kind: NodeKind::HumanInput, prompt: Some(question.into()), ..Self::new(name, "") }
+    }
+}
+
+fn is_agent(kind: &NodeKind) -> bool {
+    *kind == NodeKind::Agent
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PipelineEdge {
+    pub from: String,
+    pub to: String,
+    #[serde(default)]
+    pub condition: EdgeCondition,
+}
+
+/// A workflow: agent roles with their tools and models, wired by
+/// conditional edges. Stored as `<home>/pipelines/<name>.toml`:
+///
+/// ```toml
+/// description = "plan, write code, test, review"
+/// [[nodes]]
+/// name = "plan"
+/// role = "planner"
+/// [[nodes]]
+/// name = "codegen"
+/// role = "executor"
+/// tools = ["read_file", "write_file"]
+/// [[edges]]
+/// from = "plan"
+/// to = "codegen"
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Pipeline {
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub nodes: Vec<PipelineNode>,
+    #[serde(default)]
+    pub edges: Vec<PipelineEdge>,
+}
+
+impl Default for Pipeline {
+    /// The built-in plan → execute → review graph.
+    fn default() -> Self {
+        let node = |name: &str| PipelineNode::new(name, name);
+        let edge = |from: &str, to: &str| PipelineEdge { from: from.into(), to: to.into(), condition: EdgeCondition::Always };
+        Self {
+            description: "plan, execute, review".into(),
+            nodes: vec![node("planner"), node("executor"), node("critic")],
+            edges: vec![edge("planner", "executor"), edge("executor", "critic")],
+        }
+    }
+}
+
+impl Pipeline {
+    pub fn node(&self, name: &str) -> Option<&PipelineNode> {
+        self.nodes.iter().find(|n| n.name == name)
+    }
+
+    /// Add `node`; with `after`, it is spliced in between `after` and that
+    /// node's successors (e.g. a critic inserted after the executor).
+    pub fn add_node(&mut self, node: PipelineNode, after: Option<&str>) -> anyhow::Result<()> {
+        if self.node(&node.name).is_some() {
+            return Err(anyhow::anyhow!("node {} already exists", node.name));
+        }
+        if let Some(after) = after {
+            if self.node(after).is_none() {
+                return Err(anyhow::anyhow!("no node named {}", after));
+            }
+            for edge in self.edges.iter_mut().filter(|e| e.from == after) {
+                edge.from = node.name.clone();
+            }
+            self.edges.push(PipelineEdge { from: after.into(), to: node.name.clone(), condition: EdgeCondition::Always });
+        }
+        self.nodes.push(node);
+        Ok(())
+    }
+
+    /// Remove a node, reconnecting its predecessors to its successors with
+    /// the outgoing edges' conditions.
+    pub fn remove_node(&mut self, name: &str) -> anyhow::Result<()> {
+        if self.node(name).is_none() {
+            return Err(anyhow::anyhow!("no node named {}", name));
+        }
+        let preds: Vec<String> = self.edges.iter().filter(|e| e.to == name).map(|e| e.from.clone()).collect();
+        let succs: Vec<PipelineEdge> = self.edges.iter().filter(|e| e.from == name).cloned().collect();
+        self.edges.retain(|e| e.from != name && e.to != name);
+        for pred in &preds {
+            for succ in &succs {
+                if !self.edges.iter().any(|e| &e.from == pred && e.to == succ.to) {
+                    self.edges.push(PipelineEdge { from: pred.clone(), to: succ.to.clone(), condition: succ.condition.clone() });
+                }
+            }
+        }
+        self.nodes.retain(|n| n.name != name);
+        Ok(())
+    }
+
+    /// Set the condition on `from -> to`, adding the edge if missing. Edges
+    /// that would close a cycle are rejected.
+    pub fn set_edge(&mut self, from: &str, to: &str, condition: EdgeCondition) -> anyhow::Result<()> {
+        for n in [from, to] {
+            if self.node(n).is_none() {
+                return Err(anyhow::anyhow!("no node named {}", n));
+            }
+        }
+        match self.edges.iter_mut().find(|e| e.from == from && e.to == to) {
+            Some(edge) => edge.condition = condition,
+            None => {
+                self.edges.push(PipelineEdge { from: from.into(), to: to.into(), condition });
+                if let Err(e) = self.order() {
+                    self.edges.pop();
+                    return Err(e);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn remove_edge(&mut self, from: &str, to: &str) -> anyhow::Result<()> {
+        let before = self.edges.len();
+        self.edges.retain(|e| !(e.from == from && e.to == to));
+        if self.edges.len() == before {
+            return Err(anyhow::anyhow!("no edge {} -> {}", from, to));
+        }
+        Ok(())
+    }
+
+    pub fn incoming(&self, name: &str) -> impl Iterator<Item = &PipelineEdge> {
+        let name = name.to_string();
+        self.edges.iter().filter(move |e| e.to == name)
+    }
+
+    /// Node names in execution order; errors on unknown endpoints, duplicate
+    /// names or cycles.
+    pub fn order(&self) -> anyhow::Result<Vec<String>> {
+        let mut indegree: HashMap<&str, usize> = HashMap::new();
+        for n in &self.nodes {
+            if indegree.insert(&n.name, 0).is_some() {
+                return Err(anyhow::anyhow!("duplicate node {}", n.name));
+            }
+        }
+        for e in &self.edges {
+            for end in [&e.from, &e.to] {
+                if !indegree.contains_key(end.as_str()) {
+                    return Err(anyhow::anyhow!("edge {} -> {} references unknown node {}", e.from, e.to, end));
+                }
+            }
+            *indegree.get_mut(e.to.as_str()).unwrap() += 1;
+        }
+        // keep declaration order among ready nodes so runs are stable
+        let mut order = vec![];
+        let mut done = BTreeSet::new();
+        while order.len() < self.nodes.len() {
+            let Some(next) = self.nodes.iter().find(|n| !done.contains(&n.name) && indegree[n.name.as_str()] == 0) else {
+                return Err(anyhow::anyhow!("pipeline has a cycle"));
+            };
+            done.insert(next.name.clone());
+            order.push(next.name.clone());
+            for e in self.edges.iter().filter(|e| e.from == next.name) {
+                *indegree.get_mut(e.to.as_str()).unwrap() -= 1;
+            }
+        }
+        Ok(order)
+    }
+
+    /// Structural checks plus unknown tools and empty names/roles, reported
+    /// together.
+    pub fn validate(&self, tools: &[String]) -> anyhow::Result<()> {
+        let mut errors = vec![];
+        if self.nodes.is_empty() {
+            errors.push("no nodes".to_string());
+        }
+        if let Err(e) = self.order() {
+            errors.push(e.to_string());
+        }
+        for n in &self.nodes {
+            let incomplete = match n.kind {
+                NodeKind::Agent => n.role.trim().is_empty(),
+                NodeKind::HumanInput => n.prompt.as_deref().unwrap_or_default().trim().is_empty(),
+            };
+            if n.name.trim().is_empty() || incomplete {
+                errors.push(format!("node {:?} needs a name and a role (or a prompt for human input)", n.name));
+            }
+            for t in n.tools.iter().filter(|t| !tools.contains(t)) {
+                errors.push(format!("node {} uses unknown tool {}", n.name, t));
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!(errors.join("; ")))
+        }
+    }
+
+    /// Lines for the Agents view and `agent graph`.
+    pub fn describe(&self) -> Vec<String> {
+        let mut lines: Vec<String> = self
+            .nodes
+            .iter()
+            .map(|n| match n.kind {
+                NodeKind::Agent => format!("{} • {}", n.name, n.role),
+                NodeKind::HumanInput => format!("{} • ask: {}", n.name, n.prompt.as_deref().unwrap_or_default()),
+            })
+            .collect();
+        lines.extend(self.edges.iter().map(|e| format!("{} -> {} [{}]", e.from, e.to, e.condition.label())));
+        lines
+    }
+}
+
+/// Named pipelines stored as `<home>/pipelines/<name>.toml`.
+pub struct PipelineStore {
+    dir: PathBuf,
+}
+
+impl PipelineStore {
+    pub fn new(home: &Path) -> Self {
+        Self { dir: home.join("pipelines") }
+    }
+
+    fn check_name(name: &str) -> anyhow::Result<()> {
+        if name.is_empty() || name.contains(['/', '\\', '.']) {
+            return Err(anyhow::anyhow!("invalid pipeline name: {:?}", name));
+        }
+        Ok(())
+    }
+
+    pub fn save(&self, name: &str, pipeline: &Pipeline) -> anyhow::Result<PathBuf> {
+        Self::check_name(name)?;
+        pipeline.order()?;
+        fs::create_dir_all(&self.dir)?;
+        let p = self.dir.join(format!("{}.toml", name));
+        fs::write(&p, toml::to_string_pretty(pipeline)?)?;
+        Ok(p)
+    }
+
+    /// Load and validate a pipeline by name, or from a path ending in `.toml`.
+    /// Names are the stems `save` accepts, so one cannot reach outside the
+    /// pipelines directory.
+    pub fn load(&self, name: &str, tools: &[String]) -> anyhow::Result<Pipeline> {
+        let p = if name.ends_with(".toml") {
+            PathBuf::from(name)
+        } else {
+            Self::check_name(name)?;
+            self.dir.join(format!("{}.toml", name))
+        };
+        Self::load_file(&p, tools).map_err(|e| anyhow::anyhow!("pipeline {}: {}", name, e))
+    }
+
+    fn load_file(path: &Path, tools: &[String]) -> anyhow::Result<Pipeline> {
+        let pipeline: Pipeline = toml::from_str(&fs::read_to_string(path)?)?;
+        pipeline.validate(tools)?;
+        Ok(pipeline)
+    }
+
+    /// Every `*.toml` in the directory by name, with its validation result.
+    pub fn list(&self, tools: &[String]) -> Vec<(String, anyhow::Result<Pipeline>)> {
+        let Ok(entries) = fs::read_dir(&self.dir) else { return vec![] };
+        let mut out: Vec<_> = entries
+            .flatten()
+            .map(|e| e.path())
+            .filter(|p| p.extension().map(|x| x == "toml").unwrap_or(false))
+            .filter_map(|p| Some((p.file_stem()?.to_string_lossy().into_owned(), Self::load_file(&p, tools))))
+            .collect();
+        out.sort_by(|a, b| a.0.cmp(&b.0));
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edit_and_save_pipeline() -> anyhow::Result<()> {
+        let mut p = Pipeline::default();
+        let review = PipelineNode { prompt: Some("Look for vulnerabilities.".into()), ..PipelineNode::new("security-review", "critic") };
+        p.add_node(review, Some("executor"))?;
+        assert_eq!(p.order()?, vec!["planner", "executor", "security-review", "critic"]);
+
+        p.set_edge("security-review", "critic", EdgeCondition::parse("contains:LGTM")?)?;
+        assert!(p.set_edge("critic", "planner", EdgeCondition::Always).is_err());
+        assert!(p.remove_edge("critic", "planner").is_err());
+
+        p.remove_node("security-review")?;
+        assert_eq!(p.edges.iter().find(|e| e.from == "executor").map(|e| e.condition.clone()), Some(EdgeCondition::Contains("LGTM".into())));
+
+        let dir = tempfile::tempdir()?;
+        let store = PipelineStore::new(dir.path());
+        store.save("reviewed", &p)?;
+        assert_eq!(store.load("reviewed", &[])?, p);
+        assert!(store.save("../x", &p).is_err());
+        fs::write(dir.path().join("outside.toml"), toml::to_string_pretty(&p)?)?;
+        assert!(store.load("../outside", &[]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_at_load() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let pipelines = dir.path().join("pipelines");
+        fs::create_dir_all(&pipelines)?;
+        let good = r#"
+            [[nodes]]
+            name = "codegen"
+            role = "executor"
+            tools = ["write_file"]
+            [[nodes]]
+            name = "test"
+            role = "executor"
+            model = "big.gguf"
+            [[nodes]]
+            name = "approve"
+            kind = "human_input"
+            prompt = "Merge the change?"
+            timeout_secs = 600
+            default = "no"
+            [[edges]]
+            from = "codegen"
+            to = "test"
+            condition = { contains = "done" }
+        "#;
+        let bad = good.replace(r#"["write_file"]"#, r#"["write_file", "rm_rf"]"#) + "[[edges]]\nfrom = \"test\"\nto = \"ghost\"\n";
+        fs::write(pipelines.join("good.toml"), good)?;
+        fs::write(pipelines.join("bad.toml"), bad)?;
+        let store = PipelineStore::new(dir.path());
+        let err = store.load("bad", &["write_file".to_string()]).unwrap_err().to_string();
+        assert!(err.contains("unknown node ghost") && err.contains("unknown tool rm_rf"), "{}", err);
+
+        let listed = store.list(&["write_file".to_string()]);
+        assert_eq!(listed.iter().map(|(n, r)| (n.as_str(), r.is_ok())).collect::<Vec<_>>(), vec![("bad", false), ("good", true)]);
+        let good = store.load("good", &["write_file".to_string()])?;
+        assert_eq!(good.edges[0].condition, EdgeCondition::Contains("done".into()));
+        assert_eq!(good.node("test").and_then(|n| n.model.as_deref()), Some("big.gguf"));
+        assert_eq!(good.node("approve").map(|n| n.kind), Some(NodeKind::HumanInput));
+        Ok(())
+    }
+}
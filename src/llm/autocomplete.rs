@@ -0,0 +1,58 @@
+use crate::models::server::Provider;
+use crate::types::Message;
+
+/// Longest suggestion offered as ghost text.
+const MAX_SUGGESTION_CHARS: usize = 80;
+
+/// Prompt asking a model to continue `prefix` without restating it.
+pub fn completion_messages(prefix: &str) -> Vec<Message> {
+    vec![
+        Message::new(
+            "system",
+            "You complete the user's partially typed prompt. Reply with only the few words that should come next, \
+             without repeating the text already typed.",
+        ),
+        Message::new("user", prefix),
+    ]
+}
+
+/// Turn a raw model reply into ghost text: the first line, with an echoed
+/// prefix removed and leading spaces dropped when the prefix ends in one.
+pub fn clean_completion(prefix: &str, raw: &str) -> Option<String> {
+    let raw = raw.trim_start_matches(['\n', '\r']);
+    let rest = raw.strip_prefix(prefix).unwrap_or(raw);
+    let line = rest.lines().next()?.trim_end();
+    if line.trim().is_empty() {
+        return None;
+    }
+    let line = if prefix.ends_with(' ') { line.trim_start() } else { line };
+    Some(line.chars().take(MAX_SUGGESTION_CHARS).collect())
+}
+
+/// Ask `provider` for a continuation of `prefix`; errors count as no
+/// suggestion.
+pub async fn suggest(provider: &dyn Provider, prefix: &str) -> Option<String> {
+    match provider.predict(prefix).await {
+        Ok(suggestion) => suggestion,
+        Err(e) => {
+            tracing::debug!("autocomplete failed: {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::server::MockProvider;
+
+    #[tokio::test]
+    async fn test_suggestion_is_single_trimmed_line() {
+        assert_eq!(clean_completion("write a ", "unit test\nfor the parser"), Some("unit test".into()));
+        assert_eq!(clean_completion("write a", " unit test"), Some(" unit test".into()));
+        assert_eq!(clean_completion("fix the", "fix the build"), Some(" build".into()));
+        assert_eq!(clean_completion("refac", "tor the module"), Some("tor the module".into()));
+        assert_eq!(clean_completion("x", "\n\n"), None);
+        assert_eq!(suggest(&MockProvider { model: "m.gguf".into() }, "add ").await, Some("[mock:m.gguf] echo: add".into()));
+    }
+}
@@ -0,0 +1,124 @@
+//! Embeddings. [`Embedder::embed_batch`] is the call to use for more than a
+//! handful of texts: OpenAI-compatible endpoints take up to
+//! [`MAX_BATCH_INPUTS`] inputs in one `/v1/embeddings` request, so a large
+//! workspace costs a few round trips instead of one per chunk. Backends
+//! without batching embed the texts concurrently.
+
+use crate::llm::llama::{LlamaClient, LlmError};
+use crate::llm::BATCH_CONCURRENCY;
+use async_trait::async_trait;
+use futures_util::{StreamExt, TryStreamExt};
+use serde::{Deserialize, Serialize};
+
+/// Inputs sent in one `/v1/embeddings` request.
+pub const MAX_BATCH_INPUTS: usize = 64;
+
+#[async_trait]
+pub trait Embedder: Send + Sync {
+    async fn embed(&self, text: &str) -> anyhow::Result<Vec<f32>>;
+
+    /// One vector per text, in order. The default embeds them concurrently,
+    /// [`BATCH_CONCURRENCY`] at a time.
+    async fn embed_batch(&self, texts: &[String]) -> anyhow::Result<Vec<Vec<f32>>> {
+        let calls: Vec<_> = texts.iter().map(|t| self.embed(t)).collect();
+        futures_util::stream::iter(calls).buffered(BATCH_CONCURRENCY).try_collect().await
+    }
+}
+
+#[derive(Serialize)]
+struct EmbeddingRequest<'a> {
+    model: &'a str,
+    input: &'a [String],
+}
+
+#[derive(Deserialize)]
+struct EmbeddingResponse {
+    data: Vec<Embedding>,
+}
+
+#[derive(Deserialize)]
+struct Embedding {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+impl LlamaClient {
+    /// Embeddings for `input`, in one request.
+    async fn embeddings(&self, input: &[String]) -> Result<Vec<Vec<f32>>, LlmError> {
+        let url = format!("{}/v1/embeddings", self.endpoint.trim_end_matches('/'));
+        crate::net::check(&url).map_err(LlmError::Offline)?;
+        let mut req = self.client.post(&url).json(&EmbeddingRequest { model: &self.model, input });
+        if let Some(key) = self.api_key().await {
+            req = req.bearer_auth(key.expose());
+        }
+        if let Some(id) = crate::request_id::current() {
+            req = req.header(crate::request_id::HEADER, id);
+        }
+        let mut resp: EmbeddingResponse = req.send().await?.error_for_status()?.json().await?;
+        if resp.data.len() != input.len() {
+            return Err(LlmError::InvalidResponse);
+        }
+        // servers may answer out of order; `index` says which input each is for
+        resp.data.sort_by_key(|e| e.index);
+        Ok(resp.data.into_iter().map(|e| e.embedding).collect())
+    }
+}
+
+#[async_trait]
+impl Embedder for LlamaClient {
+    async fn embed(&self, text: &str) -> anyhow::Result<Vec<f32>> {
+        let mut vectors = self.embeddings(&[text.to_string()]).await?;
+        Ok(vectors.pop().unwrap_or_default())
+    }
+
+    async fn embed_batch(&self, texts: &[String]) -> anyhow::Result<Vec<Vec<f32>>> {
+        let mut vectors = Vec::with_capacity(texts.len());
+        for chunk in texts.chunks(MAX_BATCH_INPUTS) {
+            vectors.extend(self.embeddings(chunk).await?);
+        }
+        Ok(vectors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::post, Json, Router};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_batches_are_packed_into_few_requests() -> anyhow::Result<()> {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counted = Arc::clone(&calls);
+        // answers in reverse order, each vector holding the input's length
+        let app = Router::new().route(
+            "/v1/embeddings",
+            post(move |Json(body): Json<serde_json::Value>| {
+                counted.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    let input = body["input"].as_array().cloned().unwrap_or_default();
+                    let data: Vec<_> = input
+                        .iter()
+                        .enumerate()
+                        .rev()
+                        .map(|(i, t)| serde_json::json!({"index": i, "embedding": [t.as_str().unwrap_or_default().len() as f32]}))
+                        .collect();
+                    Json(serde_json::json!({"data": data}))
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let client = LlamaClient::new(format!("http://{}", addr), "embed");
+        let texts: Vec<String> = (0..100).map(|n| "x".repeat(n)).collect();
+        let vectors = client.embed_batch(&texts).await?;
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert_eq!(vectors.len(), 100);
+        assert!(vectors.iter().enumerate().all(|(n, v)| v == &[n as f32]));
+        assert_eq!(client.embed("four").await?, [4.0]);
+        Ok(())
+    }
+}
@@ -0,0 +1,172 @@
+//! llama.cpp GBNF grammars. [`from_schema`] turns the JSON Schema subset
+//! that `tools::schema::validate` understands into a grammar, so sampling
+//! can only produce JSON of the right shape.
+
+use serde_json::Value;
+
+/// Rules every generated grammar can refer to.
+const PRIMITIVES: [(&str, &str); 8] = [
+    ("value", r#"object | array | string | number | boolean | null"#),
+    ("object", r#""{" ws ( string ":" ws value ( "," ws string ":" ws value )* )? "}" ws"#),
+    ("array", r#""[" ws ( value ( "," ws value )* )? "]" ws"#),
+    ("string", r#""\"" ( [^"\\\x7F\x00-\x1F] | "\\" ( ["\\/bfnrt] | "u" [0-9a-fA-F] [0-9a-fA-F] [0-9a-fA-F] [0-9a-fA-F] ) )* "\"" ws"#),
+    ("number", r#""-"? ( [0-9] | [1-9] [0-9]* ) ( "." [0-9]+ )? ( [eE] [-+]? [0-9]+ )? ws"#),
+    ("integer", r#""-"? ( [0-9] | [1-9] [0-9]* ) ws"#),
+    ("boolean", r#"( "true" | "false" ) ws"#),
+    ("null", r#""null" ws"#),
+];
+
+/// Any JSON value.
+pub fn json() -> String {
+    Builder::default().finish("value".into())
+}
+
+/// Grammar accepting JSON that conforms to `schema`.
+///
+/// Object properties come out in schema order, required ones first;
+/// properties not in the schema are not generated. Keywords outside the
+/// validator's subset (`pattern`, `minItems`, ...) are not enforced here,
+/// so callers still validate the reply.
+pub fn from_schema(schema: &Value) -> String {
+    let mut b = Builder::default();
+    let root = b.visit(schema, "schema");
+    b.finish(root)
+}
+
+#[derive(Default)]
+struct Builder {
+    rules: Vec<(String, String)>,
+}
+
+impl Builder {
+    fn finish(mut self, root: String) -> String {
+        let mut out = format!("root ::= {}\n", root);
+        self.rules.extend(PRIMITIVES.iter().map(|(n, r)| (n.to_string(), r.to_string())));
+        self.rules.push(("ws".into(), r#"[ \t\n]*"#.into()));
+        for (name, rule) in &self.rules {
+            out.push_str(&format!("{} ::= {}\n", name, rule));
+        }
+        out
+    }
+
+    /// Add a rule named after `hint`, made unique.
+    fn rule(&mut self, hint: &str, body: String) -> String {
+        let mut name: String = hint.chars().map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '-' }).collect();
+        let base = name.clone();
+        let mut n = 1;
+        while name == "root" || self.rules.iter().any(|(r, _)| *r == name) || PRIMITIVES.iter().any(|(p, _)| *p == name) {
+            n += 1;
+            name = format!("{}-{}", base, n);
+        }
+        self.rules.push((name.clone(), body));
+        name
+    }
+
+    /// Expression for `schema`; `hint` names any rules it needs. An empty
+    /// `enum` (e.g. no tools enabled) cannot be written as GBNF, so it is
+    /// left to the validator and only the type is constrained.
+    fn visit(&mut self, schema: &Value, hint: &str) -> String {
+        if let Some(Value::Array(options)) = schema.get("enum").filter(|o| o.as_array().is_some_and(|o| !o.is_empty())) {
+            let alts: Vec<String> = options.iter().map(|v| literal(&v.to_string())).collect();
+            return format!("( {} ) ws", alts.join(" | "));
+        }
+        match schema.get("type") {
+            Some(Value::String(t)) => self.visit_type(t, schema, hint),
+            Some(Value::Array(types)) => {
+                let alts: Vec<String> = types.iter().filter_map(|t| t.as_str()).map(|t| self.visit_type(t, schema, hint)).collect();
+                format!("( {} )", alts.join(" | "))
+            }
+            _ => "value".into(),
+        }
+    }
+
+    fn visit_type(&mut self, t: &str, schema: &Value, hint: &str) -> String {
+        match t {
+            "object" => self.visit_object(schema, hint),
+            "array" => match schema.get("items") {
+                Some(items) => {
+                    let item = self.visit(items, &format!("{}-item", hint));
+                    let item = self.rule(&format!("{}-item", hint), item);
+                    self.rule(hint, format!(r#""[" ws ( {item} ( "," ws {item} )* )? "]" ws"#))
+                }
+                None => "array".into(),
+            },
+            "string" | "number" | "integer" | "boolean" | "null" => t.into(),
+            _ => "value".into(),
+        }
+    }
+
+    fn visit_object(&mut self, schema: &Value, hint: &str) -> String {
+        let Some(props) = schema.get("properties").and_then(|p| p.as_object()).filter(|p| !p.is_empty()) else {
+            return "object".into();
+        };
+        let required: Vec<&str> = schema.get("required").and_then(|r| r.as_array()).map(|r| r.iter().filter_map(|k| k.as_str()).collect()).unwrap_or_default();
+        let mut members = vec![];
+        for (key, prop) in props {
+            let value = self.visit(prop, &format!("{}-{}", hint, key));
+            members.push((required.contains(&key.as_str()), format!(r#"{} ":" ws {}"#, literal(&Value::String(key.clone()).to_string()), value)));
+        }
+        members.sort_by_key(|(req, _)| !*req);
+        let body = if members[0].0 {
+            // required members are fixed; optional ones may follow each in turn
+            let mut parts = vec![];
+            for (i, (req, m)) in members.iter().enumerate() {
+                let sep = if i == 0 { "" } else { r#""," ws "# };
+                parts.push(if *req { format!("{}{}", sep, m) } else { format!("( {}{} )?", sep, m) });
+            }
+            parts.join(" ")
+        } else {
+            // nothing required: any of the known members, comma separated
+            let member = self.rule(&format!("{}-member", hint), members.iter().map(|(_, m)| m.as_str()).collect::<Vec<_>>().join(" | "));
+            format!(r#"( {member} ( "," ws {member} )* )?"#)
+        };
+        self.rule(hint, format!(r#""{{" ws {} "}}" ws"#, body))
+    }
+}
+
+/// GBNF string literal matching `text` exactly.
+fn literal(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_schema_becomes_grammar() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "note": {"type": ["string", "null"]},
+                "steps": {"type": "array", "items": {"type": "string"}},
+                "tool": {"type": "string", "enum": ["read", "write"]},
+            },
+            "required": ["tool", "steps"]
+        });
+        let g = from_schema(&schema);
+        assert!(g.starts_with("root ::= schema\n"), "{}", g);
+        assert!(g.contains(r#"schema-steps ::= "[" ws ( schema-steps-item ( "," ws schema-steps-item )* )? "]" ws"#), "{}", g);
+        // required first in schema order, then the optional member
+        assert!(
+            g.contains(r#"schema ::= "{" ws "\"steps\"" ":" ws schema-steps "," ws "\"tool\"" ":" ws ( "\"read\"" | "\"write\"" ) ws ( "," ws "\"note\"" ":" ws ( string | null ) )? "}" ws"#),
+            "{}",
+            g
+        );
+
+        // every rule that is used is defined
+        let defined: Vec<&str> = g.lines().filter_map(|l| l.split(" ::= ").next()).collect();
+        for line in g.lines() {
+            let body = line.split_once(" ::= ").map(|(_, b)| b).unwrap_or_default();
+            for word in body.split_whitespace().filter(|w| w.chars().all(|c| c.is_ascii_lowercase() || c == '-' || c.is_ascii_digit()) && w.starts_with(|c: char| c.is_ascii_lowercase())) {
+                assert!(defined.contains(&word), "{} is not defined in\n{}", word, g);
+            }
+        }
+        assert!(json().starts_with("root ::= value\n"));
+
+        let none = from_schema(&json!({"type": "object", "properties": {"tool": {"type": "string", "enum": []}}, "required": ["tool"]}));
+        assert!(none.contains(r#"schema ::= "{" ws "\"tool\"" ":" ws string "}" ws"#), "{}", none);
+        assert!(!none.contains("(  )"), "{}", none);
+    }
+}
@@ -0,0 +1,94 @@
+//! Output guards applied to every reply, whatever the provider: the reply
+//! ends before the first stop sequence and is cut at `max_tokens`. Backends
+//! that honour `stop` and `max_tokens` themselves make this a no-op; the
+//! rest (mock, native, plugins) are held to the same limits here.
+
+use crate::llm::params::InferenceParams;
+use serde::{Deserialize, Serialize};
+
+/// Why generation ended, as in the chat-completions `finish_reason`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FinishReason {
+    Stop,
+    /// Cut at the output token limit; the reply is incomplete.
+    Length,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Completion {
+    pub text: String,
+    pub finish: FinishReason,
+}
+
+impl Completion {
+    pub fn truncated(&self) -> bool {
+        self.finish == FinishReason::Length
+    }
+}
+
+/// Apply `params.stop` and `params.max_tokens` to `text`. `finish` is what
+/// the backend reported; a limit hit here overrides it.
+pub fn enforce(text: &str, finish: FinishReason, params: &InferenceParams) -> Completion {
+    let mut text = text;
+    if let Some(at) = params.stop.iter().filter(|s| !s.is_empty()).filter_map(|s| text.find(s.as_str())).min() {
+        text = &text[..at];
+    }
+    let mut finish = finish;
+    if let Some(max) = params.max_tokens {
+        if let Some(at) = token_start(text, max as usize) {
+            text = text[..at].trim_end();
+            finish = FinishReason::Length;
+        }
+    }
+    Completion { text: text.to_string(), finish }
+}
+
+/// Byte offset of the `n`th (0-based) token, estimated the way BPE
+/// vocabularies split text: a run of letters and digits takes a token per
+/// four characters, and every other visible character is one.
+fn token_start(text: &str, n: usize) -> Option<usize> {
+    let mut tokens = 0;
+    let mut run = 0;
+    for (i, c) in text.char_indices() {
+        if c.is_whitespace() {
+            run = 0;
+            continue;
+        }
+        let starts = if c.is_alphanumeric() {
+            run += 1;
+            run % 4 == 1
+        } else {
+            run = 0;
+            true
+        };
+        if starts {
+            if tokens == n {
+                return Some(i);
+            }
+            tokens += 1;
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stop_sequences_and_token_limit() {
+        let params = InferenceParams { stop: vec!["\nUser:".into(), "###".into()], max_tokens: Some(4), ..Default::default() };
+        let stopped = enforce("one two\nUser: three ### four", FinishReason::Stop, &params);
+        assert_eq!(stopped, Completion { text: "one two".into(), finish: FinishReason::Stop });
+
+        let cut = enforce("a b  c d e f", FinishReason::Stop, &params);
+        assert_eq!(cut.text, "a b  c d");
+        assert!(cut.truncated());
+        assert_eq!(enforce("unbelievably so", FinishReason::Stop, &params).text, "unbelievably so");
+        assert_eq!(enforce("x.unwrap_or_default()", FinishReason::Stop, &params).text, "x.unwrap");
+
+        let exact = enforce("a b c d", FinishReason::Length, &InferenceParams::default());
+        assert!(exact.truncated(), "the backend's own length finish is kept");
+    }
+}
@@ -1,14 +1,30 @@
+use crate::llm::guard::{self, Completion, FinishReason};
+use crate::llm::params::InferenceParams;
+use crate::llm::wire::{encode, ChatMessage};
+use crate::secrets::{self, Secret};
 use crate::types::Message;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
+use std::sync::{Arc, OnceLock};
 use thiserror::Error;
-use std::time::Duration;
 
 #[derive(Debug, Clone)]
 pub struct LlamaClient {
-    client: Client,
+    pub(crate) client: Client,
     pub endpoint: String,
     pub model: String,
+    /// Sent with every request; unset fields keep the server's defaults.
+    pub params: InferenceParams,
+    /// Secret sent as the bearer token: `llm` (`agent secrets set llm`) when
+    /// the endpoint is `llm_endpoint`, a remote's own secret for `remote:`
+    /// models, otherwise none. Looked up on the first request.
+    pub secret: Option<String>,
+    /// A reply cut off mid-stream is retried from where it stopped.
+    pub resume: bool,
+    /// Whether the model reads images, when config says; otherwise the
+    /// server is asked when the provider starts.
+    pub vision: Option<bool>,
+    probed: Arc<OnceLock<bool>>,
 }
 
 #[derive(Error, Debug)]
@@ -17,18 +33,73 @@ pub enum LlmError {
     Http(#[from] reqwest::Error),
     #[error("invalid response")]
     InvalidResponse,
+    #[error("could not attach image: {0}")]
+    Image(String),
+    #[error("request not sent")]
+    Offline(#[source] crate::errors::ConfigError),
+    #[error("reply stopped after {0} bytes")]
+    Interrupted(usize),
+    /// 429, with the seconds `Retry-After` asked for.
+    #[error("rate limited")]
+    RateLimited(Option<u64>),
 }
 
-#[derive(Serialize)]
-struct ChatRequest<'a> {
-    model: &'a str,
-    messages: &'a [LLMsg<'a>],
+impl LlmError {
+    /// Whether trying the request again may succeed: the connection
+    /// failed or dropped, or the server was busy or failing.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            LlmError::Http(e) => {
+                e.is_connect() || e.is_timeout() || e.is_body() || e.status().is_some_and(|s| s.as_u16() == 429 || s.is_server_error())
+            }
+            LlmError::Interrupted(_) | LlmError::RateLimited(_) => true,
+            _ => false,
+        }
+    }
+}
+
+impl From<LlmError> for crate::errors::ProviderError {
+    fn from(error: LlmError) -> Self {
+        use crate::errors::ProviderError;
+        match error {
+            LlmError::Http(e) if e.is_timeout() => ProviderError::Timeout,
+            LlmError::Http(e) => match e.status() {
+                Some(s) if s.as_u16() == 429 => ProviderError::RateLimited { retry_after: None },
+                Some(s) if s.is_client_error() => ProviderError::BadRequest { detail: e.to_string() },
+                _ => ProviderError::Unavailable(e.to_string()),
+            },
+            LlmError::RateLimited(retry_after) => ProviderError::RateLimited { retry_after },
+            LlmError::Image(detail) => ProviderError::BadRequest { detail },
+            e => ProviderError::Unavailable(e.to_string()),
+        }
+    }
+}
+
+/// A try that failed, with what of the reply had arrived.
+struct Failed {
+    error: LlmError,
+    partial: String,
+}
+
+impl From<LlmError> for Failed {
+    fn from(error: LlmError) -> Self {
+        Self { error, partial: String::new() }
+    }
+}
+
+impl From<reqwest::Error> for Failed {
+    fn from(error: reqwest::Error) -> Self {
+        LlmError::Http(error).into()
+    }
 }
 
 #[derive(Serialize)]
-struct LLMsg<'a> {
-    role: &'a str,
-    content: &'a str,
+struct ChatRequest<'a> {
+    model: &'a str,
+    messages: &'a [ChatMessage],
+    #[serde(flatten)]
+    params: &'a InferenceParams,
+    stream: bool,
 }
 
 #[derive(Deserialize)]
@@ -39,6 +110,8 @@ struct ChatResponse {
 #[derive(Deserialize)]
 struct Choice {
     message: Option<RespMsg>,
+    #[serde(default)]
+    finish_reason: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -47,53 +120,162 @@ struct RespMsg {
 }
 
 impl LlamaClient {
+    /// A client for `model` at `endpoint`, or at its named remote when the
+    /// model is a `remote:<name>/<model>` spec.
     pub fn new(endpoint: impl Into<String>, model: impl Into<String>) -> Self {
-        let client = Client::builder()
-            .timeout(Duration::from_secs(60))
-            .build()
-            .unwrap_or_else(|_| Client::new());
-        Self {
-            client,
+        let mut client = Self {
+            client: crate::net::client(crate::net::LLM),
             endpoint: endpoint.into(),
             model: model.into(),
+            params: InferenceParams::default(),
+            secret: None,
+            resume: true,
+            vision: None,
+            probed: Arc::default(),
+        };
+        if secrets::llm_key_allowed(&client.endpoint) {
+            client.secret = Some(secrets::LLM_API_KEY.to_string());
+        }
+        match crate::llm::remote::resolve(&client.model) {
+            Some(Ok(target)) => {
+                client.secret = Some(target.secret);
+                client.endpoint = target.endpoint;
+                client.model = target.model;
+                client.resume = target.resume;
+                client.vision = target.vision;
+            }
+            Some(Err(e)) => log::warn!("{}", e),
+            None => {}
+        }
+        client
+    }
+
+    pub fn with_params(mut self, params: InferenceParams) -> Self {
+        self.params = params;
+        self
+    }
+
+    /// This client asking for `model`: same endpoint, params and key, unless
+    /// `model` names a remote, which brings its own.
+    pub fn with_model(&self, model: &str) -> Self {
+        let mut client = Self::new(self.endpoint.clone(), model).with_params(self.params.clone());
+        if client.endpoint == self.endpoint {
+            client.secret = self.secret.clone();
+            client.resume = self.resume;
+            client.vision = self.vision;
         }
+        client
+    }
+
+    /// Whether the server says its model reads images: llama.cpp's `/props`
+    /// lists `modalities`. False when it does not answer.
+    async fn probe_vision(&self) -> bool {
+        let url = format!("{}/props", self.endpoint.trim_end_matches('/'));
+        if crate::net::check(&url).is_err() {
+            return false;
+        }
+        let mut req = self.client.get(&url);
+        if let Some(key) = self.api_key().await {
+            req = req.bearer_auth(key.expose());
+        }
+        let props: Option<serde_json::Value> = match req.send().await.and_then(|r| r.error_for_status()) {
+            Ok(resp) => resp.json().await.ok(),
+            Err(_) => None,
+        };
+        props.and_then(|p| p.pointer("/modalities/vision")?.as_bool()).unwrap_or(false)
+    }
+
+    /// The bearer token for this client's endpoint, if it has one.
+    pub(crate) async fn api_key(&self) -> Option<Secret> {
+        secrets::lookup(self.secret.as_deref()?).await
     }
 
     pub async fn request(&self, messages: &[Message]) -> Result<String, LlmError> {
-        let msgs: Vec<LLMsg<'_>> = messages
-            .iter()
-            .map(|m| LLMsg {
-                role: &m.role,
-                content: &m.content,
-            })
-            .collect();
+        let result = self.complete(messages).await.map(|c| c.text);
+        crate::transcript::record("remote", &self.model, messages, result.as_deref().map_err(|e| e.to_string()));
+        result
+    }
+
+    /// The reply with `stop` and `max_tokens` enforced, and whether it was
+    /// cut short. Transient failures are retried per `[chat_retry]`; see
+    /// `llm::resume`.
+    pub async fn complete(&self, messages: &[Message]) -> Result<Completion, LlmError> {
+        let retry = crate::llm::resume::current();
+        let mut key = crate::llm::resume::new_key();
+        let mut partial = String::new();
+        let mut attempt = 1;
+        loop {
+            let mut sent = messages.to_vec();
+            if !partial.is_empty() {
+                sent.push(Message::new("assistant", &partial));
+            }
+            match self.attempt(&sent, &key).await {
+                Ok((text, finish)) => return Ok(guard::enforce(&(partial + &text), finish, &self.params)),
+                Err(failed) if attempt < retry.max_attempts && failed.error.is_transient() => {
+                    // a longer prefix is a different request, with a key of its own
+                    if self.resume && !failed.partial.is_empty() {
+                        partial.push_str(&failed.partial);
+                        key = crate::llm::resume::new_key();
+                    }
+                    log::warn!("chat try {} of {} failed, retrying: {}", attempt, retry.max_attempts, failed.error);
+                    attempt += 1;
+                    tokio::time::sleep(retry.delay(attempt)).await;
+                }
+                Err(failed) => return Err(failed.error),
+            }
+        }
+    }
+
+    /// One try: the reply and why it ended. Servers that do not stream
+    /// answer with the whole reply at once.
+    async fn attempt(&self, messages: &[Message], key: &str) -> Result<(String, FinishReason), Failed> {
+        let msgs = encode(messages).map_err(|e| LlmError::Image(e.to_string()))?;
 
         let body = ChatRequest {
             model: &self.model,
             messages: &msgs,
+            params: &self.params,
+            stream: true,
         };
 
         let url = format!("{}/v1/chat/completions", self.endpoint.trim_end_matches('/'));
+        crate::net::check(&url).map_err(LlmError::Offline)?;
 
-        let resp = self
-            .client
-            .post(&url)
-            .json(&body)
-            .send()
-            .await?
-            .error_for_status()?
-            .json::<ChatResponse>()
-            .await
-            .map_err(LlmError::Http)?;
-
-        let text = resp
-            .choices
-            .get(0)
-            .and_then(|c| c.message.as_ref())
-            .and_then(|m| m.content.clone())
-            .ok_or(LlmError::InvalidResponse)?;
-
-        Ok(text)
+        let mut req = self.client.post(&url).header("Idempotency-Key", key).json(&body);
+        if let Some(key) = self.api_key().await {
+            req = req.bearer_auth(key.expose());
+        }
+        if let Some(id) = crate::request_id::current() {
+            req = req.header(crate::request_id::HEADER, id);
+        }
+        let resp = req.send().await?;
+        if resp.status().as_u16() == 429 {
+            let retry_after = resp.headers().get(reqwest::header::RETRY_AFTER).and_then(|v| v.to_str().ok()?.trim().parse().ok());
+            return Err(LlmError::RateLimited(retry_after).into());
+        }
+        let mut resp = resp.error_for_status()?;
+        let streamed = resp.headers().get(reqwest::header::CONTENT_TYPE).and_then(|v| v.to_str().ok()).is_some_and(|t| t.starts_with("text/event-stream"));
+        let (text, finish_reason) = if streamed {
+            let mut stream = crate::llm::resume::Stream::default();
+            loop {
+                match resp.chunk().await {
+                    Ok(Some(bytes)) => stream.feed(&bytes),
+                    Ok(None) if stream.finished() => break,
+                    Ok(None) => return Err(Failed { error: LlmError::Interrupted(stream.text.len()), partial: stream.text }),
+                    Err(e) => return Err(Failed { error: LlmError::Http(e), partial: stream.text }),
+                }
+            }
+            (stream.text, stream.finish_reason)
+        } else {
+            let resp = resp.json::<ChatResponse>().await?;
+            let choice = resp.choices.into_iter().next().ok_or(LlmError::InvalidResponse)?;
+            (choice.message.and_then(|m| m.content).ok_or(LlmError::InvalidResponse)?, choice.finish_reason)
+        };
+        let finish = match finish_reason.as_deref() {
+            Some("length") => FinishReason::Length,
+            _ => FinishReason::Stop,
+        };
+        Ok((text, finish))
     }
 }
 
@@ -108,4 +290,130 @@ impl Llm for LlamaClient {
             Err(e) => Err(anyhow::anyhow!(e.to_string())),
         }
     }
+
+    async fn chat_constrained(&self, messages: &[Message], schema: &serde_json::Value) -> anyhow::Result<String> {
+        let mut params = self.params.clone();
+        params.grammar = Some(crate::llm::grammar::from_schema(schema));
+        Ok(self.clone().with_params(params).request(messages).await?)
+    }
+
+    fn for_model(&self, model: &str) -> Option<Arc<dyn Llm>> {
+        Some(Arc::new(self.with_model(model)))
+    }
+}
+
+/// Lets a remote OpenAI-compatible endpoint sit next to local providers,
+/// e.g. in the compare view.
+#[async_trait::async_trait]
+impl crate::models::server::Provider for LlamaClient {
+    fn name(&self) -> &'static str {
+        "remote"
+    }
+
+    fn model_id(&self) -> String {
+        self.model.clone()
+    }
+
+    fn metadata(&self) -> crate::models::server::ProviderMetadata {
+        crate::models::server::ProviderMetadata { provider: "remote", model: self.model.clone(), vision: self.supports_vision(), endpoint: Some(self.endpoint.clone()) }
+    }
+
+    /// Asks the server about vision once, unless config already says.
+    async fn start(&self) -> anyhow::Result<()> {
+        if self.vision.is_none() && self.probed.get().is_none() {
+            let _ = self.probed.set(self.probe_vision().await);
+        }
+        Ok(())
+    }
+
+    /// The remote's `vision` setting, else what the server said when this
+    /// started; images are refused until one of them says yes.
+    fn supports_vision(&self) -> bool {
+        self.vision.or_else(|| self.probed.get().copied()).unwrap_or(false)
+    }
+
+    async fn chat(&self, messages: &[Message]) -> Result<String, crate::errors::ProviderError> {
+        Ok(self.request(messages).await?)
+    }
+
+    async fn chat_with(&self, messages: &[Message], params: &InferenceParams) -> Result<String, crate::errors::ProviderError> {
+        Ok(self.clone().with_params(params.clone()).request(messages).await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// The headers and body of one request read off `conn`.
+    async fn read_request(conn: &mut tokio::net::TcpStream) -> String {
+        let mut raw = vec![];
+        let mut buf = [0u8; 4096];
+        loop {
+            let n = conn.read(&mut buf).await.unwrap();
+            raw.extend_from_slice(&buf[..n]);
+            let text = String::from_utf8_lossy(&raw).to_string();
+            if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                let length = head.lines().find_map(|l| l.to_ascii_lowercase().strip_prefix("content-length:").map(|v| v.trim().parse::<usize>().unwrap())).unwrap_or(0);
+                if body.len() >= length || n == 0 {
+                    return text;
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cut_off_stream_resumes_from_the_partial_reply() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let mut requests = vec![];
+            // the first try streams the start of the reply and drops the connection
+            let (mut conn, _) = listener.accept().await.unwrap();
+            requests.push(read_request(&mut conn).await);
+            let event = "data: {\"choices\":[{\"delta\":{\"content\":\"Hel\"}}]}\n\n";
+            let head = "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nContent-Length: 4096\r\n\r\n";
+            conn.write_all(format!("{}{}", head, event).as_bytes()).await.unwrap();
+            drop(conn);
+            // the retry gets the rest, without streaming
+            let (mut conn, _) = listener.accept().await.unwrap();
+            requests.push(read_request(&mut conn).await);
+            let body = r#"{"choices":[{"message":{"content":"lo"},"finish_reason":"stop"}]}"#;
+            let reply = format!("HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}", body.len(), body);
+            conn.write_all(reply.as_bytes()).await.unwrap();
+            requests
+        });
+
+        let client = LlamaClient::new(format!("http://{}", addr), "m");
+        let reply = client.complete(&[Message::new("user", "say hello")]).await.unwrap();
+        assert_eq!(reply.text, "Hello");
+        let requests = server.await.unwrap();
+        let key = |r: &str| r.lines().find_map(|l| l.to_ascii_lowercase().strip_prefix("idempotency-key:").map(|k| k.trim().to_string())).unwrap();
+        assert!(!requests[0].contains("\"assistant\"") && requests[0].contains("\"stream\":true"));
+        assert!(requests[1].contains(r#"{"role":"assistant","content":"Hel"}"#));
+        assert_ne!(key(&requests[0]), key(&requests[1]));
+    }
+
+    #[tokio::test]
+    async fn test_vision_is_asked_of_the_server_unless_configured() -> anyhow::Result<()> {
+        use crate::models::server::Provider;
+        let app = axum::Router::new().route("/props", axum::routing::get(|| async { axum::Json(serde_json::json!({"modalities": {"vision": true}})) }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let endpoint = format!("http://{}", listener.local_addr()?);
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let llava = LlamaClient::new(&endpoint, "llava");
+        assert!(!llava.supports_vision(), "unknown until started");
+        llava.start().await?;
+        assert!(llava.supports_vision() && llava.metadata().vision);
+
+        // no /props: a text-only server, or one that cannot say
+        let closed = LlamaClient::new("http://127.0.0.1:9", "text");
+        closed.start().await?;
+        assert!(!closed.supports_vision());
+        let configured = LlamaClient { vision: Some(true), ..LlamaClient::new("http://127.0.0.1:9", "gpt-x") };
+        assert!(configured.supports_vision());
+        Ok(())
+    }
 }
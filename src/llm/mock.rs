@@ -1,6 +1,10 @@
 use crate::types::Message;
 use crate::llm::Llm;
 use async_trait::async_trait;
+use parking_lot::Mutex;
+use regex::Regex;
+use serde::Deserialize;
+use std::path::Path;
 
 pub struct MockLlm {
     pub response: String,
@@ -18,3 +22,150 @@ impl Llm for MockLlm {
         Ok(self.response.clone())
     }
 }
+
+/// One scripted reply. Every matcher given must hold; a rule with none
+/// matches any call.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScriptRule {
+    /// 1-based call number.
+    #[serde(default)]
+    pub call: Option<usize>,
+    /// Regex searched in the last message of the prompt.
+    #[serde(default)]
+    pub prompt: Option<String>,
+    /// How many times the rule may fire; unlimited when unset.
+    #[serde(default)]
+    pub times: Option<usize>,
+    pub reply: String,
+}
+
+/// A fixture file:
+///
+/// ```yaml
+/// default: "LGTM"
+/// responses:
+///   - call: 1
+///     reply: "1. build\n2. test"
+///   - prompt: "(?i)classify the failure"
+///     reply: '{"cause": "flaky_test", "explanation": "timeout"}'
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Script {
+    #[serde(default)]
+    pub responses: Vec<ScriptRule>,
+    /// Reply when no rule matches; without one that call fails.
+    #[serde(default)]
+    pub default: Option<String>,
+}
+
+/// Deterministic `Llm` replaying a [`Script`]: the first matching rule
+/// answers each call, and every prompt is kept for assertions.
+pub struct ScriptedLlm {
+    rules: Vec<(ScriptRule, Option<Regex>)>,
+    default: Option<String>,
+    state: Mutex<ScriptState>,
+}
+
+#[derive(Default)]
+struct ScriptState {
+    fired: Vec<usize>,
+    calls: Vec<Vec<Message>>,
+}
+
+impl ScriptedLlm {
+    pub fn new(script: Script) -> anyhow::Result<Self> {
+        let rules = script
+            .responses
+            .into_iter()
+            .map(|rule| {
+                let re = rule.prompt.as_deref().map(Regex::new).transpose()?;
+                Ok((rule, re))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let fired = vec![0; rules.len()];
+        Ok(Self { rules, default: script.default, state: Mutex::new(ScriptState { fired, calls: vec![] }) })
+    }
+
+    pub fn from_yaml(yaml: &str) -> anyhow::Result<Self> {
+        Self::new(serde_yaml::from_str(yaml)?)
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        Self::from_yaml(&std::fs::read_to_string(path)?).map_err(|e| anyhow::anyhow!("fixture {}: {}", path.display(), e))
+    }
+
+    /// Replies `reply` to every call, in order, then fails.
+    pub fn replies(replies: &[&str]) -> Self {
+        let responses = replies
+            .iter()
+            .enumerate()
+            .map(|(i, r)| ScriptRule { call: Some(i + 1), prompt: None, times: None, reply: r.to_string() })
+            .collect();
+        Self::new(Script { responses, default: None }).expect("no regexes to compile")
+    }
+
+    /// Every prompt received so far, oldest first.
+    pub fn calls(&self) -> Vec<Vec<Message>> {
+        self.state.lock().calls.clone()
+    }
+}
+
+#[async_trait]
+impl Llm for ScriptedLlm {
+    async fn chat(&self, messages: &[Message]) -> anyhow::Result<String> {
+        let mut state = self.state.lock();
+        state.calls.push(messages.to_vec());
+        let n = state.calls.len();
+        let last = messages.last().map(|m| m.content.as_str()).unwrap_or_default();
+        let hit = self.rules.iter().enumerate().find(|(i, (rule, re))| {
+            rule.call.map(|c| c == n).unwrap_or(true)
+                && re.as_ref().map(|re| re.is_match(last)).unwrap_or(true)
+                && rule.times.map(|t| state.fired[*i] < t).unwrap_or(true)
+        });
+        match hit {
+            Some((i, (rule, _))) => {
+                state.fired[i] += 1;
+                Ok(rule.reply.clone())
+            }
+            None => self
+                .default
+                .clone()
+                .ok_or_else(|| anyhow::anyhow!("no scripted reply for call {}: {:?}", n, last)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_scripted_matchers() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let fixture = dir.path().join("fixture.yaml");
+        std::fs::write(
+            &fixture,
+            r#"
+responses:
+  - call: 2
+    reply: second
+  - prompt: "(?i)^plan for"
+    times: 1
+    reply: a plan
+default: fallback
+"#,
+        )?;
+        let llm = ScriptedLlm::load(&fixture)?;
+        let ask = |text: &str| vec![Message::new("user", text)];
+        assert_eq!(llm.chat(&ask("Plan for goal: x")).await?, "a plan");
+        assert_eq!(llm.chat(&ask("Plan for goal: y")).await?, "second");
+        assert_eq!(llm.chat(&ask("Plan for goal: z")).await?, "fallback");
+        assert_eq!(llm.calls().len(), 3);
+
+        let strict = ScriptedLlm::replies(&["only"]);
+        assert_eq!(strict.chat(&ask("a")).await?, "only");
+        assert!(strict.chat(&ask("b")).await.is_err());
+        assert!(ScriptedLlm::from_yaml("responses:\n  - prompt: '('\n    reply: x\n").is_err());
+        Ok(())
+    }
+}
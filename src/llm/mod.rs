@@ -1,10 +1,45 @@
+pub mod autocomplete;
+pub mod embed;
+pub mod grammar;
+pub mod guard;
 pub mod llama;
 pub mod mock;
+pub mod params;
+pub mod remote;
+pub mod resume;
+pub mod structured;
+pub mod wire;
 
 use crate::types::Message;
 use async_trait::async_trait;
+use futures_util::StreamExt;
+
+/// Requests a batch keeps in flight on backends that answer one at a time.
+pub const BATCH_CONCURRENCY: usize = 4;
 
 #[async_trait]
 pub trait Llm: Send + Sync {
     async fn chat(&self, messages: &[Message]) -> anyhow::Result<String>;
-}
\ No newline at end of file
+
+    /// Chat with the reply constrained to `schema`. Backends that support
+    /// grammar-constrained sampling override this; the default is an
+    /// unconstrained call and callers validate afterwards.
+    async fn chat_constrained(&self, messages: &[Message], _schema: &serde_json::Value) -> anyhow::Result<String> {
+        self.chat(messages).await
+    }
+
+    /// Replies to several independent conversations, in order. Backends that
+    /// answer many prompts in one call override this; the default sends them
+    /// concurrently, [`BATCH_CONCURRENCY`] at a time.
+    async fn chat_batch(&self, batch: &[Vec<Message>]) -> Vec<anyhow::Result<String>> {
+        let calls: Vec<_> = batch.iter().map(|messages| self.chat(messages)).collect();
+        futures_util::stream::iter(calls).buffered(BATCH_CONCURRENCY).collect().await
+    }
+
+    /// This backend asking for `model` instead, keeping its endpoint, params
+    /// and credentials. `None` for backends that serve a single model
+    /// (mocks, scripts); callers keep using them as they are.
+    fn for_model(&self, _model: &str) -> Option<std::sync::Arc<dyn Llm>> {
+        None
+    }
+}
@@ -0,0 +1,265 @@
+//! Sampling overrides: `[inference]` in config.toml, overlaid by the
+//! session's `/params`. Unset fields leave the backend's own defaults in
+//! place; set ones travel in the chat-completions request body or are
+//! applied to a [`NativeConfig`]. `max_tokens` and `stop` are also enforced
+//! on the reply by [`crate::llm::guard`].
+
+use crate::models::NativeConfig;
+use serde::{Deserialize, Serialize};
+
+/// `(name, min, max, step)` of each parameter, in display order.
+pub const RANGES: [(&str, f32, f32, f32); 4] = [
+    ("temperature", 0.0, 2.0, 0.05),
+    ("top_p", 0.0, 1.0, 0.05),
+    ("max_tokens", 16.0, 8192.0, 16.0),
+    ("repeat_penalty", 1.0, 2.0, 0.05),
+];
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct InferenceParams {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    /// Also read as OpenAI's newer `max_completion_tokens`.
+    #[serde(default, alias = "max_completion_tokens", skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repeat_penalty: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub presence_penalty: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frequency_penalty: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    /// Generation ends before the first of these. A single string is read
+    /// as one stop sequence, as OpenAI allows.
+    #[serde(default, deserialize_with = "one_or_many", skip_serializing_if = "Vec::is_empty")]
+    pub stop: Vec<String>,
+    /// llama.cpp GBNF grammar the reply must match; see `llm::grammar`.
+    /// Providers without grammar support ignore it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub grammar: Option<String>,
+}
+
+impl InferenceParams {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Current override of `name`, if any.
+    pub fn get(&self, name: &str) -> Option<f32> {
+        match name {
+            "temperature" => self.temperature,
+            "top_p" => self.top_p,
+            "max_tokens" => self.max_tokens.map(|v| v as f32),
+            "repeat_penalty" => self.repeat_penalty,
+            _ => None,
+        }
+    }
+
+    /// `self` with the fields set in `over` replaced.
+    pub fn overlay(&self, over: &Self) -> Self {
+        Self {
+            temperature: over.temperature.or(self.temperature),
+            top_p: over.top_p.or(self.top_p),
+            max_tokens: over.max_tokens.or(self.max_tokens),
+            repeat_penalty: over.repeat_penalty.or(self.repeat_penalty),
+            top_k: over.top_k.or(self.top_k),
+            presence_penalty: over.presence_penalty.or(self.presence_penalty),
+            frequency_penalty: over.frequency_penalty.or(self.frequency_penalty),
+            seed: over.seed.or(self.seed),
+            stop: if over.stop.is_empty() { self.stop.clone() } else { over.stop.clone() },
+            grammar: over.grammar.clone().or_else(|| self.grammar.clone()),
+        }
+    }
+
+    /// `/params set <name> <value>`; the value must be within [`RANGES`].
+    /// `set stop <text>` adds a stop sequence, with `\n` for a newline.
+    pub fn set(&mut self, name: &str, value: &str) -> anyhow::Result<()> {
+        if name == "stop" {
+            self.stop.push(value.replace("\\n", "\n"));
+            return Ok(());
+        }
+        let (_, min, max, _) = range(name)?;
+        let v: f32 = value.parse().map_err(|_| anyhow::anyhow!("{} is not a number", value))?;
+        if !(min..=max).contains(&v) {
+            return Err(anyhow::anyhow!("{} must be between {} and {}", name, min, max));
+        }
+        self.put(name, v);
+        Ok(())
+    }
+
+    /// Clear one override, or all of them for `None`.
+    pub fn reset(&mut self, name: Option<&str>) -> anyhow::Result<()> {
+        match name {
+            None => *self = Self::default(),
+            Some("stop") => self.stop.clear(),
+            Some(name) => {
+                range(name)?;
+                match name {
+                    "temperature" => self.temperature = None,
+                    "top_p" => self.top_p = None,
+                    "max_tokens" => self.max_tokens = None,
+                    _ => self.repeat_penalty = None,
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Move `name` by `steps` slider steps, starting from the native
+    /// default when it is unset.
+    pub fn nudge(&mut self, name: &str, steps: i32) {
+        let Ok((_, min, max, step)) = range(name) else { return };
+        let current = self.get(name).unwrap_or_else(|| default_value(name));
+        // round to the step so repeated nudges do not drift
+        let v = ((current + step * steps as f32) / step).round() * step;
+        self.put(name, v.clamp(min, max));
+    }
+
+    fn put(&mut self, name: &str, v: f32) {
+        match name {
+            "temperature" => self.temperature = Some(v),
+            "top_p" => self.top_p = Some(v),
+            "max_tokens" => self.max_tokens = Some(v.round() as u32),
+            _ => self.repeat_penalty = Some(v),
+        }
+    }
+
+    /// `config` with the overrides applied.
+    pub fn apply(&self, config: &NativeConfig) -> NativeConfig {
+        let mut config = config.clone();
+        if let Some(v) = self.temperature {
+            config.temperature = v;
+        }
+        if let Some(v) = self.top_p {
+            config.top_p = v;
+        }
+        if let Some(v) = self.max_tokens {
+            config.max_tokens = v as usize;
+        }
+        if let Some(v) = self.repeat_penalty {
+            config.repeat_penalty = v;
+        }
+        if let Some(v) = self.top_k {
+            config.top_k = v as usize;
+        }
+        config
+    }
+
+    /// Short form for the status bar, e.g. `temp 0.2 · max 256`.
+    pub fn summary(&self) -> String {
+        let mut parts = vec![];
+        if let Some(v) = self.temperature {
+            parts.push(format!("temp {}", display(v)));
+        }
+        if let Some(v) = self.top_p {
+            parts.push(format!("top_p {}", display(v)));
+        }
+        if let Some(v) = self.max_tokens {
+            parts.push(format!("max {}", v));
+        }
+        if let Some(v) = self.repeat_penalty {
+            parts.push(format!("rep {}", display(v)));
+        }
+        if let Some(v) = self.top_k {
+            parts.push(format!("top_k {}", v));
+        }
+        if let Some(v) = self.seed {
+            parts.push(format!("seed {}", v));
+        }
+        if !self.stop.is_empty() {
+            parts.push(format!("stop {:?}", self.stop));
+        }
+        if self.grammar.is_some() {
+            parts.push("grammar".into());
+        }
+        if parts.is_empty() {
+            "default".into()
+        } else {
+            parts.join(" · ")
+        }
+    }
+}
+
+/// `"a"`, `["a", "b"]` or `null`.
+fn one_or_many<'de, D: serde::Deserializer<'de>>(de: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+    Ok(match Option::<OneOrMany>::deserialize(de)? {
+        Some(OneOrMany::One(s)) => vec![s],
+        Some(OneOrMany::Many(v)) => v,
+        None => vec![],
+    })
+}
+
+fn range(name: &str) -> anyhow::Result<(&'static str, f32, f32, f32)> {
+    RANGES.iter().copied().find(|(n, ..)| *n == name).ok_or_else(|| {
+        let names: Vec<&str> = RANGES.iter().map(|(n, ..)| *n).collect();
+        anyhow::anyhow!("unknown parameter {}; expected one of {}", name, names.join(", "))
+    })
+}
+
+/// `v` to two decimals without trailing zeros.
+pub fn display(v: f32) -> String {
+    let s = format!("{:.2}", v);
+    s.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+/// The value used when `name` is not overridden.
+pub fn default_value(name: &str) -> f32 {
+    let d = NativeConfig::default();
+    match name {
+        "temperature" => d.temperature,
+        "top_p" => d.top_p,
+        "max_tokens" => d.max_tokens as f32,
+        _ => d.repeat_penalty,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overrides_validate_and_apply() -> anyhow::Result<()> {
+        let mut p = InferenceParams::default();
+        p.set("temperature", "0.2")?;
+        p.set("max_tokens", "256")?;
+        assert!(p.set("top_p", "1.5").is_err());
+        assert!(p.set("top_k", "5").is_err());
+        assert_eq!(p.summary(), "temp 0.2 · max 256");
+        assert_eq!(serde_json::to_value(&p)?, serde_json::json!({"temperature": 0.2f32, "max_tokens": 256}));
+
+        let config = p.apply(&NativeConfig::default());
+        assert_eq!((config.temperature, config.max_tokens, config.top_p), (0.2, 256, 0.9));
+
+        p.nudge("repeat_penalty", -1);
+        assert!((p.repeat_penalty.unwrap_or_default() - 1.05).abs() < 1e-4);
+        assert_eq!(p.summary(), "temp 0.2 · max 256 · rep 1.05");
+        p.nudge("repeat_penalty", -10);
+        assert_eq!(p.repeat_penalty, Some(1.0));
+        p.reset(Some("temperature"))?;
+        p.reset(Some("max_tokens"))?;
+        p.reset(Some("repeat_penalty"))?;
+        assert!(p.is_empty());
+
+        p.set("stop", "\\n\\nUser:")?;
+        let config = InferenceParams { max_tokens: Some(512), temperature: Some(0.7), ..Default::default() };
+        let merged = config.overlay(&InferenceParams { temperature: Some(0.1), ..p });
+        assert_eq!((merged.temperature, merged.max_tokens), (Some(0.1), Some(512)));
+        assert_eq!(merged.stop, vec!["\n\nUser:".to_string()]);
+
+        // OpenAI's spellings
+        let sent: InferenceParams = serde_json::from_str(r#"{"stop": "END", "max_completion_tokens": 8, "top_k": 5}"#)?;
+        assert_eq!((sent.stop.clone(), sent.max_tokens, sent.apply(&NativeConfig::default()).top_k), (vec!["END".to_string()], Some(8), 5));
+        Ok(())
+    }
+}
@@ -0,0 +1,155 @@
+//! Named remote endpoints. `[remotes.<name>]` in config.toml gives an
+//! OpenAI-compatible server a name, its key and a default model:
+//!
+//! ```toml
+//! [remotes.work]
+//! url = "https://llm.example.com"
+//! default_model = "gpt-x"
+//! ```
+//!
+//! A model written `remote:work/gpt-x` (or `remote:work` for the default
+//! model) is then sent to that server, wherever a model name is accepted:
+//! `/model`, `llm_model`, the router's tiers. The key is the secret
+//! `remote.<name>` unless `secret` names another one.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::{OnceLock, RwLock};
+
+/// Model names starting with this go to a named remote.
+pub const PREFIX: &str = "remote:";
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RemoteEndpoint {
+    /// Base URL, without `/v1`.
+    pub url: String,
+    /// Secret holding the bearer token; `remote.<name>` when unset.
+    pub secret: Option<String>,
+    /// Model used for `remote:<name>` without a model.
+    pub default_model: Option<String>,
+    /// The server continues a trailing assistant message, so a reply cut
+    /// off mid-stream is retried from where it stopped (see `llm::resume`).
+    pub resume: bool,
+    /// Whether its models read images. Unset, the server is asked (llama.cpp's
+    /// `/props`) and images are refused when it cannot say.
+    pub vision: Option<bool>,
+}
+
+/// Where a `remote:` model is sent.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Target {
+    pub endpoint: String,
+    pub model: String,
+    pub secret: String,
+    pub resume: bool,
+    pub vision: Option<bool>,
+}
+
+fn remotes() -> &'static RwLock<BTreeMap<String, RemoteEndpoint>> {
+    static REMOTES: OnceLock<RwLock<BTreeMap<String, RemoteEndpoint>>> = OnceLock::new();
+    REMOTES.get_or_init(Default::default)
+}
+
+/// Use `configured` for clients built from now on; called at startup and
+/// when config.toml is reloaded.
+pub fn configure(configured: BTreeMap<String, RemoteEndpoint>) {
+    *remotes().write().unwrap_or_else(|e| e.into_inner()) = configured;
+}
+
+/// `(name, model)` of a `remote:<name>[/<model>]` spec.
+pub fn parse(spec: &str) -> Option<(&str, Option<&str>)> {
+    let rest = spec.strip_prefix(PREFIX)?;
+    match rest.split_once('/') {
+        Some((name, model)) => Some((name, Some(model).filter(|m| !m.is_empty()))),
+        None => Some((rest, None)),
+    }
+}
+
+/// The endpoint, model and secret for `spec` among `configured`.
+pub fn target(configured: &BTreeMap<String, RemoteEndpoint>, spec: &str) -> anyhow::Result<Target> {
+    let (name, model) = parse(spec).ok_or_else(|| anyhow::anyhow!("{} is not a {}<name>/<model> model", spec, PREFIX))?;
+    let Some(remote) = configured.get(name) else {
+        let known: Vec<&str> = configured.keys().map(String::as_str).collect();
+        anyhow::bail!("no remote named {:?} (configured: {})", name, if known.is_empty() { "none".into() } else { known.join(", ") });
+    };
+    let model = model
+        .map(str::to_string)
+        .or_else(|| remote.default_model.clone())
+        .ok_or_else(|| anyhow::anyhow!("remote {} has no default_model; use {}{}/<model>", name, PREFIX, name))?;
+    Ok(Target {
+        endpoint: remote.url.trim_end_matches('/').to_string(),
+        model,
+        secret: remote.secret.clone().unwrap_or_else(|| format!("remote.{}", name)),
+        resume: remote.resume,
+        vision: remote.vision,
+    })
+}
+
+/// `target` among the configured remotes; `None` for models that are not
+/// `remote:` specs.
+pub fn resolve(spec: &str) -> Option<anyhow::Result<Target>> {
+    parse(spec)?;
+    Some(target(&remotes().read().unwrap_or_else(|e| e.into_inner()), spec))
+}
+
+/// Model ids the remote serves, from its `/v1/models`.
+pub async fn list_models(name: &str, remote: &RemoteEndpoint) -> anyhow::Result<Vec<String>> {
+    let url = format!("{}/v1/models", remote.url.trim_end_matches('/'));
+    crate::net::check(&url)?;
+    let mut req = crate::net::client(crate::net::LLM).get(&url);
+    let secret = remote.secret.clone().unwrap_or_else(|| format!("remote.{}", name));
+    if let Some(key) = crate::secrets::lookup(&secret).await {
+        req = req.bearer_auth(key.expose());
+    }
+    let body: serde_json::Value = req.send().await?.error_for_status()?.json().await?;
+    Ok(ids(&body))
+}
+
+/// The `id`s of an OpenAI-style model list.
+fn ids(body: &serde_json::Value) -> Vec<String> {
+    body["data"].as_array().into_iter().flatten().filter_map(|m| m["id"].as_str().map(str::to_string)).collect()
+}
+
+/// Local and remote models in one list.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Catalog {
+    /// `(model, where it is)`; remote models are written as `remote:` specs.
+    pub models: Vec<(String, String)>,
+    /// `(remote, error)` for each remote whose list could not be fetched.
+    pub failed: Vec<(String, String)>,
+}
+
+/// `local` models followed by those of every configured remote.
+pub async fn catalog(local: Vec<String>, configured: &BTreeMap<String, RemoteEndpoint>) -> Catalog {
+    let mut catalog = Catalog { models: local.into_iter().map(|m| (m, "local".to_string())).collect(), failed: vec![] };
+    for (name, remote) in configured {
+        match list_models(name, remote).await {
+            Ok(ids) => catalog.models.extend(ids.into_iter().map(|id| (format!("{}{}/{}", PREFIX, name, id), remote.url.clone()))),
+            Err(e) => catalog.failed.push((name.clone(), e.to_string())),
+        }
+    }
+    catalog
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_specs_resolve_against_named_remotes() {
+        let configured: BTreeMap<String, RemoteEndpoint> =
+            toml::from_str("[work]\nurl = \"https://llm.example.com/\"\ndefault_model = \"gpt-x\"\n[lab]\nurl = \"http://lab:8000\"\nsecret = \"lab-key\"\n").unwrap();
+        assert_eq!(parse("remote:work/gpt-x/mini"), Some(("work", Some("gpt-x/mini"))));
+        assert_eq!(parse("local.gguf"), None);
+
+        let t = target(&configured, "remote:work").unwrap();
+        assert_eq!(t, Target { endpoint: "https://llm.example.com".into(), model: "gpt-x".into(), secret: "remote.work".into(), resume: false, vision: None });
+        assert_eq!(target(&configured, "remote:lab/qwen").unwrap().secret, "lab-key");
+        assert!(target(&configured, "remote:lab").unwrap_err().to_string().contains("no default_model"));
+        assert!(target(&configured, "remote:home/x").unwrap_err().to_string().contains("configured: lab, work"));
+
+        let body = serde_json::json!({"object": "list", "data": [{"id": "gpt-x"}, {"id": "gpt-y"}]});
+        assert_eq!(ids(&body), vec!["gpt-x", "gpt-y"]);
+    }
+}
@@ -0,0 +1,127 @@
+//! Retrying chats that fail on the way. `[chat_retry]` in config.toml sets
+//! how often a chat request is tried when the connection drops, the server
+//! answers 429 or 5xx, or a streamed reply stops before it is finished.
+//!
+//! Replies are streamed, so a reply cut off mid-way has a partial text.
+//! Endpoints that continue a trailing assistant message (llama.cpp, and
+//! remotes with `resume = true`) get the request again with that partial as
+//! the assistant's prefix and only produce the rest; others start over.
+//! Every request carries an `Idempotency-Key`, the same for each try of the
+//! same body, so the local model server answers a retry of a reply it
+//! already produced from memory instead of generating it twice.
+
+use serde::{Deserialize, Serialize};
+use std::sync::{OnceLock, RwLock};
+use std::time::Duration;
+
+/// `[chat_retry]` in config.toml.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChatRetryConfig {
+    /// Total tries including the first; 1 disables retries.
+    pub max_attempts: u32,
+    /// Wait before the second try, doubled for each one after.
+    pub backoff_ms: u64,
+}
+
+impl Default for ChatRetryConfig {
+    fn default() -> Self {
+        Self { max_attempts: 3, backoff_ms: 500 }
+    }
+}
+
+impl ChatRetryConfig {
+    /// Wait before try `attempt` (1-based; the first try never waits).
+    pub fn delay(&self, attempt: u32) -> Duration {
+        if attempt <= 1 {
+            return Duration::ZERO;
+        }
+        Duration::from_millis(self.backoff_ms.saturating_mul(1 << (attempt - 2).min(16)))
+    }
+}
+
+fn config() -> &'static RwLock<ChatRetryConfig> {
+    static CONFIG: OnceLock<RwLock<ChatRetryConfig>> = OnceLock::new();
+    CONFIG.get_or_init(Default::default)
+}
+
+/// Use `configured` from now on; called at startup and when config.toml is
+/// reloaded.
+pub fn configure(configured: ChatRetryConfig) {
+    *config().write().unwrap_or_else(|e| e.into_inner()) = configured;
+}
+
+pub fn current() -> ChatRetryConfig {
+    config().read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// A new `Idempotency-Key`.
+pub fn new_key() -> String {
+    uuid::Uuid::new_v4().simple().to_string()
+}
+
+/// An OpenAI-style server-sent event stream of chat deltas, read as it
+/// arrives.
+#[derive(Debug, Default)]
+pub struct Stream {
+    buf: Vec<u8>,
+    /// The reply so far.
+    pub text: String,
+    pub finish_reason: Option<String>,
+    /// `data: [DONE]` arrived.
+    pub done: bool,
+}
+
+impl Stream {
+    pub fn feed(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+        while let Some(end) = self.buf.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.buf.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            let Some(data) = line.trim().strip_prefix("data:").map(str::trim) else { continue };
+            if data == "[DONE]" {
+                self.done = true;
+                continue;
+            }
+            let Ok(event) = serde_json::from_str::<serde_json::Value>(data) else { continue };
+            let choice = &event["choices"][0];
+            if let Some(delta) = choice["delta"]["content"].as_str() {
+                self.text.push_str(delta);
+            }
+            if let Some(reason) = choice["finish_reason"].as_str() {
+                self.finish_reason = Some(reason.to_string());
+            }
+        }
+    }
+
+    /// Whether the server said the reply is complete.
+    pub fn finished(&self) -> bool {
+        self.done || self.finish_reason.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stream_reads_deltas_split_anywhere() {
+        let events = "data: {\"choices\":[{\"delta\":{\"role\":\"assistant\"}}]}\n\n\
+                      data: {\"choices\":[{\"delta\":{\"content\":\"Hel\"}}]}\n\n\
+                      : keep-alive\n\n\
+                      data: {\"choices\":[{\"delta\":{\"content\":\"lo\"},\"finish_reason\":null}]}\n\n\
+                      data: {\"choices\":[{\"delta\":{},\"finish_reason\":\"length\"}]}\n\n\
+                      data: [DONE]\n\n";
+        let mut stream = Stream::default();
+        for chunk in events.as_bytes().chunks(7) {
+            stream.feed(chunk);
+            if stream.text == "Hel" {
+                assert!(!stream.finished());
+            }
+        }
+        assert_eq!((stream.text.as_str(), stream.finish_reason.as_deref(), stream.done), ("Hello", Some("length"), true));
+
+        let retry = ChatRetryConfig::default();
+        assert_eq!([retry.delay(1), retry.delay(2), retry.delay(3)], [Duration::ZERO, Duration::from_millis(500), Duration::from_millis(1000)]);
+    }
+}
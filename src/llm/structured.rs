@@ -0,0 +1,55 @@
+use crate::llm::Llm;
+use crate::tools::schema::{extract_json, validate};
+use crate::types::Message;
+use serde_json::Value;
+
+/// Get a reply from `llm` that conforms to `schema`.
+///
+/// Constrained backends should succeed on the first call; for the rest the
+/// reply is validated and the model is asked to repair it up to `max_repairs`
+/// times with the validation errors.
+pub async fn complete(llm: &dyn Llm, messages: &[Message], schema: &Value, max_repairs: usize) -> anyhow::Result<Value> {
+    let mut messages = messages.to_vec();
+    messages.insert(
+        0,
+        Message::new("system", format!("Reply only with JSON that conforms to this schema:\n{}", schema)),
+    );
+    let mut last_errors = vec![];
+    for attempt in 0..=max_repairs {
+        let resp = llm.chat_constrained(&messages, schema).await?;
+        let errors = match extract_json(&resp) {
+            Some(v) => match validate(schema, &v) {
+                Ok(()) => return Ok(v),
+                Err(errs) => errs,
+            },
+            None => vec!["$: response is not valid JSON".to_string()],
+        };
+        tracing::warn!(attempt, "structured output invalid, repairing: {}", errors.join("; "));
+        messages.push(Message::new("assistant", resp));
+        messages.push(Message::new(
+            "user",
+            format!("That reply does not match the schema:\n- {}\nReply with corrected JSON only.", errors.join("\n- ")),
+        ));
+        last_errors = errors;
+    }
+    Err(anyhow::anyhow!("response did not match schema: {}", last_errors.join("; ")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::mock::MockLlm;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_complete_validates_reply() {
+        let schema = json!({"type": "object", "properties": {"steps": {"type": "array"}}, "required": ["steps"]});
+        let ok = MockLlm::new("```json\n{\"steps\": [\"a\"]}\n```");
+        let v = complete(&ok, &[Message::new("user", "plan")], &schema, 0).await.unwrap();
+        assert_eq!(v, json!({"steps": ["a"]}));
+
+        let bad = MockLlm::new("{\"step\": 1}");
+        let err = complete(&bad, &[Message::new("user", "plan")], &schema, 1).await.unwrap_err();
+        assert!(err.to_string().contains("missing required property `steps`"));
+    }
+}
@@ -0,0 +1,96 @@
+//! OpenAI chat-completions message format, shared by `LlamaClient` and the
+//! model server. Text-only messages travel as a plain string; messages with
+//! images use the content-parts array vision models expect.
+
+use crate::types::{ImagePart, Message};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub role: String,
+    pub content: ChatContent,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ChatContent {
+    Text(String),
+    Parts(Vec<ContentPart>),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentPart {
+    Text { text: String },
+    ImageUrl { image_url: ImageUrl },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImageUrl {
+    pub url: String,
+}
+
+impl ChatMessage {
+    /// Fails when an attached image file cannot be read.
+    pub fn from_message(m: &Message) -> anyhow::Result<Self> {
+        if m.images.is_empty() {
+            return Ok(Self { role: m.role.clone(), content: ChatContent::Text(m.full_text()) });
+        }
+        let mut parts = vec![ContentPart::Text { text: m.full_text() }];
+        for image in &m.images {
+            parts.push(ContentPart::ImageUrl { image_url: ImageUrl { url: image.to_url()? } });
+        }
+        Ok(Self { role: m.role.clone(), content: ChatContent::Parts(parts) })
+    }
+
+    pub fn into_message(self) -> Message {
+        match self.content {
+            ChatContent::Text(text) => Message::new(self.role, text),
+            ChatContent::Parts(parts) => {
+                let mut text = vec![];
+                let mut images = vec![];
+                for part in parts {
+                    match part {
+                        ContentPart::Text { text: t } => text.push(t),
+                        ContentPart::ImageUrl { image_url } => images.push(ImagePart::from_url(&image_url.url)),
+                    }
+                }
+                Message::new(self.role, text.join("\n")).with_images(images)
+            }
+        }
+    }
+}
+
+pub fn encode(messages: &[Message]) -> anyhow::Result<Vec<ChatMessage>> {
+    messages.iter().map(ChatMessage::from_message).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_images_round_trip_as_content_parts() -> anyhow::Result<()> {
+        let td = tempfile::tempdir()?;
+        let png = td.path().join("shot.png");
+        std::fs::write(&png, b"png bytes")?;
+        let messages = vec![
+            Message::new("system", "be brief"),
+            Message::new("user", "what is this?").with_images(vec![ImagePart::Path { path: png }, ImagePart::Url { url: "https://example.com/a.jpg".into() }]),
+        ];
+        let wire = serde_json::to_value(encode(&messages)?)?;
+        assert_eq!(wire[0]["content"], "be brief");
+        assert_eq!(wire[1]["content"][0], serde_json::json!({"type": "text", "text": "what is this?"}));
+        assert_eq!(wire[1]["content"][1]["image_url"]["url"], "data:image/png;base64,cG5nIGJ5dGVz");
+
+        let back: Vec<ChatMessage> = serde_json::from_value(wire)?;
+        let user = back[1].clone().into_message();
+        assert_eq!(user.content, "what is this?");
+        assert_eq!(user.images[0], ImagePart::Base64 { media_type: "image/png".into(), data: "cG5nIGJ5dGVz".into() });
+        assert_eq!(user.images[1].label(), "[image: https://example.com/a.jpg]");
+
+        let missing = Message::new("user", "x").with_images(vec![ImagePart::Path { path: td.path().join("gone.png") }]);
+        assert!(ChatMessage::from_message(&missing).is_err());
+        Ok(())
+    }
+}
@@ -8,8 +8,23 @@ mod tui;
 mod types;
 mod config;
 mod models;
+mod workspace;
+mod tasks;
+mod editor;
+mod secrets;
+mod net;
+mod doctor;
+mod errors;
+mod update;
+mod stats;
+mod transcript;
+mod symbols;
+mod backup;
+mod paths;
+mod request_id;
+mod startup;
 
-use clap::Parser;
+use clap::{CommandFactory, Parser};
 use env_logger::Env;
 use log::info;
 use tokio::signal;
@@ -18,16 +33,146 @@ use crate::cli::commands::Commands;
 use crate::models::server::Provider;
 
 #[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
+async fn main() {
+    env_logger::Builder::from_env(Env::default().default_filter_or("info"))
+        .format(|buf, record| {
+            use std::io::Write;
+            let id = request_id::current().map(|id| format!(" request={}", id)).unwrap_or_default();
+            writeln!(buf, "[{} {:<5} {}{}] {}", buf.timestamp(), buf.default_styled_level(record.level()), record.target(), id, record.args())
+        })
+        .init();
 
     let cli = Commands::parse();
+    startup::begin(cli.profile_startup);
+    let format = cli.error_format;
+    let migrated = startup::span("migrate state");
+    match paths::migrate() {
+        Ok(notes) => notes.iter().for_each(|n| info!("{}", n)),
+        Err(e) => log::warn!("state left in the old layout: {:#}", e),
+    }
+    drop(migrated);
+    let result = run(cli).await;
+    if let Some(profile) = startup::report() {
+        eprint!("{}", profile);
+    }
+    if let Err(e) = result {
+        let report = errors::ErrorReport::from_anyhow(&e);
+        match format {
+            crate::cli::commands::ErrorFormat::Text => eprintln!("{}", report.render()),
+            crate::cli::commands::ErrorFormat::Json => eprintln!("{}", serde_json::json!({ "error": report })),
+        }
+        std::process::exit(1);
+    }
+}
+
+async fn run(cli: Commands) -> anyhow::Result<()> {
+    let configured = startup::span("load config");
+    let startup = crate::config::RuntimeConfig::load();
+    net::configure(startup.network.clone());
+    crate::llm::remote::configure(startup.remotes.clone());
+    crate::secrets::scope_llm_key(&startup.llm_endpoint);
+    crate::llm::resume::configure(startup.chat_retry.clone());
+    if cli.offline {
+        net::force_offline();
+    }
+    stats::configure(&startup.stats, &crate::paths::home());
+    crate::tools::audit::configure(&startup.tools, &crate::paths::home());
+    if let Err(e) = transcript::configure(&startup.transcript, &crate::paths::home()) {
+        log::warn!("transcripts off: {}", e);
+    }
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(name) = stats::cli_command(&Commands::command(), &args) {
+        stats::record("cli", &name, vec![]);
+    }
+    update::cleanup();
+    drop(configured);
 
     match cli.command {
-        crate::cli::commands::Cmd::Run { goal } => {
-            info!("Starting agent run: {}", goal);
+        crate::cli::commands::Cmd::Run { goal, resume, schema, pipeline, dry_run, yes, watch, on_change } => {
             let mut sa = agent::super_agent::SuperAgent::new();
-            sa.run_goal(goal).await?;
+            sa.profile = load_project_profile();
+            let store = crate::tasks::TaskStore::new(crate::paths::home());
+            sa.tasks = Some(store.clone());
+            let cfg = crate::config::RuntimeConfig::load();
+            if let Ok(cwd) = std::env::current_dir() {
+                sa.file_policy = crate::tools::policy::FileAccessPolicy::new(cwd, &cfg.files);
+            }
+            sa.retry = cfg.retry_for(None);
+            sa.notes_budget = cfg.memory.notes_budget;
+            sa.approve_plan = !yes && cfg.approve_plan_for(&sa.profile);
+            sa.clarify_questions = if yes { 0 } else { cfg.clarify_questions };
+            sa.router = crate::agent::router::Router::new(cfg.router.clone());
+            if let Some(name) = pipeline {
+                sa.pipeline = Some(crate::graph::pipeline::PipelineStore::new(store.home()).load(&name, &sa.tool_names())?);
+            }
+            if let Some(path) = schema {
+                sa.output_schema = Some(serde_json::from_str(&std::fs::read_to_string(path)?)?);
+            }
+            if let Some(e) = crate::models::setup::detect(sa.endpoint(), &cfg.model_dir).await.error() {
+                return Err(e.into());
+            }
+            let report = match (resume, goal) {
+                (Some(id), _) => {
+                    let cp = crate::tasks::checkpoint::CheckpointStore::new(store.home()).load(&id)?;
+                    info!("Resuming run {} after {} completed node(s)", cp.task.id, cp.completed.len());
+                    let task = cp.task.clone();
+                    transcript::begin_run(&task.id);
+                    if let Some(profile) = &task.agent_profile {
+                        sa.system_prompt = cfg.personas.get(profile).cloned();
+                    }
+                    sa.retry = task.retry.clone().unwrap_or_else(|| cfg.retry_for(task.agent_profile.as_deref()));
+                    sa.pipeline = cp.pipeline.clone();
+                    sa.checkpoint = Some(cp);
+                    sa.run_task(task).await
+                }
+                (None, Some(goal)) if watch => {
+                    let on_change = match on_change.or_else(|| cfg.watch.pipeline.clone()) {
+                        Some(name) => Some(crate::graph::pipeline::PipelineStore::new(store.home()).load(&name, &sa.tool_names())?),
+                        None => None,
+                    };
+                    let root = std::env::current_dir()?;
+                    info!("Watching {} for changes: {}", root.display(), goal);
+                    let mut first = true;
+                    agent::watch::run(sa, goal, on_change, &root, &cfg.watch, Default::default(), |line, result| {
+                        if first {
+                            first = false;
+                            if let Ok(report) = result {
+                                println!("{}", report);
+                            }
+                        }
+                        println!("{}", line);
+                    })
+                    .await;
+                    return Ok(());
+                }
+                (None, Some(goal)) if dry_run => {
+                    info!("Starting dry run: {}", goal);
+                    let mut task = agent::super_agent::SuperAgent::goal_task(goal);
+                    task.title = format!("dry run: {}", task.title);
+                    transcript::begin_run(&task.id);
+                    sa.dry_run = Some(Default::default());
+                    let report = sa.run_task(task).await?;
+                    println!("{}", report);
+                    eprint!("Run it for real? [y/N] ");
+                    let mut answer = String::new();
+                    std::io::stdin().read_line(&mut answer)?;
+                    if !answer.trim().eq_ignore_ascii_case("y") {
+                        println!("nothing was changed; run again without --dry-run to apply");
+                        return Ok(());
+                    }
+                    // apply what was reviewed rather than planning the goal again
+                    let proposals = sa.dry_run.take().unwrap_or_default();
+                    sa.apply_proposals(&proposals)
+                }
+                (None, Some(goal)) => {
+                    info!("Starting agent run: {}", goal);
+                    let task = agent::super_agent::SuperAgent::goal_task(goal);
+                    transcript::begin_run(&task.id);
+                    sa.run_task(task).await
+                }
+                (None, None) => unreachable!("clap requires --goal or --resume"),
+            };
+            println!("{}", report?);
         }
         crate::cli::commands::Cmd::Chat => {
             println!("chat mode not implemented yet; start TUI with `agent tui` or use `agent run --goal`.");
@@ -40,10 +185,22 @@ async fn main() -> anyhow::Result<()> {
         crate::cli::commands::Cmd::Logs => {
             println!("Logs are written to stdout via env_logger.");
         }
-        crate::cli::commands::Cmd::Tui => {
+        crate::cli::commands::Cmd::Tui { session } => {
             info!("Starting TUI...");
-            let config = crate::config::RuntimeConfig::load();
-            let mut app = tui::app::TuiApp::new(config)?;
+            let config = startup;
+            let _lock = {
+                let _span = startup::span("instance lock");
+                crate::models::instance::InstanceLock::acquire(&config.model_dir, "tui")?
+            };
+            stats::record("session", "tui", stats::features(&config));
+            let profile = {
+                let _span = startup::span("project profile");
+                load_project_profile()
+            };
+            let mut app = {
+                let _span = startup::span("tui init");
+                tui::app::TuiApp::new(config, profile)?.with_session(session)
+            };
             tokio::select! {
                 res = app.run() => { res?; }
                 _ = signal::ctrl_c() => {
@@ -51,6 +208,113 @@ async fn main() -> anyhow::Result<()> {
                 }
             }
         }
+        crate::cli::commands::Cmd::Pipelines { cmd } => match cmd {
+            crate::cli::commands::PipelineCmd::List => {
+                let tools = agent::super_agent::SuperAgent::new().tool_names();
+                let store = crate::graph::pipeline::PipelineStore::new(&crate::paths::home());
+                for (name, pipeline) in store.list(&tools) {
+                    match pipeline {
+                        Ok(p) => {
+                            let order = p.order().unwrap_or_default().join(" → ");
+                            println!("{:<16} {}  {}", name, order, p.description);
+                        }
+                        Err(e) => println!("{:<16} invalid: {}", name, e),
+                    }
+                }
+            }
+        },
+        crate::cli::commands::Cmd::Skills { cmd } => {
+            let cfg = crate::config::RuntimeConfig::load();
+            let market = agent::marketplace::Marketplace::new(cfg.skills, agent::plugin_engine::default_skills_dir());
+            match cmd {
+                crate::cli::commands::SkillCmd::Search { term } => {
+                    let found = market.search(&term).await?;
+                    if found.is_empty() {
+                        println!("no skills match {:?}", term);
+                    }
+                    for l in found {
+                        let installed = l.installed.as_deref().map(|v| format!(" (installed {})", v)).unwrap_or_default();
+                        println!("{:<20} {:<10} {:<16} {}{}", l.entry.name, l.entry.version, l.status(), l.entry.description, installed);
+                    }
+                }
+                crate::cli::commands::SkillCmd::Install { name } => {
+                    let (entry, verified) = market.install(&name).await?;
+                    println!("Installed {} {} ({})", entry.name, entry.version, verified.describe());
+                }
+            }
+        }
+        crate::cli::commands::Cmd::Tasks { cmd } => {
+            let store = crate::tasks::TaskStore::new(crate::paths::home());
+            match cmd {
+                crate::cli::commands::TaskCmd::List => {
+                    let tree = store.tree();
+                    let all: Vec<_> = tree.iter().map(|(_, t)| t.clone()).collect();
+                    for (depth, t) in tree {
+                        let progress = crate::tasks::rollup(&t, &all);
+                        let bar = crate::tui::charts::bar_text(progress, 10);
+                        println!("{}  {:<9} {} {:>3}% {}{}", &t.id[..8], format!("{:?}", t.status), bar, (progress * 100.0).round(), "  ".repeat(depth), t.title);
+                    }
+                }
+                crate::cli::commands::TaskCmd::Cancel { id } => {
+                    let cancelled = store.cancel(&id)?;
+                    println!("Cancelled {} task(s)", cancelled.len());
+                }
+                crate::cli::commands::TaskCmd::New { template, title, run } => {
+                    let cfg = crate::config::RuntimeConfig::load();
+                    let templates = crate::tasks::templates::TemplateStore::new(store.home());
+                    let mut task = templates.get(&template, &cfg.templates)?.instantiate();
+                    if let Some(title) = title {
+                        task.title = title;
+                    }
+                    store.save(&task)?;
+                    println!("Created task {} from template {}", task.id, template);
+                    if run {
+                        let mut sa = agent::super_agent::SuperAgent::new();
+                        sa.profile = load_project_profile();
+                        sa.tasks = Some(store.clone());
+                        sa.notes_budget = cfg.memory.notes_budget;
+                        sa.approve_plan = cfg.approve_plan_for(&sa.profile);
+                        sa.clarify_questions = cfg.clarify_questions;
+                        if let Ok(cwd) = std::env::current_dir() {
+                            sa.file_policy = crate::tools::policy::FileAccessPolicy::new(cwd, &cfg.files);
+                        }
+                        if let Some(profile) = &task.agent_profile {
+                            sa.system_prompt = cfg.personas.get(profile).cloned();
+                            if sa.system_prompt.is_none() {
+                                log::warn!("agent profile {} is not a configured persona", profile);
+                            }
+                        }
+                        sa.retry = cfg.retry_for(task.agent_profile.as_deref());
+                        sa.router = crate::agent::router::Router::new(cfg.router.clone());
+                        println!("{}", sa.run_task(task).await?);
+                    }
+                }
+                crate::cli::commands::TaskCmd::Templates => {
+                    let cfg = crate::config::RuntimeConfig::load();
+                    let templates = crate::tasks::templates::TemplateStore::new(store.home());
+                    for (name, t) in templates.all(&cfg.templates) {
+                        println!("{:<16} {} ({} steps)", name, t.title, t.steps.len());
+                    }
+                }
+                crate::cli::commands::TaskCmd::SaveTemplate { id, name } => {
+                    let task = store.find(&id)?;
+                    let templates = crate::tasks::templates::TemplateStore::new(store.home());
+                    let path = templates.save(&name, &crate::tasks::templates::TaskTemplate::from_task(&task))?;
+                    println!("Saved template {} -> {}", name, path.display());
+                }
+                crate::cli::commands::TaskCmd::Artifacts { id, open } => {
+                    let task = store.find(&id)?;
+                    let dir = crate::tasks::artifacts::dir(store.home(), &task.id);
+                    println!("{} ({:?}) — {}", task.title, task.status, dir.display());
+                    for f in crate::tasks::artifacts::list(store.home(), &task.id) {
+                        println!("  {}", f.display());
+                    }
+                    if open {
+                        crate::tasks::artifacts::open(&dir)?;
+                    }
+                }
+            }
+        }
         crate::cli::commands::Cmd::Models { cmd } => {
             let cfg = crate::config::RuntimeConfig::load();
             let mgr = crate::models::ModelManager::new(Some(cfg.model_dir.clone()))?;
@@ -59,6 +323,9 @@ async fn main() -> anyhow::Result<()> {
                     let ms = mgr.discover()?;
                     println!("Models:");
                     for m in ms { println!("- {} ({}, {} bytes)", m.name, m.format, m.size); }
+                    let remote = crate::llm::remote::catalog(vec![], &cfg.remotes).await;
+                    for (model, url) in &remote.models { println!("- {} ({})", model, url); }
+                    for (name, e) in &remote.failed { eprintln!("remote {}: {}", name, e); }
                 }
                 crate::cli::commands::ModelCmd::Import { path } => {
                     let p = std::path::Path::new(&path);
@@ -69,49 +336,105 @@ async fn main() -> anyhow::Result<()> {
                     mgr.remove(&name)?;
                     println!("Removed model {}", name);
                 }
-                crate::cli::commands::ModelCmd::Serve { action, model } => {
-                    let mgr = std::sync::Arc::new(mgr);
-                    let server = crate::models::ModelServer::new(mgr.clone(), cfg.model_server_addr);
-                    if action == "start" {
-                        server.start_local_server().await?;
-                        if let Some(mn) = model {
-                            // try to start a real Llama provider if binary available, else fallback to mock
-                            let ms = mgr.discover()?;
-                            if let Some(minfo) = ms.into_iter().find(|m| m.name == mn) {
-                                let lp = crate::models::server::LlamaProvider::new(None, minfo.path.clone(), cfg.model_server_addr);
-                                match lp.start().await {
-                                    Ok(_) => {
-                                        server.register_provider(&mn, std::sync::Arc::new(lp)).await?;
-                                        println!("Model server started and llama provider registered for {}", mn);
-                                    }
-                                    Err(e) => {
-                                        // fallback mock
-                                        server.register_mock_for_model(&mn).await?;
-                                        println!("Started server but llama provider failed; registered mock for {}: {}", mn, e);
-                                    }
-                                }
-                            } else {
-                                println!("model {} not found", mn);
+                crate::cli::commands::ModelCmd::Verify { name, record } => {
+                    use crate::models::integrity::{self, Verdict};
+                    let mut bad = 0;
+                    for (path, verdict) in integrity::verify_dir(&mgr.dir, name.as_deref())? {
+                        let file = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+                        match verdict {
+                            Ok(Verdict::Unrecorded) if record => {
+                                integrity::record(&path)?;
+                                println!("{:<40} ok, checksum recorded", file);
+                            }
+                            Ok(verdict) => {
+                                bad += verdict.is_bad() as usize;
+                                println!("{:<40} {}", file, verdict.describe());
+                            }
+                            Err(e) => {
+                                bad += 1;
+                                println!("{:<40} cannot read: {}", file, e);
                             }
-                        } else {
-                            println!("Model server started on {}", server.addr);
                         }
-                    } else if action == "stop" {
-                        println!("stop action not implemented in v0.1");
+                    }
+                    if bad > 0 {
+                        anyhow::bail!("{} model file(s) failed verification", bad);
+                    }
+                }
+                crate::cli::commands::ModelCmd::Serve { action, model } => {
+                    use crate::models::instance;
+                    let home = crate::paths::home();
+                    let mut model = model;
+                    match action.as_str() {
+                        "start" => {}
+                        "stop" | "restart" => match instance::request_stop(&home).await? {
+                            Some(stopped) => {
+                                println!("Stopped the model server on {}", stopped.addr);
+                                model = model.or(stopped.model);
+                            }
+                            None if action == "stop" => println!("No model server is running"),
+                            None => {}
+                        },
+                        other => anyhow::bail!("unknown action {:?}: use start, stop or restart", other),
+                    }
+                    if action == "stop" {
+                        return Ok(());
+                    }
+                    let warmup = crate::models::preload::Warmup::spawn(&cfg.models.preload, cfg.model_dir.clone());
+                    let (hosted, said) = instance::host(&cfg, model, warmup, crate::models::server::DRAIN + std::time::Duration::from_secs(2), None).await?;
+                    if !cfg.models.preload.is_empty() {
+                        println!("preloading {}", cfg.models.preload.join(", "));
+                    }
+                    println!("{}", said);
+                    if let Some(warning) = crate::models::access::exposure_warning(hosted.handle.addr) {
+                        eprintln!("warning: {}", warning);
+                    }
+                    println!("Ctrl-C or `agent models serve stop` to stop");
+                    tokio::select! {
+                        r = signal::ctrl_c() => r?,
+                        _ = hosted.handle.stop_requested() => {}
+                    }
+                    println!("Stopping: letting requests in flight finish (up to {:?})", crate::models::server::DRAIN);
+                    if !hosted.shutdown().await {
+                        println!("Some requests were still running and were cut off");
+                    }
+                }
+                crate::cli::commands::ModelCmd::Bench { name, prompt_file, provider, save } => {
+                    let minfo = mgr
+                        .discover()?
+                        .into_iter()
+                        .find(|m| m.name == name)
+                        .ok_or_else(|| errors::ProviderError::ModelNotFound(name.clone()))?;
+                    let seed = match prompt_file {
+                        Some(p) => std::fs::read_to_string(p)?,
+                        None => "Summarize the design of a local-first multi-agent runtime.".to_string(),
+                    };
+                    let p: Box<dyn Provider> = match provider.as_deref() {
+                        None | Some("native") => Box::new(crate::models::NativeProvider::new(minfo.path.clone())),
+                        Some("llama") => Box::new(crate::models::server::LlamaProvider::new(None, minfo.path.clone(), cfg.model_server_addr)),
+                        Some(other) => return Err(errors::ProviderError::UnknownProvider(other.to_string()).into()),
+                    };
+                    let result = crate::models::bench::run(&name, p.as_ref(), &seed).await?;
+                    result.print_table();
+                    if save {
+                        let dir = crate::paths::home().join("bench");
+                        let path = result.save(&dir)?;
+                        println!("\nSaved {}\n", path.display());
+                        crate::models::bench::print_comparison(&crate::models::bench::BenchResult::load_all(&dir));
                     }
                 }
                 crate::cli::commands::ModelCmd::Install { tool } => {
-                    if tool.as_deref() == Some("llama") || tool.is_none() {
+                    if net::offline() {
+                        println!("Offline mode: the install script downloads llama.cpp, so it was not run. See docs/INSTALL.md to install it by hand.");
+                    } else if tool.as_deref() == Some("llama") || tool.is_none() {
                         // try to run helper script if present
-                        let script = std::path::Path::new("./scripts/install_llama.sh");
-                        if script.exists() {
-                            println!("Running install script: {:?}", script);
-                            match std::process::Command::new("/bin/sh").arg(script).status() {
+                        if let Some(mut installer) = crate::models::platform::installer(std::path::Path::new("./scripts")) {
+                            println!("Running install script: {:?}", installer);
+                            match installer.status() {
                                 Ok(s) => { println!("Installer exited: {}", s); }
                                 Err(e) => { println!("Failed to run installer: {}", e); }
                             }
                         } else {
-                            println!("No install script found at ./scripts/install_llama.sh. See docs/INSTALL.md for guidance.");
+                            println!("No install script found in ./scripts (install_llama.sh, or install_llama.ps1 on Windows). See docs/INSTALL.md for guidance.");
                         }
                     } else {
                         println!("Unknown tool: {:?}. Supported: llama", tool);
@@ -119,6 +442,428 @@ async fn main() -> anyhow::Result<()> {
                 }
             }
         }
+        crate::cli::commands::Cmd::Metrics => {
+            let cfg = crate::config::RuntimeConfig::load();
+            let url = format!("http://{}/metrics", cfg.server_addr());
+            match net::client(net::MODEL_SERVER).get(&url).send().await {
+                Ok(resp) => print!("{}", resp.text().await?),
+                Err(e) => println!("could not reach model server at {}: {}", url, e),
+            }
+        }
+        crate::cli::commands::Cmd::Paths => print!("{}", paths::describe()),
+        crate::cli::commands::Cmd::EditorServe { reference } => {
+            if reference {
+                print!("{}", editor::protocol::reference());
+                return Ok(());
+            }
+            let cfg = crate::config::RuntimeConfig::load();
+            let root = std::env::current_dir()?;
+            let endpoint = cfg.chat_endpoint();
+            let llm = std::sync::Arc::new(crate::llm::llama::LlamaClient::new(&endpoint, &cfg.llm_model));
+            let server = editor::EditorServer::new(llm, crate::tools::policy::FileAccessPolicy::new(root, &cfg.files))
+                .with_agent(&endpoint, &cfg.llm_model, crate::tasks::TaskStore::new(crate::paths::home()));
+            server.serve(tokio::io::BufReader::new(tokio::io::stdin()), tokio::io::stdout()).await?;
+        }
+        crate::cli::commands::Cmd::Doctor { cmd: None, json } => {
+            let report = doctor::run(crate::config::RuntimeConfig::path().as_deref()).await;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                print!("{}", report.render());
+            }
+            if !report.ok {
+                std::process::exit(1);
+            }
+        }
+        crate::cli::commands::Cmd::Doctor { cmd: Some(crate::cli::commands::DoctorCmd::Network), .. } => {
+            let cfg = crate::config::RuntimeConfig::load();
+            let mut targets = vec![
+                (net::LLM, format!("{}/v1/models", cfg.chat_endpoint().trim_end_matches('/'))),
+                (net::MODEL_SERVER, format!("http://{}/v1/models", cfg.server_addr())),
+            ];
+            if cfg.skills.index_url.starts_with("http://") || cfg.skills.index_url.starts_with("https://") {
+                targets.push((net::SKILLS, cfg.skills.index_url.clone()));
+            }
+            let mut failed = 0;
+            for (provider, url) in targets {
+                println!("{} ({})", provider, url);
+                for check in net::diagnose(provider, &url).await {
+                    failed += usize::from(!check.ok);
+                    println!("  {} {:<8} {}", if check.ok { "ok  " } else { "FAIL" }, check.name, check.detail);
+                }
+            }
+            if failed > 0 {
+                anyhow::bail!("{} network check(s) failed", failed);
+            }
+        }
+        crate::cli::commands::Cmd::SelfUpdate { channel, check, yes } => {
+            let cfg = crate::config::RuntimeConfig::load();
+            let releases = update::releases(&cfg.update).await?;
+            let Some(release) = update::pick(&releases, channel, update::CURRENT, update::BUILD_TAG) else {
+                println!("agent {} is up to date ({:?} channel)", update::installed(), channel);
+                return Ok(());
+            };
+            println!("agent {} → {}\n", update::installed(), release.name.as_deref().unwrap_or(&release.tag_name));
+            println!("{}\n", release.body.as_deref().unwrap_or("(no release notes)").trim());
+            if check {
+                return Ok(());
+            }
+            if !yes {
+                eprint!("Apply this update? [y/N] ");
+                let mut answer = String::new();
+                std::io::stdin().read_line(&mut answer)?;
+                if !answer.trim().eq_ignore_ascii_case("y") {
+                    println!("not updated");
+                    return Ok(());
+                }
+            }
+            let (bytes, verified) = update::download(&cfg.update, &release).await?;
+            let saved = backup::pre_upgrade_path(&crate::paths::home(), &release.tag_name);
+            let manifest = backup::create(&backup::Places::current(), &saved).map_err(|e| anyhow::anyhow!("not updated: the pre-upgrade backup failed: {:#}", e))?;
+            println!("backed up {} to {}", manifest.describe(), saved.display());
+            update::swap(&std::env::current_exe()?, &bytes)?;
+            println!("updated to {} ({})", release.tag_name, verified.describe());
+        }
+        crate::cli::commands::Cmd::Stats { cmd } => {
+            let home = crate::paths::home();
+            match cmd {
+                None => println!("{}", stats::summary(&stats::load(&home))),
+                Some(crate::cli::commands::StatsCmd::On) | Some(crate::cli::commands::StatsCmd::Off) => {
+                    let mut cfg = crate::config::RuntimeConfig::load();
+                    cfg.stats.enabled = matches!(cmd, Some(crate::cli::commands::StatsCmd::On));
+                    cfg.save()?;
+                    if cfg.stats.enabled {
+                        println!("recording command names and configured features to {}; nothing leaves this machine", stats::path(&home).display());
+                    } else {
+                        println!("usage stats off");
+                    }
+                }
+                Some(crate::cli::commands::StatsCmd::Export { out }) => {
+                    let raw = std::fs::read_to_string(stats::path(&home)).unwrap_or_default();
+                    match out {
+                        Some(p) => {
+                            std::fs::write(&p, raw)?;
+                            println!("exported to {}", p.display());
+                        }
+                        None => print!("{}", raw),
+                    }
+                }
+                Some(crate::cli::commands::StatsCmd::Clear) => {
+                    match std::fs::remove_file(stats::path(&home)) {
+                        Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+                        _ => println!("usage stats cleared"),
+                    }
+                }
+            }
+        }
+        crate::cli::commands::Cmd::Tools { cmd } => match cmd {
+            crate::cli::commands::ToolCmd::List => {
+                for name in agent::super_agent::SuperAgent::new().tool_names() {
+                    let state = if crate::tools::audit::enabled(&name) { "" } else { "  (disabled)" };
+                    println!("{}{}", name, state);
+                }
+            }
+            crate::cli::commands::ToolCmd::Stats => {
+                print!("{}", crate::tools::audit::summary(&crate::tools::audit::load(&crate::paths::home())));
+            }
+        },
+        crate::cli::commands::Cmd::Trace { cmd: crate::cli::commands::TraceCmd::ExportScript { run, output } } => {
+            let store = crate::tasks::TaskStore::new(crate::paths::home());
+            let task = store.find(&run)?;
+            let script = crate::tasks::trace::script(&task, &crate::tasks::trace::load(store.home(), &task.id)?);
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, &script)?;
+                    #[cfg(unix)]
+                    {
+                        use std::os::unix::fs::PermissionsExt;
+                        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))?;
+                    }
+                    println!("wrote {}", path.display());
+                }
+                None => print!("{}", script),
+            }
+        }
+        crate::cli::commands::Cmd::Backup { cmd } => match cmd {
+            crate::cli::commands::BackupCmd::Create { path } => {
+                let manifest = backup::create(&backup::Places::current(), &path)?;
+                println!("wrote {}: {}", path.display(), manifest.describe());
+            }
+            crate::cli::commands::BackupCmd::Restore { path, yes } => {
+                let (manifest, _) = backup::verify(&path)?;
+                println!("{} verified: {}", path.display(), manifest.describe());
+                for f in &manifest.files {
+                    println!("  {}", f.path);
+                }
+                if !yes {
+                    eprint!("Overwrite these files with the backup? [y/N] ");
+                    let mut answer = String::new();
+                    std::io::stdin().read_line(&mut answer)?;
+                    if !answer.trim().eq_ignore_ascii_case("y") {
+                        println!("nothing restored");
+                        return Ok(());
+                    }
+                }
+                backup::restore(&backup::Places::current(), &path)?;
+                println!("restored {} file(s)", manifest.files.len());
+            }
+        },
+        crate::cli::commands::Cmd::Memory { cmd: crate::cli::commands::MemoryCmd::Prune { dry_run, yes } } => {
+            use crate::memory::retention;
+            let config = crate::config::RuntimeConfig::load().memory.retention;
+            if !config.is_set() {
+                println!("no limits set; see [memory.retention] in config.toml");
+                return Ok(());
+            }
+            let doomed = retention::plan(retention::scan(&crate::paths::home()), &config, crate::tasks::now_secs());
+            if doomed.is_empty() {
+                println!("{}", retention::report(&doomed));
+                return Ok(());
+            }
+            println!("{} {}", if dry_run { "Would delete" } else { "Will delete" }, retention::report(&doomed));
+            if dry_run {
+                return Ok(());
+            }
+            if !yes {
+                eprint!("Delete these? [y/N] ");
+                let mut answer = String::new();
+                std::io::stdin().read_line(&mut answer)?;
+                if !answer.trim().eq_ignore_ascii_case("y") {
+                    println!("nothing deleted");
+                    return Ok(());
+                }
+            }
+            retention::apply(&doomed)?;
+            println!("deleted {} item(s)", doomed.len());
+        }
+        crate::cli::commands::Cmd::Sessions { cmd } => {
+            let store = crate::memory::sessions::SessionStore::new(&crate::paths::home());
+            match cmd {
+                crate::cli::commands::SessionCmd::List => {
+                    let sessions = store.list();
+                    if sessions.is_empty() {
+                        println!("no sessions in {}", store.dir().display());
+                    }
+                    for s in sessions {
+                        let source = s.source.map(|f| format!(" (from {})", f)).unwrap_or_default();
+                        println!("{}  {:>4} message(s)  {}{}", &s.id[..8], s.messages.len(), s.title, source);
+                    }
+                }
+                crate::cli::commands::SessionCmd::Import { format, file } => {
+                    let raw = std::fs::read_to_string(&file).map_err(|e| anyhow::anyhow!("cannot read {}: {}", file.display(), e))?;
+                    for s in crate::memory::sessions::import(format, &raw)? {
+                        store.save(&s)?;
+                        println!("imported {}  {:>4} message(s)  {}", &s.id[..8], s.messages.len(), s.title);
+                    }
+                    println!("continue one in the TUI with /sessions open <id>");
+                }
+            }
+        }
+        crate::cli::commands::Cmd::Transcript { run, json } => {
+            let home = crate::paths::home();
+            match run {
+                None => {
+                    let runs = transcript::runs(&home);
+                    if runs.is_empty() {
+                        println!("no transcripts in {}", transcript::dir(&home).display());
+                    }
+                    for (id, modified, entries) in runs {
+                        println!("{:<24} {:>5} exchange(s)  last written {}", id, entries, modified);
+                    }
+                }
+                Some(id) => {
+                    let (run, entries) = transcript::find(&home, &id)?;
+                    if json {
+                        for e in &entries {
+                            println!("{}", serde_json::to_string(e)?);
+                        }
+                    } else {
+                        print!("{}", transcript::render(&run, &entries));
+                    }
+                }
+            }
+        }
+        crate::cli::commands::Cmd::Review { base, fix, json } => {
+            let cfg = crate::config::RuntimeConfig::load();
+            let base = base.unwrap_or_else(|| cfg.review.base.clone());
+            let root = std::env::current_dir()?;
+            let diff = agent::review::git_diff(&root, &base)?;
+            if diff.trim().is_empty() {
+                println!("no changes against {}", base);
+                return Ok(());
+            }
+            let llm = crate::llm::llama::LlamaClient::new(cfg.chat_endpoint(), cfg.llm_model.clone());
+            let findings = agent::review::review(&llm, &diff, &cfg.review).await?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&findings)?);
+            } else {
+                if findings.is_empty() {
+                    println!("no findings against {}", base);
+                }
+                for f in &findings {
+                    println!("{:<9} {}  {}", f.severity.label(), f.location(), f.message);
+                    if let Some(s) = &f.suggestion {
+                        println!("          suggestion: {}", s);
+                    }
+                }
+            }
+            for f in findings.iter().filter(|f| fix && f.suggestion.is_some()) {
+                let mut sa = agent::super_agent::SuperAgent::new();
+                sa.profile = load_project_profile();
+                sa.tasks = Some(crate::tasks::TaskStore::new(crate::paths::home()));
+                sa.file_policy = crate::tools::policy::FileAccessPolicy::new(root.clone(), &cfg.files);
+                sa.connect(&cfg.chat_endpoint(), &cfg.llm_model);
+                info!("Fixing {}", f.location());
+                match sa.run_goal(f.fix_goal()).await {
+                    Ok(report) => println!("fixed {}:\n{}", f.location(), report),
+                    Err(e) => println!("could not fix {}: {}", f.location(), e),
+                }
+            }
+        }
+        crate::cli::commands::Cmd::TestGen { target, attempts } => {
+            let cfg = crate::config::RuntimeConfig::load();
+            let profile = load_project_profile();
+            let Some(test_command) = profile.test_command.clone() else {
+                anyhow::bail!("no test_command for this project; set one in {}", crate::workspace::PROFILE_FILE);
+            };
+            let root = std::env::current_dir()?;
+            let source = agent::test_gen::resolve(&root, &target)?;
+            let llm = crate::llm::llama::LlamaClient::new(cfg.chat_endpoint(), cfg.llm_model.clone());
+            info!("Generating tests for {}", source.display());
+            let report = agent::test_gen::generate(&llm, &root, &source, &test_command, attempts).await?;
+            print!("{}", report.render());
+            if !report.passed {
+                std::process::exit(1);
+            }
+        }
+        crate::cli::commands::Cmd::Docgen { path, overview, yes } => {
+            let cfg = crate::config::RuntimeConfig::load();
+            let root = std::env::current_dir()?;
+            let policy = crate::tools::policy::FileAccessPolicy::new(root.clone(), &cfg.files);
+            let mut index = symbols::index(&root, &load_project_profile(), &policy);
+            if let Some(prefix) = &path {
+                index.retain(|s| s.file.starts_with(prefix.trim_start_matches("./")));
+            }
+            let llm = crate::llm::llama::LlamaClient::new(cfg.chat_endpoint(), cfg.llm_model.clone());
+            let missing = index.iter().filter(|s| !s.documented).count();
+            println!("{} public item(s), {} without docs", index.len(), missing);
+            let mut accepted = vec![];
+            let mut accept_all = yes;
+            for edit in agent::docgen::draft(&llm, &root, &index).await? {
+                let source = std::fs::read_to_string(root.join(&edit.file))?;
+                print!("{}", agent::docgen::diff(&edit, &source));
+                if !accept_all {
+                    eprint!("Apply? [y]es/[n]o/[a]ll/[q]uit ");
+                    let mut answer = String::new();
+                    std::io::stdin().read_line(&mut answer)?;
+                    match answer.trim() {
+                        "y" | "Y" => {}
+                        "a" | "A" => accept_all = true,
+                        "q" | "Q" => break,
+                        _ => continue,
+                    }
+                }
+                accepted.push(edit);
+            }
+            println!("applied {} doc comment(s)", agent::docgen::apply(&root, &accepted)?);
+            if overview {
+                let project = root.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+                let text = agent::docgen::overview(&llm, &project, &index).await?;
+                println!("{}", text);
+                let target = root.join(agent::docgen::OVERVIEW_FILE);
+                let write = yes || {
+                    eprint!("Write {}{}? [y/N] ", agent::docgen::OVERVIEW_FILE, if target.exists() { " (replacing it)" } else { "" });
+                    let mut answer = String::new();
+                    std::io::stdin().read_line(&mut answer)?;
+                    answer.trim().eq_ignore_ascii_case("y")
+                };
+                if write {
+                    std::fs::create_dir_all(root.join("docs"))?;
+                    std::fs::write(&target, text)?;
+                    println!("wrote {}", agent::docgen::OVERVIEW_FILE);
+                }
+            }
+        }
+        crate::cli::commands::Cmd::Refactor { instruction, paths, attempts, yes } => {
+            let cfg = crate::config::RuntimeConfig::load();
+            let root = std::env::current_dir()?;
+            let request = agent::refactor::RefactorRequest { instruction, scope: agent::refactor::scope_globs(&root, &paths), attempts };
+            let llm = crate::llm::llama::LlamaClient::new(cfg.chat_endpoint(), cfg.llm_model.clone());
+            info!("Refactoring within {}", request.scope.join(", "));
+            let report = agent::refactor::run(&llm, &root, &request, &cfg.files, &load_project_profile(), |diff| {
+                println!("{}", diff);
+                if yes {
+                    return Ok(true);
+                }
+                eprint!("Apply this diff? [y/N] ");
+                let mut answer = String::new();
+                std::io::stdin().read_line(&mut answer)?;
+                Ok(answer.trim().eq_ignore_ascii_case("y"))
+            })
+            .await?;
+            print!("{}", report.render());
+            if !report.applied {
+                std::process::exit(1);
+            }
+        }
+        crate::cli::commands::Cmd::Triage { log, json, open } => {
+            let cfg = crate::config::RuntimeConfig::load();
+            let text = if log == "-" {
+                let mut text = String::new();
+                std::io::Read::read_to_string(&mut std::io::stdin(), &mut text)?;
+                text
+            } else {
+                std::fs::read_to_string(&log)?
+            };
+            let root = std::env::current_dir()?;
+            let profile = load_project_profile();
+            let policy = crate::tools::policy::FileAccessPolicy::new(root.clone(), &cfg.files);
+            let files = crate::workspace::file_index(&root, &profile, &policy);
+            let index = symbols::index(&root, &profile, &policy);
+            let llm = crate::llm::llama::LlamaClient::new(cfg.chat_endpoint(), cfg.llm_model.clone());
+            let report = agent::triage::triage(&llm, &root, &text, &files, &index).await?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                print!("{}", report.render());
+            }
+            if open && !report.implicated().is_empty() {
+                let _lock = crate::models::instance::InstanceLock::acquire(&cfg.model_dir, "tui")?;
+                let mut app = tui::app::TuiApp::new(cfg, profile)?.with_files(&report.implicated());
+                tokio::select! {
+                    res = app.run() => { res?; }
+                    _ = signal::ctrl_c() => {}
+                }
+            }
+        }
+        crate::cli::commands::Cmd::Secrets { cmd } => {
+            let store = crate::secrets::SecretStore::open(crate::paths::home());
+            match cmd {
+                crate::cli::commands::SecretCmd::Set { name } => {
+                    let value = if std::io::IsTerminal::is_terminal(&std::io::stdin()) {
+                        rpassword::prompt_password(format!("value for {}: ", name))?
+                    } else {
+                        let mut line = String::new();
+                        std::io::stdin().read_line(&mut line)?;
+                        line.trim_end_matches(['\r', '\n']).to_string()
+                    };
+                    anyhow::ensure!(!value.is_empty(), "empty value");
+                    let backend = store.set(&name, &value)?;
+                    println!("stored {} in the {}", name, backend);
+                }
+                crate::cli::commands::SecretCmd::Get { name, reveal } => match store.get(&name)? {
+                    Some((secret, _)) if reveal => println!("{}", secret.expose()),
+                    Some((secret, backend)) => println!("{} = {} ({})", name, secret, backend),
+                    None => anyhow::bail!("secret {} is not set", name),
+                },
+                crate::cli::commands::SecretCmd::Rm { name } => {
+                    if !store.remove(&name)? {
+                        anyhow::bail!("secret {} is not set", name);
+                    }
+                    println!("removed {}", name);
+                }
+            }
+        }
         crate::cli::commands::Cmd::Exit => {
             println!("exiting");
         }
@@ -126,3 +871,19 @@ async fn main() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+/// Detect (or read) the project profile for the current directory.
+fn load_project_profile() -> crate::workspace::ProjectProfile {
+    let cwd = std::env::current_dir().unwrap_or_else(|_| std::path::PathBuf::from("."));
+    match crate::workspace::ProjectProfile::load_or_detect(&cwd) {
+        Ok(p) => {
+            info!("project profile: {:?}", p.kind);
+            crate::tools::audit::configure_project(&p.policy);
+            p
+        }
+        Err(e) => {
+            log::warn!("failed to load {}: {}", crate::workspace::PROFILE_FILE, e);
+            crate::workspace::ProjectProfile::detect(&cwd)
+        }
+    }
+}
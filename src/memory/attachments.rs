@@ -0,0 +1,132 @@
+//! Files attached to chat messages. Small text files go to the model
+//! verbatim, large ones as a summary, images as image parts. Each attachment
+//! is re-read when the message is sent, so its recorded hash is the version
+//! the model actually saw.
+
+use crate::llm::Llm;
+use crate::types::{AttachMode, Attachment, ImagePart, Message};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+/// Text files up to this size are embedded verbatim.
+pub const EMBED_LIMIT: u64 = 16 * 1024;
+/// How much of a large file the summarizer reads.
+const SUMMARY_INPUT_LIMIT: usize = 64 * 1024;
+
+/// Turn pasted or dropped text into a path: terminals quote it, escape
+/// spaces or send a `file://` URL.
+pub fn dropped_path(text: &str) -> Option<PathBuf> {
+    let text = text.trim();
+    let unquoted = ['\'', '"'].iter().find_map(|q| text.strip_prefix(*q).and_then(|t| t.strip_suffix(*q))).unwrap_or(text);
+    let path = PathBuf::from(unquoted.strip_prefix("file://").unwrap_or(unquoted).replace("\\ ", " "));
+    path.is_file().then_some(path)
+}
+
+/// Check a file can be attached and record its current version.
+pub fn inspect(path: &Path) -> anyhow::Result<Attachment> {
+    let data = std::fs::read(path).map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))?;
+    let mode = if ImagePart::is_image_path(path) {
+        AttachMode::Image
+    } else if data.iter().take(8192).any(|b| *b == 0) {
+        return Err(anyhow::anyhow!("{} is a binary file", path.display()));
+    } else if data.len() as u64 <= EMBED_LIMIT {
+        AttachMode::Embed
+    } else {
+        AttachMode::Summary
+    };
+    Ok(Attachment {
+        path: path.to_path_buf(),
+        bytes: data.len() as u64,
+        sha256: hex::encode(Sha256::digest(&data)),
+        mode,
+        text: String::new(),
+    })
+}
+
+/// Label for the chip above the input box.
+pub fn chip(a: &Attachment) -> String {
+    let name = a.path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    let size = if a.bytes < 1024 { format!("{} B", a.bytes) } else { format!("{:.1} KB", a.bytes as f64 / 1024.0) };
+    let mode = match a.mode {
+        AttachMode::Embed => "",
+        AttachMode::Summary => ", summarized",
+        AttachMode::Image => ", image",
+    };
+    format!("{} ({}{})", name, size, mode)
+}
+
+/// Build the user message for `text` with `attachments` read as they are now.
+pub async fn prepare(llm: &dyn Llm, text: &str, attachments: &[Attachment]) -> anyhow::Result<Message> {
+    let mut sent = vec![];
+    let mut images = vec![];
+    let mut summaries = vec![];
+    for pending in attachments {
+        let mut a = inspect(&pending.path)?;
+        match a.mode {
+            AttachMode::Image => images.push(ImagePart::Path { path: a.path.clone() }),
+            AttachMode::Embed => a.text = std::fs::read_to_string(&a.path)?,
+            AttachMode::Summary => summaries.push((sent.len(), summary_request(&a.path)?)),
+        }
+        sent.push(a);
+    }
+    // large files are summarized together rather than one after another
+    let requests: Vec<Vec<Message>> = summaries.iter().map(|(_, r)| r.clone()).collect();
+    for ((i, _), summary) in summaries.iter().zip(llm.chat_batch(&requests).await) {
+        sent[*i].text = summary?;
+    }
+    let mut msg = Message::new("user", text).with_images(images);
+    msg.attachments = sent;
+    Ok(msg)
+}
+
+fn summary_request(path: &Path) -> anyhow::Result<Vec<Message>> {
+    let text = std::fs::read_to_string(path)?;
+    let mut cut = text.len().min(SUMMARY_INPUT_LIMIT);
+    while !text.is_char_boundary(cut) {
+        cut -= 1;
+    }
+    let truncated = if cut < text.len() { "\n[truncated]" } else { "" };
+    let prompt = format!(
+        "Summarize the file {} so it can serve as context in a conversation. Keep names, signatures, numbers and structure; drop boilerplate.\n\n{}{}",
+        path.display(),
+        &text[..cut],
+        truncated
+    );
+    Ok(vec![Message::new("user", prompt)])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::mock::ScriptedLlm;
+
+    #[tokio::test]
+    async fn test_attachments_are_embedded_or_summarized_by_size() -> anyhow::Result<()> {
+        let td = tempfile::tempdir()?;
+        let small = td.path().join("notes.md");
+        let large = td.path().join("big log.txt");
+        std::fs::write(&small, "v1")?;
+        std::fs::write(&large, "line\n".repeat(5000))?;
+        let other = td.path().join("trace.txt");
+        std::fs::write(&other, "frame\n".repeat(5000))?;
+        std::fs::write(td.path().join("blob.bin"), [0u8, 1, 2])?;
+        assert!(inspect(&td.path().join("blob.bin")).is_err());
+
+        let pending = vec![inspect(&small)?, inspect(&dropped_path(&format!("'file://{}'", large.display())).expect("dropped path"))?, inspect(&other)?];
+        assert_eq!(pending[1].mode, AttachMode::Summary);
+        assert_eq!(chip(&pending[0]), "notes.md (2 B)");
+
+        // edited after attaching: the message records what was sent
+        std::fs::write(&small, "v2!")?;
+        let llm = ScriptedLlm::from_yaml("responses:\n  - prompt: '^Summarize the file .*trace'\n    reply: a stack trace\n  - prompt: '^Summarize the file'\n    reply: 5000 identical lines\n")?;
+        let msg = prepare(&llm, "look", &pending).await?;
+        assert_eq!(msg.attachments[0].bytes, 3);
+        assert_ne!(msg.attachments[0].sha256, pending[0].sha256);
+        let full = msg.full_text();
+        assert!(full.contains("Attached file") && full.contains("v2!"), "{}", full);
+        assert!(full.contains("Summary of attached file") && full.contains("5000 identical lines"), "{}", full);
+        // summarized together, each summary with its own file
+        assert_eq!((msg.attachments[1].text.as_str(), msg.attachments[2].text.as_str()), ("5000 identical lines", "a stack trace"));
+        Ok(())
+    }
+}
@@ -0,0 +1,198 @@
+use crate::memory::summary;
+use crate::types::{new_id, Message};
+
+/// Where a conversation was forked from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Branch {
+    pub parent: String,
+    /// Number of parent messages shared with the fork.
+    pub at: usize,
+}
+
+/// A chat thread. Forks copy the shared prefix and then diverge, so each
+/// branch owns its own history.
+#[derive(Debug, Clone)]
+pub struct Conversation {
+    pub id: String,
+    pub title: String,
+    pub messages: Vec<Message>,
+    pub branch: Option<Branch>,
+    /// System prompt sent ahead of every request; not part of `messages`.
+    pub system: Option<String>,
+    /// Name of the persona the system prompt came from, if any.
+    pub persona: Option<String>,
+}
+
+impl Conversation {
+    pub fn new(title: impl Into<String>) -> Self {
+        Self { id: new_id(), title: title.into(), messages: vec![], branch: None, system: None, persona: None }
+    }
+
+    /// Set an ad-hoc system prompt; clears the persona name.
+    pub fn set_system(&mut self, text: impl Into<String>) {
+        let text = text.into();
+        self.system = if text.trim().is_empty() { None } else { Some(text) };
+        self.persona = None;
+    }
+
+    pub fn set_persona(&mut self, name: impl Into<String>, prompt: impl Into<String>) {
+        self.system = Some(prompt.into());
+        self.persona = Some(name.into());
+    }
+
+    /// Label for the status bar: persona name, `custom` or `none`.
+    pub fn persona_label(&self) -> &str {
+        match (&self.persona, &self.system) {
+            (Some(name), _) => name,
+            (None, Some(_)) => "custom",
+            (None, None) => "none",
+        }
+    }
+
+    /// Messages to send to the model, with the system prompt first.
+    pub fn request_messages(&self) -> Vec<Message> {
+        let mut out = Vec::with_capacity(self.messages.len() + 1);
+        if let Some(system) = &self.system {
+            out.push(Message::new("system", system));
+        }
+        out.extend(self.messages.iter().map(|m| match m.role.as_str() {
+            summary::ROLE => Message::new("system", format!("Summary of the earlier conversation:\n{}", m.content)),
+            _ => m.clone(),
+        }));
+        out
+    }
+
+    /// How many leading messages compaction would replace, leaving the
+    /// latest `keep`; 0 when fewer than two would go.
+    pub fn compactable(&self, keep: usize) -> usize {
+        match self.messages.len().saturating_sub(keep) {
+            n if n < 2 => 0,
+            n => n,
+        }
+    }
+
+    /// Replace the first `count` messages with a summary holding them.
+    /// An earlier summary among them hands over its originals, so the
+    /// summary always holds the messages as they were written.
+    pub fn compact(&mut self, count: usize, text: impl Into<String>) {
+        let count = count.min(self.messages.len());
+        let mut originals = vec![];
+        for m in self.messages.drain(..count) {
+            match m.role.as_str() {
+                summary::ROLE => originals.extend(m.replaces),
+                _ => originals.push(m),
+            }
+        }
+        let mut m = Message::new(summary::ROLE, text);
+        m.replaces = originals;
+        self.messages.insert(0, m);
+    }
+
+    /// Markdown transcript including the persona and system prompt.
+    pub fn export_markdown(&self) -> String {
+        let mut out = format!("# {}\n\n- persona: {}\n", self.display_title(), self.persona_label());
+        if let Some(system) = &self.system {
+            out.push_str(&format!("- system: {}\n", system.replace('\n', " ")));
+        }
+        for m in &self.messages {
+            out.push_str(&format!("\n**{}**: {}\n", m.role, m.content));
+            for image in &m.images {
+                out.push_str(&format!("\n{}\n", image.label()));
+            }
+        }
+        out
+    }
+
+    pub fn push(&mut self, m: Message) {
+        self.messages.push(m);
+    }
+
+    /// Fork keeping messages `[0, at]` (inclusive). Out-of-range indices fork
+    /// the whole conversation.
+    pub fn fork(&self, at: usize) -> Conversation {
+        let keep = (at + 1).min(self.messages.len());
+        Conversation {
+            id: new_id(),
+            title: self.title.clone(),
+            messages: self.messages[..keep].to_vec(),
+            branch: Some(Branch { parent: self.id.clone(), at: keep }),
+            system: self.system.clone(),
+            persona: self.persona.clone(),
+        }
+    }
+
+    /// Title for tabs, with a branch marker showing the fork point.
+    pub fn display_title(&self) -> String {
+        match &self.branch {
+            Some(b) => format!("{} ⎇{}", self.title, b.at),
+            None => self.title.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fork_shares_prefix_then_diverges() {
+        let mut main = Conversation::new("chat");
+        main.push(Message::new("user", "a"));
+        main.push(Message::new("assistant", "b"));
+        main.push(Message::new("user", "c"));
+
+        let mut fork = main.fork(1);
+        assert_eq!(fork.messages.len(), 2);
+        assert_eq!(fork.display_title(), "chat ⎇2");
+        assert_eq!(fork.branch.as_ref().unwrap().parent, main.id);
+
+        fork.push(Message::new("user", "d"));
+        assert_eq!(main.messages.len(), 3);
+        assert_eq!(fork.messages[2].content, "d");
+        assert_eq!(main.fork(99).messages.len(), 3);
+    }
+
+    #[test]
+    fn test_compaction_keeps_originals_and_recent_turns() {
+        let mut conv = Conversation::new("chat");
+        for i in 0..6 {
+            conv.push(Message::new(if i % 2 == 0 { "user" } else { "assistant" }, i.to_string()));
+        }
+        assert_eq!(conv.compactable(5), 0);
+        assert_eq!(conv.compactable(2), 4);
+        let kept = conv.messages[4].id.clone();
+        conv.compact(4, "- counted to 3");
+        assert_eq!(conv.messages.len(), 3);
+        assert_eq!(conv.messages[0].replaces.len(), 4);
+        assert_eq!(conv.messages[1].id, kept, "ids follow messages that moved");
+        let sent = conv.request_messages();
+        assert_eq!((sent[0].role.as_str(), sent[0].content.as_str()), ("system", "Summary of the earlier conversation:\n- counted to 3"));
+        assert_eq!(sent[1].content, "4");
+
+        conv.push(Message::new("user", "6"));
+        conv.compact(conv.compactable(1), "- counted to 5");
+        let originals: Vec<&str> = conv.messages[0].replaces.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(originals, ["0", "1", "2", "3", "4", "5"]);
+        assert_eq!(conv.messages.len(), 2);
+    }
+
+    #[test]
+    fn test_system_prompt_and_persona() {
+        let mut conv = Conversation::new("chat");
+        conv.push(Message::new("user", "hi"));
+        assert_eq!(conv.persona_label(), "none");
+        assert_eq!(conv.request_messages().len(), 1);
+
+        conv.set_persona("reviewer", "You review Rust code.");
+        let msgs = conv.request_messages();
+        assert_eq!(msgs[0].role, "system");
+        assert_eq!(msgs[0].content, "You review Rust code.");
+        assert!(conv.export_markdown().contains("- persona: reviewer"));
+        assert_eq!(conv.fork(0).persona_label(), "reviewer");
+
+        conv.set_system("Be brief.");
+        assert_eq!(conv.persona_label(), "custom");
+        conv.set_system("");
+        assert_eq!(conv.persona_label(), "none");
+    }
+}
@@ -0,0 +1,214 @@
+use std::collections::HashSet;
+use std::sync::OnceLock;
+use tokio::sync::broadcast;
+
+/// An event published by one session for any other session to observe.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SessionEvent {
+    Message { from_session: String, to_session: Option<String>, content: String },
+    TaskUpdate { session: String, task_id: String, status: String },
+    VariableChange { key: String, old_value: Option<String>, new_value: Option<String> },
+    FileChange { session: String, file: String, action: String },
+    AgentAction { session: String, agent: String, action: String },
+    /// Pair mode narration of what an agent is doing ("read src/main.rs"),
+    /// for a feed beside the conversation rather than in it.
+    Commentary { session: String, agent: String, text: String },
+}
+
+/// Event kinds a session can subscribe to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EventKind {
+    Message,
+    TaskUpdate,
+    VariableChange,
+    FileChange,
+    AgentAction,
+    Commentary,
+}
+
+impl EventKind {
+    pub fn all() -> [EventKind; 6] {
+        [
+            EventKind::Message,
+            EventKind::TaskUpdate,
+            EventKind::VariableChange,
+            EventKind::FileChange,
+            EventKind::AgentAction,
+            EventKind::Commentary,
+        ]
+    }
+
+    pub fn parse(input: &str) -> Option<Self> {
+        match input.to_lowercase().as_str() {
+            "message" | "msg" => Some(EventKind::Message),
+            "task" | "taskupdate" => Some(EventKind::TaskUpdate),
+            "var" | "variable" | "variablechange" => Some(EventKind::VariableChange),
+            "file" | "filechange" => Some(EventKind::FileChange),
+            "agent" | "agentaction" => Some(EventKind::AgentAction),
+            "commentary" | "pair" => Some(EventKind::Commentary),
+            _ => None,
+        }
+    }
+}
+
+impl SessionEvent {
+    pub fn kind(&self) -> EventKind {
+        match self {
+            SessionEvent::Message { .. } => EventKind::Message,
+            SessionEvent::TaskUpdate { .. } => EventKind::TaskUpdate,
+            SessionEvent::VariableChange { .. } => EventKind::VariableChange,
+            SessionEvent::FileChange { .. } => EventKind::FileChange,
+            SessionEvent::AgentAction { .. } => EventKind::AgentAction,
+            SessionEvent::Commentary { .. } => EventKind::Commentary,
+        }
+    }
+
+    /// Session that produced the event, if the event carries one.
+    pub fn source(&self) -> Option<&str> {
+        match self {
+            SessionEvent::Message { from_session, .. } => Some(from_session),
+            SessionEvent::TaskUpdate { session, .. }
+            | SessionEvent::FileChange { session, .. }
+            | SessionEvent::AgentAction { session, .. }
+            | SessionEvent::Commentary { session, .. } => Some(session),
+            SessionEvent::VariableChange { .. } => None,
+        }
+    }
+
+    /// One-line text suitable for a system message in a chat view.
+    pub fn describe(&self) -> String {
+        match self {
+            SessionEvent::Message { from_session, content, .. } => format!("[{}] {}", from_session, content),
+            SessionEvent::TaskUpdate { session, task_id, status } => {
+                format!("[{}] task {} -> {}", session, task_id, status)
+            }
+            SessionEvent::VariableChange { key, new_value, .. } => match new_value {
+                Some(v) => format!("var {} = {}", key, v),
+                None => format!("var {} removed", key),
+            },
+            SessionEvent::FileChange { session, file, action } => format!("[{}] {} {}", session, action, file),
+            SessionEvent::AgentAction { session, agent, action } => format!("[{}] {}: {}", session, agent, action),
+            SessionEvent::Commentary { session, agent, text } => format!("[{}] {} … {}", session, agent, text),
+        }
+    }
+}
+
+/// Broadcast channel for cross-session events.
+#[derive(Debug, Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<SessionEvent>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new(100)
+    }
+}
+
+impl EventBus {
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+        Self { sender }
+    }
+
+    /// The process's bus: agents, the TUI and the editor server publish and
+    /// watch here unless given a bus of their own.
+    pub fn shared() -> Self {
+        static SHARED: OnceLock<EventBus> = OnceLock::new();
+        SHARED.get_or_init(EventBus::default).clone()
+    }
+
+    pub fn publish(&self, event: SessionEvent) {
+        // no receivers is not an error: nobody is watching yet
+        let _ = self.sender.send(event);
+    }
+
+    /// Subscribe `session` to the given kinds. Events the session published
+    /// itself are filtered out so a session does not echo its own activity.
+    pub fn subscribe(&self, session: impl Into<String>, kinds: &[EventKind]) -> Subscription {
+        Subscription {
+            session: session.into(),
+            kinds: kinds.iter().copied().collect(),
+            source: None,
+            receiver: self.sender.subscribe(),
+        }
+    }
+}
+
+/// A session's view of the event bus.
+pub struct Subscription {
+    pub session: String,
+    pub kinds: HashSet<EventKind>,
+    /// Only accept events produced by this session (a "watcher" on one worker).
+    pub source: Option<String>,
+    receiver: broadcast::Receiver<SessionEvent>,
+}
+
+impl Subscription {
+    pub fn watch(&mut self, kind: EventKind) {
+        self.kinds.insert(kind);
+    }
+
+    pub fn unwatch(&mut self, kind: EventKind) {
+        self.kinds.remove(&kind);
+    }
+
+    fn accepts(&self, event: &SessionEvent) -> bool {
+        if !self.kinds.contains(&event.kind()) {
+            return false;
+        }
+        match (event.source(), &self.source) {
+            (Some(src), _) if src == self.session => false,
+            (Some(src), Some(want)) => src == want,
+            (None, Some(_)) => false,
+            _ => true,
+        }
+    }
+
+    /// Drain pending events without blocking, keeping the ones this
+    /// subscription is interested in. Lagged events are skipped.
+    pub fn drain(&mut self) -> Vec<SessionEvent> {
+        let mut out = vec![];
+        loop {
+            match self.receiver.try_recv() {
+                Ok(ev) => {
+                    if self.accepts(&ev) {
+                        out.push(ev);
+                    }
+                }
+                Err(broadcast::error::TryRecvError::Lagged(_)) => continue,
+                Err(_) => break,
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_watcher_receives_only_subscribed_kinds() {
+        let bus = EventBus::default();
+        let mut watcher = bus.subscribe("watcher", &[EventKind::TaskUpdate]);
+        watcher.source = Some("worker".into());
+
+        bus.publish(SessionEvent::TaskUpdate { session: "worker".into(), task_id: "t1".into(), status: "done".into() });
+        bus.publish(SessionEvent::TaskUpdate { session: "other".into(), task_id: "t2".into(), status: "done".into() });
+        bus.publish(SessionEvent::FileChange { session: "worker".into(), file: "a.rs".into(), action: "write".into() });
+        bus.publish(SessionEvent::TaskUpdate { session: "watcher".into(), task_id: "t3".into(), status: "done".into() });
+
+        let got = watcher.drain();
+        assert_eq!(got.len(), 1);
+        assert_eq!(got[0].describe(), "[worker] task t1 -> done");
+        assert!(watcher.drain().is_empty());
+    }
+
+    #[test]
+    fn test_shared_bus_reaches_every_holder() {
+        let mut watcher = EventBus::shared().subscribe("watcher", &[EventKind::Message]);
+        EventBus::shared().publish(SessionEvent::Message { from_session: "worker".into(), to_session: None, content: "shared-bus-probe".into() });
+        assert!(watcher.drain().iter().any(|e| e.describe() == "[worker] shared-bus-probe"));
+    }
+}
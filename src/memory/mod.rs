@@ -1 +1,9 @@
 pub mod store;
+pub mod shared;
+pub mod events;
+pub mod conversation;
+pub mod attachments;
+pub mod summary;
+pub mod notes;
+pub mod sessions;
+pub mod retention;
@@ -0,0 +1,55 @@
+//! Knowledge notes: facts pinned to a project with `/remember` ("we use
+//! sqlx, not diesel"). They live in the project's `.supercode.toml` and go
+//! out as a system message with every chat request and agent step, as many
+//! as fit in `memory.notes_budget` tokens, oldest first.
+
+use crate::models::metrics::estimate_tokens;
+use crate::types::Message;
+
+/// How many of `notes`, from the first, fit in `budget` tokens.
+pub fn within_budget(notes: &[String], budget: u64) -> usize {
+    let mut used = 0;
+    notes
+        .iter()
+        .take_while(|n| {
+            used += estimate_tokens(n) + 1;
+            used <= budget
+        })
+        .count()
+}
+
+/// The system message carrying the notes that fit, if any.
+pub fn context(notes: &[String], budget: u64) -> Option<Message> {
+    let fit = &notes[..within_budget(notes, budget)];
+    if fit.is_empty() {
+        return None;
+    }
+    let lines: Vec<String> = fit.iter().map(|n| format!("- {}", n)).collect();
+    Some(Message::new("system", format!("Project notes (always apply these):\n{}", lines.join("\n"))))
+}
+
+/// Put the notes message after the leading system messages of `messages`.
+pub fn inject(messages: &mut Vec<Message>, notes: &[String], budget: u64) {
+    if let Some(m) = context(notes, budget) {
+        let at = messages.iter().take_while(|m| m.role == "system").count();
+        messages.insert(at, m);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_notes_fit_the_budget_and_follow_the_system_prompt() {
+        let notes = vec!["we use sqlx, not diesel".to_string(), "tests run with cargo nextest".to_string()];
+        assert_eq!(within_budget(&notes, 6), 1);
+        assert_eq!(within_budget(&notes, 100), 2);
+        assert!(context(&notes, 2).is_none());
+
+        let mut messages = vec![Message::new("system", "be brief"), Message::new("user", "hi")];
+        inject(&mut messages, &notes, 6);
+        assert_eq!(messages[1].content, "Project notes (always apply these):\n- we use sqlx, not diesel");
+        assert_eq!(messages[2].role, "user");
+    }
+}
@@ -0,0 +1,243 @@
+//! How long chat history is kept. `[memory.retention]` limits the saved
+//! sessions and provider transcripts under the state home by count, age and
+//! total size; every limit is off at 0. The TUI's janitor applies them in
+//! the background, and `agent memory prune` shows what they would delete
+//! and asks before deleting it.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetentionConfig {
+    /// Saved sessions kept, newest first.
+    pub max_sessions: usize,
+    /// Sessions and transcripts untouched for longer are deleted.
+    pub max_age_days: u64,
+    /// The oldest history goes until sessions and transcripts fit in this.
+    pub max_total_mb: u64,
+    /// Minutes between the janitor's passes while the TUI runs.
+    pub janitor_minutes: u64,
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self { max_sessions: 0, max_age_days: 0, max_total_mb: 0, janitor_minutes: 60 }
+    }
+}
+
+impl RetentionConfig {
+    pub fn is_set(&self) -> bool {
+        self.max_sessions > 0 || self.max_age_days > 0 || self.max_total_mb > 0
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Session,
+    Transcript,
+}
+
+impl Kind {
+    fn name(self) -> &'static str {
+        match self {
+            Kind::Session => "session",
+            Kind::Transcript => "transcript",
+        }
+    }
+}
+
+/// One file of history.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Item {
+    pub kind: Kind,
+    pub id: String,
+    pub path: PathBuf,
+    pub bytes: u64,
+    /// Unix seconds it was last written.
+    pub modified: u64,
+}
+
+/// An item the limits delete, and which limit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Doomed {
+    pub item: Item,
+    pub reason: String,
+}
+
+fn files(kind: Kind, dir: &Path, extension: &str) -> Vec<Item> {
+    std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|e| {
+            let path = e.path();
+            let id = path.file_name()?.to_str()?.strip_suffix(extension)?.to_string();
+            let meta = e.metadata().ok()?;
+            let modified = meta.modified().ok()?.duration_since(std::time::UNIX_EPOCH).ok()?.as_secs();
+            Some(Item { kind, id, path, bytes: meta.len(), modified })
+        })
+        .collect()
+}
+
+/// The sessions and transcripts under `home`, leaving out the transcript
+/// this process is writing.
+pub fn scan(home: &Path) -> Vec<Item> {
+    let mut items = files(Kind::Session, crate::memory::sessions::SessionStore::new(home).dir(), ".json");
+    let current = crate::transcript::current_run();
+    items.extend(files(Kind::Transcript, &crate::transcript::dir(home), ".jsonl").into_iter().filter(|t| Some(&t.id) != current.as_ref()));
+    items
+}
+
+/// What `config` deletes of `items` at `now`, oldest first.
+pub fn plan(mut items: Vec<Item>, config: &RetentionConfig, now: u64) -> Vec<Doomed> {
+    items.sort_by(|a, b| b.modified.cmp(&a.modified).then(a.id.cmp(&b.id)));
+    let mut doomed = vec![];
+    let mut kept = vec![];
+    let mut sessions = 0;
+    for item in items {
+        let age_days = now.saturating_sub(item.modified) / 86_400;
+        if config.max_age_days > 0 && age_days > config.max_age_days {
+            doomed.push(Doomed { item, reason: format!("older than {} days", config.max_age_days) });
+            continue;
+        }
+        if item.kind == Kind::Session {
+            sessions += 1;
+            if config.max_sessions > 0 && sessions > config.max_sessions {
+                doomed.push(Doomed { item, reason: format!("beyond the newest {} sessions", config.max_sessions) });
+                continue;
+            }
+        }
+        kept.push(item);
+    }
+    if config.max_total_mb > 0 {
+        let limit = config.max_total_mb * 1024 * 1024;
+        let mut total: u64 = kept.iter().map(|i| i.bytes).sum();
+        while total > limit {
+            let Some(item) = kept.pop() else { break };
+            total -= item.bytes;
+            doomed.push(Doomed { item, reason: format!("over the {} MB limit", config.max_total_mb) });
+        }
+    }
+    doomed.sort_by(|a, b| a.item.modified.cmp(&b.item.modified).then(a.item.id.cmp(&b.item.id)));
+    doomed
+}
+
+fn size(bytes: u64) -> String {
+    match bytes {
+        b if b < 1024 => format!("{} B", b),
+        b if b < 1024 * 1024 => format!("{:.1} KB", b as f64 / 1024.0),
+        b => format!("{:.1} MB", b as f64 / (1024.0 * 1024.0)),
+    }
+}
+
+/// What `doomed` deletes, one item per line under a total.
+pub fn report(doomed: &[Doomed]) -> String {
+    if doomed.is_empty() {
+        return "nothing to prune".into();
+    }
+    let mut out = format!("{} item(s), {}:", doomed.len(), size(doomed.iter().map(|d| d.item.bytes).sum()));
+    for d in doomed {
+        out.push_str(&format!("\n  {:<10} {:<24} {:>9}  {}", d.item.kind.name(), d.item.id, size(d.item.bytes), d.reason));
+    }
+    out
+}
+
+/// Delete `doomed`; returns the bytes freed.
+pub fn apply(doomed: &[Doomed]) -> anyhow::Result<u64> {
+    let mut freed = 0;
+    for d in doomed {
+        match std::fs::remove_file(&d.item.path) {
+            Ok(()) => freed += d.item.bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => anyhow::bail!("cannot delete {}: {}", d.item.path.display(), e),
+        }
+    }
+    Ok(freed)
+}
+
+/// Applies the limits every `janitor_minutes` in the background, starting
+/// with the first poll.
+pub struct Janitor {
+    home: PathBuf,
+    last: Option<Instant>,
+    running: bool,
+    tx: mpsc::UnboundedSender<Result<String, String>>,
+    rx: mpsc::UnboundedReceiver<Result<String, String>>,
+}
+
+impl Janitor {
+    pub fn new(home: PathBuf) -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        Self { home, last: None, running: false, tx, rx }
+    }
+
+    /// Start a pass when one is due; returns what finished passes deleted
+    /// (passes that deleted nothing report nothing).
+    pub fn poll(&mut self, config: &RetentionConfig) -> Vec<Result<String, String>> {
+        let mut done = vec![];
+        while let Ok(result) = self.rx.try_recv() {
+            self.running = false;
+            done.extend(result.map(|r| (!r.is_empty()).then_some(r)).transpose());
+        }
+        let every = Duration::from_secs(config.janitor_minutes.max(1) * 60);
+        if !config.is_set() || self.running || self.last.is_some_and(|at| at.elapsed() < every) {
+            return done;
+        }
+        self.last = Some(Instant::now());
+        self.running = true;
+        let (home, config, tx) = (self.home.clone(), config.clone(), self.tx.clone());
+        tokio::task::spawn_blocking(move || {
+            let doomed = plan(scan(&home), &config, crate::tasks::now_secs());
+            let result = match apply(&doomed) {
+                Ok(_) if doomed.is_empty() => Ok(String::new()),
+                Ok(_) => Ok(format!("Pruned {}", report(&doomed))),
+                Err(e) => Err(e.to_string()),
+            };
+            let _ = tx.send(result);
+        });
+        done
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limits_pick_the_oldest_history() -> anyhow::Result<()> {
+        const DAY: u64 = 86_400;
+        let now = 100 * DAY;
+        let item = |kind, id: &str, days_old: u64, mb: u64| Item { kind, id: id.into(), path: PathBuf::from(id), bytes: mb * 1024 * 1024, modified: now - days_old * DAY };
+        let items = vec![
+            item(Kind::Session, "s-new", 0, 1),
+            item(Kind::Session, "s-mid", 2, 1),
+            item(Kind::Session, "s-old", 5, 1),
+            item(Kind::Transcript, "t-mid", 3, 4),
+            item(Kind::Transcript, "t-ancient", 40, 1),
+        ];
+        assert!(plan(items.clone(), &RetentionConfig::default(), now).is_empty());
+
+        let config = RetentionConfig { max_sessions: 2, max_age_days: 30, max_total_mb: 5, ..Default::default() };
+        let doomed = plan(items, &config, now);
+        let picked: Vec<(&str, &str)> = doomed.iter().map(|d| (d.item.id.as_str(), d.reason.as_str())).collect();
+        assert_eq!(
+            picked,
+            vec![("t-ancient", "older than 30 days"), ("s-old", "beyond the newest 2 sessions"), ("t-mid", "over the 5 MB limit")]
+        );
+        assert!(report(&doomed).starts_with("3 item(s), 6.0 MB:\n  transcript t-ancient"));
+
+        let td = tempfile::tempdir()?;
+        let store = crate::memory::sessions::SessionStore::new(td.path());
+        std::fs::create_dir_all(store.dir())?;
+        std::fs::write(store.dir().join("abc.json"), "{}")?;
+        let found = scan(td.path());
+        assert_eq!((found.len(), found[0].kind, found[0].bytes), (1, Kind::Session, 2));
+        let doomed = plan(found, &RetentionConfig { max_age_days: 1, ..Default::default() }, crate::tasks::now_secs() + 3 * DAY);
+        assert_eq!(apply(&doomed)?, 2);
+        assert!(scan(td.path()).is_empty());
+        Ok(())
+    }
+}
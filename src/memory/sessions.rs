@@ -0,0 +1,376 @@
+//! Saved chat sessions, one JSON file each under `<home>/sessions/`. The
+//! TUI saves and reopens them with `/sessions`; `agent sessions import`
+//! turns other agents' transcripts into sessions, so a conversation begun
+//! in Aider, Claude Code or an OpenAI-style client can be continued here.
+//!
+//! - `aider`: `.aider.chat.history.md`. `#### ` lines are the user's, `> `
+//!   lines are Aider's own output and are dropped, the rest is the reply.
+//!   Each `# aider chat started at` begins another session.
+//! - `claude`: a Claude Code project transcript (`<session>.jsonl`). Text
+//!   blocks become messages, `tool_use` blocks and their `tool_result`s
+//!   become the reply's tool calls; side chains and meta lines are dropped.
+//! - `openai-jsonl`: one chat-completions message per line, or one
+//!   `{"messages": [...]}` conversation per line.
+
+use crate::memory::conversation::Conversation;
+use crate::types::{new_id, Message, ToolCallRecord};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Characters of the first user message kept as a session's title.
+const TITLE_CHARS: usize = 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    Aider,
+    Claude,
+    OpenaiJsonl,
+}
+
+impl Format {
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Aider => "aider",
+            Format::Claude => "claude",
+            Format::OpenaiJsonl => "openai-jsonl",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    pub id: String,
+    pub title: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system: Option<String>,
+    pub messages: Vec<Message>,
+    /// Format the session was imported from; `None` for our own.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    pub updated_at: u64,
+}
+
+impl Session {
+    pub fn from_conversation(conv: &Conversation) -> Self {
+        Self {
+            id: conv.id.clone(),
+            title: conv.title.clone(),
+            system: conv.system.clone(),
+            messages: conv.messages.clone(),
+            source: None,
+            updated_at: crate::tasks::now_secs(),
+        }
+    }
+
+    /// A conversation that saves back to this session.
+    pub fn to_conversation(&self) -> Conversation {
+        let mut conv = Conversation::new(self.title.clone());
+        conv.id = self.id.clone();
+        conv.system = self.system.clone();
+        conv.messages = self.messages.clone();
+        conv
+    }
+}
+
+pub struct SessionStore {
+    dir: PathBuf,
+}
+
+impl SessionStore {
+    pub fn new(home: &Path) -> Self {
+        Self { dir: home.join("sessions") }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn save(&self, session: &Session) -> anyhow::Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        std::fs::write(self.dir.join(format!("{}.json", session.id)), serde_json::to_string_pretty(session)?)?;
+        Ok(())
+    }
+
+    /// Every session, most recently updated first.
+    pub fn list(&self) -> Vec<Session> {
+        let mut out: Vec<Session> = std::fs::read_dir(&self.dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter(|e| e.path().extension().is_some_and(|x| x == "json"))
+            .filter_map(|e| serde_json::from_str(&std::fs::read_to_string(e.path()).ok()?).ok())
+            .collect();
+        out.sort_by(|a, b| b.updated_at.cmp(&a.updated_at).then(a.id.cmp(&b.id)));
+        out
+    }
+
+    /// The session whose id is or starts with `id`.
+    pub fn find(&self, id: &str) -> anyhow::Result<Session> {
+        let mut matches: Vec<Session> = self.list().into_iter().filter(|s| s.id.starts_with(id)).collect();
+        match matches.len() {
+            1 => Ok(matches.remove(0)),
+            0 => anyhow::bail!("no session {}", id),
+            _ => match matches.iter().position(|s| s.id == id) {
+                Some(at) => Ok(matches.remove(at)),
+                None => anyhow::bail!("session id {} is ambiguous: {}", id, matches.iter().map(|s| &s.id[..8]).collect::<Vec<_>>().join(", ")),
+            },
+        }
+    }
+}
+
+/// The sessions in `raw`, a transcript in `format`.
+pub fn import(format: Format, raw: &str) -> anyhow::Result<Vec<Session>> {
+    let sessions = match format {
+        Format::Aider => aider(raw),
+        Format::Claude => claude(raw)?,
+        Format::OpenaiJsonl => openai_jsonl(raw)?,
+    };
+    if sessions.is_empty() {
+        anyhow::bail!("no messages found in this {} transcript", format.name());
+    }
+    Ok(sessions.into_iter().map(|mut s| {
+        s.source = Some(format.name().to_string());
+        s
+    }).collect())
+}
+
+/// Collects one imported session's messages.
+#[derive(Default)]
+struct Builder {
+    title: Option<String>,
+    system: Option<String>,
+    messages: Vec<Message>,
+    /// Tool call id to `(message, call)` indices, for attaching results.
+    calls: HashMap<String, (usize, usize)>,
+}
+
+impl Builder {
+    /// Add text from `role`; consecutive assistant text is one reply.
+    fn say(&mut self, role: &str, text: &str) {
+        let text = text.trim();
+        if text.is_empty() {
+            return;
+        }
+        match self.messages.last_mut() {
+            Some(last) if role == "assistant" && last.role == "assistant" => {
+                if !last.content.is_empty() {
+                    last.content.push_str("\n\n");
+                }
+                last.content.push_str(text);
+            }
+            _ => self.messages.push(Message::new(role, text)),
+        }
+    }
+
+    fn call(&mut self, id: &str, tool: &str, input: String) {
+        if self.messages.last().is_none_or(|m| m.role != "assistant") {
+            self.messages.push(Message::new("assistant", ""));
+        }
+        let at = self.messages.len() - 1;
+        let calls = &mut self.messages[at].tool_calls;
+        calls.push(ToolCallRecord { tool: tool.to_string(), input, output: String::new(), ok: true });
+        self.calls.insert(id.to_string(), (at, calls.len() - 1));
+    }
+
+    fn result(&mut self, id: &str, output: String, ok: bool) {
+        if let Some(call) = self.calls.get(id).and_then(|&(m, c)| self.messages.get_mut(m)?.tool_calls.get_mut(c)) {
+            call.output = output;
+            call.ok = ok;
+        }
+    }
+
+    fn finish(self) -> Option<Session> {
+        if self.messages.is_empty() {
+            return None;
+        }
+        let title = self.title.or_else(|| {
+            let first = self.messages.iter().find(|m| m.role == "user")?;
+            Some(first.content.lines().next()?.chars().take(TITLE_CHARS).collect())
+        });
+        Some(Session {
+            id: new_id(),
+            title: title.unwrap_or_else(|| "imported".into()),
+            system: self.system,
+            messages: self.messages,
+            source: None,
+            updated_at: crate::tasks::now_secs(),
+        })
+    }
+}
+
+fn aider(raw: &str) -> Vec<Session> {
+    let mut sessions = vec![];
+    let mut session = Builder::default();
+    let (mut role, mut text) = ("assistant", String::new());
+    for line in raw.lines() {
+        if line.starts_with("# aider chat started at") {
+            session.say(role, &std::mem::take(&mut text));
+            sessions.extend(std::mem::take(&mut session).finish());
+            continue;
+        }
+        let (now, body) = match line.strip_prefix("#### ") {
+            Some(asked) => ("user", asked),
+            None if line.starts_with('>') => continue,
+            None => ("assistant", line),
+        };
+        if now != role {
+            session.say(role, &std::mem::take(&mut text));
+            role = now;
+        }
+        text.push_str(body);
+        text.push('\n');
+    }
+    session.say(role, &text);
+    sessions.extend(session.finish());
+    sessions
+}
+
+/// Text of a string or of the `text` parts of a content array.
+fn text_of(content: &Value) -> String {
+    match content {
+        Value::String(s) => s.clone(),
+        Value::Array(parts) => parts.iter().filter(|p| p["type"] == "text").filter_map(|p| p["text"].as_str()).collect::<Vec<_>>().join("\n"),
+        _ => String::new(),
+    }
+}
+
+fn lines(raw: &str) -> anyhow::Result<Vec<Value>> {
+    raw.lines()
+        .enumerate()
+        .filter(|(_, l)| !l.trim().is_empty())
+        .map(|(i, l)| serde_json::from_str(l).map_err(|e| anyhow::anyhow!("line {}: {}", i + 1, e)))
+        .collect()
+}
+
+fn claude(raw: &str) -> anyhow::Result<Vec<Session>> {
+    let mut session = Builder::default();
+    for entry in lines(raw)? {
+        let kind = entry["type"].as_str().unwrap_or("");
+        if kind == "summary" {
+            session.title = entry["summary"].as_str().map(str::to_string);
+        }
+        if !matches!(kind, "user" | "assistant") || entry["isSidechain"] == true || entry["isMeta"] == true {
+            continue;
+        }
+        let content = &entry["message"]["content"];
+        let Some(blocks) = content.as_array() else {
+            let text = text_of(content);
+            // slash commands and their local output
+            if !text.starts_with("<command-") && !text.starts_with("<local-command-") {
+                session.say(kind, &text);
+            }
+            continue;
+        };
+        for block in blocks {
+            match block["type"].as_str() {
+                Some("text") => session.say(kind, block["text"].as_str().unwrap_or("")),
+                Some("tool_use") => session.call(block["id"].as_str().unwrap_or(""), block["name"].as_str().unwrap_or("tool"), block["input"].to_string()),
+                Some("tool_result") => session.result(block["tool_use_id"].as_str().unwrap_or(""), text_of(&block["content"]), block["is_error"] != true),
+                _ => {}
+            }
+        }
+    }
+    Ok(session.finish().into_iter().collect())
+}
+
+/// One chat-completions message into `session`.
+fn openai_message(session: &mut Builder, m: &Value) {
+    let text = text_of(&m["content"]);
+    match m["role"].as_str().unwrap_or("") {
+        "system" | "developer" if session.system.is_none() && session.messages.is_empty() => session.system = Some(text),
+        "tool" => session.result(m["tool_call_id"].as_str().unwrap_or(""), text, true),
+        role @ ("user" | "assistant") => {
+            session.say(role, &text);
+            for call in m["tool_calls"].as_array().into_iter().flatten() {
+                let function = &call["function"];
+                session.call(call["id"].as_str().unwrap_or(""), function["name"].as_str().unwrap_or("tool"), function["arguments"].as_str().unwrap_or("").to_string());
+            }
+        }
+        _ => {}
+    }
+}
+
+fn openai_jsonl(raw: &str) -> anyhow::Result<Vec<Session>> {
+    let mut sessions = vec![];
+    let mut loose = Builder::default();
+    for entry in lines(raw)? {
+        match entry["messages"].as_array() {
+            Some(messages) => {
+                let mut session = Builder::default();
+                for m in messages {
+                    openai_message(&mut session, m);
+                }
+                sessions.extend(session.finish());
+            }
+            None => openai_message(&mut loose, &entry),
+        }
+    }
+    sessions.extend(loose.finish());
+    Ok(sessions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aider_history_splits_at_each_start() {
+        let raw = "# aider chat started at 2024-05-01 10:00:00\n\n> Aider v0.40\n> Model: gpt-4o\n\n#### add a hello function\n#### in hello.py\n\nHere it is:\n\n```python\ndef hello(): pass\n```\n\n> Applied edit to hello.py\n\n# aider chat started at 2024-05-02 09:00:00\n\n#### now test it\n\nDone.\n";
+        let sessions = import(Format::Aider, raw).unwrap();
+        assert_eq!(sessions.len(), 2);
+        let roles: Vec<(&str, &str)> = sessions[0].messages.iter().map(|m| (m.role.as_str(), m.content.as_str())).collect();
+        assert_eq!(roles, vec![("user", "add a hello function\nin hello.py"), ("assistant", "Here it is:\n\n```python\ndef hello(): pass\n```")]);
+        assert_eq!((sessions[0].title.as_str(), sessions[1].source.as_deref()), ("add a hello function", Some("aider")));
+    }
+
+    #[test]
+    fn test_claude_tool_results_join_their_calls() {
+        let raw = [
+            r#"{"type":"summary","summary":"Fix the parser"}"#,
+            r#"{"type":"user","message":{"role":"user","content":"the parser panics"}}"#,
+            r#"{"type":"assistant","message":{"role":"assistant","content":[{"type":"thinking","thinking":"hm"},{"type":"text","text":"Let me look."},{"type":"tool_use","id":"t1","name":"Read","input":{"file_path":"src/parse.rs"}}]}}"#,
+            r#"{"type":"user","message":{"role":"user","content":[{"type":"tool_result","tool_use_id":"t1","content":"fn parse() {}","is_error":false}]}}"#,
+            r#"{"type":"assistant","isSidechain":true,"message":{"role":"assistant","content":[{"type":"text","text":"sub agent"}]}}"#,
+            r#"{"type":"assistant","message":{"role":"assistant","content":[{"type":"text","text":"Found it."}]}}"#,
+            r#"{"type":"user","isMeta":true,"message":{"role":"user","content":"caveat"}}"#,
+        ]
+        .join("\n");
+        let session = import(Format::Claude, &raw).unwrap().remove(0);
+        assert_eq!(session.title, "Fix the parser");
+        assert_eq!(session.messages.len(), 2);
+        assert_eq!(session.messages[1].content, "Let me look.\n\nFound it.");
+        let call = &session.messages[1].tool_calls[0];
+        assert_eq!((call.tool.as_str(), call.output.as_str(), call.ok), ("Read", "fn parse() {}", true));
+        assert!(call.input.contains("src/parse.rs"));
+        assert!(import(Format::Claude, "{\"type\":\"summary\"}").unwrap_err().to_string().contains("no messages"));
+    }
+
+    #[test]
+    fn test_openai_lines_and_conversations() -> anyhow::Result<()> {
+        let raw = [
+            r#"{"messages":[{"role":"system","content":"be brief"},{"role":"user","content":[{"type":"text","text":"hi"}]},{"role":"assistant","content":null,"tool_calls":[{"id":"c1","type":"function","function":{"name":"ls","arguments":"{}"}}]},{"role":"tool","tool_call_id":"c1","content":"a.rs"},{"role":"assistant","content":"one file"}]}"#,
+            r#"{"role":"user","content":"loose question"}"#,
+            r#"{"role":"assistant","content":"loose answer"}"#,
+        ]
+        .join("\n");
+        let sessions = import(Format::OpenaiJsonl, &raw)?;
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions[0].system.as_deref(), Some("be brief"));
+        assert_eq!(sessions[0].messages[1].content, "one file");
+        assert_eq!(sessions[0].messages[1].tool_calls[0].output, "a.rs");
+        assert_eq!(sessions[1].messages.len(), 2);
+
+        let td = tempfile::tempdir()?;
+        let store = SessionStore::new(td.path());
+        for s in &sessions {
+            store.save(s)?;
+        }
+        let back = store.find(&sessions[0].id[..8])?.to_conversation();
+        assert_eq!((back.id.as_str(), back.messages.len()), (sessions[0].id.as_str(), 2));
+        assert_eq!(store.list().len(), 2);
+        assert!(import(Format::OpenaiJsonl, "not json").unwrap_err().to_string().contains("line 1"));
+        Ok(())
+    }
+}
@@ -0,0 +1,61 @@
+use parking_lot::RwLock;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// Key/value store shared between sessions and agents.
+///
+/// Cloning is cheap and every clone points at the same map, so a handle can be
+/// given to the TUI, to each session's context and to agent tools.
+#[derive(Debug, Default, Clone)]
+pub struct SharedVars {
+    inner: Arc<RwLock<BTreeMap<String, String>>>,
+}
+
+impl SharedVars {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set `key` to `value`, returning the previous value if any.
+    pub fn set(&self, key: &str, value: &str) -> Option<String> {
+        self.inner.write().insert(key.to_string(), value.to_string())
+    }
+
+    pub fn get(&self, key: &str) -> Option<String> {
+        self.inner.read().get(key).cloned()
+    }
+
+    pub fn remove(&self, key: &str) -> Option<String> {
+        self.inner.write().remove(key)
+    }
+
+    /// All variables sorted by key.
+    pub fn list(&self) -> Vec<(String, String)> {
+        self.inner
+            .read()
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.read().is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clones_share_state() {
+        let a = SharedVars::new();
+        let b = a.clone();
+        assert_eq!(a.set("branch", "main"), None);
+        assert_eq!(b.get("branch").as_deref(), Some("main"));
+        assert_eq!(b.set("branch", "dev").as_deref(), Some("main"));
+        assert_eq!(a.list(), vec![("branch".to_string(), "dev".to_string())]);
+        assert_eq!(a.remove("branch").as_deref(), Some("dev"));
+        assert!(b.is_empty());
+    }
+}
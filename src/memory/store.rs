@@ -32,6 +32,11 @@ impl MemoryStore {
         self.inner.read().short_term.clone()
     }
 
+    /// Replace short-term memory, e.g. with what a checkpoint recorded.
+    pub fn set_short(&self, messages: Vec<Message>) {
+        self.inner.write().short_term = messages;
+    }
+
     pub fn get_long(&self) -> Vec<Message> {
         self.inner.read().long_term.clone()
     }
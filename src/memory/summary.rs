@@ -0,0 +1,105 @@
+//! Conversation compaction. `/summarize`, or a conversation growing past
+//! `memory.compact_after` messages, has the model summarise the older turns;
+//! the summary replaces them in the context sent with each request (the
+//! originals stay on the summary message for display) and is kept in
+//! long-term memory, `<home>/memory/summaries.jsonl`, by project.
+
+use crate::types::Message;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Role of a summary message in a conversation. It is sent as a system
+/// message.
+pub const ROLE: &str = "summary";
+
+/// `[memory]` in config.toml.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MemoryConfig {
+    /// Compact a conversation once it has more messages than this; 0 only
+    /// compacts on `/summarize`.
+    pub compact_after: usize,
+    /// Latest messages compaction leaves as they are.
+    pub keep_recent: usize,
+    /// Tokens of `/remember` notes sent with each request; notes past it
+    /// are kept but not sent.
+    pub notes_budget: u64,
+    /// `[memory.retention]`: how many saved sessions and transcripts are kept.
+    pub retention: crate::memory::retention::RetentionConfig,
+}
+
+impl Default for MemoryConfig {
+    fn default() -> Self {
+        Self { compact_after: 40, keep_recent: 6, notes_budget: 400, retention: Default::default() }
+    }
+}
+
+/// Request asking the model to summarise `messages`.
+pub fn request(messages: &[Message]) -> Vec<Message> {
+    let transcript: Vec<String> = messages.iter().map(|m| format!("{}: {}", m.role, m.full_text())).collect();
+    vec![
+        Message::new(
+            "system",
+            "Summarise this conversation for your own later reference. Keep decisions, facts about the user's project, \
+             names of files, functions and commands, and open questions. Drop pleasantries. Use short bullet points.",
+        ),
+        Message::new("user", transcript.join("\n\n")),
+    ]
+}
+
+/// A summary in long-term memory.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Summary {
+    pub at: u64,
+    /// Working directory of the session that wrote it.
+    pub project: String,
+    pub conversation: String,
+    /// Number of messages it replaced.
+    pub replaced: usize,
+    pub text: String,
+}
+
+pub struct SummaryStore {
+    path: PathBuf,
+}
+
+impl SummaryStore {
+    pub fn new(home: &Path) -> Self {
+        Self { path: home.join("memory").join("summaries.jsonl") }
+    }
+
+    pub fn add(&self, summary: &Summary) -> anyhow::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut f = std::fs::OpenOptions::new().create(true).append(true).open(&self.path)?;
+        writeln!(f, "{}", serde_json::to_string(summary)?)?;
+        Ok(())
+    }
+
+    /// Summaries written for `project`, oldest first.
+    pub fn for_project(&self, project: &str) -> Vec<Summary> {
+        std::fs::read_to_string(&self.path)
+            .map(|s| s.lines().filter_map(|l| serde_json::from_str::<Summary>(l).ok()).filter(|s| s.project == project).collect())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summaries_are_kept_per_project() {
+        let home = tempfile::tempdir().unwrap();
+        let store = SummaryStore::new(home.path());
+        let summary = |project: &str| Summary { at: 1, project: project.into(), conversation: "c".into(), replaced: 4, text: "- uses sqlx".into() };
+        store.add(&summary("/work/a")).unwrap();
+        store.add(&summary("/work/b")).unwrap();
+        assert_eq!(store.for_project("/work/a"), vec![summary("/work/a")]);
+
+        let req = request(&[Message::new("user", "hi"), Message::new("assistant", "hello")]);
+        assert_eq!(req[1].content, "user: hi\n\nassistant: hello");
+    }
+}
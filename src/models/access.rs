@@ -0,0 +1,176 @@
+//! Who can reach the model server. `[server]` in config.toml sets the
+//! interface it listens on and which browser origins may call its API.
+//! The server has no authentication, so it listens on loopback unless told
+//! otherwise, and the TUI and `agent models serve` warn when it does not.
+//! `model_server_addr` stays the address clients use; only its port is
+//! taken for listening. `/ui` and `/v1` also check the `Host` header, so a
+//! page whose DNS name is rebound to this machine cannot reach them.
+
+use axum::extract::Request;
+use axum::http::{header, HeaderValue, Method, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+
+const ALLOW_METHODS: &str = "GET, POST, DELETE, OPTIONS";
+const ALLOW_HEADERS: &str = "authorization, content-type, idempotency-key, if-none-match, x-request-id";
+/// Response headers scripts on an allowed origin may read.
+const EXPOSE_HEADERS: &str = "etag, idempotent-replayed, retry-after, x-request-id";
+
+/// `[server]` in config.toml.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ServerConfig {
+    /// Interface to listen on; `0.0.0.0` is every interface.
+    pub bind: IpAddr,
+    /// Origins (`https://dash.example:8443`, or `*` for any) whose pages may
+    /// call the API. Empty by default: only the server's own `/ui` can.
+    pub cors_origins: Vec<String>,
+    /// DNS names `/ui` and `/v1` answer to besides `localhost` and IP
+    /// addresses, e.g. the machine's name when it listens on the network.
+    pub allowed_hosts: Vec<String>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self { bind: IpAddr::V4(Ipv4Addr::LOCALHOST), cors_origins: vec![], allowed_hosts: vec![] }
+    }
+}
+
+impl ServerConfig {
+    /// Where to listen: `bind` with the port of `addr`.
+    pub fn listen_addr(&self, addr: SocketAddr) -> SocketAddr {
+        SocketAddr::new(self.bind, addr.port())
+    }
+}
+
+/// The warning to show for a server listening on `addr`: anything but a
+/// loopback address lets other machines in.
+pub fn exposure_warning(addr: SocketAddr) -> Option<String> {
+    if addr.ip().is_loopback() {
+        return None;
+    }
+    let reach = if addr.ip().is_unspecified() { "every network interface" } else { "a network interface" };
+    Some(format!(
+        "the model server on {} listens on {} without authentication: anyone who can reach it can chat, read tasks and use the web UI. Set server.bind = \"127.0.0.1\" unless that is intended, and list the names clients use in server.allowed_hosts (requests to /ui and /v1 under any other name are refused)",
+        addr, reach
+    ))
+}
+
+/// Whether `host` (a `Host` header, port included) names this server: an IP
+/// address, `localhost` or a name in `allowed`. A rebound DNS name is none
+/// of these.
+fn host_allowed(allowed: &[String], host: &str) -> bool {
+    let name = match host.strip_prefix('[') {
+        Some(rest) => rest.split(']').next().unwrap_or(rest),
+        None => host.rsplit_once(':').filter(|(_, port)| port.parse::<u16>().is_ok()).map_or(host, |(name, _)| name),
+    };
+    let name = name.trim_end_matches('.').to_ascii_lowercase();
+    name.parse::<IpAddr>().is_ok()
+        || name == "localhost"
+        || name.ends_with(".localhost")
+        || allowed.iter().any(|a| a.trim_end_matches('.').eq_ignore_ascii_case(&name))
+}
+
+/// Refuses `/ui` and `/v1` requests whose `Host` is not in `host_allowed`.
+/// Requests without one (HTTP/1.0 clients) pass, as does everything else.
+pub async fn host_check(allowed: Arc<Vec<String>>, req: Request, next: Next) -> Response {
+    let path = req.uri().path();
+    let guarded = ["/ui", "/v1"].iter().any(|p| path == *p || path.starts_with(&format!("{}/", p)));
+    let host = req.headers().get(header::HOST).map(|v| v.to_str().unwrap_or_default().to_string());
+    match host {
+        Some(host) if guarded && !host_allowed(&allowed, &host) => (StatusCode::FORBIDDEN, "unknown host").into_response(),
+        _ => next.run(req).await,
+    }
+}
+
+fn allows(origins: &[String], origin: &str) -> bool {
+    origins.iter().any(|o| o == "*" || o.trim_end_matches('/').eq_ignore_ascii_case(origin))
+}
+
+/// CORS for `origins`: preflights from an allowed origin are answered here,
+/// other requests from one get the allow headers. Requests from origins not
+/// on the list get none, so browsers keep their pages out.
+pub async fn cors(origins: Arc<Vec<String>>, req: Request, next: Next) -> Response {
+    let origin = req.headers().get(header::ORIGIN).and_then(|v| v.to_str().ok()).map(str::to_string);
+    let Some(origin) = origin.filter(|o| allows(&origins, o)) else {
+        return next.run(req).await;
+    };
+    let preflight = req.method() == Method::OPTIONS && req.headers().contains_key(header::ACCESS_CONTROL_REQUEST_METHOD);
+    let mut response = if preflight { StatusCode::NO_CONTENT.into_response() } else { next.run(req).await };
+    let headers = response.headers_mut();
+    if let Ok(origin) = HeaderValue::from_str(&origin) {
+        headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+    }
+    headers.append(header::VARY, HeaderValue::from_static("origin"));
+    if preflight {
+        headers.insert(header::ACCESS_CONTROL_ALLOW_METHODS, HeaderValue::from_static(ALLOW_METHODS));
+        headers.insert(header::ACCESS_CONTROL_ALLOW_HEADERS, HeaderValue::from_static(ALLOW_HEADERS));
+        headers.insert(header::ACCESS_CONTROL_MAX_AGE, HeaderValue::from_static("600"));
+    } else {
+        headers.insert(header::ACCESS_CONTROL_EXPOSE_HEADERS, HeaderValue::from_static(EXPOSE_HEADERS));
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ModelManager, ModelServer};
+
+    #[tokio::test]
+    async fn test_only_listed_origins_get_cors_headers() -> anyhow::Result<()> {
+        assert_eq!(exposure_warning("127.0.0.1:11400".parse()?), None);
+        assert!(exposure_warning("0.0.0.0:11400".parse()?).is_some_and(|w| w.contains("every network interface")));
+        assert_eq!(ServerConfig::default().listen_addr("10.0.0.2:9000".parse()?), "127.0.0.1:9000".parse::<SocketAddr>()?);
+
+        let td = tempfile::tempdir()?;
+        let mgr = Arc::new(ModelManager { dir: td.path().to_path_buf() });
+        let server = ModelServer::new(mgr, "127.0.0.1:0".parse()?).with_cors(vec!["https://dash.example/".into()]);
+        let handle = server.start_local_server().await?;
+        let client = reqwest::Client::new();
+        let url = format!("http://{}/v1/models", handle.addr);
+
+        let preflight = client
+            .request(reqwest::Method::OPTIONS, &url)
+            .header("Origin", "https://dash.example")
+            .header("Access-Control-Request-Method", "POST")
+            .send()
+            .await?;
+        assert_eq!(preflight.status(), 204);
+        assert_eq!(preflight.headers()["access-control-allow-origin"], "https://dash.example");
+        assert_eq!(preflight.headers()["access-control-allow-methods"], ALLOW_METHODS);
+
+        let allowed = client.get(&url).header("Origin", "https://dash.example").send().await?;
+        assert_eq!(allowed.headers()["access-control-allow-origin"], "https://dash.example");
+        assert_eq!(allowed.headers()["access-control-expose-headers"], EXPOSE_HEADERS);
+        let other = client.get(&url).header("Origin", "https://evil.example").send().await?;
+        assert!(other.headers().get("access-control-allow-origin").is_none());
+        handle.shutdown().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_ui_and_api_refuse_unknown_host_names() -> anyhow::Result<()> {
+        let allowed = vec!["box.lan".to_string()];
+        for host in ["127.0.0.1:11400", "[::1]:11400", "localhost:11400", "LOCALHOST", "app.localhost", "box.lan:80", "10.0.0.2"] {
+            assert!(host_allowed(&allowed, host), "{host}");
+        }
+        for host in ["evil.example", "evil.example:11400", "localhost.evil.example", "box.lan.evil.example"] {
+            assert!(!host_allowed(&allowed, host), "{host}");
+        }
+
+        let td = tempfile::tempdir()?;
+        let mgr = Arc::new(ModelManager { dir: td.path().to_path_buf() });
+        let handle = ModelServer::new(mgr, "127.0.0.1:0".parse()?).start_local_server().await?;
+        let client = reqwest::Client::new();
+        let url = format!("http://{}/v1/models", handle.addr);
+        assert_eq!(client.get(&url).header("Host", "evil.example").send().await?.status(), 403);
+        assert_eq!(client.get(format!("http://{}/ui", handle.addr)).header("Host", "evil.example").send().await?.status(), 403);
+        assert_eq!(client.get(&url).send().await?.status(), 200);
+        handle.shutdown().await;
+        Ok(())
+    }
+}
@@ -0,0 +1,200 @@
+use crate::models::metrics::estimate_tokens;
+use crate::models::server::{Provider, Timings};
+use crate::types::Message;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// Prompt sizes (in words, our token estimate) each benchmark runs at.
+pub const CONTEXT_SIZES: [usize; 3] = [128, 512, 2048];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchRun {
+    pub context: usize,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub elapsed_ms: f64,
+    pub prefill_tps: f64,
+    pub gen_tps: f64,
+    /// Token counts and rates come from the backend's timings; otherwise
+    /// they are estimates over the whole request time.
+    #[serde(default)]
+    pub measured: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchResult {
+    /// When it ran, in seconds since the epoch.
+    #[serde(default)]
+    pub at: u64,
+    pub model: String,
+    pub provider: String,
+    pub load_ms: u128,
+    /// Resident memory growth while loading, when the platform reports it.
+    pub rss_delta_bytes: Option<u64>,
+    pub runs: Vec<BenchRun>,
+}
+
+/// Resident set size of this process (Linux only).
+fn rss_bytes() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|l| l.starts_with("VmRSS:"))?;
+    let kb: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kb * 1024)
+}
+
+/// Repeat `seed` until the prompt reaches roughly `words` tokens.
+fn build_prompt(seed: &str, words: usize) -> String {
+    let seed: Vec<&str> = seed.split_whitespace().collect();
+    let seed = if seed.is_empty() { vec!["lorem"] } else { seed };
+    seed.iter().cycle().take(words).cloned().collect::<Vec<_>>().join(" ")
+}
+
+fn rate(tokens: u64, ms: f64) -> f64 {
+    if ms <= 0.0 {
+        return 0.0;
+    }
+    tokens as f64 * 1000.0 / ms
+}
+
+/// One benchmark run: rates from `timings` when the backend reports them,
+/// else both from the whole request time.
+fn bench_run(context: usize, prompt: &str, reply: &str, elapsed_ms: f64, timings: Option<Timings>) -> BenchRun {
+    match timings {
+        Some(t) => BenchRun {
+            context,
+            prompt_tokens: t.prompt_n,
+            completion_tokens: t.predicted_n,
+            elapsed_ms,
+            prefill_tps: rate(t.prompt_n, t.prompt_ms),
+            gen_tps: rate(t.predicted_n, t.predicted_ms),
+            measured: true,
+        },
+        None => {
+            let (prompt_tokens, completion_tokens) = (estimate_tokens(prompt), estimate_tokens(reply));
+            BenchRun {
+                context,
+                prompt_tokens,
+                completion_tokens,
+                elapsed_ms,
+                prefill_tps: rate(prompt_tokens, elapsed_ms),
+                gen_tps: rate(completion_tokens, elapsed_ms),
+                measured: false,
+            }
+        }
+    }
+}
+
+/// Start the provider, then time one chat call per context size.
+pub async fn run(model: &str, provider: &dyn Provider, seed: &str) -> anyhow::Result<BenchResult> {
+    let rss_before = rss_bytes();
+    let started = Instant::now();
+    provider.start().await?;
+    let load_ms = started.elapsed().as_millis();
+    let rss_delta_bytes = match (rss_before, rss_bytes()) {
+        (Some(a), Some(b)) => Some(b.saturating_sub(a)),
+        _ => None,
+    };
+
+    let mut runs = vec![];
+    for context in CONTEXT_SIZES {
+        let prompt = build_prompt(seed, context);
+        let messages = vec![Message::new("user", prompt)];
+        let started = Instant::now();
+        let (reply, timings) = provider.chat_timed(&messages).await?;
+        let elapsed_ms = started.elapsed().as_secs_f64() * 1000.0;
+        runs.push(bench_run(context, &messages[0].content, &reply, elapsed_ms, timings));
+    }
+    provider.stop().await.ok();
+    Ok(BenchResult { at: crate::tasks::now_secs(), model: model.to_string(), provider: provider.name().to_string(), load_ms, rss_delta_bytes, runs })
+}
+
+impl BenchResult {
+    pub fn print_table(&self) {
+        println!("{} ({}) load {} ms, rss +{}", self.model, self.provider, self.load_ms,
+            self.rss_delta_bytes.map(|b| format!("{} KiB", b / 1024)).unwrap_or_else(|| "n/a".into()));
+        println!("{:>8} {:>10} {:>10} {:>12} {:>12}", "context", "prompt", "output", "prefill t/s", "gen t/s");
+        for r in &self.runs {
+            println!("{:>8} {:>10} {:>10} {:>12.1} {:>12.1}", r.context, r.prompt_tokens, r.completion_tokens, r.prefill_tps, r.gen_tps);
+        }
+        if self.runs.iter().any(|r| !r.measured) {
+            println!("(the provider reports no timings: rates are estimated over the whole request time)");
+        }
+    }
+
+    /// Append this run to the model's results in `dir`.
+    pub fn save(&self, dir: &Path) -> anyhow::Result<PathBuf> {
+        use std::io::Write;
+        fs::create_dir_all(dir)?;
+        let p = dir.join(format!("{}.jsonl", self.model));
+        let mut file = fs::OpenOptions::new().create(true).append(true).open(&p)?;
+        writeln!(file, "{}", serde_json::to_string(self)?)?;
+        Ok(p)
+    }
+
+    /// Every result previously saved in `dir`, fastest generation first.
+    /// Single-result `.json` files from before runs were appended count too.
+    pub fn load_all(dir: &Path) -> Vec<BenchResult> {
+        let mut out: Vec<BenchResult> = fs::read_dir(dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter_map(|e| fs::read_to_string(e.path()).ok())
+            .flat_map(|s| match serde_json::from_str::<BenchResult>(&s) {
+                Ok(legacy) => vec![legacy],
+                Err(_) => s.lines().filter_map(|l| serde_json::from_str(l).ok()).collect(),
+            })
+            .collect();
+        out.sort_by(|a, b| b.mean_gen_tps().partial_cmp(&a.mean_gen_tps()).unwrap_or(std::cmp::Ordering::Equal));
+        out
+    }
+
+    pub fn mean_gen_tps(&self) -> f64 {
+        if self.runs.is_empty() {
+            return 0.0;
+        }
+        self.runs.iter().map(|r| r.gen_tps).sum::<f64>() / self.runs.len() as f64
+    }
+}
+
+/// Print saved results side by side so quantizations can be compared.
+pub fn print_comparison(results: &[BenchResult]) {
+    println!("{:<32} {:>10} {:>10} {:>12}", "model", "provider", "load ms", "gen t/s");
+    for r in results {
+        println!("{:<32} {:>10} {:>10} {:>12.1}", r.model, r.provider, r.load_ms, r.mean_gen_tps());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::server::MockProvider;
+
+    #[tokio::test]
+    async fn test_bench_mock_provider_and_save() -> anyhow::Result<()> {
+        let provider = MockProvider { model: PathBuf::from("tiny.gguf") };
+        let result = run("tiny", &provider, "hello world").await?;
+        assert_eq!(result.runs.len(), CONTEXT_SIZES.len());
+        assert_eq!(result.runs[0].prompt_tokens, 128);
+        assert!(!result.runs[0].measured);
+
+        let td = tempfile::tempdir()?;
+        result.save(td.path())?;
+        result.save(td.path())?;
+        let saved = BenchResult::load_all(td.path());
+        assert_eq!(saved.len(), 2, "a second run is added, not written over the first");
+        assert_eq!(saved[0].model, "tiny");
+        Ok(())
+    }
+
+    #[test]
+    fn test_rates_come_from_backend_timings() {
+        let timings = Timings { prompt_n: 500, prompt_ms: 250.0, predicted_n: 40, predicted_ms: 2000.0 };
+        let run = bench_run(512, "ignored", "ignored", 2300.0, Some(timings));
+        assert_eq!((run.prompt_tokens, run.completion_tokens), (500, 40));
+        assert_eq!((run.prefill_tps, run.gen_tps), (2000.0, 20.0));
+        assert!(run.measured);
+        assert_eq!(Timings::of(&serde_json::json!({"timings": {"prompt_n": 500, "prompt_ms": 250.0, "predicted_n": 40, "predicted_ms": 2000.0, "cache_n": 3}})), Some(timings));
+    }
+}
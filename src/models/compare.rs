@@ -0,0 +1,84 @@
+use crate::models::metrics::estimate_tokens;
+use crate::models::server::Provider;
+use crate::types::Message;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// One side of an A/B comparison.
+#[derive(Debug, Clone)]
+pub struct CompareColumn {
+    pub label: String,
+    pub reply: Result<String, String>,
+    pub latency_ms: f64,
+    pub tokens: u64,
+}
+
+/// Latency is the chat alone: loading the model is not part of it.
+async fn ask(label: String, provider: Arc<dyn Provider>, messages: Vec<Message>) -> CompareColumn {
+    if let Err(e) = provider.start().await {
+        return CompareColumn { label, reply: Err(format!("start failed: {}", e)), latency_ms: 0.0, tokens: 0 };
+    }
+    let started = Instant::now();
+    let reply = provider.chat(&messages).await.map_err(|e| e.to_string());
+    let latency_ms = started.elapsed().as_secs_f64() * 1000.0;
+    let tokens = reply.as_deref().map(estimate_tokens).unwrap_or(0);
+    CompareColumn { label, reply, latency_ms, tokens }
+}
+
+/// Send the same prompt to both providers concurrently.
+pub async fn side_by_side(
+    prompt: &str,
+    a: (String, Arc<dyn Provider>),
+    b: (String, Arc<dyn Provider>),
+) -> [CompareColumn; 2] {
+    let messages = vec![Message::new("user", prompt)];
+    let (left, right) = tokio::join!(ask(a.0, a.1, messages.clone()), ask(b.0, b.1, messages));
+    [left, right]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::server::MockProvider;
+
+    #[tokio::test]
+    async fn test_side_by_side_reports_each_column() {
+        let a: Arc<dyn Provider> = Arc::new(MockProvider { model: "a.gguf".into() });
+        let b: Arc<dyn Provider> = Arc::new(MockProvider { model: "b.gguf".into() });
+        let [left, right] = side_by_side("hi there", ("a".into(), a), ("b".into(), b)).await;
+        assert_eq!(left.label, "a");
+        assert_eq!(left.reply.as_deref(), Ok("[mock:a.gguf] echo: hi there"));
+        assert_eq!(right.reply.as_deref(), Ok("[mock:b.gguf] echo: hi there"));
+        assert_eq!(right.tokens, 4);
+    }
+
+    struct SlowStart(MockProvider);
+
+    #[async_trait::async_trait]
+    impl Provider for SlowStart {
+        fn name(&self) -> &'static str {
+            "slow"
+        }
+
+        fn model_id(&self) -> String {
+            self.0.model_id()
+        }
+
+        async fn start(&self) -> anyhow::Result<()> {
+            tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+            Ok(())
+        }
+
+        async fn chat(&self, messages: &[Message]) -> Result<String, crate::errors::ProviderError> {
+            self.0.chat(messages).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_latency_leaves_out_the_model_load() {
+        let slow: Arc<dyn Provider> = Arc::new(SlowStart(MockProvider { model: "a.gguf".into() }));
+        let column = ask("a".into(), slow, vec![Message::new("user", "hi")]).await;
+        assert!(column.reply.is_ok());
+        assert!(column.latency_ms < 300.0, "{}", column.latency_ms);
+    }
+}
@@ -0,0 +1,64 @@
+//! Conversations kept by the model server, for thin clients that send only
+//! the new turn. `POST /v1/conversations` creates one; a chat request that
+//! names it with `"conversation": "<id>"` is answered with the stored
+//! history in front of its messages, and the turn (the request's messages
+//! and the reply) is added to the history once the reply is produced. Each
+//! history is a [`MemoryStore`]'s short-term memory. Conversations live in
+//! memory, are dropped after [`IDLE`] without use, and at most [`CAPACITY`]
+//! are kept.
+
+use crate::memory::store::MemoryStore;
+use crate::types::Message;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+pub const IDLE: Duration = Duration::from_secs(60 * 60);
+pub const CAPACITY: usize = 256;
+
+#[derive(Default)]
+pub struct Conversations {
+    /// History and last use, by id.
+    stores: Mutex<HashMap<String, (Instant, MemoryStore)>>,
+}
+
+impl Conversations {
+    /// A new conversation starting with `messages` (e.g. a system prompt);
+    /// its id.
+    pub fn create(&self, messages: Vec<Message>) -> String {
+        let id = format!("conv_{}", uuid::Uuid::new_v4().simple());
+        let store = MemoryStore::new();
+        for m in messages {
+            store.add_short(m);
+        }
+        let mut stores = self.stores.lock().unwrap_or_else(|e| e.into_inner());
+        stores.retain(|_, (used, _)| used.elapsed() < IDLE);
+        if stores.len() >= CAPACITY {
+            if let Some(oldest) = stores.iter().min_by_key(|(_, (used, _))| *used).map(|(id, _)| id.clone()) {
+                stores.remove(&oldest);
+            }
+        }
+        stores.insert(id.clone(), (Instant::now(), store));
+        id
+    }
+
+    /// The conversation's history; using it keeps the conversation alive.
+    pub fn get(&self, id: &str) -> Option<MemoryStore> {
+        let mut stores = self.stores.lock().unwrap_or_else(|e| e.into_inner());
+        match stores.get_mut(id) {
+            Some((used, _)) if used.elapsed() >= IDLE => {
+                stores.remove(id);
+                None
+            }
+            Some((used, store)) => {
+                *used = Instant::now();
+                Some(store.clone())
+            }
+            None => None,
+        }
+    }
+
+    pub fn delete(&self, id: &str) -> bool {
+        self.stores.lock().unwrap_or_else(|e| e.into_inner()).remove(id).is_some()
+    }
+}
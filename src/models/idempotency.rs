@@ -0,0 +1,99 @@
+//! `Idempotency-Key` for the model server's chat route. A client that lost
+//! the connection retries with the same key; the server then answers with
+//! the reply it produced for the first try, or waits for that try when it
+//! is still running, instead of generating the reply twice. Keys are
+//! remembered for [`TTL`], at most [`CAPACITY`] of them; a try that failed
+//! is forgotten at once so its retry runs again.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::OnceCell;
+
+pub const HEADER: &str = "idempotency-key";
+/// Set on answers that come from an earlier try.
+pub const REPLAYED: &str = "idempotent-replayed";
+pub const TTL: Duration = Duration::from_secs(10 * 60);
+pub const CAPACITY: usize = 1024;
+
+/// A key's reply, once produced, and when the key was first seen.
+type Slot<T> = (Instant, Arc<OnceCell<T>>);
+
+/// Replies of type `T` by key.
+pub struct Replies<T> {
+    slots: Mutex<HashMap<String, Slot<T>>>,
+}
+
+impl<T> Default for Replies<T> {
+    fn default() -> Self {
+        Self { slots: Mutex::new(HashMap::new()) }
+    }
+}
+
+impl<T: Clone> Replies<T> {
+    /// The reply for `key`: `produce`'s, or the one an earlier try with the
+    /// same key produced. The flag is set for the latter. Replies `keep`
+    /// refuses are not remembered.
+    pub async fn run<F>(&self, key: &str, produce: F, keep: impl Fn(&T) -> bool) -> (T, bool)
+    where
+        F: Future<Output = T>,
+    {
+        let slot = {
+            let mut slots = self.slots.lock().unwrap_or_else(|e| e.into_inner());
+            slots.retain(|_, (at, _)| at.elapsed() < TTL);
+            if slots.len() >= CAPACITY && !slots.contains_key(key) {
+                if let Some(oldest) = slots.iter().min_by_key(|(_, (at, _))| *at).map(|(k, _)| k.clone()) {
+                    slots.remove(&oldest);
+                }
+            }
+            Arc::clone(&slots.entry(key.to_string()).or_insert_with(|| (Instant::now(), Arc::new(OnceCell::new()))).1)
+        };
+        let mut produced = false;
+        let reply = slot
+            .get_or_init(|| async {
+                produced = true;
+                produce.await
+            })
+            .await
+            .clone();
+        if produced && !keep(&reply) {
+            let mut slots = self.slots.lock().unwrap_or_else(|e| e.into_inner());
+            if slots.get(key).is_some_and(|(_, s)| Arc::ptr_eq(s, &slot)) {
+                slots.remove(key);
+            }
+        }
+        (reply, !produced)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[tokio::test]
+    async fn test_a_key_produces_its_reply_once() {
+        let replies: Replies<Result<u32, u32>> = Replies::default();
+        let runs = AtomicU32::new(0);
+        let produce = |value: Result<u32, u32>| {
+            let runs = &runs;
+            async move {
+                runs.fetch_add(1, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                value
+            }
+        };
+        let keep = |r: &Result<u32, u32>| r.is_ok();
+        // concurrent tries wait for the first one
+        let (a, b) = tokio::join!(replies.run("k", produce(Ok(1)), keep), replies.run("k", produce(Ok(2)), keep));
+        assert_eq!((a, b), ((Ok(1), false), (Ok(1), true)));
+        assert_eq!(replies.run("k", produce(Ok(3)), keep).await, (Ok(1), true));
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+
+        // a failure is not replayed
+        assert_eq!(replies.run("bad", produce(Err(7)), keep).await, (Err(7), false));
+        assert_eq!(replies.run("bad", produce(Ok(8)), keep).await, (Ok(8), false));
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+    }
+}
@@ -0,0 +1,280 @@
+//! Running-instance bookkeeping. The model server falls back to a free port
+//! from `[model_server_ports]` when `model_server_addr` is taken and records
+//! where it really listens in `<home>/run/model_server.json`, which the
+//! TUI and CLI consult before connecting. [`InstanceLock`] keeps two
+//! processes of the same kind off one `model_dir`. [`host`] starts a server
+//! in this process (`agent models serve start`, the TUI's `/serve`), and
+//! [`request_stop`] stops one in another process through its shutdown route.
+
+use crate::errors::ConfigError;
+use crate::models::server::Provider;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// `[model_server_ports]` in config.toml.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PortRange {
+    /// Try the range when `model_server_addr` is busy; otherwise fail.
+    pub auto: bool,
+    pub start: u16,
+    pub end: u16,
+}
+
+impl Default for PortRange {
+    fn default() -> Self {
+        Self { auto: true, start: 11400, end: 11499 }
+    }
+}
+
+/// Bind `addr`, or the first free port of `ports` on the same interface.
+pub fn bind(addr: SocketAddr, ports: &PortRange) -> std::io::Result<TcpListener> {
+    match TcpListener::bind(addr) {
+        Err(e) if e.kind() == std::io::ErrorKind::AddrInUse && ports.auto => {
+            for port in (ports.start..=ports.end).filter(|p| *p != addr.port()) {
+                if let Ok(listener) = TcpListener::bind(SocketAddr::new(addr.ip(), port)) {
+                    tracing::warn!("{} is busy; model server moved to port {}", addr, port);
+                    return Ok(listener);
+                }
+            }
+            Err(std::io::Error::new(std::io::ErrorKind::AddrInUse, format!("{} and ports {}-{} are all busy", addr, ports.start, ports.end)))
+        }
+        other => other,
+    }
+}
+
+/// Contents of the runtime state file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServerState {
+    /// Address actually bound.
+    pub addr: SocketAddr,
+    /// What config.toml asked for.
+    pub configured: SocketAddr,
+    pub pid: u32,
+    pub started: u64,
+    /// Bearer token of the shutdown route.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    /// Model it was started with, for a restart without one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+}
+
+fn state_path(home: &Path) -> PathBuf {
+    home.join("run").join("model_server.json")
+}
+
+impl ServerState {
+    /// Write the state file; it is removed when the returned guard drops.
+    pub fn publish(self, home: &Path) -> anyhow::Result<StateGuard> {
+        let path = state_path(home);
+        std::fs::create_dir_all(path.parent().expect("state file has a parent"))?;
+        std::fs::write(&path, serde_json::to_vec_pretty(&self)?)?;
+        Ok(StateGuard { path, pid: self.pid })
+    }
+
+    /// The recorded server, if its address still accepts connections.
+    pub fn read(home: &Path) -> Option<ServerState> {
+        let state: ServerState = serde_json::from_slice(&std::fs::read(state_path(home)).ok()?).ok()?;
+        // a crashed server leaves its file behind
+        TcpStream::connect_timeout(&state.addr, Duration::from_millis(200)).ok()?;
+        Some(state)
+    }
+}
+
+pub struct StateGuard {
+    path: PathBuf,
+    pid: u32,
+}
+
+impl Drop for StateGuard {
+    fn drop(&mut self) {
+        // only remove our own record, not a newer server's
+        let ours = std::fs::read(&self.path).ok().and_then(|b| serde_json::from_slice::<ServerState>(&b).ok()).is_some_and(|s| s.pid == self.pid);
+        if ours {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// Where the model server configured at `configured` is really listening.
+pub fn running_addr(home: &Path, configured: SocketAddr) -> SocketAddr {
+    match ServerState::read(home) {
+        Some(state) if state.configured == configured => state.addr,
+        _ => configured,
+    }
+}
+
+/// `endpoint`, moved to the running server's port when it points at the
+/// configured model server address.
+pub fn resolve_endpoint(home: &Path, endpoint: &str, configured: SocketAddr) -> String {
+    let target = format!("://{}", configured);
+    let Some(at) = endpoint.find(&target) else { return endpoint.to_string() };
+    let rest = &endpoint[at + target.len()..];
+    if !(rest.is_empty() || rest.starts_with('/')) {
+        return endpoint.to_string();
+    }
+    let actual = running_addr(home, configured);
+    format!("{}://{}{}", &endpoint[..at], actual, rest)
+}
+
+/// A model server running in this process, with its hold on `model_dir`
+/// and its state file; both are released when it is dropped.
+pub struct Hosted {
+    pub handle: crate::models::server::ServerHandle,
+    pub model: Option<String>,
+    _lock: InstanceLock,
+    _state: StateGuard,
+}
+
+impl Hosted {
+    /// `ServerHandle::shutdown`, keeping the lock and the state file until
+    /// the server has stopped.
+    pub async fn shutdown(self) -> bool {
+        self.handle.shutdown().await
+    }
+}
+
+/// Start the model server `cfg` describes in this process, serving `model`
+/// through llama.cpp (or the echo mock with `models.allow_mock`). `lock`
+/// is how long to wait for another server to let go of `model_dir`, e.g.
+/// while it drains for a restart. The request log goes to `requests`, or
+/// the app log without it. Returns the server and what happened.
+pub async fn host(
+    cfg: &crate::config::RuntimeConfig,
+    model: Option<String>,
+    warmup: crate::models::preload::Warmup,
+    lock: Duration,
+    requests: Option<tokio::sync::mpsc::UnboundedSender<crate::models::server::RequestLog>>,
+) -> anyhow::Result<(Hosted, String)> {
+    let manager = std::sync::Arc::new(crate::models::ModelManager::new(Some(cfg.model_dir.clone()))?);
+    let _lock = InstanceLock::acquire_within(&cfg.model_dir, "server", lock)?;
+    let mut server = crate::models::ModelServer::new(manager.clone(), cfg.server.listen_addr(cfg.model_server_addr))
+        .with_ports(cfg.model_server_ports.clone())
+        .with_cors(cfg.server.cors_origins.clone())
+        .with_allowed_hosts(cfg.server.allowed_hosts.clone())
+        .with_warmup(warmup)
+        .with_tasks(crate::tasks::TaskStore::new(crate::paths::home()));
+    if let Some(requests) = requests {
+        server = server.with_request_log(requests);
+    }
+    let handle = server.start_local_server().await?;
+    let mut said = format!("Model server started on {}", handle.addr);
+    if let Some(name) = &model {
+        let found = manager.discover()?.into_iter().find(|m| &m.name == name).ok_or_else(|| crate::errors::ProviderError::ModelNotFound(name.clone()))?;
+        let llama = crate::models::server::LlamaProvider::new(None, found.path.clone(), cfg.model_server_addr).with_prefix_cache(cfg.models.prefix_cache.clone());
+        match llama.start().await {
+            Ok(_) => {
+                server.register_provider(name, std::sync::Arc::new(llama)).await?;
+                said.push_str(&format!(" and llama provider registered for {}", name));
+            }
+            Err(e) if cfg.models.allow_mock => {
+                server.register_mock_for_model(name).await?;
+                said.push_str(&format!(" but llama provider failed; models.allow_mock is set, so {} answers with the echo mock: {}", name, e));
+            }
+            Err(e) => return Err(e.context(format!("could not serve {}", name))),
+        }
+    }
+    let _state = ServerState {
+        addr: handle.addr,
+        configured: cfg.model_server_addr,
+        pid: std::process::id(),
+        started: crate::tasks::now_secs(),
+        token: Some(handle.token.clone()),
+        model: model.clone(),
+    }
+    .publish(&crate::paths::home())?;
+    Ok((Hosted { handle, model, _lock, _state }, said))
+}
+
+/// Ask the server recorded under `home` to stop, and wait until it has
+/// (up to its drain time). `None` when no server is running.
+pub async fn request_stop(home: &Path) -> anyhow::Result<Option<ServerState>> {
+    let Some(state) = ServerState::read(home) else { return Ok(None) };
+    let Some(token) = &state.token else {
+        anyhow::bail!("the model server on {} (pid {}) predates remote shutdown; stop it with Ctrl-C", state.addr, state.pid);
+    };
+    let url = format!("http://{}{}", state.addr, crate::models::server::SHUTDOWN_ROUTE);
+    crate::net::client(crate::net::MODEL_SERVER).post(&url).bearer_auth(token).send().await?.error_for_status()?;
+    let deadline = std::time::Instant::now() + crate::models::server::DRAIN + Duration::from_secs(2);
+    while ServerState::read(home).is_some_and(|s| s.pid == state.pid) {
+        if std::time::Instant::now() > deadline {
+            anyhow::bail!("the model server on {} (pid {}) did not stop within {:?}", state.addr, state.pid, crate::models::server::DRAIN);
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    Ok(Some(state))
+}
+
+/// Exclusive hold on `dir` for one kind of process (`tui`, `server`),
+/// released when dropped or when the process dies.
+#[derive(Debug)]
+pub struct InstanceLock {
+    _file: File,
+}
+
+impl InstanceLock {
+    pub fn acquire(dir: &Path, kind: &str) -> anyhow::Result<InstanceLock> {
+        std::fs::create_dir_all(dir)?;
+        let path = dir.join(format!(".{}.lock", kind));
+        let file = File::options().read(true).write(true).create(true).truncate(false).open(&path)?;
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(std::fs::TryLockError::WouldBlock) => {
+                let holder = std::fs::read_to_string(&path).unwrap_or_default();
+                return Err(ConfigError::InstanceLocked { kind: kind.to_string(), pid: holder.trim().to_string(), dir: dir.display().to_string() }.into());
+            }
+            Err(std::fs::TryLockError::Error(e)) => return Err(e.into()),
+        }
+        file.set_len(0)?;
+        std::io::Write::write_all(&mut &file, std::process::id().to_string().as_bytes())?;
+        Ok(InstanceLock { _file: file })
+    }
+
+    /// `acquire`, retrying for up to `wait` while another process holds it.
+    pub fn acquire_within(dir: &Path, kind: &str, wait: Duration) -> anyhow::Result<InstanceLock> {
+        let deadline = std::time::Instant::now() + wait;
+        loop {
+            match Self::acquire(dir, kind) {
+                Err(_) if std::time::Instant::now() < deadline => std::thread::sleep(Duration::from_millis(100)),
+                other => return other,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_busy_port_falls_back_and_is_recorded() {
+        let taken = TcpListener::bind("127.0.0.1:0").unwrap();
+        let configured = taken.local_addr().unwrap();
+        let free = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let ports = PortRange { auto: true, start: free, end: free };
+        let listener = bind(configured, &ports).unwrap();
+        let actual = listener.local_addr().unwrap();
+        assert_eq!(actual.port(), free);
+        assert!(bind(configured, &PortRange { auto: false, ..ports }).is_err());
+
+        let home = tempfile::tempdir().unwrap();
+        let endpoint = format!("http://{}/v1", configured);
+        assert_eq!(resolve_endpoint(home.path(), &endpoint, configured), endpoint);
+        let guard = ServerState { addr: actual, configured, pid: std::process::id(), started: 0, token: None, model: None }.publish(home.path()).unwrap();
+        assert_eq!(resolve_endpoint(home.path(), &endpoint, configured), format!("http://{}/v1", actual));
+        assert_eq!(resolve_endpoint(home.path(), "http://10.0.0.1:8080", configured), "http://10.0.0.1:8080");
+        drop(guard);
+        assert_eq!(running_addr(home.path(), configured), configured);
+
+        let lock = InstanceLock::acquire(home.path(), "tui").unwrap();
+        let err = InstanceLock::acquire(home.path(), "tui").unwrap_err().to_string();
+        assert!(err.contains(&format!("pid {}", std::process::id())), "{}", err);
+        assert!(InstanceLock::acquire(home.path(), "server").is_ok());
+        drop(lock);
+        assert!(InstanceLock::acquire(home.path(), "tui").is_ok());
+    }
+}
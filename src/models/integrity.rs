@@ -0,0 +1,368 @@
+//! Catching corrupted and truncated model files before llama.cpp trips over
+//! them with an error that says nothing about the file.
+//!
+//! Two checks:
+//! - structure: a GGUF file's header is read and the end of its last tensor
+//!   worked out, so a file cut short is caught without reading the weights.
+//!   It runs before llama.cpp loads a model.
+//! - checksum: the file's SHA-256 against the one recorded in
+//!   `<model_dir>/.checksums.json`. It is recorded on `agent models import`,
+//!   when the TUI sees a download land in `model_dir`, and by
+//!   `agent models verify --record`.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+
+const CHECKSUMS: &str = ".checksums.json";
+const GGUF_MAGIC: &[u8; 4] = b"GGUF";
+/// More metadata or tensors than this is a broken header, not a model.
+const MAX_ITEMS: u64 = 1 << 24;
+
+/// What was recorded of a model file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Recorded {
+    pub sha256: String,
+    pub size: u64,
+}
+
+/// `.checksums.json`: recorded files by file name.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Checksums {
+    pub files: BTreeMap<String, Recorded>,
+}
+
+impl Checksums {
+    pub fn load(dir: &Path) -> Self {
+        std::fs::read_to_string(dir.join(CHECKSUMS)).ok().and_then(|s| serde_json::from_str(&s).ok()).unwrap_or_default()
+    }
+
+    pub fn save(&self, dir: &Path) -> anyhow::Result<()> {
+        let tmp = dir.join(format!("{}.tmp", CHECKSUMS));
+        std::fs::write(&tmp, serde_json::to_string_pretty(self)?)?;
+        std::fs::rename(tmp, dir.join(CHECKSUMS))?;
+        Ok(())
+    }
+}
+
+pub fn sha256_file(path: &Path) -> anyhow::Result<String> {
+    let mut file = BufReader::with_capacity(1 << 20, File::open(path)?);
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 1 << 20];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().and_then(|n| n.to_str()).unwrap_or_default().to_string()
+}
+
+/// Hash `path` and record it in its directory's checksums.
+pub fn record(path: &Path) -> anyhow::Result<Recorded> {
+    let dir = path.parent().ok_or_else(|| anyhow::anyhow!("{} has no directory", path.display()))?;
+    let recorded = Recorded { sha256: sha256_file(path)?, size: std::fs::metadata(path)?.len() };
+    let mut checksums = Checksums::load(dir);
+    checksums.files.insert(file_name(path), recorded.clone());
+    checksums.save(dir)?;
+    Ok(recorded)
+}
+
+/// Drop `path` from its directory's checksums.
+pub fn forget(path: &Path) -> anyhow::Result<()> {
+    let Some(dir) = path.parent() else { return Ok(()) };
+    let mut checksums = Checksums::load(dir);
+    if checksums.files.remove(&file_name(path)).is_some() {
+        checksums.save(dir)?;
+    }
+    Ok(())
+}
+
+/// The outcome of verifying one file.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Verdict {
+    /// Matches its recorded checksum.
+    Ok,
+    /// Sound structure, but no checksum recorded to compare with.
+    Unrecorded,
+    Truncated { expected: u64, actual: u64 },
+    Corrupt(String),
+    Mismatch { recorded: String, actual: String },
+}
+
+impl Verdict {
+    pub fn is_bad(&self) -> bool {
+        !matches!(self, Verdict::Ok | Verdict::Unrecorded)
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Verdict::Ok => "ok".into(),
+            Verdict::Unrecorded => "ok (no checksum recorded)".into(),
+            Verdict::Truncated { expected, actual } => format!("truncated: {} of {} bytes; download it again", actual, expected),
+            Verdict::Corrupt(reason) => format!("corrupt: {}", reason),
+            Verdict::Mismatch { recorded, actual } => format!("checksum mismatch: recorded {}…, file has {}…", &recorded[..12.min(recorded.len())], &actual[..12.min(actual.len())]),
+        }
+    }
+}
+
+/// Structure, then the checksum when one was recorded.
+pub fn verify(path: &Path) -> anyhow::Result<Verdict> {
+    if let Some(bad) = structure(path)? {
+        return Ok(bad);
+    }
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let Some(recorded) = Checksums::load(dir).files.remove(&file_name(path)) else { return Ok(Verdict::Unrecorded) };
+    let actual = sha256_file(path)?;
+    Ok(if actual == recorded.sha256 { Verdict::Ok } else { Verdict::Mismatch { recorded: recorded.sha256, actual } })
+}
+
+/// `Err` naming what is wrong when `path` is not a sound model file; the
+/// cheap check run before a model is loaded.
+pub fn check(path: &Path) -> anyhow::Result<()> {
+    match structure(path)? {
+        Some(bad) => anyhow::bail!("{} is {}", file_name(path), bad.describe()),
+        None => Ok(()),
+    }
+}
+
+/// What is wrong with the structure of `path`, if anything. Only GGUF
+/// files are looked into.
+pub fn structure(path: &Path) -> anyhow::Result<Option<Verdict>> {
+    if !path.extension().and_then(|e| e.to_str()).is_some_and(|e| e.eq_ignore_ascii_case("gguf")) {
+        return Ok(None);
+    }
+    let actual = std::fs::metadata(path)?.len();
+    let mut reader = Gguf { inner: BufReader::new(File::open(path)?), pos: 0 };
+    match reader.data_end() {
+        Ok(expected) if expected > actual => Ok(Some(Verdict::Truncated { expected, actual })),
+        Ok(_) => Ok(None),
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(Some(Verdict::Corrupt("the file ends inside its header; download it again".into()))),
+        Err(e) => Ok(Some(Verdict::Corrupt(e.to_string()))),
+    }
+}
+
+fn invalid(reason: impl Into<String>) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, reason.into())
+}
+
+/// `(elements per block, bytes per block)` of a ggml tensor type.
+fn block(kind: u32) -> Option<(u64, u64)> {
+    Some(match kind {
+        0 => (1, 4),
+        1 | 30 => (1, 2),
+        2 | 20 => (32, 18),
+        3 => (32, 20),
+        6 => (32, 22),
+        7 => (32, 24),
+        8 => (32, 34),
+        9 => (32, 36),
+        10 => (256, 84),
+        11 | 21 => (256, 110),
+        12 => (256, 144),
+        13 => (256, 176),
+        14 => (256, 210),
+        15 => (256, 292),
+        16 => (256, 66),
+        17 => (256, 74),
+        18 => (256, 98),
+        19 => (256, 50),
+        22 => (256, 82),
+        23 => (256, 136),
+        24 => (1, 1),
+        25 => (1, 2),
+        26 => (1, 4),
+        27 | 28 => (1, 8),
+        29 => (256, 56),
+        34 => (256, 54),
+        35 => (256, 66),
+        _ => return None,
+    })
+}
+
+/// Reads a GGUF header as far as the tensor table.
+struct Gguf {
+    inner: BufReader<File>,
+    pos: u64,
+}
+
+impl Gguf {
+    fn bytes(&mut self, n: usize) -> std::io::Result<Vec<u8>> {
+        let mut buf = vec![0u8; n];
+        self.inner.read_exact(&mut buf)?;
+        self.pos += n as u64;
+        Ok(buf)
+    }
+
+    fn u32(&mut self) -> std::io::Result<u32> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into().unwrap_or_default()))
+    }
+
+    fn u64(&mut self) -> std::io::Result<u64> {
+        Ok(u64::from_le_bytes(self.bytes(8)?.try_into().unwrap_or_default()))
+    }
+
+    fn count(&mut self, what: &str) -> std::io::Result<u64> {
+        let n = self.u64()?;
+        if n > MAX_ITEMS {
+            return Err(invalid(format!("header claims {} {}", n, what)));
+        }
+        Ok(n)
+    }
+
+    fn skip(&mut self, n: u64) -> std::io::Result<()> {
+        let skipped = std::io::copy(&mut (&mut self.inner).take(n), &mut std::io::sink())?;
+        self.pos += skipped;
+        if skipped < n {
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(())
+    }
+
+    fn string(&mut self) -> std::io::Result<String> {
+        let len = self.u64()?;
+        if len > MAX_ITEMS {
+            return Err(invalid(format!("string of {} bytes in the header", len)));
+        }
+        Ok(String::from_utf8_lossy(&self.bytes(len as usize)?).into_owned())
+    }
+
+    /// Skip a metadata value of type `kind`; returns it when it is a u32.
+    fn value(&mut self, kind: u32) -> std::io::Result<Option<u32>> {
+        match kind {
+            0 | 1 | 7 => self.skip(1)?,
+            2 | 3 => self.skip(2)?,
+            4 | 5 => return self.u32().map(Some),
+            6 => self.skip(4)?,
+            8 => {
+                self.string()?;
+            }
+            9 => {
+                let item = self.u32()?;
+                for _ in 0..self.count("array items")? {
+                    self.value(item)?;
+                }
+            }
+            10..=12 => self.skip(8)?,
+            other => return Err(invalid(format!("unknown metadata type {}", other))),
+        }
+        Ok(None)
+    }
+
+    /// Where the tensor data ends, i.e. the size the file should have.
+    fn data_end(&mut self) -> std::io::Result<u64> {
+        if self.bytes(4)? != GGUF_MAGIC {
+            return Err(invalid("not a GGUF file"));
+        }
+        let version = self.u32()?;
+        if !(1..=3).contains(&version) {
+            return Err(invalid(format!("unknown GGUF version {}", version)));
+        }
+        let tensors = self.count("tensors")?;
+        let entries = self.count("metadata entries")?;
+        let mut alignment = 32u64;
+        for _ in 0..entries {
+            let key = self.string()?;
+            let kind = self.u32()?;
+            if let Some(v) = self.value(kind)? {
+                if key == "general.alignment" && v > 0 {
+                    alignment = v as u64;
+                }
+            }
+        }
+        let mut end = 0u64;
+        for _ in 0..tensors {
+            self.string()?;
+            let dims = self.u32()?;
+            if dims > 8 {
+                return Err(invalid(format!("tensor with {} dimensions", dims)));
+            }
+            let mut elements = 1u64;
+            for _ in 0..dims {
+                elements = elements.saturating_mul(self.u64()?);
+            }
+            let kind = self.u32()?;
+            let offset = self.u64()?;
+            // unknown (newer) types are left out of the size
+            if let Some((per_block, bytes)) = block(kind) {
+                end = end.max(offset.saturating_add(elements.div_ceil(per_block).saturating_mul(bytes)));
+            }
+        }
+        Ok(self.pos.div_ceil(alignment) * alignment + end)
+    }
+}
+
+/// Every model file in `dir` (or the one named `name`) with its verdict.
+pub fn verify_dir(dir: &Path, name: Option<&str>) -> anyhow::Result<Vec<(PathBuf, anyhow::Result<Verdict>)>> {
+    let models = crate::models::ModelManager::new(Some(dir.to_path_buf()))?.rescan()?.models;
+    let picked: Vec<_> = models.into_iter().filter(|m| name.is_none_or(|n| m.name == n)).collect();
+    if let (Some(name), true) = (name, picked.is_empty()) {
+        return Err(crate::errors::ProviderError::ModelNotFound(name.to_string()).into());
+    }
+    Ok(picked.into_iter().map(|m| (m.path.clone(), verify(&m.path))).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A GGUF file with one metadata entry and one F32 tensor of `floats`.
+    fn gguf(floats: u64) -> Vec<u8> {
+        let mut out = GGUF_MAGIC.to_vec();
+        out.extend(3u32.to_le_bytes());
+        out.extend(1u64.to_le_bytes());
+        out.extend(1u64.to_le_bytes());
+        let string = |s: &str, out: &mut Vec<u8>| {
+            out.extend((s.len() as u64).to_le_bytes());
+            out.extend(s.as_bytes());
+        };
+        string("general.name", &mut out);
+        out.extend(8u32.to_le_bytes());
+        string("tiny", &mut out);
+        string("w", &mut out);
+        out.extend(1u32.to_le_bytes());
+        out.extend(floats.to_le_bytes());
+        out.extend(0u32.to_le_bytes());
+        out.extend(0u64.to_le_bytes());
+        out.resize(out.len().div_ceil(32) * 32, 0);
+        out.extend(vec![1u8; floats as usize * 4]);
+        out
+    }
+
+    #[test]
+    fn test_truncated_and_changed_models_are_caught() -> anyhow::Result<()> {
+        let td = tempfile::tempdir()?;
+        let path = td.path().join("tiny.gguf");
+        let whole = gguf(100);
+        std::fs::write(&path, &whole)?;
+        assert_eq!(verify(&path)?, Verdict::Unrecorded);
+        record(&path)?;
+        assert_eq!(verify(&path)?, Verdict::Ok);
+        assert!(check(&path).is_ok());
+
+        std::fs::write(&path, &whole[..whole.len() - 10])?;
+        assert_eq!(verify(&path)?, Verdict::Truncated { expected: whole.len() as u64, actual: whole.len() as u64 - 10 });
+        assert!(check(&path).unwrap_err().to_string().contains("truncated"));
+        std::fs::write(&path, &whole[..30])?;
+        assert!(verify(&path)?.describe().contains("ends inside its header"));
+
+        let mut flipped = whole.clone();
+        *flipped.last_mut().unwrap() = 9;
+        std::fs::write(&path, &flipped)?;
+        assert!(matches!(verify(&path)?, Verdict::Mismatch { .. }));
+        std::fs::write(&path, b"not a model at all")?;
+        assert!(matches!(verify(&path)?, Verdict::Corrupt(_)));
+
+        let found = verify_dir(td.path(), None)?;
+        assert_eq!(found.len(), 1, "the checksums file is not a model");
+        Ok(())
+    }
+}
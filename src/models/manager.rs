@@ -1,9 +1,14 @@
+use crate::errors::ProviderError;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
 use std::fs;
 use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::SystemTime;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ModelInfo {
     pub name: String,
     pub path: PathBuf,
@@ -15,16 +20,29 @@ pub struct ModelManager {
     pub dir: PathBuf,
 }
 
+/// A model directory's contents as last read.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Listing {
+    pub models: Vec<ModelInfo>,
+    /// Quoted HTTP entity tag; changes whenever `models` does.
+    pub etag: String,
+    /// The directory's mtime when it was read.
+    modified: Option<SystemTime>,
+}
+
+/// Listings by directory, shared by every manager in the process. A
+/// listing stands until the directory's mtime moves, i.e. a model file is
+/// added, removed or renamed; a file rewritten in place needs a rescan.
+fn listings() -> &'static Mutex<HashMap<PathBuf, Listing>> {
+    static LISTINGS: OnceLock<Mutex<HashMap<PathBuf, Listing>>> = OnceLock::new();
+    LISTINGS.get_or_init(Default::default)
+}
+
 impl ModelManager {
     pub fn new(dir: Option<PathBuf>) -> anyhow::Result<Self> {
         let dir = match dir {
             Some(d) => d,
-            None => {
-                let mut d = dirs::data_dir().ok_or_else(|| anyhow::anyhow!("no data dir available"))?;
-                d.push("super-agent");
-                d.push("models");
-                d
-            }
+            None => crate::paths::models_dir(),
         };
         fs::create_dir_all(&dir)?;
         Ok(Self { dir })
@@ -36,12 +54,43 @@ impl ModelManager {
         Ok((Self { dir: td.path().to_path_buf() }, td))
     }
 
+    /// The models in `dir`, from the cached listing while it is current.
     pub fn discover(&self) -> anyhow::Result<Vec<ModelInfo>> {
+        Ok(self.listing()?.models)
+    }
+
+    /// The cached listing of `dir`, read again when the directory changed.
+    pub fn listing(&self) -> anyhow::Result<Listing> {
+        let modified = fs::metadata(&self.dir)?.modified().ok();
+        let cached = listings().lock().unwrap_or_else(|e| e.into_inner()).get(&self.dir).cloned();
+        match cached {
+            Some(listing) if modified.is_some() && listing.modified == modified => Ok(listing),
+            _ => self.rescan(),
+        }
+    }
+
+    /// Read `dir` whatever the cache holds, and cache the result.
+    pub fn rescan(&self) -> anyhow::Result<Listing> {
+        let modified = fs::metadata(&self.dir)?.modified().ok();
+        let mut models = self.scan()?;
+        models.sort_by(|a, b| a.name.cmp(&b.name));
+        let digest = Sha256::digest(serde_json::to_vec(&models)?);
+        let listing = Listing { etag: format!("\"{}\"", hex::encode(&digest[..8])), models, modified };
+        listings().lock().unwrap_or_else(|e| e.into_inner()).insert(self.dir.clone(), listing.clone());
+        Ok(listing)
+    }
+
+    fn forget(&self) {
+        listings().lock().unwrap_or_else(|e| e.into_inner()).remove(&self.dir);
+    }
+
+    fn scan(&self) -> anyhow::Result<Vec<ModelInfo>> {
         let mut out = vec![];
         for entry in fs::read_dir(&self.dir)? {
             let e = entry?;
             let p = e.path();
-            if p.is_file() {
+            // dotfiles are ours (`.checksums.json`) or the system's
+            if p.is_file() && !e.file_name().to_string_lossy().starts_with('.') {
                 let meta = fs::metadata(&p)?;
                 let size = meta.len();
                 let format = p
@@ -58,7 +107,7 @@ impl ModelManager {
 
     pub fn import(&self, src: &Path) -> anyhow::Result<ModelInfo> {
         if !src.exists() {
-            return Err(anyhow::anyhow!("source model not found"));
+            return Err(ProviderError::ModelNotFound(src.display().to_string()).into());
         }
         let file_name = src.file_name().ok_or_else(|| anyhow::anyhow!("invalid filename"))?;
         let dest = self.dir.join(file_name);
@@ -66,6 +115,8 @@ impl ModelManager {
         let mut r = fs::File::open(src)?;
         let mut w = fs::File::create(&dest)?;
         std::io::copy(&mut r, &mut w)?;
+        self.forget();
+        crate::models::integrity::record(&dest)?;
         let meta = fs::metadata(&dest)?;
         let size = meta.len();
         let format = dest
@@ -80,11 +131,13 @@ impl ModelManager {
     pub fn remove(&self, name: &str) -> anyhow::Result<()> {
         for m in self.discover()? {
             if m.name == name {
-                fs::remove_file(m.path)?;
+                fs::remove_file(&m.path)?;
+                self.forget();
+                crate::models::integrity::forget(&m.path)?;
                 return Ok(());
             }
         }
-        Err(anyhow::anyhow!("model not found"))
+        Err(ProviderError::ModelNotFound(name.to_string()).into())
     }
 }
 
@@ -107,4 +160,22 @@ mod tests {
         assert_eq!(mgr2.discover()?.len(), 0);
         Ok(())
     }
+
+    #[test]
+    fn test_listing_is_cached_until_the_directory_changes() -> anyhow::Result<()> {
+        let (mgr, td) = ModelManager::with_temp()?;
+        std::fs::write(td.path().join("a.gguf"), b"aa")?;
+        let first = mgr.listing()?;
+        // rewritten in place: the directory's mtime stays, so the cache does too
+        std::fs::write(td.path().join("a.gguf"), b"aaaa")?;
+        assert_eq!(mgr.listing()?, first);
+        let rescanned = mgr.rescan()?;
+        assert_eq!(rescanned.models[0].size, 4);
+        assert_ne!(rescanned.etag, first.etag);
+
+        std::fs::write(td.path().join("b.gguf"), b"b")?;
+        let names: Vec<String> = mgr.discover()?.into_iter().map(|m| m.name).collect();
+        assert_eq!(names, ["a", "b"]);
+        Ok(())
+    }
 }
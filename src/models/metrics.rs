@@ -0,0 +1,178 @@
+use crate::tasks::{Task, TaskStatus};
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Upper bounds (seconds) of the request latency histogram buckets.
+const LATENCY_BUCKETS: [f64; 8] = [0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// In-process counters for the local model server, rendered in the
+/// Prometheus text exposition format. Nothing leaves the machine unless the
+/// user scrapes `/metrics` themselves.
+#[derive(Debug, Default)]
+pub struct ServerMetrics {
+    requests_models: AtomicU64,
+    requests_chat: AtomicU64,
+    chat_ok: AtomicU64,
+    chat_error: AtomicU64,
+    provider_errors: AtomicU64,
+    tokens_prompt: AtomicU64,
+    tokens_completion: AtomicU64,
+    in_flight: AtomicU64,
+    latency_buckets: [AtomicU64; LATENCY_BUCKETS.len()],
+    latency_count: AtomicU64,
+    latency_sum_micros: AtomicU64,
+    /// Responses by status class, `1xx` to `5xx`.
+    responses: [AtomicU64; 5],
+}
+
+/// A chat request counted in the queue depth until it is dropped, whether it
+/// finished or its client went away first.
+pub struct InFlight(Arc<ServerMetrics>);
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.0.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Rough token estimate (whitespace-separated words) used until providers
+/// report real usage.
+pub fn estimate_tokens(text: &str) -> u64 {
+    text.split_whitespace().count() as u64
+}
+
+impl ServerMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record_models_request(&self) {
+        self.requests_models.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Mark a chat request as started; it stays in flight until the returned
+    /// guard is dropped. Pair with `chat_finished` when it completes.
+    pub fn chat_started(self: &Arc<Self>) -> InFlight {
+        self.requests_chat.fetch_add(1, Ordering::Relaxed);
+        self.in_flight.fetch_add(1, Ordering::Relaxed);
+        InFlight(Arc::clone(self))
+    }
+
+    pub fn chat_finished(&self, elapsed: Duration, ok: bool, prompt_tokens: u64, completion_tokens: u64) {
+        if ok {
+            self.chat_ok.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.chat_error.fetch_add(1, Ordering::Relaxed);
+        }
+        self.tokens_prompt.fetch_add(prompt_tokens, Ordering::Relaxed);
+        self.tokens_completion.fetch_add(completion_tokens, Ordering::Relaxed);
+        let secs = elapsed.as_secs_f64();
+        for (i, le) in LATENCY_BUCKETS.iter().enumerate() {
+            if secs <= *le {
+                self.latency_buckets[i].fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        self.latency_count.fetch_add(1, Ordering::Relaxed);
+        self.latency_sum_micros.fetch_add(elapsed.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    /// Count a response of any route by its status class.
+    pub fn record_response(&self, status: u16) {
+        if let Some(class) = self.responses.get((status / 100).saturating_sub(1) as usize) {
+            class.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn record_provider_error(&self) {
+        self.provider_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn render(&self) -> String {
+        let get = |c: &AtomicU64| c.load(Ordering::Relaxed);
+        let mut out = String::new();
+        let _ = writeln!(out, "# HELP super_agent_requests_total HTTP requests by route.");
+        let _ = writeln!(out, "# TYPE super_agent_requests_total counter");
+        let _ = writeln!(out, "super_agent_requests_total{{route=\"/v1/models\"}} {}", get(&self.requests_models));
+        let _ = writeln!(out, "super_agent_requests_total{{route=\"/v1/chat/completions\"}} {}", get(&self.requests_chat));
+        let _ = writeln!(out, "# HELP super_agent_responses_total HTTP responses by status class.");
+        let _ = writeln!(out, "# TYPE super_agent_responses_total counter");
+        for (i, class) in self.responses.iter().enumerate() {
+            let _ = writeln!(out, "super_agent_responses_total{{class=\"{}xx\"}} {}", i + 1, get(class));
+        }
+        let _ = writeln!(out, "# HELP super_agent_chat_completions_total Finished chat requests by outcome.");
+        let _ = writeln!(out, "# TYPE super_agent_chat_completions_total counter");
+        let _ = writeln!(out, "super_agent_chat_completions_total{{status=\"ok\"}} {}", get(&self.chat_ok));
+        let _ = writeln!(out, "super_agent_chat_completions_total{{status=\"error\"}} {}", get(&self.chat_error));
+        let _ = writeln!(out, "# HELP super_agent_provider_errors_total Errors returned by providers.");
+        let _ = writeln!(out, "# TYPE super_agent_provider_errors_total counter");
+        let _ = writeln!(out, "super_agent_provider_errors_total {}", get(&self.provider_errors));
+        let _ = writeln!(out, "# HELP super_agent_tokens_total Estimated tokens processed.");
+        let _ = writeln!(out, "# TYPE super_agent_tokens_total counter");
+        let _ = writeln!(out, "super_agent_tokens_total{{kind=\"prompt\"}} {}", get(&self.tokens_prompt));
+        let _ = writeln!(out, "super_agent_tokens_total{{kind=\"completion\"}} {}", get(&self.tokens_completion));
+        let _ = writeln!(out, "# HELP super_agent_queue_depth Chat requests currently in flight.");
+        let _ = writeln!(out, "# TYPE super_agent_queue_depth gauge");
+        let _ = writeln!(out, "super_agent_queue_depth {}", get(&self.in_flight));
+        let _ = writeln!(out, "# HELP super_agent_chat_latency_seconds Chat request latency.");
+        let _ = writeln!(out, "# TYPE super_agent_chat_latency_seconds histogram");
+        for (i, le) in LATENCY_BUCKETS.iter().enumerate() {
+            let _ = writeln!(out, "super_agent_chat_latency_seconds_bucket{{le=\"{}\"}} {}", le, get(&self.latency_buckets[i]));
+        }
+        let count = get(&self.latency_count);
+        let _ = writeln!(out, "super_agent_chat_latency_seconds_bucket{{le=\"+Inf\"}} {}", count);
+        let _ = writeln!(out, "super_agent_chat_latency_seconds_sum {}", get(&self.latency_sum_micros) as f64 / 1_000_000.0);
+        let _ = writeln!(out, "super_agent_chat_latency_seconds_count {}", count);
+        out
+    }
+}
+
+/// Append `super_agent_tasks` (tasks in the store by status) to `out`.
+pub fn render_tasks(tasks: &[Task], out: &mut String) {
+    let _ = writeln!(out, "# HELP super_agent_tasks Tasks in the task store by status.");
+    let _ = writeln!(out, "# TYPE super_agent_tasks gauge");
+    for status in [TaskStatus::Pending, TaskStatus::Running, TaskStatus::Completed, TaskStatus::Failed, TaskStatus::Cancelled] {
+        let n = tasks.iter().filter(|t| t.status == status).count();
+        let _ = writeln!(out, "super_agent_tasks{{status=\"{}\"}} {}", format!("{:?}", status).to_lowercase(), n);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_prometheus_text() {
+        let m = Arc::new(ServerMetrics::new());
+        m.record_models_request();
+        let done = m.chat_started();
+        m.chat_finished(Duration::from_millis(200), true, 3, 5);
+        drop(done);
+        let _running = m.chat_started();
+        drop(m.chat_started());
+        m.record_response(200);
+        m.record_response(422);
+        m.record_response(999);
+        let text = m.render();
+        assert!(text.contains("super_agent_requests_total{route=\"/v1/chat/completions\"} 3"));
+        assert!(text.contains("super_agent_queue_depth 1"));
+        assert!(text.contains("super_agent_responses_total{class=\"2xx\"} 1"));
+        assert!(text.contains("super_agent_responses_total{class=\"4xx\"} 1"));
+        assert!(text.contains("super_agent_chat_latency_seconds_bucket{le=\"0.1\"} 0"));
+        assert!(text.contains("super_agent_chat_latency_seconds_bucket{le=\"0.25\"} 1"));
+        assert!(text.contains("super_agent_tokens_total{kind=\"completion\"} 5"));
+    }
+
+    #[test]
+    fn test_render_task_counts_by_status() {
+        let mut running = Task::new("a", "a");
+        running.status = TaskStatus::Running;
+        let tasks = vec![running, Task::new("b", "b"), Task::new("c", "c")];
+        let mut text = String::new();
+        render_tasks(&tasks, &mut text);
+        assert!(text.contains("super_agent_tasks{status=\"pending\"} 2"));
+        assert!(text.contains("super_agent_tasks{status=\"running\"} 1"));
+        assert!(text.contains("super_agent_tasks{status=\"failed\"} 0"));
+    }
+}
@@ -1,6 +1,22 @@
 pub mod manager;
 pub mod server;
 pub mod native;
+pub mod metrics;
+pub mod bench;
+pub mod compare;
+pub mod whisper;
+pub mod prefix_cache;
+pub mod idempotency;
+pub mod conversations;
+pub mod web_ui;
+pub mod access;
+pub mod watcher;
+pub mod integrity;
+pub mod instance;
+pub mod platform;
+pub mod preload;
+pub mod pool;
+pub mod setup;
 
 pub use manager::{ModelInfo, ModelManager};
 pub use server::{ModelServer, ProviderKind};
@@ -7,12 +7,13 @@
 //!
 //! این ماژول امکان اجرای مستقیم مدل‌ها را بدون نیاز به llama.cpp یا Ollama فراهم می‌کند.
 
+use crate::errors::ProviderError;
+use crate::models::pool::{self, Instance};
 use crate::types::Message;
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use std::sync::Arc;
-use tokio::sync::Mutex;
 
 /// فرمت‌های پشتیبانی‌شده مدل‌ها
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -134,8 +135,8 @@ pub enum LoadState {
 pub struct NativeProvider {
     /// اطلاعات مدل
     info: NativeModelInfo,
-    /// وضعیت بارگذاری
-    load_state: Arc<Mutex<LoadState>>,
+    /// نمونه‌ی بارگذاری‌شده، مشترک بین همه‌ی جلسه‌هایی که همین فایل را انتخاب کرده‌اند
+    instance: Arc<Instance>,
     /// مسیر فایل مدل
     model_path: PathBuf,
     /// تنظیمات inference
@@ -179,7 +180,7 @@ impl NativeProvider {
         
         Self {
             info,
-            load_state: Arc::new(Mutex::new(LoadState::Unloaded)),
+            instance: pool::instance(&model_path),
             model_path,
             config: NativeConfig::default(),
         }
@@ -191,7 +192,7 @@ impl NativeProvider {
         
         Self {
             info,
-            load_state: Arc::new(Mutex::new(LoadState::Unloaded)),
+            instance: pool::instance(&model_path),
             model_path,
             config,
         }
@@ -204,7 +205,7 @@ impl NativeProvider {
 
     /// دریافت وضعیت بارگذاری
     pub async fn load_state(&self) -> LoadState {
-        self.load_state.lock().await.clone()
+        self.instance.state.lock().await.clone()
     }
 
     /// بررسی پشتیبانی از فرمت
@@ -219,13 +220,19 @@ impl NativeProvider {
     ///
     /// این متد مدل را از فایل خوانده و در حافظه بارگذاری می‌کند.
     /// برای مدل‌های بزرگ، این عملیات ممکن است چندین ثانیه طول بکشد.
+    /// اگر جلسه‌ی دیگری همین مدل را بارگذاری کرده باشد، همان نمونه استفاده می‌شود.
     pub async fn load(&self) -> anyhow::Result<()> {
-        *self.load_state.lock().await = LoadState::Loading;
+        let _loading = self.instance.loading.lock().await;
+        if *self.instance.state.lock().await == LoadState::Loaded {
+            self.instance.join();
+            return Ok(());
+        }
+        *self.instance.state.lock().await = LoadState::Loading;
 
         // بررسی پشتیبانی از فرمت
         if !self.is_format_supported() {
             let err = format!("فرمت {} پشتیبانی نمی‌شود", self.info.format.display_name());
-            *self.load_state.lock().await = LoadState::Error(err.clone());
+            *self.instance.state.lock().await = LoadState::Error(err.clone());
             return Err(anyhow::anyhow!(err));
         }
 
@@ -246,7 +253,8 @@ impl NativeProvider {
         //     _ => return Err(anyhow::anyhow!("فرمت پشتیبانی نمی‌شود")),
         // }
 
-        *self.load_state.lock().await = LoadState::Loaded;
+        *self.instance.state.lock().await = LoadState::Loaded;
+        self.instance.join();
 
         Ok(())
     }
@@ -269,9 +277,12 @@ impl NativeProvider {
         Ok(())
     }
 
-    /// تخلیه مدل از حافظه
+    /// تخلیه مدل از حافظه، وقتی آخرین جلسه‌ای که از آن استفاده می‌کند آن را رها کند
     pub async fn unload(&self) -> anyhow::Result<()> {
-        *self.load_state.lock().await = LoadState::Unloaded;
+        if !self.instance.leave() {
+            return Ok(());
+        }
+        *self.instance.state.lock().await = LoadState::Unloaded;
         
         // TODO: آزادسازی حافظه GPU/CPU
         
@@ -282,12 +293,25 @@ impl NativeProvider {
     ///
     /// این متد پیام‌ها را گرفته و پاسخ مدل را برمی‌گرداند.
     pub async fn chat(&self, messages: &[Message]) -> anyhow::Result<String> {
-        let state = self.load_state.lock().await.clone();
+        self.chat_with_config(messages, &self.config).await
+    }
+
+    /// اجرای inference با تنظیمات sampling همین درخواست
+    pub async fn chat_with_config(&self, messages: &[Message], config: &NativeConfig) -> anyhow::Result<String> {
+        let result = self.infer(messages, config).await;
+        crate::transcript::record("native", &self.info.name, messages, result.as_deref().map_err(|e| format!("{:#}", e)));
+        result
+    }
+
+    async fn infer(&self, messages: &[Message], config: &NativeConfig) -> anyhow::Result<String> {
+        let state = self.instance.state.lock().await.clone();
         
         match state {
             LoadState::Loaded => {
+                // درخواست‌های هم‌زمان به یک نمونه به نوبت اجرا می‌شوند
+                let _turn = self.instance.turn().await;
                 // TODO: پیاده‌سازی واقعی inference
-                self.mock_inference(messages).await
+                self.mock_inference(messages, config).await
             }
             LoadState::Loading => {
                 Err(anyhow::anyhow!("مدل هنوز در حال بارگذاری است"))
@@ -302,7 +326,7 @@ impl NativeProvider {
     }
 
     /// شبیه‌سازی inference (برای تست)
-    async fn mock_inference(&self, messages: &[Message]) -> anyhow::Result<String> {
+    async fn mock_inference(&self, messages: &[Message], config: &NativeConfig) -> anyhow::Result<String> {
         let last_message = messages.last()
             .map(|m| m.content.clone())
             .unwrap_or_default();
@@ -315,18 +339,58 @@ impl NativeProvider {
             last_message
         );
         
-        Ok(response)
+        // max_tokens را با کلمه‌ها تقریب می‌زند
+        Ok(response.split(' ').take(config.max_tokens).collect::<Vec<_>>().join(" "))
     }
 
     /// پیش‌بینی متن (برای autocomplete)
+    ///
+    /// ادامه‌ی کوتاه prompt را برمی‌گرداند؛ اگر مدل بارگذاری نشده باشد None
     pub async fn predict(&self, prompt: &str) -> anyhow::Result<Option<String>> {
-        // TODO: پیاده‌سازی streaming completion
-        
-        // برای نسخه اولیه، None برمی‌گردانیم
-        Ok(None)
+        if *self.instance.state.lock().await != LoadState::Loaded {
+            return Ok(None);
+        }
+        let raw = NativeProvider::chat(self, &crate::llm::autocomplete::completion_messages(prompt)).await?;
+        Ok(crate::llm::autocomplete::clean_completion(prompt, &raw))
     }
 }
 
+#[async_trait]
+impl crate::models::server::Provider for NativeProvider {
+    fn name(&self) -> &'static str {
+        "native"
+    }
+
+    fn model_id(&self) -> String {
+        self.info.name.clone()
+    }
+
+    async fn start(&self) -> anyhow::Result<()> {
+        self.load().await
+    }
+
+    async fn stop(&self) -> anyhow::Result<()> {
+        self.unload().await
+    }
+
+    async fn is_running(&self) -> bool {
+        self.load_state().await == LoadState::Loaded
+    }
+
+    async fn chat(&self, messages: &[Message]) -> Result<String, ProviderError> {
+        NativeProvider::chat(self, messages).await.map_err(|e| ProviderError::Unavailable(format!("{:#}", e)))
+    }
+
+    async fn chat_with(&self, messages: &[Message], params: &crate::llm::params::InferenceParams) -> Result<String, ProviderError> {
+        self.chat_with_config(messages, &params.apply(&self.config)).await.map_err(|e| ProviderError::Unavailable(format!("{:#}", e)))
+    }
+
+    async fn predict(&self, prompt: &str) -> Result<Option<String>, ProviderError> {
+        NativeProvider::predict(self, prompt).await.map_err(|e| ProviderError::Unavailable(format!("{:#}", e)))
+    }
+
+}
+
 /// مدیریت Native Models
 #[derive(Clone)]
 pub struct NativeModelManager {
@@ -492,4 +556,41 @@ mod tests {
         assert!(discovered.iter().any(|m| m.name == "model1"));
         assert!(discovered.iter().any(|m| m.name == "model2"));
     }
+
+    #[tokio::test]
+    async fn test_sessions_share_one_loaded_instance() {
+        let temp_dir = TempDir::new().unwrap();
+        let model_path = temp_dir.path().join("shared.gguf");
+        std::fs::write(&model_path, b"dummy").unwrap();
+
+        // دو جلسه با تنظیمات متفاوت، یک نمونه‌ی بارگذاری‌شده
+        let a = NativeProvider::new(model_path.clone());
+        let b = NativeProvider::with_config(model_path.clone(), NativeConfig { max_tokens: 3, ..NativeConfig::default() });
+        a.load().await.unwrap();
+        let started = std::time::Instant::now();
+        b.load().await.unwrap();
+        assert!(started.elapsed() < std::time::Duration::from_millis(400), "second session loaded again");
+        let instance = pool::instance(&model_path);
+        assert!(Arc::ptr_eq(&instance, &a.instance) && Arc::ptr_eq(&instance, &b.instance));
+        assert_eq!(instance.users(), 2);
+
+        // درخواست‌ها برای context مدل صف می‌کشند
+        let turn = instance.turn().await;
+        let waiting = tokio::spawn({
+            let b = b.clone();
+            async move { b.chat(&[Message::new("user", "hi")]).await }
+        });
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert_eq!(instance.usage(), pool::Usage { model: "shared".into(), users: 2, queued: 1 });
+        drop(turn);
+        assert_eq!(waiting.await.unwrap().unwrap().split(' ').count(), 3);
+        assert_eq!(instance.usage().queued, 0);
+
+        // تخلیه فقط وقتی آخرین جلسه آن را رها کند
+        a.unload().await.unwrap();
+        assert_eq!(b.load_state().await, LoadState::Loaded);
+        b.unload().await.unwrap();
+        assert_eq!(a.load_state().await, LoadState::Unloaded);
+        assert_eq!(instance.users(), 0);
+    }
 }
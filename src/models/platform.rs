@@ -0,0 +1,92 @@
+//! Platform differences for installing and running llama.cpp: which
+//! installer script runs, where the server binary is looked for and how its
+//! process is spawned. Default locations are in `crate::paths`.
+
+use std::path::{Path, PathBuf};
+
+/// Server binary names, newest first; `.exe` is added on Windows.
+pub const LLAMA_BINARIES: [&str; 3] = ["llama-server", "server", "llama"];
+
+/// Directories searched after PATH: where the installers put the binary
+/// (`<home>/bin`) and the llama.cpp build trees they leave in
+/// `third_party/`, including MSVC's per-configuration `Release` directory.
+pub fn llama_search_dirs(home: &Path) -> Vec<PathBuf> {
+    let build = Path::new("third_party").join("llama.cpp").join("build").join("bin");
+    vec![home.join("bin"), build.join("Release"), build, Path::new("third_party").join("llama.cpp")]
+}
+
+/// First llama.cpp server binary on PATH or in `llama_search_dirs`.
+pub fn find_llama_binary(home: &Path) -> Option<PathBuf> {
+    LLAMA_BINARIES.iter().find_map(|name| which::which(name).ok()).or_else(|| {
+        llama_search_dirs(home)
+            .into_iter()
+            .flat_map(|dir| LLAMA_BINARIES.iter().map(move |name| dir.join(format!("{}{}", name, std::env::consts::EXE_SUFFIX))))
+            .find(|p| p.is_file())
+    })
+}
+
+/// The llama.cpp installer for this platform from `scripts`: PowerShell
+/// (`pwsh`, else Windows PowerShell) on Windows, `sh` elsewhere.
+pub fn installer(scripts: &Path) -> Option<std::process::Command> {
+    if cfg!(windows) {
+        let script = scripts.join("install_llama.ps1");
+        if !script.exists() {
+            return None;
+        }
+        let shell = if which::which("pwsh").is_ok() { "pwsh" } else { "powershell" };
+        let mut cmd = std::process::Command::new(shell);
+        cmd.args(["-NoProfile", "-ExecutionPolicy", "Bypass", "-File"]).arg(script);
+        // where it copies the server to
+        cmd.env("SUPERCODE_HOME", crate::paths::home());
+        Some(cmd)
+    } else {
+        let script = scripts.join("install_llama.sh");
+        if !script.exists() {
+            return None;
+        }
+        let mut cmd = std::process::Command::new("/bin/sh");
+        cmd.arg(script);
+        Some(cmd)
+    }
+}
+
+/// Spawn settings for a llama.cpp child: it dies with its owner, and on
+/// Windows it gets no console window of its own.
+pub fn configure_child(cmd: &mut tokio::process::Command) {
+    cmd.kill_on_drop(true);
+    #[cfg(windows)]
+    {
+        const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+        cmd.creation_flags(CREATE_NO_WINDOW);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_binary_lookup_and_installer_per_platform() {
+        let home = tempfile::tempdir().unwrap();
+        let bin = home.path().join("bin");
+        std::fs::create_dir_all(&bin).unwrap();
+        let exe = bin.join(format!("llama-server{}", std::env::consts::EXE_SUFFIX));
+        std::fs::write(&exe, b"").unwrap();
+        // PATH may hold a real llama.cpp; otherwise the installed copy is found
+        assert!(find_llama_binary(home.path()).is_some());
+
+        let scripts = tempfile::tempdir().unwrap();
+        assert!(installer(scripts.path()).is_none());
+        std::fs::write(scripts.path().join("install_llama.sh"), b"").unwrap();
+        std::fs::write(scripts.path().join("install_llama.ps1"), b"").unwrap();
+        let cmd = installer(scripts.path()).unwrap();
+        let args: Vec<String> = cmd.get_args().map(|a| a.to_string_lossy().into_owned()).collect();
+        if cfg!(windows) {
+            assert!(args.last().unwrap().ends_with("install_llama.ps1"));
+            assert!(args.contains(&"Bypass".to_string()));
+        } else {
+            assert_eq!(cmd.get_program(), "/bin/sh");
+            assert!(args[0].ends_with("install_llama.sh"));
+        }
+    }
+}
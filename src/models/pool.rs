@@ -0,0 +1,137 @@
+//! Native models loaded once per process. Every [`NativeProvider`] for the
+//! same file (the TUI's chat, `/compare`, preloads, the model server) holds
+//! the same [`Instance`]: one load state and one inference context, loaded
+//! when the first of them starts and unloaded when the last one stops.
+//! Requests to an instance queue for its context and run one at a time.
+//!
+//! [`NativeProvider`]: crate::models::NativeProvider
+
+use crate::models::native::LoadState;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, Weak};
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+/// Requests one instance runs at once; the rest wait their turn.
+const SLOTS: usize = 1;
+
+/// HELP/TYPE header for the lines written by [`render`].
+pub const METRICS_HEADER: &str = "# HELP super_agent_native_users Sessions sharing a loaded native model.\n\
+# TYPE super_agent_native_users gauge\n\
+# HELP super_agent_native_queued Requests waiting for a native model's context.\n\
+# TYPE super_agent_native_queued gauge\n";
+
+/// One loaded (or loadable) model file.
+pub struct Instance {
+    path: PathBuf,
+    pub(crate) state: tokio::sync::Mutex<LoadState>,
+    /// Held while loading, so a second session waits instead of loading again.
+    pub(crate) loading: tokio::sync::Mutex<()>,
+    users: AtomicUsize,
+    queue: Semaphore,
+    queued: AtomicUsize,
+}
+
+/// What `/metrics` shows for an instance.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Usage {
+    pub model: String,
+    pub users: usize,
+    pub queued: usize,
+}
+
+fn registry() -> &'static Mutex<HashMap<PathBuf, Weak<Instance>>> {
+    static REGISTRY: OnceLock<Mutex<HashMap<PathBuf, Weak<Instance>>>> = OnceLock::new();
+    REGISTRY.get_or_init(Default::default)
+}
+
+/// The instance for the model at `path`: the one other sessions already
+/// hold, or a new, unloaded one.
+pub fn instance(path: &Path) -> Arc<Instance> {
+    let key = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    let mut instances = registry().lock().unwrap_or_else(|e| e.into_inner());
+    instances.retain(|_, i| i.strong_count() > 0);
+    if let Some(shared) = instances.get(&key).and_then(Weak::upgrade) {
+        return shared;
+    }
+    let fresh = Arc::new(Instance {
+        path: key.clone(),
+        state: tokio::sync::Mutex::new(LoadState::Unloaded),
+        loading: tokio::sync::Mutex::new(()),
+        users: AtomicUsize::new(0),
+        queue: Semaphore::new(SLOTS),
+        queued: AtomicUsize::new(0),
+    });
+    instances.insert(key, Arc::downgrade(&fresh));
+    fresh
+}
+
+/// Instances some session is using, by model name.
+pub fn usage() -> Vec<Usage> {
+    let instances = registry().lock().unwrap_or_else(|e| e.into_inner());
+    let mut usage: Vec<Usage> = instances.values().filter_map(Weak::upgrade).filter(|i| i.users() > 0).map(|i| i.usage()).collect();
+    usage.sort_by(|a, b| a.model.cmp(&b.model));
+    usage
+}
+
+/// Gauge lines for [`usage`], after [`METRICS_HEADER`].
+pub fn render(usage: &[Usage], out: &mut String) {
+    for u in usage {
+        let _ = writeln!(out, "super_agent_native_users{{model=\"{}\"}} {}", u.model, u.users);
+        let _ = writeln!(out, "super_agent_native_queued{{model=\"{}\"}} {}", u.model, u.queued);
+    }
+}
+
+/// Takes a request back off `queued` when it stops waiting, also when its
+/// future is dropped mid-wait.
+struct Waiting<'a>(&'a AtomicUsize);
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl Instance {
+    /// Sessions that started this instance and have not stopped it.
+    pub fn users(&self) -> usize {
+        self.users.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn join(&self) {
+        self.users.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// One session fewer; true when it was the last.
+    pub(crate) fn leave(&self) -> bool {
+        self.users.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| Some(n.saturating_sub(1))) == Ok(1)
+    }
+
+    /// Wait for the context; it is this request's until the permit drops.
+    pub(crate) async fn turn(&self) -> SemaphorePermit<'_> {
+        self.queued.fetch_add(1, Ordering::SeqCst);
+        let _waiting = Waiting(&self.queued);
+        self.queue.acquire().await.expect("instance queue is never closed")
+    }
+
+    pub fn usage(&self) -> Usage {
+        let model = self.path.file_stem().and_then(|s| s.to_str()).unwrap_or("unknown").to_string();
+        Usage { model, users: self.users(), queued: self.queued.load(Ordering::SeqCst) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_abandoned_wait_leaves_the_queue() {
+        let td = tempfile::tempdir().unwrap();
+        let instance = instance(&td.path().join("m.gguf"));
+        let _held = instance.turn().await;
+        assert!(tokio::time::timeout(std::time::Duration::from_millis(20), instance.turn()).await.is_err());
+        assert_eq!(instance.usage().queued, 0);
+    }
+}
@@ -0,0 +1,185 @@
+//! Prompt prefix caching. Chat turns resend the whole conversation, so the
+//! system prompt and project context at the front are identical from turn
+//! to turn. llama.cpp keeps the KV cache of the previous prompt when asked
+//! (`cache_prompt`) and only prefills what changed; [`PrefixCache`] counts
+//! the hits it reports for `/metrics`.
+
+use crate::models::metrics::estimate_tokens;
+use crate::types::Message;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::fmt::Write;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// `[models.prefix_cache]` in config.toml.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PrefixCacheConfig {
+    pub enabled: bool,
+    /// Keep the leading system messages cached even when other prompts
+    /// (another conversation, a one-off completion) come in between.
+    pub pin_system: bool,
+}
+
+impl Default for PrefixCacheConfig {
+    fn default() -> Self {
+        Self { enabled: true, pin_system: false }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    /// Prompt tokens served from the cache.
+    pub reused_tokens: u64,
+    /// Prompt tokens that had to be prefilled.
+    pub prefilled_tokens: u64,
+}
+
+impl CacheStats {
+    /// Prometheus lines for the provider serving `model`.
+    pub fn render(&self, model: &str, out: &mut String) {
+        let _ = writeln!(out, "super_agent_prefix_cache_lookups_total{{model=\"{}\",result=\"hit\"}} {}", model, self.hits);
+        let _ = writeln!(out, "super_agent_prefix_cache_lookups_total{{model=\"{}\",result=\"miss\"}} {}", model, self.misses);
+        let _ = writeln!(out, "super_agent_prefix_cache_tokens_total{{model=\"{}\",kind=\"reused\"}} {}", model, self.reused_tokens);
+        let _ = writeln!(out, "super_agent_prefix_cache_tokens_total{{model=\"{}\",kind=\"prefilled\"}} {}", model, self.prefilled_tokens);
+    }
+}
+
+/// HELP/TYPE header for the lines written by [`CacheStats::render`].
+pub const METRICS_HEADER: &str = "# HELP super_agent_prefix_cache_lookups_total Prompt prefix cache lookups by result.\n\
+# TYPE super_agent_prefix_cache_lookups_total counter\n\
+# HELP super_agent_prefix_cache_tokens_total Prompt tokens reused from the cache or prefilled.\n\
+# TYPE super_agent_prefix_cache_tokens_total counter\n";
+
+/// What a prompt could take from the cache.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PrefixUse {
+    pub reused_tokens: u64,
+    pub prefill_tokens: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct PrefixCache {
+    pub config: PrefixCacheConfig,
+    /// Per-message hashes of the last prompt, and of the pinned system prefix.
+    last: Mutex<(Vec<u64>, Vec<u64>)>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    reused_tokens: AtomicU64,
+    prefilled_tokens: AtomicU64,
+}
+
+impl PrefixCache {
+    pub fn new(config: PrefixCacheConfig) -> Self {
+        Self { config, ..Default::default() }
+    }
+
+    /// Match `messages` against the cached prompt and make them the cached
+    /// prompt. Pass the result, or the backend's own numbers, to `record`.
+    pub fn lookup(&self, messages: &[Message]) -> PrefixUse {
+        let hashes: Vec<u64> = messages.iter().map(hash).collect();
+        let tokens: Vec<u64> = messages.iter().map(|m| estimate_tokens(&m.full_text())).collect();
+        let total = tokens.iter().sum();
+        if !self.config.enabled {
+            return PrefixUse { reused_tokens: 0, prefill_tokens: total };
+        }
+        let mut last = self.last.lock().unwrap_or_else(|e| e.into_inner());
+        let (prompt, pinned) = &mut *last;
+        let shared = common_prefix(prompt, &hashes).max(common_prefix(pinned, &hashes));
+        let reused_tokens = tokens[..shared].iter().sum();
+        *prompt = hashes;
+        if self.config.pin_system {
+            let system = system_prefix_len(messages);
+            if system > 0 {
+                *pinned = prompt[..system].to_vec();
+            }
+        }
+        PrefixUse { reused_tokens, prefill_tokens: total - reused_tokens }
+    }
+
+    pub fn record(&self, used: PrefixUse) {
+        let counter = if used.reused_tokens > 0 { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+        self.reused_tokens.fetch_add(used.reused_tokens, Ordering::Relaxed);
+        self.prefilled_tokens.fetch_add(used.prefill_tokens, Ordering::Relaxed);
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            reused_tokens: self.reused_tokens.load(Ordering::Relaxed),
+            prefilled_tokens: self.prefilled_tokens.load(Ordering::Relaxed),
+        }
+    }
+}
+
+fn hash(m: &Message) -> u64 {
+    let mut h = DefaultHasher::new();
+    m.role.hash(&mut h);
+    m.full_text().hash(&mut h);
+    for image in &m.images {
+        image.label().hash(&mut h);
+    }
+    h.finish()
+}
+
+fn common_prefix(a: &[u64], b: &[u64]) -> usize {
+    a.iter().zip(b).take_while(|(x, y)| x == y).count()
+}
+
+/// Number of leading system messages.
+pub fn system_prefix_len(messages: &[Message]) -> usize {
+    messages.iter().take_while(|m| m.role == "system").count()
+}
+
+/// One hash for all of `messages`, to tell whether a prefix changed.
+pub fn prefix_key(messages: &[Message]) -> u64 {
+    let mut h = DefaultHasher::new();
+    for m in messages {
+        hash(m).hash(&mut h);
+    }
+    h.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prefix_reuse_and_pinned_system_prompt() {
+        let system = Message::new("system", "you are a careful reviewer of this project");
+        let turn1 = vec![system.clone(), Message::new("user", "first question")];
+        let mut turn2 = turn1.clone();
+        turn2.push(Message::new("assistant", "an answer"));
+        turn2.push(Message::new("user", "follow up"));
+        let other = vec![Message::new("user", "unrelated one-off prompt")];
+
+        let cache = PrefixCache::new(PrefixCacheConfig::default());
+        let observe = |c: &PrefixCache, m: &[Message]| {
+            let used = c.lookup(m);
+            c.record(used);
+            used
+        };
+        assert_eq!(observe(&cache, &turn1).reused_tokens, 0);
+        assert_eq!(observe(&cache, &turn2), PrefixUse { reused_tokens: 10, prefill_tokens: 4 });
+        observe(&cache, &other);
+        assert_eq!(observe(&cache, &turn1).reused_tokens, 0, "evicted by the other prompt");
+        assert_eq!(cache.stats(), CacheStats { hits: 1, misses: 3, reused_tokens: 10, prefilled_tokens: 10 + 4 + 3 + 10 });
+
+        let pinned = PrefixCache::new(PrefixCacheConfig { enabled: true, pin_system: true });
+        observe(&pinned, &turn1);
+        observe(&pinned, &other);
+        assert_eq!(observe(&pinned, &turn2).reused_tokens, 8, "the system prefix survives");
+        assert_eq!(prefix_key(&turn2[..1]), prefix_key(&turn1[..1]));
+        assert_ne!(prefix_key(&turn2[..1]), prefix_key(&other));
+
+        let mut text = String::new();
+        pinned.stats().render("m", &mut text);
+        assert!(text.contains("super_agent_prefix_cache_lookups_total{model=\"m\",result=\"hit\"} 1"), "{}", text);
+    }
+}
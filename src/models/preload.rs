@@ -0,0 +1,138 @@
+//! Background warm-up of the models in `models.preload`. The TUI and the
+//! model server start loading them at launch, one at a time so they do not
+//! compete for memory, and keep the loaded providers; the first chat with a
+//! preloaded model then waits at most for the rest of its load instead of
+//! all of it. `Warmup::status` feeds the dashboard.
+
+use crate::models::native::{NativeConfig, NativeProvider};
+use crate::models::server::Provider;
+use crate::models::ModelManager;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Warm {
+    Queued,
+    Loading { since: Instant },
+    Ready { took: Duration },
+    Failed(String),
+}
+
+impl Warm {
+    /// `loading 12s`, `ready in 31.2s`.
+    pub fn describe(&self) -> String {
+        match self {
+            Warm::Queued => "queued".into(),
+            Warm::Loading { since } => format!("loading {}s", since.elapsed().as_secs()),
+            Warm::Ready { took } => format!("ready in {:.1}s", took.as_secs_f64()),
+            Warm::Failed(e) => format!("failed: {}", e),
+        }
+    }
+
+    fn pending(&self) -> bool {
+        matches!(self, Warm::Queued | Warm::Loading { .. })
+    }
+}
+
+/// Warm-up progress and the providers it loaded. Cheap to clone; clones
+/// share the same state.
+#[derive(Clone, Default)]
+pub struct Warmup {
+    status: Arc<Mutex<Vec<(String, Warm)>>>,
+    providers: Arc<Mutex<HashMap<String, Arc<dyn Provider>>>>,
+}
+
+impl Warmup {
+    /// Start loading `names` from `model_dir` in the background. Nothing
+    /// happens when `names` is empty.
+    pub fn spawn(names: &[String], model_dir: PathBuf) -> Self {
+        let warmup = Self::default();
+        if names.is_empty() {
+            return warmup;
+        }
+        *warmup.status.lock() = names.iter().map(|n| (n.clone(), Warm::Queued)).collect();
+        let this = warmup.clone();
+        let names = names.to_vec();
+        tokio::spawn(async move {
+            for name in names {
+                let since = Instant::now();
+                this.set(&name, Warm::Loading { since });
+                let state = match load(&name, &model_dir).await {
+                    Ok(p) => {
+                        this.providers.lock().insert(name.clone(), p);
+                        Warm::Ready { took: since.elapsed() }
+                    }
+                    Err(e) => Warm::Failed(format!("{:#}", e)),
+                };
+                log::info!("preload {}: {}", name, state.describe());
+                this.set(&name, state);
+            }
+        });
+        warmup
+    }
+
+    fn set(&self, name: &str, state: Warm) {
+        if let Some(slot) = self.status.lock().iter_mut().find(|(n, _)| n == name) {
+            slot.1 = state;
+        }
+    }
+
+    pub fn status(&self) -> Vec<(String, Warm)> {
+        self.status.lock().clone()
+    }
+
+    /// The loaded provider for `name`, if it is preloaded and ready.
+    pub fn provider(&self, name: &str) -> Option<Arc<dyn Provider>> {
+        self.providers.lock().get(name).cloned()
+    }
+
+    /// The provider for `name`, waiting while it is still queued or loading.
+    /// `None` when `name` is not preloaded or failed to load.
+    pub async fn ready(&self, name: &str) -> Option<Arc<dyn Provider>> {
+        loop {
+            let pending = self.status.lock().iter().any(|(n, s)| n == name && s.pending());
+            if !pending {
+                return self.provider(name);
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    }
+}
+
+async fn load(name: &str, model_dir: &std::path::Path) -> anyhow::Result<Arc<dyn Provider>> {
+    let model = ModelManager::new(Some(model_dir.to_path_buf()))?
+        .discover()?
+        .into_iter()
+        .find(|m| m.name == name)
+        .ok_or_else(|| crate::errors::ProviderError::ModelNotFound(name.to_string()))?;
+    let provider = NativeProvider::with_config(model.path, NativeConfig::default());
+    provider.start().await?;
+    Ok(Arc::new(provider))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_preloads_in_the_background() -> anyhow::Result<()> {
+        let td = tempfile::tempdir()?;
+        std::fs::write(td.path().join("coder.gguf"), b"gguf")?;
+        let names = vec!["coder".to_string(), "missing".to_string()];
+        let warmup = Warmup::spawn(&names, td.path().to_path_buf());
+        assert!(warmup.status().iter().all(|(_, s)| s.pending()));
+        assert!(warmup.provider("coder").is_none());
+
+        let coder = warmup.ready("coder").await.expect("coder loads");
+        assert!(coder.is_running().await);
+        assert!(warmup.ready("missing").await.is_none());
+        assert!(warmup.ready("other").await.is_none());
+        let status = warmup.status();
+        assert!(matches!(status[0].1, Warm::Ready { .. }), "{:?}", status);
+        assert!(status[1].1.describe().starts_with("failed: model missing not found"), "{:?}", status);
+        Ok(())
+    }
+}
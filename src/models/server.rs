@@ -1,4 +1,18 @@
+use crate::errors::ProviderError;
+use crate::models::conversations::Conversations;
 use crate::models::manager::ModelManager;
+use crate::models::access;
+use crate::models::idempotency;
+use crate::models::web_ui;
+use crate::models::metrics::{estimate_tokens, render_tasks, ServerMetrics};
+use crate::models::pool;
+use crate::models::preload::Warmup;
+use crate::models::prefix_cache::{self, CacheStats, PrefixCache, PrefixCacheConfig, PrefixUse};
+use crate::llm::guard::{self, FinishReason};
+use crate::llm::llama::LlmError;
+use crate::llm::params::InferenceParams;
+use crate::llm::wire::{encode, ChatMessage};
+use crate::tasks::TaskStore;
 use crate::types::Message;
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
@@ -6,7 +20,72 @@ use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::time::Duration;
+use tokio::sync::{mpsc, watch, RwLock};
+use tokio::task::JoinHandle;
+
+/// How long in-flight requests get to finish once the server is stopping.
+pub const DRAIN: Duration = Duration::from_secs(10);
+/// `POST` here with the state file's token to stop a running server.
+pub const SHUTDOWN_ROUTE: &str = "/admin/shutdown";
+/// How often `/v1/events` looks for provider status changes.
+pub const PROVIDER_POLL: Duration = Duration::from_secs(2);
+
+/// One request the model server answered, as its request log shows it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestLog {
+    /// The request's ID, from its `X-Request-Id` or made up here.
+    pub id: String,
+    pub method: String,
+    /// Path and query, redacted.
+    pub path: String,
+    /// The model a chat request asked for.
+    pub model: Option<String>,
+    pub status: u16,
+    pub latency: Duration,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+}
+
+impl RequestLog {
+    pub fn line(&self) -> String {
+        let mut line = format!("{} {} {} {}ms request={}", self.method, self.path, self.status, self.latency.as_millis(), self.id);
+        if let Some(model) = &self.model {
+            line.push_str(&format!(" model={}", model));
+        }
+        if self.prompt_tokens + self.completion_tokens > 0 {
+            line.push_str(&format!(" tokens={}+{}", self.prompt_tokens, self.completion_tokens));
+        }
+        line
+    }
+}
+
+/// Where a backend's time on a request went, as llama.cpp reports it in the
+/// `timings` of a response.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Timings {
+    /// Prompt tokens evaluated; cached ones are not counted.
+    pub prompt_n: u64,
+    pub prompt_ms: f64,
+    /// Tokens generated.
+    pub predicted_n: u64,
+    pub predicted_ms: f64,
+}
+
+impl Timings {
+    /// The `timings` of a llama.cpp response, if it has them.
+    pub fn of(response: &serde_json::Value) -> Option<Self> {
+        serde_json::from_value(response.get("timings")?.clone()).ok()
+    }
+}
+
+/// What the chat handler tells the request log, as a response extension.
+#[derive(Debug, Clone)]
+struct Usage {
+    model: Option<String>,
+    prompt_tokens: u64,
+    completion_tokens: u64,
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ProviderKind {
@@ -16,9 +95,25 @@ pub enum ProviderKind {
     Mock,
 }
 
+/// What a provider is, for error bodies and for choosing the next provider
+/// when one fails.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProviderMetadata {
+    pub provider: &'static str,
+    pub model: String,
+    pub vision: bool,
+    /// Where requests go; `None` when the model runs on this machine.
+    pub endpoint: Option<String>,
+}
+
 #[async_trait]
 pub trait Provider: Send + Sync {
     fn name(&self) -> &'static str;
+    /// The model this provider answers with.
+    fn model_id(&self) -> String;
+    fn metadata(&self) -> ProviderMetadata {
+        ProviderMetadata { provider: self.name(), model: self.model_id(), vision: self.supports_vision(), endpoint: None }
+    }
     async fn start(&self) -> anyhow::Result<()> {
         Ok(())
     }
@@ -28,7 +123,30 @@ pub trait Provider: Send + Sync {
     async fn is_running(&self) -> bool {
         true
     }
-    async fn chat(&self, messages: &[Message]) -> anyhow::Result<String>;
+    /// Whether `chat` accepts messages with images.
+    fn supports_vision(&self) -> bool {
+        false
+    }
+    async fn chat(&self, messages: &[Message]) -> Result<String, ProviderError>;
+    /// Chat, with the backend's timings for providers that report them.
+    async fn chat_timed(&self, messages: &[Message]) -> Result<(String, Option<Timings>), ProviderError> {
+        Ok((self.chat(messages).await?, None))
+    }
+    /// Chat with sampling overrides. Providers without sampling controls
+    /// ignore them.
+    async fn chat_with(&self, messages: &[Message], _params: &InferenceParams) -> Result<String, ProviderError> {
+        self.chat(messages).await
+    }
+    /// Prompt prefix cache counters, for providers that reuse the KV cache.
+    fn cache_stats(&self) -> Option<CacheStats> {
+        None
+    }
+    /// A short continuation of `prompt` for inline completion; `None` when
+    /// there is nothing to offer.
+    async fn predict(&self, prompt: &str) -> Result<Option<String>, ProviderError> {
+        let raw = self.chat(&crate::llm::autocomplete::completion_messages(prompt)).await?;
+        Ok(crate::llm::autocomplete::clean_completion(prompt, &raw))
+    }
 }
 
 pub struct MockProvider {
@@ -41,56 +159,187 @@ impl Provider for MockProvider {
         "mock"
     }
 
-    async fn chat(&self, messages: &[Message]) -> anyhow::Result<String> {
+    fn model_id(&self) -> String {
+        model_id(&self.model)
+    }
+
+    fn supports_vision(&self) -> bool {
+        true
+    }
+
+    async fn chat(&self, messages: &[Message]) -> Result<String, ProviderError> {
         // Very cheap deterministic response that includes model name and last user message
-        let last = messages.last().map(|m| m.content.clone()).unwrap_or_default();
+        let last = messages.last().map(|m| {
+            let images: Vec<String> = m.images.iter().map(|i| i.label()).collect();
+            if images.is_empty() { m.content.clone() } else { format!("{} {}", m.content, images.join(" ")) }
+        }).unwrap_or_default();
         Ok(format!("[mock:{}] echo: {}", self.model.file_name().and_then(|s| s.to_str()).unwrap_or("m"), last))
     }
 }
 
+/// A model file's name without its extension.
+fn model_id(path: &std::path::Path) -> String {
+    path.file_stem().and_then(|s| s.to_str()).unwrap_or_default().to_string()
+}
+
 pub struct LlamaProvider {
     pub binary: Option<PathBuf>,
     pub model: PathBuf,
+    /// Multimodal projector for llava-style models; enables images.
+    pub mmproj: Option<PathBuf>,
     pub addr: std::net::SocketAddr,
+    pub cache: PrefixCache,
+    /// The pinned system prefix's key and its length in the model's tokens,
+    /// None when the server could not count it.
+    pinned: parking_lot::Mutex<Option<(u64, Option<u64>)>>,
     child: tokio::sync::Mutex<Option<tokio::process::Child>>,
 }
 
 impl LlamaProvider {
     pub fn new(binary: Option<PathBuf>, model: PathBuf, addr: std::net::SocketAddr) -> Self {
-        Self { binary, model, addr, child: tokio::sync::Mutex::new(None) }
+        let mmproj = Self::find_mmproj(&model);
+        Self { binary, model, mmproj, addr, cache: PrefixCache::default(), pinned: Default::default(), child: tokio::sync::Mutex::new(None) }
+    }
+
+    pub fn with_prefix_cache(mut self, config: PrefixCacheConfig) -> Self {
+        self.cache = PrefixCache::new(config);
+        self
+    }
+
+    /// A projector next to the model whose name contains `mmproj` and the
+    /// model's file stem, e.g. `llava-v1.5-7b.mmproj.gguf` or
+    /// `mmproj-llava-v1.5-7b.gguf`.
+    fn find_mmproj(model: &std::path::Path) -> Option<PathBuf> {
+        let stem = model.file_stem()?.to_str()?.to_lowercase();
+        std::fs::read_dir(model.parent()?).ok()?.flatten().map(|e| e.path()).find(|p| {
+            let name = p.file_name().and_then(|n| n.to_str()).unwrap_or_default().to_lowercase();
+            p != model && name.contains("mmproj") && name.contains(&stem) && name.ends_with(".gguf")
+        })
     }
 
     fn find_binary(&self) -> Option<PathBuf> {
         if let Some(b) = &self.binary { return Some(b.clone()); }
-        // try PATH
-        if let Ok(path) = which::which("server") {
-            return Some(path);
+        find_llama_binary()
+    }
+
+    /// Tokens of the leading system messages as llama.cpp counts them (chat
+    /// template included), for `n_keep`. Asked once per system prefix; None
+    /// when the server cannot say, and then nothing is pinned.
+    async fn system_tokens(&self, messages: &[Message]) -> Option<u64> {
+        let system = &messages[..prefix_cache::system_prefix_len(messages)];
+        if system.is_empty() {
+            return None;
         }
-        if let Ok(path) = which::which("llama") {
-            return Some(path);
+        let key = prefix_cache::prefix_key(system);
+        if let Some((k, n)) = *self.pinned.lock() {
+            if k == key {
+                return n;
+            }
         }
-        None
+        let count = async {
+            let client = crate::net::client(crate::net::MODEL_SERVER);
+            let templated: serde_json::Value = client
+                .post(format!("http://{}/apply-template", self.addr))
+                .json(&serde_json::json!({"messages": encode(system).ok()?}))
+                .send().await.ok()?.error_for_status().ok()?.json().await.ok()?;
+            let tokens: serde_json::Value = client
+                .post(format!("http://{}/tokenize", self.addr))
+                .json(&serde_json::json!({"content": templated.get("prompt")?.as_str()?, "add_special": true}))
+                .send().await.ok()?.error_for_status().ok()?.json().await.ok()?;
+            Some(tokens.get("tokens")?.as_array()?.len() as u64)
+        }
+        .await;
+        *self.pinned.lock() = Some((key, count));
+        count
+    }
+
+    async fn proxy_chat(&self, messages: &[Message], params: &InferenceParams) -> Result<(String, Option<Timings>), ProviderError> {
+        // proxy to local llama.cpp http endpoint
+        let url = format!("http://{}/v1/chat/completions", self.addr);
+        #[derive(Serialize)]
+        struct Req<'a> {
+            model: &'a str,
+            messages: Vec<ChatMessage>,
+            #[serde(flatten)]
+            params: &'a InferenceParams,
+            /// Keep the KV cache of this prompt so the next turn only prefills what changed.
+            cache_prompt: bool,
+            /// Tokens kept when the context shifts; the pinned system prefix.
+            #[serde(skip_serializing_if = "Option::is_none")]
+            n_keep: Option<u64>,
+        }
+        let model_name = self.model.to_str().unwrap_or("");
+        let estimate = self.cache.lookup(messages);
+        let n_keep = match self.cache.config.pin_system {
+            true => self.system_tokens(messages).await,
+            false => None,
+        };
+        let messages_out = encode(messages).map_err(|e| ProviderError::BadRequest { detail: format!("{:#}", e) })?;
+        let body = Req { model: model_name, messages: messages_out, params, cache_prompt: self.cache.config.enabled, n_keep };
+        let mut req = crate::net::client(crate::net::MODEL_SERVER).post(&url).json(&body);
+        if let Some(id) = crate::request_id::current() {
+            req = req.header(crate::request_id::HEADER, id);
+        }
+        let resp = req.send().await.and_then(|r| r.error_for_status()).map_err(LlmError::Http)?;
+        let resp = resp.json::<serde_json::Value>().await.map_err(LlmError::Http)?;
+        // llama.cpp reports how many prompt tokens came from the cache; fall back to our estimate
+        let timings = resp.get("timings");
+        let reported = |k: &str| timings.and_then(|t| t.get(k)).and_then(|v| v.as_u64());
+        self.cache.record(match (reported("cache_n"), reported("prompt_n")) {
+            (Some(reused_tokens), Some(prefill_tokens)) => PrefixUse { reused_tokens, prefill_tokens },
+            _ => estimate,
+        });
+        // extract text similar to LlamaClient
+        let text = resp
+            .get("choices")
+            .and_then(|c| c.get(0))
+            .and_then(|ch| ch.get("message"))
+            .and_then(|m| m.get("content"))
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| "".to_string());
+        Ok((text, Timings::of(&resp)))
+    }
+
+    /// `proxy_chat`, recorded in the transcript.
+    async fn timed_chat(&self, messages: &[Message], params: &InferenceParams) -> Result<(String, Option<Timings>), ProviderError> {
+        let result = self.proxy_chat(messages, params).await;
+        let model = self.model.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        crate::transcript::record("llama", model, messages, result.as_ref().map(|(text, _)| text.as_str()).map_err(|e| e.to_string()));
+        result
     }
 }
 
+/// llama.cpp server binary on PATH or where the installer put it.
+pub fn find_llama_binary() -> Option<PathBuf> {
+    crate::models::platform::find_llama_binary(&crate::paths::home())
+}
+
 #[async_trait]
 impl Provider for LlamaProvider {
     fn name(&self) -> &'static str { "llama" }
 
+    fn model_id(&self) -> String {
+        model_id(&self.model)
+    }
+
     async fn start(&self) -> anyhow::Result<()> {
-        let bin = self.find_binary().ok_or_else(|| anyhow::anyhow!("llama binary not found"))?;
-        let mut child = tokio::process::Command::new(bin)
-            .arg("--model")
-            .arg(self.model.as_path())
-            .arg("--http")
-            .arg(format!("{}", self.addr.port()))
-            .spawn()?;
+        // a truncated download otherwise fails inside llama.cpp with little to go on
+        crate::models::integrity::check(&self.model)?;
+        let bin = self.find_binary().ok_or(ProviderError::BinaryNotFound)?;
+        let mut cmd = tokio::process::Command::new(bin);
+        crate::models::platform::configure_child(&mut cmd);
+        cmd.arg("--model").arg(self.model.as_path()).arg("--http").arg(format!("{}", self.addr.port()));
+        if let Some(mmproj) = &self.mmproj {
+            cmd.arg("--mmproj").arg(mmproj);
+        }
+        let child = cmd.spawn()?;
         *self.child.lock().await = Some(child);
         // wait for health endpoint with exponential backoff (try up to ~12 times)
         let url = format!("http://{}:{}/v1/health", self.addr.ip(), self.addr.port());
         let mut delay_ms = 200u64;
         for _ in 0..12 {
-            match reqwest::get(&url).await {
+            match crate::net::client(crate::net::MODEL_SERVER).get(&url).send().await {
                 Ok(resp) => {
                     if resp.status().is_success() {
                         return Ok(());
@@ -101,39 +350,42 @@ impl Provider for LlamaProvider {
             tokio::time::sleep(std::time::Duration::from_millis(delay_ms)).await;
             delay_ms = (delay_ms.saturating_mul(2)).min(3000);
         }
-        Err(anyhow::anyhow!("llama provider failed to start or health-check failed"))
+        Err(ProviderError::StartFailed.into())
     }
 
     async fn stop(&self) -> anyhow::Result<()> {
         if let Some(mut c) = self.child.lock().await.take() {
+            // TerminateProcess on Windows, SIGKILL elsewhere; then reap it
             c.kill().await.ok();
+            c.wait().await.ok();
         }
         Ok(())
     }
 
+    fn supports_vision(&self) -> bool {
+        self.mmproj.is_some()
+    }
+
+    fn cache_stats(&self) -> Option<CacheStats> {
+        Some(self.cache.stats())
+    }
+
     async fn is_running(&self) -> bool {
         if let Some(c) = &*self.child.lock().await {
             c.id().is_some()
         } else { false }
     }
 
-    async fn chat(&self, messages: &[Message]) -> anyhow::Result<String> {
-        // proxy to local llama.cpp http endpoint
-        let url = format!("http://{}/v1/chat/completions", self.addr);
-        #[derive(Serialize)] struct Req<'a> { model: &'a str, messages: &'a [crate::types::Message] }
-        let model_name = self.model.to_str().unwrap_or("");
-        let body = Req { model: model_name, messages };
-        let resp = reqwest::Client::new().post(&url).json(&body).send().await?.json::<serde_json::Value>().await?;
-        // extract text similar to LlamaClient
-        let text = resp
-            .get("choices")
-            .and_then(|c| c.get(0))
-            .and_then(|ch| ch.get("message"))
-            .and_then(|m| m.get("content"))
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string())
-            .unwrap_or_else(|| "".to_string());
-        Ok(text)
+    async fn chat(&self, messages: &[Message]) -> Result<String, ProviderError> {
+        self.chat_with(messages, &InferenceParams::default()).await
+    }
+
+    async fn chat_timed(&self, messages: &[Message]) -> Result<(String, Option<Timings>), ProviderError> {
+        self.timed_chat(messages, &InferenceParams::default()).await
+    }
+
+    async fn chat_with(&self, messages: &[Message], params: &InferenceParams) -> Result<String, ProviderError> {
+        self.timed_chat(messages, params).await.map(|(text, _)| text)
     }
 }
 
@@ -141,97 +393,417 @@ pub struct ModelServer {
     manager: Arc<ModelManager>,
     providers: Arc<RwLock<HashMap<String, Arc<dyn Provider>>>>,
     pub addr: SocketAddr,
+    /// Ports tried when `addr` is busy.
+    pub ports: crate::models::instance::PortRange,
+    pub metrics: Arc<ServerMetrics>,
+    /// Preloaded models, served once ready without being registered.
+    pub warmup: Warmup,
+    /// Where the request log goes; the app log at info level without one.
+    pub requests: Option<mpsc::UnboundedSender<RequestLog>>,
+    conversations: Arc<Conversations>,
+    /// Listed by `/v1/tasks`; no tasks without one.
+    pub tasks: Option<TaskStore>,
+    /// Browser origins allowed to call the API; see `models::access`.
+    pub cors_origins: Vec<String>,
+    /// Extra names `/ui` and `/v1` answer to; see `access::host_check`.
+    pub allowed_hosts: Vec<String>,
 }
 
 impl ModelServer {
     pub fn new(manager: Arc<ModelManager>, bind: SocketAddr) -> Self {
-        Self { manager, providers: Arc::new(RwLock::new(HashMap::new())), addr: bind }
+        Self { manager, providers: Arc::new(RwLock::new(HashMap::new())), addr: bind, ports: Default::default(), metrics: Arc::new(ServerMetrics::new()), warmup: Warmup::default(), requests: None, conversations: Arc::default(), tasks: None, cors_origins: vec![], allowed_hosts: vec![] }
     }
 
-    pub async fn start_local_server(&self) -> anyhow::Result<()> {
+    pub fn with_request_log(mut self, requests: mpsc::UnboundedSender<RequestLog>) -> Self {
+        self.requests = Some(requests);
+        self
+    }
+
+    pub fn with_cors(mut self, origins: Vec<String>) -> Self {
+        self.cors_origins = origins;
+        self
+    }
+
+    pub fn with_allowed_hosts(mut self, hosts: Vec<String>) -> Self {
+        self.allowed_hosts = hosts;
+        self
+    }
+
+    pub fn with_tasks(mut self, tasks: TaskStore) -> Self {
+        self.tasks = Some(tasks);
+        self
+    }
+
+    pub fn with_warmup(mut self, warmup: Warmup) -> Self {
+        self.warmup = warmup;
+        self
+    }
+
+    pub fn with_ports(mut self, ports: crate::models::instance::PortRange) -> Self {
+        self.ports = ports;
+        self
+    }
+
+    /// Serve in the background. The handle holds the address actually
+    /// bound, which differs from `addr` when that was busy.
+    pub async fn start_local_server(&self) -> anyhow::Result<ServerHandle> {
         // spawn a minimal axum server that serves /v1/models and /v1/chat/completions
         use axum::{routing::{get, post}, Router, extract::Json, response::IntoResponse};
         use serde_json::json;
 
         let mgr = Arc::clone(&self.manager);
         let providers = Arc::clone(&self.providers);
+        let metrics = Arc::clone(&self.metrics);
+        let models_metrics = Arc::clone(&self.metrics);
+        let export_metrics = Arc::clone(&self.metrics);
+        let logged_metrics = Arc::clone(&self.metrics);
+        let requests = self.requests.clone();
+        let export_providers = Arc::clone(&self.providers);
+        let stopped_providers = Arc::clone(&self.providers);
+        let warmup = self.warmup.clone();
+        let conversations = Arc::clone(&self.conversations);
+        let watched_providers = Arc::clone(&self.providers);
+        let tasks = self.tasks.clone();
+        let export_tasks = self.tasks.clone();
+        let cors_origins = Arc::new(self.cors_origins.clone());
+        let allowed_hosts = Arc::new(self.allowed_hosts.clone());
+        let (created, shown, deleted) = (Arc::clone(&self.conversations), Arc::clone(&self.conversations), Arc::clone(&self.conversations));
+        let replies: Arc<idempotency::Replies<(Result<ChatResp, ChatError>, Usage)>> = Arc::default();
+        let stop = Arc::new(watch::channel(false).0);
+        let admin_stop = Arc::clone(&stop);
+        let events_stop = Arc::clone(&stop);
+        let mut token = [0u8; 16];
+        getrandom::getrandom(&mut token)?;
+        let token = hex::encode(token);
+        let expected = format!("Bearer {}", token);
 
-        #[derive(Deserialize)]
-        struct ChatReq {
-            model: Option<String>,
-            messages: Vec<Message>,
-        }
-
-        #[derive(Serialize)]
-        struct ChatChoice {
-            message: Option<MessageResp>,
-        }
-        #[derive(Serialize)]
-        struct MessageResp {
-            content: Option<String>,
-        }
-        #[derive(Serialize)]
-        struct ChatResp {
-            choices: Vec<ChatChoice>,
-        }
-
-        let list_models = move || {
+        // answers from the cached listing; a client holding the current ETag gets 304
+        let list_models = move |headers: axum::http::HeaderMap| {
             let mgr = Arc::clone(&mgr);
+            let metrics = Arc::clone(&models_metrics);
+            let known = headers.get(axum::http::header::IF_NONE_MATCH).and_then(|v| v.to_str().ok()).map(str::to_string);
             async move {
-                match mgr.discover() {
-                    Ok(ms) => {
-                        let models: Vec<_> = ms.into_iter().map(|m| json!({"name": m.name, "format": m.format, "size": m.size})).collect();
-                        (axum::http::StatusCode::OK, Json(json!({"models": models})))
+                metrics.record_models_request();
+                match mgr.listing() {
+                    Ok(listing) => {
+                        let etag = [(axum::http::header::ETAG, listing.etag.clone()), (axum::http::header::CACHE_CONTROL, "no-cache".to_string())];
+                        if known.is_some_and(|k| etag_matches(&k, &listing.etag)) {
+                            return (axum::http::StatusCode::NOT_MODIFIED, etag).into_response();
+                        }
+                        let models: Vec<_> = listing.models.into_iter().map(|m| json!({"name": m.name, "format": m.format, "size": m.size})).collect();
+                        (axum::http::StatusCode::OK, etag, Json(json!({"models": models}))).into_response()
                     }
-                    Err(e) => (axum::http::StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))),
+                    Err(e) => (axum::http::StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))).into_response(),
                 }
             }
         };
 
-        let chat = move |Json(payload): Json<ChatReq>| {
+        let chat = move |headers: axum::http::HeaderMap, Json(payload): Json<ChatReq>| {
             let providers = Arc::clone(&providers);
-            async move {
-                // select provider: if model supplied and provider registered, else use mock provider if any
-                let model = payload.model.clone();
-                let mut resp_text = String::from("no provider");
-                if let Some(mn) = model {
-                    let pmap = providers.read().await;
-                    if let Some(p) = pmap.get(&mn) {
-                        match p.chat(&payload.messages).await {
-                            Ok(s) => resp_text = s,
-                            Err(e) => resp_text = format!("error: {}", e),
+            let metrics = Arc::clone(&metrics);
+            let warmup = warmup.clone();
+            let replies = Arc::clone(&replies);
+            let conversation = payload.conversation.clone().map(|id| {
+                let store = conversations.get(&id);
+                (id, store)
+            });
+            let key = headers.get(idempotency::HEADER).and_then(|v| v.to_str().ok()).map(str::to_string);
+            let stream = payload.stream.then(|| payload.stream_options.as_ref().is_some_and(|o| o.include_usage));
+            let answer = async move {
+                let _in_flight = metrics.chat_started();
+                let started = std::time::Instant::now();
+                // the named model's provider (or a preloaded one, once warmed up); without a model,
+                // every registered provider in turn until one answers or fails for a reason the next
+                // would share
+                let chain: Vec<Arc<dyn Provider>> = match &payload.model {
+                    Some(mn) => {
+                        let registered = providers.read().await.get(mn).cloned();
+                        match registered {
+                            Some(p) => vec![p],
+                            None => warmup.ready(mn).await.into_iter().collect(),
                         }
-                    } else {
-                        resp_text = format!("no provider registered for model {}", mn);
                     }
-                } else {
-                    // try any provider
-                    let pmap = providers.read().await;
-                    if let Some((_k, p)) = pmap.iter().next() {
-                        match p.chat(&payload.messages).await {
-                            Ok(s) => resp_text = s,
-                            Err(e) => resp_text = format!("error: {}", e),
+                    None => {
+                        let pmap = providers.read().await;
+                        let mut named: Vec<_> = pmap.iter().collect();
+                        named.sort_by_key(|(name, _)| name.as_str());
+                        named.into_iter().map(|(_, p)| Arc::clone(p)).collect()
+                    }
+                };
+                let params = payload.params();
+                let turn: Vec<Message> = payload.messages.into_iter().map(ChatMessage::into_message).collect();
+                let (store, params) = match (conversation, params) {
+                    (Some((id, None)), _) => (None, Err(ProviderError::BadRequest { detail: format!("no conversation {} (it may have expired)", id) })),
+                    (conversation, params) => (conversation.and_then(|(_, store)| store), params),
+                };
+                let mut messages = store.as_ref().map(|s| s.get_short()).unwrap_or_default();
+                messages.extend(turn.iter().cloned());
+                let has_images = messages.iter().any(|m| !m.images.is_empty());
+                let mut outcome = Err(ChatError {
+                    error: match &payload.model {
+                        Some(mn) => ProviderError::ModelNotFound(mn.clone()),
+                        None => ProviderError::Unavailable("no providers available".into()),
+                    },
+                    provider: None,
+                });
+                let (chain, params) = match params {
+                    Ok(params) => (chain, params),
+                    Err(error) => {
+                        outcome = Err(ChatError { error, provider: None });
+                        (vec![], InferenceParams::default())
+                    }
+                };
+                for p in chain {
+                    if has_images && !p.supports_vision() {
+                        let detail = format!("provider {} does not accept images", p.name());
+                        outcome = Err(ChatError { error: ProviderError::BadRequest { detail }, provider: Some(p.metadata()) });
+                        continue;
+                    }
+                    match p.chat_with(&messages, &params).await {
+                        // stop and max_tokens hold for every provider, not only those that implement them
+                        Ok(s) => {
+                            let done = guard::enforce(&s, FinishReason::Stop, &params);
+                            if let Some(store) = &store {
+                                for m in turn.iter().cloned() {
+                                    store.add_short(m);
+                                }
+                                store.add_short(Message::new("assistant", &done.text));
+                            }
+                            outcome = Ok((done, p.model_id()));
+                            break;
+                        }
+                        Err(error) => {
+                            metrics.record_provider_error();
+                            let next = error.should_fall_back();
+                            if next {
+                                tracing::warn!("{} failed, trying the next provider: {}", p.name(), error);
+                            }
+                            outcome = Err(ChatError { error, provider: Some(p.metadata()) });
+                            if !next {
+                                break;
+                            }
+                        }
+                    }
+                }
+                let prompt_tokens = messages.iter().map(|m| estimate_tokens(&m.full_text())).sum();
+                let completion_tokens = outcome.as_ref().map_or(0, |(done, _)| estimate_tokens(&done.text));
+                metrics.chat_finished(started.elapsed(), outcome.is_ok(), prompt_tokens, completion_tokens);
+                let out = outcome.map(|(done, model)| {
+                    let choice = ChatChoice { index: 0, message: Some(MessageResp { role: "assistant", content: Some(done.text) }), finish_reason: Some(done.finish) };
+                    ChatResp {
+                        id: format!("chatcmpl-{}", uuid::Uuid::new_v4().simple()),
+                        object: "chat.completion",
+                        created: std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0, |d| d.as_secs()),
+                        model: payload.model.clone().unwrap_or(model),
+                        choices: vec![choice],
+                        usage: ChatUsage { prompt_tokens, completion_tokens, total_tokens: prompt_tokens + completion_tokens },
+                    }
+                });
+                (out, Usage { model: payload.model, prompt_tokens, completion_tokens })
+            };
+            async move {
+                let ((out, mut usage), replayed) = match key {
+                    Some(key) => replies.run(&key, answer, |(out, _)| out.is_ok()).await,
+                    None => (answer.await, false),
+                };
+                if replayed {
+                    // generated for the first try; counting it again would double it
+                    (usage.prompt_tokens, usage.completion_tokens) = (0, 0);
+                }
+                let replayed = [(idempotency::REPLAYED, if replayed { "true" } else { "false" })];
+                match out {
+                    Ok(out) => match stream {
+                        Some(include_usage) => {
+                            let sse = [(axum::http::header::CONTENT_TYPE, "text/event-stream"), (axum::http::header::CACHE_CONTROL, "no-cache")];
+                            (axum::http::StatusCode::OK, replayed, sse, axum::Extension(usage), out.events(include_usage)).into_response()
+                        }
+                        None => (axum::http::StatusCode::OK, replayed, axum::Extension(usage), Json(out)).into_response(),
+                    },
+                    Err(failed) => {
+                        let retry_after = match failed.error {
+                            ProviderError::RateLimited { retry_after: Some(secs) } => Some(secs),
+                            _ => None,
+                        };
+                        let mut response = (failed.status(), replayed, axum::Extension(usage), Json(failed.body())).into_response();
+                        if let Some(secs) = retry_after {
+                            response.headers_mut().insert(axum::http::header::RETRY_AFTER, secs.into());
                         }
-                    } else {
-                        resp_text = "no providers available".to_string();
+                        response
                     }
                 }
-                let choice = ChatChoice { message: Some(MessageResp { content: Some(resp_text) }) };
-                let out = ChatResp { choices: vec![choice] };
-                (axum::http::StatusCode::OK, Json(out))
             }
         };
 
-        let app = Router::new().route("/v1/models", get(list_models)).route("/v1/chat/completions", post(chat));
-        let addr = self.addr;
-        let listener = tokio::net::TcpListener::bind(addr).await?;
-        let server = axum::serve(listener, app.into_make_service());
-        tokio::spawn(async move {
-            if let Err(e) = server.await {
-                tracing::error!("model server error: {}", e);
+        // conversations: create with optional opening messages, read back, delete
+        let create_conversation = move |body: Option<Json<NewConversation>>| {
+            let messages = body.map(|Json(b)| b.messages.into_iter().map(ChatMessage::into_message).collect()).unwrap_or_default();
+            let id = created.create(messages);
+            async move { Json(json!({"id": id, "object": "conversation"})) }
+        };
+        let not_found = |id: &str| (axum::http::StatusCode::NOT_FOUND, Json(json!({"error": {"message": format!("no conversation {}", id), "type": "not_found"}}))).into_response();
+        let show_conversation = move |axum::extract::Path(id): axum::extract::Path<String>| {
+            let store = shown.get(&id);
+            async move {
+                match store.map(|s| encode(&s.get_short())) {
+                    Some(Ok(messages)) => Json(json!({"id": id, "object": "conversation", "messages": messages})).into_response(),
+                    Some(Err(e)) => (axum::http::StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": {"message": format!("{:#}", e)}}))).into_response(),
+                    None => not_found(&id),
+                }
+            }
+        };
+        let delete_conversation = move |axum::extract::Path(id): axum::extract::Path<String>| {
+            let gone = deleted.delete(&id);
+            async move {
+                match gone {
+                    true => Json(json!({"id": id, "object": "conversation.deleted", "deleted": true})).into_response(),
+                    false => not_found(&id),
+                }
+            }
+        };
+
+        let events = move |ws: axum::extract::ws::WebSocketUpgrade| {
+            let providers = Arc::clone(&watched_providers);
+            let stop = Arc::clone(&events_stop).subscribe();
+            async move { ws.on_upgrade(move |socket| stream_events(socket, providers, stop)) }
+        };
+
+        // tasks newest first, with rolled-up progress, for the web UI and dashboards
+        let list_tasks = move || {
+            let tasks = tasks.clone();
+            async move {
+                let listed: Vec<_> = tasks.as_ref().map_or(vec![], |store| {
+                    let all = store.list();
+                    all.iter().map(|t| json!({
+                        "id": t.id, "title": t.title, "status": t.status, "progress": crate::tasks::rollup(t, &all),
+                        "parent_id": t.parent_id, "created_at": t.created_at, "finished_at": t.finished_at,
+                    })).collect()
+                });
+                Json(json!({"tasks": listed}))
+            }
+        };
+
+        let ui = |path: Option<axum::extract::Path<String>>| async move {
+            match web_ui::asset(path.as_ref().map_or("", |p| p.0.as_str())) {
+                Some((content_type, body)) => ([(axum::http::header::CONTENT_TYPE, content_type)], body.into_owned()).into_response(),
+                None => axum::http::StatusCode::NOT_FOUND.into_response(),
+            }
+        };
+
+        let export = move || {
+            let metrics = Arc::clone(&export_metrics);
+            let providers = Arc::clone(&export_providers);
+            let tasks = export_tasks.clone();
+            async move {
+                let mut text = metrics.render();
+                if let Some(store) = &tasks {
+                    render_tasks(&store.list(), &mut text);
+                }
+                let pmap = providers.read().await;
+                let mut caches: Vec<_> = pmap.iter().filter_map(|(model, p)| Some((model, p.cache_stats()?))).collect();
+                caches.sort_by_key(|(model, _)| model.as_str());
+                if !caches.is_empty() {
+                    text.push_str(prefix_cache::METRICS_HEADER);
+                }
+                for (model, stats) in caches {
+                    stats.render(model, &mut text);
+                }
+                let native = pool::usage();
+                if !native.is_empty() {
+                    text.push_str(pool::METRICS_HEADER);
+                    pool::render(&native, &mut text);
+                }
+                ([(axum::http::header::CONTENT_TYPE, "text/plain; version=0.0.4")], text)
             }
+        };
+
+        let shutdown = move |headers: axum::http::HeaderMap| {
+            let stop = Arc::clone(&admin_stop);
+            let allowed = headers.get(axum::http::header::AUTHORIZATION).and_then(|v| v.to_str().ok()) == Some(expected.as_str());
+            async move {
+                if !allowed {
+                    return (axum::http::StatusCode::UNAUTHORIZED, "wrong or missing token");
+                }
+                stop.send_replace(true);
+                (axum::http::StatusCode::OK, "stopping")
+            }
+        };
+
+        let app = Router::new()
+            .route("/v1/models", get(list_models))
+            .route("/v1/chat/completions", post(chat))
+            .route("/v1/conversations", post(create_conversation))
+            .route("/v1/conversations/:id", get(show_conversation).delete(delete_conversation))
+            .route("/v1/events", get(events))
+            .route("/v1/tasks", get(list_tasks))
+            .route(web_ui::ROUTE, get(ui))
+            .route(&format!("{}/", web_ui::ROUTE), get(ui))
+            .route(&format!("{}/*path", web_ui::ROUTE), get(ui))
+            .route("/metrics", get(export))
+            .route(SHUTDOWN_ROUTE, post(shutdown))
+            .layer(axum::middleware::from_fn(move |req: axum::extract::Request, next: axum::middleware::Next| access::cors(Arc::clone(&cors_origins), req, next)))
+            .layer(axum::middleware::from_fn(move |req: axum::extract::Request, next: axum::middleware::Next| access::host_check(Arc::clone(&allowed_hosts), req, next)))
+            .layer(axum::middleware::from_fn(move |req: axum::extract::Request, next: axum::middleware::Next| {
+                let (metrics, requests) = (Arc::clone(&logged_metrics), requests.clone());
+                async move {
+                    let started = std::time::Instant::now();
+                    let method = req.method().to_string();
+                    let path = crate::transcript::redact(req.uri().path_and_query().map_or("/", |p| p.as_str()));
+                    let sent = req.headers().get(crate::request_id::HEADER).and_then(|v| v.to_str().ok()).and_then(crate::request_id::accept);
+                    let id = sent.unwrap_or_else(crate::request_id::new_id);
+                    let mut response = crate::request_id::scope(id.clone(), next.run(req)).await;
+                    if let Ok(value) = axum::http::HeaderValue::from_str(&id) {
+                        response.headers_mut().insert(crate::request_id::HEADER, value);
+                    }
+                    let usage = response.extensions().get::<Usage>().cloned();
+                    let status = response.status().as_u16();
+                    metrics.record_response(status);
+                    let log = RequestLog {
+                        id,
+                        method,
+                        path,
+                        model: usage.as_ref().and_then(|u| u.model.as_deref()).map(crate::transcript::redact),
+                        status,
+                        latency: started.elapsed(),
+                        prompt_tokens: usage.as_ref().map_or(0, |u| u.prompt_tokens),
+                        completion_tokens: usage.as_ref().map_or(0, |u| u.completion_tokens),
+                    };
+                    match &requests {
+                        Some(tx) => {
+                            let _ = tx.send(log);
+                        }
+                        None => log::info!(target: "server", "{}", log.line()),
+                    }
+                    response
+                }
+            }));
+        let listener = crate::models::instance::bind(self.addr, &self.ports)?;
+        listener.set_nonblocking(true)?;
+        let listener = tokio::net::TcpListener::from_std(listener)?;
+        let bound = listener.local_addr()?;
+        let mut draining = stop.subscribe();
+        let mut stopping = stop.subscribe();
+        let server = axum::serve(listener, app.into_make_service()).with_graceful_shutdown(async move {
+            let _ = draining.wait_for(|s| *s).await;
         });
-        Ok(())
+        let task = tokio::spawn(async move {
+            let mut server = std::pin::pin!(std::future::IntoFuture::into_future(server));
+            tokio::select! {
+                result = &mut server => {
+                    if let Err(e) = result {
+                        tracing::error!("model server error: {}", e);
+                    }
+                }
+                _ = async { stopping.wait_for(|s| *s).await.is_ok() } => {
+                    if tokio::time::timeout(DRAIN, &mut server).await.is_err() {
+                        tracing::warn!("model server: requests still running after {:?}; stopping anyway", DRAIN);
+                    }
+                }
+            }
+            for provider in stopped_providers.read().await.values() {
+                provider.stop().await.ok();
+            }
+        });
+        Ok(ServerHandle { addr: bound, token, stop, task: Some(task) })
     }
 
     pub async fn register_mock_for_model(&self, model_name: &str) -> anyhow::Result<()> {
@@ -243,7 +815,7 @@ impl ModelServer {
                 return Ok(());
             }
         }
-        Err(anyhow::anyhow!("model not found"))
+        Err(ProviderError::ModelNotFound(model_name.to_string()).into())
     }
 
     pub async fn register_provider(&self, name: &str, provider: Arc<dyn Provider>) -> anyhow::Result<()> {
@@ -252,11 +824,339 @@ impl ModelServer {
     }
 }
 
+/// The chat-completions request. Sampling options travel to the provider in
+/// `params`; fields this server has no use for (`user`, `logprobs`, ...)
+/// are ignored.
+#[derive(Deserialize)]
+struct ChatReq {
+    model: Option<String>,
+    messages: Vec<ChatMessage>,
+    #[serde(flatten)]
+    params: InferenceParams,
+    #[serde(default)]
+    stream: bool,
+    #[serde(default)]
+    stream_options: Option<StreamOptions>,
+    /// Only one choice is generated.
+    #[serde(default)]
+    n: Option<u32>,
+    /// `json_object` or `json_schema`, enforced with a grammar.
+    #[serde(default)]
+    response_format: Option<serde_json::Value>,
+    /// A conversation from `POST /v1/conversations`; its history goes
+    /// before `messages`. See `models::conversations`.
+    #[serde(default)]
+    conversation: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+struct NewConversation {
+    #[serde(default)]
+    messages: Vec<ChatMessage>,
+}
+
+#[derive(Deserialize, Default)]
+struct StreamOptions {
+    #[serde(default)]
+    include_usage: bool,
+}
+
+impl ChatReq {
+    /// The params with `response_format` turned into a grammar, or why the
+    /// request cannot be served.
+    fn params(&self) -> Result<InferenceParams, ProviderError> {
+        if self.n.is_some_and(|n| n != 1) {
+            return Err(ProviderError::BadRequest { detail: "only n = 1 is supported".into() });
+        }
+        let mut params = self.params.clone();
+        let Some(format) = &self.response_format else { return Ok(params) };
+        let grammar = match format["type"].as_str() {
+            Some("text") => None,
+            Some("json_object") => Some(crate::llm::grammar::json()),
+            Some("json_schema") => match format["json_schema"].get("schema") {
+                Some(schema) => Some(crate::llm::grammar::from_schema(schema)),
+                None => return Err(ProviderError::BadRequest { detail: "response_format.json_schema.schema is missing".into() }),
+            },
+            other => return Err(ProviderError::BadRequest { detail: format!("unsupported response_format type {:?}", other.unwrap_or("")) }),
+        };
+        if params.grammar.is_none() {
+            params.grammar = grammar;
+        }
+        Ok(params)
+    }
+}
+
+#[derive(Serialize, Clone)]
+struct ChatChoice {
+    index: u32,
+    message: Option<MessageResp>,
+    finish_reason: Option<FinishReason>,
+}
+
+#[derive(Serialize, Clone)]
+struct MessageResp {
+    role: &'static str,
+    content: Option<String>,
+}
+
+#[derive(Serialize, Clone, Copy)]
+struct ChatUsage {
+    prompt_tokens: u64,
+    completion_tokens: u64,
+    total_tokens: u64,
+}
+
+#[derive(Serialize, Clone)]
+struct ChatResp {
+    id: String,
+    object: &'static str,
+    created: u64,
+    model: String,
+    choices: Vec<ChatChoice>,
+    /// Estimated as words, as in the server metrics.
+    usage: ChatUsage,
+}
+
+impl ChatResp {
+    /// The reply as server-sent `chat.completion.chunk` events. The reply is
+    /// generated whole, so it arrives as one delta.
+    fn events(&self, include_usage: bool) -> String {
+        let chunk = |choices: serde_json::Value, usage: Option<ChatUsage>| {
+            let mut chunk = serde_json::json!({"id": self.id, "object": "chat.completion.chunk", "created": self.created, "model": self.model, "choices": choices});
+            if let Some(usage) = usage {
+                chunk["usage"] = serde_json::json!(usage);
+            }
+            format!("data: {}\n\n", chunk)
+        };
+        let mut events = String::new();
+        for choice in &self.choices {
+            let content = choice.message.as_ref().and_then(|m| m.content.clone());
+            events.push_str(&chunk(serde_json::json!([{"index": choice.index, "delta": {"role": "assistant", "content": content}, "finish_reason": null}]), None));
+            events.push_str(&chunk(serde_json::json!([{"index": choice.index, "delta": {}, "finish_reason": choice.finish_reason}]), None));
+        }
+        if include_usage {
+            events.push_str(&chunk(serde_json::json!([]), Some(self.usage)));
+        }
+        events.push_str("data: [DONE]\n\n");
+        events
+    }
+}
+
+/// A registered provider as `/v1/events` reports it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct ProviderStatus {
+    /// The model name it is registered under.
+    name: String,
+    #[serde(flatten)]
+    metadata: ProviderMetadata,
+    running: bool,
+}
+
+async fn provider_status(providers: &RwLock<HashMap<String, Arc<dyn Provider>>>) -> Vec<ProviderStatus> {
+    let pmap = providers.read().await;
+    let mut status = vec![];
+    for (name, p) in pmap.iter() {
+        status.push(ProviderStatus { name: name.clone(), metadata: p.metadata(), running: p.is_running().await });
+    }
+    status.sort_by(|a, b| a.name.cmp(&b.name));
+    status
+}
+
+/// Send agent events (see `agent::events`) as they happen, and the provider
+/// list whenever it changes, one JSON text message each, until the client
+/// goes away or the server stops.
+async fn stream_events(mut socket: axum::extract::ws::WebSocket, providers: Arc<RwLock<HashMap<String, Arc<dyn Provider>>>>, mut stop: watch::Receiver<bool>) {
+    use axum::extract::ws::Message as Frame;
+    use tokio::sync::broadcast::error::RecvError;
+    let mut agent = crate::agent::events::subscribe();
+    let mut poll = tokio::time::interval(PROVIDER_POLL);
+    let mut shown = None;
+    loop {
+        let event = tokio::select! {
+            _ = poll.tick() => {
+                let status = provider_status(&providers).await;
+                if shown.as_ref() == Some(&status) {
+                    continue;
+                }
+                shown = Some(status.clone());
+                serde_json::json!({"type": "providers", "providers": status})
+            }
+            event = agent.recv() => match event {
+                Ok(event) => serde_json::to_value(&event).unwrap_or_default(),
+                Err(RecvError::Lagged(missed)) => serde_json::json!({"type": "lagged", "missed": missed}),
+                Err(RecvError::Closed) => break,
+            },
+            frame = socket.recv() => match frame {
+                Some(Ok(Frame::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => continue,
+            },
+            _ = async { stop.wait_for(|s| *s).await.is_ok() } => break,
+        };
+        if socket.send(Frame::Text(event.to_string())).await.is_err() {
+            return;
+        }
+    }
+    let _ = socket.send(Frame::Close(None)).await;
+}
+
+/// A chat no provider answered: the last provider's error, and which
+/// provider that was.
+#[derive(Debug, Clone)]
+struct ChatError {
+    error: ProviderError,
+    provider: Option<ProviderMetadata>,
+}
+
+impl ChatError {
+    fn status(&self) -> axum::http::StatusCode {
+        use axum::http::StatusCode;
+        match self.error {
+            ProviderError::ModelNotFound(_) => StatusCode::NOT_FOUND,
+            ProviderError::BadRequest { .. } => StatusCode::BAD_REQUEST,
+            ProviderError::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+            ProviderError::Timeout => StatusCode::GATEWAY_TIMEOUT,
+            ProviderError::Unavailable(_) | ProviderError::NoModel { .. } => StatusCode::SERVICE_UNAVAILABLE,
+            _ => StatusCode::BAD_GATEWAY,
+        }
+    }
+
+    /// OpenAI-style error body, with the provider and model it came from.
+    fn body(&self) -> serde_json::Value {
+        serde_json::json!({"error": {
+            "message": self.error.to_string(),
+            "type": self.error.kind(),
+            "code": self.error.code(),
+            "provider": self.provider.as_ref().map(|p| p.provider),
+            "model": self.provider.as_ref().map(|p| p.model.as_str()),
+        }})
+    }
+}
+
+/// Whether an `If-None-Match` value names `etag`: `*`, or a list of tags
+/// compared weakly.
+fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    if_none_match.trim() == "*" || if_none_match.split(',').any(|tag| tag.trim().trim_start_matches("W/") == etag)
+}
+
+/// A model server running in this process. Dropping it asks the server to
+/// stop; [`ServerHandle::shutdown`] also waits for it.
+pub struct ServerHandle {
+    pub addr: SocketAddr,
+    /// Bearer token for [`SHUTDOWN_ROUTE`].
+    pub token: String,
+    stop: Arc<watch::Sender<bool>>,
+    task: Option<JoinHandle<()>>,
+}
+
+impl ServerHandle {
+    /// Resolves once something asks the server to stop, e.g. another
+    /// process through [`SHUTDOWN_ROUTE`].
+    pub async fn stop_requested(&self) {
+        let _ = self.stop.subscribe().wait_for(|s| *s).await;
+    }
+
+    /// Whether the server has been asked to stop.
+    pub fn is_stopping(&self) -> bool {
+        *self.stop.borrow()
+    }
+
+    /// Stop taking connections, let in-flight requests finish for up to
+    /// [`DRAIN`], then stop the providers. False when it had to be aborted.
+    pub async fn shutdown(mut self) -> bool {
+        self.stop.send_replace(true);
+        let Some(mut task) = self.task.take() else { return true };
+        match tokio::time::timeout(DRAIN + Duration::from_secs(2), &mut task).await {
+            Ok(_) => true,
+            Err(_) => {
+                task.abort();
+                false
+            }
+        }
+    }
+}
+
+impl Drop for ServerHandle {
+    fn drop(&mut self) {
+        self.stop.send_replace(true);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use tempfile::tempdir;
 
+    /// A llama.cpp stand-in that ignores its arguments and runs until killed.
+    fn sleeper(dir: &std::path::Path) -> anyhow::Result<PathBuf> {
+        if cfg!(windows) {
+            let path = dir.join("llama-server.cmd");
+            std::fs::write(&path, "@ping -n 60 127.0.0.1 >nul\r\n")?;
+            return Ok(path);
+        }
+        let path = dir.join("llama-server");
+        std::fs::write(&path, "#!/bin/sh\nexec sleep 60\n")?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))?;
+        }
+        Ok(path)
+    }
+
+    #[tokio::test]
+    async fn test_llama_provider_starts_and_reaps_its_process() -> anyhow::Result<()> {
+        let td = tempdir()?;
+        // answers the health check the stand-in cannot
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        tokio::spawn(async move { axum::serve(listener, axum::Router::new().route("/v1/health", axum::routing::get(|| async { "ok" }))).await });
+
+        let provider = LlamaProvider::new(Some(sleeper(td.path())?), td.path().join("model.bin"), addr);
+        provider.start().await?;
+        {
+            let mut child = provider.child.lock().await;
+            let child = child.as_mut().expect("start keeps the child");
+            assert!(child.try_wait()?.is_none(), "the server process is running");
+        }
+        provider.stop().await?;
+        assert!(provider.child.lock().await.is_none());
+        provider.stop().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_pinned_prefix_is_counted_by_the_server() -> anyhow::Result<()> {
+        // a llama.cpp stand-in: the template wraps the prompt, every character is a token
+        let seen: Arc<parking_lot::Mutex<Vec<serde_json::Value>>> = Arc::default();
+        let tokenized = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let (bodies, counted) = (Arc::clone(&seen), Arc::clone(&tokenized));
+        let app = axum::Router::new()
+            .route("/apply-template", axum::routing::post(|axum::Json(v): axum::Json<serde_json::Value>| async move {
+                axum::Json(serde_json::json!({"prompt": format!("<s>{}</s>", v["messages"][0]["content"].as_str().unwrap_or_default())}))
+            }))
+            .route("/tokenize", axum::routing::post(move |axum::Json(v): axum::Json<serde_json::Value>| async move {
+                counted.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                axum::Json(serde_json::json!({"tokens": v["content"].as_str().unwrap_or_default().chars().map(|_| 1).collect::<Vec<_>>()}))
+            }))
+            .route("/v1/chat/completions", axum::routing::post(move |axum::Json(v): axum::Json<serde_json::Value>| async move {
+                bodies.lock().push(v);
+                axum::Json(serde_json::json!({"choices": [{"message": {"content": "ok"}}]}))
+            }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let provider = LlamaProvider::new(None, PathBuf::from("m.gguf"), addr).with_prefix_cache(PrefixCacheConfig { enabled: true, pin_system: true });
+        let turn = vec![Message::new("system", "be brief"), Message::new("user", "hi there")];
+        provider.chat(&turn).await?;
+        provider.chat(&[turn.clone(), vec![Message::new("user", "again")]].concat()).await?;
+        provider.chat(&turn[1..]).await?;
+        let keeps: Vec<_> = seen.lock().iter().map(|b| b.get("n_keep").cloned()).collect();
+        assert_eq!(keeps, vec![Some(serde_json::json!(15)), Some(serde_json::json!(15)), None]);
+        assert_eq!(tokenized.load(std::sync::atomic::Ordering::Relaxed), 1, "counted once per prefix");
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_register_mock_for_model() -> anyhow::Result<()> {
         let td = tempdir()?;
@@ -270,4 +1170,337 @@ mod tests {
         assert!(map.contains_key("mymodel"));
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_shutdown_route_wants_the_token_and_drains() -> anyhow::Result<()> {
+        let td = tempdir()?;
+        let mgr = Arc::new(ModelManager { dir: td.path().to_path_buf() });
+        let server = ModelServer::new(mgr, "127.0.0.1:0".parse().unwrap());
+        let handle = server.start_local_server().await?;
+        let url = format!("http://{}{}", handle.addr, SHUTDOWN_ROUTE);
+        let client = reqwest::Client::new();
+        assert_eq!(client.post(&url).bearer_auth("guess").send().await?.status(), 401);
+        assert!(!handle.is_stopping());
+        assert_eq!(client.post(&url).bearer_auth(&handle.token).send().await?.status(), 200);
+        handle.stop_requested().await;
+        let addr = handle.addr;
+        assert!(handle.shutdown().await);
+        assert!(std::net::TcpStream::connect(addr).is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_request_log_has_model_tokens_and_status() -> anyhow::Result<()> {
+        let td = tempdir()?;
+        std::fs::write(td.path().join("tiny.gguf"), b"gguf")?;
+        let mgr = Arc::new(ModelManager { dir: td.path().to_path_buf() });
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let server = ModelServer::new(mgr, "127.0.0.1:0".parse().unwrap()).with_request_log(tx);
+        server.register_mock_for_model("tiny").await?;
+        let handle = server.start_local_server().await?;
+        let client = reqwest::Client::new();
+        let body = serde_json::json!({"model": "tiny", "messages": [{"role": "user", "content": "hello there"}]});
+        client.post(format!("http://{}/v1/chat/completions", handle.addr)).json(&body).send().await?;
+        client.get(format!("http://{}/v1/models?key=sk-abcdefghijklmnopqrst", handle.addr)).send().await?;
+        client.get(format!("http://{}/nowhere", handle.addr)).send().await?;
+
+        let chat = rx.recv().await.unwrap();
+        assert_eq!((chat.method.as_str(), chat.status, chat.model.as_deref(), chat.prompt_tokens), ("POST", 200, Some("tiny"), 2));
+        assert!(chat.line().starts_with("POST /v1/chat/completions 200 ") && chat.line().ends_with(" model=tiny tokens=2+4"));
+        let models = rx.recv().await.unwrap();
+        assert_eq!(models.line().split(' ').take(3).collect::<Vec<_>>(), ["GET", "/v1/models?key=[redacted]", "200"]);
+        assert_eq!(rx.recv().await.unwrap().status, 404);
+        let text = server.metrics.render();
+        assert!(text.contains("super_agent_responses_total{class=\"2xx\"} 2"));
+        assert!(text.contains("super_agent_responses_total{class=\"4xx\"} 1"));
+
+        // the model list is revalidated by its ETag
+        let models_url = format!("http://{}/v1/models", handle.addr);
+        let etag = client.get(&models_url).send().await?.headers()[reqwest::header::ETAG].to_str()?.to_string();
+        let unchanged = client.get(&models_url).header("If-None-Match", format!("W/\"x\", {}", etag)).send().await?;
+        assert_eq!(unchanged.status(), 304);
+        assert_eq!(client.get(&models_url).header("If-None-Match", "\"stale\"").send().await?.status(), 200);
+
+        // a retry with the same key is answered from the first try
+        let url = format!("http://{}/v1/chat/completions", handle.addr);
+        for replayed in ["false", "true"] {
+            let resp = client.post(&url).header("Idempotency-Key", "k1").json(&body).send().await?;
+            assert_eq!(resp.headers()[idempotency::REPLAYED], replayed);
+        }
+        handle.shutdown().await;
+        Ok(())
+    }
+
+    /// Fails every chat with the same error.
+    struct Failing(ProviderError);
+
+    #[async_trait]
+    impl Provider for Failing {
+        fn name(&self) -> &'static str {
+            "failing"
+        }
+
+        fn model_id(&self) -> String {
+            "busy".into()
+        }
+
+        async fn chat(&self, _messages: &[Message]) -> Result<String, ProviderError> {
+            Err(self.0.clone())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_provider_errors_map_to_statuses_and_fall_back() -> anyhow::Result<()> {
+        let td = tempdir()?;
+        std::fs::write(td.path().join("tiny.gguf"), b"gguf")?;
+        let mgr = Arc::new(ModelManager { dir: td.path().to_path_buf() });
+        let server = ModelServer::new(mgr, "127.0.0.1:0".parse().unwrap());
+        server.register_provider("a-busy", Arc::new(Failing(ProviderError::RateLimited { retry_after: Some(7) }))).await?;
+        server.register_mock_for_model("tiny").await?;
+        let handle = server.start_local_server().await?;
+        let client = reqwest::Client::new();
+        let url = format!("http://{}/v1/chat/completions", handle.addr);
+        let ask = |model: Option<&str>| serde_json::json!({"model": model, "messages": [{"role": "user", "content": "hi"}]});
+
+        // without a model the busy provider is skipped
+        let resp = client.post(&url).json(&ask(None)).send().await?;
+        assert_eq!(resp.status(), 200);
+        assert!(resp.json::<serde_json::Value>().await?["choices"][0]["message"]["content"].as_str().unwrap().starts_with("[mock:tiny.gguf]"));
+
+        let resp = client.post(&url).json(&ask(Some("a-busy"))).send().await?;
+        assert_eq!((resp.status().as_u16(), resp.headers()[reqwest::header::RETRY_AFTER].to_str()?), (429, "7"));
+        let body: serde_json::Value = resp.json().await?;
+        assert_eq!(body["error"]["code"], "E209");
+        assert_eq!((body["error"]["provider"].as_str(), body["error"]["model"].as_str()), (Some("failing"), Some("busy")));
+
+        let resp = client.post(&url).json(&ask(Some("nope"))).send().await?;
+        assert_eq!(resp.status(), 404);
+        assert_eq!(resp.json::<serde_json::Value>().await?["error"]["type"], "model_not_found");
+        assert!(!ProviderError::BadRequest { detail: "x".into() }.should_fall_back());
+        handle.shutdown().await;
+        Ok(())
+    }
+
+    /// Keeps the params of the last chat.
+    #[derive(Default)]
+    struct Params(std::sync::Mutex<InferenceParams>);
+
+    #[async_trait]
+    impl Provider for Params {
+        fn name(&self) -> &'static str {
+            "params"
+        }
+
+        fn model_id(&self) -> String {
+            "echo".into()
+        }
+
+        async fn chat(&self, messages: &[Message]) -> Result<String, ProviderError> {
+            self.chat_with(messages, &InferenceParams::default()).await
+        }
+
+        async fn chat_with(&self, _messages: &[Message], params: &InferenceParams) -> Result<String, ProviderError> {
+            *self.0.lock().unwrap() = params.clone();
+            Ok("ok".into())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_request_options_reach_the_provider_and_usage_comes_back() -> anyhow::Result<()> {
+        let td = tempdir()?;
+        let mgr = Arc::new(ModelManager { dir: td.path().to_path_buf() });
+        let server = ModelServer::new(mgr, "127.0.0.1:0".parse().unwrap());
+        let provider = Arc::new(Params::default());
+        server.register_provider("echo", provider.clone()).await?;
+        let handle = server.start_local_server().await?;
+        let client = reqwest::Client::new();
+        let url = format!("http://{}/v1/chat/completions", handle.addr);
+
+        let body = serde_json::json!({
+            "messages": [{"role": "user", "content": "hi"}],
+            "temperature": 0.5, "top_k": 20, "seed": 7, "stop": "###", "max_completion_tokens": 64,
+            "response_format": {"type": "json_object"}, "user": "someone",
+        });
+        let resp: serde_json::Value = client.post(&url).json(&body).send().await?.json().await?;
+        let sent = provider.0.lock().unwrap().clone();
+        assert_eq!((sent.temperature, sent.top_k, sent.seed, sent.max_tokens), (Some(0.5), Some(20), Some(7), Some(64)));
+        assert_eq!((sent.stop, sent.grammar), (vec!["###".to_string()], Some(crate::llm::grammar::json())));
+        assert_eq!((resp["object"].as_str(), resp["model"].as_str()), (Some("chat.completion"), Some("echo")));
+        let usage = &resp["usage"];
+        assert_eq!(usage["total_tokens"].as_u64(), Some(usage["prompt_tokens"].as_u64().unwrap() + usage["completion_tokens"].as_u64().unwrap()));
+
+        let streamed = serde_json::json!({"messages": [{"role": "user", "content": "hi"}], "stream": true, "stream_options": {"include_usage": true}});
+        let resp = client.post(&url).json(&streamed).send().await?;
+        assert_eq!(resp.headers()[reqwest::header::CONTENT_TYPE], "text/event-stream");
+        let mut stream = crate::llm::resume::Stream::default();
+        let text = resp.text().await?;
+        stream.feed(text.as_bytes());
+        assert_eq!((stream.text.as_str(), stream.finish_reason.as_deref(), stream.done), ("ok", Some("stop"), true));
+        assert!(text.contains("\"usage\":{"));
+
+        let resp = client.post(&url).json(&serde_json::json!({"messages": [], "n": 2})).send().await?;
+        assert_eq!(resp.status(), 400);
+        handle.shutdown().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_conversations_keep_history_for_thin_clients() -> anyhow::Result<()> {
+        let td = tempdir()?;
+        std::fs::write(td.path().join("tiny.gguf"), b"gguf")?;
+        let mgr = Arc::new(ModelManager { dir: td.path().to_path_buf() });
+        let server = ModelServer::new(mgr, "127.0.0.1:0".parse().unwrap());
+        server.register_mock_for_model("tiny").await?;
+        let handle = server.start_local_server().await?;
+        let client = reqwest::Client::new();
+        let base = format!("http://{}/v1", handle.addr);
+
+        let opening = serde_json::json!({"messages": [{"role": "system", "content": "be brief"}]});
+        let created: serde_json::Value = client.post(format!("{}/conversations", base)).json(&opening).send().await?.json().await?;
+        let id = created["id"].as_str().unwrap().to_string();
+        for text in ["one", "two"] {
+            let turn = serde_json::json!({"model": "tiny", "conversation": id, "messages": [{"role": "user", "content": text}]});
+            assert_eq!(client.post(format!("{}/chat/completions", base)).json(&turn).send().await?.status(), 200);
+        }
+        let shown: serde_json::Value = client.get(format!("{}/conversations/{}", base, id)).send().await?.json().await?;
+        let roles: Vec<&str> = shown["messages"].as_array().unwrap().iter().filter_map(|m| m["role"].as_str()).collect();
+        assert_eq!(roles, ["system", "user", "assistant", "user", "assistant"]);
+        assert_eq!(shown["messages"][4]["content"], "[mock:tiny.gguf] echo: two");
+
+        assert_eq!(client.delete(format!("{}/conversations/{}", base, id)).send().await?.status(), 200);
+        assert_eq!(client.get(format!("{}/conversations/{}", base, id)).send().await?.status(), 404);
+        let gone = serde_json::json!({"model": "tiny", "conversation": id, "messages": [{"role": "user", "content": "three"}]});
+        assert_eq!(client.post(format!("{}/chat/completions", base)).json(&gone).send().await?.status(), 400);
+        handle.shutdown().await;
+        Ok(())
+    }
+
+    /// The next text message as JSON, if one comes within 5s.
+    async fn next_json<S>(socket: &mut S) -> Option<serde_json::Value>
+    where
+        S: futures_util::Stream<Item = Result<tokio_tungstenite::tungstenite::Message, tokio_tungstenite::tungstenite::Error>> + Unpin,
+    {
+        use futures_util::StreamExt;
+        let frame = tokio::time::timeout(Duration::from_secs(5), socket.next()).await.ok()??.ok()?;
+        serde_json::from_str(frame.to_text().ok()?).ok()
+    }
+
+    #[tokio::test]
+    async fn test_events_socket_streams_providers_and_agent_events() -> anyhow::Result<()> {
+        use crate::agent::events::{self, AgentEvent};
+        let td = tempdir()?;
+        std::fs::write(td.path().join("tiny.gguf"), b"gguf")?;
+        let mgr = Arc::new(ModelManager { dir: td.path().to_path_buf() });
+        let server = ModelServer::new(mgr, "127.0.0.1:0".parse().unwrap());
+        server.register_mock_for_model("tiny").await?;
+        let handle = server.start_local_server().await?;
+        let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{}/v1/events", handle.addr)).await?;
+        let providers = next_json(&mut socket).await.unwrap();
+        assert_eq!(providers["type"], "providers");
+        assert_eq!(providers["providers"][0], serde_json::json!({"name": "tiny", "provider": "mock", "model": "tiny", "vision": true, "endpoint": null, "running": true}));
+        events::publish(AgentEvent::Action { agent: "planner".into(), action: "socket test".into() });
+        // other tests' runs share the bus
+        let mut seen = None;
+        while let Some(event) = next_json(&mut socket).await {
+            if event["action"] == "socket test" {
+                seen = Some(event);
+                break;
+            }
+        }
+        assert_eq!(seen, Some(serde_json::json!({"type": "action", "agent": "planner", "action": "socket test"})));
+        handle.shutdown().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_web_ui_and_task_list_are_served() -> anyhow::Result<()> {
+        let td = tempdir()?;
+        let tasks = TaskStore::new(td.path());
+        tasks.save(&crate::tasks::Task::new("tidy up", "tidy up the repo"))?;
+        let mgr = Arc::new(ModelManager { dir: td.path().to_path_buf() });
+        let server = ModelServer::new(mgr, "127.0.0.1:0".parse().unwrap()).with_tasks(tasks);
+        let handle = server.start_local_server().await?;
+        let client = reqwest::Client::new();
+        let base = format!("http://{}", handle.addr);
+
+        for page in ["/ui", "/ui/"] {
+            let resp = client.get(format!("{}{}", base, page)).send().await?;
+            assert!(resp.headers()[reqwest::header::CONTENT_TYPE].to_str()?.starts_with("text/html"));
+            assert!(resp.text().await?.contains("/ui/app.js"));
+        }
+        let script = client.get(format!("{}/ui/app.js", base)).send().await?;
+        assert!(script.headers()[reqwest::header::CONTENT_TYPE].to_str()?.starts_with("text/javascript"));
+        assert_eq!(client.get(format!("{}/ui/missing.js", base)).send().await?.status(), 404);
+
+        let listed: serde_json::Value = client.get(format!("{}/v1/tasks", base)).send().await?.json().await?;
+        assert_eq!((listed["tasks"][0]["title"].as_str(), listed["tasks"][0]["status"].as_str()), (Some("tidy up"), Some("Pending")));
+        let metrics = client.get(format!("{}/metrics", base)).send().await?.text().await?;
+        assert!(metrics.contains("super_agent_tasks{status=\"pending\"} 1"));
+        handle.shutdown().await;
+        Ok(())
+    }
+
+    #[test]
+    fn test_llava_projector_enables_vision() -> anyhow::Result<()> {
+        let td = tempdir()?;
+        let addr: std::net::SocketAddr = "127.0.0.1:11402".parse().unwrap();
+        for name in ["llava-7b.gguf", "mmproj-llava-7b-f16.gguf", "mistral.gguf", "mmproj-other.gguf"] {
+            std::fs::write(td.path().join(name), b"gguf")?;
+        }
+        let llava = LlamaProvider::new(None, td.path().join("llava-7b.gguf"), addr);
+        assert_eq!(llava.mmproj, Some(td.path().join("mmproj-llava-7b-f16.gguf")));
+        assert!(llava.supports_vision());
+        assert!(!LlamaProvider::new(None, td.path().join("mistral.gguf"), addr).supports_vision());
+        Ok(())
+    }
+
+    /// Remembers the request ID it answered under.
+    #[derive(Default)]
+    struct SeesId(std::sync::Mutex<Option<String>>);
+
+    #[async_trait]
+    impl Provider for SeesId {
+        fn name(&self) -> &'static str {
+            "sees-id"
+        }
+
+        fn model_id(&self) -> String {
+            "echo".into()
+        }
+
+        async fn chat(&self, _messages: &[Message]) -> Result<String, ProviderError> {
+            *self.0.lock().unwrap() = crate::request_id::current();
+            Ok("ok".into())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_request_ids_reach_the_provider_log_and_response() -> anyhow::Result<()> {
+        let td = tempdir()?;
+        let mgr = Arc::new(ModelManager { dir: td.path().to_path_buf() });
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let server = ModelServer::new(mgr, "127.0.0.1:0".parse().unwrap()).with_request_log(tx);
+        let provider = Arc::new(SeesId::default());
+        server.register_provider("echo", provider.clone()).await?;
+        let handle = server.start_local_server().await?;
+        let client = reqwest::Client::new();
+        let url = format!("http://{}/v1/chat/completions", handle.addr);
+        let body = serde_json::json!({"messages": [{"role": "user", "content": "hi"}]});
+
+        let resp = client.post(&url).header("X-Request-Id", "ui-42").json(&body).send().await?;
+        assert_eq!(resp.headers()[crate::request_id::HEADER], "ui-42");
+        assert_eq!(provider.0.lock().unwrap().as_deref(), Some("ui-42"));
+        let log = rx.recv().await.unwrap();
+        assert_eq!(log.id, "ui-42");
+        assert!(log.line().contains(" request=ui-42"));
+
+        // without one, or with one that could forge log lines, the server picks its own
+        let resp = client.post(&url).header("X-Request-Id", "a b").json(&body).send().await?;
+        let id = resp.headers()[crate::request_id::HEADER].to_str()?.to_string();
+        assert_eq!(id.len(), 12);
+        assert_eq!(provider.0.lock().unwrap().as_deref(), Some(id.as_str()));
+        assert_eq!(rx.recv().await.unwrap().id, id);
+        handle.shutdown().await;
+        Ok(())
+    }
 }
@@ -0,0 +1,81 @@
+//! Whether a chat model is available. With nothing answering on the chat
+//! endpoint there is no model, whatever the defaults say; `agent run`
+//! refuses to start and the TUI shows what to do instead of sending chats
+//! into the void.
+
+use crate::errors::ProviderError;
+use std::path::Path;
+use std::time::Duration;
+
+/// How long the endpoint gets to answer before it counts as down.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ModelSetup {
+    /// The chat endpoint answers.
+    Connected(String),
+    /// Models are in `model_dir` but nothing serves them.
+    NotServed(Vec<String>),
+    /// No endpoint answers and `model_dir` is empty.
+    Missing,
+}
+
+impl ModelSetup {
+    pub fn classify(endpoint: &str, answers: bool, local: Vec<String>) -> Self {
+        match (answers, local.is_empty()) {
+            (true, _) => ModelSetup::Connected(endpoint.to_string()),
+            (false, false) => ModelSetup::NotServed(local),
+            (false, true) => ModelSetup::Missing,
+        }
+    }
+
+    pub fn is_ready(&self) -> bool {
+        matches!(self, ModelSetup::Connected(_))
+    }
+
+    /// The error `agent run` fails with when there is no model.
+    pub fn error(&self) -> Option<ProviderError> {
+        match self {
+            ModelSetup::Connected(_) => None,
+            ModelSetup::NotServed(local) => Some(ProviderError::NoModel { local: local.clone() }),
+            ModelSetup::Missing => Some(ProviderError::NoModel { local: vec![] }),
+        }
+    }
+}
+
+/// Probe `endpoint` and list `model_dir`. Any HTTP answer counts: the
+/// server is up even when it does not know `/v1/models`.
+pub async fn detect(endpoint: &str, model_dir: &Path) -> ModelSetup {
+    let url = format!("{}/v1/models", endpoint.trim_end_matches('/'));
+    let answers = crate::net::client(crate::net::LLM).get(&url).timeout(PROBE_TIMEOUT).send().await.is_ok();
+    let mut local: Vec<String> = crate::models::ModelManager::new(Some(model_dir.to_path_buf()))
+        .and_then(|m| m.discover())
+        .map(|ms| ms.into_iter().map(|m| m.name).collect())
+        .unwrap_or_default();
+    local.sort();
+    ModelSetup::classify(endpoint, answers, local)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::ErrorReport;
+
+    #[tokio::test]
+    async fn test_no_model_without_an_endpoint_or_local_files() {
+        let td = tempfile::tempdir().unwrap();
+        // nothing listens on port 9 (discard) on test machines
+        let setup = detect("http://127.0.0.1:9", td.path()).await;
+        assert_eq!(setup, ModelSetup::Missing);
+        let report = ErrorReport::from_anyhow(&setup.error().unwrap().into());
+        assert_eq!(report.code, "E206");
+        assert!(report.hint.unwrap().contains("agent models import"));
+
+        std::fs::write(td.path().join("coder.gguf"), b"gguf").unwrap();
+        let setup = detect("http://127.0.0.1:9", td.path()).await;
+        assert_eq!(setup, ModelSetup::NotServed(vec!["coder".into()]));
+        assert!(ErrorReport::from_anyhow(&setup.error().unwrap().into()).hint.unwrap().contains("agent models serve start coder"));
+
+        assert!(ModelSetup::classify("http://x", true, vec![]).is_ready());
+    }
+}
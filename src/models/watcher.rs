@@ -0,0 +1,107 @@
+//! Notices models appearing in or leaving `model_dir`, e.g. after a
+//! download outside the app. The directory's mtime is polled, and when it
+//! moves the shared listing (see `ModelManager::listing`) is read again and
+//! compared with the last one. Files still being written under a temporary
+//! name (`.part`, `.tmp`, `.download`, `.crdownload`) and hidden files are
+//! not models yet; a download shows up once it is renamed into place.
+
+use crate::models::ModelManager;
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Time between looks at the directory.
+pub const POLL: Duration = Duration::from_secs(2);
+const PARTIAL: &[&str] = &["part", "tmp", "download", "crdownload"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    /// File name of a new model.
+    Added(String),
+    Removed(String),
+}
+
+impl Change {
+    pub fn describe(&self) -> String {
+        match self {
+            Change::Added(file) => format!("found new model {}", file),
+            Change::Removed(file) => format!("model {} was removed", file),
+        }
+    }
+}
+
+pub struct ModelDirWatcher {
+    dir: PathBuf,
+    modified: Option<SystemTime>,
+    files: BTreeSet<String>,
+    /// Whether `files` holds a first look yet.
+    primed: bool,
+    checked: Instant,
+}
+
+impl ModelDirWatcher {
+    /// Reads nothing yet, to stay off the startup path: the first look
+    /// records what `dir` holds, and only changes after it are reported.
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir, modified: None, files: BTreeSet::new(), primed: false, checked: Instant::now() }
+    }
+
+    /// Changes since the last look, at most every [`POLL`].
+    pub fn poll(&mut self) -> Vec<Change> {
+        if self.checked.elapsed() < POLL {
+            return vec![];
+        }
+        self.checked = Instant::now();
+        self.check()
+    }
+
+    /// Look now: one stat while the directory is unchanged.
+    pub fn check(&mut self) -> Vec<Change> {
+        let modified = std::fs::metadata(&self.dir).and_then(|m| m.modified()).ok();
+        if modified.is_some() && modified == self.modified {
+            return vec![];
+        }
+        self.modified = modified;
+        let first = !std::mem::replace(&mut self.primed, true);
+        let Ok(listing) = ModelManager::new(Some(self.dir.clone())).and_then(|m| m.rescan()) else { return vec![] };
+        let files: BTreeSet<String> = listing
+            .models
+            .iter()
+            .filter_map(|m| m.path.file_name()?.to_str().map(str::to_string))
+            .filter(|f| !f.starts_with('.') && !PARTIAL.iter().any(|ext| f.ends_with(&format!(".{}", ext))))
+            .collect();
+        if first {
+            self.files = files;
+            return vec![];
+        }
+        let mut changes: Vec<Change> = files.difference(&self.files).map(|f| Change::Added(f.clone())).collect();
+        changes.extend(self.files.difference(&files).map(|f| Change::Removed(f.clone())));
+        self.files = files;
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reports_models_once_they_are_in_place() -> anyhow::Result<()> {
+        let td = tempfile::tempdir()?;
+        std::fs::write(td.path().join("old.gguf"), b"old")?;
+        let mut watcher = ModelDirWatcher::new(td.path().to_path_buf());
+        assert!(watcher.check().is_empty());
+
+        std::fs::write(td.path().join("foo.gguf.part"), b"half")?;
+        assert!(watcher.check().is_empty());
+        std::fs::rename(td.path().join("foo.gguf.part"), td.path().join("foo.gguf"))?;
+        std::fs::remove_file(td.path().join("old.gguf"))?;
+        let changes = watcher.check();
+        assert_eq!(changes, vec![Change::Added("foo.gguf".into()), Change::Removed("old.gguf".into())]);
+        assert_eq!(changes[0].describe(), "found new model foo.gguf");
+        // the shared listing was refreshed with it
+        let names: Vec<String> = ModelManager::new(Some(td.path().to_path_buf()))?.discover()?.into_iter().map(|m| m.name).collect();
+        assert_eq!(names, ["foo"]);
+        Ok(())
+    }
+}
@@ -0,0 +1,35 @@
+//! The browser view the model server serves under [`ROUTE`]: chat, tasks
+//! and model status for a headless instance, built only on the server's
+//! public API (`/v1/chat/completions`, `/v1/conversations`, `/v1/tasks`,
+//! `/v1/models` and `/v1/events`). The page and its assets live in `web/ui/`
+//! and are compiled into the binary.
+
+use std::borrow::Cow;
+
+pub const ROUTE: &str = "/ui";
+
+#[derive(rust_embed::RustEmbed)]
+#[folder = "web/ui/"]
+struct Assets;
+
+/// The asset at `path` under [`ROUTE`] and its content type; the page for
+/// an empty path.
+pub fn asset(path: &str) -> Option<(&'static str, Cow<'static, [u8]>)> {
+    let path = match path.trim_start_matches('/') {
+        "" => "index.html",
+        path => path,
+    };
+    let file = Assets::get(path)?;
+    Some((content_type(path), file.data))
+}
+
+fn content_type(path: &str) -> &'static str {
+    match path.rsplit_once('.').map(|(_, ext)| ext) {
+        Some("html") => "text/html; charset=utf-8",
+        Some("js") => "text/javascript; charset=utf-8",
+        Some("css") => "text/css; charset=utf-8",
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        _ => "application/octet-stream",
+    }
+}
@@ -0,0 +1,142 @@
+//! Speech-to-text for the chat input: a recorder subprocess writes a WAV
+//! file, then the whisper.cpp CLI transcribes it locally.
+
+use crate::errors::{ConfigError, ProviderError};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::{Child, Command};
+
+/// `[models.whisper]` in config.toml. Voice input is off until `model` is set.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WhisperConfig {
+    /// ggml whisper model, e.g. `ggml-base.en.bin`.
+    pub model: Option<PathBuf>,
+    /// whisper.cpp CLI (`whisper-cli`, or `main` in older builds).
+    pub binary: String,
+    /// Spoken language, or `auto`.
+    pub language: String,
+    /// Records 16 kHz mono WAV until interrupted; the output path is appended.
+    pub recorder: Vec<String>,
+}
+
+impl Default for WhisperConfig {
+    fn default() -> Self {
+        Self {
+            model: None,
+            binary: "whisper-cli".into(),
+            language: "auto".into(),
+            recorder: ["arecord", "-q", "-f", "S16_LE", "-r", "16000", "-c", "1"].map(String::from).to_vec(),
+        }
+    }
+}
+
+impl WhisperConfig {
+    fn model(&self) -> anyhow::Result<&Path> {
+        let model = self.model.as_deref().ok_or(ConfigError::Missing { key: "models.whisper.model", purpose: "to use voice input" })?;
+        if !model.exists() {
+            return Err(ProviderError::ModelNotFound(model.display().to_string()).into());
+        }
+        Ok(model)
+    }
+
+    /// Start recording from the microphone.
+    pub fn record(&self) -> anyhow::Result<Recording> {
+        self.model()?;
+        let (program, args) = self.recorder.split_first().ok_or_else(|| ConfigError::Invalid { key: "models.whisper.recorder", reason: "empty".into() })?;
+        let path = std::env::temp_dir().join(format!("supercode-voice-{}.wav", crate::types::new_id()));
+        let child = Command::new(program)
+            .args(args)
+            .arg(&path)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| ProviderError::ProgramFailed { program: program.clone(), reason: e.to_string() })?;
+        Ok(Recording { child, path, config: self.clone() })
+    }
+
+    /// Transcribe a 16 kHz WAV file.
+    pub async fn transcribe(&self, wav: &Path) -> anyhow::Result<String> {
+        let out = Command::new(&self.binary)
+            .arg("-m")
+            .arg(self.model()?)
+            .arg("-f")
+            .arg(wav)
+            .args(["-nt", "-np", "-l", &self.language])
+            .stdin(Stdio::null())
+            .output()
+            .await
+            .map_err(|e| ProviderError::ProgramFailed { program: self.binary.clone(), reason: e.to_string() })?;
+        if !out.status.success() {
+            return Err(ProviderError::ProgramFailed { program: self.binary.clone(), reason: String::from_utf8_lossy(&out.stderr).trim().to_string() }.into());
+        }
+        let text = String::from_utf8_lossy(&out.stdout);
+        Ok(text.split_whitespace().collect::<Vec<_>>().join(" "))
+    }
+}
+
+/// A recorder running in the background.
+pub struct Recording {
+    child: Child,
+    path: PathBuf,
+    config: WhisperConfig,
+}
+
+impl Recording {
+    /// Stop recording and transcribe what was said.
+    pub async fn finish(mut self) -> anyhow::Result<String> {
+        self.stop().await;
+        let text = self.config.transcribe(&self.path).await;
+        let _ = std::fs::remove_file(&self.path);
+        text
+    }
+
+    /// Interrupt the recorder so it finalises the WAV header; kill it if
+    /// it does not exit promptly.
+    async fn stop(&mut self) {
+        #[cfg(unix)]
+        if let Some(pid) = self.child.id() {
+            let _ = Command::new("kill").args(["-INT", &pid.to_string()]).status().await;
+        }
+        if tokio::time::timeout(Duration::from_secs(2), self.child.wait()).await.is_err() {
+            let _ = self.child.kill().await;
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[tokio::test]
+    async fn test_record_and_transcribe_with_stub_tools() -> anyhow::Result<()> {
+        let td = tempfile::tempdir()?;
+        let model = td.path().join("ggml-tiny.bin");
+        std::fs::write(&model, b"model")?;
+        // prints its arguments so the test can check the invocation
+        let whisper = td.path().join("whisper-cli");
+        std::fs::write(&whisper, "#!/bin/sh\ntest -s \"$4\" || exit 1\necho \"  $1 $5\"\necho ' hello   world '\n")?;
+        std::fs::set_permissions(&whisper, std::fs::Permissions::from_mode(0o755))?;
+
+        let config = WhisperConfig {
+            model: Some(model),
+            binary: whisper.to_string_lossy().into_owned(),
+            language: "en".into(),
+            recorder: vec!["sh".into(), "-c".into(), "echo RIFF > \"$0\"; exec sleep 30".into()],
+        };
+        let recording = config.record()?;
+        let path = recording.path.clone();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(recording.finish().await?, "-m -nt hello world");
+        assert!(!path.exists(), "the recording is removed after transcription");
+
+        let unset = WhisperConfig::default();
+        assert!(unset.record().is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,238 @@
+//! Where everything this agent keeps lives, per user.
+//!
+//! - config: `$XDG_CONFIG_HOME/super-agent` (`~/.config`, `~/Library/Application
+//!   Support`, `%APPDATA%`): config.toml.
+//! - data: `$XDG_DATA_HOME/super-agent` (`~/.local/share`, `~/Library/Application
+//!   Support`, `%APPDATA%`): the state home (tasks, sessions, memory, skills,
+//!   secrets, …) and pane layouts. `$SUPERCODE_HOME` moves the state home.
+//! - models: `models/` under data; on Windows under `%LOCALAPPDATA%` instead,
+//!   so multi-gigabyte models don't roam with the profile. Models already
+//!   under `%APPDATA%` stay there: configs may name that directory.
+//! - cache: `$XDG_CACHE_HOME/super-agent` (`~/.cache`, `~/Library/Caches`,
+//!   `%LOCALAPPDATA%`): command palette and quick-open rankings.
+//!
+//! The `XDG_*` variables are honoured on every platform when they hold an
+//! absolute path. Without any home directory the fallback is a per-user
+//! directory under the system temp dir, never the current directory.
+//! State from before this layout, in `~/.supercode`, is moved over once by
+//! [`migrate`]. `agent paths` prints what all of this resolves to.
+
+use std::path::{Path, PathBuf};
+
+const APP: &str = "super-agent";
+/// The state home before the XDG layout.
+const LEGACY: &str = ".supercode";
+/// Files of the data dir that belong in the cache now.
+const CACHED: &[&str] = &["palette_usage.json", "quick_open_usage.json"];
+
+/// `$var` when it is an absolute path, else the platform's directory.
+fn base(var: &str, platform: Option<PathBuf>) -> PathBuf {
+    std::env::var_os(var)
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .or(platform)
+        .unwrap_or_else(|| std::env::temp_dir().join(format!("{}-{}", APP, whoami())))
+        .join(APP)
+}
+
+fn whoami() -> String {
+    std::env::var("USER").or_else(|_| std::env::var("USERNAME")).unwrap_or_else(|_| "user".into())
+}
+
+pub fn config_dir() -> PathBuf {
+    base("XDG_CONFIG_HOME", dirs::config_dir())
+}
+
+pub fn config_file() -> PathBuf {
+    config_dir().join("config.toml")
+}
+
+pub fn data_dir() -> PathBuf {
+    base("XDG_DATA_HOME", dirs::data_dir())
+}
+
+pub fn cache_dir() -> PathBuf {
+    base("XDG_CACHE_HOME", dirs::cache_dir())
+}
+
+/// Root of per-user state: `$SUPERCODE_HOME`, else the data dir.
+pub fn home() -> PathBuf {
+    match std::env::var_os("SUPERCODE_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => data_dir(),
+    }
+}
+
+/// Default `model_dir`.
+pub fn models_dir() -> PathBuf {
+    let roaming = data_dir().join("models");
+    if !cfg!(windows) {
+        return roaming;
+    }
+    keep_existing(roaming, base("XDG_DATA_HOME", dirs::data_local_dir()).join("models"))
+}
+
+/// `old` while it holds anything and `new` does not yet, else `new`.
+fn keep_existing(old: PathBuf, new: PathBuf) -> PathBuf {
+    let has_entries = |dir: &Path| std::fs::read_dir(dir).is_ok_and(|mut entries| entries.next().is_some());
+    if has_entries(&old) && !has_entries(&new) {
+        old
+    } else {
+        new
+    }
+}
+
+/// `~/.supercode`, where state lived before.
+pub fn legacy_home() -> Option<PathBuf> {
+    dirs::home_dir().map(|d| d.join(LEGACY))
+}
+
+/// Create `dir` readable by its owner only.
+pub fn ensure_private(dir: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o700))?;
+    }
+    Ok(())
+}
+
+fn copy_tree(from: &Path, to: &Path) -> std::io::Result<()> {
+    if from.is_dir() {
+        std::fs::create_dir_all(to)?;
+        for entry in std::fs::read_dir(from)? {
+            let entry = entry?;
+            copy_tree(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        Ok(())
+    } else {
+        std::fs::copy(from, to).map(|_| ())
+    }
+}
+
+/// Move `from` to `to`, copying when they are on different filesystems.
+fn relocate(from: &Path, to: &Path) -> std::io::Result<()> {
+    if std::fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    copy_tree(from, to)?;
+    if from.is_dir() {
+        std::fs::remove_dir_all(from)
+    } else {
+        std::fs::remove_file(from)
+    }
+}
+
+/// Move what is in `legacy` into `home`, entry by entry, then remove
+/// `legacy` if nothing is left. An entry `home` already has stays where it
+/// is; `run/` only describes processes of the old layout and is dropped.
+/// Returns a line per entry moved or left behind.
+pub fn migrate_from(legacy: &Path, home: &Path) -> anyhow::Result<Vec<String>> {
+    let mut notes = vec![];
+    let Ok(entries) = std::fs::read_dir(legacy) else { return Ok(notes) };
+    ensure_private(home)?;
+    let mut entries: Vec<PathBuf> = entries.flatten().map(|e| e.path()).collect();
+    entries.sort();
+    for from in entries {
+        let Some(name) = from.file_name() else { continue };
+        let to = home.join(name);
+        if name == "run" {
+            let _ = std::fs::remove_dir_all(&from);
+        } else if to.exists() {
+            notes.push(format!("kept {}: {} already exists", from.display(), to.display()));
+        } else {
+            relocate(&from, &to).map_err(|e| anyhow::anyhow!("cannot move {} to {}: {}", from.display(), to.display(), e))?;
+            notes.push(format!("moved {} to {}", from.display(), to.display()));
+        }
+    }
+    if std::fs::read_dir(legacy).is_ok_and(|mut rest| rest.next().is_none()) {
+        std::fs::remove_dir(legacy)?;
+    }
+    Ok(notes)
+}
+
+/// Bring state from the old layout into this one: `~/.supercode` into the
+/// state home (unless `$SUPERCODE_HOME` chooses one), and the rankings from
+/// the data dir into the cache. Cheap when there is nothing to move.
+pub fn migrate() -> anyhow::Result<Vec<String>> {
+    let mut notes = vec![];
+    if std::env::var_os("SUPERCODE_HOME").is_none() {
+        if let Some(legacy) = legacy_home().filter(|l| l.is_dir() && *l != home()) {
+            notes.extend(migrate_from(&legacy, &home())?);
+        }
+    }
+    for file in CACHED {
+        let (from, to) = (data_dir().join(file), cache_dir().join(file));
+        if from.is_file() && !to.exists() {
+            std::fs::create_dir_all(cache_dir())?;
+            relocate(&from, &to)?;
+        }
+    }
+    Ok(notes)
+}
+
+/// `agent paths`: every location, and whether the old one is still around.
+pub fn describe() -> String {
+    let mut rows = vec![
+        ("config", config_file()),
+        ("data", data_dir()),
+        ("state", home()),
+        ("models", models_dir()),
+        ("cache", cache_dir()),
+    ];
+    if let Some(legacy) = legacy_home().filter(|l| l.exists()) {
+        rows.push(("legacy", legacy));
+    }
+    let mut out: String = rows.iter().map(|(name, path)| format!("{:<8}{}\n", name, path.display())).collect();
+    if std::env::var_os("SUPERCODE_HOME").is_some() {
+        out.push_str("(state is set by $SUPERCODE_HOME)\n");
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_legacy_state_moves_without_clobbering() -> anyhow::Result<()> {
+        let td = tempfile::tempdir()?;
+        let (legacy, home) = (td.path().join(".supercode"), td.path().join("data/super-agent"));
+        for (path, text) in [("tasks/a.json", "a"), ("sessions/s.json", "old"), ("stats.jsonl", "{}"), ("run/model_server.json", "{}")] {
+            std::fs::create_dir_all(legacy.join(path).parent().unwrap())?;
+            std::fs::write(legacy.join(path), text)?;
+        }
+        std::fs::create_dir_all(home.join("sessions"))?;
+        std::fs::write(home.join("sessions/s.json"), "new")?;
+
+        let notes = migrate_from(&legacy, &home)?;
+        assert_eq!(notes.len(), 3);
+        assert!(notes[0].starts_with("kept") && notes[0].contains("sessions"));
+        assert_eq!(std::fs::read_to_string(home.join("tasks/a.json"))?, "a");
+        assert_eq!(std::fs::read_to_string(home.join("sessions/s.json"))?, "new");
+        assert!(!home.join("run").exists() && !legacy.join("run").exists());
+        // what was kept back leaves the old home in place
+        assert!(legacy.join("sessions/s.json").exists());
+
+        std::fs::remove_dir_all(legacy.join("sessions"))?;
+        assert!(migrate_from(&legacy, &home)?.is_empty());
+        assert!(!legacy.exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_models_stay_in_the_old_directory_until_the_new_one_is_used() -> anyhow::Result<()> {
+        let td = tempfile::tempdir()?;
+        let (old, new) = (td.path().join("Roaming/models"), td.path().join("Local/models"));
+        assert_eq!(keep_existing(old.clone(), new.clone()), new);
+        std::fs::create_dir_all(&old)?;
+        assert_eq!(keep_existing(old.clone(), new.clone()), new, "an empty old directory is not kept");
+        std::fs::write(old.join("coder.gguf"), "")?;
+        assert_eq!(keep_existing(old.clone(), new.clone()), old);
+        std::fs::create_dir_all(&new)?;
+        std::fs::write(new.join("chat.gguf"), "")?;
+        assert_eq!(keep_existing(old, new.clone()), new);
+        Ok(())
+    }
+}
@@ -0,0 +1,76 @@
+//! Request IDs: one short ID per operation (a chat turn, a run, a request
+//! to the model server) that follows it through LLM calls, tool calls, log
+//! lines and the error it ends in, so `grep <id>` over the logs, the audit
+//! log and the server's request log finds exactly that operation.
+//!
+//! The ID lives in a task-local set by [`scope`]; anything running inside
+//! reads it with [`current`]. Tasks spawned from inside start without one;
+//! blocking work carries it over with [`enter`].
+
+use std::future::Future;
+use tracing::Instrument;
+
+/// Carries the ID over HTTP, both to the model server and back from it.
+pub const HEADER: &str = "x-request-id";
+
+/// Longest ID accepted from a client.
+const MAX_LEN: usize = 64;
+
+tokio::task_local! {
+    static CURRENT: String;
+}
+
+pub fn new_id() -> String {
+    uuid::Uuid::new_v4().simple().to_string()[..12].to_string()
+}
+
+/// The ID of the operation running now, if any.
+pub fn current() -> Option<String> {
+    CURRENT.try_with(Clone::clone).ok()
+}
+
+/// Run `fut` as the operation `id`, inside a `request` span.
+pub async fn scope<F: Future>(id: String, fut: F) -> F::Output {
+    let span = tracing::info_span!("request", id = %id);
+    CURRENT.scope(id, fut.instrument(span)).await
+}
+
+/// Run `f` as the operation `id`, for work handed to a blocking thread.
+pub fn enter<R>(id: Option<String>, f: impl FnOnce() -> R) -> R {
+    match id {
+        Some(id) => CURRENT.sync_scope(id, f),
+        None => f(),
+    }
+}
+
+/// An ID a client sent, if it is one worth keeping: short, and only
+/// letters, digits, `-`, `_` and `.` so it cannot forge log lines.
+pub fn accept(id: &str) -> Option<String> {
+    let ok = !id.is_empty() && id.len() <= MAX_LEN && id.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    ok.then(|| id.to_string())
+}
+
+/// ` (request <id>)` inside a scope, empty outside one.
+pub fn suffix() -> String {
+    current().map(|id| format!(" (request {})", id)).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_ids_are_scoped_to_the_operation() {
+        assert_eq!(current(), None);
+        let id = new_id();
+        assert_eq!(id.len(), 12);
+        let seen = scope(id.clone(), async { (current(), suffix()) }).await;
+        assert_eq!(seen, (Some(id.clone()), format!(" (request {})", id)));
+        assert_eq!(current(), None);
+        assert_eq!(enter(Some(id.clone()), current), Some(id));
+
+        assert_eq!(accept("abc-123_x.y").as_deref(), Some("abc-123_x.y"));
+        assert_eq!(accept("a\nb"), None);
+        assert_eq!(accept(&"a".repeat(65)), None);
+    }
+}
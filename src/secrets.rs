@@ -0,0 +1,425 @@
+//! API keys and other secrets, kept out of config.toml. Values go to the OS
+//! keyring (the Keychain on macOS, the Credential Manager on Windows,
+//! `secret-tool` on Linux) and otherwise to `<home>/secrets.json`, sealed
+//! with XChaCha20-Poly1305 under a key from `SUPERCODE_SECRETS_KEY` or a
+//! generated `secrets.key` readable only by the owner.
+//!
+//! [`Secret`] never prints its value, so a client holding one can be logged
+//! or debug-printed safely. Lookups may spawn a keyring tool; async code and
+//! the TUI go through [`lookup`] and [`peek`], which never block on it.
+
+use anyhow::{anyhow, bail, Context};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use std::path::PathBuf;
+#[cfg(not(any(target_os = "macos", windows)))]
+use std::process::{Command, Stdio};
+use std::sync::{Mutex, OnceLock, RwLock};
+
+/// Secret holding the key for `llm_endpoint`, sent as a bearer token to that
+/// endpoint only (see [`scope_llm_key`]).
+pub const LLM_API_KEY: &str = "llm";
+
+/// Service name under which keyring entries are stored.
+const SERVICE: &str = "super-agent";
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Debug for Secret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Secret({})", mask(&self.0))
+    }
+}
+
+impl std::fmt::Display for Secret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&mask(&self.0))
+    }
+}
+
+/// `••••` plus the last four characters, enough to tell keys apart; short
+/// values are hidden entirely.
+pub fn mask(value: &str) -> String {
+    let chars: Vec<char> = value.chars().collect();
+    if chars.len() < 12 {
+        return "••••••••".into();
+    }
+    format!("••••{}", chars[chars.len() - 4..].iter().collect::<String>())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    Keyring,
+    File,
+}
+
+impl std::fmt::Display for Backend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Backend::Keyring => "keyring",
+            Backend::File => "encrypted file",
+        })
+    }
+}
+
+pub struct SecretStore {
+    /// Keyring tool, when the platform has one.
+    keyring: Option<Keyring>,
+    file: EncryptedFile,
+}
+
+impl SecretStore {
+    /// The keyring when available, with the encrypted file under `home`.
+    pub fn open(home: PathBuf) -> Self {
+        Self { keyring: Keyring::detect(), file: EncryptedFile { home } }
+    }
+
+    /// File storage only.
+    pub fn file(home: PathBuf) -> Self {
+        Self { keyring: None, file: EncryptedFile { home } }
+    }
+
+    /// Store `value`; the keyring is tried first and the file is used when
+    /// it fails (no keyring daemon on a headless box, for instance).
+    pub fn set(&self, name: &str, value: &str) -> anyhow::Result<Backend> {
+        invalidate(name);
+        if let Some(keyring) = &self.keyring {
+            match keyring.set(name, value) {
+                Ok(()) => {
+                    // don't leave an older copy behind in the file
+                    self.file.remove(name)?;
+                    return Ok(Backend::Keyring);
+                }
+                Err(e) => log::warn!("keyring unavailable, storing {} in the encrypted file: {}", name, e),
+            }
+        }
+        self.file.set(name, value)?;
+        Ok(Backend::File)
+    }
+
+    pub fn get(&self, name: &str) -> anyhow::Result<Option<(Secret, Backend)>> {
+        if let Some(value) = self.keyring.as_ref().and_then(|k| k.get(name)) {
+            return Ok(Some((Secret(value), Backend::Keyring)));
+        }
+        Ok(self.file.get(name)?.map(|v| (Secret(v), Backend::File)))
+    }
+
+    /// Remove `name` everywhere; false when it was not stored.
+    pub fn remove(&self, name: &str) -> anyhow::Result<bool> {
+        invalidate(name);
+        let in_keyring = self.keyring.as_ref().is_some_and(|k| k.remove(name));
+        Ok(self.file.remove(name)? || in_keyring)
+    }
+}
+
+fn cache() -> &'static Mutex<HashMap<String, Option<Secret>>> {
+    static CACHE: OnceLock<Mutex<HashMap<String, Option<Secret>>>> = OnceLock::new();
+    CACHE.get_or_init(Default::default)
+}
+
+fn invalidate(name: &str) {
+    cache().lock().unwrap_or_else(|e| e.into_inner()).remove(name);
+}
+
+/// `name` from the default store, looked up once per process, so keyring
+/// tools aren't spawned per request. Blocks on the first lookup.
+pub fn cached(name: &str) -> Option<Secret> {
+    let mut cache = cache().lock().unwrap_or_else(|e| e.into_inner());
+    cache
+        .entry(name.to_string())
+        .or_insert_with(|| match SecretStore::open(crate::paths::home()).get(name) {
+            Ok(found) => found.map(|(s, _)| s),
+            Err(e) => {
+                log::warn!("could not read secret {}: {}", name, e);
+                None
+            }
+        })
+        .clone()
+}
+
+/// `name` as [`cached`] has it, without blocking: `None` until a lookup has
+/// finished. The TUI renders from this and starts [`lookup`] in the
+/// background.
+pub fn peek(name: &str) -> Option<Option<Secret>> {
+    cache().lock().unwrap_or_else(|e| e.into_inner()).get(name).cloned()
+}
+
+/// [`cached`] for async code: a first lookup runs on the blocking pool.
+pub async fn lookup(name: &str) -> Option<Secret> {
+    if let Some(found) = peek(name) {
+        return found;
+    }
+    let name = name.to_string();
+    tokio::task::spawn_blocking(move || cached(&name)).await.ok().flatten()
+}
+
+fn llm_endpoint() -> &'static RwLock<Option<String>> {
+    static ENDPOINT: OnceLock<RwLock<Option<String>>> = OnceLock::new();
+    ENDPOINT.get_or_init(Default::default)
+}
+
+/// Send the `llm` key only to `endpoint` (`llm_endpoint` in config.toml);
+/// called at startup and whenever it changes.
+pub fn scope_llm_key(endpoint: &str) {
+    *llm_endpoint().write().unwrap_or_else(|e| e.into_inner()) = origin(endpoint);
+}
+
+/// Whether a client for `endpoint` may send the `llm` key: it must be the
+/// same scheme, host and port as the scoped endpoint.
+pub fn llm_key_allowed(endpoint: &str) -> bool {
+    let scoped = llm_endpoint().read().unwrap_or_else(|e| e.into_inner());
+    scoped.is_some() && *scoped == origin(endpoint)
+}
+
+fn origin(url: &str) -> Option<String> {
+    let url = reqwest::Url::parse(url).ok()?;
+    Some(url.origin().ascii_serialization()).filter(|o| o != "null")
+}
+
+/// The Keychain on macOS and the Credential Manager on Windows, through the
+/// `keyring` crate; nothing is passed on a command line.
+#[cfg(any(target_os = "macos", windows))]
+struct Keyring;
+
+#[cfg(any(target_os = "macos", windows))]
+impl Keyring {
+    fn detect() -> Option<Self> {
+        Some(Self)
+    }
+
+    fn entry(name: &str) -> keyring::Result<keyring::Entry> {
+        keyring::Entry::new(SERVICE, name)
+    }
+
+    fn set(&self, name: &str, value: &str) -> anyhow::Result<()> {
+        Ok(Self::entry(name)?.set_password(value)?)
+    }
+
+    fn get(&self, name: &str) -> Option<String> {
+        Self::entry(name).ok()?.get_password().ok().filter(|v| !v.is_empty())
+    }
+
+    fn remove(&self, name: &str) -> bool {
+        Self::entry(name).and_then(|e| e.delete_credential()).is_ok()
+    }
+}
+
+/// libsecret's `secret-tool`, which reads the value from stdin so it stays
+/// out of the process list.
+#[cfg(not(any(target_os = "macos", windows)))]
+struct Keyring {
+    tool: PathBuf,
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+impl Keyring {
+    fn detect() -> Option<Self> {
+        if !cfg!(unix) {
+            return None;
+        }
+        which::which("secret-tool").ok().map(|tool| Self { tool })
+    }
+
+    fn set(&self, name: &str, value: &str) -> anyhow::Result<()> {
+        let mut child = Command::new(&self.tool)
+            .args(["store", "--label", &format!("{} {}", SERVICE, name), "service", SERVICE, "account", name])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        child.stdin.take().context("keyring stdin")?.write_all(value.as_bytes())?;
+        let out = child.wait_with_output()?;
+        if !out.status.success() {
+            bail!("{}", String::from_utf8_lossy(&out.stderr).trim());
+        }
+        Ok(())
+    }
+
+    fn get(&self, name: &str) -> Option<String> {
+        let out = Command::new(&self.tool).args(["lookup", "service", SERVICE, "account", name]).stderr(Stdio::null()).output().ok()?;
+        let value = String::from_utf8(out.stdout).ok()?.trim_end_matches('\n').to_string();
+        (out.status.success() && !value.is_empty()).then_some(value)
+    }
+
+    fn remove(&self, name: &str) -> bool {
+        // secret-tool clear succeeds whether or not the entry existed
+        let existed = self.get(name).is_some();
+        Command::new(&self.tool)
+            .args(["clear", "service", SERVICE, "account", name])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|s| s.success())
+            && existed
+    }
+}
+
+/// `secrets.json`: name → base64(nonce ‖ ciphertext ‖ tag), sealed with
+/// XChaCha20-Poly1305 and the entry's name as associated data, so values
+/// can't be swapped between names.
+struct EncryptedFile {
+    home: PathBuf,
+}
+
+const NONCE_LEN: usize = 24;
+
+impl EncryptedFile {
+    fn path(&self) -> PathBuf {
+        self.home.join("secrets.json")
+    }
+
+    fn load(&self) -> anyhow::Result<BTreeMap<String, String>> {
+        match std::fs::read_to_string(self.path()) {
+            Ok(text) => Ok(serde_json::from_str(&text).with_context(|| format!("reading {}", self.path().display()))?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn save(&self, entries: &BTreeMap<String, String>) -> anyhow::Result<()> {
+        std::fs::create_dir_all(&self.home)?;
+        write_private(&self.path(), serde_json::to_string_pretty(entries)?.as_bytes())
+    }
+
+    /// The master key; generated on first use unless given in the environment.
+    fn key(&self, create: bool) -> anyhow::Result<Option<[u8; 32]>> {
+        if let Ok(pass) = std::env::var("SUPERCODE_SECRETS_KEY") {
+            return Ok(Some(Sha256::digest(pass.as_bytes()).into()));
+        }
+        let path = self.home.join("secrets.key");
+        match std::fs::read_to_string(&path) {
+            Ok(text) => {
+                let bytes = hex::decode(text.trim()).context("secrets.key is not hex")?;
+                Ok(Some(bytes.try_into().map_err(|_| anyhow!("secrets.key must hold 32 bytes"))?))
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound && create => {
+                let mut key = [0u8; 32];
+                getrandom::getrandom(&mut key).map_err(|e| anyhow!("no randomness: {}", e))?;
+                std::fs::create_dir_all(&self.home)?;
+                write_private(&path, hex::encode(key).as_bytes())?;
+                Ok(Some(key))
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn set(&self, name: &str, value: &str) -> anyhow::Result<()> {
+        let key = self.key(true)?.context("no secrets key")?;
+        let mut entries = self.load()?;
+        entries.insert(name.to_string(), BASE64.encode(seal(&key, name, value.as_bytes())?));
+        self.save(&entries)
+    }
+
+    fn get(&self, name: &str) -> anyhow::Result<Option<String>> {
+        let entries = self.load()?;
+        let Some(sealed) = entries.get(name) else { return Ok(None) };
+        let key = self.key(false)?.context("secrets.json exists but secrets.key is missing")?;
+        let plain = open(&key, name, &BASE64.decode(sealed)?).with_context(|| format!("secret {}", name))?;
+        Ok(Some(String::from_utf8(plain)?))
+    }
+
+    fn remove(&self, name: &str) -> anyhow::Result<bool> {
+        let mut entries = self.load()?;
+        if entries.remove(name).is_none() {
+            return Ok(false);
+        }
+        self.save(&entries)?;
+        Ok(true)
+    }
+}
+
+fn write_private(path: &std::path::Path, bytes: &[u8]) -> anyhow::Result<()> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(path)?.write_all(bytes)?;
+    Ok(())
+}
+
+fn seal(key: &[u8; 32], name: &str, plain: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let mut nonce = [0u8; NONCE_LEN];
+    getrandom::getrandom(&mut nonce).map_err(|e| anyhow!("no randomness: {}", e))?;
+    let sealed = XChaCha20Poly1305::new(key.into())
+        .encrypt(XNonce::from_slice(&nonce), Payload { msg: plain, aad: name.as_bytes() })
+        .map_err(|_| anyhow!("could not encrypt"))?;
+    Ok([nonce.as_slice(), &sealed].concat())
+}
+
+fn open(key: &[u8; 32], name: &str, sealed: &[u8]) -> anyhow::Result<Vec<u8>> {
+    if sealed.len() < NONCE_LEN {
+        bail!("sealed value too short");
+    }
+    let (nonce, body) = sealed.split_at(NONCE_LEN);
+    XChaCha20Poly1305::new(key.into())
+        .decrypt(XNonce::from_slice(nonce), Payload { msg: body, aad: name.as_bytes() })
+        .map_err(|_| anyhow!("wrong key or corrupted value"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_store_round_trip_and_masking() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = SecretStore::file(dir.path().to_path_buf());
+        assert_eq!(store.set("llm", "sk-test-0123456789abcd").unwrap(), Backend::File);
+        let (secret, backend) = store.get("llm").unwrap().unwrap();
+        assert_eq!((secret.expose(), backend), ("sk-test-0123456789abcd", Backend::File));
+        assert_eq!(format!("{:?}", secret), "Secret(••••abcd)");
+        assert_eq!(mask("short"), "••••••••");
+
+        let on_disk = std::fs::read_to_string(dir.path().join("secrets.json")).unwrap();
+        assert!(!on_disk.contains("sk-test"), "{}", on_disk);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(dir.path().join("secrets.key")).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        // a tampered value is rejected, not decrypted to garbage
+        let mut entries: BTreeMap<String, String> = serde_json::from_str(&on_disk).unwrap();
+        let mut raw = BASE64.decode(&entries["llm"]).unwrap();
+        raw[NONCE_LEN] ^= 1;
+        entries.insert("llm".into(), BASE64.encode(raw));
+        std::fs::write(dir.path().join("secrets.json"), serde_json::to_string(&entries).unwrap()).unwrap();
+        assert!(store.get("llm").is_err());
+
+        // nor is a value moved under another name
+        store.set("other", "sk-other-0123456789abcd").unwrap();
+        let mut entries: BTreeMap<String, String> = serde_json::from_str(&std::fs::read_to_string(dir.path().join("secrets.json")).unwrap()).unwrap();
+        entries.insert("llm".into(), entries["other"].clone());
+        std::fs::write(dir.path().join("secrets.json"), serde_json::to_string(&entries).unwrap()).unwrap();
+        assert!(store.get("llm").is_err());
+
+        assert!(store.remove("llm").unwrap());
+        assert!(!store.remove("llm").unwrap());
+        assert!(store.get("llm").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_llm_key_is_scoped_to_its_endpoint() {
+        scope_llm_key("https://llm.example.com/");
+        assert!(llm_key_allowed("https://llm.example.com"));
+        assert!(llm_key_allowed("https://LLM.example.com:443/v1"));
+        assert!(!llm_key_allowed("http://llm.example.com"));
+        assert!(!llm_key_allowed("https://llm.example.com:8443"));
+        assert!(!llm_key_allowed("http://127.0.0.1:8080"));
+        assert!(!llm_key_allowed("not a url"));
+    }
+}
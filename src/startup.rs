@@ -0,0 +1,108 @@
+//! `--profile-startup`: where the time goes between launch and the TUI's
+//! first frame. Each startup step runs under a [`span`]; with the flag set
+//! the spans are printed to stderr when the process exits (the TUI would
+//! draw over them any earlier). Without it spans cost one atomic load.
+
+use std::fmt::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// One timed step, relative to launch.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Step {
+    pub name: &'static str,
+    pub start: Duration,
+    pub took: Duration,
+}
+
+fn launched() -> Instant {
+    static LAUNCHED: OnceLock<Instant> = OnceLock::new();
+    *LAUNCHED.get_or_init(Instant::now)
+}
+
+fn steps() -> &'static Mutex<Vec<Step>> {
+    static STEPS: OnceLock<Mutex<Vec<Step>>> = OnceLock::new();
+    STEPS.get_or_init(Default::default)
+}
+
+/// Start the clock; call first thing in `main`.
+pub fn begin(enabled: bool) {
+    launched();
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Times a step until dropped.
+pub struct Span {
+    name: &'static str,
+    started: Instant,
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        if enabled() {
+            record(Step { name: self.name, start: self.started - launched(), took: self.started.elapsed() });
+        }
+    }
+}
+
+pub fn span(name: &'static str) -> Span {
+    Span { name, started: Instant::now() }
+}
+
+/// A point in time rather than a step, e.g. the first frame.
+pub fn mark(name: &'static str) {
+    if enabled() {
+        record(Step { name, start: launched().elapsed(), took: Duration::ZERO });
+    }
+}
+
+fn record(step: Step) {
+    steps().lock().unwrap_or_else(|e| e.into_inner()).push(step);
+}
+
+/// The steps in the order they started, one per line; `None` without
+/// `--profile-startup`.
+pub fn report() -> Option<String> {
+    if !enabled() {
+        return None;
+    }
+    let mut steps = steps().lock().unwrap_or_else(|e| e.into_inner()).clone();
+    steps.sort_by_key(|s| s.start);
+    Some(render(&steps))
+}
+
+fn render(steps: &[Step]) -> String {
+    let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+    let mut out = String::from("startup profile (ms):\n      at    took  step\n");
+    for s in steps {
+        let took = if s.took.is_zero() { String::new() } else { format!("{:.1}", ms(s.took)) };
+        let _ = writeln!(out, "{:>8.1} {:>7}  {}", ms(s.start), took, s.name);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_lists_steps_by_start() {
+        let ms = Duration::from_millis;
+        let steps = [
+            Step { name: "config", start: ms(1), took: ms(3) },
+            Step { name: "tui init", start: ms(5), took: ms(40) },
+            Step { name: "first frame", start: ms(52), took: Duration::ZERO },
+        ];
+        assert_eq!(
+            render(&steps),
+            "startup profile (ms):\n      at    took  step\n     1.0     3.0  config\n     5.0    40.0  tui init\n    52.0          first frame\n"
+        );
+    }
+}
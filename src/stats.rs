@@ -0,0 +1,171 @@
+//! Opt-in usage stats, kept on this machine only. With `[stats] enabled`
+//! (`agent stats on`), each CLI subcommand and TUI slash command appends its
+//! name to `<home>/stats.jsonl`, and each session records which
+//! features were configured. Arguments, prompts, file names and replies are
+//! never recorded, and nothing is sent anywhere; `agent stats` summarises
+//! the file and `agent stats export` copies it out.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{OnceLock, RwLock};
+
+/// `[stats]` in config.toml; off unless the user turns it on.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StatsConfig {
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Event {
+    pub at: u64,
+    /// `cli`, `tui` or `session`.
+    pub kind: String,
+    /// Command name from the fixed command set, e.g. `models list`, `/theme`.
+    pub name: String,
+    /// Configured features, on `session` events.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub features: Vec<String>,
+}
+
+pub fn path(home: &Path) -> PathBuf {
+    home.join("stats.jsonl")
+}
+
+fn sink() -> &'static RwLock<Option<PathBuf>> {
+    static SINK: OnceLock<RwLock<Option<PathBuf>>> = OnceLock::new();
+    SINK.get_or_init(Default::default)
+}
+
+/// Record to `home` from now on, or stop recording.
+pub fn configure(config: &StatsConfig, home: &Path) {
+    *sink().write().unwrap_or_else(|e| e.into_inner()) = config.enabled.then(|| path(home));
+}
+
+/// Append an event when stats are on; failures are only logged.
+pub fn record(kind: &str, name: &str, features: Vec<String>) {
+    let Some(path) = sink().read().unwrap_or_else(|e| e.into_inner()).clone() else { return };
+    let event = Event { at: crate::tasks::now_secs(), kind: kind.into(), name: name.into(), features };
+    let line = match serde_json::to_string(&event) {
+        Ok(l) => l,
+        Err(_) => return,
+    };
+    let written = std::fs::create_dir_all(path.parent().unwrap_or(Path::new("."))).and_then(|_| {
+        let mut f = std::fs::OpenOptions::new().create(true).append(true).open(&path)?;
+        writeln!(f, "{}", line)
+    });
+    if let Err(e) = written {
+        log::debug!("could not record usage stats: {}", e);
+    }
+}
+
+/// Features switched on in `config`, by name only.
+pub fn features(config: &crate::config::RuntimeConfig) -> Vec<String> {
+    let mut on = vec![];
+    let mut flag = |name: &str, set: bool| {
+        if set {
+            on.push(name.to_string());
+        }
+    };
+    flag("native-plugins", cfg!(feature = "native-plugins"));
+    flag("router", !config.router.tiers.is_empty());
+    flag("prefix-cache", config.models.prefix_cache.enabled);
+    flag("autocomplete", config.ui.autocomplete);
+    flag("proxy", config.network.defaults.proxy.as_deref().is_some_and(|p| !p.is_empty()));
+    flag("skill-signatures", config.skills.require_signature);
+    flag("personas", !config.personas.is_empty());
+    flag("templates", !config.templates.is_empty());
+    on
+}
+
+/// Name of the CLI subcommand in `args` (without the binary), as declared
+/// to clap: `doctor`, `models list`. Values never appear, even positional
+/// ones, because only words matching a declared subcommand are taken.
+pub fn cli_command(command: &clap::Command, args: &[String]) -> Option<String> {
+    let mut names = vec![];
+    let mut current = command;
+    for arg in args.iter().filter(|a| !a.starts_with('-')) {
+        match current.find_subcommand(arg) {
+            Some(sub) => {
+                names.push(sub.get_name().to_string());
+                current = sub;
+            }
+            None => break,
+        }
+    }
+    (!names.is_empty()).then(|| names.join(" "))
+}
+
+pub fn load(home: &Path) -> Vec<Event> {
+    std::fs::read_to_string(path(home)).map(|s| s.lines().filter_map(|l| serde_json::from_str(l).ok()).collect()).unwrap_or_default()
+}
+
+/// Text summary for `agent stats`.
+pub fn summary(events: &[Event]) -> String {
+    if events.is_empty() {
+        return "no usage recorded yet".into();
+    }
+    let mut commands: BTreeMap<&str, usize> = BTreeMap::new();
+    let mut features: BTreeMap<&str, usize> = BTreeMap::new();
+    let mut sessions = 0;
+    for e in events {
+        if e.kind == "session" {
+            sessions += 1;
+            for f in &e.features {
+                *features.entry(f).or_default() += 1;
+            }
+        } else {
+            *commands.entry(&e.name).or_default() += 1;
+        }
+    }
+    let first = events.iter().map(|e| e.at).min().unwrap_or(0);
+    let days = (crate::tasks::now_secs().saturating_sub(first) / 86_400).max(1);
+    let mut out = format!("{} sessions and {} commands over {} day(s)\n\nCommands\n", sessions, commands.values().sum::<usize>(), days);
+    let mut ranked: Vec<_> = commands.into_iter().collect();
+    ranked.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    for (name, n) in ranked {
+        out.push_str(&format!("  {:<24} {}\n", name, n));
+    }
+    if !features.is_empty() {
+        out.push_str("\nFeatures (sessions with each on)\n");
+        for (name, n) in features {
+            out.push_str(&format!("  {:<24} {}\n", name, n));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_opt_in_recording_and_names_only() {
+        let cli = crate::cli::commands::Commands::command();
+        let args = |s: &str| s.split(' ').map(String::from).collect::<Vec<_>>();
+        assert_eq!(cli_command(&cli, &args("secrets set llm sk-123")).as_deref(), Some("secrets set"));
+        assert_eq!(cli_command(&cli, &args("run --goal build")).as_deref(), Some("run"));
+        assert_eq!(cli_command(&cli, &args("tasks cancel list")).as_deref(), Some("tasks cancel"));
+
+        let home = tempfile::tempdir().unwrap();
+        configure(&StatsConfig::default(), home.path());
+        record("cli", "doctor", vec![]);
+        assert!(load(home.path()).is_empty(), "off by default");
+
+        configure(&StatsConfig { enabled: true }, home.path());
+        record("session", "tui", vec!["router".into()]);
+        record("tui", "/theme", vec![]);
+        record("tui", "/theme", vec![]);
+        record("cli", "doctor", vec![]);
+        configure(&StatsConfig::default(), home.path());
+        let events = load(home.path());
+        assert_eq!(events.len(), 4);
+        let text = summary(&events);
+        assert!(text.starts_with("1 sessions and 3 commands"), "{}", text);
+        assert!(text.contains("  /theme                   2\n  doctor                   1\n"), "{}", text);
+        assert!(text.contains("router"));
+    }
+}
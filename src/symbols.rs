@@ -0,0 +1,150 @@
+//! Symbol index: the public items of a project's Rust, Python, JavaScript
+//! and TypeScript files, found line by line with per-language patterns
+//! rather than a parser. Each symbol records where it is declared and
+//! whether a doc comment already precedes it (a docstring follows it in
+//! Python). `agent test-gen` and `agent docgen` are built on it.
+
+use crate::tools::policy::FileAccessPolicy;
+use crate::workspace::{file_index, ProjectProfile};
+use regex::Regex;
+use std::path::Path;
+use std::sync::OnceLock;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Function,
+    Type,
+    Constant,
+    Module,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Symbol {
+    /// Path relative to the project root.
+    pub file: String,
+    /// 1-based line of the declaration.
+    pub line: usize,
+    pub kind: SymbolKind,
+    pub name: String,
+    /// The declaration up to its body, e.g. `pub fn add(a: i32) -> i32`.
+    pub signature: String,
+    pub documented: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    Rust,
+    Python,
+    Script,
+}
+
+impl Language {
+    pub fn of(path: &Path) -> Option<Self> {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("rs") => Some(Language::Rust),
+            Some("py") => Some(Language::Python),
+            Some("js" | "ts" | "mjs") => Some(Language::Script),
+            _ => None,
+        }
+    }
+
+    /// Patterns with a `name` group, by kind.
+    fn patterns(self) -> &'static [(SymbolKind, Regex)] {
+        static RUST: OnceLock<Vec<(SymbolKind, Regex)>> = OnceLock::new();
+        static PYTHON: OnceLock<Vec<(SymbolKind, Regex)>> = OnceLock::new();
+        static SCRIPT: OnceLock<Vec<(SymbolKind, Regex)>> = OnceLock::new();
+        let compile = |list: &[(SymbolKind, &str)]| list.iter().map(|(k, p)| (*k, Regex::new(p).expect("valid symbol pattern"))).collect();
+        match self {
+            Language::Rust => RUST.get_or_init(|| {
+                compile(&[
+                    (SymbolKind::Function, r"^\s*pub(?:\([a-z]+\))?\s+(?:const\s+)?(?:async\s+)?(?:unsafe\s+)?fn\s+(?P<name>\w+)[^{;]*"),
+                    (SymbolKind::Type, r"^\s*pub(?:\([a-z]+\))?\s+(?:struct|enum|trait|type|union)\s+(?P<name>\w+)[^{;=(]*"),
+                    (SymbolKind::Constant, r"^\s*pub(?:\([a-z]+\))?\s+(?:const|static)\s+(?P<name>\w+)\s*:[^=]*"),
+                    (SymbolKind::Module, r"^\s*pub(?:\([a-z]+\))?\s+mod\s+(?P<name>\w+)"),
+                ])
+            }),
+            Language::Python => PYTHON.get_or_init(|| {
+                compile(&[
+                    (SymbolKind::Function, r"^(?:async\s+)?def\s+(?P<name>[a-zA-Z]\w*)\s*\([^)]*\)[^:]*"),
+                    (SymbolKind::Type, r"^class\s+(?P<name>[a-zA-Z]\w*)[^:]*"),
+                ])
+            }),
+            Language::Script => SCRIPT.get_or_init(|| {
+                compile(&[
+                    (SymbolKind::Function, r"^export\s+(?:default\s+)?(?:async\s+)?function\s*\*?\s*(?P<name>\w+)\s*\([^)]*\)[^{]*"),
+                    (SymbolKind::Type, r"^export\s+(?:default\s+)?(?:abstract\s+)?(?:class|interface|type|enum)\s+(?P<name>\w+)[^{=]*"),
+                    (SymbolKind::Constant, r"^export\s+const\s+(?P<name>\w+)"),
+                ])
+            }),
+        }
+    }
+}
+
+/// Whether the item declared at `lines[at]` has a doc comment.
+fn documented(language: Language, lines: &[&str], at: usize) -> bool {
+    match language {
+        Language::Python => lines.get(at + 1).map(|l| l.trim_start().starts_with("\"\"\"") || l.trim_start().starts_with("'''")).unwrap_or(false),
+        Language::Rust | Language::Script => {
+            // skip attributes and decorators between the comment and the item
+            let above = lines[..at].iter().rev().map(|l| l.trim()).find(|l| !l.starts_with("#[") && !l.starts_with('@'));
+            match (language, above) {
+                (Language::Rust, Some(l)) => l.starts_with("///") || l.starts_with("//!") || l.ends_with("*/"),
+                (_, Some(l)) => l.ends_with("*/"),
+                _ => false,
+            }
+        }
+    }
+}
+
+/// Public items declared in `source`, the contents of `file`.
+pub fn parse(file: &str, source: &str) -> Vec<Symbol> {
+    let Some(language) = Language::of(Path::new(file)) else { return vec![] };
+    let lines: Vec<&str> = source.lines().collect();
+    let mut out = vec![];
+    for (i, line) in lines.iter().enumerate() {
+        let Some((kind, caps)) = language.patterns().iter().find_map(|(k, re)| re.captures(line).map(|c| (*k, c))) else { continue };
+        out.push(Symbol {
+            file: file.to_string(),
+            line: i + 1,
+            kind,
+            name: caps["name"].to_string(),
+            signature: caps[0].trim().to_string(),
+            documented: documented(language, &lines, i),
+        });
+    }
+    out
+}
+
+/// Public items of every file under `root` that `policy` lets agents read.
+pub fn index(root: &Path, profile: &ProjectProfile, policy: &FileAccessPolicy) -> Vec<Symbol> {
+    file_index(root, profile, policy)
+        .into_iter()
+        .filter(|f| Language::of(Path::new(f)).is_some())
+        .flat_map(|f| {
+            let source = std::fs::read_to_string(root.join(&f)).unwrap_or_default();
+            parse(&f, &source)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_public_items_and_their_docs_are_found() {
+        let rust = "/// Adds.\npub fn add(a: i32) -> i32 {\n}\n#[derive(Debug)]\npub struct Point {\nfn private() {}\npub const MAX: usize = 3;\n";
+        let symbols = parse("src/lib.rs", rust);
+        let names: Vec<(&str, usize, bool)> = symbols.iter().map(|s| (s.name.as_str(), s.line, s.documented)).collect();
+        assert_eq!(names, vec![("add", 2, true), ("Point", 5, false), ("MAX", 7, false)]);
+        assert_eq!(symbols[0].signature, "pub fn add(a: i32) -> i32");
+        assert_eq!(symbols[2].kind, SymbolKind::Constant);
+
+        let python = "class Shape:\n    \"\"\"A shape.\"\"\"\ndef area(r):\n    return r\ndef _hidden():\n    pass\n";
+        let symbols = parse("geo.py", python);
+        assert_eq!(symbols.iter().map(|s| (s.name.as_str(), s.documented)).collect::<Vec<_>>(), vec![("Shape", true), ("area", false)]);
+
+        let ts = "/** Loads. */\nexport async function load(p: string): Promise<void> {\nexport interface Opts {\n";
+        assert_eq!(parse("src/a.ts", ts).iter().map(|s| s.documented).collect::<Vec<_>>(), vec![true, false]);
+    }
+}
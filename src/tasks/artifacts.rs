@@ -0,0 +1,181 @@
+use crate::memory::events::{EventBus, SessionEvent};
+use crate::tasks::trace::{self, Step};
+use crate::types::ToolCallRecord;
+use parking_lot::Mutex;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Collects what a task produced while it runs: files written by tools and
+/// tool outputs. Cheap to clone; clones share the same record.
+#[derive(Debug, Clone, Default)]
+pub struct ArtifactRecorder {
+    root: PathBuf,
+    files: Arc<Mutex<BTreeSet<PathBuf>>>,
+    outputs: Arc<Mutex<Vec<(String, String)>>>,
+    calls: Arc<Mutex<Vec<Step>>>,
+    /// Every call with its input and result, failed ones included.
+    inspected: Arc<Mutex<Vec<ToolCallRecord>>>,
+    /// Where written files are announced, and the session they came from.
+    events: Option<(EventBus, String)>,
+}
+
+impl ArtifactRecorder {
+    /// `root` is the workspace the task runs in; recorded files are stored
+    /// relative to it.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into(), ..Default::default() }
+    }
+
+    /// Store recorded files relative to `root` from now on.
+    pub fn set_root(&mut self, root: impl Into<PathBuf>) {
+        self.root = root.into();
+    }
+
+    /// Announce written files on `events` as `session`'s from now on.
+    pub fn set_events(&mut self, events: EventBus, session: &str) {
+        self.events = Some((events, session.to_string()));
+    }
+
+    pub fn file_written(&self, path: &Path) {
+        self.files.lock().insert(path.to_path_buf());
+        if let Some((events, session)) = &self.events {
+            let file = path.strip_prefix(&self.root).unwrap_or(path).display().to_string();
+            events.publish(SessionEvent::FileChange { session: session.clone(), file, action: "wrote".into() });
+        }
+    }
+
+    pub fn tool_output(&self, tool: &str, output: &str) {
+        self.outputs.lock().push((tool.to_string(), output.to_string()));
+    }
+
+    /// Note a successful call for the task's replayable trace.
+    pub fn tool_call(&self, step: Step) {
+        self.calls.lock().push(step);
+    }
+
+    /// Note a call, successful or not, for the chat's tool-call inspector.
+    pub fn inspect(&self, call: ToolCallRecord) {
+        self.inspected.lock().push(call);
+    }
+
+    pub fn tool_calls(&self) -> Vec<ToolCallRecord> {
+        self.inspected.lock().clone()
+    }
+
+    /// Write everything recorded plus `summary` to
+    /// `<home>/artifacts/<task_id>` and return that directory.
+    pub fn collect(&self, home: &Path, task_id: &str, summary: &str) -> anyhow::Result<PathBuf> {
+        let dir = dir(home, task_id);
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join("summary.md"), summary)?;
+
+        let outputs = self.outputs.lock();
+        if !outputs.is_empty() {
+            let log: String = outputs.iter().map(|(tool, out)| format!("## {}\n\n```\n{}\n```\n\n", tool, out)).collect();
+            fs::write(dir.join("tool_outputs.md"), log)?;
+        }
+
+        let calls = self.calls.lock();
+        if !calls.is_empty() {
+            let lines: Vec<String> = calls.iter().map(serde_json::to_string).collect::<Result<_, _>>()?;
+            fs::write(dir.join(trace::FILE), lines.join("\n") + "\n")?;
+        }
+
+        for file in self.files.lock().iter() {
+            let rel = file.strip_prefix(&self.root).unwrap_or(file);
+            let rel: PathBuf = rel.components().filter(|c| matches!(c, std::path::Component::Normal(_))).collect();
+            let dest = dir.join("files").join(rel);
+            if let Some(parent) = dest.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            if let Err(e) = fs::copy(file, &dest) {
+                tracing::warn!("artifact {} not copied: {}", file.display(), e);
+            }
+        }
+        Ok(dir)
+    }
+}
+
+pub fn dir(home: &Path, task_id: &str) -> PathBuf {
+    home.join("artifacts").join(task_id)
+}
+
+/// Files under a task's artifact directory, relative to it.
+pub fn list(home: &Path, task_id: &str) -> Vec<PathBuf> {
+    let root = dir(home, task_id);
+    let mut out = vec![];
+    let mut stack = vec![root.clone()];
+    while let Some(d) = stack.pop() {
+        for entry in fs::read_dir(&d).into_iter().flatten().flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                stack.push(path);
+            } else if let Ok(rel) = path.strip_prefix(&root) {
+                out.push(rel.to_path_buf());
+            }
+        }
+    }
+    out.sort();
+    out
+}
+
+/// Open `path` with the platform's default handler.
+pub fn open(path: &Path) -> anyhow::Result<()> {
+    let opener = if cfg!(target_os = "macos") {
+        "open"
+    } else if cfg!(windows) {
+        "explorer"
+    } else {
+        "xdg-open"
+    };
+    std::process::Command::new(opener).arg(path).spawn()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collect_copies_files_and_outputs() -> anyhow::Result<()> {
+        let work = tempfile::tempdir()?;
+        let home = tempfile::tempdir()?;
+        let written = work.path().join("src/lib.rs");
+        fs::create_dir_all(written.parent().unwrap())?;
+        fs::write(&written, "pub fn x() {}")?;
+
+        let recorder = ArtifactRecorder::new(work.path());
+        recorder.clone().file_written(&written);
+        recorder.tool_output("echo", "hi");
+        let dir = recorder.collect(home.path(), "t1", "# done")?;
+
+        assert_eq!(fs::read_to_string(dir.join("files/src/lib.rs"))?, "pub fn x() {}");
+        let files = list(home.path(), "t1");
+        let names: Vec<String> = files.iter().map(|p| p.to_string_lossy().replace('\\', "/")).collect();
+        assert_eq!(names, vec!["files/src/lib.rs", "summary.md", "tool_outputs.md"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_inspector_sees_failed_calls_too() {
+        use crate::tools::registry::{EchoTool, ToolRegistry};
+        use crate::types::AgentInput;
+        let work = tempfile::tempdir().unwrap();
+        let reg = ToolRegistry::new();
+        reg.register(Arc::new(EchoTool));
+        let policy = crate::tools::policy::FileAccessPolicy::new(work.path(), &crate::config::FilesConfig::default());
+        reg.register(Arc::new(crate::tools::fs::ReadFileTool { policy }));
+        let recorder = ArtifactRecorder::new(work.path());
+        reg.set_recorder(recorder.clone());
+
+        reg.run("echo", AgentInput { text: "hi".into() }).unwrap();
+        assert!(reg.run("read_file", AgentInput { text: "missing.txt".into() }).is_err());
+        let calls = recorder.tool_calls();
+        assert_eq!(calls[0], ToolCallRecord { tool: "echo".into(), input: "hi".into(), output: "hi".into(), ok: true });
+        assert!(!calls[1].ok && calls[1].output.starts_with("error: "));
+        // only successful calls make the replayable trace
+        assert_eq!(recorder.calls.lock().len(), 1);
+    }
+}
@@ -0,0 +1,135 @@
+//! The Tasks view's kanban board. A task's column follows from its status
+//! and dependencies: a planned step depends on the steps before it, so a
+//! pending subtask whose earlier siblings have not completed is Blocked, as
+//! is a failed task. Moving a card sets the status its new column stands
+//! for; Blocked is never a target, it only follows from the tasks around.
+
+use crate::tasks::{Task, TaskStatus};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    Pending,
+    InProgress,
+    Blocked,
+    Done,
+}
+
+impl Column {
+    pub const ALL: [Column; 4] = [Column::Pending, Column::InProgress, Column::Blocked, Column::Done];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Column::Pending => "Pending",
+            Column::InProgress => "In Progress",
+            Column::Blocked => "Blocked",
+            Column::Done => "Done",
+        }
+    }
+
+    pub fn index(self) -> usize {
+        Column::ALL.iter().position(|c| *c == self).unwrap_or(0)
+    }
+
+    /// The column a card lands in when moved `delta` columns, passing over
+    /// Blocked.
+    fn step(self, delta: isize) -> Option<Column> {
+        let mut at = self.index() as isize;
+        loop {
+            at += delta.signum();
+            match Column::ALL.get(usize::try_from(at).ok()?)? {
+                Column::Blocked => continue,
+                column => return Some(*column),
+            }
+        }
+    }
+}
+
+/// The first earlier sibling of `task` that has not completed.
+pub fn blocker<'a>(task: &Task, all: &'a [Task]) -> Option<&'a Task> {
+    let parent = task.parent_id.as_deref()?;
+    all.iter()
+        .filter(|t| t.parent_id.as_deref() == Some(parent) && t.index < task.index && t.status != TaskStatus::Completed)
+        .min_by_key(|t| t.index)
+}
+
+pub fn column(task: &Task, all: &[Task]) -> Column {
+    match task.status {
+        TaskStatus::Completed | TaskStatus::Cancelled => Column::Done,
+        TaskStatus::Failed => Column::Blocked,
+        TaskStatus::Running => Column::InProgress,
+        TaskStatus::Pending if blocker(task, all).is_some() => Column::Blocked,
+        TaskStatus::Pending => Column::Pending,
+    }
+}
+
+/// `task` moved `delta` columns (`-1` left, `1` right), with the column it
+/// ends up in. Refused when the task waits on another one, or when it would
+/// be done before its subtasks.
+pub fn shift(task: &Task, all: &[Task], delta: isize) -> anyhow::Result<(Column, Task)> {
+    let from = column(task, all);
+    let Some(to) = from.step(delta) else { anyhow::bail!("{} is already in {}", &task.id[..8], from.label()) };
+    let mut moved = task.clone();
+    let reopen = |moved: &mut Task, status: TaskStatus| {
+        if moved.status.is_finished() {
+            moved.progress = 0.0;
+        }
+        if status == TaskStatus::Running {
+            moved.started_at = Some(crate::tasks::now_secs());
+        }
+        moved.status = status;
+        moved.finished_at = None;
+    };
+    match to {
+        Column::Pending => reopen(&mut moved, TaskStatus::Pending),
+        Column::InProgress => {
+            if let Some(b) = blocker(task, all) {
+                anyhow::bail!("{} waits on {} ({})", &task.id[..8], &b.id[..8], b.title);
+            }
+            reopen(&mut moved, TaskStatus::Running);
+        }
+        // `step` passes over Blocked
+        Column::Done | Column::Blocked => {
+            let open = all.iter().filter(|t| t.parent_id.as_deref() == Some(task.id.as_str()) && !t.status.is_finished()).count();
+            if open > 0 {
+                anyhow::bail!("{} has {} unfinished subtask(s)", &task.id[..8], open);
+            }
+            moved.finish(TaskStatus::Completed);
+        }
+    }
+    Ok((column(&moved, all), moved))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_columns_follow_status_and_dependencies() {
+        let mut parent = Task::new("ship the release", "");
+        parent.status = TaskStatus::Running;
+        let mut first = parent.subtask(0, "bump the version");
+        first.status = TaskStatus::Running;
+        let second = parent.subtask(1, "tag it");
+        let mut failed = Task::new("flaky job", "");
+        failed.status = TaskStatus::Failed;
+        let mut all = vec![parent.clone(), first.clone(), second.clone(), failed.clone()];
+        let columns: Vec<Column> = all.iter().map(|t| column(t, &all)).collect();
+        assert_eq!(columns, vec![Column::InProgress, Column::InProgress, Column::Blocked, Column::Blocked]);
+
+        // a blocked step cannot start, and a parent is not done before its steps
+        assert!(shift(&second, &all, -1).unwrap_err().to_string().contains("waits on"));
+        assert!(shift(&parent, &all, 1).unwrap_err().to_string().contains("2 unfinished subtask"));
+        assert!(shift(&Task::new("new", ""), &all, -1).unwrap_err().to_string().contains("already in Pending"));
+
+        // moving right from In Progress passes over Blocked
+        let (to, done) = shift(&first, &all, 1).unwrap();
+        assert_eq!((to, done.status, done.progress), (Column::Done, TaskStatus::Completed, 1.0));
+        all[1] = done.clone();
+        assert_eq!(column(&second, &all), Column::Pending);
+
+        let (to, retried) = shift(&failed, &all, -1).unwrap();
+        assert_eq!((to, retried.status), (Column::InProgress, TaskStatus::Running));
+        let (to, reopened) = shift(&done, &all, -1).unwrap();
+        assert_eq!((to, reopened.status, reopened.progress, reopened.finished_at), (Column::InProgress, TaskStatus::Running, 0.0, None));
+    }
+}
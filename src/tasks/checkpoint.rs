@@ -0,0 +1,116 @@
+use crate::graph::pipeline::Pipeline;
+use crate::tasks::{now_secs, Task};
+use crate::types::Message;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Output of a node that finished before the checkpoint was written.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodeResult {
+    pub name: String,
+    pub ok: bool,
+    pub output: String,
+}
+
+/// Execution state of a run, rewritten after every node as
+/// `<home>/checkpoints/<task-id>.json` so `agent run --resume <task-id>` can
+/// pick up after the last completed node.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub task: Task,
+    /// The pipeline the run used; `None` for plan → execute → review.
+    pub pipeline: Option<Pipeline>,
+    pub completed: Vec<NodeResult>,
+    /// Shared variables at the time of the checkpoint.
+    #[serde(default)]
+    pub shared_vars: BTreeMap<String, String>,
+    /// Short-term memory of the sub-agents by role, so a resumed step keeps
+    /// what the steps before it saw.
+    #[serde(default)]
+    pub short_term: BTreeMap<String, Vec<Message>>,
+    pub updated_at: u64,
+}
+
+impl Checkpoint {
+    pub fn new(task: Task, pipeline: Option<Pipeline>) -> Self {
+        Self { task, pipeline, completed: vec![], shared_vars: BTreeMap::new(), short_term: BTreeMap::new(), updated_at: now_secs() }
+    }
+
+    pub fn result(&self, node: &str) -> Option<&NodeResult> {
+        self.completed.iter().find(|r| r.name == node)
+    }
+
+    pub fn record(&mut self, node: &str, ok: bool, output: &str) {
+        self.completed.retain(|r| r.name != node);
+        self.completed.push(NodeResult { name: node.into(), ok, output: output.into() });
+        self.updated_at = now_secs();
+    }
+}
+
+pub struct CheckpointStore {
+    dir: PathBuf,
+}
+
+impl CheckpointStore {
+    pub fn new(home: &Path) -> Self {
+        Self { dir: home.join("checkpoints") }
+    }
+
+    pub fn save(&self, cp: &Checkpoint) -> anyhow::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        // write then rename, so a crash mid-write keeps the previous checkpoint
+        let tmp = self.dir.join(format!("{}.json.tmp", cp.task.id));
+        fs::write(&tmp, serde_json::to_string_pretty(cp)?)?;
+        fs::rename(tmp, self.dir.join(format!("{}.json", cp.task.id)))?;
+        Ok(())
+    }
+
+    /// Checkpoint whose task id is `id` or starts with it.
+    pub fn load(&self, id: &str) -> anyhow::Result<Checkpoint> {
+        let mut matches: Vec<PathBuf> = fs::read_dir(&self.dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|e| e.path())
+            .filter(|p| p.extension().map(|x| x == "json").unwrap_or(false))
+            .filter(|p| p.file_stem().map(|s| s.to_string_lossy().starts_with(id)).unwrap_or(false))
+            .collect();
+        match matches.len() {
+            1 => Ok(serde_json::from_str(&fs::read_to_string(matches.remove(0))?)?),
+            0 => Err(anyhow::anyhow!("no checkpoint for run {}", id)),
+            n => Err(anyhow::anyhow!("{} checkpoints match {}, use a longer id", n, id)),
+        }
+    }
+
+    pub fn remove(&self, task_id: &str) {
+        let _ = fs::remove_file(self.dir.join(format!("{}.json", task_id)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checkpoint_roundtrip() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let store = CheckpointStore::new(dir.path());
+        let mut cp = Checkpoint::new(Task::new("ship", "ship it"), Some(Pipeline::default()));
+        cp.record("planner", true, "1. build");
+        cp.record("planner", true, "1. build\n2. test");
+        cp.shared_vars.insert("branch".into(), "main".into());
+        cp.short_term.insert("executor".into(), vec![Message::new("tool", "built")]);
+        store.save(&cp)?;
+
+        let loaded = store.load(&cp.task.id[..8])?;
+        assert_eq!(loaded, cp);
+        assert_eq!(loaded.completed.len(), 1);
+        assert_eq!(loaded.result("planner").map(|r| r.output.as_str()), Some("1. build\n2. test"));
+
+        store.remove(&cp.task.id);
+        assert!(store.load(&cp.task.id).is_err());
+        Ok(())
+    }
+}
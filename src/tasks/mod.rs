@@ -0,0 +1,338 @@
+pub mod artifacts;
+pub mod board;
+pub mod checkpoint;
+pub mod retry;
+pub mod templates;
+pub mod timing;
+pub mod trace;
+
+use retry::{Diagnosis, FailureCause, RetryPolicy};
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub fn now_secs() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TaskStatus {
+    Pending,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl TaskStatus {
+    pub fn is_finished(self) -> bool {
+        matches!(self, TaskStatus::Completed | TaskStatus::Failed | TaskStatus::Cancelled)
+    }
+}
+
+fn one() -> f64 {
+    1.0
+}
+
+/// A question the planner asked before planning, and the user's answer.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Clarification {
+    pub question: String,
+    pub answer: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Task {
+    pub id: String,
+    pub title: String,
+    pub description: String,
+    pub status: TaskStatus,
+    #[serde(default)]
+    pub steps: Vec<String>,
+    /// Persona (see `[personas]` in the config) the agents run with.
+    #[serde(default)]
+    pub agent_profile: Option<String>,
+    pub parent_id: Option<String>,
+    /// Position among siblings.
+    #[serde(default)]
+    pub index: usize,
+    /// Share of the parent's progress this subtask accounts for.
+    #[serde(default = "one")]
+    pub weight: f64,
+    /// Own progress in `[0, 1]`; parents use the rollup of their children.
+    #[serde(default)]
+    pub progress: f64,
+    /// Unix seconds.
+    pub created_at: u64,
+    /// When it last started running, in unix seconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<u64>,
+    pub finished_at: Option<u64>,
+    /// Seconds it was expected to take when it started (see `timing`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub estimate_secs: Option<u64>,
+    /// Overrides the configured policy for this task.
+    #[serde(default)]
+    pub retry: Option<RetryPolicy>,
+    #[serde(default)]
+    pub attempts: u32,
+    /// One diagnosis per failed attempt, oldest first.
+    #[serde(default)]
+    pub failures: Vec<Diagnosis>,
+    /// Which model each step ran on and why, when `[router]` is configured.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub routing: Vec<crate::agent::router::RoutingDecision>,
+    /// Answers to the planner's questions, part of the goal from then on.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub clarifications: Vec<Clarification>,
+}
+
+impl Task {
+    pub fn new(title: impl Into<String>, description: impl Into<String>) -> Self {
+        Self {
+            id: crate::types::new_id(),
+            title: title.into(),
+            description: description.into(),
+            status: TaskStatus::Pending,
+            steps: vec![],
+            agent_profile: None,
+            parent_id: None,
+            index: 0,
+            weight: 1.0,
+            progress: 0.0,
+            created_at: now_secs(),
+            started_at: None,
+            finished_at: None,
+            estimate_secs: None,
+            retry: None,
+            attempts: 0,
+            failures: vec![],
+            routing: vec![],
+            clarifications: vec![],
+        }
+    }
+
+    /// Goal text handed to the planner: the description plus any steps and
+    /// clarifications.
+    pub fn goal(&self) -> String {
+        let mut goal = self.description.clone();
+        if !self.steps.is_empty() {
+            goal.push_str("\n\nSteps:");
+            for (i, step) in self.steps.iter().enumerate() {
+                goal.push_str(&format!("\n{}. {}", i + 1, step));
+            }
+        }
+        if !self.clarifications.is_empty() {
+            goal.push_str("\n\nClarifications:");
+            for c in &self.clarifications {
+                goal.push_str(&format!("\nQ: {}\nA: {}", c.question, c.answer));
+            }
+        }
+        goal
+    }
+
+    /// Child of this task for one planned step.
+    pub fn subtask(&self, index: usize, title: impl Into<String>) -> Task {
+        let title = title.into();
+        let mut child = Task::new(title.clone(), title);
+        child.parent_id = Some(self.id.clone());
+        child.index = index;
+        child.agent_profile = self.agent_profile.clone();
+        child
+    }
+
+    /// Cause of the last failed attempt.
+    pub fn failure_cause(&self) -> Option<FailureCause> {
+        self.failures.last().map(|d| d.cause)
+    }
+
+    pub fn finish(&mut self, status: TaskStatus) {
+        if status == TaskStatus::Completed {
+            self.progress = 1.0;
+        }
+        self.status = status;
+        self.finished_at = Some(now_secs());
+    }
+}
+
+/// Numbered (`1.`, `2)`) or bulleted (`-`, `*`) lines of a plan, with the
+/// markers stripped. Plans without such lines yield nothing.
+pub fn steps_from_plan(plan: &str) -> Vec<String> {
+    plan.lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            let rest = if let Some(rest) = line.strip_prefix(['-', '*']) {
+                rest
+            } else {
+                let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+                if digits == 0 {
+                    return None;
+                }
+                line[digits..].strip_prefix(['.', ')'])?
+            };
+            let step = rest.trim();
+            (!step.is_empty()).then(|| step.to_string())
+        })
+        .collect()
+}
+
+/// Tasks persisted as `<home>/tasks/<id>.json`.
+#[derive(Debug, Clone)]
+pub struct TaskStore {
+    home: PathBuf,
+}
+
+impl TaskStore {
+    pub fn new(home: impl Into<PathBuf>) -> Self {
+        Self { home: home.into() }
+    }
+
+    pub fn home(&self) -> &Path {
+        &self.home
+    }
+
+    fn dir(&self) -> PathBuf {
+        self.home.join("tasks")
+    }
+
+    pub fn save(&self, task: &Task) -> anyhow::Result<()> {
+        fs::create_dir_all(self.dir())?;
+        fs::write(self.dir().join(format!("{}.json", task.id)), serde_json::to_string_pretty(task)?)?;
+        Ok(())
+    }
+
+    /// Delete task `id`; a task that was never saved is not an error.
+    pub fn remove(&self, id: &str) -> anyhow::Result<()> {
+        match fs::remove_file(self.dir().join(format!("{}.json", id))) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Task whose id is `id` or starts with it, if exactly one matches.
+    pub fn find(&self, id: &str) -> anyhow::Result<Task> {
+        let mut matches: Vec<Task> = self.list().into_iter().filter(|t| t.id.starts_with(id)).collect();
+        match matches.len() {
+            1 => Ok(matches.remove(0)),
+            0 => Err(anyhow::anyhow!("no task matches {}", id)),
+            n => Err(anyhow::anyhow!("{} tasks match {}, use a longer id", n, id)),
+        }
+    }
+
+    /// All tasks, newest first.
+    pub fn list(&self) -> Vec<Task> {
+        let mut tasks: Vec<Task> = fs::read_dir(self.dir())
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter_map(|e| fs::read_to_string(e.path()).ok())
+            .filter_map(|s| serde_json::from_str(&s).ok())
+            .collect();
+        tasks.sort_by(|a, b| b.created_at.cmp(&a.created_at).then(a.id.cmp(&b.id)));
+        tasks
+    }
+}
+
+impl TaskStore {
+    /// Progress of `task`: its own for leaves, otherwise the weighted mean
+    /// of its children's rolled-up progress.
+    pub fn progress(&self, task: &Task) -> f64 {
+        rollup(task, &self.list())
+    }
+
+    /// Cancel `id` and every unfinished descendant. Returns the ids changed.
+    pub fn cancel(&self, id: &str) -> anyhow::Result<Vec<String>> {
+        let all = self.list();
+        let root = self.find(id)?;
+        let mut changed = vec![];
+        let mut stack = vec![root];
+        while let Some(mut task) = stack.pop() {
+            stack.extend(all.iter().filter(|t| t.parent_id.as_deref() == Some(task.id.as_str())).cloned());
+            if !task.status.is_finished() {
+                task.finish(TaskStatus::Cancelled);
+                self.save(&task)?;
+                changed.push(task.id.clone());
+            }
+        }
+        Ok(changed)
+    }
+
+    /// Every task with its depth, parents before their children; roots
+    /// newest first.
+    pub fn tree(&self) -> Vec<(usize, Task)> {
+        let all = self.list();
+        let mut out = vec![];
+        let mut stack: Vec<(usize, Task)> = all.iter().filter(|t| t.parent_id.is_none()).rev().map(|t| (0, t.clone())).collect();
+        while let Some((depth, task)) = stack.pop() {
+            let mut kids: Vec<&Task> = all.iter().filter(|t| t.parent_id.as_deref() == Some(task.id.as_str())).collect();
+            kids.sort_by_key(|t| std::cmp::Reverse(t.index));
+            stack.extend(kids.into_iter().map(|t| (depth + 1, t.clone())));
+            out.push((depth, task));
+        }
+        out
+    }
+}
+
+/// [`TaskStore::progress`] against an already-loaded task list, for callers
+/// that show many tasks at once.
+pub fn rollup(task: &Task, all: &[Task]) -> f64 {
+    let kids: Vec<&Task> = all.iter().filter(|t| t.parent_id.as_deref() == Some(task.id.as_str())).collect();
+    let total: f64 = kids.iter().map(|k| k.weight).sum();
+    if kids.is_empty() || total <= 0.0 {
+        return if task.status == TaskStatus::Completed { 1.0 } else { task.progress };
+    }
+    kids.iter().map(|k| k.weight * rollup(k, all)).sum::<f64>() / total
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_store_round_trip_and_prefix_lookup() -> anyhow::Result<()> {
+        let td = tempfile::tempdir()?;
+        let store = TaskStore::new(td.path());
+        let mut task = Task::new("refactor", "split the parser");
+        store.save(&task)?;
+        task.finish(TaskStatus::Completed);
+        store.save(&task)?;
+
+        let found = store.find(&task.id[..8])?;
+        assert_eq!(found.status, TaskStatus::Completed);
+        assert!(found.finished_at.is_some());
+        assert_eq!(store.list().len(), 1);
+        assert!(store.find("zzzz").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_subtask_rollup_tree_and_cascading_cancel() -> anyhow::Result<()> {
+        let td = tempfile::tempdir()?;
+        let store = TaskStore::new(td.path());
+        let parent = Task::new("ship", "ship it");
+        let mut a = parent.subtask(0, "build");
+        a.weight = 3.0;
+        let b = parent.subtask(1, "test");
+        let b1 = b.subtask(0, "unit");
+        for t in [&parent, &a, &b, &b1] {
+            store.save(t)?;
+        }
+
+        a.finish(TaskStatus::Completed);
+        store.save(&a)?;
+        assert_eq!(store.progress(&parent), 0.75);
+
+        let tree: Vec<(usize, String)> = store.tree().into_iter().map(|(d, t)| (d, t.title)).collect();
+        assert_eq!(tree, vec![(0, "ship".into()), (1, "build".into()), (1, "test".into()), (2, "unit".into())]);
+
+        let cancelled = store.cancel(&parent.id)?;
+        assert_eq!(cancelled.len(), 3);
+        assert_eq!(store.find(&b1.id)?.status, TaskStatus::Cancelled);
+        assert_eq!(store.find(&a.id)?.status, TaskStatus::Completed);
+
+        assert_eq!(steps_from_plan("Plan:\n1. read\n2) write\n - test\n2024 was fine"), vec!["read", "write", "test"]);
+        Ok(())
+    }
+}
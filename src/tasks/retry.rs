@@ -0,0 +1,140 @@
+use crate::llm::Llm;
+use crate::types::Message;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::time::Duration;
+
+/// How a failed task is retried. Set globally as `[retry]` in the config,
+/// per template, or per task.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// Total attempts including the first; 1 disables retries.
+    pub max_attempts: u32,
+    pub backoff_ms: u64,
+    pub backoff_factor: f64,
+    /// Model used from the second attempt on ("retry with bigger model").
+    pub escalate_model: Option<String>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self { max_attempts: 1, backoff_ms: 1000, backoff_factor: 2.0, escalate_model: None }
+    }
+}
+
+impl RetryPolicy {
+    /// Wait before attempt `attempt` (1-based; the first attempt never waits).
+    pub fn delay(&self, attempt: u32) -> Duration {
+        if attempt <= 1 {
+            return Duration::ZERO;
+        }
+        let ms = self.backoff_ms as f64 * self.backoff_factor.powi(attempt as i32 - 2);
+        Duration::from_millis(ms.min(u64::MAX as f64) as u64)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureCause {
+    ToolError,
+    BadPlan,
+    FlakyTest,
+    ModelError,
+    Other,
+}
+
+impl FailureCause {
+    pub fn all() -> [FailureCause; 5] {
+        [FailureCause::ToolError, FailureCause::BadPlan, FailureCause::FlakyTest, FailureCause::ModelError, FailureCause::Other]
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            FailureCause::ToolError => "tool error",
+            FailureCause::BadPlan => "bad plan",
+            FailureCause::FlakyTest => "flaky test",
+            FailureCause::ModelError => "model error",
+            FailureCause::Other => "other",
+        }
+    }
+
+    /// Keyword fallback when the model can't be asked.
+    fn guess(error: &str) -> Self {
+        let e = error.to_lowercase();
+        if e.contains("tool") {
+            FailureCause::ToolError
+        } else if e.contains("test") {
+            FailureCause::FlakyTest
+        } else if e.contains("plan") {
+            FailureCause::BadPlan
+        } else if e.contains("llm") || e.contains("connect") || e.contains("timed out") {
+            FailureCause::ModelError
+        } else {
+            FailureCause::Other
+        }
+    }
+}
+
+/// Why an attempt failed, as recorded on the task.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Diagnosis {
+    pub attempt: u32,
+    pub cause: FailureCause,
+    pub explanation: String,
+    pub error: String,
+}
+
+/// Ask `llm` to classify a failure; falls back to keyword matching if the
+/// model is unreachable or answers off-schema.
+pub async fn diagnose(llm: &dyn Llm, goal: &str, error: &str, attempt: u32) -> Diagnosis {
+    let causes: Vec<String> = FailureCause::all()
+        .iter()
+        .map(|c| serde_json::to_value(c).ok().and_then(|v| v.as_str().map(str::to_string)).unwrap_or_default())
+        .collect();
+    let schema = json!({
+        "type": "object",
+        "properties": {
+            "cause": {"type": "string", "enum": causes},
+            "explanation": {"type": "string"}
+        },
+        "required": ["cause", "explanation"]
+    });
+    let messages = vec![Message::new(
+        "user",
+        format!("A task failed. Classify the failure.\n\nGoal:\n{}\n\nError:\n{}", goal, error),
+    )];
+    let answer = crate::llm::structured::complete(llm, &messages, &schema, 1).await;
+    let (cause, explanation) = match answer {
+        Ok(v) => (
+            serde_json::from_value(v["cause"].clone()).unwrap_or(FailureCause::Other),
+            v["explanation"].as_str().unwrap_or_default().to_string(),
+        ),
+        Err(e) => {
+            tracing::warn!("failure diagnosis unavailable: {}", e);
+            (FailureCause::guess(error), "classified by keywords".to_string())
+        }
+    };
+    Diagnosis { attempt, cause, explanation, error: error.to_string() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::mock::MockLlm;
+
+    #[tokio::test]
+    async fn test_backoff_and_diagnosis() {
+        let policy = RetryPolicy { max_attempts: 4, backoff_ms: 100, ..Default::default() };
+        let delays: Vec<u64> = (1..=4).map(|a| policy.delay(a).as_millis() as u64).collect();
+        assert_eq!(delays, vec![0, 100, 200, 400]);
+
+        let llm = MockLlm::new(r#"{"cause": "bad_plan", "explanation": "steps skip the build"}"#);
+        let d = diagnose(&llm, "ship", "cargo failed", 1).await;
+        assert_eq!(d.cause, FailureCause::BadPlan);
+        assert_eq!(d.explanation, "steps skip the build");
+
+        let confused = MockLlm::new("no idea");
+        assert_eq!(diagnose(&confused, "ship", "tool not found", 2).await.cause, FailureCause::ToolError);
+    }
+}
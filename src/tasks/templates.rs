@@ -0,0 +1,114 @@
+use crate::tasks::Task;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Reusable task definition. Stored under `[templates.<name>]` in the config
+/// or as `<home>/templates/<name>.toml`; files win on name clashes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaskTemplate {
+    pub title: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub steps: Vec<String>,
+    #[serde(default)]
+    pub agent_profile: Option<String>,
+    #[serde(default)]
+    pub retry: Option<crate::tasks::retry::RetryPolicy>,
+}
+
+impl TaskTemplate {
+    /// "Save as template": keep what defines the work, drop run state.
+    pub fn from_task(task: &Task) -> Self {
+        Self {
+            title: task.title.clone(),
+            description: task.description.clone(),
+            steps: task.steps.clone(),
+            agent_profile: task.agent_profile.clone(),
+            retry: task.retry.clone(),
+        }
+    }
+
+    pub fn instantiate(&self) -> Task {
+        let mut task = Task::new(self.title.clone(), self.description.clone());
+        task.steps = self.steps.clone();
+        task.agent_profile = self.agent_profile.clone();
+        task.retry = self.retry.clone();
+        task
+    }
+}
+
+pub struct TemplateStore {
+    dir: PathBuf,
+}
+
+impl TemplateStore {
+    pub fn new(home: &Path) -> Self {
+        Self { dir: home.join("templates") }
+    }
+
+    pub fn save(&self, name: &str, template: &TaskTemplate) -> anyhow::Result<PathBuf> {
+        if name.is_empty() || name.contains(['/', '\\', '.']) {
+            return Err(anyhow::anyhow!("invalid template name: {:?}", name));
+        }
+        fs::create_dir_all(&self.dir)?;
+        let p = self.dir.join(format!("{}.toml", name));
+        fs::write(&p, toml::to_string_pretty(template)?)?;
+        Ok(p)
+    }
+
+    /// Config templates overlaid with the saved ones.
+    pub fn all(&self, configured: &BTreeMap<String, TaskTemplate>) -> BTreeMap<String, TaskTemplate> {
+        let mut out = configured.clone();
+        for entry in fs::read_dir(&self.dir).into_iter().flatten().flatten() {
+            let path = entry.path();
+            let Some(name) = path.file_stem().and_then(|s| s.to_str()).map(str::to_string) else { continue };
+            match fs::read_to_string(&path).map_err(anyhow::Error::from).and_then(|s| Ok(toml::from_str(&s)?)) {
+                Ok(t) => {
+                    out.insert(name, t);
+                }
+                Err(e) => tracing::warn!("skipping template {}: {}", path.display(), e),
+            }
+        }
+        out
+    }
+
+    pub fn get(&self, name: &str, configured: &BTreeMap<String, TaskTemplate>) -> anyhow::Result<TaskTemplate> {
+        let mut all = self.all(configured);
+        all.remove(name).ok_or_else(|| {
+            anyhow::anyhow!("no template {:?} (have: {})", name, all.keys().cloned().collect::<Vec<_>>().join(", "))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_as_template_and_instantiate() -> anyhow::Result<()> {
+        let home = tempfile::tempdir()?;
+        let store = TemplateStore::new(home.path());
+        let mut configured = BTreeMap::new();
+        configured.insert("refactor".to_string(), TaskTemplate { title: "Refactor".into(), description: "tidy".into(), steps: vec![], agent_profile: None, retry: None });
+
+        let mut done = Task::new("Add tests", "cover the parser");
+        done.steps = vec!["read parser".into(), "write tests".into()];
+        done.agent_profile = Some("tester".into());
+        store.save("tests", &TaskTemplate::from_task(&done))?;
+        // saved templates shadow config ones
+        store.save("refactor", &TaskTemplate { title: "Refactor (mine)".into(), ..TaskTemplate::from_task(&done) })?;
+
+        assert_eq!(store.all(&configured).len(), 2);
+        assert_eq!(store.get("refactor", &configured)?.title, "Refactor (mine)");
+        let task = store.get("tests", &configured)?.instantiate();
+        assert_ne!(task.id, done.id);
+        assert_eq!(task.agent_profile.as_deref(), Some("tester"));
+        assert!(task.goal().ends_with("Steps:\n1. read parser\n2. write tests"));
+        assert!(store.get("nope", &configured).unwrap_err().to_string().contains("refactor, tests"));
+        assert!(store.save("../x", &TaskTemplate::from_task(&done)).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,179 @@
+//! Time tracking. Each completed task and step adds its duration to
+//! `<home>/timings.jsonl`. A step's estimate is the median duration of the
+//! earlier steps most like it, matched on the words of their titles. A
+//! running task's ETA is what remains of its estimate, plus the estimates
+//! of its steps that have not run yet.
+
+use crate::tasks::{Task, TaskStatus};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Name of the history under the state home.
+pub const FILE: &str = "timings.jsonl";
+
+/// Most similar earlier steps an estimate is taken from.
+const NEIGHBOURS: usize = 5;
+/// Share of title words two steps need in common to count as alike.
+const MIN_SIMILARITY: f64 = 0.3;
+
+/// How long a completed task or step took.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Timing {
+    pub title: String,
+    pub secs: u64,
+    /// Unix seconds it finished.
+    pub at: u64,
+}
+
+pub struct TimingStore {
+    path: PathBuf,
+}
+
+impl TimingStore {
+    pub fn new(home: &Path) -> Self {
+        Self { path: home.join(FILE) }
+    }
+
+    /// Add `task`'s duration, if it completed and its start is known.
+    pub fn record(&self, task: &Task) -> anyhow::Result<()> {
+        let (TaskStatus::Completed, Some(secs), Some(at)) = (task.status, elapsed(task, 0), task.finished_at) else { return Ok(()) };
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = fs::OpenOptions::new().create(true).append(true).open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(&Timing { title: task.title.clone(), secs, at })?)?;
+        Ok(())
+    }
+
+    pub fn load(&self) -> Vec<Timing> {
+        let raw = fs::read_to_string(&self.path).unwrap_or_default();
+        raw.lines().filter_map(|l| serde_json::from_str(l).ok()).collect()
+    }
+}
+
+fn words(title: &str) -> BTreeSet<String> {
+    title.split(|c: char| !c.is_alphanumeric()).filter(|w| w.len() > 2).map(str::to_lowercase).collect()
+}
+
+/// Shared words over all words of both titles.
+fn similarity(a: &BTreeSet<String>, b: &BTreeSet<String>) -> f64 {
+    let all = a.union(b).count();
+    if all == 0 {
+        return 0.0;
+    }
+    a.intersection(b).count() as f64 / all as f64
+}
+
+/// Expected seconds for a step titled `title`; `None` without alike steps.
+pub fn estimate(history: &[Timing], title: &str) -> Option<u64> {
+    let wanted = words(title);
+    let mut alike: Vec<(f64, u64)> = history
+        .iter()
+        .map(|t| (similarity(&wanted, &words(&t.title)), t.secs))
+        .filter(|(s, _)| *s >= MIN_SIMILARITY)
+        .collect();
+    // most alike first, then the most recent of equally alike ones
+    alike.reverse();
+    alike.sort_by(|a, b| b.0.total_cmp(&a.0));
+    let mut secs: Vec<u64> = alike.into_iter().take(NEIGHBOURS).map(|(_, s)| s).collect();
+    secs.sort_unstable();
+    secs.get(secs.len() / 2).copied()
+}
+
+/// Seconds `task` has run: up to its finish, else up to `now`.
+pub fn elapsed(task: &Task, now: u64) -> Option<u64> {
+    let start = task.started_at?;
+    Some(task.finished_at.unwrap_or(now).saturating_sub(start))
+}
+
+/// Seconds until `task` is expected to finish, among `all` tasks. `None`
+/// when it is not running or pending, or nothing alike has run before.
+pub fn eta(task: &Task, all: &[Task], history: &[Timing], now: u64) -> Option<u64> {
+    if task.status.is_finished() {
+        return None;
+    }
+    let kids: Vec<&Task> = all.iter().filter(|t| t.parent_id.as_deref() == Some(task.id.as_str())).collect();
+    if !kids.is_empty() {
+        let left: Vec<u64> = kids.iter().filter_map(|k| eta(k, all, history, now)).collect();
+        return (!left.is_empty()).then(|| left.iter().sum());
+    }
+    let expected = task.estimate_secs.or_else(|| estimate(history, &task.title))?;
+    Some(expected.saturating_sub(elapsed(task, now).unwrap_or(0)))
+}
+
+/// `secs` as `45s`, `3m 05s` or `1h 02m`.
+pub fn format(secs: u64) -> String {
+    match secs {
+        s if s < 60 => format!("{}s", s),
+        s if s < 3600 => format!("{}m {:02}s", s / 60, s % 60),
+        s => format!("{}h {:02}m", s / 3600, s % 3600 / 60),
+    }
+}
+
+/// "elapsed 3m 05s, ETA 40s" for a running task, empty otherwise.
+pub fn describe(task: &Task, all: &[Task], history: &[Timing], now: u64) -> String {
+    if task.status != TaskStatus::Running {
+        return String::new();
+    }
+    let mut out = format!("elapsed {}", format(elapsed(task, now).unwrap_or(0)));
+    if let Some(left) = eta(task, all, history, now) {
+        out.push_str(&format!(", ETA {}", format(left)));
+    }
+    out
+}
+
+/// The time section of a run's report: the total, then each step with the
+/// estimate it started with.
+pub fn report(task: &Task, steps: &[Task], now: u64) -> String {
+    let mut out = format!("Time: {}", format(elapsed(task, now).unwrap_or(0)));
+    for step in steps {
+        let Some(secs) = elapsed(step, now) else { continue };
+        out.push_str(&format!("\n  {}. {} — {}", step.index + 1, step.title, format(secs)));
+        if let Some(expected) = step.estimate_secs {
+            out.push_str(&format!(" (estimated {})", format(expected)));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimates_come_from_alike_steps() -> anyhow::Result<()> {
+        let td = tempfile::tempdir()?;
+        let store = TimingStore::new(td.path());
+        for (title, secs) in [("run the unit tests", 100), ("run the unit tests again", 140), ("run unit tests for parser", 120), ("write the changelog", 10)] {
+            let mut task = Task::new(title, "");
+            task.started_at = Some(1_000);
+            task.finish(TaskStatus::Completed);
+            task.finished_at = Some(1_000 + secs);
+            store.record(&task)?;
+        }
+        // not completed: not recorded
+        store.record(&Task::new("run the unit tests", ""))?;
+        let history = store.load();
+        assert_eq!(history.len(), 4);
+        assert_eq!(estimate(&history, "Run the unit tests"), Some(120));
+        assert_eq!(estimate(&history, "deploy to staging"), None);
+
+        let mut parent = Task::new("release", "");
+        parent.status = TaskStatus::Running;
+        parent.started_at = Some(0);
+        let mut running = parent.subtask(0, "run the unit tests");
+        running.status = TaskStatus::Running;
+        running.started_at = Some(50);
+        running.estimate_secs = Some(100);
+        let pending = parent.subtask(1, "write the changelog");
+        let all = vec![parent.clone(), running.clone(), pending];
+        assert_eq!(eta(&running, &all, &history, 80), Some(70));
+        assert_eq!(eta(&parent, &all, &history, 80), Some(80));
+        assert_eq!(describe(&parent, &all, &history, 80), "elapsed 1m 20s, ETA 1m 20s");
+        assert_eq!(format(3725), "1h 02m");
+        Ok(())
+    }
+}
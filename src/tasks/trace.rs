@@ -0,0 +1,104 @@
+//! Replayable traces. While a task runs, every successful tool call is noted
+//! with the shell commands that redo it (`Tool::replay`) and the list is
+//! kept with the task's artifacts as `trace.jsonl`. `agent trace
+//! export-script <run-id>` turns it into a standalone POSIX shell script, with
+//! written files and patches embedded, so a workflow that worked can be
+//! repeated on another checkout without a model.
+
+use crate::tasks::Task;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Name of the trace in a task's artifact directory.
+pub const FILE: &str = "trace.jsonl";
+
+/// One successful tool call.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Step {
+    pub tool: String,
+    pub mutates: bool,
+    /// Shell commands that redo the call; `None` when the tool cannot say.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replay: Option<String>,
+}
+
+pub fn load(home: &Path, task_id: &str) -> anyhow::Result<Vec<Step>> {
+    let path = crate::tasks::artifacts::dir(home, task_id).join(FILE);
+    let raw = std::fs::read_to_string(&path).map_err(|e| anyhow::anyhow!("no trace for {} ({}): {}", task_id, path.display(), e))?;
+    raw.lines().filter(|l| !l.trim().is_empty()).map(|l| Ok(serde_json::from_str(l)?)).collect()
+}
+
+/// `text` quoted for a POSIX shell.
+pub fn quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', r"'\''"))
+}
+
+/// A quoted here-document with `text` as its body, behind `command`. The
+/// body is written byte for byte: a missing final newline is kept too.
+pub fn heredoc(command: &str, text: &str) -> String {
+    let mut delimiter = "SUPERCODE_EOF".to_string();
+    while text.lines().any(|l| l == delimiter) {
+        delimiter.push('_');
+    }
+    if text.ends_with('\n') || text.is_empty() {
+        format!("{} <<'{d}'\n{}{d}", command, text, d = delimiter)
+    } else {
+        // command substitution drops the newline the here-document adds
+        format!("printf '%s' \"$(cat <<'{d}'\n{}\n{d}\n)\" | {}", text, command, d = delimiter)
+    }
+}
+
+/// The script replaying `steps` of `task` in the directory given as its
+/// first argument (the current one by default).
+pub fn script(task: &Task, steps: &[Step]) -> String {
+    let replayed = steps.iter().filter(|s| s.replay.is_some()).count();
+    let mut out = format!(
+        "#!/bin/sh\n# Replays agent run {} without a model: {} step(s) from its trace.\n# Goal: {}\n\
+         # Usage: sh {}.sh [project-dir]\nset -eu\ncd \"${{1:-.}}\"\n",
+        task.id,
+        replayed,
+        task.title.replace('\n', " "),
+        &task.id[..task.id.len().min(8)]
+    );
+    for (i, step) in steps.iter().enumerate() {
+        match &step.replay {
+            Some(commands) => out.push_str(&format!("\n# {}. {}\n{}\n", i + 1, step.tool, commands.trim_end_matches('\n'))),
+            None if step.mutates => out.push_str(&format!("\n# {}. {}: cannot be replayed, skipped\n", i + 1, step.tool)),
+            None => {}
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::fs::WriteFileTool;
+    use crate::tools::policy::FileAccessPolicy;
+    use crate::tools::registry::Tool;
+    use crate::types::AgentInput;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_exported_script_rebuilds_the_files() {
+        let tool = WriteFileTool { policy: FileAccessPolicy::unrestricted(), recorder: None };
+        let step = |text: &str| Step { tool: "write_file".into(), mutates: true, replay: tool.replay(&AgentInput { text: text.into() }) };
+        let steps = vec![
+            step("src/it's.rs\nfn main() {}\n"),
+            step("notes.txt\nno newline\nSUPERCODE_EOF"),
+            Step { tool: "read_file".into(), mutates: false, replay: None },
+            Step { tool: "skill_tool".into(), mutates: true, replay: None },
+        ];
+        let task = Task::new("write two files", "");
+        let script = script(&task, &steps);
+        assert!(script.contains("# 4. skill_tool: cannot be replayed, skipped") && !script.contains("read_file"));
+
+        let work = tempfile::tempdir().unwrap();
+        let path = work.path().join("replay.sh");
+        std::fs::write(&path, &script).unwrap();
+        let status = std::process::Command::new("sh").arg(&path).arg(work.path()).status().unwrap();
+        assert!(status.success());
+        assert_eq!(std::fs::read_to_string(work.path().join("src/it's.rs")).unwrap(), "fn main() {}\n");
+        assert_eq!(std::fs::read_to_string(work.path().join("notes.txt")).unwrap(), "no newline\nSUPERCODE_EOF");
+    }
+}
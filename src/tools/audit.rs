@@ -0,0 +1,300 @@
+//! Tool execution audit log. Every tool call the registry runs appends a
+//! line to `<home>/tool_calls.jsonl`: the tool, a hash of its input,
+//! how long it took, whether it failed (and with which error code) and how
+//! many bytes it wrote. The input itself is never kept. Once the log
+//! reaches [`MAX_LOG_BYTES`] it moves to `tool_calls.1.jsonl`, replacing
+//! the previous one, so at most two logs' worth is kept. `agent tools stats`
+//! and the Agents view's Tools panel summarise the log; `[tools] disabled`
+//! switches tools off for every run.
+//!
+//! The project's `[policy]` in `.supercode.toml` is held here too: its
+//! disabled tools add to the user's, and a shell command must match both
+//! `tools.commands` and `policy.commands` when both are set.
+
+use crate::errors::ToolError;
+use crate::workspace::ProjectPolicy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{OnceLock, RwLock};
+
+/// `[tools]` in config.toml.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ToolsConfig {
+    /// Tools runs may not call; they are left out of the prompt as well.
+    pub disabled: Vec<String>,
+    /// Append each call to the audit log.
+    pub audit: bool,
+    /// Regexes a shell command must match to run; empty allows any.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub commands: Vec<String>,
+    /// Give agent runs the `shell` and `git` tools even when no `commands`
+    /// list limits them.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub shell: bool,
+}
+
+impl Default for ToolsConfig {
+    fn default() -> Self {
+        Self { disabled: vec![], audit: true, commands: vec![], shell: false }
+    }
+}
+
+/// One line of the audit log.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Call {
+    pub at: u64,
+    pub tool: String,
+    /// First 16 hex digits of the input's SHA-256.
+    pub args: String,
+    pub duration_ms: u64,
+    pub ok: bool,
+    /// Error code of a failed call (E000 when unclassified).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    #[serde(default)]
+    pub bytes_written: u64,
+    /// The chat turn, run or server request that made the call.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+}
+
+pub fn args_hash(input: &str) -> String {
+    hex::encode(&Sha256::digest(input.as_bytes())[..8])
+}
+
+/// Size at which the log is rotated.
+pub const MAX_LOG_BYTES: u64 = 4 * 1024 * 1024;
+
+pub fn path(home: &Path) -> PathBuf {
+    home.join("tool_calls.jsonl")
+}
+
+/// The log before the last rotation.
+fn previous(log: &Path) -> PathBuf {
+    log.with_extension("1.jsonl")
+}
+
+#[derive(Default)]
+struct State {
+    log: Option<PathBuf>,
+    disabled: Vec<String>,
+    commands: Option<Vec<Regex>>,
+    shell: bool,
+    project_disabled: Vec<String>,
+    project_commands: Option<Vec<Regex>>,
+}
+
+/// `None` for an empty list, which allows any command. Invalid patterns
+/// are dropped with a warning, so they allow nothing.
+fn compile(patterns: &[String]) -> Option<Vec<Regex>> {
+    if patterns.is_empty() {
+        return None;
+    }
+    let compiled = patterns
+        .iter()
+        .filter_map(|p| match Regex::new(&format!("^(?:{})$", p)) {
+            Ok(re) => Some(re),
+            Err(e) => {
+                log::warn!("ignoring command pattern {:?}: {}", p, e);
+                None
+            }
+        })
+        .collect();
+    Some(compiled)
+}
+
+/// Whether `command` passes every set of `layers`.
+fn allowed(layers: &[&Option<Vec<Regex>>], command: &str) -> bool {
+    layers.iter().all(|layer| layer.as_ref().is_none_or(|res| res.iter().any(|re| re.is_match(command))))
+}
+
+fn state() -> &'static RwLock<State> {
+    static STATE: OnceLock<RwLock<State>> = OnceLock::new();
+    STATE.get_or_init(Default::default)
+}
+
+/// Log to `home` (when `config.audit` is on) and disable `config.disabled`
+/// from now on.
+pub fn configure(config: &ToolsConfig, home: &Path) {
+    let mut state = state().write().unwrap_or_else(|e| e.into_inner());
+    state.log = config.audit.then(|| path(home));
+    state.disabled = config.disabled.clone();
+    state.commands = compile(&config.commands);
+    state.shell = config.shell;
+}
+
+/// Apply the project's policy from now on, on top of `[tools]`.
+pub fn configure_project(policy: &ProjectPolicy) {
+    let mut state = state().write().unwrap_or_else(|e| e.into_inner());
+    state.project_disabled = policy.disabled();
+    state.project_commands = compile(&policy.commands);
+}
+
+pub fn enabled(tool: &str) -> bool {
+    let state = state().read().unwrap_or_else(|e| e.into_inner());
+    !state.disabled.iter().chain(&state.project_disabled).any(|d| d == tool)
+}
+
+/// Whether agent runs get `shell` and `git`: `tools.shell` is on, or a
+/// `commands` list limits what they may run.
+pub fn shell_enabled() -> bool {
+    let state = state().read().unwrap_or_else(|e| e.into_inner());
+    state.shell || state.commands.is_some() || state.project_commands.is_some()
+}
+
+/// Refuse a shell command that `tools.commands` or the project's
+/// `policy.commands` does not allow.
+pub fn check_command(command: &str) -> Result<(), ToolError> {
+    let state = state().read().unwrap_or_else(|e| e.into_inner());
+    if allowed(&[&state.commands, &state.project_commands], command.trim()) {
+        Ok(())
+    } else {
+        Err(ToolError::CommandNotAllowed(command.trim().to_string()))
+    }
+}
+
+/// Append `call` to the configured log; failures are only logged.
+pub fn record(call: &Call) {
+    let Some(path) = state().read().unwrap_or_else(|e| e.into_inner()).log.clone() else { return };
+    if let Err(e) = append(&path, call, MAX_LOG_BYTES) {
+        log::debug!("could not write the tool audit log: {}", e);
+    }
+}
+
+/// Append `call`, first rotating the log when it has reached `max` bytes.
+fn append(path: &Path, call: &Call, max: u64) -> anyhow::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    if std::fs::metadata(path).is_ok_and(|m| m.len() >= max) {
+        std::fs::rename(path, previous(path))?;
+    }
+    let mut f = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(f, "{}", serde_json::to_string(call)?)?;
+    Ok(())
+}
+
+/// The calls in both logs, oldest first.
+pub fn load(home: &Path) -> Vec<Call> {
+    let log = path(home);
+    [previous(&log), log]
+        .iter()
+        .filter_map(|p| std::fs::read_to_string(p).ok())
+        .flat_map(|s| s.lines().filter_map(|l| serde_json::from_str(l).ok()).collect::<Vec<Call>>())
+        .collect()
+}
+
+/// Totals for one tool.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ToolStats {
+    pub tool: String,
+    pub calls: u64,
+    pub failures: u64,
+    pub total_ms: u64,
+    pub max_ms: u64,
+    pub bytes_written: u64,
+}
+
+impl ToolStats {
+    pub fn avg_ms(&self) -> u64 {
+        self.total_ms / self.calls.max(1)
+    }
+
+    pub fn failure_rate(&self) -> f64 {
+        self.failures as f64 / self.calls.max(1) as f64
+    }
+}
+
+/// Per-tool totals, slowest on average first.
+pub fn stats(calls: &[Call]) -> Vec<ToolStats> {
+    let mut by_tool: BTreeMap<&str, ToolStats> = BTreeMap::new();
+    for c in calls {
+        let s = by_tool.entry(&c.tool).or_insert_with(|| ToolStats { tool: c.tool.clone(), ..Default::default() });
+        s.calls += 1;
+        s.failures += u64::from(!c.ok);
+        s.total_ms += c.duration_ms;
+        s.max_ms = s.max_ms.max(c.duration_ms);
+        s.bytes_written += c.bytes_written;
+    }
+    let mut out: Vec<ToolStats> = by_tool.into_values().collect();
+    out.sort_by(|a, b| b.avg_ms().cmp(&a.avg_ms()).then(a.tool.cmp(&b.tool)));
+    out
+}
+
+/// `agent tools stats`: slowest tools, then the ones failing most.
+pub fn summary(calls: &[Call]) -> String {
+    if calls.is_empty() {
+        return "no tool calls recorded yet\n".into();
+    }
+    let stats = stats(calls);
+    let mut out = format!("{} tool call(s)\n\nslowest (avg / max):\n", calls.len());
+    for s in &stats {
+        out.push_str(&format!("  {:<20} {:>7}ms {:>7}ms  {} call(s), {} bytes written\n", s.tool, s.avg_ms(), s.max_ms, s.calls, s.bytes_written));
+    }
+    let mut failing: Vec<&ToolStats> = stats.iter().filter(|s| s.failures > 0).collect();
+    failing.sort_by(|a, b| b.failure_rate().total_cmp(&a.failure_rate()).then(a.tool.cmp(&b.tool)));
+    if !failing.is_empty() {
+        out.push_str("\nmost failing:\n");
+        for s in failing {
+            out.push_str(&format!("  {:<20} {:>5.1}%  {} of {}\n", s.tool, s.failure_rate() * 100.0, s.failures, s.calls));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats_rank_slowest_and_failing_tools() {
+        let home = tempfile::tempdir().unwrap();
+        let call = |tool: &str, ms: u64, ok: bool| Call { at: 1, tool: tool.into(), args: args_hash("x"), duration_ms: ms, ok, code: None, bytes_written: 0, request_id: None };
+        for c in [call("read_file", 4, true), call("write_file", 30, true), call("write_file", 10, false), call("read_file", 2, false)] {
+            append(&path(home.path()), &c, MAX_LOG_BYTES).unwrap();
+        }
+        let calls = load(home.path());
+        assert_eq!(calls.len(), 4);
+        assert_eq!(calls[0].args.len(), 16);
+
+        let stats = stats(&calls);
+        assert_eq!(stats[0], ToolStats { tool: "write_file".into(), calls: 2, failures: 1, total_ms: 40, max_ms: 30, bytes_written: 0 });
+        assert_eq!(stats[1].avg_ms(), 3);
+        assert!(summary(&calls).contains("most failing:\n  read_file"));
+    }
+
+    #[test]
+    fn test_log_is_rotated_at_its_cap() {
+        let home = tempfile::tempdir().unwrap();
+        let log = path(home.path());
+        for ms in 0..10 {
+            let c = Call { at: 1, tool: "echo".into(), args: args_hash("x"), duration_ms: ms, ok: true, code: None, bytes_written: 0, request_id: None };
+            append(&log, &c, 300).unwrap();
+        }
+        // each line is about 100 bytes: older lines were rotated away twice
+        assert!(std::fs::metadata(&log).unwrap().len() < 400);
+        let kept: Vec<u64> = load(home.path()).iter().map(|c| c.duration_ms).collect();
+        assert!(kept.len() < 10 && kept.ends_with(&[9]), "{:?}", kept);
+        assert!(kept.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn test_commands_must_pass_user_and_project_patterns() {
+        let user = compile(&["cargo .*".into(), "ls( .*)?".into()]);
+        let project = compile(&["cargo (build|test)( .*)?".into(), "ls".into(), "(".into()]);
+        assert!(allowed(&[&user, &project], "cargo test -q"));
+        assert!(!allowed(&[&user, &project], "cargo publish"));
+        assert!(!allowed(&[&user, &project], "ls -la"));
+        assert!(!allowed(&[&user, &project], "cargo test; rm -rf /"));
+        assert!(allowed(&[&None, &None], "rm -rf target"));
+        assert!(!allowed(&[&compile(&["(".into()])], "ls"));
+
+        let policy: ProjectPolicy = toml::from_str("[tools]\nshell = false\nread_file = true\n").unwrap();
+        assert_eq!(policy.disabled(), vec!["shell"]);
+    }
+}
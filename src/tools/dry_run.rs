@@ -0,0 +1,150 @@
+//! Dry runs. With `agent run --dry-run` (or `/dryrun on` in the TUI) every
+//! tool that changes something outside the run (`Tool::mutates`) is swapped
+//! for a `Recorder` that notes what it would have done and tells the model
+//! it did. The run's report ends with the proposed actions, so the user can
+//! check them; approving them replays the recorded calls against the real
+//! tools (`Proposals::apply`) rather than planning the goal again.
+
+use crate::tools::registry::{Tool, ToolOutput, ToolRegistry, ToolResult};
+use crate::types::AgentInput;
+use parking_lot::Mutex;
+use serde_json::Value;
+use std::sync::Arc;
+
+/// An action a dry run held back.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Proposal {
+    pub tool: String,
+    /// What the call would have done, e.g. `write 120 bytes to src/lib.rs`.
+    pub action: String,
+    /// The call's input, replayed by `Proposals::apply`.
+    pub input: String,
+}
+
+/// Actions proposed during one run. Clones share the same list.
+#[derive(Clone, Default)]
+pub struct Proposals(Arc<Mutex<Vec<Proposal>>>);
+
+impl Proposals {
+    pub fn list(&self) -> Vec<Proposal> {
+        self.0.lock().clone()
+    }
+
+    /// Markdown section appended to the run's report.
+    pub fn report(&self) -> String {
+        let list = self.list();
+        if list.is_empty() {
+            return "## Proposed actions\n\nNone: the run did not try to change anything.\n".into();
+        }
+        let mut out = format!("## Proposed actions ({})\n\n", list.len());
+        for (i, p) in list.iter().enumerate() {
+            out.push_str(&format!("{}. [{}] {}\n", i + 1, p.tool, p.action));
+        }
+        out
+    }
+
+    /// Run each proposed call, in order, on the real tools in `reg`. Stops at
+    /// the first failure, since later calls usually build on earlier ones.
+    pub fn apply(&self, reg: &ToolRegistry) -> anyhow::Result<String> {
+        let list = self.list();
+        let mut out = format!("## Applied actions ({})\n\n", list.len());
+        for (i, p) in list.iter().enumerate() {
+            if let Err(e) = reg.run(&p.tool, AgentInput { text: p.input.clone() }) {
+                anyhow::bail!("{}applying {}. [{}] {} failed: {:#}", out, i + 1, p.tool, p.action, e);
+            }
+            out.push_str(&format!("{}. [{}] {}\n", i + 1, p.tool, p.action));
+        }
+        Ok(out)
+    }
+}
+
+/// Stands in for a mutating tool during a dry run.
+pub struct Recorder {
+    inner: Arc<dyn Tool>,
+    proposals: Proposals,
+}
+
+impl Tool for Recorder {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn description(&self) -> &'static str {
+        self.inner.description()
+    }
+
+    fn input_schema(&self) -> Value {
+        self.inner.input_schema()
+    }
+
+    fn mutates(&self) -> bool {
+        true
+    }
+
+    fn describe_call(&self, input: &AgentInput) -> String {
+        self.inner.describe_call(input)
+    }
+
+    /// What the real tool would do, so a dry run's trace exports the
+    /// proposed actions as a script.
+    fn replay(&self, input: &AgentInput) -> Option<String> {
+        self.inner.replay(input)
+    }
+
+    fn run(&self, input: AgentInput) -> ToolResult {
+        let action = self.inner.describe_call(&input);
+        self.proposals.0.lock().push(Proposal { tool: self.name().to_string(), action: action.clone(), input: input.text });
+        Ok(ToolOutput { text: format!("dry run: would {} (not done)", action), ..Default::default() })
+    }
+}
+
+/// `tool` itself, or a recorder in its place when it mutates.
+pub fn wrap(tool: Arc<dyn Tool>, proposals: &Proposals) -> Arc<dyn Tool> {
+    if tool.mutates() {
+        Arc::new(Recorder { inner: tool, proposals: proposals.clone() })
+    } else {
+        tool
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::fs::{ReadFileTool, WriteFileTool};
+    use crate::tools::policy::FileAccessPolicy;
+
+    #[test]
+    fn test_mutating_tools_are_recorded_not_run() {
+        let td = tempfile::tempdir().unwrap();
+        let policy = FileAccessPolicy::new(td.path(), &crate::config::FilesConfig::default());
+        let proposals = Proposals::default();
+        let write = wrap(Arc::new(WriteFileTool { policy: policy.clone(), recorder: None }), &proposals);
+        let read = wrap(Arc::new(ReadFileTool { policy }), &proposals);
+
+        let out = write.run(AgentInput { text: "notes.txt\nhello".into() }).unwrap();
+        assert_eq!(out.text, "dry run: would write 5 bytes to notes.txt (not done)");
+        assert!(!td.path().join("notes.txt").exists());
+        assert!(read.run(AgentInput { text: "notes.txt".into() }).is_err());
+        assert_eq!(proposals.report(), "## Proposed actions (1)\n\n1. [write_file] write 5 bytes to notes.txt\n");
+    }
+
+    #[test]
+    fn test_apply_replays_the_recorded_calls() {
+        let td = tempfile::tempdir().unwrap();
+        let policy = FileAccessPolicy::new(td.path(), &crate::config::FilesConfig::default());
+        let proposals = Proposals::default();
+        let write: Arc<dyn Tool> = Arc::new(WriteFileTool { policy, recorder: None });
+        let recorded = wrap(Arc::clone(&write), &proposals);
+        recorded.run(AgentInput { text: "a.txt\none".into() }).unwrap();
+        recorded.run(AgentInput { text: "b.txt\ntwo".into() }).unwrap();
+        recorded.run(AgentInput { text: "../c.txt\nthree".into() }).unwrap();
+        assert!(!td.path().join("a.txt").exists());
+
+        let reg = ToolRegistry::new();
+        reg.register(write);
+        let err = proposals.apply(&reg).unwrap_err().to_string();
+        assert!(err.contains("2. [write_file] write 3 bytes to b.txt\napplying 3."), "{}", err);
+        assert_eq!(std::fs::read_to_string(td.path().join("a.txt")).unwrap(), "one");
+        assert_eq!(std::fs::read_to_string(td.path().join("b.txt")).unwrap(), "two");
+    }
+}
@@ -0,0 +1,98 @@
+use crate::tasks::artifacts::ArtifactRecorder;
+use crate::tasks::trace::{heredoc, quote};
+use crate::tools::policy::{Access, FileAccessPolicy};
+use crate::tools::registry::{Tool, ToolOutput, ToolResult};
+use crate::types::AgentInput;
+use std::path::Path;
+
+/// Reads a workspace file. Input is the path.
+pub struct ReadFileTool {
+    pub policy: FileAccessPolicy,
+}
+
+impl Tool for ReadFileTool {
+    fn name(&self) -> &'static str {
+        "read_file"
+    }
+
+    fn description(&self) -> &'static str {
+        "Read a file from the workspace (input: `path`)"
+    }
+
+    fn describe_call(&self, input: &AgentInput) -> String {
+        format!("read {}", input.text.trim())
+    }
+
+    fn run(&self, input: AgentInput) -> ToolResult {
+        let path = self.policy.check(Path::new(input.text.trim()), Access::Read)?;
+        let text = std::fs::read_to_string(&path)?;
+        Ok(ToolOutput { text, ..Default::default() })
+    }
+}
+
+/// Writes a workspace file. Input is the path, a newline, then the contents.
+pub struct WriteFileTool {
+    pub policy: FileAccessPolicy,
+    /// Notified of every written file, for task artifacts.
+    pub recorder: Option<ArtifactRecorder>,
+}
+
+impl Tool for WriteFileTool {
+    fn name(&self) -> &'static str {
+        "write_file"
+    }
+
+    fn description(&self) -> &'static str {
+        "Write a file in the workspace (input: `path` on the first line, contents after)"
+    }
+
+    fn mutates(&self) -> bool {
+        true
+    }
+
+    fn describe_call(&self, input: &AgentInput) -> String {
+        let (path, contents) = input.text.split_once('\n').unwrap_or((input.text.as_str(), ""));
+        format!("write {} bytes to {}", contents.len(), path.trim())
+    }
+
+    fn replay(&self, input: &AgentInput) -> Option<String> {
+        let (path, contents) = input.text.split_once('\n').unwrap_or((input.text.as_str(), ""));
+        let path = quote(path.trim());
+        Some(format!("mkdir -p \"$(dirname {})\"\n{}", path, heredoc(&format!("cat > {}", path), contents)))
+    }
+
+    fn run(&self, input: AgentInput) -> ToolResult {
+        let (path, contents) = input.text.split_once('\n').unwrap_or((input.text.as_str(), ""));
+        let path = self.policy.check(Path::new(path.trim()), Access::Write)?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, contents)?;
+        if let Some(r) = &self.recorder {
+            r.file_written(&path);
+        }
+        Ok(ToolOutput { text: format!("wrote {} bytes to {}", contents.len(), path.display()), bytes_written: contents.len() as u64 })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::FilesConfig;
+
+    #[test]
+    fn test_file_tools_respect_policy() -> anyhow::Result<()> {
+        let td = tempfile::tempdir()?;
+        let policy = FileAccessPolicy::new(td.path(), &FilesConfig::default());
+        let write = WriteFileTool { policy: policy.clone(), recorder: None };
+        let read = ReadFileTool { policy };
+
+        write.run(AgentInput { text: "notes/a.txt\nhello".into() })?;
+        assert_eq!(read.run(AgentInput { text: "notes/a.txt".into() })?.text, "hello");
+
+        let err = write.run(AgentInput { text: "secrets/token\nx".into() }).unwrap_err();
+        assert!(err.to_string().contains("files.deny"));
+        assert!(write.run(AgentInput { text: "../escape.txt\nx".into() }).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,77 @@
+//! Git. `git` runs one git command in the workspace root; its input is the
+//! arguments, one per line, so a commit message needs no quoting. Agent runs
+//! with a workspace root get it alongside `shell`, and a dry run records it
+//! like any other mutating tool. Commands must pass `tools.commands` as
+//! `git <args>`, and paths among the arguments the file policy.
+
+use crate::tools::policy::{Access, FileAccessPolicy};
+use crate::tools::registry::{Tool, ToolOutput, ToolResult};
+use crate::types::AgentInput;
+use std::path::PathBuf;
+
+/// Lines of output the tool returns.
+const TAIL_LINES: usize = 100;
+
+/// Runs `git` with the input's lines as arguments.
+pub struct GitTool {
+    pub root: PathBuf,
+    pub policy: FileAccessPolicy,
+}
+
+fn args(input: &AgentInput) -> Vec<&str> {
+    input.text.lines().map(str::trim).filter(|a| !a.is_empty()).collect()
+}
+
+impl Tool for GitTool {
+    fn name(&self) -> &'static str {
+        "git"
+    }
+
+    fn description(&self) -> &'static str {
+        "Run a git command in the workspace root (input: the arguments, one per line, e.g. `commit\\n-m\\nFix typo`)"
+    }
+
+    fn mutates(&self) -> bool {
+        true
+    }
+
+    fn describe_call(&self, input: &AgentInput) -> String {
+        format!("run `git {}`", args(input).join(" "))
+    }
+
+    fn replay(&self, input: &AgentInput) -> Option<String> {
+        let quoted: Vec<String> = args(input).into_iter().map(crate::tasks::trace::quote).collect();
+        Some(format!("git {}", quoted.join(" ")))
+    }
+
+    fn run(&self, input: AgentInput) -> ToolResult {
+        let args = args(&input);
+        if args.is_empty() {
+            anyhow::bail!("git needs at least one argument");
+        }
+        crate::tools::audit::check_command(&format!("git {}", args.join(" ")))?;
+        self.policy.check_words(args.iter().map(|a| (*a, Access::Read)))?;
+        let output = std::process::Command::new("git").args(&args).current_dir(&self.root).output()?;
+        let text = format!("{}{}", String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr));
+        let lines: Vec<&str> = text.lines().collect();
+        let mut tail = lines[lines.len().saturating_sub(TAIL_LINES)..].join("\n");
+        if !output.status.success() {
+            tail.push_str(&format!("\n[{}]", output.status));
+        }
+        Ok(ToolOutput { text: tail, ..Default::default() })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_arguments_are_one_per_line() {
+        let tool = GitTool { root: PathBuf::from("."), policy: FileAccessPolicy::unrestricted() };
+        let input = AgentInput { text: "commit\n-m\nit's done\n".into() };
+        assert_eq!(tool.describe_call(&input), "run `git commit -m it's done`");
+        assert_eq!(tool.replay(&input).unwrap(), r"git 'commit' '-m' 'it'\''s done'");
+        assert!(tool.run(AgentInput { text: " \n".into() }).is_err());
+    }
+}
@@ -1 +1,12 @@
 pub mod registry;
+pub mod shared_vars;
+pub mod policy;
+pub mod fs;
+pub mod schema;
+pub mod parallel;
+pub mod audit;
+pub mod dry_run;
+pub mod build;
+pub mod patch;
+pub mod shell;
+pub mod git;
@@ -0,0 +1,168 @@
+//! Running several tool calls from one model turn. Independent calls run
+//! concurrently, at most `limit` at a time; a call whose input refers to an
+//! earlier call's output with `{{output.N}}` (N is the 0-based position in
+//! the batch) waits for it and gets the output substituted, escaped when
+//! the input is JSON. Results come back in call order whatever order they
+//! finished in.
+
+use crate::errors::ToolError;
+use crate::tools::registry::{ToolCall, ToolRegistry, ToolResult};
+use regex::Regex;
+use std::sync::{Arc, OnceLock};
+use tokio::sync::Semaphore;
+
+/// Default bound on concurrently running tool calls.
+pub const DEFAULT_LIMIT: usize = 4;
+
+fn reference() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"\{\{output\.(\d+)\}\}").expect("valid regex"))
+}
+
+/// Indices of the earlier calls whose outputs `call` refers to.
+pub fn dependencies(index: usize, call: &ToolCall) -> Vec<usize> {
+    let mut deps: Vec<usize> = reference()
+        .captures_iter(&call.input.text)
+        .filter_map(|c| c[1].parse().ok())
+        .filter(|d| *d < index)
+        .collect();
+    deps.sort_unstable();
+    deps.dedup();
+    deps
+}
+
+/// Group calls into waves: every call runs in the first wave after all the
+/// calls it depends on.
+pub fn waves(calls: &[ToolCall]) -> Vec<Vec<usize>> {
+    let mut wave_of = vec![0usize; calls.len()];
+    for (i, call) in calls.iter().enumerate() {
+        wave_of[i] = dependencies(i, call).iter().map(|d| wave_of[*d] + 1).max().unwrap_or(0);
+    }
+    let mut waves = vec![vec![]; wave_of.iter().max().map_or(0, |w| w + 1)];
+    for (i, w) in wave_of.iter().enumerate() {
+        waves[*w].push(i);
+    }
+    waves
+}
+
+/// Whether each call waits on another one.
+pub fn sequential(calls: &[ToolCall]) -> Vec<bool> {
+    calls.iter().enumerate().map(|(i, c)| !dependencies(i, c).is_empty()).collect()
+}
+
+/// `text` with each `{{output.N}}` replaced by that output. In a JSON input
+/// the output is escaped as string contents, so quotes and newlines in it
+/// cannot break the input.
+fn substitute(text: &str, json: bool, outputs: &[Option<String>]) -> String {
+    reference()
+        .replace_all(text, |c: &regex::Captures| match c[1].parse::<usize>().ok().and_then(|d| outputs.get(d).cloned().flatten()) {
+            Some(out) if json => {
+                let quoted = serde_json::Value::String(out).to_string();
+                quoted[1..quoted.len() - 1].to_string()
+            }
+            Some(out) => out,
+            None => c[0].to_string(),
+        })
+        .into_owned()
+}
+
+/// Run `calls` against `tools`, returning one result per call in order.
+pub async fn run(tools: &ToolRegistry, calls: Vec<ToolCall>, limit: usize) -> Vec<ToolResult> {
+    let semaphore = Arc::new(Semaphore::new(limit.max(1)));
+    let mut results: Vec<Option<ToolResult>> = calls.iter().map(|_| None).collect();
+    for wave in waves(&calls) {
+        let mut handles = vec![];
+        for i in wave {
+            let mut call = calls[i].clone();
+            let outputs: Vec<Option<String>> = results.iter().map(|r| r.as_ref().and_then(|r| r.as_ref().ok()).map(|o| o.text.clone())).collect();
+            let failed = dependencies(i, &call).into_iter().find(|d| outputs[*d].is_none());
+            call.input.text = substitute(&call.input.text, call.json, &outputs);
+            let (tools, semaphore, id) = (tools.clone(), Arc::clone(&semaphore), crate::request_id::current());
+            handles.push((i, tokio::spawn(async move {
+                if let Some(d) = failed {
+                    return Err(ToolError::DependencyFailed(d).into());
+                }
+                let _permit = semaphore.acquire_owned().await?;
+                tokio::task::spawn_blocking(move || crate::request_id::enter(id, || tools.run(&call.tool, call.input))).await?
+            })));
+        }
+        for (i, handle) in handles {
+            results[i] = Some(handle.await.unwrap_or_else(|e| Err(ToolError::Crashed(e.to_string()).into())));
+        }
+    }
+    results.into_iter().map(|r| r.unwrap_or_else(|| Err(anyhow::anyhow!("not run")))).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::registry::{Tool, ToolOutput};
+    use crate::types::AgentInput;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    /// Upper-cases its input. The first calls wait (up to a few seconds)
+    /// until `together` of them run at once, so overlap shows as `peak`
+    /// without relying on timing.
+    #[derive(Default)]
+    struct Slow {
+        together: usize,
+        running: AtomicUsize,
+        peak: AtomicUsize,
+        finished: parking_lot::Mutex<Vec<String>>,
+    }
+    impl Tool for Slow {
+        fn name(&self) -> &'static str {
+            "slow"
+        }
+        fn description(&self) -> &'static str {
+            "sleeps, then upper-cases its input"
+        }
+        fn run(&self, input: AgentInput) -> ToolResult {
+            let now = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak.fetch_max(now, Ordering::SeqCst);
+            let deadline = std::time::Instant::now() + Duration::from_secs(5);
+            while self.peak.load(Ordering::SeqCst) < self.together && std::time::Instant::now() < deadline {
+                std::thread::sleep(Duration::from_millis(5));
+            }
+            self.running.fetch_sub(1, Ordering::SeqCst);
+            self.finished.lock().push(input.text.clone());
+            Ok(ToolOutput { text: input.text.to_uppercase(), ..Default::default() })
+        }
+    }
+
+    fn call(input: &str) -> ToolCall {
+        ToolCall { tool: "slow".into(), input: AgentInput { text: input.into() }, json: false }
+    }
+
+    #[tokio::test]
+    async fn test_independent_calls_overlap_and_dependent_ones_wait() {
+        let tools = ToolRegistry::new();
+        let slow = Arc::new(Slow { together: 3, ..Default::default() });
+        tools.register(slow.clone());
+        let calls = vec![call("a"), call("b"), call("c"), call("from {{output.0}} and {{output.2}}")];
+        assert_eq!(waves(&calls), vec![vec![0, 1, 2], vec![3]]);
+        assert_eq!(sequential(&calls), vec![false, false, false, true]);
+
+        let results = run(&tools, calls, 4).await;
+        let texts: Vec<String> = results.into_iter().map(|r| r.map(|o| o.text).unwrap_or_else(|e| e.to_string())).collect();
+        assert_eq!(texts, ["A", "B", "C", "FROM A AND C"]);
+        // the first wave overlapped; the dependent call ran after all of it
+        assert_eq!(slow.peak.load(Ordering::SeqCst), 3);
+        assert_eq!(slow.finished.lock().last().map(String::as_str), Some("from A and C"));
+
+        let missing = ToolCall { tool: "missing".into(), input: AgentInput { text: String::new() }, json: false };
+        let results = run(&tools, vec![missing, call("{{output.0}}")], 2).await;
+        assert!(results[1].as_ref().is_err_and(|e| e.to_string().contains("call 0")));
+    }
+
+    #[test]
+    fn test_outputs_are_escaped_in_json_inputs() {
+        let outputs = [Some("say \"hi\"\nbye".to_string())];
+        let input = r#"{"path": "a.txt", "text": "{{output.0}}"}"#;
+        let json: serde_json::Value = serde_json::from_str(&substitute(input, true, &outputs)).expect("still valid JSON");
+        assert_eq!(json["text"], "say \"hi\"\nbye");
+        assert_eq!(substitute("> {{output.0}}", false, &outputs), "> say \"hi\"\nbye");
+        assert_eq!(substitute("{{output.3}}", false, &outputs), "{{output.3}}");
+    }
+}
@@ -0,0 +1,213 @@
+//! Unified diffs as edits. `apply_patch` takes a diff covering any number of
+//! files (`--- a/…` / `+++ b/…` headers, `@@` hunks), checks every file
+//! against the file policy, and only writes once every hunk applies, so a
+//! patch lands whole or not at all. Hunks are matched on their context and
+//! removed lines, near the line the header names but anywhere after the
+//! previous hunk, since models rarely get line numbers exactly right.
+
+use crate::tasks::artifacts::ArtifactRecorder;
+use crate::tools::policy::{Access, FileAccessPolicy};
+use crate::tools::registry::{Tool, ToolOutput, ToolResult};
+use crate::types::AgentInput;
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hunk {
+    /// 1-based line the hunk claims to start at in the old file.
+    pub old_start: usize,
+    /// Each line with its marker: ' ', '-' or '+'.
+    pub lines: Vec<(char, String)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilePatch {
+    pub path: String,
+    /// `--- /dev/null`: the file is created.
+    pub created: bool,
+    pub hunks: Vec<Hunk>,
+}
+
+fn strip_prefix(header: &str) -> String {
+    let path = header.split('\t').next().unwrap_or_default().trim();
+    path.strip_prefix("a/").or_else(|| path.strip_prefix("b/")).unwrap_or(path).to_string()
+}
+
+/// `start[,count]` of a hunk header; the count defaults to 1.
+fn range(spec: &str) -> Option<(usize, usize)> {
+    match spec.split_once(',') {
+        Some((start, count)) => Some((start.parse().ok()?, count.parse().ok()?)),
+        None => Some((spec.parse().ok()?, 1)),
+    }
+}
+
+/// The file patches in `diff`, in order. Lines within the counts of a hunk's
+/// `@@` header belong to the hunk, so a removed line that itself starts with
+/// `-- ` is not taken for a file header.
+pub fn parse(diff: &str) -> anyhow::Result<Vec<FilePatch>> {
+    let mut patches: Vec<FilePatch> = vec![];
+    let mut old: Option<String> = None;
+    // old and new lines the current hunk has yet to cover
+    let mut left = (0usize, 0usize);
+    for line in diff.lines() {
+        let in_hunk = left != (0, 0);
+        if let Some(hunk) = patches.last_mut().and_then(|p| p.hunks.last_mut()).filter(|_| in_hunk) {
+            let (marker, text) = match line.chars().next() {
+                // `\ No newline at end of file`
+                Some('\\') => continue,
+                Some(c @ (' ' | '-' | '+')) => (c, &line[1..]),
+                // blank context lines often lose their leading space
+                _ => (' ', line),
+            };
+            match marker {
+                '-' => left.0 = left.0.saturating_sub(1),
+                '+' => left.1 = left.1.saturating_sub(1),
+                _ => left = (left.0.saturating_sub(1), left.1.saturating_sub(1)),
+            }
+            hunk.lines.push((marker, text.to_string()));
+        } else if let Some(rest) = line.strip_prefix("--- ") {
+            old = Some(rest.to_string());
+        } else if let Some(rest) = line.strip_prefix("+++ ") {
+            let created = old.take().map(|o| o.trim() == "/dev/null").unwrap_or(false);
+            patches.push(FilePatch { path: strip_prefix(rest), created, hunks: vec![] });
+        } else if let Some(rest) = line.strip_prefix("@@ -") {
+            let Some(patch) = patches.last_mut() else { anyhow::bail!("hunk before any file header") };
+            let mut specs = rest.split_whitespace();
+            let old_range = specs.next().and_then(range);
+            let new_range = specs.next().and_then(|s| s.strip_prefix('+')).and_then(range);
+            let (Some((old_start, old_count)), Some((_, new_count))) = (old_range, new_range) else {
+                anyhow::bail!("bad hunk header: {}", line)
+            };
+            left = (old_count, new_count);
+            patch.hunks.push(Hunk { old_start, lines: vec![] });
+        } else if let Some(hunk) = patches.last_mut().and_then(|p| p.hunks.last_mut()) {
+            match line.chars().next() {
+                Some(c @ (' ' | '-' | '+')) => hunk.lines.push((c, line[1..].to_string())),
+                // blank context lines often lose their leading space
+                None => hunk.lines.push((' ', String::new())),
+                _ => {}
+            }
+        }
+    }
+    if patches.is_empty() {
+        anyhow::bail!("no file headers (`--- a/path` / `+++ b/path`) in the patch");
+    }
+    Ok(patches)
+}
+
+/// `source` with `hunks` applied.
+pub fn apply_hunks(source: &str, hunks: &[Hunk]) -> anyhow::Result<String> {
+    let mut lines: Vec<String> = source.lines().map(str::to_string).collect();
+    // lines added or removed by earlier hunks, and where matching may resume
+    let mut shift: isize = 0;
+    let mut from = 0;
+    for (n, hunk) in hunks.iter().enumerate() {
+        let old: Vec<&str> = hunk.lines.iter().filter(|(c, _)| *c != '+').map(|(_, l)| l.as_str()).collect();
+        let new: Vec<String> = hunk.lines.iter().filter(|(c, _)| *c != '-').map(|(_, l)| l.clone()).collect();
+        let matches_at = |at: usize| at + old.len() <= lines.len() && lines[at..at + old.len()].iter().zip(&old).all(|(a, b)| a.trim_end() == b.trim_end());
+        let hint = ((hunk.old_start.max(1) - 1) as isize + shift).max(from as isize) as usize;
+        let at = if old.is_empty() {
+            Some(hint.min(lines.len()))
+        } else {
+            // nearest match to the stated line, not before the previous hunk
+            (0..=lines.len()).flat_map(|d| [hint.checked_add(d), hint.checked_sub(d).filter(|&a| a >= from && d > 0)]).flatten().find(|&a| matches_at(a))
+        };
+        let Some(at) = at else { anyhow::bail!("hunk {} (line {}) does not match the file", n + 1, hunk.old_start) };
+        lines.splice(at..at + old.len(), new.iter().cloned());
+        shift += new.len() as isize - old.len() as isize;
+        from = at + new.len();
+    }
+    let mut out = lines.join("\n");
+    if !lines.is_empty() && (source.ends_with('\n') || source.is_empty()) {
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// Applies a unified diff to workspace files. Input is the diff.
+pub struct PatchTool {
+    pub policy: FileAccessPolicy,
+    pub recorder: Option<ArtifactRecorder>,
+}
+
+impl Tool for PatchTool {
+    fn name(&self) -> &'static str {
+        "apply_patch"
+    }
+
+    fn description(&self) -> &'static str {
+        "Apply a unified diff to workspace files (input: the diff, with `--- a/path` and `+++ b/path` headers)"
+    }
+
+    fn mutates(&self) -> bool {
+        true
+    }
+
+    fn describe_call(&self, input: &AgentInput) -> String {
+        match parse(&input.text) {
+            Ok(patches) => format!("patch {}", patches.iter().map(|p| p.path.as_str()).collect::<Vec<_>>().join(", ")),
+            Err(_) => "apply an unreadable patch".into(),
+        }
+    }
+
+    /// The diff through `patch`, which also tolerates shifted line numbers.
+    fn replay(&self, input: &AgentInput) -> Option<String> {
+        Some(crate::tasks::trace::heredoc("patch -p1 --forward --no-backup-if-mismatch", &input.text))
+    }
+
+    fn run(&self, input: AgentInput) -> ToolResult {
+        let mut writes = vec![];
+        for patch in parse(&input.text)? {
+            let path = self.policy.check(Path::new(&patch.path), Access::Write)?;
+            let source = if patch.created { String::new() } else { std::fs::read_to_string(&path)? };
+            let patched = apply_hunks(&source, &patch.hunks).map_err(|e| anyhow::anyhow!("{}: {}", patch.path, e))?;
+            writes.push((patch.path, path, patched));
+        }
+        let mut bytes = 0;
+        for (_, path, text) in &writes {
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            std::fs::write(path, text)?;
+            bytes += text.len() as u64;
+            if let Some(r) = &self.recorder {
+                r.file_written(path);
+            }
+        }
+        let names: Vec<&str> = writes.iter().map(|(name, _, _)| name.as_str()).collect();
+        Ok(ToolOutput { text: format!("patched {} file(s): {}", names.len(), names.join(", ")), bytes_written: bytes })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_patches_apply_whole_or_not_at_all() {
+        let td = tempfile::tempdir().unwrap();
+        std::fs::write(td.path().join("a.rs"), "fn a() {\n    1\n}\n\nfn b() {\n    2\n}\n").unwrap();
+        let tool = PatchTool { policy: FileAccessPolicy::new(td.path(), &Default::default()), recorder: None };
+
+        // line numbers off by two, and a new file
+        let diff = "--- a/a.rs\n+++ b/a.rs\n@@ -3,3 +3,3 @@\n fn b() {\n-    2\n+    3\n }\n--- /dev/null\n+++ b/new.rs\n@@ -0,0 +1 @@\n+fn new() {}\n";
+        let out = tool.run(AgentInput { text: diff.into() }).unwrap();
+        assert_eq!(out.text, "patched 2 file(s): a.rs, new.rs");
+        assert_eq!(std::fs::read_to_string(td.path().join("a.rs")).unwrap(), "fn a() {\n    1\n}\n\nfn b() {\n    3\n}\n");
+        assert_eq!(std::fs::read_to_string(td.path().join("new.rs")).unwrap(), "fn new() {}\n");
+
+        // the second file's hunk does not match, so a.rs is left alone too
+        let bad = "--- a/a.rs\n+++ b/a.rs\n@@ -1 +1 @@\n-fn a() {\n+fn aa() {\n--- a/new.rs\n+++ b/new.rs\n@@ -1 +1 @@\n-fn old() {}\n+fn x() {}\n";
+        assert!(tool.run(AgentInput { text: bad.into() }).unwrap_err().to_string().contains("new.rs: hunk 1"));
+        assert!(std::fs::read_to_string(td.path().join("a.rs")).unwrap().starts_with("fn a()"));
+        assert!(tool.run(AgentInput { text: "--- a/.git/config\n+++ b/.git/config\n@@ -1 +1 @@\n+x\n".into() }).is_err());
+    }
+
+    #[test]
+    fn test_removed_lines_that_look_like_headers_stay_in_the_hunk() {
+        let diff = "--- a/q.sql\n+++ b/q.sql\n@@ -1,2 +1 @@\n--- old query\n select 1;\n\\ No newline at end of file\n--- a/r.sql\n+++ b/r.sql\n@@ -1 +1 @@\n-a\n+b\n";
+        let patches = parse(diff).unwrap();
+        assert_eq!(patches.iter().map(|p| p.path.as_str()).collect::<Vec<_>>(), vec!["q.sql", "r.sql"]);
+        assert_eq!(patches[0].hunks[0].lines, vec![('-', "-- old query".to_string()), (' ', "select 1;".to_string())]);
+        assert_eq!(apply_hunks("-- old query\nselect 1;\n", &patches[0].hunks).unwrap(), "select 1;\n");
+    }
+}
@@ -0,0 +1,307 @@
+use crate::config::FilesConfig;
+use std::path::{Component, Path, PathBuf};
+use thiserror::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum AccessError {
+    #[error("access denied: {path} is outside the workspace {root}")]
+    OutsideWorkspace { path: String, root: String },
+    #[error("access denied: {path} matches files.deny pattern `{pattern}`")]
+    Denied { path: String, pattern: String },
+    #[error("access denied: {path} is not covered by files.allow")]
+    NotAllowed { path: String },
+    #[error("access denied: {path} is not in the writable paths")]
+    NotWritable { path: String },
+}
+
+/// Enforces `files.allow` / `files.deny` for every file an agent touches.
+///
+/// Patterns are globs relative to the workspace root: `*` and `?` stay within
+/// one path segment, `**` crosses directories and a trailing `/` means the
+/// whole directory.
+///
+/// Writes must also match every list of writable globs: `files.writable`
+/// and the project's `policy.writable`, when set.
+#[derive(Debug, Clone, Default)]
+pub struct FileAccessPolicy {
+    root: Option<PathBuf>,
+    allow: Vec<String>,
+    deny: Vec<String>,
+    writable: Vec<Vec<String>>,
+}
+
+impl FileAccessPolicy {
+    pub fn new(root: impl Into<PathBuf>, files: &FilesConfig) -> Self {
+        Self {
+            root: Some(normalize(&root.into())),
+            allow: files.allow.clone(),
+            deny: files.deny.clone(),
+            writable: vec![],
+        }
+        .with_writable(&files.writable)
+    }
+
+    /// Also require writes to match one of `globs`; an empty list changes
+    /// nothing.
+    pub fn with_writable(mut self, globs: &[String]) -> Self {
+        if !globs.is_empty() {
+            self.writable.push(globs.to_vec());
+        }
+        self
+    }
+
+    /// A policy that lets everything through (used where no workspace applies).
+    pub fn unrestricted() -> Self {
+        Self::default()
+    }
+
+    /// The workspace root, when there is one.
+    pub fn root(&self) -> Option<&Path> {
+        self.root.as_deref()
+    }
+
+    /// Resolve `path` against the workspace and check it. Returns the absolute
+    /// path on success.
+    ///
+    /// Symlinks are followed too: the path must stay inside the workspace,
+    /// and match the patterns, both as written and as it resolves on disk.
+    pub fn check(&self, path: &Path, access: Access) -> Result<PathBuf, AccessError> {
+        let root = match &self.root {
+            Some(r) => r,
+            None => return Ok(path.to_path_buf()),
+        };
+        let abs = normalize(&root.join(path));
+        let outside = |path: &Path| AccessError::OutsideWorkspace { path: path.display().to_string(), root: root.display().to_string() };
+        let rel = relative(&abs, root).ok_or_else(|| outside(&abs))?;
+        self.check_rel(rel.clone(), access)?;
+        if let Ok(real_root) = root.canonicalize() {
+            let real = resolve(&abs).ok_or_else(|| outside(&abs))?;
+            let real_rel = relative(&real, &real_root).ok_or_else(|| outside(&real))?;
+            if real_rel != rel {
+                self.check_rel(real_rel, access)?;
+            }
+        }
+        Ok(abs)
+    }
+
+    /// The patterns for `rel`, a path relative to the root.
+    fn check_rel(&self, rel: String, access: Access) -> Result<(), AccessError> {
+        if let Some(pattern) = self.deny.iter().find(|p| glob_match(p, &rel)) {
+            return Err(AccessError::Denied { path: rel, pattern: pattern.clone() });
+        }
+        // the workspace root itself is always listable
+        let is_root = rel.is_empty() && access == Access::Read;
+        if !self.allow.is_empty() && !is_root && !self.allow.iter().any(|p| glob_match(p, &rel)) {
+            return Err(AccessError::NotAllowed { path: rel });
+        }
+        if access == Access::Write && !self.writable.iter().all(|globs| globs.iter().any(|p| glob_match(p, &rel))) {
+            return Err(AccessError::NotWritable { path: rel });
+        }
+        Ok(())
+    }
+
+    /// Check the words of a command that may name files, for `shell` and
+    /// `git`: a word containing `/` or naming something under the root must
+    /// be readable, and a written one (a redirect target) writable. `rev:path`
+    /// is checked as `path`. This is a best effort; a command can reach files
+    /// in ways no word check sees.
+    pub fn check_words<'a>(&self, words: impl IntoIterator<Item = (&'a str, Access)>) -> Result<(), AccessError> {
+        let Some(root) = &self.root else { return Ok(()) };
+        for (word, access) in words {
+            let word = match word.rsplit_once(':') {
+                Some((_, path)) if !word.contains("://") => path,
+                _ => word,
+            };
+            if word.is_empty() || word.starts_with('-') || word == "/dev/null" {
+                continue;
+            }
+            if access == Access::Write || word.contains('/') || root.join(word).symlink_metadata().is_ok() {
+                self.check(Path::new(word), access)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The words of a shell command line with quotes removed; redirect targets
+/// are marked as written.
+pub fn shell_words(command: &str) -> Vec<(String, Access)> {
+    let mut words = vec![];
+    let mut word = String::new();
+    let mut quote = None;
+    let mut redirect = false;
+    for c in command.chars().chain([' ']) {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => word.push(c),
+            None if c == '\'' || c == '"' => quote = Some(c),
+            None if c.is_whitespace() || ";|&()<>".contains(c) => {
+                if !word.is_empty() {
+                    words.push((std::mem::take(&mut word), if redirect { Access::Write } else { Access::Read }));
+                    redirect = false;
+                }
+                // `>&2` duplicates a descriptor rather than naming a file
+                match c {
+                    '>' => redirect = true,
+                    '&' => redirect = false,
+                    _ => {}
+                }
+            }
+            None => word.push(c),
+        }
+    }
+    words
+}
+
+/// `path` relative to `root` with `/` separators; `None` outside it.
+fn relative(path: &Path, root: &Path) -> Option<String> {
+    Some(path.strip_prefix(root).ok()?.to_string_lossy().replace('\\', "/"))
+}
+
+/// `abs` with symlinks resolved: its longest existing prefix canonicalized,
+/// the rest appended. `None` for a dangling symlink, whose target can't be
+/// checked.
+fn resolve(abs: &Path) -> Option<PathBuf> {
+    let mut existing = abs;
+    let mut rest = vec![];
+    loop {
+        if let Ok(real) = existing.canonicalize() {
+            return Some(rest.iter().rev().fold(real, |p, c| p.join(c)));
+        }
+        if existing.symlink_metadata().is_ok() {
+            return None;
+        }
+        rest.push(existing.file_name()?);
+        existing = existing.parent()?;
+    }
+}
+
+/// Lexically resolve `.` and `..` so that `a/../../b` cannot escape the root.
+fn normalize(p: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for c in p.components() {
+        match c {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                out.pop();
+            }
+            other => out.push(other.as_os_str()),
+        }
+    }
+    out
+}
+
+pub fn glob_match(pattern: &str, path: &str) -> bool {
+    let pattern = if let Some(dir) = pattern.strip_suffix('/') {
+        format!("{}/**", dir)
+    } else {
+        pattern.to_string()
+    };
+    // `dir/**` also matches `dir` itself
+    if let Some(dir) = pattern.strip_suffix("/**") {
+        if glob_match_inner(dir.as_bytes(), path.as_bytes()) {
+            return true;
+        }
+    }
+    glob_match_inner(pattern.as_bytes(), path.as_bytes())
+}
+
+fn glob_match_inner(p: &[u8], s: &[u8]) -> bool {
+    match p.first() {
+        None => s.is_empty(),
+        Some(b'*') if p.get(1) == Some(&b'*') => {
+            let rest = &p[2..];
+            // `**/` may match zero directories
+            if rest.first() == Some(&b'/') && glob_match_inner(&rest[1..], s) {
+                return true;
+            }
+            (0..=s.len()).any(|i| glob_match_inner(rest, &s[i..]))
+        }
+        Some(b'*') => {
+            let rest = &p[1..];
+            for i in 0..=s.len() {
+                if glob_match_inner(rest, &s[i..]) {
+                    return true;
+                }
+                if i < s.len() && s[i] == b'/' {
+                    break;
+                }
+            }
+            false
+        }
+        Some(b'?') => !s.is_empty() && s[0] != b'/' && glob_match_inner(&p[1..], &s[1..]),
+        Some(c) => s.first() == Some(c) && glob_match_inner(&p[1..], &s[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("secrets/", "secrets/db.txt"));
+        assert!(glob_match("secrets/**", "secrets"));
+        assert!(glob_match("**/.env", ".env"));
+        assert!(glob_match("**/.env", "app/.env"));
+        assert!(glob_match("src/*.rs", "src/main.rs"));
+        assert!(!glob_match("src/*.rs", "src/tui/app.rs"));
+        assert!(glob_match("src/**/*.rs", "src/tui/app.rs"));
+        assert!(!glob_match("*.pem", "keys/a.pem"));
+    }
+
+    #[test]
+    fn test_policy_denies_secrets_and_escapes() {
+        let files = FilesConfig { allow: vec![], deny: vec!["secrets/".into()], writable: vec![] };
+        let policy = FileAccessPolicy::new("/work", &files);
+        assert!(policy.check(Path::new("src/main.rs"), Access::Read).is_ok());
+        assert_eq!(
+            policy.check(Path::new("secrets/key"), Access::Read),
+            Err(AccessError::Denied { path: "secrets/key".into(), pattern: "secrets/".into() })
+        );
+        assert!(matches!(
+            policy.check(Path::new("../etc/passwd"), Access::Write),
+            Err(AccessError::OutsideWorkspace { .. })
+        ));
+
+        let files = FilesConfig { allow: vec!["src/**".into()], deny: vec![], writable: vec![] };
+        let policy = FileAccessPolicy::new("/work", &files);
+        assert!(policy.check(Path::new("src/lib.rs"), Access::Write).is_ok());
+        assert!(policy.check(Path::new("Cargo.toml"), Access::Read).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinks_cannot_escape_or_dodge_patterns() {
+        let outside = tempfile::tempdir().unwrap();
+        let work = tempfile::tempdir().unwrap();
+        std::fs::create_dir(work.path().join("secrets")).unwrap();
+        std::os::unix::fs::symlink(outside.path(), work.path().join("out")).unwrap();
+        std::os::unix::fs::symlink(work.path().join("secrets"), work.path().join("plain")).unwrap();
+        std::os::unix::fs::symlink(outside.path().join("missing"), work.path().join("dangling")).unwrap();
+        let files = FilesConfig { allow: vec![], deny: vec!["secrets/".into()], writable: vec![] };
+        let policy = FileAccessPolicy::new(work.path(), &files);
+
+        assert!(policy.check(Path::new("src/new/lib.rs"), Access::Write).is_ok());
+        for path in ["out/passwd", "out/new/file", "dangling"] {
+            assert!(matches!(policy.check(Path::new(path), Access::Write), Err(AccessError::OutsideWorkspace { .. })), "{}", path);
+        }
+        assert!(matches!(policy.check(Path::new("plain/key"), Access::Read), Err(AccessError::Denied { .. })));
+    }
+
+    #[test]
+    fn test_writes_must_match_every_writable_list() {
+        let files = FilesConfig { allow: vec![], deny: vec![], writable: vec!["src/".into(), "docs/".into()] };
+        let policy = FileAccessPolicy::new("/work", &files).with_writable(&["src/**".into(), "tests/".into()]);
+        assert!(policy.check(Path::new("src/lib.rs"), Access::Write).is_ok());
+        assert!(policy.check(Path::new("docs/README.md"), Access::Read).is_ok());
+        assert_eq!(policy.check(Path::new("docs/README.md"), Access::Write), Err(AccessError::NotWritable { path: "docs/README.md".into() }));
+        assert!(policy.check(Path::new("tests/it.rs"), Access::Write).is_err());
+    }
+}
@@ -1,11 +1,15 @@
 use crate::types::AgentInput;
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
 
 pub type ToolResult = anyhow::Result<ToolOutput>;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ToolOutput {
     pub text: String,
+    /// Bytes the tool wrote to disk, for the audit log.
+    #[serde(default)]
+    pub bytes_written: u64,
 }
 
 #[derive(Debug)]
@@ -17,9 +21,41 @@ pub struct ToolInfo {
 pub trait Tool: Send + Sync {
     fn name(&self) -> &'static str;
     fn description(&self) -> &'static str;
+    /// JSON schema of the `input` field of a call to this tool.
+    fn input_schema(&self) -> Value {
+        json!({"type": "string"})
+    }
+    /// Whether the tool changes anything outside the run (files, processes,
+    /// repositories); dry runs record such calls instead of running them.
+    fn mutates(&self) -> bool {
+        false
+    }
+    /// What a call with `input` does, for dry-run reports.
+    fn describe_call(&self, input: &AgentInput) -> String {
+        let first = input.text.lines().next().unwrap_or_default();
+        format!("run {} with {:?}", self.name(), first.chars().take(80).collect::<String>())
+    }
+    /// POSIX shell commands that redo a call with `input` from the
+    /// workspace root, for `agent trace export-script`.
+    fn replay(&self, _input: &AgentInput) -> Option<String> {
+        None
+    }
     fn run(&self, input: AgentInput) -> ToolResult;
 }
 
+/// A validated request from the model to run a tool.
+#[derive(Debug, Clone)]
+pub struct ToolCall {
+    pub tool: String,
+    pub input: AgentInput,
+    /// The model gave an object or array, serialized into `input.text`.
+    pub json: bool,
+}
+
+use crate::errors::ToolError;
+use crate::memory::events::{EventBus, SessionEvent};
+use crate::tools::audit;
+use crate::tasks::artifacts::ArtifactRecorder;
 use parking_lot::RwLock;
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -27,25 +63,166 @@ use std::sync::Arc;
 #[derive(Default, Clone)]
 pub struct ToolRegistry {
     inner: Arc<RwLock<HashMap<String, Arc<dyn Tool>>>>,
+    recorder: Arc<RwLock<Option<ArtifactRecorder>>>,
+    /// Bus, session and agent name tool calls are narrated under in pair mode.
+    commentary: Arc<RwLock<Option<(EventBus, String, String)>>>,
 }
 
 impl ToolRegistry {
     pub fn new() -> Self {
         Self {
             inner: Arc::new(RwLock::new(HashMap::new())),
+            recorder: Arc::new(RwLock::new(None)),
+            commentary: Arc::new(RwLock::new(None)),
         }
     }
 
+    /// Publish each call's `describe_call` on `events` as commentary from
+    /// `agent` in `session`.
+    pub fn set_commentary(&self, events: EventBus, session: &str, agent: &str) {
+        *self.commentary.write() = Some((events, session.to_string(), agent.to_string()));
+    }
+
+    /// Record successful tool outputs as task artifacts.
+    pub fn set_recorder(&self, recorder: ArtifactRecorder) {
+        *self.recorder.write() = Some(recorder);
+    }
+
     pub fn register(&self, t: Arc<dyn Tool>) {
         self.inner
             .write()
             .insert(t.name().to_string(), Arc::clone(&t));
     }
 
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.inner.read().keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// Drop every tool not in `keep`.
+    pub fn retain(&self, keep: &[String]) {
+        self.inner.write().retain(|name, _| keep.contains(name));
+    }
+
+    /// Schema of the tool-call envelope the model must emit:
+    /// `{"tool": "<name>", "input": ...}`. Disabled tools are left out.
+    pub fn call_schema(&self) -> Value {
+        let names: Vec<String> = self.names().into_iter().filter(|n| audit::enabled(n)).collect();
+        json!({
+            "type": "object",
+            "properties": {
+                "tool": {"type": "string", "enum": names},
+                "input": {}
+            },
+            "required": ["tool", "input"],
+            "additionalProperties": false
+        })
+    }
+
+    /// Envelope for one turn: a single call or an array of calls.
+    pub fn batch_schema(&self) -> Value {
+        let mut schema = self.call_schema();
+        schema["type"] = json!(["object", "array"]);
+        schema["items"] = self.call_schema();
+        schema
+    }
+
+    /// Validate a single call or an array of calls; errors in an array are
+    /// prefixed with the element's index.
+    pub fn validate_batch(&self, calls: &Value) -> Result<Vec<ToolCall>, Vec<String>> {
+        let Value::Array(items) = calls else {
+            return self.validate_call(calls).map(|c| vec![c]);
+        };
+        if items.is_empty() {
+            return Err(vec!["$: empty array of tool calls".to_string()]);
+        }
+        let mut out = vec![];
+        let mut errors = vec![];
+        for (i, item) in items.iter().enumerate() {
+            match self.validate_call(item) {
+                Ok(call) => out.push(call),
+                Err(errs) => errors.extend(errs.into_iter().map(|e| e.replacen('$', &format!("$[{}]", i), 1))),
+            }
+        }
+        if errors.is_empty() {
+            Ok(out)
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// One line per tool, for inclusion in prompts.
+    pub fn describe(&self) -> String {
+        let map = self.inner.read();
+        let mut lines: Vec<String> = map
+            .values()
+            .filter(|t| audit::enabled(t.name()))
+            .map(|t| format!("- {}: {} (input schema: {})", t.name(), t.description(), t.input_schema()))
+            .collect();
+        lines.sort();
+        lines.join("\n")
+    }
+
+    /// Check a model-produced tool call against the envelope and the tool's
+    /// own input schema.
+    pub fn validate_call(&self, call: &Value) -> Result<ToolCall, Vec<String>> {
+        crate::tools::schema::validate(&self.call_schema(), call)?;
+        let tool = call["tool"].as_str().unwrap_or_default().to_string();
+        let input = &call["input"];
+        if let Some(t) = self.inner.read().get(&tool) {
+            crate::tools::schema::validate(&t.input_schema(), input)
+                .map_err(|errs| errs.into_iter().map(|e| e.replacen('$', "$.input", 1)).collect::<Vec<_>>())?;
+        }
+        let (text, json) = match input {
+            Value::String(s) => (s.clone(), false),
+            other => (other.to_string(), true),
+        };
+        Ok(ToolCall { tool, input: AgentInput { text }, json })
+    }
+
+    /// Run `name`, recording the call in the audit log.
     pub fn run(&self, name: &str, input: AgentInput) -> ToolResult {
         let map = self.inner.read();
-        let t = map.get(name).ok_or_else(|| anyhow::anyhow!("tool not found"))?;
-        t.run(input)
+        let t = map.get(name).ok_or_else(|| ToolError::NotFound(name.to_string()))?;
+        if !audit::enabled(name) {
+            return Err(ToolError::Disabled(name.to_string()).into());
+        }
+        if let Some((events, session, agent)) = self.commentary.read().as_ref() {
+            events.publish(SessionEvent::Commentary { session: session.clone(), agent: agent.clone(), text: t.describe_call(&input) });
+        }
+        let args = audit::args_hash(&input.text);
+        let replay = t.replay(&input);
+        let started = std::time::Instant::now();
+        let text = input.text.clone();
+        let res = t.run(input);
+        audit::record(&audit::Call {
+            at: crate::tasks::now_secs(),
+            tool: name.to_string(),
+            args,
+            duration_ms: started.elapsed().as_millis() as u64,
+            ok: res.is_ok(),
+            code: res.as_ref().err().map(|e| crate::errors::ErrorReport::from_anyhow(e).code.to_string()),
+            bytes_written: res.as_ref().map_or(0, |o| o.bytes_written),
+            request_id: crate::request_id::current(),
+        });
+        if let Some(r) = self.recorder.read().as_ref() {
+            let output = match &res {
+                Ok(out) => out.text.clone(),
+                Err(e) => format!("error: {:#}", e),
+            };
+            r.inspect(crate::types::ToolCallRecord { tool: name.to_string(), input: text, output, ok: res.is_ok() });
+        }
+        match &res {
+            Ok(out) => {
+                if let Some(r) = self.recorder.read().as_ref() {
+                    r.tool_output(name, &out.text);
+                    r.tool_call(crate::tasks::trace::Step { tool: name.to_string(), mutates: t.mutates(), replay });
+                }
+            }
+            Err(e) => tracing::warn!(tool = name, "tool error: {}", e),
+        }
+        res
     }
 }
 
@@ -61,6 +238,6 @@ impl Tool for EchoTool {
     }
 
     fn run(&self, input: AgentInput) -> ToolResult {
-        Ok(ToolOutput { text: input.text })
+        Ok(ToolOutput { text: input.text, ..Default::default() })
     }
 }
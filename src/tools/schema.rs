@@ -0,0 +1,125 @@
+use serde_json::Value;
+
+/// Validate `value` against a subset of JSON Schema: `type`, `enum`,
+/// `properties`, `required`, `additionalProperties: false` and `items`.
+///
+/// Returns every violation found, each prefixed with the path of the
+/// offending value (`$` is the root), so the messages can be fed back to the
+/// model verbatim.
+pub fn validate(schema: &Value, value: &Value) -> Result<(), Vec<String>> {
+    let mut errors = vec![];
+    check(schema, value, "$", &mut errors);
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+fn type_name(v: &Value) -> &'static str {
+    match v {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_i64() || n.is_u64() => "integer",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn type_matches(expected: &str, v: &Value) -> bool {
+    let actual = type_name(v);
+    actual == expected || (expected == "number" && actual == "integer")
+}
+
+fn check(schema: &Value, value: &Value, path: &str, errors: &mut Vec<String>) {
+    if let Some(t) = schema.get("type") {
+        let ok = match t {
+            Value::String(t) => type_matches(t, value),
+            Value::Array(ts) => ts.iter().filter_map(|t| t.as_str()).any(|t| type_matches(t, value)),
+            _ => true,
+        };
+        if !ok {
+            errors.push(format!("{}: expected {}, got {}", path, t, type_name(value)));
+            return;
+        }
+    }
+    if let Some(Value::Array(options)) = schema.get("enum") {
+        if !options.contains(value) {
+            errors.push(format!("{}: must be one of {}", path, Value::Array(options.clone())));
+        }
+    }
+    if let Value::Object(obj) = value {
+        let props = schema.get("properties").and_then(|p| p.as_object());
+        if let Some(Value::Array(required)) = schema.get("required") {
+            for key in required.iter().filter_map(|k| k.as_str()) {
+                if !obj.contains_key(key) {
+                    errors.push(format!("{}: missing required property `{}`", path, key));
+                }
+            }
+        }
+        for (key, v) in obj {
+            match props.and_then(|p| p.get(key)) {
+                Some(sub) => check(sub, v, &format!("{}.{}", path, key), errors),
+                None => {
+                    if schema.get("additionalProperties") == Some(&Value::Bool(false)) {
+                        errors.push(format!("{}: unexpected property `{}`", path, key));
+                    }
+                }
+            }
+        }
+    }
+    if let (Value::Array(items), Some(item_schema)) = (value, schema.get("items")) {
+        for (i, v) in items.iter().enumerate() {
+            check(item_schema, v, &format!("{}[{}]", path, i), errors);
+        }
+    }
+}
+
+/// Pull the first JSON object or array out of free-form model output,
+/// tolerating surrounding prose and ``` fences.
+pub fn extract_json(text: &str) -> Option<Value> {
+    if let Ok(v) = serde_json::from_str(text.trim()) {
+        return Some(v);
+    }
+    let start = text.find(['{', '['])?;
+    let end = text.rfind(['}', ']'])?;
+    if end < start {
+        return None;
+    }
+    serde_json::from_str(&text[start..=end]).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_validate_reports_all_errors() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "tool": {"type": "string", "enum": ["echo"]},
+                "count": {"type": "integer"},
+                "tags": {"type": "array", "items": {"type": "string"}}
+            },
+            "required": ["tool", "input"],
+            "additionalProperties": false
+        });
+        assert!(validate(&schema, &json!({"tool": "echo", "input": 1, "count": 2})).is_err());
+        let errs = validate(&schema, &json!({"tool": "rm", "count": 1.5, "tags": ["a", 2]})).unwrap_err();
+        assert_eq!(errs.len(), 4);
+        assert!(errs.iter().any(|e| e == "$: missing required property `input`"));
+        assert!(errs.iter().any(|e| e == "$.tags[1]: expected \"string\", got integer"));
+        assert!(validate(&json!({"type": "number"}), &json!(3)).is_ok());
+    }
+
+    #[test]
+    fn test_extract_json_from_prose() {
+        let v = extract_json("Sure:\n```json\n{\"a\": 1}\n```").unwrap();
+        assert_eq!(v, json!({"a": 1}));
+        assert!(extract_json("no json here").is_none());
+    }
+}
@@ -0,0 +1,80 @@
+use crate::errors::ToolError;
+use crate::memory::shared::SharedVars;
+use crate::tools::registry::{Tool, ToolOutput, ToolResult};
+use crate::types::AgentInput;
+
+/// Reads a cross-session shared variable. Input is the variable name.
+pub struct GetSharedVarTool {
+    pub vars: SharedVars,
+}
+
+impl Tool for GetSharedVarTool {
+    fn name(&self) -> &'static str {
+        "get_shared_var"
+    }
+
+    fn description(&self) -> &'static str {
+        "Read a shared variable by name (input: `key`)"
+    }
+
+    fn run(&self, input: AgentInput) -> ToolResult {
+        let key = input.text.trim();
+        if key.is_empty() {
+            return Err(ToolError::InvalidInput { tool: "get_shared_var", reason: "missing key".into() }.into());
+        }
+        let value = self
+            .vars
+            .get(key)
+            .ok_or_else(|| ToolError::VarNotSet(key.to_string()))?;
+        Ok(ToolOutput { text: value, ..Default::default() })
+    }
+}
+
+/// Writes a cross-session shared variable. Input is `key=value`.
+pub struct SetSharedVarTool {
+    pub vars: SharedVars,
+}
+
+impl Tool for SetSharedVarTool {
+    fn name(&self) -> &'static str {
+        "set_shared_var"
+    }
+
+    fn description(&self) -> &'static str {
+        "Set a shared variable visible to every session (input: `key=value`)"
+    }
+
+    fn run(&self, input: AgentInput) -> ToolResult {
+        let (key, value) = input
+            .text
+            .split_once('=')
+            .ok_or_else(|| ToolError::InvalidInput { tool: "set_shared_var", reason: "expected `key=value`".into() })?;
+        let key = key.trim();
+        if key.is_empty() {
+            return Err(ToolError::InvalidInput { tool: "set_shared_var", reason: "missing key".into() }.into());
+        }
+        self.vars.set(key, value.trim());
+        Ok(ToolOutput { text: format!("{}={}", key, value.trim()), ..Default::default() })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::registry::ToolRegistry;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_set_then_get_through_registry() {
+        let vars = SharedVars::new();
+        let reg = ToolRegistry::new();
+        reg.register(Arc::new(SetSharedVarTool { vars: vars.clone() }));
+        reg.register(Arc::new(GetSharedVarTool { vars: vars.clone() }));
+
+        reg.run("set_shared_var", AgentInput { text: "target = x86_64".into() }).unwrap();
+        let out = reg.run("get_shared_var", AgentInput { text: "target".into() }).unwrap();
+        assert_eq!(out.text, "x86_64");
+        assert!(reg.run("get_shared_var", AgentInput { text: "missing".into() }).is_err());
+        assert!(reg.run("set_shared_var", AgentInput { text: "novalue".into() }).is_err());
+    }
+}
@@ -0,0 +1,158 @@
+//! Shell commands. `shell` runs a command line through the platform shell in
+//! the workspace root and hands each line of output to a sink as it arrives,
+//! so the TUI's terminal pane fills while the command runs; the tool's own
+//! output is the tail. The TUI runs `/term` and confirmed `!` requests
+//! through it. Agent runs with a workspace root get it when `tools.shell`
+//! is on or a `commands` list limits it (recorded, not run, in a dry run);
+//! their commands must also pass the file policy word by word.
+
+use crate::tools::policy::{shell_words, FileAccessPolicy};
+use crate::tools::registry::{Tool, ToolOutput, ToolResult};
+use crate::types::AgentInput;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
+use tokio::sync::mpsc::UnboundedSender;
+
+/// Lines of output the tool returns.
+const TAIL_LINES: usize = 100;
+
+/// Run `command` in `root`, calling `on_line` with each line of stdout and
+/// stderr as it is printed.
+pub fn run_streaming(root: &Path, command: &str, mut on_line: impl FnMut(String)) -> anyhow::Result<ExitStatus> {
+    crate::tools::audit::check_command(command)?;
+    let (shell, flag) = if cfg!(windows) { ("cmd", "/C") } else { ("sh", "-c") };
+    let mut child = Command::new(shell)
+        .args([flag, command])
+        .current_dir(root)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let (tx, rx) = std::sync::mpsc::channel();
+    let forward = |stream: Box<dyn Read + Send>| {
+        let tx = tx.clone();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stream).lines().map_while(Result::ok) {
+                if tx.send(line).is_err() {
+                    break;
+                }
+            }
+        })
+    };
+    let readers = [
+        child.stdout.take().map(|s| forward(Box::new(s))),
+        child.stderr.take().map(|s| forward(Box::new(s))),
+    ];
+    drop(tx);
+    for line in rx {
+        on_line(line);
+    }
+    for reader in readers.into_iter().flatten() {
+        let _ = reader.join();
+    }
+    Ok(child.wait()?)
+}
+
+/// Runs a shell command. Input is the command line.
+pub struct ShellTool {
+    pub root: PathBuf,
+    /// Paths the command names must pass it.
+    pub policy: FileAccessPolicy,
+    /// Receives every line of output while the command runs.
+    pub sink: Option<UnboundedSender<String>>,
+}
+
+impl Tool for ShellTool {
+    fn name(&self) -> &'static str {
+        "shell"
+    }
+
+    fn description(&self) -> &'static str {
+        "Run a command line through the shell in the workspace root (input: the command)"
+    }
+
+    fn mutates(&self) -> bool {
+        true
+    }
+
+    fn describe_call(&self, input: &AgentInput) -> String {
+        format!("run `{}`", input.text.trim())
+    }
+
+    fn replay(&self, input: &AgentInput) -> Option<String> {
+        Some(input.text.trim().to_string())
+    }
+
+    fn run(&self, input: AgentInput) -> ToolResult {
+        let words = shell_words(input.text.trim());
+        self.policy.check_words(words.iter().map(|(w, access)| (w.as_str(), *access)))?;
+        let mut lines: Vec<String> = vec![];
+        let status = run_streaming(&self.root, input.text.trim(), |line| {
+            if let Some(sink) = &self.sink {
+                let _ = sink.send(line.clone());
+            }
+            lines.push(line);
+        })?;
+        if !status.success() {
+            let line = format!("[{}]", status);
+            if let Some(sink) = &self.sink {
+                let _ = sink.send(line.clone());
+            }
+            lines.push(line);
+        }
+        let tail = lines[lines.len().saturating_sub(TAIL_LINES)..].join("\n");
+        Ok(ToolOutput { text: tail, ..Default::default() })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_output_streams_to_the_sink() {
+        let td = tempfile::tempdir().unwrap();
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let tool = ShellTool { root: td.path().to_path_buf(), policy: FileAccessPolicy::unrestricted(), sink: Some(tx) };
+        let out = tool.run(AgentInput { text: "echo one; echo two >&2; exit 3".into() }).unwrap();
+        let mut streamed = vec![];
+        while let Ok(line) = rx.try_recv() {
+            streamed.push(line);
+        }
+        assert_eq!(streamed.len(), 3);
+        assert!(streamed.contains(&"one".to_string()) && streamed.contains(&"two".to_string()));
+        assert_eq!(streamed[2], "[exit status: 3]");
+        assert!(out.text.ends_with("[exit status: 3]"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_denied_paths_and_commands_are_refused() {
+        use crate::tools::git::GitTool;
+        let td = tempfile::tempdir().unwrap();
+        std::fs::create_dir(td.path().join("secrets")).unwrap();
+        std::fs::write(td.path().join("secrets/token"), "t0ken").unwrap();
+        let files = crate::config::FilesConfig { allow: vec![], deny: vec!["secrets/".into()], writable: vec!["out/".into()] };
+        let policy = FileAccessPolicy::new(td.path(), &files);
+        let shell = ShellTool { root: td.path().to_path_buf(), policy: policy.clone(), sink: None };
+        let git = GitTool { root: td.path().to_path_buf(), policy };
+        let run = |tool: &dyn Tool, text: &str| tool.run(AgentInput { text: text.into() }).map(|o| o.text).map_err(|e| e.to_string());
+
+        assert!(run(&shell, "cat 'secrets/token'").unwrap_err().contains("files.deny"));
+        assert!(run(&shell, "cat secrets").unwrap_err().contains("files.deny"));
+        assert!(run(&shell, "cat /etc/passwd").unwrap_err().contains("outside the workspace"));
+        assert!(run(&shell, "echo hi > notes.txt").unwrap_err().contains("writable"));
+        assert_eq!(run(&shell, "mkdir out && echo hi > out/notes.txt 2>&1 && cat out/notes.txt").unwrap(), "hi");
+        assert!(run(&git, "show\nHEAD:secrets/token").unwrap_err().contains("files.deny"));
+        assert!(run(&git, "-C\n/etc\nstatus").unwrap_err().contains("outside the workspace"));
+
+        // any command without a `§` stays allowed, so tests running alongside are unaffected
+        crate::tools::audit::configure_project(&crate::workspace::ProjectPolicy { commands: vec!["[^§]*".into()], ..Default::default() });
+        let refused = (run(&shell, "echo §"), run(&git, "log\n--grep=§"));
+        crate::tools::audit::configure_project(&Default::default());
+        assert!(refused.0.unwrap_err().contains("command not allowed"));
+        assert!(refused.1.unwrap_err().contains("command not allowed: git log --grep=§"));
+    }
+}
@@ -0,0 +1,302 @@
+//! Transcripts of what each provider was sent and what it answered, for
+//! auditing what left this machine. Off unless `[transcript] enabled`; each
+//! run (an `agent run` task, a TUI session, any other command) appends to
+//! `<home>/transcripts/<run-id>.jsonl`, separate from the app log.
+//! Secrets matching the built-in or configured patterns are replaced before
+//! anything is written, long messages are cut and a run's file stops
+//! growing at `max_run_bytes`. `agent transcript [run-id]` reads them back.
+
+use crate::types::Message;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{OnceLock, RwLock};
+
+/// What a redacted match is replaced with.
+pub const REDACTED: &str = "[redacted]";
+
+/// API keys, bearer tokens and `password = …` style assignments.
+const BUILTIN_PATTERNS: &[&str] = &[
+    r"(?i)bearer\s+[a-z0-9._~+/=-]{8,}",
+    r"\bsk-[A-Za-z0-9_-]{16,}",
+    r"\bgh[pousr]_[A-Za-z0-9]{20,}",
+    r"\bAKIA[0-9A-Z]{16}\b",
+    r"-----BEGIN [A-Z ]*PRIVATE KEY-----[\s\S]*?-----END [A-Z ]*PRIVATE KEY-----",
+    r#"(?i)\b(password|passwd|secret|token|api[_-]?key)\b(\s*[:=]\s*)["']?[^\s"',;]+"#,
+];
+
+/// `[transcript]` in config.toml.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TranscriptConfig {
+    pub enabled: bool,
+    /// Per-provider switch (`remote`, `llama`, `native`); unlisted providers
+    /// are recorded while `enabled` is set.
+    pub providers: BTreeMap<String, bool>,
+    /// Regexes redacted on top of the built-in key and token patterns.
+    pub redact: Vec<String>,
+    /// Message and reply text beyond this many bytes is cut.
+    pub max_message_bytes: usize,
+    /// A run's transcript stops growing at this size.
+    pub max_run_bytes: u64,
+}
+
+impl Default for TranscriptConfig {
+    fn default() -> Self {
+        Self { enabled: false, providers: BTreeMap::new(), redact: vec![], max_message_bytes: 16 * 1024, max_run_bytes: 8 * 1024 * 1024 }
+    }
+}
+
+impl TranscriptConfig {
+    pub fn records(&self, provider: &str) -> bool {
+        self.enabled && self.providers.get(provider).copied().unwrap_or(true)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Turn {
+    pub role: String,
+    pub text: String,
+    /// Image labels; image data is never written.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub images: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    pub at: u64,
+    pub provider: String,
+    pub model: String,
+    pub prompt: Vec<Turn>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reply: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Some text was cut at `max_message_bytes`.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+}
+
+/// Writes one run's transcript.
+#[derive(Debug)]
+pub struct Transcripts {
+    config: TranscriptConfig,
+    patterns: Vec<Regex>,
+    dir: PathBuf,
+    run: String,
+}
+
+impl Transcripts {
+    /// Invalid `redact` patterns fail here rather than letting text through.
+    pub fn new(config: &TranscriptConfig, home: &Path, run: &str) -> anyhow::Result<Self> {
+        let mut patterns = vec![];
+        for p in BUILTIN_PATTERNS.iter().copied().chain(config.redact.iter().map(String::as_str)) {
+            patterns.push(Regex::new(p).map_err(|e| crate::errors::ConfigError::Invalid { key: "transcript.redact", reason: format!("{:?}: {}", p, e) })?);
+        }
+        Ok(Self { config: config.clone(), patterns, dir: dir(home), run: run.to_string() })
+    }
+
+    pub fn redact(&self, text: &str) -> String {
+        let mut out = text.to_string();
+        for p in &self.patterns {
+            out = p
+                .replace_all(&out, |caps: &regex::Captures| match (caps.get(1), caps.get(2)) {
+                    // keep `password = ` so the reader sees what was hidden
+                    (Some(key), Some(sep)) => format!("{}{}{}", key.as_str(), sep.as_str(), REDACTED),
+                    _ => REDACTED.to_string(),
+                })
+                .into_owned();
+        }
+        out
+    }
+
+    /// Redacted and cut to `max_message_bytes`; sets `truncated` when cut.
+    fn clean(&self, text: &str, truncated: &mut bool) -> String {
+        let text = self.redact(text);
+        if text.len() <= self.config.max_message_bytes {
+            return text;
+        }
+        *truncated = true;
+        let mut end = self.config.max_message_bytes;
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        format!("{}… [{} bytes cut]", &text[..end], text.len() - end)
+    }
+
+    pub fn entry(&self, provider: &str, model: &str, messages: &[Message], outcome: Result<&str, String>) -> Entry {
+        let mut truncated = false;
+        let prompt = messages
+            .iter()
+            .map(|m| Turn { role: m.role.clone(), text: self.clean(&m.full_text(), &mut truncated), images: m.images.iter().map(|i| i.label()).collect() })
+            .collect();
+        let (reply, error) = match outcome {
+            Ok(text) => (Some(self.clean(text, &mut truncated)), None),
+            Err(e) => (None, Some(self.redact(&e))),
+        };
+        Entry { at: crate::tasks::now_secs(), provider: provider.into(), model: model.into(), prompt, reply, error, truncated }
+    }
+
+    /// Append an exchange with `provider` unless it is switched off or the
+    /// run is at its size cap.
+    pub fn record(&self, provider: &str, model: &str, messages: &[Message], outcome: Result<&str, String>) -> anyhow::Result<()> {
+        if !self.config.records(provider) {
+            return Ok(());
+        }
+        let line = serde_json::to_string(&self.entry(provider, model, messages, outcome))?;
+        let path = self.dir.join(format!("{}.jsonl", self.run));
+        let size = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+        if size + line.len() as u64 + 1 > self.config.max_run_bytes {
+            anyhow::bail!("transcript {} reached transcript.max_run_bytes", self.run);
+        }
+        std::fs::create_dir_all(&self.dir)?;
+        let mut f = std::fs::OpenOptions::new().create(true).append(true).open(&path)?;
+        writeln!(f, "{}", line)?;
+        Ok(())
+    }
+}
+
+pub fn dir(home: &Path) -> PathBuf {
+    home.join("transcripts")
+}
+
+fn sink() -> &'static RwLock<Option<Transcripts>> {
+    static SINK: OnceLock<RwLock<Option<Transcripts>>> = OnceLock::new();
+    SINK.get_or_init(Default::default)
+}
+
+fn new_run_id() -> String {
+    format!("{}-{}", crate::tasks::now_secs(), &uuid::Uuid::new_v4().simple().to_string()[..6])
+}
+
+/// Record to `home` from now on, or stop. The run id survives a reload.
+pub fn configure(config: &TranscriptConfig, home: &Path) -> anyhow::Result<()> {
+    let mut sink = sink().write().unwrap_or_else(|e| e.into_inner());
+    let run = sink.as_ref().map(|t| t.run.clone()).unwrap_or_else(new_run_id);
+    *sink = None;
+    if config.enabled {
+        *sink = Some(Transcripts::new(config, home, &run)?);
+    }
+    Ok(())
+}
+
+/// Record the rest of this process under `run`, e.g. an agent run's task id.
+pub fn begin_run(run: &str) {
+    if let Some(t) = sink().write().unwrap_or_else(|e| e.into_inner()).as_mut() {
+        t.run = run.to_string();
+    }
+}
+
+/// The current run id while transcripts are on.
+pub fn current_run() -> Option<String> {
+    sink().read().unwrap_or_else(|e| e.into_inner()).as_ref().map(|t| t.run.clone())
+}
+
+/// `text` with secrets replaced: by the configured patterns while
+/// transcripts are on, else by the built-in ones.
+pub fn redact(text: &str) -> String {
+    static BUILTIN: OnceLock<Option<Transcripts>> = OnceLock::new();
+    let sink = sink().read().unwrap_or_else(|e| e.into_inner());
+    match sink.as_ref().or(BUILTIN.get_or_init(|| Transcripts::new(&TranscriptConfig::default(), Path::new(""), "").ok()).as_ref()) {
+        Some(t) => t.redact(text),
+        None => text.to_string(),
+    }
+}
+
+/// Record an exchange when transcripts are on; failures are only logged.
+pub fn record(provider: &str, model: &str, messages: &[Message], outcome: Result<&str, String>) {
+    let sink = sink().read().unwrap_or_else(|e| e.into_inner());
+    let Some(t) = sink.as_ref() else { return };
+    if let Err(e) = t.record(provider, model, messages, outcome) {
+        log::warn!("transcript not written: {}", e);
+    }
+}
+
+/// Recorded runs, newest first: id, last write and entry count.
+pub fn runs(home: &Path) -> Vec<(String, u64, usize)> {
+    let mut out: Vec<_> = std::fs::read_dir(dir(home))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|e| {
+            let path = e.path();
+            let id = path.file_name()?.to_str()?.strip_suffix(".jsonl")?.to_string();
+            let modified = e.metadata().ok()?.modified().ok()?.duration_since(std::time::UNIX_EPOCH).ok()?.as_secs();
+            let entries = std::fs::read_to_string(&path).map(|s| s.lines().count()).unwrap_or(0);
+            Some((id, modified, entries))
+        })
+        .collect();
+    out.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    out
+}
+
+/// The run whose id is or starts with `id`.
+pub fn find(home: &Path, id: &str) -> anyhow::Result<(String, Vec<Entry>)> {
+    let matches: Vec<String> = runs(home).into_iter().map(|r| r.0).filter(|r| r.starts_with(id)).collect();
+    let run = match matches.as_slice() {
+        [one] => one.clone(),
+        [] => anyhow::bail!("no transcript for run {}", id),
+        _ if matches.iter().any(|r| r == id) => id.to_string(),
+        _ => anyhow::bail!("run id {} is ambiguous: {}", id, matches.join(", ")),
+    };
+    let raw = std::fs::read_to_string(dir(home).join(format!("{}.jsonl", run)))?;
+    Ok((run, raw.lines().filter_map(|l| serde_json::from_str(l).ok()).collect()))
+}
+
+/// Text rendering for `agent transcript <run-id>`.
+pub fn render(run: &str, entries: &[Entry]) -> String {
+    let mut out = format!("run {} — {} exchange(s)\n", run, entries.len());
+    for (i, e) in entries.iter().enumerate() {
+        out.push_str(&format!("\n#{} {} {} at {}{}\n", i + 1, e.provider, e.model, e.at, if e.truncated { " (truncated)" } else { "" }));
+        for turn in &e.prompt {
+            out.push_str(&format!("  [{}] {}\n", turn.role, turn.text.replace('\n', "\n    ")));
+            for image in &turn.images {
+                out.push_str(&format!("    {}\n", image));
+            }
+        }
+        match (&e.reply, &e.error) {
+            (Some(reply), _) => out.push_str(&format!("  → {}\n", reply.replace('\n', "\n    "))),
+            (None, Some(error)) => out.push_str(&format!("  ✗ {}\n", error)),
+            _ => {}
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redacts_caps_and_reads_back() {
+        let home = tempfile::tempdir().unwrap();
+        let mut config = TranscriptConfig { enabled: true, redact: vec![r"ACME-\d+".into()], max_message_bytes: 40, ..Default::default() };
+        config.providers.insert("native".into(), false);
+        let t = Transcripts::new(&config, home.path(), "run-1").unwrap();
+
+        assert_eq!(t.redact("use sk-abcdefghijklmnop1234 now"), "use [redacted] now");
+        assert_eq!(t.redact("password: hunter22 and api_key=\"xyz\""), "password: [redacted] and api_key=[redacted]\"");
+        assert_eq!(t.redact("Authorization: Bearer abc.def.ghi123"), "Authorization: [redacted]");
+        assert_eq!(t.redact("ticket ACME-42"), "ticket [redacted]");
+
+        let messages = vec![Message::new("system", "be brief"), Message::new("user", "my token=s3cr3t, then a very long question that goes on")];
+        t.record("remote", "local.gguf", &messages, Ok("sure")).unwrap();
+        t.record("native", "tiny", &messages, Ok("not recorded")).unwrap();
+        t.record("llama", "m", &messages[..1], Err("connection refused".into())).unwrap();
+
+        let (run, entries) = find(home.path(), "run").unwrap();
+        assert_eq!((run.as_str(), entries.len()), ("run-1", 2));
+        assert_eq!(entries[0].prompt[1].text, "my token=[redacted], then a very long qu… [19 bytes cut]");
+        assert!(entries[0].truncated);
+        assert_eq!((entries[0].reply.as_deref(), entries[1].error.as_deref()), (Some("sure"), Some("connection refused")));
+        let text = render(&run, &entries);
+        assert!(text.contains("#2 llama m") && text.contains("✗ connection refused"), "{}", text);
+
+        let small = Transcripts::new(&TranscriptConfig { enabled: true, max_run_bytes: 10, ..Default::default() }, home.path(), "run-2").unwrap();
+        assert!(small.record("remote", "m", &messages, Ok("x")).is_err());
+        assert!(find(home.path(), "run-2").is_err());
+        assert!(Transcripts::new(&TranscriptConfig { redact: vec!["(".into()], ..Default::default() }, home.path(), "r").is_err());
+    }
+}
@@ -1,73 +1,472 @@
-use crate::config::{RuntimeConfig, ThemeName};
+use crate::agent::human::{HumanChannel, HumanPrompt, PlanDecision, PlanEstimate, PlanReview, Prompt};
+use crate::agent::marketplace::{Listing, Marketplace};
+use crate::agent::plugin_engine::PluginEngine;
+use crate::agent::super_agent::SuperAgent;
+use crate::config::{ConfigWatcher, RuntimeConfig, ThemeName};
+use crate::errors::{ErrorReport, ProviderError};
+use crate::models::setup::ModelSetup;
+use crate::llm::guard::Completion;
+use crate::llm::params::InferenceParams;
+use crate::memory::attachments;
+use crate::memory::conversation::Conversation;
+use crate::memory::events::{EventBus, EventKind, SessionEvent, Subscription};
+use crate::memory::notes;
+use crate::memory::shared::SharedVars;
+use crate::memory::summary::{Summary, SummaryStore};
+use crate::models::compare::CompareColumn;
+use crate::models::server::Provider;
+use crate::tui::focus::{self, Action, Focus};
+use crate::tui::i18n::{self, t};
+use crate::tui::layout::BodyLayout;
+use crate::tui::palette::{self, UsageStore};
+use crate::tui::panes::{Axis, LayoutStore, Move, PaneKind, PaneLayout};
+use crate::tui::preview::{Preview, PreviewRegistry};
+use crate::tui::ring::Ring;
 use crate::tui::theme::{AppTheme, ThemeCatalog};
-use crate::tui::views::{agents::AgentsView, dashboard::DashboardView, models::ModelsView, settings::SettingsView, tasks::TasksView, ViewId};
-use crossterm::event::{self, Event as CEvent, KeyCode, KeyEvent, KeyModifiers};
-use ratatui::{backend::{Backend, CrosstermBackend}, layout::{Constraint, Direction, Layout, Rect}, style::{Color, Modifier, Style}, text::{Span, Spans}, widgets::{Block, Borders, List, ListItem, Paragraph, Wrap}, Frame, Terminal};
+use crate::workspace::ProjectProfile;
+use crate::types::{AgentInput, Attachment};
+use crate::tui::views::{agents::AgentsView, chat::ChatView, compare::CompareView, dashboard::DashboardView, file::FileView, memory::MemoryView, models::ModelsView, panels::{Panel, PanelsView, Source}, review::ReviewView, settings::SettingsView, skills::SkillsView, tasks::TasksView, ViewId};
+use crossterm::event::{self, Event as CEvent, KeyEvent};
+use ratatui::{backend::{Backend, CrosstermBackend}, layout::{Constraint, Direction, Layout, Rect}, style::{Color, Modifier, Style}, text::{Span, Spans}, widgets::{Block, Borders, Clear, List, ListItem, Paragraph, Wrap}, Frame, Terminal};
+use std::collections::{HashSet, VecDeque};
 use std::io;
+use std::sync::Arc;
+use tokio::sync::mpsc;
 use std::time::{Duration, Instant};
 
-const TICK_RATE_MS: u64 = 200;
+/// Tick rate while something is happening: a key was just pressed or a
+/// reply is on its way.
+const ACTIVE_TICK_MS: u64 = 30;
+/// Tick rate otherwise.
+const IDLE_TICK_MS: u64 = 500;
+/// How long after the last key the TUI stays on the active tick.
+const ACTIVE_WINDOW_MS: u64 = 1500;
+/// Typing pause before asking for an inline completion.
+const AUTOCOMPLETE_IDLE_MS: u64 = 400;
+/// Matches shown in the Quick Open list.
+const QUICK_OPEN_RESULTS: usize = 15;
+/// Rows of the pair-mode feed beneath the chat.
+const PAIR_LINES: usize = 4;
+/// Commentary lines kept for the pair-mode feed.
+const PAIR_HISTORY: usize = 200;
+/// Marks the model server's request log in the activity log.
+const SERVER_LOG: &str = "server: ";
+/// How often the chat endpoint is looked up again, in case the model server
+/// moved or the network came and went.
+const ENDPOINT_RECHECK: Duration = Duration::from_secs(5);
+
+/// What a tick changed: the parts always on screen (header, input,
+/// overlays, activity log, sidebar) and each view. A frame is drawn only
+/// when something on screen changed; a hidden view is drawn fresh when it is
+/// switched to.
+#[derive(Debug, Default)]
+struct Dirty {
+    screen: bool,
+    views: HashSet<ViewId>,
+}
+
+impl Dirty {
+    fn view(&mut self, view: ViewId) {
+        self.views.insert(view);
+    }
+}
+
+/// Ctrl+O fuzzy file finder state.
+struct QuickOpen {
+    query: String,
+    files: Vec<String>,
+    selected: usize,
+}
 
 pub struct TuiApp {
     pub config: RuntimeConfig,
+    pub profile: ProjectProfile,
     pub theme_catalog: ThemeCatalog,
     pub active_theme: AppTheme,
     pub view: ViewId,
+    /// Who gets keys when no overlay is open; see `focus()`.
+    focus: Focus,
+    /// Activity log entries scrolled past while the sidebar has focus.
+    sidebar_scroll: usize,
+    /// Latest error toast; its hint and causes show when expanded.
+    error: Option<ErrorReport>,
+    error_expanded: bool,
     pub last_tick: Instant,
+    /// Last key, paste or resize; the tick speeds up after one.
+    last_input: Instant,
+    /// Something changed since the last frame.
+    dirty: bool,
     pub input: String,
-    pub logs: Vec<String>,
-    pub notifications: Vec<String>,
+    pub logs: Ring<String>,
+    /// Only the model server's request log shows (`/logs server`).
+    logs_server_only: bool,
+    pub notifications: Ring<String>,
+    pub shared_vars: SharedVars,
+    pub events: EventBus,
+    pub subscription: Subscription,
+    /// Commentary of pair-mode runs, kept out of `subscription`'s log.
+    commentary: Subscription,
+    /// Latest commentary lines, oldest first.
+    pair_feed: std::collections::VecDeque<String>,
+    /// Pair mode: runs narrate into `pair_feed`, shown beneath the chat.
+    pair: bool,
+    pub conversations: Vec<Conversation>,
+    pub active_conversation: usize,
     pub dashboard: DashboardView,
+    /// Models from `models.preload`, loading since launch.
+    warmup: crate::models::preload::Warmup,
+    pub chat: ChatView,
     pub agents: AgentsView,
     pub models: ModelsView,
+    pub skills_view: SkillsView,
     pub tasks: TasksView,
+    pub memory: MemoryView,
+    pub review: ReviewView,
+    pub compare: CompareView,
     pub settings: SettingsView,
+    pub file: FileView,
+    pub panels: PanelsView,
+    pub task_store: crate::tasks::TaskStore,
+    /// Name layouts are saved under (`agent tui --session`).
+    session: String,
+    panes: PaneLayout,
+    layouts: LayoutStore,
+    /// Where the panes were last drawn, for Alt+arrow focus moves.
+    pane_area: Rect,
+    /// `/term` output shown in terminal panes.
+    terminal: Ring<String>,
+    shell_tx: mpsc::UnboundedSender<String>,
+    shell_rx: mpsc::UnboundedReceiver<String>,
+    /// Commands proposed for `!` requests coming back.
+    proposal_tx: mpsc::UnboundedSender<Result<crate::agent::shell::Proposal, ErrorReport>>,
+    proposal_rx: mpsc::UnboundedReceiver<Result<crate::agent::shell::Proposal, ErrorReport>>,
+    /// A proposed command waiting for confirmation.
+    shell_proposal: Option<crate::agent::shell::Proposal>,
+    quick_open: Option<QuickOpen>,
+    ghost: Option<String>,
+    last_edit: Instant,
+    completion_requested: String,
+    completion_tx: mpsc::UnboundedSender<(String, String)>,
+    completion_rx: mpsc::UnboundedReceiver<(String, String)>,
+    /// Log lines from pipelines started with `/pipeline run`.
+    run_tx: mpsc::UnboundedSender<String>,
+    run_rx: mpsc::UnboundedReceiver<String>,
+    /// Reports of runs started from the Chat view, with their tool calls,
+    /// for the conversation (by id) they were started in.
+    run_reply_tx: mpsc::UnboundedSender<(String, crate::types::Message)>,
+    run_reply_rx: mpsc::UnboundedReceiver<(String, crate::types::Message)>,
+    /// Questions from `human_input` nodes, answered with `/answer`.
+    human: HumanChannel,
+    human_rx: mpsc::UnboundedReceiver<Prompt>,
+    questions: VecDeque<HumanPrompt>,
+    /// Plan of a run waiting for approval.
+    plan_review: Option<PlanReview>,
+    /// Loaded on first `/skills`, then polled for changed files.
+    skills: Option<Arc<parking_lot::RwLock<PluginEngine>>>,
+    skills_checked: Instant,
+    /// Applies `[memory.retention]` to saved history in the background.
+    janitor: crate::memory::retention::Janitor,
+    /// Models appearing in or leaving `model_dir`.
+    model_watcher: crate::models::watcher::ModelDirWatcher,
+    /// Verdicts on models that just arrived in `model_dir`.
+    verify_tx: mpsc::UnboundedSender<Result<String, String>>,
+    verify_rx: mpsc::UnboundedReceiver<Result<String, String>>,
+    /// Skill index searches for the Skills view.
+    market_tx: mpsc::UnboundedSender<Result<Vec<Listing>, String>>,
+    market_rx: mpsc::UnboundedReceiver<Result<Vec<Listing>, String>>,
+    /// The model server `/serve` started; stopped when the TUI exits.
+    model_server: Option<crate::models::instance::Hosted>,
+    serve_tx: mpsc::UnboundedSender<Result<(crate::models::instance::Hosted, String), String>>,
+    serve_rx: mpsc::UnboundedReceiver<Result<(crate::models::instance::Hosted, String), String>>,
+    /// Requests the model server answered.
+    requests_tx: mpsc::UnboundedSender<crate::models::server::RequestLog>,
+    requests_rx: mpsc::UnboundedReceiver<crate::models::server::RequestLog>,
+    /// Refreshed panel content by panel id.
+    panel_tx: mpsc::UnboundedSender<(String, Result<String, String>)>,
+    panel_rx: mpsc::UnboundedReceiver<(String, Result<String, String>)>,
+    /// Sampling overrides for this session, set with `/params` or the
+    /// Settings sliders; see `inference_params`.
+    params: InferenceParams,
+    /// Files attached with `/attach` (or dropped), sent with the next chat message.
+    attachments: Vec<Attachment>,
+    /// Push-to-talk recording in progress, and transcripts on their way.
+    recording: Option<crate::models::whisper::Recording>,
+    voice_tx: mpsc::UnboundedSender<Result<String, ErrorReport>>,
+    voice_rx: mpsc::UnboundedReceiver<Result<String, ErrorReport>>,
+    setup_tx: mpsc::UnboundedSender<Result<ModelSetup, ErrorReport>>,
+    setup_rx: mpsc::UnboundedReceiver<Result<ModelSetup, ErrorReport>>,
+    /// `config.chat_endpoint()`, which reads the server state file and may
+    /// probe the network, so it is resolved off the UI thread and kept here.
+    endpoint: String,
+    /// When the endpoint was last looked up; `None` to look it up now.
+    endpoint_checked: Option<Instant>,
+    endpoint_tx: mpsc::UnboundedSender<String>,
+    endpoint_rx: mpsc::UnboundedReceiver<String>,
+    catalog_tx: mpsc::UnboundedSender<crate::llm::remote::Catalog>,
+    catalog_rx: mpsc::UnboundedReceiver<crate::llm::remote::Catalog>,
+    /// Summaries coming back: conversation id, messages replaced, summary.
+    summary_tx: mpsc::UnboundedSender<(String, usize, Result<String, ErrorReport>)>,
+    summary_rx: mpsc::UnboundedReceiver<(String, usize, Result<String, ErrorReport>)>,
+    /// Findings of a `/review` coming back.
+    review_tx: mpsc::UnboundedSender<Result<Vec<crate::agent::review::Finding>, ErrorReport>>,
+    review_rx: mpsc::UnboundedReceiver<Result<Vec<crate::agent::review::Finding>, ErrorReport>>,
+    /// Conversations with a summary in flight.
+    summarizing: std::collections::HashSet<String>,
+    /// Agent runs started here only record what mutating tools would do.
+    dry_run: bool,
+    /// Label and recorded actions of the latest dry run, for `/dryrun apply`.
+    last_dry_run: Option<(String, crate::tools::dry_run::Proposals)>,
+    /// The `/run --watch` loop and its runs so far.
+    watching: Option<(tokio::task::JoinHandle<()>, crate::agent::watch::WatchLog)>,
+    file_usage: UsageStore,
+    config_watcher: Option<ConfigWatcher>,
+    palette_usage: UsageStore,
+    palette_selected: usize,
+    previews: PreviewRegistry,
+    preview: Option<(String, Preview)>,
+    preview_requested: String,
+    preview_tx: mpsc::UnboundedSender<(String, Preview)>,
+    preview_rx: mpsc::UnboundedReceiver<(String, Preview)>,
+    compare_tx: mpsc::UnboundedSender<[CompareColumn; 2]>,
+    compare_rx: mpsc::UnboundedReceiver<[CompareColumn; 2]>,
+    /// Replies by conversation; `Some(id)` continues that truncated message.
+    chat_tx: mpsc::UnboundedSender<(String, Option<String>, Result<Completion, ErrorReport>)>,
+    chat_rx: mpsc::UnboundedReceiver<(String, Option<String>, Result<Completion, ErrorReport>)>,
+    /// User messages whose attachments were read at send time, or could not
+    /// be: (conversation, message id, message).
+    sent_tx: mpsc::UnboundedSender<(String, String, Result<crate::types::Message, ErrorReport>)>,
+    sent_rx: mpsc::UnboundedReceiver<(String, String, Result<crate::types::Message, ErrorReport>)>,
 }
 
 impl TuiApp {
-    pub fn new(config: RuntimeConfig) -> anyhow::Result<Self> {
+    pub fn new(config: RuntimeConfig, profile: ProjectProfile) -> anyhow::Result<Self> {
         let theme_catalog = ThemeCatalog::default();
         let active_theme = theme_catalog.resolve(&config.theme);
+        if !i18n::set_language(&config.ui.language) {
+            tracing::warn!("unknown ui.language {:?}, using {}", config.ui.language, i18n::language());
+        }
+        let events = EventBus::shared();
+        // under the layout session's name; `with_session` renames them
+        let subscription = events.subscribe("default", &[]);
+        let commentary = events.subscribe("default", &[EventKind::Commentary]);
+        let pair = config.pair_mode;
+        let (compare_tx, compare_rx) = mpsc::unbounded_channel();
+        let (chat_tx, chat_rx) = mpsc::unbounded_channel();
+        let (sent_tx, sent_rx) = mpsc::unbounded_channel();
+        let (preview_tx, preview_rx) = mpsc::unbounded_channel();
+        let (completion_tx, completion_rx) = mpsc::unbounded_channel();
+        let (run_tx, run_rx) = mpsc::unbounded_channel();
+        let (run_reply_tx, run_reply_rx) = mpsc::unbounded_channel();
+        let (market_tx, market_rx) = mpsc::unbounded_channel();
+        let (serve_tx, serve_rx) = mpsc::unbounded_channel();
+        let (requests_tx, requests_rx) = mpsc::unbounded_channel();
+        let (verify_tx, verify_rx) = mpsc::unbounded_channel();
+        let (panel_tx, panel_rx) = mpsc::unbounded_channel();
+        let (shell_tx, shell_rx) = mpsc::unbounded_channel();
+        let (proposal_tx, proposal_rx) = mpsc::unbounded_channel();
+        let (voice_tx, voice_rx) = mpsc::unbounded_channel();
+        let (setup_tx, setup_rx) = mpsc::unbounded_channel();
+        let (endpoint_tx, endpoint_rx) = mpsc::unbounded_channel();
+        let endpoint = config.llm_endpoint.clone();
+        let (catalog_tx, catalog_rx) = mpsc::unbounded_channel();
+        let (summary_tx, summary_rx) = mpsc::unbounded_channel();
+        let (review_tx, review_rx) = mpsc::unbounded_channel();
+        let (human, human_rx) = HumanChannel::remote();
+        let previews = PreviewRegistry::with_defaults(config.model_dir.clone());
+        let warmup = crate::models::preload::Warmup::spawn(&config.models.preload, config.model_dir.clone());
+        let model_watcher = crate::models::watcher::ModelDirWatcher::new(config.model_dir.clone());
+        let mut logs = Ring::new(config.ui.log_lines);
+        logs.push("SuperAgentCLI ready".to_string());
+        logs.extend(crate::transcript::current_run().map(|run| format!("transcript: agent transcript {}", run)));
+        let notifications = Ring::new(config.ui.notification_lines);
+        let terminal = Ring::new(config.ui.terminal_lines);
         Ok(Self {
             config,
+            profile,
             theme_catalog,
             active_theme,
             view: ViewId::Dashboard,
+            focus: Focus::for_view(ViewId::Dashboard),
+            sidebar_scroll: 0,
+            error: None,
+            error_expanded: false,
             last_tick: Instant::now(),
+            last_input: Instant::now(),
+            dirty: true,
             input: String::new(),
-            logs: vec!["SuperAgentCLI ready".into()],
-            notifications: vec![],
+            logs,
+            logs_server_only: false,
+            notifications,
+            shared_vars: SharedVars::new(),
+            events,
+            subscription,
+            commentary,
+            pair_feed: Default::default(),
+            pair,
+            conversations: vec![Conversation::new("chat")],
+            active_conversation: 0,
             dashboard: DashboardView::default(),
+            warmup,
+            chat: ChatView::default(),
             agents: AgentsView::default(),
             models: ModelsView::default(),
+            skills_view: SkillsView::default(),
             tasks: TasksView::default(),
+            memory: MemoryView::default(),
+            review: ReviewView::default(),
+            compare: CompareView::default(),
             settings: SettingsView::default(),
+            file: FileView::default(),
+            panels: PanelsView::default(),
+            task_store: crate::tasks::TaskStore::new(crate::paths::home()),
+            session: "default".into(),
+            panes: PaneLayout::single(PaneKind::View(ViewId::Dashboard)),
+            layouts: LayoutStore::open(Some(crate::paths::data_dir().join("layouts.json"))),
+            pane_area: Rect::default(),
+            terminal,
+            shell_tx,
+            shell_rx,
+            proposal_tx,
+            proposal_rx,
+            shell_proposal: None,
+            quick_open: None,
+            ghost: None,
+            last_edit: Instant::now(),
+            completion_requested: String::new(),
+            completion_tx,
+            completion_rx,
+            run_tx,
+            run_rx,
+            run_reply_tx,
+            run_reply_rx,
+            human,
+            human_rx,
+            questions: VecDeque::new(),
+            plan_review: None,
+            skills: None,
+            skills_checked: Instant::now(),
+            janitor: crate::memory::retention::Janitor::new(crate::paths::home()),
+            model_watcher,
+            verify_tx,
+            verify_rx,
+            market_tx,
+            market_rx,
+            model_server: None,
+            serve_tx,
+            serve_rx,
+            requests_tx,
+            requests_rx,
+            panel_tx,
+            panel_rx,
+            attachments: vec![],
+            params: InferenceParams::default(),
+            recording: None,
+            voice_tx,
+            voice_rx,
+            setup_tx,
+            setup_rx,
+            endpoint,
+            endpoint_checked: None,
+            endpoint_tx,
+            endpoint_rx,
+            catalog_tx,
+            catalog_rx,
+            summary_tx,
+            summary_rx,
+            review_tx,
+            review_rx,
+            summarizing: Default::default(),
+            dry_run: false,
+            last_dry_run: None,
+            watching: None,
+            file_usage: UsageStore::open(Some(crate::paths::cache_dir().join("quick_open_usage.json"))),
+            config_watcher: RuntimeConfig::path().map(ConfigWatcher::new),
+            palette_usage: UsageStore::open(Some(crate::paths::cache_dir().join("palette_usage.json"))),
+            palette_selected: 0,
+            previews,
+            preview: None,
+            preview_requested: String::new(),
+            preview_tx,
+            preview_rx,
+            compare_tx,
+            compare_rx,
+            chat_tx,
+            chat_rx,
+            sent_tx,
+            sent_rx,
         })
     }
 
+    /// Restore the pane layout saved for `session`; changes are saved back to it.
+    pub fn with_session(mut self, session: String) -> Self {
+        if let Some(layout) = self.layouts.get(&session) {
+            self.panes = layout.clone();
+            if let PaneKind::View(view) = self.panes.focused() {
+                self.view = view;
+            }
+            self.focus = Focus::for_view(self.view);
+        }
+        self.subscription.session = session.clone();
+        self.commentary.session = session.clone();
+        self.session = session;
+        self
+    }
+
+    /// Start in the File view with `files` open in tabs, each scrolled to
+    /// its line; the first is shown.
+    pub fn with_files(mut self, files: &[(String, Option<usize>)]) -> Self {
+        for (path, line) in files {
+            match self.file.open(path) {
+                Ok(()) => self.file.scroll_by(line.unwrap_or(1) as isize - 1),
+                Err(e) => self.notifications.push(format!("{}: {}", path, e)),
+            }
+        }
+        if !self.file.tabs.is_empty() {
+            self.file.active = 0;
+            self.view = ViewId::File;
+            self.focus = Focus::DiffView;
+        }
+        self
+    }
+
     pub async fn run(&mut self) -> anyhow::Result<()> {
+        self.check_model_setup();
+        self.refresh_catalog(false);
+        // Settings shows the key; a keyring lookup may be slow, so not in draw
+        tokio::spawn(crate::secrets::lookup(crate::secrets::LLM_API_KEY));
         let stdout = io::stdout();
         let _raw = RawModeGuard::enable()?;
         let backend = CrosstermBackend::new(stdout);
         let mut terminal = Terminal::new(backend)?;
         terminal.clear()?;
+        self.draw(&mut terminal)?;
+        crate::startup::mark("first frame");
 
         loop {
-            self.draw(&mut terminal)?;
-            let timeout = Duration::from_millis(TICK_RATE_MS);
-            if crossterm::event::poll(timeout)? {
-                if let CEvent::Key(key) = event::read()? {
-                    if self.handle_key(key)? {
-                        break;
-                    }
+            if std::mem::take(&mut self.dirty) {
+                self.draw(&mut terminal)?;
+            }
+            let rate = self.tick_rate();
+            if crossterm::event::poll(rate.saturating_sub(self.last_tick.elapsed()))? {
+                match event::read()? {
+                    CEvent::Key(key) if self.handle_key(key)? => break,
+                    CEvent::Paste(text) => self.handle_paste(&text),
+                    _ => {}
                 }
+                self.last_input = Instant::now();
+                self.dirty = true;
             }
-            if self.last_tick.elapsed() >= Duration::from_millis(TICK_RATE_MS) {
-                self.tick();
+            if self.last_tick.elapsed() >= rate {
+                if self.tick() {
+                    self.dirty = true;
+                }
                 self.last_tick = Instant::now();
             }
         }
 
+        if let Some(hosted) = self.model_server.take() {
+            hosted.shutdown().await;
+        }
         Ok(())
     }
 
@@ -79,13 +478,26 @@ impl TuiApp {
                 .constraints([
                     Constraint::Length(3),
                     Constraint::Min(10),
+                    Constraint::Length(if self.attachments.is_empty() { 0 } else { 1 }),
                     Constraint::Length(3),
                 ])
                 .split(size);
 
             self.render_header(f, layout[0]);
             self.render_body(f, layout[1]);
-            self.render_footer(f, layout[2]);
+            self.render_attachments(f, layout[2]);
+            self.render_footer(f, layout[3]);
+            if self.input.starts_with('/') {
+                self.render_palette(f, layout[1]);
+            }
+            if self.quick_open.is_some() {
+                self.render_quick_open(f, layout[1]);
+            }
+            if self.plan_review.is_some() {
+                self.render_plan_review(f, layout[1]);
+            } else if self.shell_proposal.is_some() {
+                self.render_shell_proposal(f, layout[1]);
+            }
         })?;
         Ok(())
     }
@@ -93,35 +505,42 @@ impl TuiApp {
     fn render_header<B: Backend>(&self, f: &mut Frame<B>, area: Rect) {
         let title = format!(" SuperAgentCLI • {} ", self.view.title());
         let subtitle = format!(
-            "Mode: {} | Theme: {}",
+            "Mode: {} | Focus: {} | Theme: {} | Project: {:?} | Layout: {} | Params: {}",
             self.view.name(),
-            self.active_theme.name
+            self.focus().name(),
+            self.active_theme.name,
+            self.profile.kind,
+            self.config.density.name(),
+            self.inference_params().summary()
         );
         let block = Block::default()
             .borders(Borders::ALL)
             .title(Span::styled(title, self.active_theme.title_style))
             .title_alignment(ratatui::layout::Alignment::Left);
-        let paragraph = Paragraph::new(Spans::from(vec![
-            Span::styled(subtitle, Style::default().fg(self.active_theme.muted_text)),
-        ]))
+        let mut spans = vec![Span::styled(subtitle, Style::default().fg(self.active_theme.muted_text))];
+        if self.dry_run {
+            spans.push(Span::raw(" "));
+            spans.push(Span::styled(" DRY RUN ", Style::default().fg(Color::Black).bg(Color::Yellow).add_modifier(Modifier::BOLD)));
+        }
+        if crate::net::offline() {
+            spans.push(Span::raw(" "));
+            spans.push(Span::styled(" OFFLINE ", Style::default().fg(Color::White).bg(Color::Blue).add_modifier(Modifier::BOLD)));
+        }
+        let paragraph = Paragraph::new(Spans::from(spans))
         .block(block)
         .alignment(ratatui::layout::Alignment::Left);
         f.render_widget(paragraph, area);
     }
 
     fn render_body<B: Backend>(&mut self, f: &mut Frame<B>, area: Rect) {
-        let sections = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints([
-                Constraint::Percentage(20),
-                Constraint::Percentage(60),
-                Constraint::Percentage(20),
-            ])
-            .split(area);
-
-        self.render_nav(f, sections[0]);
-        self.render_active_view(f, sections[1]);
-        self.render_sidebar(f, sections[2]);
+        let body = BodyLayout::compute(area, self.config.density);
+        if let Some(nav) = body.nav {
+            self.render_nav(f, nav);
+        }
+        self.render_panes(f, body.main);
+        if let Some(sidebar) = body.sidebar {
+            self.render_sidebar(f, sidebar);
+        }
     }
 
     fn render_nav<B: Backend>(&self, f: &mut Frame<B>, area: Rect) {
@@ -134,116 +553,2531 @@ impl TuiApp {
             ListItem::new(label)
         }).collect();
         let list = List::new(items)
-            .block(Block::default().borders(Borders::ALL).title("Workspace"))
+            .block(Block::default().borders(Borders::ALL).title(t("panel.workspace")))
             .highlight_style(Style::default().fg(self.active_theme.accent).add_modifier(Modifier::BOLD));
         f.render_widget(list, area);
     }
 
-    fn render_active_view<B: Backend>(&mut self, f: &mut Frame<B>, area: Rect) {
-        match self.view {
-            ViewId::Dashboard => self.dashboard.render(f, area, &self.active_theme),
+    /// Each pane in its share of `area`; the focused one gets accent edges.
+    fn render_panes<B: Backend>(&mut self, f: &mut Frame<B>, area: Rect) {
+        self.pane_area = area;
+        let rects = self.panes.rects(area);
+        let split = rects.len() > 1;
+        for (i, (kind, rect)) in self.panes.kinds().into_iter().zip(rects).enumerate() {
+            match kind {
+                PaneKind::View(view) => self.render_view(f, rect, view),
+                PaneKind::Logs => self.render_logs(f, rect),
+                PaneKind::Terminal => self.render_terminal(f, rect),
+            }
+            if split && i == self.panes.focus {
+                // leave the top edge alone, it carries the view's title
+                let edges = Block::default().borders(Borders::LEFT | Borders::RIGHT | Borders::BOTTOM).border_style(Style::default().fg(self.active_theme.accent));
+                f.render_widget(edges, rect);
+            }
+        }
+    }
+
+    /// Fast while a key was just pressed or an answer is streaming in, slow
+    /// when nothing is happening.
+    fn tick_rate(&self) -> Duration {
+        let busy = self.chat.pending || self.compare.pending || !self.summarizing.is_empty();
+        if busy || self.last_input.elapsed() < Duration::from_millis(ACTIVE_WINDOW_MS) {
+            Duration::from_millis(ACTIVE_TICK_MS)
+        } else {
+            Duration::from_millis(IDLE_TICK_MS)
+        }
+    }
+
+    /// The activity log under the current `/logs` filter.
+    fn shown_logs(&self) -> impl DoubleEndedIterator<Item = &String> {
+        self.logs.iter().filter(|l| !self.logs_server_only || l.starts_with(SERVER_LOG))
+    }
+
+    fn logs_title(&self) -> String {
+        if self.logs_server_only {
+            format!("{} · server", t("panel.activity"))
+        } else {
+            t("panel.activity").to_string()
+        }
+    }
+
+    fn render_logs<B: Backend>(&self, f: &mut Frame<B>, area: Rect) {
+        let rows = area.height.saturating_sub(2) as usize;
+        let mut items: Vec<ListItem> = self.shown_logs().rev().take(rows).map(|line| ListItem::new(line.as_str())).collect();
+        items.reverse();
+        f.render_widget(List::new(items).block(Block::default().borders(Borders::ALL).title(self.logs_title())), area);
+    }
+
+    /// The pair-mode commentary, dimmed so it reads as secondary to the chat.
+    fn render_pair_feed<B: Backend>(&self, f: &mut Frame<B>, area: Rect) {
+        let rows = area.height.saturating_sub(2) as usize;
+        let dim = Style::default().fg(self.active_theme.muted_text).add_modifier(Modifier::DIM);
+        let text: Vec<Spans> = if self.pair_feed.is_empty() {
+            vec![Spans::from(Span::styled(t("pair.empty"), dim))]
+        } else {
+            self.pair_feed.iter().skip(self.pair_feed.len().saturating_sub(rows)).map(|l| Spans::from(Span::styled(l.clone(), dim))).collect()
+        };
+        let block = Block::default().borders(Borders::TOP).border_style(dim).title(Span::styled(t("panel.pair"), dim));
+        f.render_widget(Paragraph::new(text).block(block), area);
+    }
+
+    fn render_terminal<B: Backend>(&self, f: &mut Frame<B>, area: Rect) {
+        let rows = area.height.saturating_sub(2) as usize;
+        let text = if self.terminal.is_empty() {
+            vec![Spans::from(Span::styled(t("terminal.empty"), Style::default().fg(self.active_theme.muted_text)))]
+        } else {
+            self.terminal.iter().skip(self.terminal.len().saturating_sub(rows)).map(|l| Spans::from(l.clone())).collect()
+        };
+        f.render_widget(Paragraph::new(text).block(Block::default().borders(Borders::ALL).title(t("panel.terminal"))), area);
+    }
+
+    fn render_view<B: Backend>(&mut self, f: &mut Frame<B>, area: Rect, view: ViewId) {
+        match view {
+            ViewId::Dashboard => self.dashboard.render(f, area, &self.active_theme, self.config.density),
+            ViewId::Chat if self.pair => {
+                let rows = Layout::default().direction(Direction::Vertical).constraints([Constraint::Min(5), Constraint::Length(PAIR_LINES as u16 + 2)]).split(area);
+                self.chat.render(f, rows[0], &self.active_theme, &self.conversations, self.active_conversation);
+                self.render_pair_feed(f, rows[1]);
+            }
+            ViewId::Chat => self.chat.render(f, area, &self.active_theme, &self.conversations, self.active_conversation),
             ViewId::Agents => self.agents.render(f, area, &self.active_theme),
             ViewId::Models => self.models.render(f, area, &self.active_theme),
+            ViewId::Skills => self.skills_view.render(f, area, &self.active_theme),
             ViewId::Tasks => self.tasks.render(f, area, &self.active_theme),
-            ViewId::Settings => self.settings.render(f, area, &self.active_theme, &self.config),
+            ViewId::Memory => self.memory.render(f, area, &self.active_theme, &self.profile.notes, self.config.memory.notes_budget),
+            ViewId::Review => self.review.render(f, area, &self.active_theme),
+            ViewId::Compare => self.compare.render(f, area, &self.active_theme),
+            ViewId::File => self.file.render(f, area, &self.active_theme),
+            ViewId::Panels => self.panels.render(f, area, &self.active_theme),
+            ViewId::Settings => self.settings.render(f, area, &self.active_theme, &self.config, &self.profile, &self.inference_params()),
         }
     }
 
     fn render_sidebar<B: Backend>(&self, f: &mut Frame<B>, area: Rect) {
         let sections = Layout::default()
             .direction(Direction::Vertical)
-            .constraints([Constraint::Percentage(55), Constraint::Percentage(45)])
+            .constraints([
+                Constraint::Percentage(45),
+                Constraint::Percentage(30),
+                Constraint::Percentage(25),
+            ])
             .split(area);
 
         let logs: Vec<ListItem> = self
-            .logs
-            .iter()
+            .shown_logs()
             .rev()
-            .take(8)
-            .map(|line| ListItem::new(line.clone()))
+            .skip(self.sidebar_scroll)
+            .take(sections[0].height.saturating_sub(2) as usize)
+            .map(|line| ListItem::new(line.to_string()))
             .collect();
+        let border = if self.focus() == Focus::Sidebar { Style::default().fg(self.active_theme.accent) } else { Style::default() };
         let log_list = List::new(logs)
-            .block(Block::default().borders(Borders::ALL).title("Activity"));
+            .block(Block::default().borders(Borders::ALL).border_style(border).title(self.logs_title()));
         f.render_widget(log_list, sections[0]);
 
-        let notes = Paragraph::new(self.notifications.join("\n"))
-            .block(Block::default().borders(Borders::ALL).title("Notifications"))
+        let mut notes: Vec<Spans> = self.notifications.iter().map(|n| Spans::from(n.clone())).collect();
+        if let Some(report) = self.error.as_ref().filter(|r| r.hint.is_some() || !r.details.is_empty()) {
+            let muted = Style::default().fg(self.active_theme.muted_text);
+            if self.error_expanded {
+                notes.push(Spans::from(Span::styled(format!("▾ {} {}", t("error.details"), report.code), muted)));
+                notes.extend(report.details.iter().map(|d| Spans::from(format!("  {}", d))));
+                if let Some(hint) = &report.hint {
+                    notes.push(Spans::from(Span::styled(format!("  → {}", hint), Style::default().fg(self.active_theme.accent))));
+                }
+            } else {
+                notes.push(Spans::from(Span::styled(format!("▸ {} (/details)", t("error.details")), muted)));
+            }
+        }
+        let notes = Paragraph::new(notes)
+            .block(Block::default().borders(Borders::ALL).title(t("panel.notifications")))
             .style(Style::default().fg(self.active_theme.muted_text))
             .wrap(Wrap { trim: true });
         f.render_widget(notes, sections[1]);
+
+        let vars: Vec<ListItem> = if self.shared_vars.is_empty() {
+            vec![ListItem::new(t("vars.empty"))]
+        } else {
+            self.shared_vars
+                .list()
+                .into_iter()
+                .map(|(k, v)| ListItem::new(format!("{} = {}", k, v)))
+                .collect()
+        };
+        let var_list = List::new(vars)
+            .block(Block::default().borders(Borders::ALL).title(t("panel.shared_vars")))
+            .style(Style::default().fg(self.active_theme.text));
+        f.render_widget(var_list, sections[2]);
+    }
+
+    /// Pending attachments as numbered chips; `/attach rm <n>` removes one.
+    fn render_attachments<B: Backend>(&self, f: &mut Frame<B>, area: Rect) {
+        let chip = Style::default().fg(self.active_theme.text).bg(Color::DarkGray);
+        let mut spans = vec![];
+        for (i, a) in self.attachments.iter().enumerate() {
+            spans.push(Span::styled(format!(" {}: {} ", i + 1, attachments::chip(a)), chip));
+            spans.push(Span::raw(" "));
+        }
+        f.render_widget(Paragraph::new(Spans::from(spans)), area);
     }
 
     fn render_footer<B: Backend>(&self, f: &mut Frame<B>, area: Rect) {
-        let hint = "[Tab] Switch view  [/] Command  [Ctrl+S] Save  [Q] Quit";
-        let input = Paragraph::new(self.input.as_str())
-            .block(Block::default().borders(Borders::ALL).title("Command"))
+        let persona = self.conversations[self.active_conversation].persona_label();
+        let hint = format!("{}: {}  {}", t("footer.persona"), persona, t(self.focus().hint_key()));
+        let mut spans = vec![Span::raw(self.input.as_str())];
+        if let Some(ghost) = &self.ghost {
+            spans.push(Span::styled(ghost.as_str(), Style::default().fg(self.active_theme.muted_text)));
+        }
+        let input = Paragraph::new(Spans::from(spans))
+            .block(Block::default().borders(Borders::ALL).title(if self.recording.is_some() { t("panel.recording") } else { t("panel.command") }))
             .style(Style::default().fg(self.active_theme.text));
-        let overlay = Paragraph::new(Span::styled(hint, Style::default().fg(Color::Gray)))
+        let overlay = Paragraph::new(Span::styled(hint.as_str(), Style::default().fg(Color::Gray)))
             .alignment(ratatui::layout::Alignment::Center);
         f.render_widget(input, area);
         f.render_widget(overlay, area);
     }
 
-    fn handle_key(&mut self, key: KeyEvent) -> anyhow::Result<bool> {
-        match key.code {
-            KeyCode::Char('q') if key.modifiers == KeyModifiers::NONE => return Ok(true),
-            KeyCode::Tab => {
-                self.view = self.view.next();
-                self.logs.push(format!("Switched to {}", self.view.title()));
+    /// The palette is open while the command word is being typed.
+    fn palette_open(&self) -> bool {
+        self.input.starts_with('/') && !self.input.contains(' ')
+    }
+
+    /// Selectable palette entries: recent commands first when nothing has
+    /// been typed yet, otherwise ranked matches.
+    fn palette_items(&self) -> Vec<&'static str> {
+        let ranked = palette::rank(&self.input, palette::COMMANDS, &self.palette_usage);
+        if self.input != "/" {
+            return ranked;
+        }
+        let recent = self.palette_recent();
+        recent.iter().copied().chain(ranked.into_iter().filter(|c| !recent.contains(c))).collect()
+    }
+
+    /// Recently used commands that this build still knows about.
+    fn palette_recent(&self) -> Vec<&'static str> {
+        self.palette_usage
+            .recent()
+            .into_iter()
+            .filter_map(|r| palette::COMMANDS.iter().find(|(name, _)| *name == r).map(|(name, _)| *name))
+            .collect()
+    }
+
+    /// Command and arguments the preview pane should describe: the
+    /// highlighted entry while the command word is typed, else the typed one.
+    fn palette_target(&self) -> Option<(String, String)> {
+        if self.palette_open() {
+            let cmd = self.palette_items().get(self.palette_selected)?.to_string();
+            return Some((cmd, String::new()));
+        }
+        let (head, rest) = self.input.split_once(' ')?;
+        head.starts_with('/').then(|| (head.to_string(), rest.trim().to_string()))
+    }
+
+    /// Ask the preview provider for the current target unless already asked.
+    fn request_preview(&mut self) {
+        let Some((cmd, args)) = self.palette_target().filter(|(cmd, _)| self.previews.handles(cmd)) else {
+            self.preview = None;
+            return;
+        };
+        let key = format!("{} {}", cmd, args);
+        if self.preview_requested == key {
+            return;
+        }
+        self.preview_requested = key.clone();
+        let registry = self.previews.clone();
+        let tx = self.preview_tx.clone();
+        tokio::spawn(async move {
+            if let Some(preview) = registry.preview(&cmd, &args).await {
+                let _ = tx.send((key, preview));
+            }
+        });
+    }
+
+    /// The plan waiting for approval, over the middle of the body.
+    fn render_plan_review<B: Backend>(&self, f: &mut Frame<B>, body: Rect) {
+        let Some(review) = &self.plan_review else { return };
+        let width = body.width.saturating_sub(8).min(100);
+        let height = (review.plan.lines().count() as u16 + 4).min(body.height);
+        let area = Rect { x: body.x + (body.width - width) / 2, y: body.y + (body.height - height) / 2, width, height };
+        let mut lines: Vec<Spans> = review.plan.lines().map(|l| Spans::from(l.to_string())).collect();
+        lines.push(Spans::from(""));
+        lines.push(Spans::from(Span::styled(t("keys.plan"), Style::default().fg(self.active_theme.muted_text))));
+        let title = format!("Approve plan? {}", review.estimate.describe());
+        f.render_widget(Clear, area);
+        f.render_widget(
+            Paragraph::new(lines)
+                .block(Block::default().borders(Borders::ALL).title(Span::styled(title, Style::default().fg(self.active_theme.accent))))
+                .style(Style::default().fg(self.active_theme.text))
+                .wrap(Wrap { trim: false }),
+            area,
+        );
+    }
+
+    /// The command proposed for a `!` request, over the middle of the body.
+    fn render_shell_proposal<B: Backend>(&self, f: &mut Frame<B>, body: Rect) {
+        let Some(proposal) = &self.shell_proposal else { return };
+        let mut lines = vec![
+            Spans::from(Span::styled(format!("$ {}", proposal.command), Style::default().fg(self.active_theme.accent).add_modifier(Modifier::BOLD))),
+            Spans::from(""),
+            Spans::from(proposal.explanation.clone()),
+        ];
+        if proposal.destructive {
+            lines.push(Spans::from(Span::styled("This command changes or deletes files.", Style::default().fg(Color::Yellow))));
+        }
+        lines.push(Spans::from(""));
+        lines.push(Spans::from(Span::styled(t("keys.shell"), Style::default().fg(self.active_theme.muted_text))));
+        let width = body.width.saturating_sub(8).min(100);
+        let height = (lines.len() as u16 + 2 + proposal.command.len() as u16 / width.max(1)).min(body.height);
+        let area = Rect { x: body.x + (body.width - width) / 2, y: body.y + (body.height - height) / 2, width, height };
+        f.render_widget(Clear, area);
+        f.render_widget(
+            Paragraph::new(lines)
+                .block(Block::default().borders(Borders::ALL).title(format!("Run for \"{}\"?", proposal.intent)))
+                .style(Style::default().fg(self.active_theme.text))
+                .wrap(Wrap { trim: false }),
+            area,
+        );
+    }
+
+    fn render_palette<B: Backend>(&self, f: &mut Frame<B>, body: Rect) {
+        let mut rows: Vec<ListItem> = vec![];
+        if self.palette_open() {
+            let items = self.palette_items();
+            let recent_len = if self.input == "/" { self.palette_recent().len() } else { 0 };
+            let describe = |name: &str| palette::COMMANDS.iter().find(|(n, _)| *n == name).map(|(_, d)| *d).unwrap_or("");
+            for (i, name) in items.iter().enumerate() {
+                if recent_len > 0 && (i == 0 || i == recent_len) {
+                    let header = if i == 0 { "Recent" } else { "Commands" };
+                    rows.push(ListItem::new(Span::styled(header, Style::default().fg(self.active_theme.muted_text))));
+                }
+                let style = if i == self.palette_selected {
+                    Style::default().fg(self.active_theme.accent).add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default().fg(self.active_theme.text)
+                };
+                rows.push(ListItem::new(Spans::from(vec![
+                    Span::styled(format!("{:<10}", name), style),
+                    Span::styled(describe(name), Style::default().fg(self.active_theme.muted_text)),
+                ])));
             }
-            KeyCode::Char('/') => {
-                self.input.clear();
-                self.logs.push("Command palette opened".into());
+            if rows.is_empty() {
+                rows.push(ListItem::new("no matching command"));
             }
-            KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                self.config.save()?;
-                self.logs.push("Configuration saved".into());
+        }
+        let preview = self.preview.as_ref().filter(|(key, _)| *key == self.preview_requested).map(|(_, p)| self.preview_rows(p));
+        let preview_len = preview.as_ref().map(Vec::len).unwrap_or(0);
+        if rows.is_empty() && preview.is_none() {
+            return;
+        }
+
+        let height = (rows.len().max(preview_len) as u16 + 2).min(body.height);
+        let width = body.width.saturating_sub(4).min(if preview.is_some() { 120 } else { 72 });
+        let area = Rect { x: body.x + 2, y: body.y + body.height - height, width, height };
+        f.render_widget(Clear, area);
+        let (list_area, preview_area) = match (rows.is_empty(), preview.is_some()) {
+            (false, true) => {
+                let halves = Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+                    .split(area);
+                (Some(halves[0]), Some(halves[1]))
             }
-            KeyCode::Enter => {
-                if !self.input.trim().is_empty() {
-                    self.logs.push(format!("Command: {}", self.input.trim()));
-                    self.input.clear();
+            (false, false) => (Some(area), None),
+            (true, _) => (None, Some(area)),
+        };
+        if let Some(a) = list_area {
+            f.render_widget(List::new(rows).block(Block::default().borders(Borders::ALL).title("Commands")), a);
+        }
+        if let (Some(a), Some(lines)) = (preview_area, preview) {
+            f.render_widget(List::new(lines).block(Block::default().borders(Borders::ALL).title("Preview")), a);
+        }
+    }
+
+    fn preview_rows(&self, preview: &Preview) -> Vec<ListItem<'static>> {
+        match preview {
+            Preview::Text(lines) => lines.iter().map(|l| ListItem::new(l.clone())).collect(),
+            Preview::Swatches(themes) => themes
+                .iter()
+                .map(|(name, colors)| {
+                    let mut spans: Vec<Span> = colors.iter().map(|c| Span::styled("██", Style::default().fg(*c))).collect();
+                    spans.push(Span::styled(format!(" {}", name), Style::default().fg(colors[0])));
+                    ListItem::new(Spans::from(spans))
+                })
+                .collect(),
+        }
+    }
+
+    /// `/image <path>` attaches an image to the next chat message;
+    /// `/image clear` drops the pending ones.
+    fn run_image_command(&mut self, args: &str) {
+        use crate::types::ImagePart;
+        let path = std::path::PathBuf::from(args.trim());
+        match args.trim() {
+            "" => self.notifications.push("usage: /image <path> | clear".into()),
+            "clear" => self.attachments.retain(|a| a.mode != crate::types::AttachMode::Image),
+            _ if !ImagePart::is_image_path(&path) => self.notifications.push(format!("{} is not a png, jpeg, gif or webp image", path.display())),
+            _ => self.attach(&path),
+        }
+    }
+
+    /// `/attach <path>` adds a file to the next chat message; `/attach rm <n>`
+    /// and `/attach clear` remove pending ones; `/attach` lists them.
+    fn run_attach_command(&mut self, args: &str) {
+        match args.split_whitespace().collect::<Vec<_>>().as_slice() {
+            [] => {
+                if self.attachments.is_empty() {
+                    self.logs.push("no attachments".into());
                 }
+                let lines: Vec<String> = self.attachments.iter().enumerate().map(|(i, a)| format!("attachment {}: {} sha256 {}", i + 1, a.path.display(), &a.sha256[..12])).collect();
+                self.logs.extend(lines);
             }
-            KeyCode::Backspace => {
-                self.input.pop();
+            ["clear"] => self.attachments.clear(),
+            ["rm", n] => match n.parse::<usize>().ok().filter(|n| (1..=self.attachments.len()).contains(n)) {
+                Some(n) => {
+                    let a = self.attachments.remove(n - 1);
+                    self.logs.push(format!("removed attachment {}", a.path.display()));
+                }
+                None => self.notifications.push(format!("no attachment {}", n)),
+            },
+            _ => match attachments::dropped_path(args) {
+                Some(path) => self.attach(&path),
+                None => self.notifications.push(format!("{} not found", args.trim())),
+            },
+        }
+    }
+
+    /// `/params` shows the overrides, `/params set <name> <value>` and
+    /// `/params reset [name]` change them.
+    fn run_params_command(&mut self, args: &str) {
+        let result = match args.split_whitespace().collect::<Vec<_>>().as_slice() {
+            [] => Ok(()),
+            ["set", name, value] => self.params.set(name, value),
+            ["reset"] => self.params.reset(None),
+            ["reset", name] => self.params.reset(Some(name)),
+            _ => Err(anyhow::anyhow!("usage: /params [set <name> <value> | reset [name]]")),
+        };
+        match result {
+            Ok(()) => self.logs.push(format!("params: {}", self.inference_params().summary())),
+            Err(e) => self.notifications.push(e.to_string()),
+        }
+    }
+
+    /// `[inference]` from config.toml with this session's overrides on top.
+    fn inference_params(&self) -> InferenceParams {
+        self.config.inference.overlay(&self.params)
+    }
+
+    /// Move the selected Settings slider, starting from the effective value.
+    fn nudge_param(&mut self, steps: i32) {
+        let name = self.settings.selected_param();
+        let mut effective = self.inference_params();
+        effective.nudge(name, steps);
+        if let Some(v) = effective.get(name) {
+            let _ = self.params.set(name, &v.to_string());
+        }
+    }
+
+    fn attach(&mut self, path: &std::path::Path) {
+        match attachments::inspect(path) {
+            Ok(a) if self.attachments.iter().any(|p| p.path == a.path) => self.notifications.push(format!("{} is already attached", a.path.display())),
+            Ok(a) => {
+                self.logs.push(format!("attached {}", attachments::chip(&a)));
+                self.attachments.push(a);
             }
-            KeyCode::Char(c) => {
-                self.input.push(c);
+            Err(e) => self.report_error("attach", &e),
+        }
+    }
+
+    /// A pasted file path (what terminals send for drag and drop) becomes an
+    /// attachment; other text goes into the input.
+    fn handle_paste(&mut self, text: &str) {
+        match attachments::dropped_path(text) {
+            Some(path) if !self.input.starts_with('/') => self.attach(&path),
+            _ => {
+                self.input.push_str(text.trim_end_matches(['\r', '\n']));
+                self.input_edited();
+            }
+        }
+    }
+
+    /// Push-to-talk: the first Ctrl+R starts recording, the second stops it
+    /// and inserts the transcript into the input.
+    fn toggle_recording(&mut self) {
+        match self.recording.take() {
+            Some(recording) => {
+                self.logs.push("transcribing…".into());
+                let tx = self.voice_tx.clone();
+                tokio::spawn(async move {
+                    let _ = tx.send(recording.finish().await.map_err(|e| ErrorReport::from_anyhow(&e)));
+                });
+            }
+            None => match self.config.models.whisper.record() {
+                Ok(recording) => self.recording = Some(recording),
+                Err(e) => self.report_error("voice input", &e),
+            },
+        }
+    }
+
+    fn input_edited(&mut self) {
+        self.palette_selected = 0;
+        self.ghost = None;
+        self.last_edit = Instant::now();
+    }
+
+    /// After a typing pause, ask the model to continue the input. Slash
+    /// commands and empty input never get suggestions.
+    fn request_completion(&mut self) {
+        if !self.config.ui.autocomplete
+            || self.input.trim().len() < 3
+            || self.input.starts_with('/')
+            || self.ghost.is_some()
+            || self.completion_requested == self.input
+            || self.last_edit.elapsed() < Duration::from_millis(AUTOCOMPLETE_IDLE_MS)
+        {
+            return;
+        }
+        self.completion_requested = self.input.clone();
+        let prefix = self.input.clone();
+        // a preloaded native model under the chat's model name answers
+        // in-process; anything else goes to the endpoint
+        let provider = self.warmup.provider(&self.config.llm_model).unwrap_or_else(|| {
+            Arc::new(crate::llm::llama::LlamaClient::new(self.chat_endpoint(), self.config.llm_model.clone()))
+        });
+        let tx = self.completion_tx.clone();
+        tokio::spawn(async move {
+            if let Some(suggestion) = crate::llm::autocomplete::suggest(provider.as_ref(), &prefix).await {
+                let _ = tx.send((prefix, suggestion));
+            }
+        });
+    }
+
+    /// `/autocomplete [on|off]` toggles inline completions.
+    fn run_autocomplete_command(&mut self, args: &str) {
+        self.config.ui.autocomplete = match args {
+            "on" => true,
+            "off" => false,
+            _ => !self.config.ui.autocomplete,
+        };
+        self.ghost = None;
+        let state = if self.config.ui.autocomplete { "on" } else { "off" };
+        self.logs.push(format!("Autocomplete {}", state));
+    }
+
+    /// Answer the pending plan review.
+    fn decide_plan(&mut self, decision: PlanDecision) {
+        let Some(review) = self.plan_review.take() else {
+            self.notifications.push("no plan waiting for approval".into());
+            return;
+        };
+        self.logs.push(match &decision {
+            PlanDecision::Approve => "Plan approved".to_string(),
+            PlanDecision::Edit(plan) => format!("Plan edited ({})", PlanEstimate::of(plan).describe()),
+            PlanDecision::Abort => "Plan rejected; run aborted".to_string(),
+        });
+        let _ = review.reply.send(decision);
+    }
+
+    /// `/dryrun [on|off]` toggles dry runs for agent runs started here;
+    /// `/dryrun apply` carries out the actions the latest dry run proposed.
+    fn run_dry_run_command(&mut self, args: &str) {
+        match args {
+            "apply" => match self.last_dry_run.take() {
+                Some((label, proposals)) => {
+                    self.dry_run = false;
+                    let mut sa = SuperAgent::new();
+                    self.configure_run(&mut sa);
+                    self.logs.push(format!("applying {} proposed action(s) of {}", proposals.list().len(), label));
+                    let tx = self.run_tx.clone();
+                    tokio::task::spawn_blocking(move || {
+                        let line = match sa.apply_proposals(&proposals) {
+                            Ok(report) => format!("{} applied:\n{}", label, report),
+                            Err(e) => format!("{} apply failed: {}", label, e),
+                        };
+                        let _ = tx.send(line);
+                    });
+                }
+                None => self.notifications.push("no dry run to apply".into()),
+            },
+            _ => {
+                self.dry_run = match args {
+                    "on" => true,
+                    "off" => false,
+                    _ => !self.dry_run,
+                };
+                self.logs.push(format!("Dry run {}", if self.dry_run { "on: mutating tools only record what they would do" } else { "off" }));
+            }
+        }
+    }
+
+    /// `/tools enable|disable <name>` switches a tool on or off for agent
+    /// runs (Ctrl+S keeps it in `tools.disabled`); `/tools` alone opens the
+    /// Tools panel in the Agents view.
+    fn run_tools_command(&mut self, args: &str) {
+        let (on, name) = match args.split_once(' ') {
+            Some(("enable", name)) => (true, name.trim()),
+            Some(("disable", name)) => (false, name.trim()),
+            _ => {
+                self.view = ViewId::Agents;
+                self.focus = Focus::for_view(self.view);
+                return;
+            }
+        };
+        if !SuperAgent::new().tool_names().iter().any(|t| t == name) {
+            self.report_error("tools", &crate::errors::ToolError::NotFound(name.to_string()).into());
+            return;
+        }
+        if on && self.profile.policy.tools.get(name) == Some(&false) {
+            self.report_error("tools", &crate::errors::ToolError::Disabled(name.to_string()).into());
+            return;
+        }
+        let disabled = &mut self.config.tools.disabled;
+        disabled.retain(|t| t != name);
+        if !on {
+            disabled.push(name.to_string());
+        }
+        crate::tools::audit::configure(&self.config.tools, &crate::paths::home());
+        self.agents.tools_refreshed = None;
+        self.logs.push(format!("Tool {} {}", name, if on { "enabled" } else { "disabled" }));
+    }
+
+    /// `/template [list]`, `/template new <name>` (creates a pending task)
+    /// and `/template save <task-id> <name>`.
+    fn run_template_command(&mut self, args: &str) {
+        let templates = crate::tasks::templates::TemplateStore::new(self.task_store.home());
+        let parts: Vec<&str> = args.split_whitespace().collect();
+        let result = match parts.as_slice() {
+            [] | ["list"] => {
+                let names: Vec<String> = templates.all(&self.config.templates).into_keys().collect();
+                Ok(format!("templates: {}", if names.is_empty() { "(none)".into() } else { names.join(", ") }))
+            }
+            ["new", name] => templates.get(name, &self.config.templates).and_then(|t| {
+                let task = t.instantiate();
+                self.task_store.save(&task)?;
+                Ok(format!("created task {} from {}", &task.id[..8], name))
+            }),
+            ["save", id, name] => self.task_store.find(id).and_then(|task| {
+                templates.save(name, &crate::tasks::templates::TaskTemplate::from_task(&task))?;
+                Ok(format!("saved {} as template {}", &task.id[..8], name))
+            }),
+            _ => Err(anyhow::anyhow!("usage: /template [list] | new <name> | save <task-id> <name>")),
+        };
+        match result {
+            Ok(msg) => self.logs.push(msg),
+            Err(e) => self.notifications.push(e.to_string()),
+        }
+    }
+
+    /// Edit the Agents view's pipeline; `save` makes it runnable with
+    /// `agent run --pipeline <name>`.
+    fn run_graph_command(&mut self, args: &str) {
+        use crate::graph::pipeline::{EdgeCondition, Pipeline, PipelineNode, PipelineStore};
+        let store = PipelineStore::new(self.task_store.home());
+        let tools = SuperAgent::new().tool_names();
+        let pipeline = &mut self.agents.pipeline;
+        let parts: Vec<&str> = args.split_whitespace().collect();
+        let result = match parts.as_slice() {
+            ["add", name, role] | ["add", name, role, "after", _] => {
+                let node = PipelineNode::new(*name, *role);
+                pipeline.add_node(node, parts.get(4).copied()).map(|_| format!("added node {}", name))
+            }
+            ["ask", name, question @ ..] if !question.is_empty() => pipeline
+                .add_node(PipelineNode::human(*name, question.join(" ")), None)
+                .map(|_| format!("added human input node {}; connect it with /graph edge", name)),
+            ["rm", name] => pipeline.remove_node(name).map(|_| format!("removed node {}", name)),
+            ["edge", from, to] | ["edge", from, to, _] => EdgeCondition::parse(parts.get(3).copied().unwrap_or("always"))
+                .and_then(|c| pipeline.set_edge(from, to, c))
+                .map(|_| format!("edge {} -> {}", from, to)),
+            ["unedge", from, to] => pipeline.remove_edge(from, to).map(|_| format!("removed edge {} -> {}", from, to)),
+            ["save", name] => store.save(name, pipeline).map(|p| {
+                self.agents.pipeline_name = Some(name.to_string());
+                format!("saved pipeline {} to {}", name, p.display())
+            }),
+            ["load", name] => store.load(name, &tools).map(|p| {
+                *pipeline = p;
+                self.agents.pipeline_name = Some(name.to_string());
+                format!("loaded pipeline {}", name)
+            }),
+            ["reset"] => {
+                *pipeline = Pipeline::default();
+                self.agents.pipeline_name = None;
+                Ok("pipeline reset".into())
+            }
+            _ => Err(anyhow::anyhow!("usage: /graph add <name> <role> [after <node>] | ask <name> <question> | rm <name> | edge <from> <to> [always|success|failure|contains:<text>] | unedge <from> <to> | save <name> | load <name> | reset")),
+        };
+        match result {
+            Ok(msg) => self.logs.push(msg),
+            Err(e) => self.notifications.push(e.to_string()),
+        }
+    }
+
+    /// `/pipeline [list]` or `/pipeline run <name> <goal>`; runs are recorded
+    /// as tasks and report back in the log.
+    fn run_pipeline_command(&mut self, args: &str) {
+        let store = crate::graph::pipeline::PipelineStore::new(self.task_store.home());
+        let mut sa = SuperAgent::new();
+        let tools = sa.tool_names();
+        let mut parts = args.splitn(3, char::is_whitespace);
+        match (parts.next(), parts.next(), parts.next()) {
+            (None | Some("list"), None, None) => {
+                let listed = store.list(&tools);
+                if listed.is_empty() {
+                    self.logs.push(format!("no pipelines in {}", crate::paths::home().join("pipelines").display()));
+                }
+                for (name, p) in listed {
+                    match p {
+                        Ok(p) => self.logs.push(format!("pipeline {}: {}", name, p.order().unwrap_or_default().join(" → "))),
+                        Err(e) => self.notifications.push(format!("pipeline {} invalid: {}", name, e)),
+                    }
+                }
+            }
+            (Some("run"), Some(name), Some(goal)) => match store.load(name, &tools) {
+                Ok(pipeline) => {
+                    sa.pipeline = Some(pipeline);
+                    self.start_run(sa, format!("pipeline {}", name), goal.trim());
+                }
+                Err(e) => self.notifications.push(e.to_string()),
+            },
+            _ => self.notifications.push("usage: /pipeline [list] | run <name> <goal>".into()),
+        }
+    }
+
+    /// Run `goal` on `sa` in the background with this session's model,
+    /// project and settings.
+    fn start_run(&mut self, mut sa: SuperAgent, label: String, goal: &str) {
+        self.configure_run(&mut sa);
+        if self.dry_run {
+            let proposals = crate::tools::dry_run::Proposals::default();
+            sa.dry_run = Some(proposals.clone());
+            self.last_dry_run = Some((label.clone(), proposals));
+            self.logs.push(format!("{} started as a dry run; `/dryrun apply` carries out what it proposes", label));
+        } else {
+            self.logs.push(format!("{} started", label));
+        }
+        let (goal, tx) = (goal.to_string(), self.run_tx.clone());
+        // runs started from a conversation answer in it, tool calls attached
+        let reply = (self.view == ViewId::Chat).then(|| (self.conversations[self.active_conversation].id.clone(), self.run_reply_tx.clone()));
+        tokio::spawn(crate::request_id::scope(crate::request_id::new_id(), async move {
+            let result = sa.run_goal(goal).await;
+            if let Some((conv_id, reply_tx)) = reply {
+                let text = match &result {
+                    Ok(report) => report.clone(),
+                    Err(e) => format!("{} failed: {}{}", label, e, crate::request_id::suffix()),
+                };
+                let mut msg = crate::types::Message::new("assistant", text);
+                msg.tool_calls = sa.recorder.tool_calls();
+                let _ = reply_tx.send((conv_id, msg));
+            }
+            let line = match result {
+                Ok(report) => format!("{} finished:\n{}", label, report),
+                Err(e) => format!("{} failed: {}{}", label, e, crate::request_id::suffix()),
+            };
+            let _ = tx.send(line);
+        }));
+    }
+
+    /// `/review [base]`: review this branch's changes against `base` in the
+    /// background and list the findings in the Review view.
+    fn run_review_command(&mut self, args: &str) {
+        let base = if args.is_empty() { self.config.review.base.clone() } else { args.to_string() };
+        let Ok(root) = std::env::current_dir() else { return };
+        let llm = crate::llm::llama::LlamaClient::new(self.chat_endpoint(), self.config.llm_model.clone());
+        let (config, tx) = (self.config.review.clone(), self.review_tx.clone());
+        self.review.status = format!("reviewing against {}…", base);
+        self.view = ViewId::Review;
+        self.focus = Focus::ReviewPanel;
+        tokio::spawn(async move {
+            let result = async {
+                let diff = crate::agent::review::git_diff(&root, &base)?;
+                if diff.trim().is_empty() {
+                    anyhow::bail!("no changes against {}", base);
+                }
+                crate::agent::review::review(&llm, &diff, &config).await
+            }
+            .await;
+            let _ = tx.send(result.map_err(|e| ErrorReport::from_anyhow(&e)));
+        });
+    }
+
+    /// Open the selected finding's file at its line.
+    fn open_finding(&mut self) {
+        let Some(finding) = self.review.selected().cloned() else { return };
+        match self.file.open(&finding.file) {
+            Ok(()) => {
+                self.file.scroll_by(finding.line.unwrap_or(1) as isize - 1);
+                self.view = ViewId::File;
+                self.focus = Focus::DiffView;
+            }
+            Err(e) => self.notifications.push(format!("{}: {}", finding.file, e)),
+        }
+    }
+
+    fn configure_run(&self, sa: &mut SuperAgent) {
+        sa.connect(&self.chat_endpoint(), &self.config.llm_model);
+        sa.profile = self.profile.clone();
+        // as `agent run` does: `files` from config.toml here, the project's
+        // `[policy] writable` when the tools are registered
+        let root = std::env::current_dir().unwrap_or_else(|_| ".".into());
+        sa.file_policy = crate::tools::policy::FileAccessPolicy::new(root, &self.config.files);
+        sa.tasks = Some(self.task_store.clone());
+        // `/var` and the Shared panel see what the run's tools set, and back
+        sa.shared_vars = self.shared_vars.clone();
+        sa.retry = self.config.retry_for(None);
+        sa.router = crate::agent::router::Router::new(self.config.router.clone());
+        sa.notes_budget = self.config.memory.notes_budget;
+        sa.approve_plan = self.config.approve_plan_for(&self.profile);
+        sa.clarify_questions = self.config.clarify_questions;
+        sa.human = self.human.clone();
+        sa.commentary = self.pair;
+        sa.events = self.events.clone();
+    }
+
+    /// `/pair [on|off]`: narrate agent runs started from now on beneath the
+    /// chat.
+    fn run_pair_command(&mut self, args: &str) {
+        self.pair = match args {
+            "on" => true,
+            "off" => false,
+            _ => !self.pair,
+        };
+        self.logs.push(format!("Pair mode {}", if self.pair { "on: runs narrate their steps beneath the chat" } else { "off" }));
+    }
+
+    /// `/run --watch <goal>` runs `goal` again whenever project files change
+    /// (re-runs use `[watch] pipeline` when set); `/run --watch off` stops.
+    fn run_watch_goal(&mut self, goal: &str) {
+        if let Some((handle, _)) = self.watching.take() {
+            handle.abort();
+            self.logs.push("stopped watching".into());
+        }
+        if goal.is_empty() || goal == "off" {
+            return;
+        }
+        let mut sa = SuperAgent::new();
+        self.configure_run(&mut sa);
+        let on_change = match &self.config.watch.pipeline {
+            Some(name) => match crate::graph::pipeline::PipelineStore::new(self.task_store.home()).load(name, &sa.tool_names()) {
+                Ok(p) => Some(p),
+                Err(e) => {
+                    self.notifications.push(format!("[watch] pipeline {}: {}", name, e));
+                    return;
+                }
+            },
+            None => None,
+        };
+        let Ok(root) = std::env::current_dir() else { return };
+        let (log, config, goal, tx) = (crate::agent::watch::WatchLog::default(), self.config.watch.clone(), goal.to_string(), self.run_tx.clone());
+        let shared = log.clone();
+        let handle = tokio::spawn(async move {
+            crate::agent::watch::run(sa, goal, on_change, &root, &config, shared, move |line, _| {
+                let _ = tx.send(format!("watch: {}", line));
+            })
+            .await;
+        });
+        self.watching = Some((handle, log));
+        self.logs.push("watching the project; `/run --watch off` stops".into());
+    }
+
+    /// `/skills [list]`, `/skills reload` (recompile every skill), or
+    /// `/skills search [term]` / `install <name>` against the skill index.
+    fn run_skills_command(&mut self, args: &str) {
+        let mut parts = args.splitn(2, char::is_whitespace);
+        match (parts.next(), parts.next().map(str::trim)) {
+            (Some("search"), term) => return self.search_skills(term.unwrap_or("")),
+            (Some("install"), Some(name)) if !name.is_empty() => {
+                let market = Marketplace::new(self.config.skills.clone(), crate::agent::plugin_engine::default_skills_dir());
+                let (name, tx, list_tx, query) = (name.to_string(), self.run_tx.clone(), self.market_tx.clone(), self.skills_view.query.clone());
+                self.logs.push(format!("installing skill {}", name));
+                tokio::spawn(async move {
+                    let line = match market.install(&name).await {
+                        Ok((entry, verified)) => format!("installed skill {} {} ({})", entry.name, entry.version, verified.describe()),
+                        Err(e) => format!("skill {} not installed: {}", name, e),
+                    };
+                    let _ = tx.send(line);
+                    let _ = list_tx.send(market.search(&query).await.map_err(|e| e.to_string()));
+                });
+                return;
             }
             _ => {}
         }
-        Ok(false)
+        if !self.load_skills() {
+            return;
+        }
+        let Some(engine) = self.skills.clone() else { return };
+        match args {
+            "" | "list" => {
+                let engine = engine.read();
+                let skills = engine.skills();
+                if skills.is_empty() {
+                    self.logs.push(format!("no skills in {}", crate::paths::home().join("skills").display()));
+                }
+                for (name, version) in skills {
+                    let about = match engine.metadata(&name) {
+                        Ok(Some(meta)) => format!(" — {}", meta.description),
+                        Ok(None) => " (core module)".to_string(),
+                        Err(e) => format!(" (metadata unavailable: {})", e),
+                    };
+                    self.logs.push(format!("skill {} v{}{}", name, version, about));
+                }
+                #[cfg(feature = "native-plugins")]
+                for (tool, plugin) in engine.native_tools() {
+                    self.logs.push(format!("native tool {} ({}) {}", tool.name, plugin, tool.description));
+                }
+            }
+            // a panel may be calling a skill; never wait for it here
+            "reload" => match engine.try_write().map(|mut e| e.reload_all()) {
+                Some(Ok(changes)) => {
+                    self.panels.synced = false;
+                    self.logs.extend(changes.iter().map(|c| c.describe()));
+                }
+                Some(Err(e)) => self.report_error("skill reload failed", &e),
+                None => self.notifications.push("a skill is running; try /skills reload again shortly".into()),
+            },
+            _ => self.notifications.push("usage: /skills [list] | reload | search [term] | install <name>".into()),
+        }
     }
 
-    fn tick(&mut self) {
-        self.dashboard.tick();
-        self.agents.tick();
-        self.models.tick();
-        self.tasks.tick();
+    /// Toast for a failed operation: `context: [code] message`. `/details`
+    /// (or `d` in the sidebar) expands its hint and causes.
+    fn report_error(&mut self, context: &str, e: &anyhow::Error) {
+        self.show_error(context, ErrorReport::from_anyhow(e));
     }
 
-    pub fn set_theme(&mut self, name: ThemeName) {
-        self.config.theme = name;
-        self.active_theme = self.theme_catalog.resolve(&self.config.theme);
+    fn show_error(&mut self, context: &str, report: ErrorReport) {
+        let request = report.request_id.as_ref().map(|id| format!(" (request {})", id)).unwrap_or_default();
+        self.notifications.push(format!("{}: {}{}", context, report.summary(), request));
+        self.error = Some(report);
+        self.error_expanded = false;
     }
-}
 
-struct RawModeGuard {
-    enabled: bool,
-}
+    /// Load skills on first use; false (with a notification) when that fails.
+    fn load_skills(&mut self) -> bool {
+        if self.skills.is_some() {
+            return true;
+        }
+        let root = std::env::current_dir().unwrap_or_else(|_| ".".into());
+        let policy = crate::tools::policy::FileAccessPolicy::new(root, &self.config.files);
+        let loaded = PluginEngine::new(None, policy).map(|e| e.with_events(self.events.clone())).and_then(|mut e| e.load_skills().map(|_| e));
+        match loaded {
+            Ok(engine) => {
+                self.skills = Some(Arc::new(parking_lot::RwLock::new(engine)));
+                true
+            }
+            Err(e) => {
+                self.report_error("could not load skills", &e);
+                false
+            }
+        }
+    }
 
-impl RawModeGuard {
-    fn enable() -> anyhow::Result<Self> {
-        crossterm::terminal::enable_raw_mode()?;
-        Ok(Self { enabled: true })
+    /// Refresh the Skills view from the index in the background.
+    fn search_skills(&mut self, term: &str) {
+        let market = Marketplace::new(self.config.skills.clone(), crate::agent::plugin_engine::default_skills_dir());
+        self.skills_view.requested = true;
+        self.skills_view.query = term.to_string();
+        self.skills_view.status = format!("searching {}…", self.config.skills.index_url);
+        let (term, tx) = (term.to_string(), self.market_tx.clone());
+        tokio::spawn(async move {
+            let _ = tx.send(market.search(&term).await.map_err(|e| e.to_string()));
+        });
     }
-}
 
-impl Drop for RawModeGuard {
-    fn drop(&mut self) {
-        if self.enabled {
-            let _ = crossterm::terminal::disable_raw_mode();
+    /// Pick up edited skill files every couple of seconds once skills are loaded.
+    fn poll_skills(&mut self) {
+        let Some(engine) = self.skills.clone() else { return };
+        if self.skills_checked.elapsed() < Duration::from_secs(2) {
+            return;
+        }
+        // busy with a panel's skill call; look again next time
+        let Some(mut engine) = engine.try_write() else { return };
+        self.skills_checked = Instant::now();
+        match engine.reload_changed() {
+            Ok(changes) => {
+                if !changes.is_empty() {
+                    self.panels.synced = false;
+                }
+                self.logs.extend(changes.iter().map(|c| c.describe()));
+            }
+            Err(e) => tracing::warn!("skill reload failed: {}", e),
+        }
+    }
+
+    fn open_quick_open(&mut self) {
+        let root = std::env::current_dir().unwrap_or_else(|_| ".".into());
+        let policy = crate::tools::policy::FileAccessPolicy::new(&root, &self.config.files);
+        let files = crate::workspace::file_index(&root, &self.profile, &policy);
+        self.file_usage.reload();
+        self.quick_open = Some(QuickOpen { query: String::new(), files, selected: 0 });
+    }
+
+    fn quick_open_matches(&self) -> Vec<(usize, Vec<usize>)> {
+        match &self.quick_open {
+            Some(q) => palette::rank_paths(&q.query, &q.files, &self.file_usage, QUICK_OPEN_RESULTS),
+            None => vec![],
+        }
+    }
+
+    /// Enter opens the file in the File view; Alt+Enter inserts its path
+    /// into the input instead.
+    fn perform_quick_open(&mut self, action: Action) {
+        let matches = self.quick_open_matches();
+        let Some(q) = self.quick_open.as_mut() else { return };
+        match action {
+            Action::Back => self.quick_open = None,
+            Action::Select(delta) => q.selected = q.selected.saturating_add_signed(delta).min(matches.len().saturating_sub(1)),
+            Action::Backspace => {
+                q.query.pop();
+                q.selected = 0;
+            }
+            Action::Type(c) => {
+                q.query.push(c);
+                q.selected = 0;
+            }
+            Action::Submit | Action::InsertPath => {
+                let Some(path) = matches.get(q.selected).map(|(i, _)| q.files[*i].clone()) else { return };
+                self.quick_open = None;
+                if let Err(e) = self.file_usage.record(&path) {
+                    tracing::warn!("could not save quick open usage: {}", e);
+                }
+                if action == Action::InsertPath {
+                    if !self.input.is_empty() && !self.input.ends_with(' ') {
+                        self.input.push(' ');
+                    }
+                    self.input.push_str(&path);
+                    self.focus = Focus::ChatInput;
+                    return;
+                }
+                match self.file.open(&path) {
+                    Ok(()) => {
+                        self.view = ViewId::File;
+                        self.focus = Focus::DiffView;
+                    }
+                    Err(e) => self.notifications.push(format!("{}: {}", path, e)),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn render_quick_open<B: Backend>(&self, f: &mut Frame<B>, body: Rect) {
+        let Some(q) = &self.quick_open else { return };
+        let highlight = Style::default().fg(self.active_theme.accent).add_modifier(Modifier::BOLD);
+        let mut rows = vec![ListItem::new(format!("> {}", q.query))];
+        for (n, (i, positions)) in self.quick_open_matches().into_iter().enumerate() {
+            let marker = if n == q.selected { "▶ " } else { "  " };
+            let mut spans = vec![Span::raw(marker)];
+            spans.extend(q.files[i].chars().enumerate().map(|(ci, c)| {
+                if positions.contains(&ci) { Span::styled(c.to_string(), highlight) } else { Span::raw(c.to_string()) }
+            }));
+            rows.push(ListItem::new(Spans::from(spans)));
+        }
+        let width = body.width.saturating_sub(4).min(80);
+        let height = (rows.len() as u16 + 2).min(body.height);
+        let area = Rect { x: body.x + (body.width - width) / 2, y: body.y + 1.min(body.height - height), width, height };
+        f.render_widget(Clear, area);
+        let title = format!("{} ({} files)", t("panel.quick_open"), q.files.len());
+        f.render_widget(List::new(rows).block(Block::default().borders(Borders::ALL).title(title)), area);
+    }
+
+    /// Overlays take the keys while open; otherwise the stored focus does.
+    fn focus(&self) -> Focus {
+        if self.quick_open.is_some() {
+            Focus::QuickOpen
+        } else if self.plan_review.is_some() && !self.input.starts_with("/plan") {
+            Focus::PlanReview
+        } else if self.shell_proposal.is_some() && self.plan_review.is_none() {
+            Focus::ShellConfirm
+        } else if self.focus == Focus::ChatInput && self.palette_open() {
+            Focus::Palette
+        } else {
+            self.focus
         }
     }
+
+    fn handle_key(&mut self, key: KeyEvent) -> anyhow::Result<bool> {
+        let focus = self.focus();
+        let Some(action) = focus::action(focus, key) else { return Ok(false) };
+        if focus == Focus::QuickOpen && !matches!(action, Action::Quit | Action::SaveConfig | Action::ZoomIn | Action::ZoomOut) {
+            self.perform_quick_open(action);
+            return Ok(false);
+        }
+        if focus == Focus::PlanReview && matches!(action, Action::Submit | Action::Back | Action::EditPlan) {
+            match action {
+                Action::Submit => self.decide_plan(PlanDecision::Approve),
+                Action::Back => self.decide_plan(PlanDecision::Abort),
+                _ => {
+                    if let Some(review) = &self.plan_review {
+                        self.input = format!("/plan edit {}", review.plan.lines().map(str::trim).filter(|l| !l.is_empty()).collect::<Vec<_>>().join(" | "));
+                        self.focus = Focus::ChatInput;
+                    }
+                }
+            }
+            return Ok(false);
+        }
+        if focus == Focus::ShellConfirm && matches!(action, Action::Submit | Action::Back | Action::EditCommand) {
+            let Some(proposal) = self.shell_proposal.take() else { return Ok(false) };
+            match action {
+                Action::Submit => self.run_shell_command(&proposal.command),
+                Action::EditCommand => {
+                    self.input = format!("/term {}", proposal.command);
+                    self.focus = Focus::ChatInput;
+                }
+                _ => self.logs.push(format!("cancelled `{}`", proposal.command)),
+            }
+            return Ok(false);
+        }
+        let view = self.view;
+        let quit = self.perform(focus, action)?;
+        if self.view != view {
+            self.show_in_pane(PaneKind::View(self.view));
+        }
+        Ok(quit)
+    }
+
+    /// Run `action` from the keymap of `focus`; true quits.
+    fn perform(&mut self, focus: Focus, action: Action) -> anyhow::Result<bool> {
+        match action {
+            Action::Quit => return Ok(true),
+            Action::SaveConfig => {
+                self.config.save_live()?;
+                self.logs.push("Configuration saved".into());
+            }
+            Action::OpenQuickOpen => self.open_quick_open(),
+            Action::ToggleRecording => self.toggle_recording(),
+            Action::ZoomIn => {
+                self.config.density = self.config.density.zoom_in();
+                self.logs.push(format!("Layout: {}", self.config.density.name()));
+            }
+            Action::ZoomOut => {
+                self.config.density = self.config.density.zoom_out();
+                self.logs.push(format!("Layout: {}", self.config.density.name()));
+            }
+            Action::PaneFocus(dir) => {
+                if self.panes.move_focus(dir, self.pane_area) {
+                    if let PaneKind::View(view) = self.panes.focused() {
+                        self.view = view;
+                    }
+                    self.focus = Focus::for_view(self.view);
+                    self.save_layout();
+                }
+            }
+            Action::PaneResize(dir) => {
+                if self.panes.resize(dir) {
+                    self.save_layout();
+                }
+            }
+            Action::PaneSplit(axis) => {
+                self.panes.split(axis, self.panes.focused());
+                self.save_layout();
+            }
+            Action::PaneClose => {
+                if self.close_pane() {
+                    self.save_layout();
+                }
+            }
+            Action::NextView | Action::PrevView => {
+                self.view = if action == Action::NextView { self.view.next() } else { self.view.prev() };
+                self.focus = Focus::for_view(self.view);
+                self.logs.push(format!("Switched to {}", self.view.title()));
+            }
+            Action::Focus(to) => self.focus = to,
+            Action::Back => match focus {
+                Focus::ChatInput | Focus::Palette if !self.input.is_empty() => {
+                    self.input.clear();
+                    self.ghost = None;
+                }
+                // the input is the chat view's own focus, so step out to global
+                _ => self.focus = match Focus::for_view(self.view) {
+                    Focus::ChatInput => Focus::Global,
+                    f => f,
+                },
+            },
+            Action::Type(c) => {
+                self.focus = Focus::ChatInput;
+                self.input.push(c);
+                if self.input == "/" {
+                    self.palette_usage.reload();
+                    self.palette_selected = 0;
+                }
+                self.input_edited();
+            }
+            Action::Backspace => {
+                self.input.pop();
+                self.input_edited();
+            }
+            Action::Complete if focus == Focus::Palette => {
+                if let Some(cmd) = self.palette_items().get(self.palette_selected) {
+                    self.input = format!("{} ", cmd);
+                }
+            }
+            Action::Complete => match self.ghost.take() {
+                Some(ghost) => self.input.push_str(&ghost),
+                None => return self.perform(focus, Action::NextView),
+            },
+            Action::AcceptGhost => {
+                if let Some(ghost) = self.ghost.take() {
+                    self.input.push_str(&ghost);
+                }
+            }
+            Action::Submit if focus == Focus::ReviewPanel => self.open_finding(),
+            Action::Submit => {
+                if focus == Focus::Palette {
+                    if let Some(cmd) = self.palette_items().get(self.palette_selected) {
+                        self.input = cmd.to_string();
+                    }
+                }
+                let line = self.input.trim().to_string();
+                if !line.is_empty() {
+                    self.run_command(&line);
+                    self.input.clear();
+                }
+            }
+            Action::Select(delta) => match focus {
+                Focus::Palette => {
+                    let len = self.palette_items().len();
+                    self.palette_selected = self.palette_selected.saturating_add_signed(delta).min(len.saturating_sub(1));
+                }
+                Focus::ModelsPanel => self.models.select_by(delta),
+                Focus::MemoryPanel => self.memory.select_by(delta, self.profile.notes.len()),
+                Focus::ReviewPanel => self.review.select_by(delta),
+                Focus::TasksPanel => self.tasks.select_by(delta),
+                _ => {}
+            },
+            Action::Nav(dir) => match (self.view, dir) {
+                (ViewId::Settings, Move::Up) => self.settings.select_by(-1),
+                (ViewId::Settings, Move::Down) => self.settings.select_by(1),
+                (ViewId::Settings, Move::Left) => self.nudge_param(-1),
+                (ViewId::Settings, Move::Right) => self.nudge_param(1),
+                (ViewId::Panels, Move::Left) => self.panels.select_by(-1),
+                (ViewId::Panels, Move::Right) => self.panels.select_by(1),
+                (ViewId::Tasks, Move::Left) => self.tasks.select_column(-1),
+                (ViewId::Tasks, Move::Right) => self.tasks.select_column(1),
+                _ => {}
+            },
+            Action::Scroll(delta) if focus == Focus::Sidebar => {
+                self.sidebar_scroll = self.sidebar_scroll.saturating_add_signed(delta).min(self.shown_logs().count().saturating_sub(1));
+            }
+            Action::Scroll(delta) => self.file.scroll_by(delta),
+            Action::ScrollTop => self.file.scroll_by(isize::MIN),
+            Action::ScrollBottom => self.file.scroll_by(isize::MAX),
+            Action::SwitchTab(delta) => self.file.switch(delta),
+            Action::CloseTab => self.file.close(),
+            Action::ClearNotifications => {
+                self.notifications.clear();
+                self.error = None;
+            }
+            Action::ToggleDetails => self.error_expanded = !self.error_expanded,
+            Action::EditNote => {
+                if let Some(note) = self.profile.notes.get(self.memory.selected) {
+                    self.input = format!("/remember edit {} {}", self.memory.selected + 1, note);
+                    self.focus = Focus::ChatInput;
+                }
+            }
+            Action::DeleteNote => self.forget_note(self.memory.selected),
+            Action::FixFinding => {
+                if let Some(finding) = self.review.selected().cloned() {
+                    let goal = finding.fix_goal();
+                    self.start_run(SuperAgent::new(), format!("fix {}", finding.location()), &goal);
+                }
+            }
+            Action::ToggleBoard => self.tasks.board = !self.tasks.board,
+            Action::MoveCard(delta) if self.tasks.board => self.move_task_card(delta),
+            Action::MoveCard(_) | Action::InsertPath | Action::EditPlan | Action::EditCommand => {}
+        }
+        Ok(false)
+    }
+
+    /// Move the selected board card and save the status its column stands
+    /// for.
+    fn move_task_card(&mut self, delta: isize) {
+        let Some(task) = self.tasks.selected_card().cloned() else { return };
+        let moved = crate::tasks::board::shift(&task, &self.task_store.list(), delta)
+            .and_then(|(column, moved)| self.task_store.save(&moved).map(|_| column));
+        match moved {
+            Ok(column) => {
+                self.logs.push(format!("moved {} to {}", &task.id[..8], column.label()));
+                self.tasks.follow(&task.id);
+            }
+            Err(e) => self.notifications.push(e.to_string()),
+        }
+    }
+
+    fn close_pane(&mut self) -> bool {
+        if !self.panes.close() {
+            self.notifications.push("the last pane cannot be closed".into());
+            return false;
+        }
+        if let PaneKind::View(view) = self.panes.focused() {
+            self.view = view;
+        }
+        self.focus = Focus::for_view(self.view);
+        true
+    }
+
+    /// Put `kind` in the focused pane and remember the layout.
+    fn show_in_pane(&mut self, kind: PaneKind) {
+        if self.panes.focused() != kind {
+            self.panes.set_focused(kind);
+            self.save_layout();
+        }
+    }
+
+    fn save_layout(&mut self) {
+        if let Err(e) = self.layouts.save(&self.session, &self.panes) {
+            tracing::warn!("could not save pane layout: {}", e);
+        }
+    }
+
+    fn run_command(&mut self, line: &str) {
+        let mut parts = line.splitn(2, char::is_whitespace);
+        let head = parts.next().unwrap_or("");
+        let rest = parts.next().unwrap_or("").trim();
+        if palette::COMMANDS.iter().any(|(name, _)| *name == head) {
+            if let Err(e) = self.palette_usage.record(head) {
+                tracing::warn!("could not save palette usage: {}", e);
+            }
+            crate::stats::record("tui", head, vec![]);
+        }
+        if let Some(intent) = line.strip_prefix('!') {
+            return self.propose_shell_command(intent.trim());
+        }
+        match head {
+            "/var" => self.run_var_command(rest),
+            "/watch" => self.run_watch_command(rest, true),
+            "/unwatch" => self.run_watch_command(rest, false),
+            "/compare" => self.run_compare_command(rest),
+            "/fork" => self.run_fork_command(rest),
+            "/switch" => self.run_switch_command(rest),
+            "/system" => self.run_system_command(rest),
+            "/persona" => self.run_persona_command(rest),
+            "/export" => self.run_export_command(rest),
+            "/sessions" => self.run_sessions_command(rest),
+            "/remember" => self.run_remember_command(rest),
+            "/forget" => match rest.parse::<usize>() {
+                Ok(n) if n > 0 => self.forget_note(n - 1),
+                _ => self.notifications.push("usage: /forget <n>".into()),
+            },
+            "/summarize" => self.run_summarize_command(rest),
+            "/expand" => self.run_expand_command(rest),
+            "/calls" => self.run_calls_command(rest),
+            "/image" => self.run_image_command(rest),
+            "/attach" => self.run_attach_command(rest),
+            "/params" => self.run_params_command(rest),
+            "/continue" => self.continue_reply(),
+            "/theme" => self.run_theme_command(rest),
+            "/model" => self.run_model_command(rest),
+            "/connect" => self.run_connect_command(rest),
+            "/serve" => self.run_serve_command(rest),
+            "/models" => match rest {
+                "" | "rescan" => {
+                    self.refresh_catalog(rest == "rescan");
+                    self.view = ViewId::Models;
+                }
+                _ => self.notifications.push("usage: /models [rescan]".into()),
+            },
+            "/logs" => match rest {
+                "" | "all" | "server" => {
+                    self.logs_server_only = rest == "server";
+                    self.sidebar_scroll = 0;
+                }
+                _ => self.notifications.push("usage: /logs [all|server]".into()),
+            },
+            "/import" => self.run_import_command(rest),
+            "/goto" => self.run_goto_command(rest),
+            "/autocomplete" => self.run_autocomplete_command(rest),
+            "/tools" => self.run_tools_command(rest),
+            "/dryrun" => self.run_dry_run_command(rest),
+            "/pair" => self.run_pair_command(rest),
+            "/review" => self.run_review_command(rest),
+            "/run" if rest.starts_with("--watch") => self.run_watch_goal(rest["--watch".len()..].trim()),
+            "/run" if !rest.is_empty() => self.start_run(SuperAgent::new(), "run".into(), rest),
+            "/plan" => match rest.split_once(' ').unwrap_or((rest, "")) {
+                ("approve", _) => self.decide_plan(PlanDecision::Approve),
+                ("abort", _) => self.decide_plan(PlanDecision::Abort),
+                ("edit", steps) if !steps.trim().is_empty() => {
+                    let plan = steps.split(" | ").map(str::trim).collect::<Vec<_>>().join("\n");
+                    self.decide_plan(PlanDecision::Edit(plan));
+                }
+                _ => self.notifications.push("usage: /plan approve | abort | edit <step> | <step> …".into()),
+            },
+            "/template" => self.run_template_command(rest),
+            "/graph" => self.run_graph_command(rest),
+            "/pipeline" => self.run_pipeline_command(rest),
+            "/skills" => self.run_skills_command(rest),
+            "/panel" => self.run_panel_command(rest),
+            "/pane" => self.run_pane_command(rest),
+            "/details" => self.error_expanded = !self.error_expanded,
+            "/term" => self.run_shell_command(rest),
+            "/answer" => match self.questions.pop_front() {
+                Some(q) => {
+                    self.logs.push(format!("[{}] answered: {}", q.node, rest));
+                    let _ = q.reply.send(rest.to_string());
+                }
+                None => self.notifications.push("no pending question".into()),
+            },
+            "/cancel" => match self.task_store.cancel(rest) {
+                Ok(ids) => self.logs.push(format!("Cancelled {} task(s)", ids.len())),
+                Err(e) => self.report_error("cancel", &e),
+            },
+            _ if self.view == ViewId::Compare && !line.starts_with('/') => self.send_compare(line),
+            _ if self.view == ViewId::Chat && !line.starts_with('/') => self.send_chat(line),
+            _ => {
+                self.logs.push(format!("Command: {}", line));
+                if !line.starts_with('/') {
+                    self.events.publish(SessionEvent::Message {
+                        from_session: self.subscription.session.clone(),
+                        to_session: None,
+                        content: line.to_string(),
+                    });
+                }
+            }
+        }
+    }
+
+    /// `/var set <key> <value>`, `/var get <key>`, `/var list`
+    fn run_var_command(&mut self, args: &str) {
+        let mut parts = args.splitn(3, char::is_whitespace);
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some("set"), Some(key), Some(value)) => {
+                let old_value = self.shared_vars.set(key, value.trim());
+                self.logs.push(format!("var {} = {}", key, value.trim()));
+                self.events.publish(SessionEvent::VariableChange {
+                    key: key.to_string(),
+                    old_value,
+                    new_value: Some(value.trim().to_string()),
+                });
+            }
+            (Some("get"), Some(key), None) => match self.shared_vars.get(key) {
+                Some(v) => self.logs.push(format!("var {} = {}", key, v)),
+                None => self.notifications.push(format!("var {} is not set", key)),
+            },
+            (Some("list"), None, None) => {
+                let vars = self.shared_vars.list();
+                if vars.is_empty() {
+                    self.logs.push("no shared variables".into());
+                }
+                for (k, v) in vars {
+                    self.logs.push(format!("var {} = {}", k, v));
+                }
+            }
+            _ => self.notifications.push("usage: /var set <key> <value> | get <key> | list".into()),
+        }
+    }
+
+    /// `/watch <kind|all> [session]`, `/unwatch <kind|all>`
+    fn run_watch_command(&mut self, args: &str, watch: bool) {
+        let mut parts = args.split_whitespace();
+        let kinds: Vec<EventKind> = match parts.next() {
+            Some("all") => EventKind::all().to_vec(),
+            Some(k) => match EventKind::parse(k) {
+                Some(kind) => vec![kind],
+                None => {
+                    self.notifications.push(format!("unknown event kind: {}", k));
+                    return;
+                }
+            },
+            None => {
+                self.notifications.push("usage: /watch <message|task|var|file|agent|commentary|all> [session]".into());
+                return;
+            }
+        };
+        for kind in kinds {
+            if watch {
+                self.subscription.watch(kind);
+            } else {
+                self.subscription.unwatch(kind);
+            }
+        }
+        if watch {
+            self.subscription.source = parts.next().map(|s| s.to_string());
+        }
+        self.logs.push(format!("{} {}", if watch { "watching" } else { "stopped watching" }, args.trim()));
+    }
+
+    /// `/compare <a> <b>` where each side is a local model name or `remote`.
+    fn run_compare_command(&mut self, args: &str) {
+        let mut parts = args.split_whitespace();
+        match (parts.next(), parts.next()) {
+            (Some(a), Some(b)) => {
+                self.compare.pair = Some((a.to_string(), b.to_string()));
+                self.compare.columns = None;
+                self.compare.prompt.clear();
+                self.view = ViewId::Compare;
+                self.logs.push(format!("Comparing {} vs {}", a, b));
+            }
+            _ => self.notifications.push("usage: /compare <model|remote> <model|remote>".into()),
+        }
+    }
+
+    fn resolve_provider(&self, spec: &str) -> anyhow::Result<Arc<dyn Provider>> {
+        if let Some(preloaded) = self.warmup.provider(spec) {
+            return Ok(preloaded);
+        }
+        if spec == "remote" {
+            return Ok(Arc::new(
+                crate::llm::llama::LlamaClient::new(self.chat_endpoint(), self.config.llm_model.clone()).with_params(self.inference_params()),
+            ));
+        }
+        let mgr = crate::models::ModelManager::new(Some(self.config.model_dir.clone()))?;
+        let m = mgr
+            .discover()?
+            .into_iter()
+            .find(|m| m.name == spec)
+            .ok_or_else(|| ProviderError::ModelNotFound(spec.to_string()))?;
+        let config = self.inference_params().apply(&crate::models::NativeConfig::default());
+        Ok(Arc::new(crate::models::NativeProvider::with_config(m.path, config)))
+    }
+
+    fn send_compare(&mut self, prompt: &str) {
+        let (a, b) = match &self.compare.pair {
+            Some(pair) => pair.clone(),
+            None => {
+                self.notifications.push("pick models first: /compare <a> <b>".into());
+                return;
+            }
+        };
+        let providers = self.resolve_provider(&a).and_then(|pa| Ok((pa, self.resolve_provider(&b)?)));
+        let (pa, pb) = match providers {
+            Ok(p) => p,
+            Err(e) => {
+                self.report_error("compare", &e);
+                return;
+            }
+        };
+        self.compare.prompt = prompt.to_string();
+        self.compare.pending = true;
+        let tx = self.compare_tx.clone();
+        let prompt = prompt.to_string();
+        tokio::spawn(async move {
+            let cols = crate::models::compare::side_by_side(&prompt, (a, pa), (b, pb)).await;
+            let _ = tx.send(cols);
+        });
+    }
+
+    /// `/fork [n]` branches the active conversation after message `n`
+    /// (default: the last message) and switches to the new branch.
+    fn run_fork_command(&mut self, args: &str) {
+        let conv = &self.conversations[self.active_conversation];
+        let at = match args.trim() {
+            "" => conv.messages.len().saturating_sub(1),
+            n => match n.parse::<usize>() {
+                Ok(n) => n,
+                Err(_) => {
+                    self.notifications.push("usage: /fork [message index]".into());
+                    return;
+                }
+            },
+        };
+        let fork = conv.fork(at);
+        self.logs.push(format!("Forked {} -> {}", conv.display_title(), fork.display_title()));
+        self.conversations.push(fork);
+        self.active_conversation = self.conversations.len() - 1;
+        self.view = ViewId::Chat;
+    }
+
+    /// `/switch <n>` selects conversation tab `n`.
+    fn run_switch_command(&mut self, args: &str) {
+        match args.trim().parse::<usize>() {
+            Ok(n) if n < self.conversations.len() => {
+                self.active_conversation = n;
+                self.view = ViewId::Chat;
+            }
+            _ => self.notifications.push(format!("usage: /switch <0..{}>", self.conversations.len() - 1)),
+        }
+    }
+
+    /// `/system <text>` sets the active conversation's system prompt;
+    /// without text it shows the current one.
+    fn run_system_command(&mut self, args: &str) {
+        let conv = &mut self.conversations[self.active_conversation];
+        if args.is_empty() {
+            let current = conv.system.clone().unwrap_or_else(|| "(none)".into());
+            self.notifications.push(format!("system [{}]: {}", conv.persona_label(), current));
+            return;
+        }
+        conv.set_system(args);
+        self.logs.push(format!("System prompt set for {}", conv.display_title()));
+    }
+
+    /// `/persona [name]` applies a persona from `[personas]` in the config;
+    /// without a name it lists them.
+    fn run_persona_command(&mut self, args: &str) {
+        if args.is_empty() {
+            let names: Vec<&str> = self.config.personas.keys().map(String::as_str).collect();
+            let listing = if names.is_empty() { "(none configured)".to_string() } else { names.join(", ") };
+            self.notifications.push(format!("personas: {}", listing));
+            return;
+        }
+        match self.config.personas.get(args) {
+            Some(prompt) => {
+                let conv = &mut self.conversations[self.active_conversation];
+                conv.set_persona(args, prompt.clone());
+                self.logs.push(format!("Persona {} active for {}", args, conv.display_title()));
+            }
+            None => self.notifications.push(format!("unknown persona: {}", args)),
+        }
+    }
+
+    /// `/export [path]` writes the active conversation as Markdown.
+    fn run_export_command(&mut self, args: &str) {
+        let conv = &self.conversations[self.active_conversation];
+        let path = if args.is_empty() { format!("conversation-{}.md", &conv.id[..8]) } else { args.to_string() };
+        match std::fs::write(&path, conv.export_markdown()) {
+            Ok(()) => self.logs.push(format!("Exported {} to {}", conv.display_title(), path)),
+            Err(e) => self.notifications.push(format!("export failed: {}", e)),
+        }
+    }
+
+    /// `/sessions [open <id> | save]` lists saved sessions, opens one as a
+    /// conversation tab, or saves the active conversation.
+    fn run_sessions_command(&mut self, args: &str) {
+        let store = crate::memory::sessions::SessionStore::new(&crate::paths::home());
+        let mut words = args.split_whitespace();
+        match (words.next(), words.next()) {
+            (None, _) => {
+                let sessions = store.list();
+                if sessions.is_empty() {
+                    self.logs.push(format!("no sessions in {}", store.dir().display()));
+                }
+                for s in sessions {
+                    let source = s.source.map(|f| format!(" (from {})", f)).unwrap_or_default();
+                    self.logs.push(format!("session {}  {} message(s)  {}{}", &s.id[..8], s.messages.len(), s.title, source));
+                }
+            }
+            (Some("open"), Some(id)) => match store.find(id) {
+                Ok(session) => {
+                    match self.conversations.iter().position(|c| c.id == session.id) {
+                        Some(at) => self.active_conversation = at,
+                        None => {
+                            self.conversations.push(session.to_conversation());
+                            self.active_conversation = self.conversations.len() - 1;
+                        }
+                    }
+                    self.logs.push(format!("Opened session {} ({} message(s))", session.title, session.messages.len()));
+                    self.view = ViewId::Chat;
+                }
+                Err(e) => self.notifications.push(e.to_string()),
+            },
+            (Some("save"), None) => {
+                let conv = &self.conversations[self.active_conversation];
+                let mut session = crate::memory::sessions::Session::from_conversation(conv);
+                // an imported session keeps saying where it came from
+                session.source = store.find(&conv.id).ok().and_then(|s| s.source);
+                match store.save(&session) {
+                    Ok(()) => self.logs.push(format!("Saved {} as session {}", conv.display_title(), &session.id[..8])),
+                    Err(e) => self.notifications.push(format!("save failed: {}", e)),
+                }
+            }
+            _ => self.notifications.push("usage: /sessions [open <id> | save]".into()),
+        }
+    }
+
+    /// `/theme <name>` switches theme (case-insensitive).
+    fn run_theme_command(&mut self, args: &str) {
+        let found = ThemeName::all().into_iter().find(|t| self.theme_catalog.resolve(t).name.eq_ignore_ascii_case(args));
+        match found {
+            Some(theme) => {
+                self.set_theme(theme);
+                self.logs.push(format!("Theme: {}", self.active_theme.name));
+            }
+            None => self.notifications.push(format!("unknown theme: {}", args)),
+        }
+    }
+
+    /// `/model <name>` selects the chat model for this session; a
+    /// `remote:<name>/<model>` spec must name a configured remote.
+    fn run_model_command(&mut self, args: &str) {
+        if args.is_empty() {
+            self.notifications.push(format!("model: {}", self.config.llm_model));
+            return;
+        }
+        if crate::llm::remote::parse(args).is_some() {
+            match crate::llm::remote::target(&self.config.remotes, args) {
+                Ok(target) => self.logs.push(format!("Model: {} at {}", target.model, target.endpoint)),
+                Err(e) => return self.notifications.push(e.to_string()),
+            }
+        } else {
+            self.logs.push(format!("Model: {}", args));
+        }
+        self.config.llm_model = args.to_string();
+    }
+
+    /// `/goto <path>[:line]` shows the snippet in Notifications.
+    /// `/pane [<kind> | split <h|v> [kind] | close | reset]`; kinds are the
+    /// view names plus `logs` and `terminal`.
+    fn run_pane_command(&mut self, args: &str) {
+        let mut words = args.split_whitespace();
+        let usage = "usage: /pane [<view>|logs|terminal] | split <h|v> [kind] | close | reset";
+        match (words.next(), words.next(), words.next()) {
+            (None, ..) => {
+                let kinds = self.panes.kinds();
+                let list: Vec<String> = kinds.iter().enumerate().map(|(i, k)| if i == self.panes.focus { format!("[{}]", k.name()) } else { k.name().to_string() }).collect();
+                self.logs.push(format!("panes: {}", list.join(" ")));
+            }
+            (Some("split"), Some(axis), kind) => {
+                let axis = match axis {
+                    "h" => Axis::Horizontal,
+                    "v" => Axis::Vertical,
+                    _ => return self.notifications.push(usage.into()),
+                };
+                let kind = match kind.map(PaneKind::from_name) {
+                    None => self.panes.focused(),
+                    Some(Some(kind)) => kind,
+                    Some(None) => return self.notifications.push(usage.into()),
+                };
+                self.panes.split(axis, kind);
+                if let PaneKind::View(view) = kind {
+                    self.view = view;
+                }
+                self.save_layout();
+            }
+            (Some("close"), None, _) => {
+                if self.close_pane() {
+                    self.save_layout();
+                }
+            }
+            (Some("reset"), None, _) => {
+                self.panes = PaneLayout::single(PaneKind::View(self.view));
+                self.save_layout();
+            }
+            (Some(name), None, _) => match PaneKind::from_name(name) {
+                Some(kind) => {
+                    if let PaneKind::View(view) = kind {
+                        self.view = view;
+                    }
+                    self.show_in_pane(kind);
+                }
+                None => self.notifications.push(usage.into()),
+            },
+            _ => self.notifications.push(usage.into()),
+        }
+    }
+
+    /// `/term <command>` runs through the platform shell; output goes to
+    /// terminal panes.
+    fn run_shell_command(&mut self, command: &str) {
+        if command.is_empty() {
+            return self.notifications.push("usage: /term <command>".into());
+        }
+        if !self.panes.kinds().contains(&PaneKind::Terminal) {
+            self.notifications.push("/term output goes to the terminal pane: /pane split v terminal".into());
+        }
+        self.terminal.push(format!("$ {}", command));
+        let Ok(root) = std::env::current_dir() else { return };
+        let (command, tx) = (command.to_string(), self.shell_tx.clone());
+        let tools = crate::tools::registry::ToolRegistry::new();
+        tools.register(std::sync::Arc::new(crate::tools::shell::ShellTool {
+            root,
+            // the user's own command, confirmed when a model wrote it
+            policy: crate::tools::policy::FileAccessPolicy::unrestricted(),
+            sink: Some(tx.clone()),
+        }));
+        tokio::task::spawn_blocking(move || {
+            if let Err(e) = tools.run("shell", AgentInput { text: command.clone() }) {
+                let _ = tx.send(format!("{}: {}", command, e));
+            }
+        });
+    }
+
+    /// `!<request>`: ask the model for a command that does it; the command
+    /// is shown for confirmation before `/term` runs it.
+    fn propose_shell_command(&mut self, intent: &str) {
+        if intent.is_empty() {
+            return self.notifications.push("usage: !<what to do>, e.g. !find the 5 largest files under target".into());
+        }
+        let Ok(root) = std::env::current_dir() else { return };
+        let llm = crate::llm::llama::LlamaClient::new(self.chat_endpoint(), self.config.llm_model.clone());
+        let (intent, tx) = (intent.to_string(), self.proposal_tx.clone());
+        self.logs.push(format!("proposing a command for \"{}\"…", intent));
+        tokio::spawn(async move {
+            let result = crate::agent::shell::propose(&llm, &intent, &root).await;
+            let _ = tx.send(result.map_err(|e| ErrorReport::from_anyhow(&e)));
+        });
+    }
+
+    fn run_panel_command(&mut self, args: &str) {
+        self.view = ViewId::Panels;
+        self.sync_panels();
+        match args {
+            "" => {}
+            "refresh" => self.panels.expire(),
+            name if !self.panels.select(name) => {
+                let titles: Vec<&str> = self.panels.panels.iter().map(|p| p.title.as_str()).collect();
+                self.notifications.push(format!("no panel {} (available: {})", name, titles.join(", ")));
+            }
+            _ => {}
+        }
+    }
+
+    /// Rebuild the panel list from `[[panels]]` and skill manifests.
+    fn sync_panels(&mut self) {
+        if self.panels.synced {
+            return;
+        }
+        let mut panels: Vec<Panel> = self.config.panels.iter().map(|p| Panel::new(&p.name, Source::Command(p.command.clone()), p.interval_secs)).collect();
+        if self.load_skills() {
+            if let Some(engine) = &self.skills {
+                for (name, panel) in engine.read().panels() {
+                    let title = if panel.title.is_empty() { name.clone() } else { panel.title };
+                    panels.push(Panel::new(title, Source::Skill(name), panel.interval_secs));
+                }
+            }
+        }
+        self.panels.sync(panels);
+    }
+
+    /// Start refreshes, in the background, for panels whose content is
+    /// stale; true when any were started.
+    fn refresh_panels(&mut self) -> bool {
+        use crate::tui::views::panels;
+        self.sync_panels();
+        let due = self.panels.due();
+        let started = !due.is_empty();
+        for (id, source) in due {
+            let (tx, skills) = (self.panel_tx.clone(), self.skills.clone());
+            tokio::spawn(async move {
+                let _ = tx.send((id, panels::refresh(source, skills, panels::REFRESH_TIMEOUT).await));
+            });
+        }
+        started
+    }
+
+    fn run_goto_command(&mut self, args: &str) {
+        let (path, line) = crate::tui::preview::parse_target(args);
+        match std::fs::read_to_string(path) {
+            Ok(text) => self.notifications.push(format!("{}:{}\n{}", path, line, crate::tui::preview::snippet(&text, line).join("\n"))),
+            Err(e) => self.notifications.push(format!("{}: {}", path, e)),
+        }
+    }
+
+    /// Find out in the background whether a chat model answers; the chat
+    /// view shows how to get one until it does.
+    /// The endpoint is looked up afresh on the way, since this follows
+    /// changes to it.
+    fn check_model_setup(&mut self) {
+        let (tx, endpoint_tx, config) = (self.setup_tx.clone(), self.endpoint_tx.clone(), self.config.clone());
+        self.endpoint_checked = Some(Instant::now());
+        tokio::spawn(async move {
+            let model_dir = config.model_dir.clone();
+            let Ok(endpoint) = tokio::task::spawn_blocking(move || config.chat_endpoint()).await else { return };
+            let _ = endpoint_tx.send(endpoint.clone());
+            let _ = tx.send(Ok(crate::models::setup::detect(&endpoint, &model_dir).await));
+        });
+    }
+
+    /// Check a model that just arrived before anything loads it, and record
+    /// its checksum when it is sound.
+    fn verify_new_model(&self, path: std::path::PathBuf) {
+        use crate::models::integrity::{self, Verdict};
+        let tx = self.verify_tx.clone();
+        tokio::task::spawn_blocking(move || {
+            let file = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+            let verdict = match integrity::verify(&path) {
+                Ok(Verdict::Unrecorded) => integrity::record(&path).map(|_| Verdict::Ok),
+                other => other,
+            };
+            let _ = tx.send(match verdict {
+                Ok(v) if v.is_bad() => Err(format!("{} is {}", file, v.describe())),
+                Ok(_) => Ok(format!("{} verified", file)),
+                Err(e) => Err(format!("cannot verify {}: {}", file, e)),
+            });
+        });
+    }
+
+    /// Fetch the model lists of the configured remotes in the background and
+    /// merge them with the local models into the Models view's catalog. The
+    /// local list comes from the cached listing unless `rescan` is set.
+    fn refresh_catalog(&mut self, rescan: bool) {
+        let tx = self.catalog_tx.clone();
+        let remotes = self.config.remotes.clone();
+        let model_dir = self.config.model_dir.clone();
+        tokio::spawn(async move {
+            let local = crate::models::ModelManager::new(Some(model_dir))
+                .and_then(|m| if rescan { m.rescan() } else { m.listing() })
+                .map(|l| l.models.into_iter().map(|m| m.name).collect())
+                .unwrap_or_default();
+            let _ = tx.send(crate::llm::remote::catalog(local, &remotes).await);
+        });
+    }
+
+    /// `/connect <url>` chats through the server at `url` for this session
+    /// (Ctrl+S keeps it).
+    fn run_connect_command(&mut self, url: &str) {
+        if url.is_empty() {
+            self.notifications.push(format!("endpoint: {}", self.config.llm_endpoint));
+            return;
+        }
+        self.config.llm_endpoint = url.trim_end_matches('/').to_string();
+        crate::secrets::scope_llm_key(&self.config.llm_endpoint);
+        self.chat.setup = None;
+        self.logs.push(format!("Endpoint: {}", self.config.llm_endpoint));
+        self.check_model_setup();
+    }
+
+    /// `/serve [model] | stop | restart` runs the model server inside the
+    /// TUI until it exits; a restart keeps the model unless given another.
+    fn run_serve_command(&mut self, args: &str) {
+        let (action, model) = match args.split_once(' ') {
+            Some((action, model)) => (action, Some(model.trim().to_string())),
+            None => (args, None),
+        };
+        let (action, model) = match action {
+            "" => ("start", None),
+            "stop" | "restart" => (action, model),
+            name => ("start", Some(name.to_string())),
+        };
+        if action == "start" && self.model_server.is_some() {
+            return self.notifications.push("the model server is already running; /serve restart [model] or /serve stop".into());
+        }
+        let running = self.model_server.take();
+        if running.is_none() && action == "stop" {
+            return self.notifications.push("no model server started from here".into());
+        }
+        let model = model.or_else(|| running.as_ref().and_then(|h| h.model.clone()));
+        if action == "stop" {
+            self.logs.push("Stopping the model server".into());
+        }
+        let (tx, requests, config, warmup, stop) = (self.serve_tx.clone(), self.requests_tx.clone(), self.config.clone(), self.warmup.clone(), action == "stop");
+        tokio::spawn(async move {
+            if let Some(hosted) = running {
+                hosted.shutdown().await;
+            }
+            if !stop {
+                let started = crate::models::instance::host(&config, model, warmup, Duration::ZERO, Some(requests)).await;
+                let _ = tx.send(started.map_err(|e| format!("{:#}", e)));
+            }
+        });
+    }
+
+    /// `/import <path>` copies a model file into model_dir off the UI thread.
+    fn run_import_command(&mut self, path: &str) {
+        if path.is_empty() {
+            self.notifications.push("usage: /import <path>".into());
+            return;
+        }
+        let (tx, src, model_dir, endpoint) = (self.setup_tx.clone(), std::path::PathBuf::from(path), self.config.model_dir.clone(), self.chat_endpoint());
+        self.logs.push(format!("Importing {}", path));
+        tokio::spawn(async move {
+            let dir = model_dir.clone();
+            let imported = tokio::task::spawn_blocking(move || crate::models::ModelManager::new(Some(dir))?.import(&src)).await;
+            let result = match imported {
+                Ok(Ok(_)) => Ok(crate::models::setup::detect(&endpoint, &model_dir).await),
+                Ok(Err(e)) => Err(ErrorReport::from_anyhow(&e.context("import failed"))),
+                Err(e) => Err(ErrorReport::from_anyhow(&anyhow::anyhow!("import failed: {}", e))),
+            };
+            let _ = tx.send(result);
+        });
+    }
+
+    fn send_chat(&mut self, text: &str) {
+        if let Some(e) = self.chat.setup.as_ref().and_then(ModelSetup::error) {
+            self.report_error("chat", &e.into());
+            // the server may have come up since the last check
+            self.check_model_setup();
+            return;
+        }
+        let conv = &mut self.conversations[self.active_conversation];
+        let mut messages = conv.request_messages();
+        notes::inject(&mut messages, &self.profile.notes, self.config.memory.notes_budget);
+        let pending = std::mem::take(&mut self.attachments);
+        // shown right away; replaced once the attachments have been read
+        let mut placeholder = crate::types::Message::new("user", text);
+        placeholder.attachments = pending.clone();
+        let id = placeholder.id.clone();
+        conv.push(placeholder);
+        let conv_id = conv.id.clone();
+        tracing::info!(conversation = %conv_id, persona = conv.persona_label(), attachments = pending.len(), "chat request");
+        let llm = crate::llm::llama::LlamaClient::new(self.chat_endpoint(), self.config.llm_model.clone()).with_params(self.inference_params());
+        let (tx, sent_tx, text) = (self.chat_tx.clone(), self.sent_tx.clone(), text.to_string());
+        self.chat.pending = true;
+        tokio::spawn(crate::request_id::scope(crate::request_id::new_id(), async move {
+            let msg = match attachments::prepare(&llm, &text, &pending).await {
+                Ok(msg) => msg,
+                Err(e) => {
+                    let mut report = ErrorReport::from_anyhow(&e);
+                    report.message = format!("attachment: {}", report.message);
+                    let _ = sent_tx.send((conv_id, id, Err(report)));
+                    return;
+                }
+            };
+            for a in &msg.attachments {
+                tracing::info!(conversation = %conv_id, path = %a.path.display(), sha256 = %a.sha256, bytes = a.bytes, mode = ?a.mode, "attachment sent");
+            }
+            messages.push(msg.clone());
+            let _ = sent_tx.send((conv_id.clone(), id, Ok(msg)));
+            let reply = llm.complete(&messages).await.map_err(|e| ErrorReport::from_anyhow(&e.into()));
+            let _ = tx.send((conv_id, None, reply));
+        }));
+    }
+
+    /// `/remember <fact>` pins a note to the project; `/remember edit <n>
+    /// <text>` rewrites one. Without arguments it opens the Memory view.
+    fn run_remember_command(&mut self, args: &str) {
+        if args.is_empty() {
+            self.view = ViewId::Memory;
+            self.focus = Focus::for_view(self.view);
+            return;
+        }
+        let edit = args.strip_prefix("edit ").and_then(|rest| rest.trim().split_once(' ')).and_then(|(n, text)| Some((n.parse::<usize>().ok()?, text.trim())));
+        match edit {
+            Some((n, text)) if (1..=self.profile.notes.len()).contains(&n) && !text.is_empty() => self.profile.notes[n - 1] = text.to_string(),
+            Some(_) => {
+                self.notifications.push("usage: /remember edit <n> <text>".into());
+                return;
+            }
+            None => self.profile.notes.push(args.to_string()),
+        }
+        self.save_profile("note saved");
+        if notes::within_budget(&self.profile.notes, self.config.memory.notes_budget) < self.profile.notes.len() {
+            self.notifications.push("notes exceed memory.notes_budget; the last ones are not sent".into());
+        }
+    }
+
+    fn forget_note(&mut self, index: usize) {
+        if index >= self.profile.notes.len() {
+            self.notifications.push("no such note".into());
+            return;
+        }
+        self.profile.notes.remove(index);
+        self.memory.select_by(0, self.profile.notes.len());
+        self.save_profile("note removed");
+    }
+
+    fn save_profile(&mut self, done: &str) {
+        let root = std::env::current_dir().unwrap_or_else(|_| ".".into());
+        match self.profile.save(&root) {
+            Ok(()) => self.notifications.push(format!("{} ({})", done, crate::workspace::PROFILE_FILE)),
+            Err(e) => self.report_error("project profile not saved", &e),
+        }
+    }
+
+    /// `/summarize` compacts the active conversation now; `/summarize
+    /// history` lists this project's summaries in long-term memory.
+    fn run_summarize_command(&mut self, args: &str) {
+        if args == "history" {
+            let summaries = SummaryStore::new(&crate::paths::home()).for_project(&project_key());
+            if summaries.is_empty() {
+                self.notifications.push("no summaries for this project yet".into());
+            }
+            for s in summaries.iter().rev().take(5) {
+                self.logs.push(format!("summary of {} messages: {}", s.replaced, s.text.lines().next().unwrap_or_default()));
+            }
+            return;
+        }
+        let keep = self.config.memory.keep_recent.min(2);
+        if !self.summarize(self.active_conversation, keep) {
+            self.notifications.push("nothing to summarize yet".into());
+        }
+    }
+
+    /// `/expand [n]` shows or hides what summary `n` (default: the latest) replaced.
+    fn run_expand_command(&mut self, args: &str) {
+        let conv = &self.conversations[self.active_conversation];
+        let index = match args.parse::<usize>() {
+            Ok(i) => Some(i),
+            Err(_) => conv.messages.iter().rposition(|m| !m.replaces.is_empty()),
+        };
+        let Some(index) = index.filter(|i| conv.messages.get(*i).is_some_and(|m| !m.replaces.is_empty())) else {
+            self.notifications.push("no summary there".into());
+            return;
+        };
+        let key = (conv.id.clone(), conv.messages[index].id.clone());
+        if !self.chat.expanded.remove(&key) {
+            self.chat.expanded.insert(key);
+        }
+    }
+
+    /// `/calls [n]` shows or hides the tool calls behind message `n` (the
+    /// latest with any by default); `/calls <n> <k>` opens the full result
+    /// of its call `k` in a viewer tab.
+    fn run_calls_command(&mut self, args: &str) {
+        let conv = &self.conversations[self.active_conversation];
+        let mut parts = args.split_whitespace().map(str::parse::<usize>);
+        let index = match parts.next() {
+            Some(Ok(i)) => Some(i),
+            Some(Err(_)) => None,
+            None => conv.messages.iter().rposition(|m| !m.tool_calls.is_empty()),
+        };
+        let Some((index, calls)) = index.and_then(|i| Some((i, &conv.messages.get(i)?.tool_calls))).filter(|(_, c)| !c.is_empty()) else {
+            self.notifications.push("no tool calls there".into());
+            return;
+        };
+        match parts.next() {
+            Some(Ok(k)) if (1..=calls.len()).contains(&k) => {
+                let call = &calls[k - 1];
+                let text = format!("{} {}\n\n{}", call.tool, call.input, call.output);
+                self.file.open_text(&format!("{} #{}.{}", call.tool, index, k), &text);
+                self.view = ViewId::File;
+                self.focus = Focus::DiffView;
+            }
+            Some(_) => self.notifications.push(format!("message {} has {} tool call(s)", index, calls.len())),
+            None => {
+                let key = (conv.id.clone(), conv.messages[index].id.clone());
+                if !self.chat.calls_expanded.remove(&key) {
+                    self.chat.calls_expanded.insert(key);
+                }
+            }
+        }
+    }
+
+    /// Summarise all but the latest `keep` messages of conversation `index`
+    /// in the background; false when there is too little to summarise.
+    fn summarize(&mut self, index: usize, keep: usize) -> bool {
+        let conv = &self.conversations[index];
+        let count = conv.compactable(keep);
+        if count == 0 || self.summarizing.contains(&conv.id) {
+            return false;
+        }
+        let request = crate::memory::summary::request(&conv.messages[..count]);
+        let conv_id = conv.id.clone();
+        self.summarizing.insert(conv_id.clone());
+        self.logs.push(format!("Summarizing {} messages", count));
+        let llm = crate::llm::llama::LlamaClient::new(self.chat_endpoint(), self.config.llm_model.clone()).with_params(self.inference_params());
+        let tx = self.summary_tx.clone();
+        tokio::spawn(async move {
+            let summary = llm.request(&request).await.map_err(|e| ErrorReport::from_anyhow(&anyhow::Error::from(e)));
+            let _ = tx.send((conv_id, count, summary));
+        });
+        true
+    }
+
+    /// `/continue` resumes the active conversation's last reply when it was
+    /// cut at the output limit.
+    fn continue_reply(&mut self) {
+        let conv = &self.conversations[self.active_conversation];
+        let Some(id) = conv.messages.last().filter(|m| m.role == "assistant" && m.truncated).map(|m| m.id.clone()) else {
+            self.notifications.push("the last reply is complete; nothing to continue".into());
+            return;
+        };
+        let mut messages = conv.request_messages();
+        notes::inject(&mut messages, &self.profile.notes, self.config.memory.notes_budget);
+        messages.push(crate::types::Message::new("user", "Continue exactly where your previous reply stopped. Do not repeat anything."));
+        let conv_id = conv.id.clone();
+        tracing::info!(conversation = %conv_id, "chat continue");
+        let llm = crate::llm::llama::LlamaClient::new(self.chat_endpoint(), self.config.llm_model.clone()).with_params(self.inference_params());
+        let tx = self.chat_tx.clone();
+        self.chat.pending = true;
+        tokio::spawn(crate::request_id::scope(crate::request_id::new_id(), async move {
+            let reply = llm.complete(&messages).await.map_err(|e| ErrorReport::from_anyhow(&e.into()));
+            let _ = tx.send((conv_id, Some(id), reply));
+        }));
+    }
+
+    /// Take in what background work produced and refresh the views that are
+    /// due. True when anything on screen changed.
+    /// Where chat requests go, as last resolved.
+    fn chat_endpoint(&self) -> String {
+        self.endpoint.clone()
+    }
+
+    /// Look the chat endpoint up again in the background when it is due.
+    fn resolve_endpoint(&mut self) {
+        if self.endpoint_checked.is_some_and(|at| at.elapsed() < ENDPOINT_RECHECK) {
+            return;
+        }
+        self.endpoint_checked = Some(Instant::now());
+        let (tx, config) = (self.endpoint_tx.clone(), self.config.clone());
+        tokio::task::spawn_blocking(move || {
+            let _ = tx.send(config.chat_endpoint());
+        });
+    }
+
+    fn tick(&mut self) -> bool {
+        let mut changed = Dirty::default();
+        self.resolve_endpoint();
+        while let Ok(endpoint) = self.endpoint_rx.try_recv() {
+            self.endpoint = endpoint;
+        }
+        let pushed = |app: &Self| (app.logs.pushed(), app.notifications.pushed(), app.terminal.pushed());
+        let lines = pushed(self);
+        let waiting = (self.questions.len(), self.plan_review.is_some());
+        while let Ok((conv_id, id, sent)) = self.sent_rx.try_recv() {
+            changed.view(ViewId::Chat);
+            let Some(conv) = self.conversations.iter_mut().find(|c| c.id == conv_id) else { continue };
+            let Some(index) = conv.messages.iter().position(|m| m.id == id) else { continue };
+            match sent {
+                Ok(mut msg) => {
+                    msg.id = id;
+                    msg.tokens = Some(crate::models::metrics::estimate_tokens(&msg.full_text()));
+                    conv.messages[index] = msg;
+                }
+                // nothing was sent: the message goes back to the input box
+                Err(report) => {
+                    let placeholder = conv.messages.remove(index);
+                    self.attachments.extend(placeholder.attachments);
+                    if self.input.is_empty() {
+                        self.input = placeholder.content;
+                    }
+                    self.chat.pending = false;
+                    self.show_error("chat error", report);
+                    changed.screen = true;
+                }
+            }
+        }
+        while let Ok((conv_id, continues, reply)) = self.chat_rx.try_recv() {
+            changed.view(ViewId::Chat);
+            changed.view(ViewId::Dashboard);
+            self.chat.pending = false;
+            let reply = match reply {
+                Ok(reply) => reply,
+                Err(report) => {
+                    self.show_error("chat error", report);
+                    continue;
+                }
+            };
+            let Some(conv) = self.conversations.iter_mut().find(|c| c.id == conv_id) else { continue };
+            let prompt: u64 = conv.messages.iter().map(|m| crate::models::metrics::estimate_tokens(&m.content)).sum();
+            self.dashboard.record_tokens(prompt + crate::models::metrics::estimate_tokens(&reply.text));
+            let tokens = crate::models::metrics::estimate_tokens(&reply.text);
+            match continues.and_then(|id| conv.messages.iter_mut().find(|m| m.id == id)) {
+                Some(msg) => {
+                    // the cut may fall mid-word or mid-code, so nothing goes in between
+                    msg.content.push_str(&reply.text);
+                    msg.truncated = reply.truncated();
+                    msg.tokens = Some(msg.tokens.unwrap_or_default() + tokens);
+                }
+                None => {
+                    let mut msg = crate::types::Message::new("assistant", reply.text.clone());
+                    msg.truncated = reply.truncated();
+                    msg.tokens = Some(tokens);
+                    conv.push(msg);
+                }
+            }
+        }
+        while let Ok(catalog) = self.catalog_rx.try_recv() {
+            for (name, e) in &catalog.failed {
+                self.logs.push(format!("remote {}: {}", name, e));
+            }
+            self.models.set_catalog(&self.chat_endpoint(), &self.config.remotes, &catalog);
+            changed.view(ViewId::Models);
+        }
+        while let Ok(result) = self.review_rx.try_recv() {
+            changed.view(ViewId::Review);
+            match result {
+                Ok(findings) => {
+                    self.logs.push(format!("review done: {} finding(s)", findings.len()));
+                    self.review.status.clear();
+                    self.review.findings = findings;
+                    self.review.selected = 0;
+                }
+                Err(e) => {
+                    self.review.status = "failed".into();
+                    self.show_error("review failed", e);
+                }
+            }
+        }
+        while let Ok((conv_id, count, summary)) = self.summary_rx.try_recv() {
+            changed.view(ViewId::Chat);
+            changed.view(ViewId::Memory);
+            self.summarizing.remove(&conv_id);
+            let text = match summary {
+                Ok(text) => text,
+                Err(e) => {
+                    self.show_error("summary failed", e);
+                    continue;
+                }
+            };
+            let Some(conv) = self.conversations.iter_mut().find(|c| c.id == conv_id) else { continue };
+            conv.compact(count, text.trim());
+            let summary = Summary { at: crate::tasks::now_secs(), project: project_key(), conversation: conv_id, replaced: count, text: text.trim().to_string() };
+            match SummaryStore::new(&crate::paths::home()).add(&summary) {
+                Ok(()) => self.logs.push(format!("Summarized {} messages into long-term memory", count)),
+                Err(e) => self.report_error("summary not saved", &e),
+            }
+        }
+        let compact_after = self.config.memory.compact_after;
+        if compact_after > 0 {
+            let due: Vec<usize> = (0..self.conversations.len()).filter(|i| self.conversations[*i].messages.len() > compact_after).collect();
+            for index in due {
+                self.summarize(index, self.config.memory.keep_recent);
+                changed.view(ViewId::Chat);
+            }
+        }
+        while let Ok(cols) = self.compare_rx.try_recv() {
+            self.compare.pending = false;
+            self.compare.columns = Some(cols);
+            changed.view(ViewId::Compare);
+        }
+        while let Ok(preview) = self.preview_rx.try_recv() {
+            self.preview = Some(preview);
+            changed.screen = true;
+        }
+        self.request_preview();
+        while let Ok((prefix, suggestion)) = self.completion_rx.try_recv() {
+            if prefix == self.input {
+                self.ghost = Some(suggestion);
+                changed.screen = true;
+            }
+        }
+        self.request_completion();
+        while let Ok(line) = self.run_rx.try_recv() {
+            self.logs.push(line);
+        }
+        while let Ok((conv_id, msg)) = self.run_reply_rx.try_recv() {
+            if let Some(conv) = self.conversations.iter_mut().find(|c| c.id == conv_id) {
+                conv.push(msg);
+                changed.view(ViewId::Chat);
+            }
+        }
+        while let Ok(line) = self.shell_rx.try_recv() {
+            self.terminal.push(line);
+        }
+        while let Ok(result) = self.proposal_rx.try_recv() {
+            changed.screen = true;
+            match result {
+                Ok(proposal) => self.shell_proposal = Some(proposal),
+                Err(e) => self.show_error("no command proposed", e),
+            }
+        }
+        if self.view == ViewId::Skills && !self.skills_view.requested {
+            self.search_skills("");
+            changed.view(ViewId::Skills);
+        }
+        while let Ok((id, result)) = self.panel_rx.try_recv() {
+            self.panels.update(&id, result);
+            changed.view(ViewId::Panels);
+        }
+        if self.view == ViewId::Panels && self.refresh_panels() {
+            changed.view(ViewId::Panels);
+        }
+        while let Ok(setup) = self.setup_rx.try_recv() {
+            changed.view(ViewId::Chat);
+            let setup = match setup {
+                Ok(setup) => setup,
+                Err(e) => {
+                    self.show_error("model setup", e);
+                    continue;
+                }
+            };
+            self.logs.push(match &setup {
+                ModelSetup::Connected(endpoint) => format!("Chat model at {}", endpoint),
+                ModelSetup::NotServed(local) => format!("No model served; installed: {}", local.join(", ")),
+                ModelSetup::Missing => "No chat model available".into(),
+            });
+            self.chat.setup = Some(setup);
+        }
+        while let Ok(transcript) = self.voice_rx.try_recv() {
+            changed.screen = true;
+            match transcript {
+                Ok(text) if text.is_empty() => self.notifications.push("no speech recognised".into()),
+                Ok(text) => {
+                    if !self.input.is_empty() && !self.input.ends_with(' ') {
+                        self.input.push(' ');
+                    }
+                    self.input.push_str(&text);
+                    self.input_edited();
+                }
+                Err(e) => self.show_error("transcription failed", e),
+            }
+        }
+        while let Ok(started) = self.serve_rx.try_recv() {
+            match started {
+                Ok((hosted, said)) => {
+                    self.logs.push(said);
+                    if let Some(warning) = crate::models::access::exposure_warning(hosted.handle.addr) {
+                        self.logs.push(format!("warning: {}", warning));
+                        self.notifications.push(warning);
+                    }
+                    self.model_server = Some(hosted);
+                    self.check_model_setup();
+                    changed.view(ViewId::Models);
+                }
+                Err(e) => self.notifications.push(format!("model server: {}", e)),
+            }
+        }
+        while let Ok(request) = self.requests_rx.try_recv() {
+            self.logs.push(format!("{}{}", SERVER_LOG, request.line()));
+        }
+        let changes = self.model_watcher.poll();
+        if !changes.is_empty() {
+            self.notifications.extend(changes.iter().map(|c| c.describe()));
+            self.refresh_catalog(false);
+            self.check_model_setup();
+            for change in changes {
+                if let crate::models::watcher::Change::Added(file) = change {
+                    self.verify_new_model(self.config.model_dir.join(file));
+                }
+            }
+        }
+        while let Ok(verdict) = self.verify_rx.try_recv() {
+            match verdict {
+                Ok(line) => self.logs.push(line),
+                Err(e) => self.notifications.push(e),
+            }
+        }
+        // stopped from another process (`agent models serve stop`)
+        if self.model_server.as_ref().is_some_and(|h| h.handle.is_stopping()) {
+            if let Some(hosted) = self.model_server.take() {
+                self.logs.push("Model server stopped".into());
+                tokio::spawn(hosted.shutdown());
+            }
+        }
+        while let Ok(found) = self.market_rx.try_recv() {
+            changed.view(ViewId::Skills);
+            match found {
+                Ok(listings) => {
+                    self.skills_view.status = format!("{} skill(s) from {}", listings.len(), self.config.skills.index_url);
+                    self.skills_view.listings = listings;
+                }
+                Err(e) => self.skills_view.status = e,
+            }
+        }
+        while let Ok(prompt) = self.human_rx.try_recv() {
+            match prompt {
+                Prompt::Question(q) => {
+                    let default = q.default.as_deref().map(|d| format!(" (default: {})", d)).unwrap_or_default();
+                    self.notifications.push(format!("[{}] {}{} — reply with /answer <text>", q.node, q.question, default));
+                    if self.input.is_empty() {
+                        self.input = "/answer ".into();
+                        self.focus = Focus::ChatInput;
+                    }
+                    self.questions.push_back(q);
+                }
+                Prompt::Plan(review) => {
+                    self.logs.push(format!("Plan ready for approval: {}", review.estimate.describe()));
+                    self.plan_review = Some(review);
+                }
+            }
+        }
+        // runs that timed out or were dropped no longer need an answer
+        self.questions.retain(|q| !q.reply.is_closed());
+        if self.plan_review.as_ref().is_some_and(|r| r.reply.is_closed()) {
+            self.plan_review = None;
+        }
+        self.reload_config();
+        self.poll_skills();
+        for pass in self.janitor.poll(&self.config.memory.retention) {
+            match pass {
+                Ok(report) => self.logs.push(report),
+                Err(e) => self.notifications.push(format!("history pruning failed: {}", e)),
+            }
+        }
+        // watched events show in the chat as system messages
+        for ev in self.subscription.drain() {
+            self.conversations[self.active_conversation].messages.push(crate::types::Message::new("system", ev.describe()));
+            changed.view(ViewId::Chat);
+        }
+        for ev in self.commentary.drain() {
+            if let SessionEvent::Commentary { agent, text, .. } = ev {
+                self.pair_feed.push_back(format!("{} … {}", agent, text));
+                if self.pair_feed.len() > PAIR_HISTORY {
+                    self.pair_feed.pop_front();
+                }
+                changed.view(ViewId::Chat);
+            }
+        }
+        let watch = self.watching.as_ref().map(|(_, log)| log.lines(5)).unwrap_or_default();
+        let warmup: Vec<(String, String)> = self.warmup.status().into_iter().map(|(name, state)| (name, state.describe())).collect();
+        if watch != self.dashboard.watch || warmup != self.dashboard.warmup {
+            (self.dashboard.watch, self.dashboard.warmup) = (watch, warmup);
+            changed.view(ViewId::Dashboard);
+        }
+        self.dashboard.tick();
+        if self.view == ViewId::Dashboard && self.dashboard.refresh_running(&self.task_store) {
+            changed.view(ViewId::Dashboard);
+        }
+        if self.agents.tick(self.task_store.home()) {
+            changed.view(ViewId::Agents);
+        }
+        if self.view == ViewId::Memory && self.memory.tick(&crate::paths::home(), &project_key()) {
+            changed.view(ViewId::Memory);
+        }
+        if self.view == ViewId::Tasks && self.tasks.tick(&self.task_store) {
+            changed.view(ViewId::Tasks);
+        }
+        // Settings says "checking…" until the keyring lookup is back
+        if crate::secrets::peek(crate::secrets::LLM_API_KEY).is_none() {
+            changed.view(ViewId::Settings);
+        }
+        changed.screen |= pushed(self) != lines || (self.questions.len(), self.plan_review.is_some()) != waiting;
+        changed.screen || self.panes.kinds().iter().any(|kind| matches!(kind, PaneKind::View(view) if changed.views.contains(view)))
+    }
+
+    /// Apply edits to `config.toml` made while the TUI is running.
+    fn reload_config(&mut self) {
+        let Some(reloaded) = self.config_watcher.as_mut().and_then(ConfigWatcher::poll) else {
+            return;
+        };
+        match reloaded {
+            Ok(new) => {
+                let changes = self.config.apply_reload(new);
+                if changes.is_empty() {
+                    return;
+                }
+                self.active_theme = self.theme_catalog.resolve(&self.config.theme);
+                if changes.applied.contains(&"llm_endpoint") {
+                    crate::secrets::scope_llm_key(&self.config.llm_endpoint);
+                    self.check_model_setup();
+                }
+                if changes.applied.contains(&"network") {
+                    crate::net::configure(self.config.network.clone());
+                }
+                if changes.applied.contains(&"remotes") {
+                    crate::llm::remote::configure(self.config.remotes.clone());
+                    self.refresh_catalog(false);
+                }
+                if changes.applied.contains(&"chat_retry") {
+                    crate::llm::resume::configure(self.config.chat_retry.clone());
+                }
+                if changes.applied.contains(&"pair_mode") {
+                    self.pair = self.config.pair_mode;
+                }
+                if changes.applied.contains(&"ui") {
+                    self.logs.resize(self.config.ui.log_lines);
+                    self.notifications.resize(self.config.ui.notification_lines);
+                    self.terminal.resize(self.config.ui.terminal_lines);
+                }
+                if changes.applied.contains(&"panels") {
+                    self.panels.synced = false;
+                }
+                self.endpoint_checked = None;
+                if changes.applied.contains(&"stats") {
+                    crate::stats::configure(&self.config.stats, &crate::paths::home());
+                }
+                if changes.applied.contains(&"tools") {
+                    crate::tools::audit::configure(&self.config.tools, &crate::paths::home());
+                }
+                if changes.applied.contains(&"transcript") {
+                    if let Err(e) = crate::transcript::configure(&self.config.transcript, &crate::paths::home()) {
+                        self.report_error("transcripts off", &e);
+                    }
+                }
+                if !i18n::set_language(&self.config.ui.language) {
+                    self.notifications.push(format!("unknown ui.language {} (available: {})", self.config.ui.language, i18n::languages().join(", ")));
+                }
+                self.notifications.push(changes.describe());
+            }
+            Err(e) => self.report_error("config not reloaded", &e),
+        }
+    }
+
+    pub fn set_theme(&mut self, name: ThemeName) {
+        self.config.theme = name;
+        self.active_theme = self.theme_catalog.resolve(&self.config.theme);
+    }
+}
+
+struct RawModeGuard {
+    enabled: bool,
+}
+
+impl RawModeGuard {
+    fn enable() -> anyhow::Result<Self> {
+        crossterm::terminal::enable_raw_mode()?;
+        // dropped files arrive as one paste event
+        crossterm::execute!(io::stdout(), crossterm::event::EnableBracketedPaste)?;
+        Ok(Self { enabled: true })
+    }
+}
+
+impl Drop for RawModeGuard {
+    fn drop(&mut self) {
+        if self.enabled {
+            let _ = crossterm::execute!(io::stdout(), crossterm::event::DisableBracketedPaste);
+            let _ = crossterm::terminal::disable_raw_mode();
+        }
+    }
+}
+
+/// Long-term memory is kept per project: the session's working directory.
+fn project_key() -> String {
+    std::env::current_dir().map(|d| d.display().to_string()).unwrap_or_default()
 }
@@ -0,0 +1,78 @@
+//! Charts drawn in the theme's colours: inline bars that fit in a list row,
+//! and gauges and sparklines as widgets of their own. The accent fills,
+//! `muted_text` draws the empty track.
+
+use crate::tui::theme::AppTheme;
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::Span,
+    widgets::{Block, Borders, Gauge, Sparkline},
+};
+
+const FULL: &str = "█";
+const TRACK: &str = "░";
+
+/// `ratio` limited to 0..=1, with NaN as 0.
+pub fn clamp(ratio: f64) -> f64 {
+    if ratio.is_nan() {
+        0.0
+    } else {
+        ratio.clamp(0.0, 1.0)
+    }
+}
+
+/// Cells of a `width`-cell bar filled at `ratio`, to the nearest cell.
+pub fn filled_cells(ratio: f64, width: usize) -> usize {
+    ((clamp(ratio) * width as f64).round() as usize).min(width)
+}
+
+/// A bar as plain text, for places that cannot style spans (and the CLI).
+pub fn bar_text(ratio: f64, width: usize) -> String {
+    let filled = filled_cells(ratio, width);
+    format!("{}{}", FULL.repeat(filled), TRACK.repeat(width - filled))
+}
+
+/// A bar with its filled part in `fill` and the track in `track`.
+pub fn bar_in<'a>(ratio: f64, width: usize, fill: Color, track: Color) -> Vec<Span<'a>> {
+    let filled = filled_cells(ratio, width);
+    vec![Span::styled(FULL.repeat(filled), Style::default().fg(fill)), Span::styled(TRACK.repeat(width - filled), Style::default().fg(track))]
+}
+
+/// A bar in the theme's accent on a muted track.
+pub fn bar<'a>(ratio: f64, width: usize, theme: &AppTheme) -> Vec<Span<'a>> {
+    bar_in(ratio, width, theme.accent, theme.muted_text)
+}
+
+/// A bordered sparkline widget.
+pub fn sparkline<'a>(values: &'a [u64], title: String, theme: &AppTheme) -> Sparkline<'a> {
+    Sparkline::default().block(Block::default().borders(Borders::ALL).title(title)).data(values).style(Style::default().fg(theme.accent))
+}
+
+/// A bordered gauge at `ratio` with `label` over it.
+pub fn gauge<'a>(ratio: f64, label: String, title: String, theme: &AppTheme) -> Gauge<'a> {
+    Gauge::default()
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .gauge_style(Style::default().fg(theme.accent).bg(Color::Reset).add_modifier(Modifier::BOLD))
+        .ratio(clamp(ratio))
+        .label(label)
+        .use_unicode(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_values_map_to_cells() {
+        assert_eq!(filled_cells(0.0, 20), 0);
+        assert_eq!(filled_cells(0.5, 20), 10);
+        assert_eq!(filled_cells(0.024, 20), 0);
+        assert_eq!(filled_cells(0.026, 20), 1);
+        assert_eq!(filled_cells(1.7, 20), 20);
+        assert_eq!(filled_cells(-0.3, 20), 0);
+        assert_eq!(filled_cells(f64::NAN, 20), 0);
+        assert_eq!(filled_cells(0.5, 0), 0);
+        assert_eq!(bar_text(0.25, 8), "██░░░░░░");
+        assert_eq!(bar_text(0.99, 4).chars().count(), 4);
+    }
+}
@@ -0,0 +1,369 @@
+//! Which part of the TUI receives keys, and what each key does there. Every
+//! focus owns its keymap, so a key means one thing at a time: `q` types a
+//! "q" in the input and quits from a view. Ctrl and Alt chords are the same
+//! everywhere.
+
+use crate::tui::panes::{Axis, Move};
+use crate::tui::views::ViewId;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Focus {
+    /// A view without keys of its own: single keys drive the app.
+    Global,
+    /// The input line; every printable key is text.
+    ChatInput,
+    /// The slash-command palette, open while a command word is typed.
+    Palette,
+    /// The Ctrl+O file finder.
+    QuickOpen,
+    ModelsPanel,
+    /// The project notes in the Memory view.
+    MemoryPanel,
+    /// The findings in the Review view.
+    ReviewPanel,
+    /// The Tasks view's list or board.
+    TasksPanel,
+    /// The activity log beside the view.
+    Sidebar,
+    /// The file viewer.
+    DiffView,
+    /// A plan waiting for approval, shown over the view.
+    PlanReview,
+    /// A proposed shell command waiting for confirmation, shown over the view.
+    ShellConfirm,
+}
+
+impl Focus {
+    /// Where keys go after switching to `view`: the input for views driven
+    /// by typing, the view itself otherwise.
+    pub fn for_view(view: ViewId) -> Focus {
+        match view {
+            ViewId::Chat | ViewId::Compare => Focus::ChatInput,
+            ViewId::Models => Focus::ModelsPanel,
+            ViewId::Memory => Focus::MemoryPanel,
+            ViewId::Review => Focus::ReviewPanel,
+            ViewId::Tasks => Focus::TasksPanel,
+            ViewId::File => Focus::DiffView,
+            _ => Focus::Global,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Focus::Global => "global",
+            Focus::ChatInput => "input",
+            Focus::Palette => "palette",
+            Focus::QuickOpen => "quick_open",
+            Focus::ModelsPanel => "models",
+            Focus::MemoryPanel => "memory",
+            Focus::ReviewPanel => "review",
+            Focus::TasksPanel => "tasks",
+            Focus::Sidebar => "sidebar",
+            Focus::DiffView => "file",
+            Focus::PlanReview => "plan",
+            Focus::ShellConfirm => "shell",
+        }
+    }
+
+    /// Footer hint listing this focus's keys.
+    pub fn hint_key(self) -> &'static str {
+        match self {
+            Focus::Global => "keys.global",
+            Focus::ChatInput => "keys.input",
+            Focus::Palette => "keys.palette",
+            Focus::QuickOpen => "keys.quick_open",
+            Focus::ModelsPanel => "keys.models",
+            Focus::MemoryPanel => "keys.memory",
+            Focus::ReviewPanel => "keys.review",
+            Focus::TasksPanel => "keys.tasks",
+            Focus::Sidebar => "keys.sidebar",
+            Focus::DiffView => "keys.file",
+            Focus::PlanReview => "keys.plan",
+            Focus::ShellConfirm => "keys.shell",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    Quit,
+    SaveConfig,
+    OpenQuickOpen,
+    ToggleRecording,
+    ZoomIn,
+    ZoomOut,
+    PaneFocus(Move),
+    PaneResize(Move),
+    PaneSplit(Axis),
+    PaneClose,
+    NextView,
+    PrevView,
+    Focus(Focus),
+    /// Leave the current focus: clear the input, close the overlay or
+    /// return to the view.
+    Back,
+    Type(char),
+    Backspace,
+    /// Run the input, or open the selected entry.
+    Submit,
+    /// Quick Open only: insert the selected path into the input.
+    InsertPath,
+    /// Tab in the input: accept the inline completion, else next view.
+    Complete,
+    AcceptGhost,
+    /// Move the selection in the palette, Quick Open, models or notes.
+    Select(isize),
+    /// Arrows in a view without its own focus (Settings, Panels).
+    Nav(Move),
+    Scroll(isize),
+    ScrollTop,
+    ScrollBottom,
+    ClearNotifications,
+    /// Expand or collapse the latest error's hint and causes.
+    ToggleDetails,
+    /// Memory view: put the selected note in the input to edit it.
+    EditNote,
+    DeleteNote,
+    /// Plan review: put the plan in the input to edit it.
+    EditPlan,
+    /// Review view: start an agent run fixing the selected finding.
+    FixFinding,
+    /// Shell confirmation: put the command in the input to edit it.
+    EditCommand,
+    /// Tasks view: switch between the list and the board.
+    ToggleBoard,
+    /// Tasks board: move the selected card to the next (`1`) or previous
+    /// (`-1`) column.
+    MoveCard(isize),
+    /// File view: move to the next (`1`) or previous (`-1`) tab.
+    SwitchTab(isize),
+    CloseTab,
+}
+
+/// Lines PageUp/PageDown scroll.
+const PAGE: isize = 20;
+
+/// The action `key` triggers with `focus` active, if any.
+pub fn action(focus: Focus, key: KeyEvent) -> Option<Action> {
+    // AltGr arrives as Ctrl+Alt on Windows and many European layouts, so
+    // `@`, `{` or `\` typed with it are text, not chords
+    if let KeyCode::Char(c) = key.code {
+        if key.modifiers.contains(KeyModifiers::CONTROL | KeyModifiers::ALT) {
+            return matches!(focus, Focus::ChatInput | Focus::Palette | Focus::QuickOpen).then_some(Action::Type(c));
+        }
+    }
+    if let Some(chord) = chord(focus, key) {
+        return Some(chord);
+    }
+    if key.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) {
+        return None;
+    }
+    match focus {
+        Focus::ChatInput => input(key),
+        Focus::Palette => palette(key),
+        Focus::QuickOpen => quick_open(key),
+        Focus::Global => match key.code {
+            KeyCode::Left => Some(Action::Nav(Move::Left)),
+            KeyCode::Right => Some(Action::Nav(Move::Right)),
+            KeyCode::Up => Some(Action::Nav(Move::Up)),
+            KeyCode::Down => Some(Action::Nav(Move::Down)),
+            _ => view(key),
+        },
+        Focus::ModelsPanel => match key.code {
+            KeyCode::Up | KeyCode::Char('k') => Some(Action::Select(-1)),
+            KeyCode::Down | KeyCode::Char('j') => Some(Action::Select(1)),
+            _ => view(key),
+        },
+        Focus::MemoryPanel => match key.code {
+            KeyCode::Up | KeyCode::Char('k') => Some(Action::Select(-1)),
+            KeyCode::Down | KeyCode::Char('j') => Some(Action::Select(1)),
+            KeyCode::Char('e') => Some(Action::EditNote),
+            KeyCode::Char('x') | KeyCode::Delete => Some(Action::DeleteNote),
+            _ => view(key),
+        },
+        Focus::ReviewPanel => match key.code {
+            KeyCode::Up | KeyCode::Char('k') => Some(Action::Select(-1)),
+            KeyCode::Down | KeyCode::Char('j') => Some(Action::Select(1)),
+            KeyCode::Enter => Some(Action::Submit),
+            KeyCode::Char('f') => Some(Action::FixFinding),
+            _ => view(key),
+        },
+        Focus::TasksPanel => match key.code {
+            KeyCode::Left | KeyCode::Right if key.modifiers.contains(KeyModifiers::SHIFT) => Some(Action::MoveCard(if key.code == KeyCode::Left { -1 } else { 1 })),
+            KeyCode::Char('<') => Some(Action::MoveCard(-1)),
+            KeyCode::Char('>') => Some(Action::MoveCard(1)),
+            KeyCode::Up | KeyCode::Char('k') => Some(Action::Select(-1)),
+            KeyCode::Down | KeyCode::Char('j') => Some(Action::Select(1)),
+            KeyCode::Left | KeyCode::Char('h') => Some(Action::Nav(Move::Left)),
+            KeyCode::Right | KeyCode::Char('l') => Some(Action::Nav(Move::Right)),
+            KeyCode::Char('v') => Some(Action::ToggleBoard),
+            _ => view(key),
+        },
+        Focus::PlanReview => match key.code {
+            KeyCode::Enter => Some(Action::Submit),
+            KeyCode::Char('e') => Some(Action::EditPlan),
+            KeyCode::Esc => Some(Action::Back),
+            _ => None,
+        },
+        Focus::ShellConfirm => match key.code {
+            KeyCode::Enter => Some(Action::Submit),
+            KeyCode::Char('e') => Some(Action::EditCommand),
+            KeyCode::Esc => Some(Action::Back),
+            _ => None,
+        },
+        Focus::DiffView => match key.code {
+            KeyCode::Up | KeyCode::Char('k') => Some(Action::Scroll(-1)),
+            KeyCode::Down | KeyCode::Char('j') => Some(Action::Scroll(1)),
+            KeyCode::PageUp => Some(Action::Scroll(-PAGE)),
+            KeyCode::PageDown | KeyCode::Char(' ') => Some(Action::Scroll(PAGE)),
+            KeyCode::Home | KeyCode::Char('g') => Some(Action::ScrollTop),
+            KeyCode::End | KeyCode::Char('G') => Some(Action::ScrollBottom),
+            KeyCode::Char(']') => Some(Action::SwitchTab(1)),
+            KeyCode::Char('[') => Some(Action::SwitchTab(-1)),
+            KeyCode::Char('x') => Some(Action::CloseTab),
+            _ => view(key),
+        },
+        Focus::Sidebar => match key.code {
+            KeyCode::Up | KeyCode::Char('k') => Some(Action::Scroll(-1)),
+            KeyCode::Down | KeyCode::Char('j') => Some(Action::Scroll(1)),
+            KeyCode::Char('c') => Some(Action::ClearNotifications),
+            KeyCode::Char('d') => Some(Action::ToggleDetails),
+            KeyCode::Esc | KeyCode::Char('b') => Some(Action::Back),
+            _ => view(key),
+        },
+    }
+}
+
+/// Ctrl and Alt chords, the same in every focus. Quick Open keeps Alt+Enter.
+fn chord(focus: Focus, key: KeyEvent) -> Option<Action> {
+    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+    let alt = key.modifiers.contains(KeyModifiers::ALT);
+    let dir = match key.code {
+        KeyCode::Left => Some(Move::Left),
+        KeyCode::Right => Some(Move::Right),
+        KeyCode::Up => Some(Move::Up),
+        KeyCode::Down => Some(Move::Down),
+        _ => None,
+    };
+    match (key.code, dir) {
+        (KeyCode::Char('q'), _) if ctrl => Some(Action::Quit),
+        (KeyCode::Char('s'), _) if ctrl => Some(Action::SaveConfig),
+        (KeyCode::Char('o'), _) if ctrl => Some(Action::OpenQuickOpen),
+        (KeyCode::Char('r'), _) if ctrl => Some(Action::ToggleRecording),
+        (KeyCode::Char('+') | KeyCode::Char('='), _) if ctrl => Some(Action::ZoomIn),
+        (KeyCode::Char('-'), _) if ctrl => Some(Action::ZoomOut),
+        (KeyCode::Enter, _) if alt && focus == Focus::QuickOpen => Some(Action::InsertPath),
+        (_, Some(dir)) if alt && key.modifiers.contains(KeyModifiers::SHIFT) => Some(Action::PaneResize(dir)),
+        (_, Some(dir)) if alt => Some(Action::PaneFocus(dir)),
+        (KeyCode::Char('v'), _) if alt => Some(Action::PaneSplit(Axis::Horizontal)),
+        (KeyCode::Char('s'), _) if alt => Some(Action::PaneSplit(Axis::Vertical)),
+        (KeyCode::Char('w'), _) if alt => Some(Action::PaneClose),
+        _ => None,
+    }
+}
+
+/// Keys shared by the focuses that sit on a view.
+fn view(key: KeyEvent) -> Option<Action> {
+    match key.code {
+        KeyCode::Char('q') => Some(Action::Quit),
+        KeyCode::Tab => Some(Action::NextView),
+        KeyCode::BackTab => Some(Action::PrevView),
+        KeyCode::Char('/') => Some(Action::Type('/')),
+        KeyCode::Enter | KeyCode::Char('i') => Some(Action::Focus(Focus::ChatInput)),
+        KeyCode::Char('b') => Some(Action::Focus(Focus::Sidebar)),
+        _ => None,
+    }
+}
+
+fn input(key: KeyEvent) -> Option<Action> {
+    match key.code {
+        KeyCode::Char(c) => Some(Action::Type(c)),
+        KeyCode::Backspace => Some(Action::Backspace),
+        KeyCode::Enter => Some(Action::Submit),
+        KeyCode::Tab => Some(Action::Complete),
+        KeyCode::BackTab => Some(Action::PrevView),
+        KeyCode::Right => Some(Action::AcceptGhost),
+        KeyCode::Esc => Some(Action::Back),
+        _ => None,
+    }
+}
+
+fn palette(key: KeyEvent) -> Option<Action> {
+    match key.code {
+        KeyCode::Up => Some(Action::Select(-1)),
+        KeyCode::Down => Some(Action::Select(1)),
+        KeyCode::Tab => Some(Action::Complete),
+        _ => input(key),
+    }
+}
+
+fn quick_open(key: KeyEvent) -> Option<Action> {
+    match key.code {
+        KeyCode::Up => Some(Action::Select(-1)),
+        KeyCode::Down => Some(Action::Select(1)),
+        KeyCode::Char(c) => Some(Action::Type(c)),
+        KeyCode::Backspace => Some(Action::Backspace),
+        KeyCode::Enter => Some(Action::Submit),
+        KeyCode::Esc => Some(Action::Back),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_each_focus_owns_its_keys() {
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        let ctrl = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL);
+
+        // letters are text in the input and commands on a view
+        assert_eq!(action(Focus::ChatInput, key(KeyCode::Char('q'))), Some(Action::Type('q')));
+        assert_eq!(action(Focus::Palette, key(KeyCode::Char('s'))), Some(Action::Type('s')));
+        assert_eq!(action(Focus::Global, key(KeyCode::Char('q'))), Some(Action::Quit));
+        assert_eq!(action(Focus::DiffView, key(KeyCode::Char('j'))), Some(Action::Scroll(1)));
+        assert_eq!(action(Focus::DiffView, key(KeyCode::Char('['))), Some(Action::SwitchTab(-1)));
+        assert_eq!(action(Focus::ModelsPanel, key(KeyCode::Char('j'))), Some(Action::Select(1)));
+        assert_eq!(action(Focus::Global, key(KeyCode::Char('j'))), None);
+        assert_eq!(action(Focus::Sidebar, key(KeyCode::Char('c'))), Some(Action::ClearNotifications));
+        assert_eq!(action(Focus::Sidebar, key(KeyCode::Char('d'))), Some(Action::ToggleDetails));
+        assert_eq!(action(Focus::MemoryPanel, key(KeyCode::Char('e'))), Some(Action::EditNote));
+        assert_eq!(action(Focus::PlanReview, key(KeyCode::Char('e'))), Some(Action::EditPlan));
+        assert_eq!(action(Focus::ReviewPanel, key(KeyCode::Enter)), Some(Action::Submit));
+        assert_eq!(action(Focus::ReviewPanel, key(KeyCode::Char('f'))), Some(Action::FixFinding));
+        assert_eq!(action(Focus::PlanReview, key(KeyCode::Char('q'))), None);
+        assert_eq!(action(Focus::ShellConfirm, key(KeyCode::Char('e'))), Some(Action::EditCommand));
+        assert_eq!(action(Focus::TasksPanel, key(KeyCode::Char('>'))), Some(Action::MoveCard(1)));
+        assert_eq!(action(Focus::TasksPanel, KeyEvent::new(KeyCode::Left, KeyModifiers::SHIFT)), Some(Action::MoveCard(-1)));
+        assert_eq!(action(Focus::TasksPanel, key(KeyCode::Char('l'))), Some(Action::Nav(Move::Right)));
+
+        // arrows and Tab depend on the focus
+        assert_eq!(action(Focus::Palette, key(KeyCode::Down)), Some(Action::Select(1)));
+        assert_eq!(action(Focus::Global, key(KeyCode::Down)), Some(Action::Nav(Move::Down)));
+        assert_eq!(action(Focus::ChatInput, key(KeyCode::Down)), None);
+        assert_eq!(action(Focus::ChatInput, key(KeyCode::Tab)), Some(Action::Complete));
+        assert_eq!(action(Focus::Global, key(KeyCode::Tab)), Some(Action::NextView));
+        assert_eq!(action(Focus::Global, key(KeyCode::Enter)), Some(Action::Focus(Focus::ChatInput)));
+
+        // chords work everywhere; other modified keys do nothing
+        for focus in [Focus::Global, Focus::ChatInput, Focus::Palette, Focus::QuickOpen, Focus::Sidebar] {
+            assert_eq!(action(focus, ctrl('s')), Some(Action::SaveConfig));
+            assert_eq!(action(focus, ctrl('q')), Some(Action::Quit));
+        }
+        assert_eq!(action(Focus::ChatInput, ctrl('x')), None);
+        let alt = |code| KeyEvent::new(code, KeyModifiers::ALT);
+        assert_eq!(action(Focus::ChatInput, alt(KeyCode::Char('s'))), Some(Action::PaneSplit(Axis::Vertical)));
+        assert_eq!(action(Focus::QuickOpen, alt(KeyCode::Enter)), Some(Action::InsertPath));
+        assert_eq!(action(Focus::Global, KeyEvent::new(KeyCode::Left, KeyModifiers::ALT | KeyModifiers::SHIFT)), Some(Action::PaneResize(Move::Left)));
+        let altgr = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL | KeyModifiers::ALT);
+        assert_eq!(action(Focus::ChatInput, altgr('@')), Some(Action::Type('@')));
+        assert_eq!(action(Focus::Palette, altgr('s')), Some(Action::Type('s')));
+        assert_eq!(action(Focus::DiffView, altgr('[')), None);
+
+        assert_eq!(Focus::for_view(ViewId::Chat), Focus::ChatInput);
+        assert_eq!(Focus::for_view(ViewId::File), Focus::DiffView);
+        assert_eq!(Focus::for_view(ViewId::Settings), Focus::Global);
+    }
+}
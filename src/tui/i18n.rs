@@ -0,0 +1,98 @@
+//! UI string catalogs. Each language is a flat TOML table of `key = "text"`
+//! under `locales/`, compiled into the binary; lookups fall back to English
+//! and then to the key itself.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::OnceLock;
+
+const SOURCES: &[(&str, &str)] = &[
+    ("en", include_str!("../../locales/en.toml")),
+    ("fa", include_str!("../../locales/fa.toml")),
+];
+
+struct Catalog {
+    lang: &'static str,
+    rtl: bool,
+    entries: HashMap<String, String>,
+}
+
+static CATALOGS: OnceLock<Vec<Catalog>> = OnceLock::new();
+static ACTIVE: AtomicUsize = AtomicUsize::new(0);
+
+fn catalogs() -> &'static [Catalog] {
+    CATALOGS.get_or_init(|| {
+        SOURCES
+            .iter()
+            .map(|(lang, src)| {
+                let entries: HashMap<String, String> = toml::from_str(src).expect("invalid locale catalog");
+                let rtl = entries.get("meta.rtl").map(|v| v == "true").unwrap_or(false);
+                Catalog { lang, rtl, entries }
+            })
+            .collect()
+    })
+}
+
+pub fn languages() -> Vec<&'static str> {
+    SOURCES.iter().map(|(lang, _)| *lang).collect()
+}
+
+/// Switch the UI language (`ui.language`). Returns false for unknown codes,
+/// leaving the current language in place.
+pub fn set_language(lang: &str) -> bool {
+    match catalogs().iter().position(|c| c.lang == lang) {
+        Some(i) => {
+            ACTIVE.store(i, Ordering::Relaxed);
+            true
+        }
+        None => false,
+    }
+}
+
+pub fn language() -> &'static str {
+    catalogs()[ACTIVE.load(Ordering::Relaxed)].lang
+}
+
+/// Translate `key` in the active language.
+pub fn t(key: &'static str) -> &'static str {
+    let all = catalogs();
+    [&all[ACTIVE.load(Ordering::Relaxed)], &all[0]]
+        .into_iter()
+        .find_map(|c| c.entries.get(key))
+        .map(String::as_str)
+        .unwrap_or(key)
+}
+
+/// Whether the active language is written right to left.
+pub fn is_rtl() -> bool {
+    catalogs()[ACTIVE.load(Ordering::Relaxed)].rtl
+}
+
+/// Whether `text` starts with a right-to-left letter (Hebrew, Arabic,
+/// Persian), ignoring leading neutral characters.
+pub fn is_rtl_text(text: &str) -> bool {
+    text.chars()
+        .find(|c| c.is_alphabetic())
+        .map(|c| matches!(c as u32, 0x0590..=0x08FF | 0xFB1D..=0xFDFF | 0xFE70..=0xFEFF))
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_catalogs_share_keys_and_fall_back() {
+        let en = &catalogs()[0];
+        for c in catalogs() {
+            for key in c.entries.keys() {
+                assert!(en.entries.contains_key(key), "{} has unknown key {}", c.lang, key);
+            }
+        }
+        assert_eq!(catalogs()[0].entries.len(), catalogs()[1].entries.len());
+        assert!(!set_language("xx"));
+        assert_eq!(t("no.such.key"), "no.such.key");
+        assert!(is_rtl_text("  «سلام» دنیا"));
+        assert!(!is_rtl_text("hello سلام"));
+    }
+}
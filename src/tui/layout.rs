@@ -1,5 +1,8 @@
 // helper layout utilities for TUI v0.1
 
+use ratatui::layout::Rect;
+use serde::{Deserialize, Serialize};
+
 pub enum Panel {
     Graph,
     Conversation,
@@ -11,3 +14,134 @@ impl Panel {
         ["Graph", "Conversation", "Logs"]
     }
 }
+
+/// Columns the active view keeps before side panels start to disappear.
+const MIN_MAIN_WIDTH: u16 = 40;
+
+/// Layout density, stepped with ZoomIn (Ctrl+=) / ZoomOut (Ctrl+-). Terminals
+/// can't change font size, so zooming trades side panels for content width.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Density {
+    Compact,
+    #[default]
+    Normal,
+    Spacious,
+}
+
+impl Density {
+    pub fn zoom_in(self) -> Self {
+        match self {
+            Density::Compact => Density::Normal,
+            _ => Density::Spacious,
+        }
+    }
+
+    pub fn zoom_out(self) -> Self {
+        match self {
+            Density::Spacious => Density::Normal,
+            _ => Density::Compact,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Density::Compact => "compact",
+            Density::Normal => "normal",
+            Density::Spacious => "spacious",
+        }
+    }
+
+    fn nav_width(self) -> u16 {
+        match self {
+            Density::Compact => 14,
+            Density::Normal => 20,
+            Density::Spacious => 24,
+        }
+    }
+
+    fn sidebar_width(self) -> u16 {
+        match self {
+            Density::Compact => 24,
+            Density::Normal => 32,
+            Density::Spacious => 40,
+        }
+    }
+
+    /// Blank cells around each panel.
+    pub fn margin(self) -> u16 {
+        match self {
+            Density::Spacious => 1,
+            _ => 0,
+        }
+    }
+
+    /// Dashboard tiles per row for a view `width` columns wide.
+    pub fn tile_columns(self, width: u16) -> usize {
+        let tile = match self {
+            Density::Compact => 30,
+            Density::Normal => 40,
+            Density::Spacious => 50,
+        };
+        ((width / tile) as usize).clamp(1, 3)
+    }
+}
+
+/// Horizontal split of the body into nav, active view and sidebar. Side
+/// panels collapse (sidebar first) when the terminal is too narrow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BodyLayout {
+    pub nav: Option<Rect>,
+    pub main: Rect,
+    pub sidebar: Option<Rect>,
+}
+
+impl BodyLayout {
+    pub fn compute(area: Rect, density: Density) -> Self {
+        let mut nav = density.nav_width();
+        let mut sidebar = density.sidebar_width();
+        if area.width < MIN_MAIN_WIDTH + nav + sidebar {
+            sidebar = 0;
+        }
+        if area.width < MIN_MAIN_WIDTH + nav {
+            nav = 0;
+        }
+        let main = Rect { x: area.x + nav, width: area.width - nav - sidebar, ..area };
+        let panel = |x, width| (width > 0).then_some(Rect { x, width, ..area });
+        BodyLayout {
+            nav: panel(area.x, nav),
+            main,
+            sidebar: panel(main.x + main.width, sidebar),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_panels_collapse_on_narrow_terminals() {
+        let area = |width| Rect { x: 0, y: 0, width, height: 30 };
+
+        let wide = BodyLayout::compute(area(160), Density::Normal);
+        assert_eq!(wide.nav.unwrap().width, 20);
+        assert_eq!(wide.sidebar.unwrap().width, 32);
+        assert_eq!(wide.main, Rect { x: 20, y: 0, width: 108, height: 30 });
+
+        let medium = BodyLayout::compute(area(80), Density::Normal);
+        assert!(medium.sidebar.is_none());
+        assert_eq!(medium.main.width, 60);
+
+        let narrow = BodyLayout::compute(area(50), Density::Normal);
+        assert!(narrow.nav.is_none());
+        assert_eq!(narrow.main.width, 50);
+
+        // zooming out makes room for the sidebar again
+        assert!(BodyLayout::compute(area(80), Density::Compact).sidebar.is_some());
+        assert_eq!(Density::Compact.zoom_out(), Density::Compact);
+        assert_eq!(Density::Normal.zoom_in().zoom_in(), Density::Spacious);
+        assert_eq!(Density::Normal.tile_columns(20), 1);
+        assert_eq!(Density::Compact.tile_columns(100), 3);
+    }
+}
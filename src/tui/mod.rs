@@ -1,4 +1,11 @@
 pub mod app;
+pub mod charts;
+pub mod focus;
+pub mod i18n;
 pub mod layout;
+pub mod palette;
+pub mod panes;
+pub mod preview;
+pub mod ring;
 pub mod theme;
 pub mod views;
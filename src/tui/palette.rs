@@ -0,0 +1,230 @@
+//! Slash-command palette and quick open: fuzzy matching blended with how often and how
+//! recently each command was used. Usage is stored in the data dir and
+//! re-read before every update, so concurrent TUI sessions share it.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Commands handled by `TuiApp::run_command`, with usage hints.
+pub const COMMANDS: &[(&str, &str)] = &[
+    ("/var", "set <key> <value> | get <key> | list — shared variables"),
+    ("/watch", "<kind|all> [session] — follow another session's events"),
+    ("/unwatch", "<kind|all> — stop following"),
+    ("/compare", "<provider> <provider> — side-by-side model comparison"),
+    ("/fork", "[n] — branch the conversation after message n"),
+    ("/switch", "<n> — change conversation tab"),
+    ("/system", "<text> — set the system prompt"),
+    ("/persona", "[name] — apply or list personas"),
+    ("/export", "[path] — write the conversation as Markdown"),
+    ("/sessions", "[open <id> | save] — list saved and imported sessions, continue one, or save this one"),
+    ("/summarize", "[history] — replace older turns with a summary kept in long-term memory"),
+    ("/calls", "[n] [k] — show the tool calls behind a message, or open call k's result"),
+    ("/expand", "[n] — show or hide the messages a summary replaced"),
+    ("/remember", "<fact> | edit <n> <text> — pin a note to this project's memory"),
+    ("/forget", "<n> — remove a project note"),
+    ("/tools", "[enable|disable <name>] — tools agent runs may call"),
+    ("/dryrun", "[on|off|apply] — agent runs record what they would change"),
+    ("/pair", "[on|off] — agent runs narrate what they are doing beneath the chat"),
+    ("/review", "[base] — review this branch's changes and list findings"),
+    ("/run", "[--watch] <goal> — plan, execute and review a goal in the background"),
+    ("/plan", "approve | abort | edit <step> | <step> … — answer a plan review"),
+    ("/image", "<path> | clear — attach an image to the next chat message"),
+    ("/params", "[set <name> <value> | reset [name]] — sampling overrides for this session"),
+    ("/continue", "resume the last reply when it was cut at the output limit"),
+    ("/attach", "[<path> | rm <n> | clear] — attach files to the next chat message"),
+    ("/theme", "<name> — switch theme"),
+    ("/model", "<name> | remote:<name>/<model> — select the chat model"),
+    ("/connect", "<url> — use a running model server as the chat endpoint"),
+    ("/serve", "[model] | stop | restart — run the model server inside the TUI until it exits"),
+    ("/models", "[rescan] — refresh the model catalog; rescan reads the model directory again"),
+    ("/logs", "[all|server] — show the whole activity log, or only the model server's requests"),
+    ("/import", "<path> — copy a model file into the model directory"),
+    ("/goto", "<path>[:line] — show a file snippet"),
+    ("/autocomplete", "[on|off] — toggle inline completions"),
+    ("/cancel", "<task-id> — cancel a task and its subtasks"),
+    ("/template", "[list] | new <name> | save <task-id> <name> — task templates"),
+    ("/graph", "add|ask|rm|edge|unedge|save|load|reset — edit the agent pipeline"),
+    ("/pipeline", "[list] | run <name> <goal> — run a saved pipeline"),
+    ("/skills", "[list] | reload | search [term] | install <name> — manage WASM skills"),
+    ("/panel", "[name | refresh] — open the Panels view, or switch to a panel"),
+    ("/pane", "[<view>|logs|terminal] | split <h|v> [kind] | close | reset — arrange panes"),
+    ("/term", "<command> — run a shell command, output in the terminal pane"),
+    ("/details", "expand or collapse the latest error's hint and causes"),
+    ("/answer", "<text> — answer a pipeline's pending question (empty uses its default)"),
+];
+
+/// How many entries the "Recent" section shows.
+const RECENT_LIMIT: usize = 5;
+/// Usage older than this counts for half as much.
+const RECENCY_HALF_LIFE_SECS: f64 = 3.0 * 24.0 * 3600.0;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Usage {
+    pub count: u32,
+    /// Unix seconds.
+    pub last_used: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UsageStore {
+    #[serde(skip)]
+    path: Option<PathBuf>,
+    pub commands: HashMap<String, Usage>,
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+impl UsageStore {
+    /// Load from `path`; a missing or unreadable file starts empty.
+    pub fn open(path: Option<PathBuf>) -> Self {
+        let mut store = path
+            .as_ref()
+            .and_then(|p| fs::read_to_string(p).ok())
+            .and_then(|s| serde_json::from_str::<UsageStore>(&s).ok())
+            .unwrap_or_default();
+        store.path = path;
+        store
+    }
+
+    /// Pick up usage recorded by other sessions.
+    pub fn reload(&mut self) {
+        if self.path.is_some() {
+            *self = Self::open(self.path.take());
+        }
+    }
+
+    /// Count one use of `command` and persist.
+    pub fn record(&mut self, command: &str) -> anyhow::Result<()> {
+        self.reload();
+        let entry = self.commands.entry(command.to_string()).or_default();
+        entry.count += 1;
+        entry.last_used = now_secs();
+        if let Some(p) = &self.path {
+            if let Some(dir) = p.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            fs::write(p, serde_json::to_string_pretty(self)?)?;
+        }
+        Ok(())
+    }
+
+    /// Most recently used commands, newest first.
+    pub fn recent(&self) -> Vec<&str> {
+        let mut used: Vec<(&str, u64)> = self.commands.iter().map(|(k, u)| (k.as_str(), u.last_used)).collect();
+        used.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        used.into_iter().take(RECENT_LIMIT).map(|(k, _)| k).collect()
+    }
+
+    /// Frequency weighted by recency: `ln(1 + count)` halved every half-life.
+    fn boost(&self, command: &str, now: u64) -> f64 {
+        match self.commands.get(command) {
+            Some(u) => {
+                let age = now.saturating_sub(u.last_used) as f64;
+                (1.0 + u.count as f64).ln() * 0.5f64.powf(age / RECENCY_HALF_LIFE_SECS)
+            }
+            None => 0.0,
+        }
+    }
+}
+
+/// Subsequence match of `query` in `text` (case-insensitive). Consecutive,
+/// prefix and word-start matches score higher; `None` if some query char is
+/// missing.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<f64> {
+    fuzzy_match(query, text).map(|(score, _)| score)
+}
+
+/// Like `fuzzy_score`, also returning the matched char positions for
+/// highlighting.
+pub fn fuzzy_match(query: &str, text: &str) -> Option<(f64, Vec<usize>)> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let mut score = 0.0;
+    let mut pos = 0;
+    let mut positions = vec![];
+    for q in query.to_lowercase().chars() {
+        let found = text[pos..].iter().position(|c| *c == q)? + pos;
+        score += 1.0;
+        if found == 0 {
+            score += 2.0;
+        } else if matches!(text[found - 1], '/' | '_' | '-' | '.') {
+            score += 1.0;
+        }
+        if positions.last().map(|p| p + 1 == found).unwrap_or(false) {
+            score += 1.5;
+        }
+        positions.push(found);
+        pos = found + 1;
+    }
+    Some((score, positions))
+}
+
+/// Paths matching `query`, best first, boosted by how recently and often
+/// each was opened. Returns indices into `paths` with match positions.
+pub fn rank_paths(query: &str, paths: &[String], usage: &UsageStore, limit: usize) -> Vec<(usize, Vec<usize>)> {
+    let now = now_secs();
+    let mut scored: Vec<(usize, f64, Vec<usize>)> = paths
+        .iter()
+        .enumerate()
+        .filter_map(|(i, p)| fuzzy_match(query, p).map(|(s, pos)| (i, s + 3.0 * usage.boost(p, now) - p.len() as f64 * 0.01, pos)))
+        .collect();
+    scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal).then(a.0.cmp(&b.0)));
+    scored.into_iter().take(limit).map(|(i, _, pos)| (i, pos)).collect()
+}
+
+/// Commands matching `query` (leading `/` optional), best first.
+pub fn rank<'a>(query: &str, commands: &[(&'a str, &'a str)], usage: &UsageStore) -> Vec<&'a str> {
+    let query = query.trim_start_matches('/');
+    let now = now_secs();
+    let mut scored: Vec<(&str, f64)> = commands
+        .iter()
+        .filter_map(|(name, _)| fuzzy_score(query, name.trim_start_matches('/')).map(|s| (*name, s + 2.0 * usage.boost(name, now))))
+        .collect();
+    scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal).then(a.0.cmp(b.0)));
+    scored.into_iter().map(|(name, _)| name).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_usage_boosts_ranking_and_persists() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("usage.json");
+        let mut store = UsageStore::open(Some(path.clone()));
+
+        assert_eq!(fuzzy_score("sw", "switch"), Some(5.5));
+        assert!(fuzzy_score("xz", "switch").is_none());
+        // unused: shorter/prefix match wins ties alphabetically
+        assert_eq!(rank("/s", COMMANDS, &store)[..3], ["/serve", "/sessions", "/skills"]);
+
+        for _ in 0..3 {
+            store.record("/system").unwrap();
+        }
+        assert_eq!(rank("/s", COMMANDS, &store)[0], "/system");
+
+        // a second session sees the same counts
+        let mut other = UsageStore::open(Some(path));
+        other.record("/fork").unwrap();
+        store.reload();
+        assert_eq!(store.commands["/system"].count, 3);
+        assert_eq!(store.recent().len(), 2);
+    }
+
+    #[test]
+    fn test_rank_paths_prefers_word_starts_and_mru() {
+        let paths: Vec<String> = ["src/tui/app.rs", "src/agent/mod.rs", "docs/app_notes.md"].iter().map(|s| s.to_string()).collect();
+        let mut usage = UsageStore::default();
+        let ranked = rank_paths("app", &paths, &usage, 10);
+        assert_eq!(ranked.len(), 2);
+        assert_eq!(ranked[0], (0, vec![8, 9, 10]));
+
+        usage.record("docs/app_notes.md").unwrap();
+        assert_eq!(rank_paths("app", &paths, &usage, 10)[0].0, 2);
+    }
+}
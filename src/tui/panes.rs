@@ -0,0 +1,369 @@
+//! Splittable panes for the main area. The layout is a binary tree of
+//! splits whose leaves each show a view, the activity log or the `/term`
+//! terminal. One leaf has focus; keys go to it. Layouts are saved per TUI
+//! session (`agent tui --session <name>`) in `layouts.json`.
+
+use crate::tui::views::ViewId;
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+/// Percentage a resize step moves the divider.
+pub const RESIZE_STEP: u16 = 5;
+const MIN_RATIO: u16 = 10;
+const MAX_RATIO: u16 = 90;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum PaneKind {
+    View(ViewId),
+    Logs,
+    /// Output of `/term` commands.
+    Terminal,
+}
+
+impl PaneKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            PaneKind::View(v) => v.name(),
+            PaneKind::Logs => "logs",
+            PaneKind::Terminal => "terminal",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "logs" => Some(PaneKind::Logs),
+            "terminal" => Some(PaneKind::Terminal),
+            _ => ViewId::from_name(name).map(PaneKind::View),
+        }
+    }
+}
+
+impl TryFrom<String> for PaneKind {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Self, String> {
+        PaneKind::from_name(&name).ok_or_else(|| format!("unknown pane {}", name))
+    }
+}
+
+impl From<PaneKind> for String {
+    fn from(kind: PaneKind) -> String {
+        kind.name().to_string()
+    }
+}
+
+/// `Horizontal` puts the two halves side by side, `Vertical` stacks them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Axis {
+    Horizontal,
+    Vertical,
+}
+
+impl Axis {
+    fn direction(self) -> Direction {
+        match self {
+            Axis::Horizontal => Direction::Horizontal,
+            Axis::Vertical => Direction::Vertical,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Move {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+impl Move {
+    fn axis(self) -> Axis {
+        match self {
+            Move::Left | Move::Right => Axis::Horizontal,
+            Move::Up | Move::Down => Axis::Vertical,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Node {
+    Leaf(PaneKind),
+    Split {
+        axis: Axis,
+        /// Share of the first half, in percent.
+        ratio: u16,
+        first: Box<Node>,
+        second: Box<Node>,
+    },
+}
+
+impl Node {
+    fn leaves(&self, out: &mut Vec<PaneKind>) {
+        match self {
+            Node::Leaf(kind) => out.push(*kind),
+            Node::Split { first, second, .. } => {
+                first.leaves(out);
+                second.leaves(out);
+            }
+        }
+    }
+
+    fn count(&self) -> usize {
+        match self {
+            Node::Leaf(_) => 1,
+            Node::Split { first, second, .. } => first.count() + second.count(),
+        }
+    }
+
+    fn rects(&self, area: Rect, out: &mut Vec<Rect>) {
+        match self {
+            Node::Leaf(_) => out.push(area),
+            Node::Split { axis, ratio, first, second } => {
+                let halves = Layout::default()
+                    .direction(axis.direction())
+                    .constraints([Constraint::Percentage(*ratio), Constraint::Percentage(100 - *ratio)])
+                    .split(area);
+                first.rects(halves[0], out);
+                second.rects(halves[1], out);
+            }
+        }
+    }
+
+    /// The `index`th leaf in reading order.
+    fn leaf_mut(&mut self, index: usize) -> Option<&mut Node> {
+        match self {
+            Node::Leaf(_) => (index == 0).then_some(self),
+            Node::Split { first, second, .. } => {
+                let n = first.count();
+                if index < n {
+                    first.leaf_mut(index)
+                } else {
+                    second.leaf_mut(index - n)
+                }
+            }
+        }
+    }
+
+    /// Drop the `index`th leaf; its sibling takes the parent's place.
+    fn remove(&mut self, index: usize) -> bool {
+        let Node::Split { first, second, .. } = self else { return false };
+        let n = first.count();
+        let (target, other, i) = if index < n { (first, second, index) } else { (second, first, index - n) };
+        if let Node::Leaf(_) = **target {
+            if i == 0 {
+                *self = std::mem::replace(other.as_mut(), Node::Leaf(PaneKind::Logs));
+                return true;
+            }
+            return false;
+        }
+        target.remove(i)
+    }
+
+    /// Move the divider of the innermost `axis` split around leaf `index`.
+    fn resize(&mut self, index: usize, along: Axis, delta: i16) -> bool {
+        let Node::Split { axis, ratio, first, second } = self else { return false };
+        let n = first.count();
+        let inner = if index < n { first.resize(index, along, delta) } else { second.resize(index - n, along, delta) };
+        if inner {
+            return true;
+        }
+        if *axis != along {
+            return false;
+        }
+        *ratio = (*ratio as i16 + delta).clamp(MIN_RATIO as i16, MAX_RATIO as i16) as u16;
+        true
+    }
+}
+
+/// A pane tree and its focused leaf.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PaneLayout {
+    pub root: Node,
+    pub focus: usize,
+}
+
+impl PaneLayout {
+    pub fn single(kind: PaneKind) -> Self {
+        Self { root: Node::Leaf(kind), focus: 0 }
+    }
+
+    pub fn count(&self) -> usize {
+        self.root.count()
+    }
+
+    pub fn kinds(&self) -> Vec<PaneKind> {
+        let mut out = vec![];
+        self.root.leaves(&mut out);
+        out
+    }
+
+    pub fn focused(&self) -> PaneKind {
+        self.kinds()[self.focus.min(self.count() - 1)]
+    }
+
+    /// Leaf areas in the same order as `kinds`.
+    pub fn rects(&self, area: Rect) -> Vec<Rect> {
+        let mut out = vec![];
+        self.root.rects(area, &mut out);
+        out
+    }
+
+    /// Show `kind` in the focused pane.
+    pub fn set_focused(&mut self, kind: PaneKind) {
+        if let Some(leaf) = self.root.leaf_mut(self.focus) {
+            *leaf = Node::Leaf(kind);
+        }
+    }
+
+    /// Split the focused pane in half; the new half shows `kind` and gets focus.
+    pub fn split(&mut self, axis: Axis, kind: PaneKind) {
+        let Some(leaf) = self.root.leaf_mut(self.focus) else { return };
+        let current = std::mem::replace(leaf, Node::Leaf(kind));
+        *leaf = Node::Split { axis, ratio: 50, first: Box::new(current), second: Box::new(Node::Leaf(kind)) };
+        self.focus += 1;
+    }
+
+    /// Close the focused pane unless it is the last one.
+    pub fn close(&mut self) -> bool {
+        if !self.root.remove(self.focus) {
+            return false;
+        }
+        self.focus = self.focus.min(self.count() - 1);
+        true
+    }
+
+    pub fn resize(&mut self, dir: Move) -> bool {
+        let delta = match dir {
+            Move::Left | Move::Up => -(RESIZE_STEP as i16),
+            Move::Right | Move::Down => RESIZE_STEP as i16,
+        };
+        self.root.resize(self.focus, dir.axis(), delta)
+    }
+
+    /// Focus the nearest pane in `dir` from the focused one, laid out in `area`.
+    pub fn move_focus(&mut self, dir: Move, area: Rect) -> bool {
+        let rects = self.rects(area);
+        let Some(from) = rects.get(self.focus) else { return false };
+        let centre = |r: &Rect| (r.x as i32 * 2 + r.width as i32, r.y as i32 * 2 + r.height as i32);
+        let (cx, cy) = centre(from);
+        let best = rects
+            .iter()
+            .enumerate()
+            .filter(|(i, r)| {
+                *i != self.focus
+                    && match dir {
+                        Move::Left => r.x + r.width <= from.x,
+                        Move::Right => r.x >= from.x + from.width,
+                        Move::Up => r.y + r.height <= from.y,
+                        Move::Down => r.y >= from.y + from.height,
+                    }
+            })
+            .min_by_key(|(_, r)| {
+                let (x, y) = centre(r);
+                // prefer panes in line with this one over closer diagonal ones
+                match dir.axis() {
+                    Axis::Horizontal => (y - cy).abs() * 4 + (x - cx).abs(),
+                    Axis::Vertical => (x - cx).abs() * 4 + (y - cy).abs(),
+                }
+            });
+        match best {
+            Some((i, _)) => {
+                self.focus = i;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Saved layouts by session name.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LayoutStore {
+    #[serde(skip)]
+    path: Option<PathBuf>,
+    pub sessions: HashMap<String, PaneLayout>,
+}
+
+impl LayoutStore {
+    /// Load from `path`; a missing or unreadable file starts empty.
+    pub fn open(path: Option<PathBuf>) -> Self {
+        let mut store = path
+            .as_ref()
+            .and_then(|p| fs::read_to_string(p).ok())
+            .and_then(|s| serde_json::from_str::<LayoutStore>(&s).ok())
+            .unwrap_or_default();
+        store.path = path;
+        store
+    }
+
+    pub fn get(&self, session: &str) -> Option<&PaneLayout> {
+        self.sessions.get(session)
+    }
+
+    pub fn save(&mut self, session: &str, layout: &PaneLayout) -> anyhow::Result<()> {
+        self.sessions.insert(session.to_string(), layout.clone());
+        if let Some(p) = &self.path {
+            if let Some(dir) = p.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            fs::write(p, serde_json::to_string_pretty(self)?)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_focus_resize_and_persist() {
+        let area = Rect { x: 0, y: 0, width: 100, height: 40 };
+        let mut layout = PaneLayout::single(PaneKind::View(ViewId::Chat));
+        assert!(!layout.close(), "the last pane stays");
+
+        // chat | file, then logs under file
+        layout.split(Axis::Horizontal, PaneKind::View(ViewId::File));
+        layout.split(Axis::Vertical, PaneKind::Logs);
+        let names: Vec<&str> = layout.kinds().iter().map(|k| k.name()).collect();
+        assert_eq!(names, ["chat", "file", "logs"]);
+        assert_eq!(layout.focused(), PaneKind::Logs);
+        assert_eq!(layout.rects(area)[2], Rect { x: 50, y: 20, width: 50, height: 20 });
+
+        assert!(layout.move_focus(Move::Left, area));
+        assert_eq!(layout.focused(), PaneKind::View(ViewId::Chat));
+        assert!(!layout.move_focus(Move::Left, area));
+        assert!(layout.move_focus(Move::Right, area));
+        assert_eq!(layout.focused(), PaneKind::View(ViewId::File));
+        assert!(layout.move_focus(Move::Down, area));
+
+        // Right widens the left column: the innermost horizontal split
+        assert!(layout.resize(Move::Right));
+        assert_eq!(layout.rects(area)[0].width, 55);
+        assert!(layout.resize(Move::Up));
+        assert_eq!(layout.rects(area)[1].height, 18);
+        for _ in 0..20 {
+            layout.resize(Move::Left);
+        }
+        assert_eq!(layout.rects(area)[0].width, 10);
+
+        layout.set_focused(PaneKind::Terminal);
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("layouts.json");
+        LayoutStore::open(Some(path.clone())).save("work", &layout).unwrap();
+        let saved = LayoutStore::open(Some(path));
+        assert_eq!(saved.get("work"), Some(&layout));
+        assert!(saved.get("other").is_none());
+
+        assert!(layout.close());
+        assert_eq!(layout.kinds(), [PaneKind::View(ViewId::Chat), PaneKind::View(ViewId::File)]);
+        assert_eq!(layout.focus, 1);
+        assert_eq!(layout.rects(area)[1].height, 40);
+    }
+}
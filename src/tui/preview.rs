@@ -0,0 +1,176 @@
+//! Previews for the highlighted palette command. Providers run off the UI
+//! thread; the app spawns `PreviewRegistry::preview` and draws the result
+//! when it arrives.
+
+use crate::config::ThemeName;
+use crate::models::ModelManager;
+use crate::tui::theme::ThemeCatalog;
+use async_trait::async_trait;
+use ratatui::style::Color;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Lines shown around the `/goto` target line.
+const SNIPPET_CONTEXT: usize = 4;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Preview {
+    Text(Vec<String>),
+    /// Theme name with its accent, text and muted colors.
+    Swatches(Vec<(String, [Color; 3])>),
+}
+
+#[async_trait]
+pub trait PreviewProvider: Send + Sync {
+    /// Slash command this provider previews, e.g. `/model`.
+    fn command(&self) -> &'static str;
+    async fn preview(&self, args: &str) -> Preview;
+}
+
+#[derive(Clone, Default)]
+pub struct PreviewRegistry {
+    providers: Vec<Arc<dyn PreviewProvider>>,
+}
+
+impl PreviewRegistry {
+    pub fn with_defaults(model_dir: PathBuf) -> Self {
+        let mut registry = Self::default();
+        registry.register(Arc::new(ModelsPreview { model_dir }));
+        registry.register(Arc::new(GotoPreview));
+        registry.register(Arc::new(ThemePreview));
+        registry
+    }
+
+    pub fn register(&mut self, provider: Arc<dyn PreviewProvider>) {
+        self.providers.push(provider);
+    }
+
+    pub fn handles(&self, command: &str) -> bool {
+        self.providers.iter().any(|p| p.command() == command)
+    }
+
+    pub async fn preview(&self, command: &str, args: &str) -> Option<Preview> {
+        let provider = self.providers.iter().find(|p| p.command() == command)?;
+        Some(provider.preview(args).await)
+    }
+}
+
+/// Installed models, filtered by the typed prefix.
+pub struct ModelsPreview {
+    pub model_dir: PathBuf,
+}
+
+#[async_trait]
+impl PreviewProvider for ModelsPreview {
+    fn command(&self) -> &'static str {
+        "/model"
+    }
+
+    async fn preview(&self, args: &str) -> Preview {
+        let dir = self.model_dir.clone();
+        let models = tokio::task::spawn_blocking(move || ModelManager::new(Some(dir))?.discover()).await;
+        let lines = match models {
+            Ok(Ok(models)) => {
+                let lines: Vec<String> = models
+                    .into_iter()
+                    .filter(|m| m.name.starts_with(args))
+                    .map(|m| format!("{} ({}, {} MiB)", m.name, m.format, m.size / (1024 * 1024)))
+                    .collect();
+                if lines.is_empty() { vec!["no installed models match".into()] } else { lines }
+            }
+            Ok(Err(e)) => vec![format!("cannot list models: {}", e)],
+            Err(e) => vec![format!("cannot list models: {}", e)],
+        };
+        Preview::Text(lines)
+    }
+}
+
+/// Snippet of `path[:line]` around the target line.
+pub struct GotoPreview;
+
+#[async_trait]
+impl PreviewProvider for GotoPreview {
+    fn command(&self) -> &'static str {
+        "/goto"
+    }
+
+    async fn preview(&self, args: &str) -> Preview {
+        let (path, line) = parse_target(args);
+        if path.is_empty() {
+            return Preview::Text(vec!["usage: /goto <path>[:line]".into()]);
+        }
+        match tokio::fs::read_to_string(path).await {
+            Ok(text) => Preview::Text(snippet(&text, line)),
+            Err(e) => Preview::Text(vec![format!("{}: {}", path, e)]),
+        }
+    }
+}
+
+/// Split `path:line` (1-based line, default 1).
+pub fn parse_target(args: &str) -> (&str, usize) {
+    let args = args.trim();
+    match args.rsplit_once(':') {
+        Some((path, line)) if line.parse::<usize>().is_ok() => (path, line.parse::<usize>().unwrap_or(1).max(1)),
+        _ => (args, 1),
+    }
+}
+
+/// Numbered lines around 1-based `line`, the target marked with `>`.
+pub fn snippet(text: &str, line: usize) -> Vec<String> {
+    let first = line.saturating_sub(SNIPPET_CONTEXT + 1);
+    text.lines()
+        .enumerate()
+        .skip(first)
+        .take(2 * SNIPPET_CONTEXT + 1)
+        .map(|(i, l)| format!("{}{:>4} {}", if i + 1 == line { ">" } else { " " }, i + 1, l))
+        .collect()
+}
+
+/// Color swatches for themes whose name starts with the typed prefix.
+pub struct ThemePreview;
+
+#[async_trait]
+impl PreviewProvider for ThemePreview {
+    fn command(&self) -> &'static str {
+        "/theme"
+    }
+
+    async fn preview(&self, args: &str) -> Preview {
+        let prefix = args.trim().to_lowercase();
+        let catalog = ThemeCatalog;
+        let swatches = ThemeName::all()
+            .iter()
+            .map(|name| catalog.resolve(name))
+            .filter(|t| t.name.to_lowercase().starts_with(&prefix))
+            .map(|t| (t.name.to_string(), [t.accent, t.text, t.muted_text]))
+            .collect();
+        Preview::Swatches(swatches)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_goto_and_theme_previews() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("lib.rs");
+        let body: String = (1..=20).map(|i| format!("line {}\n", i)).collect();
+        std::fs::write(&file, body).unwrap();
+
+        let registry = PreviewRegistry::with_defaults(dir.path().join("models"));
+        let Some(Preview::Text(lines)) = registry.preview("/goto", &format!("{}:10", file.display())).await else {
+            panic!("expected text preview");
+        };
+        assert_eq!(lines.len(), 9);
+        assert_eq!(lines[4], ">  10 line 10");
+
+        let Some(Preview::Swatches(themes)) = registry.preview("/theme", "sol").await else {
+            panic!("expected swatches");
+        };
+        assert_eq!(themes.len(), 2);
+        assert!(registry.preview("/var", "").await.is_none());
+        assert_eq!(parse_target("src/main.rs"), ("src/main.rs", 1));
+    }
+}
@@ -0,0 +1,89 @@
+//! Scrollback with a fixed size. The activity log, notifications and `/term`
+//! output keep their newest lines; appending to a full [`Ring`] drops the
+//! oldest in O(1), so a session left open for days neither slows down nor
+//! keeps growing. Sizes come from `[ui]` in config.toml.
+
+use std::collections::vec_deque::{self, VecDeque};
+
+pub struct Ring<T> {
+    items: VecDeque<T>,
+    cap: usize,
+    /// Items pushed so far, dropped ones included.
+    pushed: u64,
+}
+
+impl<T> Ring<T> {
+    /// Holds at most `cap` items (at least one).
+    pub fn new(cap: usize) -> Self {
+        let cap = cap.max(1);
+        Self { items: VecDeque::with_capacity(cap), cap, pushed: 0 }
+    }
+
+    pub fn push(&mut self, item: T) {
+        if self.items.len() == self.cap {
+            self.items.pop_front();
+        }
+        self.items.push_back(item);
+        self.pushed += 1;
+    }
+
+    /// Items pushed so far; changes whenever something is added, even once
+    /// the ring is full.
+    pub fn pushed(&self) -> u64 {
+        self.pushed
+    }
+
+    /// Change the size, dropping the oldest items that no longer fit.
+    pub fn resize(&mut self, cap: usize) {
+        self.cap = cap.max(1);
+        let over = self.items.len().saturating_sub(self.cap);
+        self.items.drain(..over);
+        self.items.shrink_to(self.cap);
+    }
+
+    /// Oldest first.
+    pub fn iter(&self) -> vec_deque::Iter<'_, T> {
+        self.items.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.items.clear();
+    }
+}
+
+impl<T> Extend<T> for Ring<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, items: I) {
+        for item in items {
+            self.push(item);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ring_keeps_the_newest() {
+        let mut ring = Ring::new(3);
+        ring.extend(1..=5);
+        assert_eq!(ring.iter().copied().collect::<Vec<_>>(), [3, 4, 5]);
+        ring.push(6);
+        assert_eq!(ring.iter().copied().collect::<Vec<_>>(), [4, 5, 6]);
+        assert_eq!(ring.pushed(), 6);
+        ring.resize(2);
+        assert_eq!(ring.iter().copied().collect::<Vec<_>>(), [5, 6]);
+        ring.resize(0);
+        assert_eq!(ring.len(), 1);
+        ring.clear();
+        assert!(ring.is_empty());
+    }
+}
@@ -1,13 +1,45 @@
+use crate::graph::pipeline::Pipeline;
+use crate::tools::audit;
 use crate::tui::theme::AppTheme;
 use ratatui::{backend::Backend, layout::{Constraint, Direction, Layout, Rect}, style::Style, widgets::{Block, Borders, List, ListItem, Paragraph, Wrap}, Frame};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// How often the Tools panel re-reads the audit log.
+const TOOLS_REFRESH: Duration = Duration::from_secs(5);
 
 #[derive(Default)]
 pub struct AgentsView {
     agents: Vec<String>,
+    /// Graph being edited with `/graph`.
+    pub pipeline: Pipeline,
+    /// Name it was loaded from or saved as.
+    pub pipeline_name: Option<String>,
+    /// Tools panel: registered tools, whether they are enabled and their
+    /// audit totals.
+    tools: Vec<(String, bool, Option<audit::ToolStats>)>,
+    /// Cleared to refresh the Tools panel on the next tick.
+    pub tools_refreshed: Option<Instant>,
 }
 
 impl AgentsView {
-    pub fn tick(&mut self) {
+    /// Re-read the tool stats when they are due; true when anything changed.
+    pub fn tick(&mut self, home: &Path) -> bool {
+        let mut changed = false;
+        if self.tools_refreshed.is_none_or(|t| t.elapsed() >= TOOLS_REFRESH) {
+            changed = true;
+            self.tools_refreshed = Some(Instant::now());
+            let stats = audit::stats(&audit::load(home));
+            self.tools = crate::agent::super_agent::SuperAgent::new()
+                .tool_names()
+                .into_iter()
+                .map(|name| {
+                    let s = stats.iter().find(|s| s.tool == name).cloned();
+                    let on = audit::enabled(&name);
+                    (name, on, s)
+                })
+                .collect();
+        }
         if self.agents.is_empty() {
             self.agents = vec![
                 "Planner • Strategy".into(),
@@ -15,7 +47,9 @@ impl AgentsView {
                 "Critic • QA review".into(),
                 "MicroAgent • Summarizer".into(),
             ];
+            changed = true;
         }
+        changed
     }
 
     pub fn render<B: Backend>(&self, f: &mut Frame<B>, area: Rect, theme: &AppTheme) {
@@ -23,6 +57,10 @@ impl AgentsView {
             .direction(Direction::Vertical)
             .constraints([Constraint::Length(5), Constraint::Min(5)])
             .split(area);
+        let body = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(25), Constraint::Percentage(35), Constraint::Percentage(40)])
+            .split(layout[1]);
 
         let intro = Paragraph::new("Define specialized agents, prompts, routing, and memory strategies.")
             .block(Block::default().borders(Borders::ALL).title("Agent Studio"))
@@ -33,6 +71,28 @@ impl AgentsView {
         let items: Vec<ListItem> = self.agents.iter().map(|a| ListItem::new(a.clone())).collect();
         let list = List::new(items)
             .block(Block::default().borders(Borders::ALL).title("Registered Agents"));
-        f.render_widget(list, layout[1]);
+        f.render_widget(list, body[0]);
+
+        let mut tools: Vec<ListItem> = self
+            .tools
+            .iter()
+            .map(|(name, on, stats)| {
+                let mut line = format!("{} {}", if *on { "✓" } else { "✗" }, name);
+                match stats {
+                    Some(s) => line.push_str(&format!("  {} calls, avg {}ms, {} failed", s.calls, s.avg_ms(), s.failures)),
+                    None => line.push_str("  no calls"),
+                }
+                let style = if *on { Style::default() } else { Style::default().fg(theme.muted_text) };
+                ListItem::new(line).style(style)
+            })
+            .collect();
+        tools.push(ListItem::new("/tools enable|disable <name>").style(Style::default().fg(theme.muted_text)));
+        f.render_widget(List::new(tools).block(Block::default().borders(Borders::ALL).title("Tools")), body[1]);
+
+        let title = format!("Pipeline: {}", self.pipeline_name.as_deref().unwrap_or("(unsaved)"));
+        let mut lines: Vec<ListItem> = self.pipeline.describe().into_iter().map(ListItem::new).collect();
+        lines.push(ListItem::new("/graph add|ask|rm|edge|unedge|save|load|reset").style(Style::default().fg(theme.muted_text)));
+        let graph = List::new(lines).block(Block::default().borders(Borders::ALL).title(title));
+        f.render_widget(graph, body[2]);
     }
 }
@@ -0,0 +1,294 @@
+use crate::memory::conversation::Conversation;
+use crate::tui::i18n::{self, t};
+use crate::tui::theme::AppTheme;
+use crate::models::setup::ModelSetup;
+use crate::types::{AttachMode, Message};
+use std::collections::BTreeSet;
+use std::hash::{Hash, Hasher};
+use ratatui::{backend::Backend, layout::{Alignment, Constraint, Direction, Layout, Rect}, style::{Modifier, Style}, text::{Span, Spans}, widgets::{Block, Borders, Paragraph, Tabs}, Frame};
+
+/// Conversation tabs plus the active conversation's messages.
+#[derive(Default)]
+pub struct ChatView {
+    pub pending: bool,
+    /// Summaries showing their original messages, by conversation and message id.
+    pub expanded: BTreeSet<(String, String)>,
+    /// Messages showing their tool calls, by conversation and message id.
+    pub calls_expanded: BTreeSet<(String, String)>,
+    /// Whether a chat model answers; `None` while that is being checked.
+    pub setup: Option<ModelSetup>,
+    lines: LineCache,
+}
+
+/// Messages already laid out for the chat's width, so a redraw only
+/// re-wraps messages that changed.
+#[derive(Default)]
+struct LineCache {
+    /// Conversation, width, theme and language the lines were wrapped for.
+    scope: (String, usize, &'static str, &'static str),
+    /// Wrapped lines by message index, with the key they were built from.
+    messages: Vec<Option<(u64, Vec<Spans<'static>>)>>,
+}
+
+/// Characters of a call's input and result shown inline.
+const CALL_PREVIEW: usize = 80;
+
+/// The first line of `text`, cut to `max` characters.
+fn preview(text: &str, max: usize) -> String {
+    let first = text.lines().find(|l| !l.trim().is_empty()).unwrap_or_default().trim();
+    let more = first.chars().count() > max || text.trim().lines().count() > 1;
+    let cut: String = first.chars().take(max).collect();
+    if more {
+        format!("{}…", cut)
+    } else {
+        cut
+    }
+}
+
+/// `line` as rows of at most `width` characters, broken at spaces where
+/// possible and at newlines always.
+fn wrap(line: Spans<'static>, width: usize) -> Vec<Spans<'static>> {
+    let width = width.max(1);
+    if line.width() <= width && !line.0.iter().any(|s| s.content.contains('\n')) {
+        return vec![line];
+    }
+    let chars: Vec<(char, Style)> = line.0.iter().flat_map(|s| s.content.chars().map(move |c| (c, s.style))).collect();
+    let mut rows = vec![];
+    let mut start = 0;
+    loop {
+        let limit = (start + width).min(chars.len());
+        if let Some(n) = chars[start..limit].iter().position(|(c, _)| *c == '\n') {
+            rows.push(&chars[start..start + n]);
+            start += n + 1;
+            continue;
+        }
+        if limit == chars.len() {
+            rows.push(&chars[start..]);
+            break;
+        }
+        // the space or newline a row breaks at is dropped
+        if matches!(chars[limit].0, ' ' | '\n') {
+            rows.push(&chars[start..limit]);
+            start = limit + 1;
+            continue;
+        }
+        match chars[start..limit].iter().rposition(|(c, _)| *c == ' ').filter(|&p| p > 0) {
+            Some(p) => {
+                rows.push(&chars[start..start + p]);
+                start += p + 1;
+            }
+            None => {
+                rows.push(&chars[start..limit]);
+                start = limit;
+            }
+        }
+    }
+    rows.into_iter().map(styled).collect()
+}
+
+/// Characters back into spans, one per run of the same style.
+fn styled(chars: &[(char, Style)]) -> Spans<'static> {
+    let mut spans: Vec<Span<'static>> = vec![];
+    for &(c, style) in chars {
+        match spans.last_mut() {
+            Some(span) if span.style == style => span.content.to_mut().push(c),
+            _ => spans.push(Span::styled(c.to_string(), style)),
+        }
+    }
+    Spans::from(spans)
+}
+
+impl ChatView {
+    /// What message `index` renders from; a different key means its cached
+    /// lines are stale.
+    fn key(&self, conv: &str, index: usize, m: &Message) -> u64 {
+        let mut h = std::collections::hash_map::DefaultHasher::new();
+        (index, &m.role, &m.content, m.truncated, m.replaces.len()).hash(&mut h);
+        for a in &m.attachments {
+            (&a.path, &a.sha256, a.mode != AttachMode::Image).hash(&mut h);
+        }
+        m.images.iter().map(|i| i.label()).collect::<Vec<_>>().hash(&mut h);
+        for c in &m.tool_calls {
+            (&c.tool, &c.input, &c.output, c.ok).hash(&mut h);
+        }
+        let at = (conv.to_string(), m.id.clone());
+        (self.expanded.contains(&at), self.calls_expanded.contains(&at)).hash(&mut h);
+        h.finish()
+    }
+
+    /// Message `index`'s lines before wrapping.
+    fn message_lines(&self, conv: &str, index: usize, m: &Message, theme: &AppTheme, width: usize) -> Vec<Spans<'static>> {
+        let mut lines = vec![];
+        let number = Span::styled(format!("{:>3} ", index), Style::default().fg(theme.muted_text));
+        let role_style = Style::default().fg(theme.accent).add_modifier(Modifier::BOLD);
+        if i18n::is_rtl_text(&m.content) {
+            // right-aligned, role on the reading side
+            let role = format!(" :{}", m.role);
+            let used = 4 + role.chars().count() + m.content.chars().count();
+            lines.push(Spans::from(vec![number, Span::raw(" ".repeat(width.saturating_sub(used))), Span::raw(m.content.clone()), Span::styled(role, role_style)]));
+        } else {
+            lines.push(Spans::from(vec![number, Span::styled(format!("{}: ", m.role), role_style), Span::raw(m.content.clone())]));
+        }
+        let files = m.attachments.iter().filter(|a| a.mode != AttachMode::Image).map(|a| format!("[file: {} @{}]", a.path.display(), &a.sha256[..8.min(a.sha256.len())]));
+        let labels: Vec<String> = files.chain(m.images.iter().map(|i| i.label())).collect();
+        if !labels.is_empty() {
+            lines.push(Spans::from(Span::styled(format!("    {}", labels.join(" ")), Style::default().fg(theme.muted_text))));
+        }
+        if !m.replaces.is_empty() {
+            let muted = Style::default().fg(theme.muted_text);
+            if self.expanded.contains(&(conv.to_string(), m.id.clone())) {
+                lines.push(Spans::from(Span::styled(format!("    ▾ {} (/expand {})", t("chat.originals"), index), muted)));
+                for o in &m.replaces {
+                    lines.push(Spans::from(Span::styled(format!("      {}: {}", o.role, o.content), muted)));
+                }
+            } else {
+                lines.push(Spans::from(Span::styled(format!("    ▸ {} ({}) — /expand {}", t("chat.originals"), m.replaces.len(), index), muted)));
+            }
+        }
+        if !m.tool_calls.is_empty() {
+            lines.extend(self.tool_calls(conv, index, m, theme));
+        }
+        if m.truncated {
+            lines.push(Spans::from(Span::styled(format!("    {}", t("chat.truncated")), Style::default().fg(theme.accent))));
+        }
+        lines
+    }
+
+    /// Message `index` wrapped to `width`, from the cache when unchanged.
+    fn wrapped(&mut self, conv: &Conversation, index: usize, theme: &AppTheme, width: usize) -> &[Spans<'static>] {
+        let m = &conv.messages[index];
+        let key = self.key(&conv.id, index, m);
+        if self.lines.messages.len() < conv.messages.len() {
+            self.lines.messages.resize(conv.messages.len(), None);
+        }
+        if !matches!(&self.lines.messages[index], Some((cached, _)) if *cached == key) {
+            let lines = self.message_lines(&conv.id, index, m, theme, width).into_iter().flat_map(|l| wrap(l, width)).collect();
+            self.lines.messages[index] = Some((key, lines));
+        }
+        self.lines.messages[index].as_ref().map_or(&[], |(_, lines)| lines)
+    }
+
+    /// The tool-call inspector under message `index`: a summary line, or
+    /// each call's tool, input and result when expanded.
+    fn tool_calls<'a>(&self, conv: &str, index: usize, m: &Message, theme: &AppTheme) -> Vec<Spans<'a>> {
+        let muted = Style::default().fg(theme.muted_text);
+        if !self.calls_expanded.contains(&(conv.to_string(), m.id.clone())) {
+            return vec![Spans::from(Span::styled(format!("    ▸ {} ({}) — /calls {}", t("chat.tool_calls"), m.tool_calls.len(), index), muted))];
+        }
+        let mut lines = vec![Spans::from(Span::styled(format!("    ▾ {} — /calls {} <k> {}", t("chat.tool_calls"), index, t("chat.open_call")), muted))];
+        for (k, call) in m.tool_calls.iter().enumerate() {
+            let (mark, color) = if call.ok { ("✓", theme.accent) } else { ("✗", ratatui::style::Color::Red) };
+            lines.push(Spans::from(vec![
+                Span::styled(format!("      {} {} ", k + 1, mark), Style::default().fg(color)),
+                Span::styled(format!("{} ", call.tool), Style::default().add_modifier(Modifier::BOLD)),
+                Span::styled(preview(&call.input, CALL_PREVIEW), muted),
+            ]));
+            lines.push(Spans::from(Span::styled(format!("          → {}", preview(&call.output, CALL_PREVIEW)), muted)));
+        }
+        lines
+    }
+
+    pub fn render<B: Backend>(&mut self, f: &mut Frame<B>, area: Rect, theme: &AppTheme, conversations: &[Conversation], active: usize) {
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(5)])
+            .split(area);
+
+        let titles: Vec<Spans> = conversations.iter().map(|c| Spans::from(c.display_title())).collect();
+        let tabs = Tabs::new(titles)
+            .select(active)
+            .block(Block::default().borders(Borders::ALL).title(t("panel.conversations")))
+            .highlight_style(Style::default().fg(theme.accent).add_modifier(Modifier::BOLD));
+        f.render_widget(tabs, layout[0]);
+
+        let width = layout[1].width.saturating_sub(2).max(1) as usize;
+        let height = layout[1].height.saturating_sub(2) as usize;
+        let conv = conversations.get(active);
+        let scope = (conv.map(|c| c.id.clone()).unwrap_or_default(), width, theme.name, i18n::language());
+        if self.lines.scope != scope {
+            self.lines = LineCache { scope, messages: vec![] };
+        }
+        if let Some(conv) = conv {
+            self.lines.messages.truncate(conv.messages.len());
+        }
+
+        let mut lines: Vec<Spans> = vec![];
+        if self.pending {
+            lines.push(Spans::from(Span::styled(t("chat.typing"), Style::default().fg(theme.muted_text))));
+        }
+        match &self.setup {
+            Some(ModelSetup::Connected(_)) => {}
+            None => lines.push(Spans::from(Span::styled(t("setup.checking"), Style::default().fg(theme.muted_text)))),
+            Some(ModelSetup::NotServed(local)) => {
+                lines.push(Spans::from(Span::styled(t("setup.not_served"), Style::default().fg(theme.accent).add_modifier(Modifier::BOLD))));
+                lines.push(Spans::from(format!("  {}", local.join(", "))));
+                lines.extend(["setup.serve", "setup.connect"].map(|k| Spans::from(t(k))));
+            }
+            Some(ModelSetup::Missing) => {
+                lines.push(Spans::from(Span::styled(t("setup.missing"), Style::default().fg(theme.accent).add_modifier(Modifier::BOLD))));
+                lines.extend(["setup.download", "setup.import", "setup.connect"].map(|k| Spans::from(t(k))));
+            }
+        }
+        let count = conv.map_or(0, |c| c.messages.len());
+        if lines.is_empty() && count == 0 {
+            lines.push(Spans::from(Span::styled(t("chat.empty"), Style::default().fg(theme.muted_text))));
+        }
+        // newest first until the panel is full, so only messages in view are
+        // laid out; the newest stay in view
+        let mut rows: Vec<Spans> = lines.into_iter().flat_map(|l| wrap(l, width)).rev().collect();
+        if let Some(conv) = conv {
+            for index in (0..count).rev() {
+                if rows.len() >= height {
+                    break;
+                }
+                rows.extend(self.wrapped(conv, index, theme, width).iter().rev().cloned());
+            }
+        }
+        rows.truncate(height);
+        rows.reverse();
+        let body = Paragraph::new(rows)
+            .block(Block::default().borders(Borders::ALL).title(t("panel.chat")))
+            .style(Style::default().fg(theme.text))
+            .alignment(if i18n::is_rtl() { Alignment::Right } else { Alignment::Left });
+        f.render_widget(body, layout[1]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(rows: &[Spans]) -> Vec<String> {
+        rows.iter().map(|r| r.0.iter().map(|s| s.content.as_ref()).collect()).collect()
+    }
+
+    #[test]
+    fn test_lines_are_wrapped_once_per_change() {
+        let bold = Style::default().add_modifier(Modifier::BOLD);
+        let line = Spans::from(vec![Span::styled("you: ", bold), Span::raw("a fairly long message\nand more")]);
+        let rows = wrap(line, 10);
+        assert_eq!(text(&rows), ["you: a", "fairly", "long", "message", "and more"]);
+        assert_eq!(rows[0].0[0].style, bold);
+        assert_eq!(text(&wrap(Spans::from("abcdefghij12"), 5)), ["abcde", "fghij", "12"]);
+
+        let theme = crate::tui::theme::ThemeCatalog.resolve(&Default::default());
+        let mut conv = Conversation::new("chat");
+        conv.messages.push(Message::new("user", "hello there"));
+        let mut view = ChatView::default();
+        let first = view.wrapped(&conv, 0, &theme, 40).to_vec();
+        assert_eq!(text(&first), ["  0 user: hello there"]);
+        let key = view.lines.messages[0].as_ref().map(|(k, _)| *k);
+        assert_eq!(text(view.wrapped(&conv, 0, &theme, 40)), text(&first));
+        assert_eq!(view.lines.messages[0].as_ref().map(|(k, _)| *k), key);
+
+        conv.messages[0].content.push_str(", again");
+        assert_eq!(text(view.wrapped(&conv, 0, &theme, 40)), ["  0 user: hello there, again"]);
+        assert_ne!(view.lines.messages[0].as_ref().map(|(k, _)| *k), key);
+
+        // expanded state is keyed by message id, so it survives the message moving
+        conv.messages[0].tool_calls.push(crate::types::ToolCallRecord { tool: "read".into(), input: "a.rs".into(), output: "fn main".into(), ok: true });
+        view.calls_expanded.insert((conv.id.clone(), conv.messages[0].id.clone()));
+        conv.messages.insert(0, Message::new("user", "first"));
+        assert_eq!(text(view.wrapped(&conv, 1, &theme, 80))[1], "    ▾ tool calls — /calls 1 <k> opens a full result");
+    }
+}
@@ -0,0 +1,65 @@
+use crate::models::compare::CompareColumn;
+use crate::tui::theme::AppTheme;
+use ratatui::{backend::Backend, layout::{Constraint, Direction, Layout, Rect}, style::{Modifier, Style}, text::{Span, Spans}, widgets::{Block, Borders, Paragraph, Wrap}, Frame};
+
+/// Split chat view sending one prompt to two models.
+#[derive(Default)]
+pub struct CompareView {
+    pub pair: Option<(String, String)>,
+    pub prompt: String,
+    pub pending: bool,
+    pub columns: Option<[CompareColumn; 2]>,
+}
+
+impl CompareView {
+    pub fn render<B: Backend>(&self, f: &mut Frame<B>, area: Rect, theme: &AppTheme) {
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(5)])
+            .split(area);
+
+        let header = match &self.pair {
+            Some((a, b)) if self.prompt.is_empty() => format!("{} vs {} — type a prompt and press Enter", a, b),
+            Some(_) => format!("> {}", self.prompt),
+            None => "Pick two models with /compare <a> <b> (model name or `remote`)".to_string(),
+        };
+        let prompt = Paragraph::new(header)
+            .block(Block::default().borders(Borders::ALL).title("Prompt"))
+            .style(Style::default().fg(theme.text));
+        f.render_widget(prompt, layout[0]);
+
+        let cols = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(layout[1]);
+
+        for (i, area) in cols.iter().enumerate() {
+            let label = match &self.pair {
+                Some((a, b)) => if i == 0 { a.clone() } else { b.clone() },
+                None => if i == 0 { "A".into() } else { "B".into() },
+            };
+            let body = match (&self.columns, self.pending) {
+                (_, true) => vec![Spans::from(Span::raw("waiting for response..."))],
+                (Some(c), false) => {
+                    let c = &c[i];
+                    let stats = format!("{:.0} ms • {} tokens", c.latency_ms, c.tokens);
+                    let text = match &c.reply {
+                        Ok(t) => t.clone(),
+                        Err(e) => format!("error: {}", e),
+                    };
+                    vec![
+                        Spans::from(Span::styled(stats, Style::default().fg(theme.accent).add_modifier(Modifier::BOLD))),
+                        Spans::from(Span::raw("")),
+                        Spans::from(Span::raw(text)),
+                    ]
+                }
+                (None, false) => vec![Spans::from(Span::styled("no response yet", Style::default().fg(theme.muted_text)))],
+            };
+            let column = Paragraph::new(body)
+                .block(Block::default().borders(Borders::ALL).title(label))
+                .style(Style::default().fg(theme.text))
+                .wrap(Wrap { trim: false });
+            f.render_widget(column, *area);
+        }
+    }
+}
@@ -1,21 +1,69 @@
+use crate::tasks::timing::{self, TimingStore};
+use crate::tasks::{TaskStatus, TaskStore};
+use crate::tui::charts;
+use crate::tui::layout::Density;
 use crate::tui::theme::AppTheme;
-use ratatui::{backend::Backend, layout::{Constraint, Direction, Layout, Rect}, style::{Style, Modifier}, text::{Span, Spans}, widgets::{Block, Borders, List, ListItem, Paragraph, Wrap}, Frame};
+use ratatui::{backend::Backend, layout::{Constraint, Direction, Layout, Rect}, style::{Color, Style, Modifier}, text::{Span, Spans}, widgets::{Block, Borders, List, ListItem, Paragraph, Wrap}, Frame};
+use std::time::{Duration, Instant};
 
 #[derive(Default)]
 pub struct DashboardView {
     highlights: Vec<String>,
+    /// `models.preload` progress: model name and state.
+    pub warmup: Vec<(String, String)>,
+    /// Latest `/run --watch` runs, oldest first.
+    pub watch: Vec<String>,
+    /// Estimated tokens of this session's chat exchanges, oldest first.
+    tokens: std::collections::VecDeque<u64>,
+    /// Running tasks with their elapsed time and ETA.
+    running: Vec<String>,
+    running_at: Option<Instant>,
 }
 
+/// How often the running tasks are re-read.
+const RUNNING_REFRESH: Duration = Duration::from_secs(2);
+
+/// Exchanges kept for the token usage chart.
+const TOKEN_HISTORY: usize = 120;
+
 impl DashboardView {
+    /// Add an exchange of `tokens` (prompt and reply) to the usage chart.
+    pub fn record_tokens(&mut self, tokens: u64) {
+        if self.tokens.len() == TOKEN_HISTORY {
+            self.tokens.pop_front();
+        }
+        self.tokens.push_back(tokens);
+    }
+
+    /// Re-read the running tasks, at most every `RUNNING_REFRESH`; true
+    /// when they were.
+    pub fn refresh_running(&mut self, store: &TaskStore) -> bool {
+        if self.running_at.is_some_and(|t| t.elapsed() < RUNNING_REFRESH) {
+            return false;
+        }
+        self.running_at = Some(Instant::now());
+        let all = store.list();
+        let (history, now) = (TimingStore::new(store.home()).load(), crate::tasks::now_secs());
+        self.running = all
+            .iter()
+            .filter(|t| t.status == TaskStatus::Running && t.parent_id.is_none())
+            .map(|t| format!("{} {}  {}", &t.id[..8], t.title, timing::describe(t, &all, &history, now)))
+            .collect();
+        true
+    }
+
     pub fn tick(&mut self) {
         if self.highlights.len() > 6 {
             self.highlights.truncate(6);
         }
     }
 
-    pub fn render<B: Backend>(&mut self, f: &mut Frame<B>, area: Rect, theme: &AppTheme) {
+    pub fn render<B: Backend>(&mut self, f: &mut Frame<B>, area: Rect, theme: &AppTheme, density: Density) {
+        // tiles sit side by side when there is room, stacked otherwise
+        let direction = if density.tile_columns(area.width) > 1 { Direction::Horizontal } else { Direction::Vertical };
         let layout = Layout::default()
-            .direction(Direction::Vertical)
+            .direction(direction)
+            .margin(density.margin())
             .constraints([Constraint::Percentage(45), Constraint::Percentage(55)])
             .split(area);
 
@@ -40,6 +88,54 @@ impl DashboardView {
         };
         let list = List::new(items)
             .block(Block::default().borders(Borders::ALL).title("Highlights"));
-        f.render_widget(list, layout[1]);
+        if self.warmup.is_empty() && self.watch.is_empty() && self.tokens.is_empty() && self.running.is_empty() {
+            f.render_widget(list, layout[1]);
+            return;
+        }
+        let tile = |lines: usize| Constraint::Length(if lines == 0 { 0 } else { lines as u16 + 2 });
+        let right = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Min(3),
+                tile(self.running.len()),
+                tile(if self.tokens.is_empty() { 0 } else { 3 }),
+                tile(self.watch.len()),
+                tile(self.warmup.len()),
+            ])
+            .split(layout[1]);
+        f.render_widget(list, right[0]);
+        if !self.running.is_empty() {
+            let running: Vec<ListItem> = self.running.iter().map(|line| ListItem::new(line.clone())).collect();
+            f.render_widget(List::new(running).block(Block::default().borders(Borders::ALL).title("Running")), right[1]);
+        }
+        if !self.tokens.is_empty() {
+            let total: u64 = self.tokens.iter().sum();
+            let title = format!("Token usage • {} exchange(s), ~{} tokens, last {}", self.tokens.len(), total, self.tokens.back().copied().unwrap_or(0));
+            let shown: Vec<u64> = self.tokens.iter().skip(self.tokens.len().saturating_sub(right[2].width.saturating_sub(2) as usize)).copied().collect();
+            f.render_widget(charts::sparkline(&shown, title, theme), right[2]);
+        }
+        let watch: Vec<ListItem> = self
+            .watch
+            .iter()
+            .map(|line| {
+                let color = if line.contains(": passed") { theme.accent } else { Color::Red };
+                ListItem::new(line.clone()).style(Style::default().fg(color))
+            })
+            .collect();
+        if !watch.is_empty() {
+            f.render_widget(List::new(watch).block(Block::default().borders(Borders::ALL).title("Watch")), right[3]);
+        }
+        let warmup: Vec<ListItem> = self
+            .warmup
+            .iter()
+            .map(|(name, state)| {
+                let color = if state.starts_with("ready") { theme.accent } else if state.starts_with("failed") { Color::Red } else { theme.muted_text };
+                ListItem::new(Spans::from(vec![Span::raw(format!("{:<20} ", name)), Span::styled(state.clone(), Style::default().fg(color))]))
+            })
+            .collect();
+        if warmup.is_empty() {
+            return;
+        }
+        f.render_widget(List::new(warmup).block(Block::default().borders(Borders::ALL).title("Warm-up")), right[4]);
     }
 }
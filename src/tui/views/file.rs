@@ -0,0 +1,115 @@
+use crate::tui::theme::AppTheme;
+use ratatui::{
+    backend::Backend,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, Borders, Paragraph, Tabs},
+    Frame,
+};
+
+/// One open file, or text shown like one.
+pub struct FileTab {
+    pub path: String,
+    pub lines: Vec<String>,
+    pub scroll: usize,
+}
+
+/// Read-only viewer for files opened from Quick Open, a review finding or
+/// `agent triage --open`, and for tool results opened with `/calls`, one tab
+/// per file.
+#[derive(Default)]
+pub struct FileView {
+    pub tabs: Vec<FileTab>,
+    pub active: usize,
+}
+
+impl FileView {
+    /// Open `path` in a new tab, or reload and switch to its tab.
+    pub fn open(&mut self, path: &str) -> anyhow::Result<()> {
+        let text = std::fs::read_to_string(path)?;
+        self.open_text(path, &text);
+        Ok(())
+    }
+
+    /// Show `text` in a tab titled `name`, replacing a tab of that name.
+    pub fn open_text(&mut self, name: &str, text: &str) {
+        let tab = FileTab { path: name.to_string(), lines: text.lines().map(str::to_string).collect(), scroll: 0 };
+        match self.tabs.iter().position(|t| t.path == name) {
+            Some(i) => {
+                self.tabs[i] = tab;
+                self.active = i;
+            }
+            None => {
+                self.tabs.push(tab);
+                self.active = self.tabs.len() - 1;
+            }
+        }
+    }
+
+    pub fn current(&self) -> Option<&FileTab> {
+        self.tabs.get(self.active)
+    }
+
+    pub fn scroll_by(&mut self, delta: isize) {
+        let Some(tab) = self.tabs.get_mut(self.active) else { return };
+        let max = tab.lines.len().saturating_sub(1);
+        tab.scroll = tab.scroll.saturating_add_signed(delta).min(max);
+    }
+
+    /// Move to the next (`1`) or previous (`-1`) tab, wrapping around.
+    pub fn switch(&mut self, delta: isize) {
+        if !self.tabs.is_empty() {
+            self.active = (self.active as isize + delta).rem_euclid(self.tabs.len() as isize) as usize;
+        }
+    }
+
+    pub fn close(&mut self) {
+        if self.active < self.tabs.len() {
+            self.tabs.remove(self.active);
+            self.active = self.active.min(self.tabs.len().saturating_sub(1));
+        }
+    }
+
+    pub fn render<B: Backend>(&self, f: &mut Frame<B>, area: Rect, theme: &AppTheme) {
+        let area = if self.tabs.len() > 1 {
+            let rows = Layout::default().direction(Direction::Vertical).constraints([Constraint::Length(1), Constraint::Min(1)]).split(area);
+            let titles: Vec<Spans> = self
+                .tabs
+                .iter()
+                .map(|t| Spans::from(t.path.rsplit('/').next().unwrap_or(&t.path).to_string()))
+                .collect();
+            let tabs = Tabs::new(titles)
+                .select(self.active)
+                .style(Style::default().fg(theme.muted_text))
+                .highlight_style(Style::default().fg(theme.accent).add_modifier(Modifier::BOLD));
+            f.render_widget(tabs, rows[0]);
+            rows[1]
+        } else {
+            area
+        };
+        let Some(tab) = self.current() else {
+            let empty = Paragraph::new("").block(Block::default().borders(Borders::ALL).title("No file — Ctrl+O to open"));
+            f.render_widget(empty, area);
+            return;
+        };
+        let height = area.height.saturating_sub(2) as usize;
+        let lines: Vec<Spans> = tab
+            .lines
+            .iter()
+            .enumerate()
+            .skip(tab.scroll)
+            .take(height)
+            .map(|(i, l)| {
+                Spans::from(vec![
+                    Span::styled(format!("{:>5} ", i + 1), Style::default().fg(theme.muted_text)),
+                    Span::raw(l.clone()),
+                ])
+            })
+            .collect();
+        let body = Paragraph::new(lines)
+            .block(Block::default().borders(Borders::ALL).title(tab.path.clone()))
+            .style(Style::default().fg(theme.text));
+        f.render_widget(body, area);
+    }
+}
@@ -0,0 +1,80 @@
+use crate::memory::notes;
+use crate::memory::summary::{Summary, SummaryStore};
+use crate::tui::theme::AppTheme;
+use ratatui::{backend::Backend, layout::{Constraint, Direction, Layout, Rect}, style::{Modifier, Style}, widgets::{Block, Borders, List, ListItem}, Frame};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// How often the view re-reads the summaries in long-term memory.
+const REFRESH: Duration = Duration::from_secs(5);
+
+/// Project notes (`/remember`) and the conversation summaries kept for
+/// this project.
+#[derive(Default)]
+pub struct MemoryView {
+    /// Note `e` edits and `x` deletes.
+    pub selected: usize,
+    summaries: Vec<Summary>,
+    refreshed: Option<Instant>,
+}
+
+impl MemoryView {
+    /// Re-read the summaries when they are due; true when they were.
+    pub fn tick(&mut self, home: &Path, project: &str) -> bool {
+        if self.refreshed.map(|t| t.elapsed() < REFRESH).unwrap_or(false) {
+            return false;
+        }
+        self.refreshed = Some(Instant::now());
+        self.summaries = SummaryStore::new(home).for_project(project);
+        true
+    }
+
+    pub fn select_by(&mut self, delta: isize, len: usize) {
+        self.selected = self.selected.saturating_add_signed(delta).min(len.saturating_sub(1));
+    }
+
+    pub fn render<B: Backend>(&self, f: &mut Frame<B>, area: Rect, theme: &AppTheme, notes: &[String], budget: u64) {
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(55), Constraint::Percentage(45)])
+            .split(area);
+
+        let sent = notes::within_budget(notes, budget);
+        let items: Vec<ListItem> = if notes.is_empty() {
+            vec![ListItem::new("No notes yet. `/remember <fact>` pins one to this project.")]
+        } else {
+            notes
+                .iter()
+                .enumerate()
+                .map(|(i, n)| {
+                    let mut line = format!("{}. {}", i + 1, n);
+                    let mut style = Style::default();
+                    if i >= sent {
+                        line.push_str("  (over budget, not sent)");
+                        style = style.fg(theme.muted_text);
+                    }
+                    if i == self.selected {
+                        style = style.fg(theme.accent).add_modifier(Modifier::BOLD);
+                    }
+                    ListItem::new(line).style(style)
+                })
+                .collect()
+        };
+        let title = format!("Project notes • {} of {} sent (budget {} tokens)", sent, notes.len(), budget);
+        f.render_widget(List::new(items).block(Block::default().borders(Borders::ALL).title(title)), layout[0]);
+
+        let summaries: Vec<ListItem> = if self.summaries.is_empty() {
+            vec![ListItem::new("No summaries yet. `/summarize` compacts the active conversation.")]
+        } else {
+            self.summaries
+                .iter()
+                .rev()
+                .map(|s| ListItem::new(format!("{} messages: {}", s.replaced, s.text.lines().next().unwrap_or_default())))
+                .collect()
+        };
+        let list = List::new(summaries)
+            .block(Block::default().borders(Borders::ALL).title("Conversation summaries"))
+            .style(Style::default().fg(theme.text));
+        f.render_widget(list, layout[1]);
+    }
+}
@@ -1,56 +1,110 @@
 pub mod agents;
+pub mod chat;
+pub mod compare;
 pub mod dashboard;
+pub mod file;
+pub mod memory;
 pub mod models;
+pub mod panels;
+pub mod review;
 pub mod settings;
+pub mod skills;
 pub mod tasks;
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+use crate::tui::i18n::t;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum ViewId {
     Dashboard,
+    Chat,
     Agents,
     Models,
+    Skills,
     Tasks,
+    Memory,
+    Review,
+    Compare,
+    File,
+    Panels,
     Settings,
 }
 
 impl ViewId {
-    pub fn all() -> [ViewId; 5] {
+    pub fn all() -> [ViewId; 12] {
         [
             ViewId::Dashboard,
+            ViewId::Chat,
             ViewId::Agents,
             ViewId::Models,
+            ViewId::Skills,
             ViewId::Tasks,
+            ViewId::Memory,
+            ViewId::Review,
+            ViewId::Compare,
+            ViewId::File,
+            ViewId::Panels,
             ViewId::Settings,
         ]
     }
 
     pub fn next(self) -> Self {
         match self {
-            ViewId::Dashboard => ViewId::Agents,
+            ViewId::Dashboard => ViewId::Chat,
+            ViewId::Chat => ViewId::Agents,
             ViewId::Agents => ViewId::Models,
-            ViewId::Models => ViewId::Tasks,
-            ViewId::Tasks => ViewId::Settings,
+            ViewId::Models => ViewId::Skills,
+            ViewId::Skills => ViewId::Tasks,
+            ViewId::Tasks => ViewId::Memory,
+            ViewId::Memory => ViewId::Review,
+            ViewId::Review => ViewId::Compare,
+            ViewId::Compare => ViewId::File,
+            ViewId::File => ViewId::Panels,
+            ViewId::Panels => ViewId::Settings,
             ViewId::Settings => ViewId::Dashboard,
         }
     }
 
+    pub fn prev(self) -> ViewId {
+        let all = ViewId::all();
+        let i = all.iter().position(|v| *v == self).unwrap_or(0);
+        all[(i + all.len() - 1) % all.len()]
+    }
+
     pub fn title(self) -> &'static str {
-        match self {
-            ViewId::Dashboard => "Mission Control",
-            ViewId::Agents => "Agents",
-            ViewId::Models => "Models",
-            ViewId::Tasks => "Tasks",
-            ViewId::Settings => "Settings",
-        }
+        t(match self {
+            ViewId::Dashboard => "view.dashboard",
+            ViewId::Chat => "view.chat",
+            ViewId::Agents => "view.agents",
+            ViewId::Models => "view.models",
+            ViewId::Skills => "view.skills",
+            ViewId::Tasks => "view.tasks",
+            ViewId::Memory => "view.memory",
+            ViewId::Review => "view.review",
+            ViewId::Compare => "view.compare",
+            ViewId::File => "view.file",
+            ViewId::Panels => "view.panels",
+            ViewId::Settings => "view.settings",
+        })
     }
 
     pub fn name(self) -> &'static str {
         match self {
             ViewId::Dashboard => "dashboard",
+            ViewId::Chat => "chat",
             ViewId::Agents => "agents",
             ViewId::Models => "models",
+            ViewId::Skills => "skills",
             ViewId::Tasks => "tasks",
+            ViewId::Memory => "memory",
+            ViewId::Review => "review",
+            ViewId::Compare => "compare",
+            ViewId::File => "file",
+            ViewId::Panels => "panels",
             ViewId::Settings => "settings",
         }
     }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        ViewId::all().into_iter().find(|v| v.name() == name)
+    }
 }
@@ -1,28 +1,36 @@
+use crate::llm::remote::{Catalog, RemoteEndpoint};
 use crate::tui::theme::AppTheme;
-use ratatui::{backend::Backend, layout::{Constraint, Direction, Layout, Rect}, style::Style, widgets::{Block, Borders, List, ListItem, Paragraph, Wrap}, Frame};
+use ratatui::{backend::Backend, layout::{Constraint, Direction, Layout, Rect}, style::{Modifier, Style}, widgets::{Block, Borders, List, ListItem, Paragraph, Wrap}, Frame};
+use std::collections::BTreeMap;
 
 #[derive(Default)]
 pub struct ModelsView {
     providers: Vec<String>,
     models: Vec<String>,
+    /// Highlighted entry of the model catalog.
+    pub selected: usize,
+    /// Whether a catalog arrived yet; discovery runs after the first frame.
+    loaded: bool,
 }
 
 impl ModelsView {
-    pub fn tick(&mut self) {
-        if self.providers.is_empty() {
-            self.providers = vec![
-                "llama.cpp • localhost".into(),
-                "Ollama • local".into(),
-                "LM Studio • local".into(),
-            ];
-        }
-        if self.models.is_empty() {
-            self.models = vec![
-                "local.gguf • offline".into(),
-                "mixtral-8x7b • remote".into(),
-                "codellama-13b • staged".into(),
-            ];
+    /// Show `catalog`, with the default endpoint and each configured remote
+    /// as providers.
+    pub fn set_catalog(&mut self, endpoint: &str, remotes: &BTreeMap<String, RemoteEndpoint>, catalog: &Catalog) {
+        self.providers = vec![format!("default • {}", endpoint)];
+        for (name, remote) in remotes {
+            match catalog.failed.iter().find(|(n, _)| n == name) {
+                Some((_, e)) => self.providers.push(format!("{} • {} • unreachable: {}", name, remote.url, e)),
+                None => self.providers.push(format!("{} • {}", name, remote.url)),
+            }
         }
+        self.models = catalog.models.iter().map(|(model, source)| format!("{} • {}", model, source)).collect();
+        self.loaded = true;
+        self.select_by(0);
+    }
+
+    pub fn select_by(&mut self, delta: isize) {
+        self.selected = self.selected.saturating_add_signed(delta).min(self.models.len().saturating_sub(1));
     }
 
     pub fn render<B: Backend>(&self, f: &mut Frame<B>, area: Rect, theme: &AppTheme) {
@@ -41,13 +49,23 @@ impl ModelsView {
             .block(Block::default().borders(Borders::ALL).title("Providers"));
         f.render_widget(provider_list, top[0]);
 
-        let summary = Paragraph::new("Configure endpoints, credentials, and connection health checks.")
+        let summary = Paragraph::new("Add OpenAI-compatible servers under [remotes.<name>] in config.toml and pick their models with /model remote:<name>/<model>.")
             .block(Block::default().borders(Borders::ALL).title("Connection"))
             .style(Style::default().fg(theme.text))
             .wrap(Wrap { trim: true });
         f.render_widget(summary, top[1]);
 
-        let models: Vec<ListItem> = self.models.iter().map(|m| ListItem::new(m.clone())).collect();
+        let placeholder = (!self.loaded).then(|| ListItem::new("discovering models…").style(Style::default().fg(theme.muted_text)));
+        let models: Vec<ListItem> = self
+            .models
+            .iter()
+            .enumerate()
+            .map(|(i, m)| {
+                let style = if i == self.selected { Style::default().fg(theme.accent).add_modifier(Modifier::BOLD) } else { Style::default() };
+                ListItem::new(m.clone()).style(style)
+            })
+            .chain(placeholder)
+            .collect();
         let model_list = List::new(models)
             .block(Block::default().borders(Borders::ALL).title("Model Catalog"));
         f.render_widget(model_list, layout[1]);
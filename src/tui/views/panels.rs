@@ -0,0 +1,307 @@
+//! Panels added without touching the core. A `[[panels]]` entry in
+//! config.toml runs a shell command; a skill whose manifest has a `[panel]`
+//! table is called with no input. Whatever text comes back (plain or light
+//! markdown) fills its own tab here, refreshed every `interval_secs` while
+//! the view is open. A refresh runs off the UI thread and gives up after
+//! [`REFRESH_TIMEOUT`].
+
+use crate::agent::plugin_engine::PluginEngine;
+use crate::tui::theme::AppTheme;
+use anyhow::{bail, Result};
+use parking_lot::RwLock;
+use ratatui::{backend::Backend, layout::{Constraint, Direction, Layout, Rect}, style::{Modifier, Style}, text::{Span, Spans}, widgets::{Block, Borders, Paragraph, Tabs, Wrap}, Frame};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How long a command or skill gets to produce a panel's content.
+pub const REFRESH_TIMEOUT: Duration = Duration::from_secs(30);
+
+fn default_interval() -> u64 {
+    30
+}
+
+/// `[[panels]]` in config.toml:
+///
+/// ```toml
+/// [[panels]]
+/// name = "CI"
+/// command = "gh run list --limit 5"
+/// interval_secs = 60
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PanelConfig {
+    pub name: String,
+    pub command: String,
+    #[serde(default = "default_interval")]
+    pub interval_secs: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    /// Shell command; its stdout is the content.
+    Command(String),
+    /// Skill called with no input; its reply is the content.
+    Skill(String),
+}
+
+pub struct Panel {
+    /// The skill, or the config entry's name and command; titles may repeat.
+    pub id: String,
+    pub title: String,
+    pub source: Source,
+    pub interval: Duration,
+    pub content: String,
+    pub error: Option<String>,
+    refreshed: Option<Instant>,
+    pending: bool,
+}
+
+impl Panel {
+    pub fn new(title: impl Into<String>, source: Source, interval_secs: u64) -> Self {
+        let title = title.into();
+        let id = match &source {
+            Source::Command(command) => format!("command:{}:{}", title, command),
+            Source::Skill(name) => format!("skill:{}", name),
+        };
+        Self { id, title, source, interval: Duration::from_secs(interval_secs.max(1)), content: String::new(), error: None, refreshed: None, pending: false }
+    }
+}
+
+/// One tab per panel; Left/Right or `/panel <name>` switch between them.
+#[derive(Default)]
+pub struct PanelsView {
+    pub panels: Vec<Panel>,
+    pub selected: usize,
+    /// Cleared when config or skills change so the panel list is rebuilt.
+    pub synced: bool,
+}
+
+impl PanelsView {
+    /// Replace the panel list, keeping content of panels that are still there.
+    pub fn sync(&mut self, panels: Vec<Panel>) {
+        let current = self.panels.get(self.selected).map(|p| p.id.clone());
+        let mut old = std::mem::take(&mut self.panels);
+        self.panels = panels
+            .into_iter()
+            .map(|mut p| {
+                if let Some(i) = old.iter().position(|o| o.id == p.id) {
+                    let o = old.swap_remove(i);
+                    (p.content, p.error, p.refreshed, p.pending) = (o.content, o.error, o.refreshed, o.pending);
+                }
+                p
+            })
+            .collect();
+        self.selected = current.and_then(|id| self.panels.iter().position(|p| p.id == id)).unwrap_or(0);
+        self.synced = true;
+    }
+
+    pub fn select_by(&mut self, delta: isize) {
+        if !self.panels.is_empty() {
+            self.selected = (self.selected as isize + delta).rem_euclid(self.panels.len() as isize) as usize;
+        }
+    }
+
+    /// Select the panel titled `name` (case-insensitive).
+    pub fn select(&mut self, name: &str) -> bool {
+        match self.panels.iter().position(|p| p.title.eq_ignore_ascii_case(name)) {
+            Some(i) => {
+                self.selected = i;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Make the selected panel refresh on the next tick.
+    pub fn expire(&mut self) {
+        if let Some(p) = self.panels.get_mut(self.selected) {
+            p.refreshed = None;
+        }
+    }
+
+    /// Ids and sources of the panels whose content is older than their
+    /// interval, marked as in flight.
+    pub fn due(&mut self) -> Vec<(String, Source)> {
+        let now = Instant::now();
+        self.panels
+            .iter_mut()
+            .filter(|p| !p.pending && p.refreshed.is_none_or(|at| now.duration_since(at) >= p.interval))
+            .map(|p| {
+                p.pending = true;
+                (p.id.clone(), p.source.clone())
+            })
+            .collect()
+    }
+
+    pub fn update(&mut self, id: &str, result: Result<String, String>) {
+        let Some(p) = self.panels.iter_mut().find(|p| p.id == id) else { return };
+        p.pending = false;
+        p.refreshed = Some(Instant::now());
+        match result {
+            Ok(text) => {
+                p.content = text;
+                p.error = None;
+            }
+            // keep the last good content under the error
+            Err(e) => p.error = Some(e),
+        }
+    }
+
+    pub fn render<B: Backend>(&self, f: &mut Frame<B>, area: Rect, theme: &AppTheme) {
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(5)])
+            .split(area);
+
+        let titles: Vec<Spans> = self.panels.iter().map(|p| Spans::from(p.title.clone())).collect();
+        let tabs = Tabs::new(titles)
+            .select(self.selected)
+            .block(Block::default().borders(Borders::ALL).title("Panels"))
+            .highlight_style(Style::default().fg(theme.accent).add_modifier(Modifier::BOLD));
+        f.render_widget(tabs, layout[0]);
+
+        let Some(panel) = self.panels.get(self.selected) else {
+            let help = "No panels. Add [[panels]] (name, command, interval_secs) to config.toml,\nor a [panel] table to a skill's manifest.";
+            f.render_widget(Paragraph::new(help).style(Style::default().fg(theme.muted_text)).block(Block::default().borders(Borders::ALL)), layout[1]);
+            return;
+        };
+        let mut lines = vec![];
+        if let Some(e) = &panel.error {
+            lines.push(Spans::from(Span::styled(format!("refresh failed: {}", e), Style::default().fg(theme.accent))));
+        }
+        if panel.content.is_empty() && panel.error.is_none() {
+            lines.push(Spans::from(Span::styled("loading…", Style::default().fg(theme.muted_text))));
+        }
+        lines.extend(markdown(&panel.content, theme));
+        let source = match &panel.source {
+            Source::Command(c) => format!("{} — every {}s", c, panel.interval.as_secs()),
+            Source::Skill(s) => format!("skill {} — every {}s", s, panel.interval.as_secs()),
+        };
+        let body = Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(source)).wrap(Wrap { trim: false });
+        f.render_widget(body, layout[1]);
+    }
+}
+
+/// Headings, bullets, code blocks and `**bold**`; everything else is plain.
+pub fn markdown(text: &str, theme: &AppTheme) -> Vec<Spans<'static>> {
+    let mut lines = vec![];
+    let mut in_code = false;
+    for line in text.lines() {
+        if line.trim_start().starts_with("```") {
+            in_code = !in_code;
+            continue;
+        }
+        if in_code {
+            lines.push(Spans::from(Span::styled(format!("  {}", line), Style::default().fg(theme.muted_text))));
+            continue;
+        }
+        let heading = line.trim_start_matches('#');
+        if heading.len() < line.len() && heading.starts_with(' ') {
+            lines.push(Spans::from(Span::styled(heading.trim().to_string(), Style::default().fg(theme.accent).add_modifier(Modifier::BOLD))));
+            continue;
+        }
+        let indent = line.len() - line.trim_start().len();
+        let (prefix, rest) = match line.trim_start().strip_prefix("- ").or_else(|| line.trim_start().strip_prefix("* ")) {
+            Some(item) => (format!("{}• ", " ".repeat(indent)), item),
+            None => (String::new(), line),
+        };
+        let mut spans = vec![Span::raw(prefix)];
+        for (i, part) in rest.split("**").enumerate() {
+            if i % 2 == 1 {
+                spans.push(Span::styled(part.to_string(), Style::default().add_modifier(Modifier::BOLD)));
+            } else if !part.is_empty() {
+                spans.push(Span::raw(part.to_string()));
+            }
+        }
+        lines.push(Spans::from(spans));
+    }
+    lines
+}
+
+/// Run a panel command through the platform shell and return its stdout.
+/// The command is killed if the future is dropped.
+pub async fn run_command(command: &str) -> Result<String> {
+    let mut cmd = if cfg!(windows) {
+        let mut cmd = tokio::process::Command::new("cmd");
+        cmd.args(["/C", command]);
+        cmd
+    } else {
+        let mut cmd = tokio::process::Command::new("sh");
+        cmd.args(["-c", command]);
+        cmd
+    };
+    let output = cmd.kill_on_drop(true).output().await?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!("{} ({})", stderr.trim().lines().last().unwrap_or("no output"), output.status);
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Fresh content for a panel from `source`, or why there is none. Skills
+/// run on the blocking pool; past `limit` the refresh gives up (a command is
+/// killed, a skill call is left to finish on its own).
+pub async fn refresh(source: Source, skills: Option<Arc<RwLock<PluginEngine>>>, limit: Duration) -> Result<String, String> {
+    let work = async {
+        match source {
+            Source::Command(command) => run_command(&command).await.map_err(|e| e.to_string()),
+            Source::Skill(name) => {
+                let engine = skills.ok_or("skills not loaded")?;
+                let called = tokio::task::spawn_blocking(move || engine.read().call_skill(&name, None));
+                called.await.map_err(|e| e.to_string())?.map(skill_text).map_err(|e| e.to_string())
+            }
+        }
+    };
+    tokio::time::timeout(limit, work).await.unwrap_or_else(|_| Err(format!("no answer within {:?}", limit)))
+}
+
+/// Skill replies are JSON; a JSON string is unwrapped to its text.
+pub fn skill_text(reply: String) -> String {
+    match serde_json::from_str::<serde_json::Value>(&reply) {
+        Ok(serde_json::Value::String(s)) => s,
+        _ => reply,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_panels_refresh_and_sync() {
+        let mut view = PanelsView::default();
+        view.sync(vec![Panel::new("CI", Source::Command("echo ok".into()), 60), Panel::new("todo", Source::Skill("todo".into()), 5)]);
+        let due = view.due();
+        assert_eq!(due.len(), 2);
+        assert!(view.due().is_empty(), "in-flight panels are not refreshed twice");
+
+        let ci = "command:CI:echo ok";
+        view.update(ci, run_command("echo '# Build' && echo '- **main** passing'").await.map_err(|e| e.to_string()));
+        view.update("skill:todo", Err("trap".into()));
+        assert!(view.panels[0].content.contains("Build"));
+        assert!(view.due().is_empty());
+        view.expire();
+        assert_eq!(view.due(), vec![(ci.to_string(), Source::Command("echo ok".into()))]);
+
+        assert!(view.select("TODO"));
+        view.sync(vec![Panel::new("todo", Source::Skill("todo".into()), 5)]);
+        assert_eq!((view.selected, view.panels[0].error.as_deref()), (0, Some("trap")));
+        assert!(run_command("exit 3").await.is_err());
+
+        // two skills may share a title
+        view.sync(vec![Panel::new("todo", Source::Skill("a".into()), 5), Panel::new("todo", Source::Skill("b".into()), 5)]);
+        view.update("skill:b", Ok("from b".into()));
+        assert_eq!((view.panels[0].content.as_str(), view.panels[1].content.as_str()), ("", "from b"));
+
+        let slow = refresh(Source::Command("sleep 5".into()), None, Duration::from_millis(100)).await;
+        assert_eq!(slow, Err("no answer within 100ms".to_string()));
+        assert_eq!(refresh(Source::Skill("todo".into()), None, REFRESH_TIMEOUT).await, Err("skills not loaded".to_string()));
+        assert_eq!(skill_text("\"- a\"".into()), "- a");
+
+        let theme = crate::tui::theme::ThemeCatalog.resolve(&Default::default());
+        let lines = markdown("# Build\n- **main** passing\n```\nlog\n```", &theme);
+        let text: Vec<String> = lines.iter().map(|l| l.0.iter().map(|s| s.content.as_ref()).collect()).collect();
+        assert_eq!(text, ["Build", "• main passing", "  log"]);
+    }
+}
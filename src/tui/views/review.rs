@@ -0,0 +1,60 @@
+use crate::agent::review::{Finding, Severity};
+use crate::tui::theme::AppTheme;
+use ratatui::{backend::Backend, layout::Rect, style::{Color, Modifier, Style}, text::{Span, Spans}, widgets::{Block, Borders, List, ListItem, ListState}, Frame};
+
+/// Findings of the latest `/review`, most severe first.
+#[derive(Default)]
+pub struct ReviewView {
+    pub findings: Vec<Finding>,
+    pub selected: usize,
+    /// What the view is waiting for or why the review failed, shown in the title.
+    pub status: String,
+}
+
+impl ReviewView {
+    pub fn select_by(&mut self, delta: isize) {
+        self.selected = self.selected.saturating_add_signed(delta).min(self.findings.len().saturating_sub(1));
+    }
+
+    pub fn selected(&self) -> Option<&Finding> {
+        self.findings.get(self.selected)
+    }
+
+    pub fn render<B: Backend>(&self, f: &mut Frame<B>, area: Rect, theme: &AppTheme) {
+        let items: Vec<ListItem> = if self.findings.is_empty() {
+            vec![ListItem::new("No findings. `/review [base]` reviews this branch's changes.")]
+        } else {
+            self.findings
+                .iter()
+                .map(|finding| {
+                    let color = match finding.severity {
+                        Severity::Critical => Color::Red,
+                        Severity::Major => Color::Yellow,
+                        Severity::Minor => theme.text,
+                        Severity::Nit => theme.muted_text,
+                    };
+                    let mut lines = vec![Spans::from(vec![
+                        Span::styled(format!("{:<9}", finding.severity.label()), Style::default().fg(color).add_modifier(Modifier::BOLD)),
+                        Span::styled(format!("{}  ", finding.location()), Style::default().fg(theme.accent)),
+                        Span::raw(finding.message.clone()),
+                    ])];
+                    if let Some(s) = &finding.suggestion {
+                        lines.push(Spans::from(Span::styled(format!("         → {}", s), Style::default().fg(theme.muted_text))));
+                    }
+                    ListItem::new(lines)
+                })
+                .collect()
+        };
+        let mut title = format!("Review • {} finding(s)", self.findings.len());
+        if !self.status.is_empty() {
+            title = format!("{} • {}", title, self.status);
+        }
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        // the state keeps the selection scrolled into view
+        let mut state = ListState::default();
+        state.select((!self.findings.is_empty()).then_some(self.selected));
+        f.render_stateful_widget(list, area, &mut state);
+    }
+}
@@ -1,15 +1,32 @@
 use crate::config::{RuntimeConfig, ThemeName};
+use crate::llm::params::{self, InferenceParams};
+use crate::tui::charts;
 use crate::tui::theme::AppTheme;
+use crate::workspace::ProjectProfile;
 use ratatui::{backend::Backend, layout::{Constraint, Direction, Layout, Rect}, style::{Modifier, Style}, text::{Span, Spans}, widgets::{Block, Borders, Paragraph, Wrap}, Frame};
 
 #[derive(Default)]
-pub struct SettingsView;
+pub struct SettingsView {
+    /// Row of the Model panel that Left/Right adjusts.
+    pub selected: usize,
+}
 
 impl SettingsView {
-    pub fn render<B: Backend>(&self, f: &mut Frame<B>, area: Rect, theme: &AppTheme, config: &RuntimeConfig) {
+    pub fn select_by(&mut self, delta: i32) {
+        self.selected = (self.selected as i32 + delta).clamp(0, params::RANGES.len() as i32 - 1) as usize;
+    }
+
+    /// Name of the selected inference parameter.
+    pub fn selected_param(&self) -> &'static str {
+        params::RANGES[self.selected].0
+    }
+
+    pub fn render<B: Backend>(&self, f: &mut Frame<B>, area: Rect, theme: &AppTheme, config: &RuntimeConfig, profile: &ProjectProfile, params: &InferenceParams) {
         let layout = Layout::default()
             .direction(Direction::Vertical)
             .constraints([
+                Constraint::Length(6),
+                Constraint::Length(6),
                 Constraint::Length(6),
                 Constraint::Length(6),
                 Constraint::Min(6),
@@ -19,11 +36,24 @@ impl SettingsView {
         let endpoints = Paragraph::new(vec![
             Spans::from(vec![Span::styled("LLM Endpoint: ", Style::default().add_modifier(Modifier::BOLD)), Span::raw(&config.llm_endpoint)]),
             Spans::from(vec![Span::styled("Default Model: ", Style::default().add_modifier(Modifier::BOLD)), Span::raw(&config.llm_model)]),
+            Spans::from(vec![
+                Span::styled("API Key: ", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(match crate::secrets::peek(crate::secrets::LLM_API_KEY) {
+                    None => "checking…".to_string(),
+                    Some(None) => "not set (agent secrets set llm)".to_string(),
+                    Some(Some(key)) => key.to_string(),
+                }),
+            ]),
             Spans::from(vec![Span::styled("Model Dir: ", Style::default().add_modifier(Modifier::BOLD)), Span::raw(config.model_dir.display().to_string())]),
+            Spans::from(vec![
+                Span::styled("Autocomplete: ", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(if config.ui.autocomplete { "on (/autocomplete off)" } else { "off (/autocomplete on)" }),
+            ]),
         ])
         .block(Block::default().borders(Borders::ALL).title("Runtime"))
         .style(Style::default().fg(theme.text));
         f.render_widget(endpoints, layout[0]);
+        self.render_model(f, layout[1], theme, params);
 
         let theme_info = Paragraph::new(vec![
             Spans::from(vec![Span::styled("Active Theme: ", Style::default().add_modifier(Modifier::BOLD)), Span::raw(theme.name)]),
@@ -34,14 +64,72 @@ impl SettingsView {
         .block(Block::default().borders(Borders::ALL).title("Appearance"))
         .style(Style::default().fg(theme.text))
         .wrap(Wrap { trim: true });
-        f.render_widget(theme_info, layout[1]);
+        f.render_widget(theme_info, layout[2]);
 
-        let tips = Paragraph::new("Settings view is the control center for providers, storage, and governance policies. Configure global prompts, memory backends, and workspace sync here.")
-            .block(Block::default().borders(Borders::ALL).title("Governance"))
-            .style(Style::default().fg(theme.muted_text))
+        let none = "-".to_string();
+        let project = Paragraph::new(vec![
+            Spans::from(vec![Span::styled("Kind: ", Style::default().add_modifier(Modifier::BOLD)), Span::raw(format!("{:?}", profile.kind))]),
+            Spans::from(vec![Span::styled("Build: ", Style::default().add_modifier(Modifier::BOLD)), Span::raw(profile.build_command.as_ref().unwrap_or(&none))]),
+            Spans::from(vec![Span::styled("Test: ", Style::default().add_modifier(Modifier::BOLD)), Span::raw(profile.test_command.as_ref().unwrap_or(&none))]),
+        ])
+        .block(Block::default().borders(Borders::ALL).title("Project (.supercode.toml)"))
+        .style(Style::default().fg(theme.text));
+        f.render_widget(project, layout[3]);
+
+        let policy = Paragraph::new(governance(config, profile))
+            .block(Block::default().borders(Borders::ALL).title("Governance (read-only: [tools]/[files] and .supercode.toml [policy])"))
+            .style(Style::default().fg(theme.text))
             .wrap(Wrap { trim: true });
-        f.render_widget(tips, layout[2]);
+        f.render_widget(policy, layout[4]);
     }
+
+    /// One slider per inference parameter; unset ones show the default dimmed.
+    fn render_model<B: Backend>(&self, f: &mut Frame<B>, area: Rect, theme: &AppTheme, params: &InferenceParams) {
+        const WIDTH: usize = 20;
+        let rows: Vec<Spans> = params::RANGES
+            .iter()
+            .enumerate()
+            .map(|(i, (name, min, max, _))| {
+                let value = params.get(name);
+                let shown = value.unwrap_or_else(|| params::default_value(name));
+                let ratio = f64::from((shown - min) / (max - min));
+                let marker = if i == self.selected { "▶ " } else { "  " };
+                let label = match value {
+                    Some(v) => params::display(v),
+                    None => format!("{} (default)", params::display(shown)),
+                };
+                // unset parameters are dimmed throughout
+                let fill = if value.is_some() { theme.accent } else { theme.muted_text };
+                let mut spans = vec![Span::raw(format!("{}{:<15}", marker, name))];
+                spans.extend(charts::bar_in(ratio, WIDTH, fill, theme.muted_text));
+                spans.push(Span::raw(format!(" {}", label)));
+                Spans::from(spans)
+            })
+            .collect();
+        let model = Paragraph::new(rows)
+            .block(Block::default().borders(Borders::ALL).title("Model (↑/↓ select, ←/→ adjust, /params reset)"))
+            .style(Style::default().fg(theme.text));
+        f.render_widget(model, area);
+    }
+}
+
+/// The user's and the project's limits side by side; a run has to pass both.
+fn governance<'a>(config: &RuntimeConfig, profile: &ProjectProfile) -> Vec<Spans<'a>> {
+    let list = |items: &[String]| if items.is_empty() { "-".to_string() } else { items.join(", ") };
+    let row = |label: &'static str, user: &[String], project: &[String], empty: &str| {
+        let value = if user.is_empty() && project.is_empty() {
+            empty.to_string()
+        } else {
+            format!("config: {}  |  project: {}", list(user), list(project))
+        };
+        Spans::from(vec![Span::styled(label, Style::default().add_modifier(Modifier::BOLD)), Span::raw(value)])
+    };
+    vec![
+        row("Disabled tools: ", &config.tools.disabled, &profile.policy.disabled(), "none"),
+        row("Commands: ", &config.tools.commands, &profile.policy.commands, "any"),
+        row("Writable: ", &config.files.writable, &profile.policy.writable, "any path agents may touch"),
+        Spans::from(vec![Span::styled("Denied: ", Style::default().add_modifier(Modifier::BOLD)), Span::raw(list(&config.files.deny))]),
+    ]
 }
 
 impl SettingsView {
@@ -0,0 +1,49 @@
+use crate::agent::marketplace::Listing;
+use crate::tui::theme::AppTheme;
+use ratatui::{backend::Backend, layout::{Constraint, Direction, Layout, Rect}, style::Style, widgets::{Block, Borders, List, ListItem, Paragraph, Wrap}, Frame};
+
+/// Marketplace listings, refreshed with `/skills search`.
+#[derive(Default)]
+pub struct SkillsView {
+    pub listings: Vec<Listing>,
+    pub query: String,
+    /// Index location, or what went wrong fetching it.
+    pub status: String,
+    /// Set once the index has been requested, so opening the view fetches it once.
+    pub requested: bool,
+}
+
+impl SkillsView {
+    pub fn render<B: Backend>(&self, f: &mut Frame<B>, area: Rect, theme: &AppTheme) {
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(4), Constraint::Min(5)])
+            .split(area);
+
+        let intro = Paragraph::new(format!("{}\n/skills search [term] | install <name> | reload", self.status))
+            .block(Block::default().borders(Borders::ALL).title("Skill Index"))
+            .style(Style::default().fg(theme.muted_text))
+            .wrap(Wrap { trim: true });
+        f.render_widget(intro, layout[0]);
+
+        let mut rows = vec![ListItem::new(format!("{:<20} {:<10} {:<10} {}", "name", "available", "installed", "status")).style(Style::default().fg(theme.accent))];
+        rows.extend(self.listings.iter().map(|l| {
+            let line = format!(
+                "{:<20} {:<10} {:<10} {} — {}",
+                l.entry.name,
+                l.entry.version,
+                l.installed.as_deref().unwrap_or("-"),
+                l.status(),
+                l.entry.description
+            );
+            let style = match l.status() {
+                "update available" => Style::default().fg(theme.accent),
+                "installed" => Style::default().fg(theme.text),
+                _ => Style::default().fg(theme.muted_text),
+            };
+            ListItem::new(line).style(style)
+        }));
+        let title = if self.query.is_empty() { "Skills".to_string() } else { format!("Skills matching {:?}", self.query) };
+        f.render_widget(List::new(rows).block(Block::default().borders(Borders::ALL).title(title)), layout[1]);
+    }
+}
@@ -1,31 +1,144 @@
+use crate::tasks::board::{self, Column};
+use crate::tasks::retry::FailureCause;
+use crate::tasks::timing::{self, TimingStore};
+use crate::tasks::{artifacts, rollup, Task, TaskStatus, TaskStore};
+use crate::tui::charts;
 use crate::tui::theme::AppTheme;
-use ratatui::{backend::Backend, layout::{Constraint, Direction, Layout, Rect}, style::{Modifier, Style}, widgets::{Block, Borders, List, ListItem, Paragraph, Wrap}, Frame};
+use ratatui::{backend::Backend, layout::{Constraint, Direction, Layout, Rect}, style::{Modifier, Style}, text::{Span, Spans}, widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap}, Frame};
+use std::time::{Duration, Instant};
+
+/// How often the view re-reads the task store.
+const REFRESH: Duration = Duration::from_secs(2);
+
+/// Cells of each task's progress bar.
+const BAR_WIDTH: usize = 10;
+
+/// A task's line: the indented id, its progress, then status and details.
+struct Row {
+    id: String,
+    progress: f64,
+    detail: String,
+}
+
+/// A task on the board, with why it sits in its column when that is not
+/// its status.
+#[derive(Clone)]
+struct Card {
+    task: Task,
+    progress: f64,
+    note: String,
+}
 
 #[derive(Default)]
 pub struct TasksView {
     queues: Vec<String>,
-    active: Vec<String>,
+    active: Vec<Row>,
+    /// Finished tasks out of all recorded ones.
+    done: (usize, usize),
+    refreshed: Option<Instant>,
+    /// Show the kanban board instead of the list.
+    pub board: bool,
+    /// One list per `Column::ALL` entry.
+    cards: Vec<Vec<Card>>,
+    /// Selected column and the card selected in it.
+    column: usize,
+    selected: usize,
+    /// Card to select after the next refresh, once it has moved.
+    follow: Option<String>,
 }
 
 impl TasksView {
-    pub fn tick(&mut self) {
-        if self.queues.is_empty() {
-            self.queues = vec![
-                "Backlog • 12".into(),
-                "Today • 4".into(),
-                "Waiting • 2".into(),
-            ];
+    /// Re-read the tasks when they are due; true when they were.
+    pub fn tick(&mut self, store: &TaskStore) -> bool {
+        if self.refreshed.map(|t| t.elapsed() < REFRESH).unwrap_or(false) {
+            return false;
+        }
+        self.refreshed = Some(Instant::now());
+        let tree = store.tree();
+        let all: Vec<Task> = tree.iter().map(|(_, t)| t.clone()).collect();
+        let count = |s: TaskStatus| all.iter().filter(|t| t.status == s).count();
+        self.done = (count(TaskStatus::Completed), all.len());
+        self.queues = vec![
+            format!("Running • {}", count(TaskStatus::Running)),
+            format!("Completed • {}", count(TaskStatus::Completed)),
+            format!("Failed • {}", count(TaskStatus::Failed)),
+        ];
+        // failures grouped by diagnosed cause
+        for cause in FailureCause::all() {
+            let n = all.iter().filter(|t| t.status == TaskStatus::Failed && t.failure_cause() == Some(cause)).count();
+            if n > 0 {
+                self.queues.push(format!("  {} • {}", cause.label(), n));
+            }
+        }
+        let (history, now) = (TimingStore::new(store.home()).load(), crate::tasks::now_secs());
+        let clock = |t: &Task| timing::describe(t, &all, &history, now);
+        self.active = tree.iter().take(100).map(|(depth, t)| Self::describe(store, *depth, t, &all, &clock(t))).collect();
+        self.cards = vec![vec![]; Column::ALL.len()];
+        for t in &all {
+            let note = match (t.status, board::blocker(t, &all)) {
+                (TaskStatus::Failed, _) => t.failure_cause().map(|c| format!("failed: {}", c.label())).unwrap_or_else(|| "failed".into()),
+                (TaskStatus::Pending, Some(b)) => format!("waits on {}", &b.id[..8]),
+                (TaskStatus::Cancelled, _) => "cancelled".into(),
+                _ => clock(t),
+            };
+            self.cards[board::column(t, &all).index()].push(Card { task: t.clone(), progress: rollup(t, &all), note });
+        }
+        if let Some(id) = self.follow.take() {
+            for (column, cards) in self.cards.iter().enumerate() {
+                if let Some(i) = cards.iter().position(|c| c.task.id == id) {
+                    (self.column, self.selected) = (column, i);
+                }
+            }
+        }
+        self.select_by(0);
+        true
+    }
+
+    /// Select the card of task `id` once the store has been re-read, which
+    /// happens on the next tick.
+    pub fn follow(&mut self, id: &str) {
+        self.follow = Some(id.to_string());
+        self.refreshed = None;
+    }
+
+    /// Move the board's selection to the next (`1`) or previous column.
+    pub fn select_column(&mut self, delta: isize) {
+        self.column = self.column.saturating_add_signed(delta).min(Column::ALL.len() - 1);
+        self.select_by(0);
+    }
+
+    pub fn select_by(&mut self, delta: isize) {
+        let len = self.cards.get(self.column).map_or(0, Vec::len);
+        self.selected = self.selected.saturating_add_signed(delta).min(len.saturating_sub(1));
+    }
+
+    pub fn selected_card(&self) -> Option<&Task> {
+        self.cards.get(self.column)?.get(self.selected).map(|c| &c.task)
+    }
+
+    fn describe(store: &TaskStore, depth: usize, task: &Task, all: &[Task], clock: &str) -> Row {
+        let progress = rollup(task, all);
+        let mut line = format!(" {:>3}% {:?}  {}", (progress * 100.0).round(), task.status, task.title);
+        if !clock.is_empty() {
+            line.push_str(&format!("  ({})", clock));
+        }
+        if task.attempts > 1 {
+            line.push_str(&format!("  (attempt {})", task.attempts));
         }
-        if self.active.is_empty() {
-            self.active = vec![
-                "Design agent workflow".into(),
-                "Implement vector memory".into(),
-                "Model registry sync".into(),
-            ];
+        if let (TaskStatus::Failed, Some(d)) = (task.status, task.failures.last()) {
+            line.push_str(&format!("  <{}: {}>", d.cause.label(), d.explanation));
         }
+        let files = artifacts::list(store.home(), &task.id);
+        if !files.is_empty() {
+            line.push_str(&format!("  [{} artifacts: {}]", files.len(), artifacts::dir(store.home(), &task.id).display()));
+        }
+        Row { id: format!("{}{} ", "  ".repeat(depth), &task.id[..8]), progress, detail: line }
     }
 
     pub fn render<B: Backend>(&self, f: &mut Frame<B>, area: Rect, theme: &AppTheme) {
+        if self.board {
+            return self.render_board(f, area, theme);
+        }
         let layout = Layout::default()
             .direction(Direction::Horizontal)
             .constraints([Constraint::Percentage(35), Constraint::Percentage(65)])
@@ -37,12 +150,29 @@ impl TasksView {
             .highlight_style(Style::default().fg(theme.accent).add_modifier(Modifier::BOLD));
         f.render_widget(queue_list, layout[0]);
 
-        let active: Vec<ListItem> = self.active.iter().map(|t| ListItem::new(t.clone())).collect();
+        let right = Layout::default().direction(Direction::Vertical).constraints([Constraint::Length(3), Constraint::Min(3)]).split(layout[1]);
+        let (done, total) = self.done;
+        let ratio = if total == 0 { 0.0 } else { done as f64 / total as f64 };
+        f.render_widget(charts::gauge(ratio, format!("{} of {} completed", done, total), "Progress".into(), theme), right[0]);
+
+        let active: Vec<ListItem> = if self.active.is_empty() {
+            vec![ListItem::new("No tasks yet. `agent run --goal ...` records one.")]
+        } else {
+            self.active
+                .iter()
+                .map(|row| {
+                    let mut spans = vec![Span::raw(row.id.clone())];
+                    spans.extend(charts::bar(row.progress, BAR_WIDTH, theme));
+                    spans.push(Span::raw(row.detail.clone()));
+                    ListItem::new(Spans::from(spans))
+                })
+                .collect()
+        };
         let list = List::new(active)
-            .block(Block::default().borders(Borders::ALL).title("Active Tasks"));
-        f.render_widget(list, layout[1]);
+            .block(Block::default().borders(Borders::ALL).title("Tasks"));
+        f.render_widget(list, right[1]);
 
-        let footer = Paragraph::new("Use this workspace to break down goals into tasks, assign to agents, and monitor progress.")
+        let footer = Paragraph::new("Artifacts: `agent tasks artifacts <id> [--open]`.")
             .style(Style::default().fg(theme.muted_text))
             .wrap(Wrap { trim: true });
         let footer_area = Rect {
@@ -53,4 +183,36 @@ impl TasksView {
         };
         f.render_widget(footer, footer_area);
     }
+
+    fn render_board<B: Backend>(&self, f: &mut Frame<B>, area: Rect, theme: &AppTheme) {
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Ratio(1, 4); 4])
+            .split(area);
+        for (i, column) in Column::ALL.iter().enumerate() {
+            let cards = self.cards.get(i).map(Vec::as_slice).unwrap_or_default();
+            let items: Vec<ListItem> = cards
+                .iter()
+                .map(|card| {
+                    let width = (columns[i].width as usize).saturating_sub(14).clamp(3, BAR_WIDTH);
+                    let mut status = vec![Span::raw("  ")];
+                    status.extend(charts::bar(card.progress, width, theme));
+                    status.push(Span::raw(format!(" {:>3}% ", (card.progress * 100.0).round())));
+                    status.push(Span::styled(card.note.clone(), Style::default().fg(theme.muted_text)));
+                    ListItem::new(vec![
+                        Spans::from(vec![Span::styled(format!("{} ", &card.task.id[..8]), Style::default().fg(theme.accent)), Span::raw(card.task.title.clone())]),
+                        Spans::from(status),
+                    ])
+                })
+                .collect();
+            let active = i == self.column;
+            let border = if active { Style::default().fg(theme.accent) } else { Style::default() };
+            let list = List::new(items)
+                .block(Block::default().borders(Borders::ALL).border_style(border).title(format!("{} • {}", column.label(), cards.len())))
+                .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+            let mut state = ListState::default();
+            state.select((active && !cards.is_empty()).then_some(self.selected));
+            f.render_stateful_widget(list, columns[i], &mut state);
+        }
+    }
 }
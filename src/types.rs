@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
 use std::time::{SystemTime, UNIX_EPOCH};
 use uuid::Uuid;
 
@@ -14,22 +15,167 @@ pub struct AgentOutput {
     pub text: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// One chat message, as the TUI shows it, conversations save it and
+/// exports write it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Message {
+    /// Stable across edits, compaction and reloads, so per-message state
+    /// follows the message rather than its position. Messages saved before
+    /// ids existed get one when loaded.
+    #[serde(default = "new_id")]
+    pub id: String,
     pub role: String,
+    /// The text part of the message.
     pub content: String,
     pub ts: u64,
+    /// Images sent with the text, for vision models.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub images: Vec<ImagePart>,
+    /// Files sent with the message, as they were when sent.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<Attachment>,
+    /// The reply was cut at the output token limit; `/continue` resumes it.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+    /// On a summary, the messages it replaced in the context.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub replaces: Vec<Message>,
+    /// Tools called while producing the message, in order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCallRecord>,
+    /// Estimated tokens of what was sent (with attachments) or received.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tokens: Option<u64>,
+}
+
+/// One tool call as the inspector shows it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolCallRecord {
+    pub tool: String,
+    pub input: String,
+    /// The output, or the error of a failed call.
+    pub output: String,
+    pub ok: bool,
 }
 
 impl Message {
     pub fn new(role: impl Into<String>, content: impl Into<String>) -> Self {
         Self {
+            id: new_id(),
             role: role.into(),
             content: content.into(),
             ts: SystemTime::now()
                 .duration_since(UNIX_EPOCH)
                 .unwrap_or_default()
                 .as_secs(),
+            images: vec![],
+            attachments: vec![],
+            truncated: false,
+            replaces: vec![],
+            tool_calls: vec![],
+            tokens: None,
+        }
+    }
+
+    /// Text sent to the model: the message followed by its attachments.
+    pub fn full_text(&self) -> String {
+        let mut out = self.content.clone();
+        for a in self.attachments.iter().filter(|a| !a.text.is_empty()) {
+            let what = match a.mode {
+                AttachMode::Summary => "Summary of attached file",
+                _ => "Attached file",
+            };
+            out.push_str(&format!("\n\n{} {}:\n```\n{}\n```", what, a.path.display(), a.text));
+        }
+        out
+    }
+
+    pub fn with_images(mut self, images: Vec<ImagePart>) -> Self {
+        self.images = images;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AttachMode {
+    /// Small text file, sent verbatim.
+    Embed,
+    /// Large text file, sent as a model-written summary.
+    Summary,
+    /// Sent as an image part.
+    Image,
+}
+
+/// A file attached to a chat message. `sha256` and `bytes` identify the
+/// exact version that was sent.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Attachment {
+    pub path: PathBuf,
+    pub bytes: u64,
+    pub sha256: String,
+    pub mode: AttachMode,
+    /// What the model saw: the file, its summary, or nothing for images.
+    #[serde(default)]
+    pub text: String,
+}
+
+/// An image attached to a message.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ImagePart {
+    /// Local file, read when the message is sent.
+    Path { path: PathBuf },
+    Base64 { media_type: String, data: String },
+    /// Remote image the provider fetches itself.
+    Url { url: String },
+}
+
+impl ImagePart {
+    pub fn is_image_path(path: &Path) -> bool {
+        Self::media_type(path).is_some()
+    }
+
+    fn media_type(path: &Path) -> Option<&'static str> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "png" => Some("image/png"),
+            "jpg" | "jpeg" => Some("image/jpeg"),
+            "gif" => Some("image/gif"),
+            "webp" => Some("image/webp"),
+            _ => None,
+        }
+    }
+
+    /// URL for an OpenAI-style `image_url` part; local files are inlined as
+    /// `data:` URLs.
+    pub fn to_url(&self) -> anyhow::Result<String> {
+        use base64::Engine;
+        match self {
+            ImagePart::Path { path } => {
+                let media_type = Self::media_type(path).ok_or_else(|| anyhow::anyhow!("{} is not a supported image", path.display()))?;
+                let data = std::fs::read(path).map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))?;
+                Ok(format!("data:{};base64,{}", media_type, base64::engine::general_purpose::STANDARD.encode(data)))
+            }
+            ImagePart::Base64 { media_type, data } => Ok(format!("data:{};base64,{}", media_type, data)),
+            ImagePart::Url { url } => Ok(url.clone()),
+        }
+    }
+
+    /// Inverse of [`ImagePart::to_url`]; local paths come back as base64.
+    pub fn from_url(url: &str) -> Self {
+        let inline = url.strip_prefix("data:").and_then(|rest| rest.split_once(";base64,"));
+        match inline {
+            Some((media_type, data)) => ImagePart::Base64 { media_type: media_type.into(), data: data.into() },
+            None => ImagePart::Url { url: url.into() },
+        }
+    }
+
+    /// Placeholder shown where the image cannot be drawn.
+    pub fn label(&self) -> String {
+        match self {
+            ImagePart::Path { path } => format!("[image: {}]", path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default()),
+            ImagePart::Base64 { media_type, data } => format!("[image: {}, {} KB]", media_type, data.len() * 3 / 4 / 1024),
+            ImagePart::Url { url } => format!("[image: {}]", url),
         }
     }
 }
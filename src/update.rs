@@ -0,0 +1,292 @@
+//! `agent self-update`: find the newest GitHub release on a channel, show
+//! its notes, download the binary for this platform, verify it and swap it
+//! in place of the running executable.
+//!
+//! A release carries one binary per platform named after [`asset_name`]
+//! plus `SHA256SUMS` (`<hex>  <file>` lines) and, when signed,
+//! `SHA256SUMS.sig`: a hex ed25519 signature of the sums file checked
+//! against the release key baked into the binary ([`RELEASE_KEY`]) and
+//! `update.trusted_keys`. Unsigned releases are refused unless
+//! `update.require_signature = false`.
+
+use crate::agent::marketplace::{parse_key, Verified};
+use anyhow::{anyhow, bail, Context, Result};
+use ed25519_dalek::Signature;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+pub const CURRENT: &str = env!("CARGO_PKG_VERSION");
+
+/// Tag this binary was released as, e.g. `nightly-20261015` or `v0.3.0`;
+/// release builds set `SUPERCODE_BUILD_TAG`, local builds have none.
+pub const BUILD_TAG: Option<&str> = option_env!("SUPERCODE_BUILD_TAG");
+
+/// Hex ed25519 key that signs releases, baked in by release builds from
+/// `SUPERCODE_RELEASE_KEY`. Always trusted, besides `update.trusted_keys`.
+pub const RELEASE_KEY: Option<&str> = option_env!("SUPERCODE_RELEASE_KEY");
+
+/// `[update]` in config.toml.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UpdateConfig {
+    /// GitHub releases API URL (or a JSON file in the same format).
+    pub releases_url: String,
+    /// Hex ed25519 keys that sign `SHA256SUMS`, on top of [`RELEASE_KEY`].
+    pub trusted_keys: Vec<String>,
+    /// Refuse releases without a valid signature from a trusted key. On by
+    /// default.
+    pub require_signature: bool,
+}
+
+impl Default for UpdateConfig {
+    fn default() -> Self {
+        Self {
+            releases_url: "https://api.github.com/repos/devkarsaz-create/supercode-ai/releases".into(),
+            trusted_keys: vec![],
+            require_signature: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Channel {
+    /// Tagged releases.
+    Stable,
+    /// Pre-releases built from main.
+    Nightly,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Release {
+    pub tag_name: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Release notes (markdown).
+    #[serde(default)]
+    pub body: Option<String>,
+    #[serde(default)]
+    pub prerelease: bool,
+    #[serde(default)]
+    pub draft: bool,
+    #[serde(default)]
+    pub assets: Vec<Asset>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Asset {
+    pub name: String,
+    pub browser_download_url: String,
+}
+
+impl Release {
+    fn asset(&self, name: &str) -> Option<&Asset> {
+        self.assets.iter().find(|a| a.name == name)
+    }
+}
+
+/// Binary name for this platform, e.g. `super-agent-x86_64-linux` or
+/// `super-agent-x86_64-windows.exe`.
+pub fn asset_name() -> String {
+    format!("super-agent-{}-{}{}", std::env::consts::ARCH, std::env::consts::OS, std::env::consts::EXE_SUFFIX)
+}
+
+/// `v1.2.3` / `1.2.3-rc.1` → (1, 2, 3); `None` for non-version tags.
+fn version(tag: &str) -> Option<(u64, u64, u64)> {
+    let core = tag.trim_start_matches('v').split(['-', '+']).next()?;
+    let mut parts = core.split('.').map(|p| p.parse::<u64>().ok());
+    Some((parts.next()??, parts.next()??, parts.next().flatten().unwrap_or(0)))
+}
+
+/// This binary's version, with its release tag when it has one.
+pub fn installed() -> String {
+    match BUILD_TAG {
+        Some(tag) => format!("{} ({})", CURRENT, tag),
+        None => CURRENT.to_string(),
+    }
+}
+
+/// The release to move to from `current`: the newest stable version above
+/// it, or the newest nightly unless `tag`, the installed build's release
+/// tag, is that nightly. A build without a tag is offered the newest
+/// nightly. Releases are listed newest first, as GitHub returns them.
+pub fn pick(releases: &[Release], channel: Channel, current: &str, tag: Option<&str>) -> Option<Release> {
+    let asset = asset_name();
+    let mut usable = releases.iter().filter(|r| !r.draft && r.asset(&asset).is_some());
+    match channel {
+        Channel::Stable => usable
+            .filter(|r| !r.prerelease)
+            .filter_map(|r| Some((version(&r.tag_name)?, r)))
+            .filter(|(v, _)| version(current).is_none_or(|c| *v > c))
+            .max_by_key(|(v, _)| *v)
+            .map(|(_, r)| r.clone()),
+        Channel::Nightly => usable.find(|r| r.prerelease).filter(|r| Some(r.tag_name.as_str()) != tag).cloned(),
+    }
+}
+
+async fn fetch(url: &str) -> Result<Vec<u8>> {
+    if url.starts_with("http://") || url.starts_with("https://") {
+        crate::net::check(url)?;
+        let resp = crate::net::client(crate::net::UPDATE)
+            .get(url)
+            // the GitHub API rejects requests without one
+            .header(reqwest::header::USER_AGENT, format!("super-agent/{}", CURRENT))
+            .header(reqwest::header::ACCEPT, "application/octet-stream, application/json")
+            .send()
+            .await?
+            .error_for_status()?;
+        return Ok(resp.bytes().await?.to_vec());
+    }
+    let path = url.strip_prefix("file://").unwrap_or(url);
+    tokio::fs::read(path).await.map_err(|e| anyhow!("{}: {}", path, e))
+}
+
+pub async fn releases(config: &UpdateConfig) -> Result<Vec<Release>> {
+    let bytes = fetch(&config.releases_url).await?;
+    serde_json::from_slice(&bytes).with_context(|| format!("bad release list from {}", config.releases_url))
+}
+
+/// Download this platform's binary from `release` and check it against
+/// `SHA256SUMS` and its signature.
+pub async fn download(config: &UpdateConfig, release: &Release) -> Result<(Vec<u8>, Verified)> {
+    let name = asset_name();
+    let asset = release.asset(&name).ok_or_else(|| anyhow!("{} has no {}", release.tag_name, name))?;
+    let sums_asset = release.asset("SHA256SUMS").ok_or_else(|| anyhow!("{} has no SHA256SUMS", release.tag_name))?;
+    let sums = fetch(&sums_asset.browser_download_url).await?;
+    let signature = match release.asset("SHA256SUMS.sig") {
+        Some(a) => Some(String::from_utf8(fetch(&a.browser_download_url).await?)?),
+        None => None,
+    };
+    let verified = verify_sums(config, &sums, signature.as_deref())?;
+    let expected = String::from_utf8_lossy(&sums)
+        .lines()
+        .find_map(|l| {
+            let (hash, file) = l.split_once(char::is_whitespace)?;
+            (file.trim().trim_start_matches('*') == name).then(|| hash.to_string())
+        })
+        .ok_or_else(|| anyhow!("SHA256SUMS has no entry for {}", name))?;
+    let bytes = fetch(&asset.browser_download_url).await?;
+    let digest = hex::encode(Sha256::digest(&bytes));
+    if !digest.eq_ignore_ascii_case(&expected) {
+        bail!("checksum mismatch for {}: expected {}, got {}", name, expected, digest);
+    }
+    Ok((bytes, verified))
+}
+
+fn verify_sums(config: &UpdateConfig, sums: &[u8], signature: Option<&str>) -> Result<Verified> {
+    let keys: Vec<&str> = RELEASE_KEY.into_iter().chain(config.trusted_keys.iter().map(String::as_str)).collect();
+    if let Some(sig) = signature {
+        let sig = Signature::from_slice(&hex::decode(sig.trim())?).map_err(|e| anyhow!("bad SHA256SUMS signature: {}", e))?;
+        for key in &keys {
+            if parse_key(key)?.verify_strict(sums, &sig).is_ok() {
+                return Ok(Verified::Signed(key.chars().take(12).collect()));
+            }
+        }
+        if !keys.is_empty() {
+            bail!("SHA256SUMS signature does not match any trusted key");
+        }
+    }
+    if config.require_signature {
+        bail!("release is not signed by a trusted key (set update.require_signature = false to accept checksums alone)");
+    }
+    Ok(Verified::Checksum)
+}
+
+fn old_path(exe: &Path) -> PathBuf {
+    exe.with_extension("old")
+}
+
+/// Replace `exe` with `bytes`. The new binary is written next to it and
+/// renamed over it, so a failure leaves the old one in place. Windows
+/// can't replace a running executable, so there the old one is moved
+/// aside first and removed by [`cleanup`] on the next start.
+pub fn swap(exe: &Path, bytes: &[u8]) -> Result<()> {
+    let staged = exe.with_extension("new");
+    std::fs::write(&staged, bytes).with_context(|| format!("writing {}", staged.display()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&staged, std::fs::Permissions::from_mode(0o755))?;
+    }
+    if cfg!(windows) {
+        let old = old_path(exe);
+        let _ = std::fs::remove_file(&old);
+        std::fs::rename(exe, &old).with_context(|| format!("moving {} aside", exe.display()))?;
+        if let Err(e) = std::fs::rename(&staged, exe) {
+            let _ = std::fs::rename(&old, exe);
+            return Err(e).context("installing the new binary");
+        }
+    } else {
+        std::fs::rename(&staged, exe).with_context(|| format!("replacing {}", exe.display()))?;
+    }
+    Ok(())
+}
+
+/// Remove the binary a Windows update left behind.
+pub fn cleanup() {
+    if let Ok(exe) = std::env::current_exe() {
+        let _ = std::fs::remove_file(old_path(&exe));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    fn release(tag: &str, prerelease: bool, dir: &Path) -> Release {
+        let asset = |name: &str| Asset { name: name.into(), browser_download_url: dir.join(name).to_string_lossy().into_owned() };
+        Release {
+            tag_name: tag.into(),
+            name: None,
+            body: Some(format!("notes for {}", tag)),
+            prerelease,
+            draft: false,
+            assets: vec![asset(&asset_name()), asset("SHA256SUMS"), asset("SHA256SUMS.sig")],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pick_verify_and_swap() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let list = vec![release("nightly-20261015", true, dir.path()), release("v0.10.0", false, dir.path()), release("v0.9.1", false, dir.path()), release("v0.1.0", false, dir.path())];
+        assert_eq!(pick(&list, Channel::Stable, "0.2.0", None).unwrap().tag_name, "v0.10.0");
+        assert!(pick(&list, Channel::Stable, "0.10.0", None).is_none());
+        assert_eq!(pick(&list, Channel::Nightly, "0.2.0", None).unwrap().tag_name, "nightly-20261015");
+        assert_eq!(pick(&list, Channel::Nightly, "0.2.0", Some("nightly-20261014")).unwrap().tag_name, "nightly-20261015");
+        assert!(pick(&list, Channel::Nightly, "0.2.0", Some("nightly-20261015")).is_none());
+
+        let binary = b"new binary".to_vec();
+        std::fs::write(dir.path().join(asset_name()), &binary)?;
+        let sums = format!("{}  {}\n", hex::encode(Sha256::digest(&binary)), asset_name());
+        std::fs::write(dir.path().join("SHA256SUMS"), &sums)?;
+        let key = SigningKey::from_bytes(&[3; 32]);
+        std::fs::write(dir.path().join("SHA256SUMS.sig"), hex::encode(key.sign(sums.as_bytes()).to_bytes()))?;
+        let config = UpdateConfig { releases_url: String::new(), trusted_keys: vec![hex::encode(key.verifying_key().to_bytes())], require_signature: true };
+
+        let (bytes, verified) = download(&config, &list[1]).await?;
+        assert_eq!(bytes, binary);
+        assert!(matches!(verified, Verified::Signed(_)));
+
+        std::fs::write(dir.path().join(asset_name()), b"tampered")?;
+        assert!(download(&config, &list[1]).await.unwrap_err().to_string().contains("checksum mismatch"));
+        let other = UpdateConfig { trusted_keys: vec![hex::encode(SigningKey::from_bytes(&[4; 32]).verifying_key().to_bytes())], ..config };
+        assert!(download(&other, &list[1]).await.is_err());
+
+        // unsigned releases are refused unless the user opts out
+        std::fs::write(dir.path().join(asset_name()), &binary)?;
+        let mut unsigned = list[1].clone();
+        unsigned.assets.retain(|a| a.name != "SHA256SUMS.sig");
+        assert!(UpdateConfig::default().require_signature);
+        assert!(download(&UpdateConfig::default(), &unsigned).await.unwrap_err().to_string().contains("not signed"));
+        let lax = UpdateConfig { require_signature: false, ..UpdateConfig::default() };
+        assert!(matches!(download(&lax, &unsigned).await?.1, Verified::Checksum));
+
+        let exe = dir.path().join("agent");
+        std::fs::write(&exe, b"old binary")?;
+        swap(&exe, &binary)?;
+        assert_eq!(std::fs::read(&exe)?, binary);
+        assert!(!exe.with_extension("new").exists());
+        Ok(())
+    }
+}
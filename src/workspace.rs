@@ -0,0 +1,227 @@
+use crate::tools::policy::{glob_match, Access, FileAccessPolicy};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Per-project settings file written next to the project manifest.
+pub const PROFILE_FILE: &str = ".supercode.toml";
+
+/// Upper bound on indexed files so huge trees don't stall the TUI.
+const MAX_INDEXED_FILES: usize = 20_000;
+/// Directories never worth indexing.
+const SKIP_DIRS: &[&str] = &["target", "node_modules", "__pycache__"];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ProjectKind {
+    Rust,
+    Node,
+    Python,
+    Unknown,
+}
+
+/// Language-specific defaults for a workspace.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProjectProfile {
+    pub kind: ProjectKind,
+    pub build_command: Option<String>,
+    pub test_command: Option<String>,
+    /// Globs the project scanner looks at first.
+    pub scan_globs: Vec<String>,
+    /// Facts pinned with `/remember`, sent with every request.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<String>,
+    /// Overrides `approve_plan` in config.toml for this project.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub approve_plan: Option<bool>,
+    /// `[policy]`: limits on tools for everyone working in this project.
+    #[serde(default, skip_serializing_if = "ProjectPolicy::is_empty")]
+    pub policy: ProjectPolicy,
+}
+
+/// Tool limits a team commits in `.supercode.toml`. They apply on top of
+/// the user's `[tools]` and `[files]` settings and can only narrow them:
+/// where both set a list, a command or path must pass both.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProjectPolicy {
+    /// Regexes a shell command (`!`, `/term`, the test command) must match.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub commands: Vec<String>,
+    /// Globs of the files agents may write.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub writable: Vec<String>,
+    /// `false` disables a tool here; `true` does not re-enable one that
+    /// config.toml disables.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub tools: BTreeMap<String, bool>,
+}
+
+impl ProjectPolicy {
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+
+    /// Tools this project turns off.
+    pub fn disabled(&self) -> Vec<String> {
+        self.tools.iter().filter(|(_, on)| !**on).map(|(name, _)| name.clone()).collect()
+    }
+}
+
+impl ProjectProfile {
+    pub fn for_kind(kind: ProjectKind) -> Self {
+        let strs = |v: &[&str]| v.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        match kind {
+            ProjectKind::Rust => Self {
+                kind,
+                build_command: Some("cargo build".into()),
+                test_command: Some("cargo test".into()),
+                scan_globs: strs(&["Cargo.toml", "src/**/*.rs", "tests/**/*.rs"]),
+                notes: vec![],
+                approve_plan: None,
+                policy: ProjectPolicy::default(),
+            },
+            ProjectKind::Node => Self {
+                kind,
+                build_command: Some("npm run build".into()),
+                test_command: Some("npm test".into()),
+                scan_globs: strs(&["package.json", "src/**/*.ts", "src/**/*.js"]),
+                notes: vec![],
+                approve_plan: None,
+                policy: ProjectPolicy::default(),
+            },
+            ProjectKind::Python => Self {
+                kind,
+                build_command: None,
+                test_command: Some("pytest".into()),
+                scan_globs: strs(&["pyproject.toml", "**/*.py"]),
+                notes: vec![],
+                approve_plan: None,
+                policy: ProjectPolicy::default(),
+            },
+            ProjectKind::Unknown => Self {
+                kind,
+                build_command: None,
+                test_command: None,
+                scan_globs: strs(&["**/*"]),
+                notes: vec![],
+                approve_plan: None,
+                policy: ProjectPolicy::default(),
+            },
+        }
+    }
+
+    /// Guess the project kind from the manifests present in `root`.
+    pub fn detect(root: &Path) -> Self {
+        let kind = if root.join("Cargo.toml").is_file() {
+            ProjectKind::Rust
+        } else if root.join("package.json").is_file() {
+            ProjectKind::Node
+        } else if root.join("pyproject.toml").is_file() {
+            ProjectKind::Python
+        } else {
+            ProjectKind::Unknown
+        };
+        Self::for_kind(kind)
+    }
+
+    pub fn path(root: &Path) -> PathBuf {
+        root.join(PROFILE_FILE)
+    }
+
+    /// Read `.supercode.toml` from `root`, or detect a profile and write it so
+    /// the user can edit the defaults. Unknown projects are not persisted.
+    pub fn load_or_detect(root: &Path) -> anyhow::Result<Self> {
+        let p = Self::path(root);
+        if p.is_file() {
+            let s = fs::read_to_string(&p)?;
+            return Ok(toml::from_str(&s)?);
+        }
+        let profile = Self::detect(root);
+        if profile.kind != ProjectKind::Unknown {
+            fs::write(&p, toml::to_string_pretty(&profile)?)?;
+        }
+        Ok(profile)
+    }
+
+    /// Write the profile to `root`'s `.supercode.toml`.
+    pub fn save(&self, root: &Path) -> anyhow::Result<()> {
+        fs::write(Self::path(root), toml::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// Relative paths of the files under `root` that `policy` lets agents read,
+/// skipping hidden and build directories and not following symlinked
+/// ones. Files matching the profile's `scan_globs` come first.
+pub fn file_index(root: &Path, profile: &ProjectProfile, policy: &FileAccessPolicy) -> Vec<String> {
+    let mut files = vec![];
+    let mut stack = vec![root.to_path_buf()];
+    'walk: while let Some(dir) = stack.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else { continue };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name.starts_with('.') || policy.check(&path, Access::Read).is_err() {
+                continue;
+            }
+            let Ok(kind) = entry.file_type() else { continue };
+            if kind.is_dir() {
+                if !SKIP_DIRS.contains(&name.as_str()) {
+                    stack.push(path);
+                }
+            } else if kind.is_symlink() && path.is_dir() {
+                continue;
+            } else if let Ok(rel) = path.strip_prefix(root) {
+                files.push(rel.to_string_lossy().replace('\\', "/"));
+                if files.len() >= MAX_INDEXED_FILES {
+                    break 'walk;
+                }
+            }
+        }
+    }
+    let preferred = |f: &String| !profile.scan_globs.iter().any(|g| glob_match(g, f));
+    files.sort_by(|a, b| preferred(a).cmp(&preferred(b)).then(a.cmp(b)));
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_and_persist_profile() -> anyhow::Result<()> {
+        let td = tempfile::tempdir()?;
+        assert_eq!(ProjectProfile::load_or_detect(td.path())?.kind, ProjectKind::Unknown);
+        assert!(!ProjectProfile::path(td.path()).exists());
+
+        fs::write(td.path().join("pyproject.toml"), "[project]\nname = \"x\"\n")?;
+        let profile = ProjectProfile::load_or_detect(td.path())?;
+        assert_eq!(profile.kind, ProjectKind::Python);
+        assert_eq!(profile.test_command.as_deref(), Some("pytest"));
+
+        // user edits survive re-detection
+        let mut edited = profile.clone();
+        edited.test_command = Some("pytest -q".into());
+        edited.notes.push("we use sqlx, not diesel".into());
+        edited.save(td.path())?;
+        assert_eq!(ProjectProfile::load_or_detect(td.path())?, edited);
+        Ok(())
+    }
+
+    #[test]
+    fn test_file_index_respects_policy_and_globs() -> anyhow::Result<()> {
+        let td = tempfile::tempdir()?;
+        for f in ["README.md", "src/main.rs", "secrets/token", "target/debug/x", ".git/HEAD"] {
+            let p = td.path().join(f);
+            fs::create_dir_all(p.parent().unwrap())?;
+            fs::write(p, "")?;
+        }
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(td.path(), td.path().join("src/loop"))?;
+        let policy = FileAccessPolicy::new(td.path(), &crate::config::FilesConfig::default());
+        let files = file_index(td.path(), &ProjectProfile::for_kind(ProjectKind::Rust), &policy);
+        assert_eq!(files, vec!["src/main.rs", "README.md"]);
+        Ok(())
+    }
+}
@@ -0,0 +1,131 @@
+// Browser view of a model server: chat through a server-side conversation,
+// the task list, and live provider status and agent activity from
+// /v1/events. Everything goes through the same API other clients use.
+"use strict";
+
+const $ = (id) => document.getElementById(id);
+let conversation = null;
+
+function item(text, cls) {
+  const li = document.createElement("li");
+  li.textContent = text;
+  if (cls) li.className = cls;
+  return li;
+}
+
+function say(role, text) {
+  const div = document.createElement("div");
+  div.className = "message " + role;
+  div.textContent = text;
+  $("transcript").append(div);
+  $("transcript").scrollTop = $("transcript").scrollHeight;
+}
+
+async function api(path, options) {
+  const resp = await fetch(path, options);
+  const body = await resp.json().catch(() => ({}));
+  if (!resp.ok) {
+    const id = resp.headers.get("x-request-id");
+    throw new Error((body.error?.message ?? resp.statusText) + (id ? ` (request ${id})` : ""));
+  }
+  return body;
+}
+
+async function send(event) {
+  event.preventDefault();
+  const text = $("prompt").value.trim();
+  if (!text) return;
+  $("prompt").value = "";
+  say("user", text);
+  const button = $("ask").querySelector("button[type=submit]");
+  button.disabled = true;
+  try {
+    if (!conversation) {
+      conversation = (await api("/v1/conversations", { method: "POST", headers: { "content-type": "application/json" }, body: "{}" })).id;
+    }
+    const request = { conversation, messages: [{ role: "user", content: text }] };
+    if ($("model").value) request.model = $("model").value;
+    const reply = await api("/v1/chat/completions", {
+      method: "POST",
+      headers: { "content-type": "application/json" },
+      body: JSON.stringify(request),
+    });
+    say("assistant", reply.choices[0].message.content);
+  } catch (e) {
+    say("error", e.message);
+  } finally {
+    button.disabled = false;
+  }
+}
+
+function reset() {
+  if (conversation) fetch("/v1/conversations/" + conversation, { method: "DELETE" });
+  conversation = null;
+  $("transcript").replaceChildren();
+}
+
+function showProviders(providers) {
+  $("providers").replaceChildren(...providers.map((p) =>
+    item(`${p.name} · ${p.provider}${p.vision ? " · vision" : ""} · ${p.running ? "running" : "stopped"}`, p.running ? "running" : "stopped")));
+  const chosen = $("model").value;
+  const any = new Option("any model", "");
+  $("model").replaceChildren(any, ...providers.map((p) => new Option(p.name, p.name)));
+  $("model").value = providers.some((p) => p.name === chosen) ? chosen : "";
+}
+
+function showActivity(event) {
+  const text = {
+    run_started: () => `started ${event.title}`,
+    action: () => `${event.agent}: ${event.action}`,
+    run_finished: () => `${event.task.slice(0, 8)} ${event.status}`,
+    lagged: () => `(${event.missed} events missed)`,
+  }[event.type];
+  if (!text) return;
+  $("activity").prepend(item(text()));
+  while ($("activity").children.length > 50) $("activity").lastChild.remove();
+  if (event.type !== "action") refreshTasks();
+}
+
+async function refreshTasks() {
+  try {
+    const { tasks } = await api("/v1/tasks");
+    $("tasks").replaceChildren(...tasks.slice(0, 20).map((t) =>
+      item(`${t.title} — ${t.status} ${Math.round(t.progress * 100)}%`, t.status)));
+    if (!tasks.length) $("tasks").replaceChildren(item("no tasks", "muted"));
+  } catch (e) {
+    $("tasks").replaceChildren(item(e.message, "Failed"));
+  }
+}
+
+async function refreshModels() {
+  try {
+    const { models } = await api("/v1/models");
+    $("installed").replaceChildren(...models.map((m) => item(`${m.name} (${m.format})`)));
+  } catch (e) {
+    $("installed").replaceChildren(item(e.message, "Failed"));
+  }
+}
+
+function listen() {
+  const socket = new WebSocket(`${location.protocol === "https:" ? "wss" : "ws"}://${location.host}/v1/events`);
+  socket.onopen = () => { $("link").textContent = "connected"; };
+  socket.onmessage = (message) => {
+    const event = JSON.parse(message.data);
+    if (event.type === "providers") showProviders(event.providers);
+    else showActivity(event);
+  };
+  socket.onclose = () => {
+    $("link").textContent = "disconnected, retrying…";
+    setTimeout(listen, 3000);
+  };
+}
+
+$("ask").addEventListener("submit", send);
+$("reset").addEventListener("click", reset);
+$("prompt").addEventListener("keydown", (e) => {
+  if (e.key === "Enter" && e.ctrlKey) send(e);
+});
+refreshTasks();
+refreshModels();
+setInterval(refreshTasks, 10000);
+listen();